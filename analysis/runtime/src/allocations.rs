@@ -0,0 +1,105 @@
+//! Allocation-tracking shim recording the size of every live heap allocation.
+//!
+//! Translated and rewritten code cannot ask the allocator how large a block is, but some C code
+//! depends on it (`malloc_usable_size`, or the copy done by `realloc`). The wrappers below
+//! allocate through libc and record the requested size of each block, keyed by its base
+//! address. The table is shared by the `--debug-pointers` bounds checks and the `CBlockPtr`
+//! type of the refactoring runtime; the instrumentation handlers record into it too.
+
+use lazy_static::lazy_static;
+use libc::{c_ulong, c_void, size_t};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+lazy_static! {
+    /// Live allocations, keyed by base address, with their size in bytes. The map is ordered so
+    /// that the allocation containing an interior pointer can be found.
+    static ref ALLOCATIONS: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+}
+
+pub fn record_alloc(ptr: usize, size: usize) {
+    if ptr != 0 {
+        ALLOCATIONS.lock().unwrap().insert(ptr, size);
+    }
+}
+
+pub fn record_free(ptr: usize) {
+    ALLOCATIONS.lock().unwrap().remove(&ptr);
+}
+
+pub fn record_realloc(old_ptr: usize, size: usize, new_ptr: usize) {
+    // A failed `realloc` leaves the old allocation untouched
+    if new_ptr != 0 {
+        record_free(old_ptr);
+        record_alloc(new_ptr, size);
+    }
+}
+
+/// The size of the allocation starting at `base`, if it is recorded.
+pub fn size(base: usize) -> Option<usize> {
+    ALLOCATIONS.lock().unwrap().get(&base).copied()
+}
+
+/// The base address and size of the recorded allocation containing `addr`, counting the
+/// address one past its end as contained.
+pub fn containing(addr: usize) -> Option<(usize, usize)> {
+    let allocations = ALLOCATIONS.lock().unwrap();
+    let (&base, &size) = allocations.range(..=addr).next_back()?;
+    if addr <= base + size {
+        Some((base, size))
+    } else {
+        None
+    }
+}
+
+/// # Safety
+///
+/// Same as `libc::malloc`.
+pub unsafe fn malloc(size: c_ulong) -> *mut c_void {
+    let ptr = libc::malloc(size as size_t);
+    record_alloc(ptr as usize, size as usize);
+    ptr
+}
+
+/// # Safety
+///
+/// Same as `libc::calloc`.
+pub unsafe fn calloc(nmemb: c_ulong, size: c_ulong) -> *mut c_void {
+    let ptr = libc::calloc(nmemb as size_t, size as size_t);
+    record_alloc(ptr as usize, (nmemb * size) as usize);
+    ptr
+}
+
+/// # Safety
+///
+/// Same as `libc::realloc`.
+pub unsafe fn realloc(old_ptr: *mut c_void, size: c_ulong) -> *mut c_void {
+    let new_ptr = libc::realloc(old_ptr, size as size_t);
+    record_realloc(old_ptr as usize, size as usize, new_ptr as usize);
+    new_ptr
+}
+
+/// # Safety
+///
+/// Same as `libc::free`.
+pub unsafe fn free(ptr: *mut c_void) {
+    record_free(ptr as usize);
+    libc::free(ptr);
+}
+
+/// The recorded size of the allocation at `ptr`, or what the allocator reports for blocks that
+/// were not allocated through this shim. Unlike the allocator, the shim only reports the
+/// requested size, so code relying on it never touches the allocator's slack space.
+///
+/// # Safety
+///
+/// Same as `libc::malloc_usable_size`.
+pub unsafe fn malloc_usable_size(ptr: *mut c_void) -> c_ulong {
+    if ptr.is_null() {
+        return 0;
+    }
+    match size(ptr as usize) {
+        Some(size) => size as c_ulong,
+        None => libc::malloc_usable_size(ptr) as c_ulong,
+    }
+}
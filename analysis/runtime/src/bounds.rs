@@ -0,0 +1,97 @@
+//! Bounds checking for the pointer arithmetic emitted by `c2rust transpile --debug-pointers`.
+//!
+//! Heap allocations are recorded by the [`allocations`] shim, which the transpiler calls in
+//! place of `malloc`, `calloc`, `realloc` and `free`. Offsetting a pointer into a recorded
+//! allocation to outside of it panics instead of silently corrupting memory. Pointers that are
+//! not into a recorded allocation (stack and static data) are offset unchecked.
+
+use crate::allocations;
+use std::mem;
+
+/// A raw pointer, `*const T` or `*mut T`
+pub trait RawPointer: Copy {
+    type Pointee;
+
+    fn addr(self) -> usize;
+
+    /// # Safety
+    ///
+    /// See [`pointer::offset`](https://doc.rust-lang.org/std/primitive.pointer.html#method.offset).
+    unsafe fn unchecked_offset(self, count: isize) -> Self;
+}
+
+impl<T> RawPointer for *const T {
+    type Pointee = T;
+
+    fn addr(self) -> usize {
+        self as usize
+    }
+
+    unsafe fn unchecked_offset(self, count: isize) -> Self {
+        self.offset(count)
+    }
+}
+
+impl<T> RawPointer for *mut T {
+    type Pointee = T;
+
+    fn addr(self) -> usize {
+        self as usize
+    }
+
+    unsafe fn unchecked_offset(self, count: isize) -> Self {
+        self.offset(count)
+    }
+}
+
+/// Panic if offsetting `ptr` by `count` elements leaves the allocation it points into. If
+/// `deref` is set, the resulting element must also lie entirely within the allocation.
+fn check_offset<P: RawPointer>(ptr: P, count: isize, deref: bool) {
+    let addr = ptr.addr();
+    let (start, size) = match allocations::containing(addr) {
+        Some(allocation) => allocation,
+        None => return,
+    };
+    let end = start + size;
+    let elt_size = mem::size_of::<P::Pointee>();
+    let target = count
+        .checked_mul(elt_size as isize)
+        .and_then(|bytes| (addr as isize).checked_add(bytes));
+    let in_bounds = match target {
+        Some(target) if target >= start as isize => {
+            let target = target as usize;
+            if deref {
+                target + elt_size <= end
+            } else {
+                target <= end
+            }
+        }
+        _ => false,
+    };
+    if !in_bounds {
+        panic!(
+            "pointer {:#x} offset by {} elements of {} bytes is out of bounds of allocation {:#x}..{:#x}",
+            addr, count, elt_size, start, end
+        );
+    }
+}
+
+/// Checked `ptr.offset(count)`. The result may point one past the end of the allocation.
+///
+/// # Safety
+///
+/// Same as [`RawPointer::unchecked_offset`] for pointers that are not into a recorded allocation.
+pub unsafe fn offset<P: RawPointer>(ptr: P, count: isize) -> P {
+    check_offset(ptr, count, false);
+    ptr.unchecked_offset(count)
+}
+
+/// Checked `ptr.offset(count)` for a pointer that is dereferenced, as in `ptr[count]`.
+///
+/// # Safety
+///
+/// Same as [`RawPointer::unchecked_offset`] for pointers that are not into a recorded allocation.
+pub unsafe fn index<P: RawPointer>(ptr: P, count: isize) -> P {
+    check_offset(ptr, count, true);
+    ptr.unchecked_offset(count)
+}
@@ -1,9 +1,11 @@
 #![allow(dead_code)]
+use crate::allocations;
 use crate::backend::TX;
 use crate::events::{Event, EventKind};
 use crate::mir_loc::MirLocId;
 
 pub fn malloc(mir_loc: MirLocId, size: u64, ptr: usize) {
+    allocations::record_alloc(ptr, size as usize);
     TX.send(Event {
         mir_loc,
         kind: EventKind::Alloc {
@@ -14,6 +16,7 @@ pub fn malloc(mir_loc: MirLocId, size: u64, ptr: usize) {
     .unwrap();
 }
 pub fn free(mir_loc: MirLocId, ptr: usize, _ptr: ()) {
+    allocations::record_free(ptr);
     TX.send(Event {
         mir_loc,
         kind: EventKind::Free { ptr },
@@ -21,6 +24,7 @@ pub fn free(mir_loc: MirLocId, ptr: usize, _ptr: ()) {
     .unwrap();
 }
 pub fn calloc(mir_loc: MirLocId, nmemb: u64, size: u64, ptr: usize) {
+    allocations::record_alloc(ptr, (nmemb * size) as usize);
     TX.send(Event {
         mir_loc,
         kind: EventKind::Alloc {
@@ -31,6 +35,7 @@ pub fn calloc(mir_loc: MirLocId, nmemb: u64, size: u64, ptr: usize) {
     .unwrap();
 }
 pub fn realloc(mir_loc: MirLocId, old_ptr: usize, size: u64, new_ptr: usize) {
+    allocations::record_realloc(old_ptr, size as usize, new_ptr);
     TX.send(Event {
         mir_loc,
         kind: EventKind::Realloc {
@@ -42,6 +47,7 @@ pub fn realloc(mir_loc: MirLocId, old_ptr: usize, size: u64, new_ptr: usize) {
     .unwrap();
 }
 pub fn reallocarray(mir_loc: MirLocId, old_ptr: usize, nmemb: u64, size: u64, new_ptr: usize) {
+    allocations::record_realloc(old_ptr, (nmemb * size) as usize, new_ptr);
     TX.send(Event {
         mir_loc,
         kind: EventKind::Realloc {
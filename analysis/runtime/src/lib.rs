@@ -1,4 +1,6 @@
+pub mod allocations;
 pub mod backend;
+pub mod bounds;
 pub mod events;
 mod handlers;
 pub mod mir_loc;
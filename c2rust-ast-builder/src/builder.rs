@@ -358,6 +358,12 @@ impl Make<GenericArgument> for Box<Type> {
     }
 }
 
+impl Make<GenericArgument> for Box<Expr> {
+    fn make(self, _mk: &Builder) -> GenericArgument {
+        GenericArgument::Const(*self)
+    }
+}
+
 impl Make<GenericArgument> for Lifetime {
     fn make(self, _mk: &Builder) -> GenericArgument {
         GenericArgument::Lifetime(self)
@@ -1415,6 +1421,23 @@ impl Builder {
         }))
     }
 
+    /// `name @ subpat`
+    pub fn ident_binding_pat<I, Pa>(self, name: I, subpat: Pa) -> Box<Pat>
+    where
+        I: Make<Ident>,
+        Pa: Make<Box<Pat>>,
+    {
+        let name = name.make(&self);
+        let subpat = subpat.make(&self);
+        Box::new(Pat::Ident(PatIdent {
+            attrs: self.attrs.into(),
+            mutability: self.mutbl.to_token(),
+            by_ref: None,
+            ident: name,
+            subpat: Some((token::At(self.span), subpat)),
+        }))
+    }
+
     pub fn tuple_pat<Pa>(self, pats: Vec<Pa>) -> Box<Pat>
     where
         Pa: Make<Box<Pat>>,
@@ -1427,6 +1450,25 @@ impl Builder {
         }))
     }
 
+    /// `path(pats...)`
+    pub fn tuple_struct_pat<Pa, P>(self, path: Pa, pats: Vec<P>) -> Box<Pat>
+    where
+        Pa: Make<Path>,
+        P: Make<Box<Pat>>,
+    {
+        let path = path.make(&self);
+        let pats: Vec<Box<Pat>> = pats.into_iter().map(|x| x.make(&self)).collect();
+        Box::new(Pat::TupleStruct(PatTupleStruct {
+            attrs: self.attrs.into(),
+            path,
+            pat: PatTuple {
+                attrs: vec![],
+                paren_token: token::Paren(self.span),
+                elems: punct_box(pats),
+            },
+        }))
+    }
+
     pub fn qpath_pat<Pa>(self, qself: Option<QSelf>, path: Pa) -> Box<Pat>
     where
         Pa: Make<Path>,
@@ -1457,6 +1499,21 @@ impl Builder {
         }))
     }
 
+    pub fn range_pat<L, H>(self, lo: L, hi: H) -> Box<Pat>
+    where
+        L: Make<Box<Expr>>,
+        H: Make<Box<Expr>>,
+    {
+        let lo = lo.make(&self);
+        let hi = hi.make(&self);
+        Box::new(Pat::Range(PatRange {
+            attrs: self.attrs.into(),
+            lo,
+            limits: RangeLimits::Closed(token::DotDotEq(self.span)),
+            hi,
+        }))
+    }
+
     pub fn mac_pat<M>(self, mac: M) -> Box<Pat>
     where
         M: Make<Macro>,
@@ -1918,6 +1975,22 @@ impl Builder {
         }
     }
 
+    pub fn tuple_variant<I>(self, name: I, fields: Vec<Field>) -> Variant
+    where
+        I: Make<Ident>,
+    {
+        let name = name.make(&self);
+        Variant {
+            ident: name,
+            fields: Fields::Unnamed(FieldsUnnamed {
+                paren_token: token::Paren(self.span),
+                unnamed: fields.into_iter().collect(),
+            }),
+            discriminant: None,
+            attrs: self.attrs,
+        }
+    }
+
     pub fn impl_item<T>(self, ty: T, items: Vec<ImplItem>) -> Box<Item>
     where
         T: Make<Box<Type>>,
@@ -1936,6 +2009,26 @@ impl Builder {
         }))
     }
 
+    pub fn trait_impl_item<Pa, T>(self, trait_: Pa, ty: T, items: Vec<ImplItem>) -> Box<Item>
+    where
+        Pa: Make<Path>,
+        T: Make<Box<Type>>,
+    {
+        let trait_ = trait_.make(&self);
+        let ty = ty.make(&self);
+        Box::new(Item::Impl(ItemImpl {
+            attrs: self.attrs,
+            unsafety: self.unsafety.to_token(),
+            defaultness: Defaultness::Final.to_token(),
+            generics: self.generics,
+            trait_: Some((None, trait_, token::For(self.span))),
+            self_ty: ty,
+            impl_token: token::Impl(self.span),
+            brace_token: token::Brace(self.span),
+            items,
+        }))
+    }
+
     pub fn extern_crate_item<I>(self, name: I, rename: Option<I>) -> Box<Item>
     where
         I: Make<Ident>,
@@ -2098,6 +2191,42 @@ impl Builder {
 
     // Impl Items
 
+    pub fn fn_impl_item<S, B>(self, sig: S, block: B) -> ImplItem
+    where
+        S: Make<Signature>,
+        B: Make<Box<Block>>,
+    {
+        let sig = sig.make(&self);
+        let block = block.make(&self);
+        ImplItem::Method(ImplItemMethod {
+            attrs: self.attrs,
+            vis: self.vis,
+            defaultness: None,
+            sig,
+            block: *block,
+        })
+    }
+
+    pub fn type_impl_item<I, T>(self, name: I, ty: T) -> ImplItem
+    where
+        I: Make<Ident>,
+        T: Make<Box<Type>>,
+    {
+        let name = name.make(&self);
+        let ty = ty.make(&self);
+        ImplItem::Type(ImplItemType {
+            attrs: self.attrs,
+            vis: self.vis,
+            defaultness: None,
+            type_token: token::Type(self.span),
+            ident: name,
+            generics: self.generics,
+            eq_token: token::Eq(self.span),
+            ty: *ty,
+            semi_token: token::Semi(self.span),
+        })
+    }
+
     pub fn mac_impl_item<M>(self, mac: M) -> ImplItem
     where
         M: Make<Macro>,
@@ -2295,7 +2424,7 @@ impl Builder {
         let ty = ty.make(&self);
         let pat = pat.make(&self);
         FnArg::Typed(PatType {
-            attrs: Vec::new(),
+            attrs: self.attrs,
             ty: ty,
             pat: pat,
             colon_token: token::Colon(self.span),
@@ -3,6 +3,7 @@
 #include <fstream>
 #include <iostream>
 #include <iterator>
+#include <memory>
 #include <set>
 #include <unordered_map>
 #include <unordered_set>
@@ -25,6 +26,7 @@
 #include "clang/Basic/TargetInfo.h"
 #include "clang/Basic/Version.h"
 #include "clang/Frontend/CompilerInstance.h"
+#include "clang/Lex/PPCallbacks.h"
 #if CLANG_VERSION_MAJOR < 10
 #include "clang/Frontend/LangStandard.h"
 #else
@@ -96,6 +98,22 @@ Optional<APSInt> getIntegerConstantExpr(const Expr &E, const ASTContext &Ctx) {
     return E.getIntegerConstantExpr(Ctx);
 #endif // CLANG_VERSION_MAJOR
 }
+
+// The parameters of a nonnull attribute as a comma separated list of their
+// indices counting from 0, which is empty if the attribute covers all of them
+std::string encodeNonNullArgs(const NonNullAttr *attr) {
+    std::string indices;
+    for (auto idx : attr->args()) {
+        if (!indices.empty())
+            indices += ",";
+#if CLANG_VERSION_MAJOR < 7
+        indices += std::to_string(idx);
+#else
+        indices += std::to_string(idx.getASTIndex());
+#endif // CLANG_VERSION_MAJOR
+    }
+    return indices;
+}
 } // namespace
 
 class TranslateASTVisitor;
@@ -236,6 +254,18 @@ class TypeEncoder final : public TypeVisitor<TypeEncoder> {
 #endif // CLANG_VERSION_MAJOR
                 tag = "nullable";
                 break;
+
+            // Calling conventions of function types, as in
+            // `void (__stdcall *)(int)`
+#if CLANG_VERSION_MAJOR >= 8
+            case attr::CDecl: tag = "cdecl"; break;
+            case attr::StdCall: tag = "stdcall"; break;
+            case attr::FastCall: tag = "fastcall"; break;
+            case attr::VectorCall: tag = "vectorcall"; break;
+            case attr::ThisCall: tag = "thiscall"; break;
+            case attr::MSABI: tag = "ms_abi"; break;
+            case attr::SysVABI: tag = "sysv_abi"; break;
+#endif // CLANG_VERSION_MAJOR
             }
             if (tag) {
                 cbor_encode_text_stringz(local, tag);
@@ -372,6 +402,24 @@ class TypeEncoder final : public TypeVisitor<TypeEncoder> {
         }
 #endif // CLANG_VERSION_MAJOR >= 10
 
+#if CLANG_VERSION_MAJOR >= 8
+        // Embedded C fixed-point types (`_Fract`, `_Accum` and their `_Sat`
+        // variants) are exported with their spelling, width and signedness
+        // so that the translator can report them and declare their users
+        if (T->isFixedPointType()) {
+            auto pol = clang::PrintingPolicy(Context->getLangOpts());
+            auto name = T->getName(pol).str();
+            auto width = Context->getTypeSize(T);
+            auto is_signed = T->isSignedFixedPointType();
+            encodeType(T, TagFixedPointType, [&](CborEncoder *local) {
+                cbor_encode_string(local, name);
+                cbor_encode_uint(local, width);
+                cbor_encode_boolean(local, is_signed);
+            });
+            return;
+        }
+#endif // CLANG_VERSION_MAJOR >= 8
+
         const TypeTag tag = [&] {
             switch (kind) {
             default: {
@@ -393,7 +441,8 @@ class TypeEncoder final : public TypeVisitor<TypeEncoder> {
             case BuiltinType::UInt: return TagUInt;
             case BuiltinType::ULong: return TagULong;
             case BuiltinType::ULongLong: return TagULongLong;
-            case BuiltinType::Half: return TagHalf;     
+            case BuiltinType::Half: return TagHalf;
+            case BuiltinType::Float16: return TagHalf;
             #if CLANG_VERSION_MAJOR >= 11
             case BuiltinType::BFloat16: return TagBFloat16;
             #endif
@@ -519,6 +568,16 @@ class TypeEncoder final : public TypeVisitor<TypeEncoder> {
         VisitQualType(t);
     }
 
+    // `__auto_type` and C23 `auto` are exported with the type deduced from the initializer
+    void VisitAutoType(const AutoType *T) {
+        auto t = T->getDeducedType();
+        auto qt = encodeQualType(t);
+        encodeType(T, TagAutoType,
+                   [qt](CborEncoder *local) { cbor_encode_uint(local, qt); });
+
+        VisitQualType(t);
+    }
+
     void VisitDecayedType(const DecayedType *T) {
         auto t = T->desugar();
         auto qt = encodeQualType(t);
@@ -808,6 +867,14 @@ class TranslateASTVisitor final
         return files;
     }
 
+    // Return the SourceManager FileIDs of the visited files
+    std::vector<FileID> getFileIDs() const {
+        std::vector<FileID> ids;
+        for (auto const &entry : file_id_mapping)
+            ids.push_back(entry.first);
+        return ids;
+    }
+
     void encodeMacros() {
         // Sort macros by source location
         std::vector<std::pair<MacroInfo *, MacroExpansionInfo>> macro_vec(
@@ -921,6 +988,25 @@ class TranslateASTVisitor final
         return true;
     }
 
+    // Microsoft structured exception handling isn't translated, but `__try`
+    // and `__leave` are exported so that only the functions using them fail
+    bool VisitSEHTryStmt(SEHTryStmt *S) {
+        Stmt *handler;
+        if (auto except = S->getExceptHandler())
+            handler = except->getBlock();
+        else
+            handler = S->getFinallyHandler()->getBlock();
+        std::vector<void *> childIds = {S->getTryBlock(), handler};
+        encode_entry(S, TagSEHStmt, childIds);
+        return true;
+    }
+
+    bool VisitSEHLeaveStmt(SEHLeaveStmt *S) {
+        std::vector<void *> childIds;
+        encode_entry(S, TagSEHStmt, childIds);
+        return true;
+    }
+
     bool VisitCompoundStmt(CompoundStmt *CS) {
         std::vector<void *> childIds;
         for (auto x : CS->children()) {
@@ -1050,13 +1136,36 @@ class TranslateASTVisitor final
         }
 
         std::vector<void *> childIds{expr, CS->getSubStmt()};
-        encode_entry(CS, TagCaseStmt, childIds, [value](CborEncoder *extra) {
+
+        // GNU case ranges (`case 1 ... 5:`) carry the upper bound as an
+        // extra child and an extra constant.
+        auto rhs = CS->getRHS();
+        APSInt rhs_value;
+        if (rhs) {
+            if (!evaluateConstantInt(rhs, rhs_value)) {
+                std::string msg = "Upper bound of case range is not an "
+                                  "integer. Aborting.";
+                printError(msg, CS);
+                abort();
+            }
+            childIds.push_back(rhs);
+        }
+
+        encode_entry(CS, TagCaseStmt, childIds,
+                     [value, rhs, rhs_value](CborEncoder *extra) {
             cbor_encode_boolean(extra, value.isSigned());
             if (value.isSigned()) {
                 cbor_encode_int(extra, value.getSExtValue());
             } else {
                 cbor_encode_uint(extra, value.getZExtValue());
             }
+            if (rhs) {
+                if (rhs_value.isSigned()) {
+                    cbor_encode_int(extra, rhs_value.getSExtValue());
+                } else {
+                    cbor_encode_uint(extra, rhs_value.getZExtValue());
+                }
+            }
         });
         return true;
     }
@@ -1074,7 +1183,8 @@ class TranslateASTVisitor final
     }
 
     // Encode ASM statements using the following encoding:
-    // Child IDs: inputs expressions, output expressions
+    // Child IDs: inputs expressions, output expressions, `asm goto` label
+    //            statements
     // Extras:
     //   Boolean true if volatile, false otherwise
     //   Assembly program fragment string
@@ -1090,6 +1200,9 @@ class TranslateASTVisitor final
         copy(E->begin_inputs(), E->end_inputs(), std::back_inserter(childIds));
         copy(E->begin_outputs(), E->end_outputs(),
              std::back_inserter(childIds));
+        for (unsigned i = 0, num = E->getNumLabels(); i < num; ++i) {
+            childIds.push_back(E->getLabelExpr(i)->getLabel()->getStmt());
+        }
 
         encode_entry(E, TagAsmStmt, childIds, [E, this](CborEncoder *local) {
             cbor_encode_boolean(local, E->isVolatile());
@@ -1343,29 +1456,44 @@ class TranslateASTVisitor final
                     cbor_encode_uint(extras, value->getZExtValue());
                 } else {
                     // It's possible to get a non ICE in a field array like so:
-                    // offsetof(S, field[idx]) so here we are encoding the type,
-                    // field, and array input expr
+                    // offsetof(S, field.sub[idx]) so here we are encoding the
+                    // type and each component of the designator: a field or
+                    // an array index expr
                     auto ty = E->getTypeSourceInfo()->getType();
                     auto qt = typeEncoder.encodeQualType(ty);
 
-                    assert(E->getNumComponents() == 2 &&
-                           "Found unsupported number of offsetof components");
-
-                    auto component0 = E->getComponent(0);
-                    auto component1 = E->getComponent(1);
-
-                    assert(component0.getKind() == 1 &&
-                           "Found unsupported offsetof component kind");
-                    assert(component1.getKind() == 0 &&
-                           "Found unsupported offsetof component kind");
-
-                    auto field = component0.getField()->getCanonicalDecl();
-                    auto expr0 = E->getIndexExpr(0);
-
                     cbor_encode_null(extras);
                     cbor_encode_uint(extras, qt);
-                    cbor_encode_uint(extras, uintptr_t(field));
-                    cbor_encode_uint(extras, uintptr_t(expr0));
+
+                    CborEncoder array;
+                    cbor_encoder_create_array(extras, &array,
+                                              E->getNumComponents());
+                    for (unsigned i = 0; i < E->getNumComponents(); i++) {
+                        auto component = E->getComponent(i);
+                        CborEncoder entry;
+                        cbor_encoder_create_array(&array, &entry, 2);
+                        switch (component.getKind()) {
+                        case OffsetOfNode::Array: {
+                            auto index = component.getArrayExprIndex();
+                            cbor_encode_int(&entry, 0);
+                            cbor_encode_uint(&entry,
+                                             uintptr_t(E->getIndexExpr(index)));
+                            break;
+                        }
+                        case OffsetOfNode::Field:
+                            cbor_encode_int(&entry, 1);
+                            cbor_encode_uint(
+                                &entry,
+                                uintptr_t(
+                                    component.getField()->getCanonicalDecl()));
+                            break;
+                        default:
+                            assert(false &&
+                                   "Found unsupported offsetof component kind");
+                        }
+                        cbor_encoder_close_container(&array, &entry);
+                    }
+                    cbor_encoder_close_container(extras, &array);
                 }
             });
 
@@ -1701,6 +1829,22 @@ class TranslateASTVisitor final
         return true;
     }
 
+#if CLANG_VERSION_MAJOR >= 19
+    bool VisitEmbedExpr(EmbedExpr *E) {
+        std::vector<void *> childIds;
+        encode_entry(E, TagEmbedExpr, childIds, [E](CborEncoder *extra) {
+            // The expression stands for a slice of the bytes of the embedded
+            // file, which become the elements of the enclosing initializer
+            auto bytes = E->getDataStringLiteral()->getBytes().substr(
+                E->getStartingElementPos(), E->getDataElementCount());
+            cbor_encode_byte_string(
+                extra, reinterpret_cast<const uint8_t *>(bytes.data()),
+                bytes.size());
+        });
+        return true;
+    }
+#endif // CLANG_VERSION_MAJOR >= 19
+
 #if CLANG_VERSION_MAJOR >= 8
     bool VisitConstantExpr(ConstantExpr *E) {
         auto children = E->children();
@@ -1859,10 +2003,25 @@ class TranslateASTVisitor final
                         } else if (auto *va = dyn_cast<VisibilityAttr>(attr)) {
                             const char *vis = VisibilityAttr::ConvertVisibilityTypeToStr(va->getVisibility());
                             cbor_encode_text_stringz(&attr_info, vis);
+                        } else if (auto *an = dyn_cast<AnnotateAttr>(attr)) {
+                            cbor_encode_text_stringz(
+                                &attr_info, an->getAnnotation().str().c_str());
+                        } else if (auto *nn = dyn_cast<NonNullAttr>(attr)) {
+                            cbor_encode_text_stringz(
+                                &attr_info, encodeNonNullArgs(nn).c_str());
                         }
                     }
                 }
 
+                // Calling conventions such as `__stdcall` are part of the
+                // function type rather than attributes of the declaration
+                auto cc = FD->getType()->castAs<FunctionType>()->getCallConv();
+                if (cc != CC_C) {
+                    cbor_encode_text_stringz(&attr_info, "callconv");
+                    auto cc_name = FunctionType::getNameForCallConv(cc).str();
+                    cbor_encode_text_stringz(&attr_info, cc_name.c_str());
+                }
+
                 cbor_encoder_close_container(array, &attr_info);
             });
         typeEncoder.VisitQualType(functionType);
@@ -1969,6 +2128,12 @@ class TranslateASTVisitor final
                         } else if (auto *aa = dyn_cast<AliasAttr>(attr)) {
                             cbor_encode_text_stringz(
                                 &attr_info, aa->getAliasee().str().c_str());
+                        } else if (auto *an = dyn_cast<AnnotateAttr>(attr)) {
+                            cbor_encode_text_stringz(
+                                &attr_info, an->getAnnotation().str().c_str());
+                        } else if (auto *nn = dyn_cast<NonNullAttr>(attr)) {
+                            cbor_encode_text_stringz(
+                                &attr_info, encodeNonNullArgs(nn).c_str());
                         }
                     }
                 }
@@ -2468,14 +2633,36 @@ void TypeEncoder::VisitVariableArrayType(const VariableArrayType *T) {
 //    abort();
 //}
 
+// Records the locations of `#pragma` directives outside of system headers.
+// Clang drops pragmas it doesn't act on (e.g. OpenMP ones without -fopenmp)
+// from the AST, so we collect them while preprocessing instead.
+class PragmaRecorder : public PPCallbacks {
+    const SourceManager &SM;
+    std::shared_ptr<std::vector<SourceLocation>> pragmas;
+
+  public:
+    PragmaRecorder(const SourceManager &SM,
+                   std::shared_ptr<std::vector<SourceLocation>> pragmas)
+        : SM(SM), pragmas(pragmas) {}
+
+    void PragmaDirective(SourceLocation Loc,
+                         PragmaIntroducerKind Introducer) override {
+        if (Introducer == PIK_HashPragma && !SM.isInSystemHeader(Loc)) {
+            pragmas->push_back(Loc);
+        }
+    }
+};
+
 class TranslateConsumer : public clang::ASTConsumer {
     Outputs *outputs;
     const std::string outfile;
     Preprocessor &PP;
+    std::shared_ptr<std::vector<SourceLocation>> pragmas;
 
   public:
-    explicit TranslateConsumer(Outputs *outputs, llvm::StringRef InFile, Preprocessor &PP)
-        : outputs(outputs), outfile(InFile.str()), PP(PP) {}
+    explicit TranslateConsumer(Outputs *outputs, llvm::StringRef InFile, Preprocessor &PP,
+                               std::shared_ptr<std::vector<SourceLocation>> pragmas)
+        : outputs(outputs), outfile(InFile.str()), PP(PP), pragmas(pragmas) {}
 
     virtual void HandleTranslationUnit(clang::ASTContext &Context) {
 
@@ -2492,7 +2679,7 @@ class TranslateConsumer : public clang::ASTConsumer {
             cbor_encoder_init(&encoder, buffer, len, 0);
 
             CborEncoder outer;
-            cbor_encoder_create_array(&encoder, &outer, 6);
+            cbor_encoder_create_array(&encoder, &outer, 7);
 
             CborEncoder array;
 
@@ -2570,10 +2757,20 @@ class TranslateConsumer : public clang::ASTConsumer {
                 cbor_encoder_close_container(&array, &entry);
             }
 #else  // CLANG_VERSION_MAJOR >= 10
-            const FileID file = sourceMgr.getMainFileID();
-            auto comments = Context.Comments.getCommentsInFile(file);
-            if (comments != nullptr) {
-                cbor_encoder_create_array(&outer, &array, comments->size());
+            // Comments are kept per file, so collect those of the main file
+            // and of the project headers, which declarations translated into
+            // the output may come from. System header comments are skipped.
+            cbor_encoder_create_array(&outer, &array, CborIndefiniteLength);
+            for (auto file : visitor.getFileIDs()) {
+                if (file != sourceMgr.getMainFileID() &&
+                    sourceMgr.isInSystemHeader(
+                        sourceMgr.getLocForStartOfFile(file)))
+                    continue;
+
+                auto comments = Context.Comments.getCommentsInFile(file);
+                if (comments == nullptr)
+                    continue;
+
                 for (auto comment : *comments) {
                     CborEncoder entry;
                     cbor_encoder_create_array(&array, &entry, 4);
@@ -2584,9 +2781,6 @@ class TranslateConsumer : public clang::ASTConsumer {
                                             raw_text.size());
                     cbor_encoder_close_container(&array, &entry);
                 }
-            } else {
-                // this happens when the C file contains no comments
-                cbor_encoder_create_array(&outer, &array, 0);
             }
 #endif // CLANG_VERSION_MAJOR >= 10
             cbor_encoder_close_container(&outer, &array);
@@ -2598,6 +2792,27 @@ class TranslateConsumer : public clang::ASTConsumer {
             auto target = Context.getTargetInfo().getTriple().str();
             cbor_encode_string(&outer, target);
 
+            // 7. Emit `#pragma` directives as arrays of source position
+            // followed by the text of the directive, which extends to the
+            // first newline not escaped by a backslash.
+            cbor_encoder_create_array(&outer, &array, pragmas->size());
+            for (auto loc : *pragmas) {
+                CborEncoder entry;
+                cbor_encoder_create_array(&array, &entry, 4);
+                visitor.encodeSourcePos(&entry, loc); // emits 3 values
+                const char *begin = sourceMgr.getCharacterData(loc);
+                const char *end = begin;
+                while (*end != '\0' &&
+                       (*end != '\n' || (end > begin && end[-1] == '\\'))) {
+                    ++end;
+                }
+                cbor_encode_byte_string(&entry,
+                                        reinterpret_cast<const uint8_t *>(begin),
+                                        end - begin);
+                cbor_encoder_close_container(&array, &entry);
+            }
+            cbor_encoder_close_container(&outer, &array);
+
             cbor_encoder_close_container(&encoder, &outer);
         };
 
@@ -2637,8 +2852,12 @@ class TranslateAction : public clang::ASTFrontendAction {
             return nullptr;
         }
 
-        return std::unique_ptr<clang::ASTConsumer>(
-            new TranslateConsumer(outputs, InFile, Compiler.getPreprocessor()));
+        auto pragmas = std::make_shared<std::vector<SourceLocation>>();
+        Compiler.getPreprocessor().addPPCallbacks(
+            std::make_unique<PragmaRecorder>(Compiler.getSourceManager(), pragmas));
+
+        return std::unique_ptr<clang::ASTConsumer>(new TranslateConsumer(
+            outputs, InFile, Compiler.getPreprocessor(), pragmas));
     }
 };
 
@@ -49,6 +49,9 @@ enum ASTEntryTag {
     TagAsmStmt,
     TagAttributedStmt,
 
+    // Microsoft extensions
+    TagSEHStmt,
+
     TagBinaryOperator = 200,
     TagUnaryOperator,
     TagDeclRefExpr,
@@ -85,6 +88,9 @@ enum ASTEntryTag {
 
     TagAtomicExpr,
 
+    // C23
+    TagEmbedExpr,
+
     TagIntegerLiteral = 300,
     TagStringLiteral,
     TagCharacterLiteral,
@@ -142,6 +148,8 @@ enum TypeTag {
     TagComplexType,
     TagHalf,
     TagBFloat16,
+    TagAutoType,
+    TagFixedPointType,
 };
 
 enum StringTypeTag {
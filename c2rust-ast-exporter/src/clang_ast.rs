@@ -97,6 +97,13 @@ pub struct CommentNode {
     pub string: String,
 }
 
+#[derive(Debug, Clone)]
+pub struct PragmaNode {
+    pub loc: SrcLoc,
+    /// Text of the directive, starting at the `#` of `#pragma`
+    pub text: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct SrcFile {
     pub path: Option<PathBuf>,
@@ -120,6 +127,7 @@ pub struct AstContext {
     pub files: Vec<SrcFile>,
     pub va_list_kind: BuiltinVaListKind,
     pub target: String,
+    pub pragmas: Vec<PragmaNode>,
 }
 
 pub fn expect_opt_str(val: &Value) -> Option<Option<&str>> {
@@ -161,13 +169,14 @@ pub fn process(items: Value) -> error::Result<AstContext> {
     let mut types: HashMap<u64, TypeNode> = HashMap::new();
     let mut comments: Vec<CommentNode> = vec![];
 
-    let (all_nodes, top_nodes, files, raw_comments, va_list_kind, target): (
+    let (all_nodes, top_nodes, files, raw_comments, va_list_kind, target, raw_pragmas): (
         Vec<VecDeque<Value>>,
         Vec<u64>,
         Vec<(String, Option<(u64, u64, u64)>)>,
         Vec<(u64, u64, u64, ByteBuf)>,
         u64,
         String,
+        Vec<(u64, u64, u64, ByteBuf)>,
     ) = from_value(items)?;
 
     let va_list_kind = import_va_list_kind(va_list_kind);
@@ -183,6 +192,18 @@ pub fn process(items: Value) -> error::Result<AstContext> {
         })
     }
 
+    let pragmas = raw_pragmas
+        .into_iter()
+        .map(|(fileid, line, column, bytes)| PragmaNode {
+            loc: SrcLoc {
+                fileid,
+                line,
+                column,
+            },
+            text: String::from_utf8_lossy(&bytes).to_string(),
+        })
+        .collect();
+
     let files = files
         .into_iter()
         .map(|(path, loc)| {
@@ -272,5 +293,6 @@ pub fn process(items: Value) -> error::Result<AstContext> {
         files,
         va_list_kind,
         target,
+        pragmas,
     })
 }
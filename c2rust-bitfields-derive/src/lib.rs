@@ -10,9 +10,6 @@ use syn::{
     PathArguments, PathSegment, Token,
 };
 
-#[cfg(target_endian = "big")]
-compile_error!("Big endian architectures are not currently supported");
-
 /// This struct keeps track of a single bitfield attr's params
 /// as well as the bitfield's field name.
 #[derive(Debug)]
@@ -118,6 +115,44 @@ fn filter_and_parse_fields(field: &Field) -> Vec<Result<BFFieldAttr, Error>> {
         .collect()
 }
 
+/// Parse the bit order of a struct from its `#[bitfield(order = "lsb0")]` or
+/// `#[bitfield(order = "msb0")]` attribute, which defaults to LSB first.
+fn parse_bit_order(attrs: &[Attribute]) -> Result<proc_macro2::TokenStream, Error> {
+    let mut order = quote! { c2rust_bitfields::BitOrder::Lsb0 };
+
+    for attr in attrs {
+        if attr.path.segments.last().unwrap().ident != "bitfield" {
+            continue;
+        }
+
+        if let Meta::List(meta_list) = attr.parse_meta()? {
+            for nested_meta in meta_list.nested {
+                if let NestedMeta::Meta(Meta::NameValue(meta_name_value)) = nested_meta {
+                    if !meta_name_value.path.is_ident("order") {
+                        continue;
+                    }
+
+                    order = match meta_name_value.lit {
+                        Lit::Str(ref lit_str) if lit_str.value() == "lsb0" => {
+                            quote! { c2rust_bitfields::BitOrder::Lsb0 }
+                        }
+                        Lit::Str(ref lit_str) if lit_str.value() == "msb0" => {
+                            quote! { c2rust_bitfields::BitOrder::Msb0 }
+                        }
+                        ref lit => {
+                            let err_str = "order param must be either \"lsb0\" or \"msb0\"";
+
+                            return Err(Error::new(lit.span(), err_str));
+                        }
+                    };
+                }
+            }
+        }
+    }
+
+    Ok(order)
+}
+
 fn parse_bitfield_ty_path(field: &BFFieldAttr) -> Path {
     let leading_colon = if field.ty.starts_with("::") {
         Some(Token![::]([
@@ -228,6 +263,10 @@ fn bitfield_struct_impl(struct_item: ItemStruct) -> Result<TokenStream, Error> {
     let field_bit_info = field_bit_info?;
     let field_bit_info_setters = &field_bit_info;
     let field_bit_info_getters = &field_bit_info;
+    let bit_order = parse_bit_order(&struct_item.attrs)?;
+    let bit_orders: Vec<_> = bitfields.iter().map(|_| bit_order.clone()).collect();
+    let bit_order_setters = &bit_orders;
+    let bit_order_getters = &bit_orders;
 
     // TODO: Method visibility determined by struct field visibility?
     let q = quote! {
@@ -240,7 +279,7 @@ fn bitfield_struct_impl(struct_item: ItemStruct) -> Result<TokenStream, Error> {
 
                     let field = &mut self.#field_names_setters;
                     let (lhs_bit, rhs_bit) = #field_bit_info_setters;
-                    int.set_field(field, (lhs_bit, rhs_bit));
+                    int.set_field_ordered(field, (lhs_bit, rhs_bit), #bit_order_setters);
                 }
 
                 /// This method allows you to read from a bitfield to a value
@@ -251,7 +290,11 @@ fn bitfield_struct_impl(struct_item: ItemStruct) -> Result<TokenStream, Error> {
 
                     let field = &self.#field_names_getters;
                     let (lhs_bit, rhs_bit) = #field_bit_info_getters;
-                    <IntType as FieldType>::get_field(field, (lhs_bit, rhs_bit))
+                    <IntType as FieldType>::get_field_ordered(
+                        field,
+                        (lhs_bit, rhs_bit),
+                        #bit_order_getters,
+                    )
                 }
             )*
         }
@@ -620,3 +620,44 @@ fn test_bool_bits() {
     assert!(bool_bits.y());
     assert!(bool_bits.z());
 }
+
+// *** Dumping AST Record Layout (--target=powerpc-linux-gnu)
+//         0 | struct date
+//     0:0-4 |   unsigned char day
+//     0:5-8 |   unsigned char month
+//    1:1-15 |   unsigned short year
+//           | [sizeof=3, align=1]
+#[repr(C, align(1))]
+#[derive(BitfieldStruct, Copy, Clone)]
+#[bitfield(order = "msb0")]
+struct BigEndianDate {
+    #[bitfield(name = "day", ty = "libc::c_uchar", bits = "0..=4")]
+    #[bitfield(name = "month", ty = "libc::c_uchar", bits = "5..=8")]
+    #[bitfield(name = "year", ty = "libc::c_ushort", bits = "9..=23")]
+    day_month_year: [u8; 3],
+}
+
+#[test]
+fn test_msb0_bits() {
+    let mut date = BigEndianDate {
+        day_month_year: [0; 3],
+    };
+
+    date.set_day(18);
+    date.set_month(7);
+    date.set_year(2000);
+
+    assert_eq!(date.day(), 18);
+    assert_eq!(date.month(), 7);
+    assert_eq!(date.year(), 2000);
+
+    // Each field starts at its most significant bit
+    assert_eq!(date.day_month_year, [0b1001_0011, 0b1000_0111, 0b1101_0000]);
+
+    date.set_month(0);
+
+    assert_eq!(date.day(), 18);
+    assert_eq!(date.month(), 0);
+    assert_eq!(date.year(), 2000);
+    assert_eq!(date.day_month_year, [0b1001_0000, 0b0000_0111, 0b1101_0000]);
+}
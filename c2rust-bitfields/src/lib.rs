@@ -2,6 +2,39 @@
 
 pub use c2rust_bitfields_derive::BitfieldStruct;
 
+/// The order in which the bits of a bitfield struct's storage are allocated, which is
+/// selected for a struct with `#[bitfield(order = "lsb0")]` or `#[bitfield(order = "msb0")]`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BitOrder {
+    /// Bit 0 is the least significant bit of the first byte, as in little endian ABIs. This is
+    /// the default.
+    Lsb0,
+    /// Bit 0 is the most significant bit of the first byte, as in big endian ABIs
+    Msb0,
+}
+
+impl BitOrder {
+    /// The byte index and mask of bit `i` of a value stored in `bit_range`, where bit 0 is the
+    /// least significant bit of the value
+    fn locate(self, bit_range: (usize, usize), i: usize) -> (usize, u8) {
+        let (lhs_bit, rhs_bit) = bit_range;
+
+        match self {
+            BitOrder::Lsb0 => {
+                let bit_index = lhs_bit + i;
+
+                (bit_index / 8, 1 << (bit_index % 8))
+            }
+            // The most significant bit of the value comes first
+            BitOrder::Msb0 => {
+                let bit_index = rhs_bit - i;
+
+                (bit_index / 8, 0x80 >> (bit_index % 8))
+            }
+        }
+    }
+}
+
 pub trait FieldType: Sized {
     const IS_SIGNED: bool;
 
@@ -13,33 +46,29 @@ pub trait FieldType: Sized {
     fn get_bit(&self, bit: usize) -> bool;
 
     fn set_field(&self, field: &mut [u8], bit_range: (usize, usize)) {
-        fn zero_bit(byte: &mut u8, n_bit: u64) {
-            let bit = 1 << n_bit;
-
-            *byte &= !bit as u8;
-        }
-
-        fn one_bit(byte: &mut u8, n_bit: u64) {
-            let bit = 1 << n_bit;
-
-            *byte |= bit as u8;
-        }
+        self.set_field_ordered(field, bit_range, BitOrder::Lsb0)
+    }
 
+    fn set_field_ordered(&self, field: &mut [u8], bit_range: (usize, usize), order: BitOrder) {
         let (lhs_bit, rhs_bit) = bit_range;
 
-        for (i, bit_index) in (lhs_bit..=rhs_bit).enumerate() {
-            let byte_index = bit_index / 8;
+        for i in 0..=(rhs_bit - lhs_bit) {
+            let (byte_index, bit) = order.locate(bit_range, i);
             let byte = &mut field[byte_index];
 
             if self.get_bit(i) {
-                one_bit(byte, (bit_index % 8) as u64);
+                *byte |= bit;
             } else {
-                zero_bit(byte, (bit_index % 8) as u64);
+                *byte &= !bit;
             }
         }
     }
 
-    fn get_field(field: &[u8], bit_range: (usize, usize)) -> Self;
+    fn get_field(field: &[u8], bit_range: (usize, usize)) -> Self {
+        Self::get_field_ordered(field, bit_range, BitOrder::Lsb0)
+    }
+
+    fn get_field_ordered(field: &[u8], bit_range: (usize, usize), order: BitOrder) -> Self;
 }
 
 macro_rules! impl_int {
@@ -52,15 +81,17 @@ macro_rules! impl_int {
                     ((*self >> bit) & 1) == 1
                 }
 
-                fn get_field(field: &[u8], bit_range: (usize, usize)) -> Self {
+                fn get_field_ordered(
+                    field: &[u8],
+                    bit_range: (usize, usize),
+                    order: BitOrder,
+                ) -> Self {
                     let (lhs_bit, rhs_bit) = bit_range;
                     let mut val = 0;
 
-                    for (i, bit_index) in (lhs_bit..=rhs_bit).enumerate() {
-                        let byte_index = bit_index / 8;
-                        let byte = field[byte_index];
-                        let bit = 1 << (bit_index % 8);
-                        let read_bit = byte & bit;
+                    for i in 0..=(rhs_bit - lhs_bit) {
+                        let (byte_index, bit) = order.locate(bit_range, i);
+                        let read_bit = field[byte_index] & bit;
 
                         if read_bit != 0 {
                             let write_bit = 1 << i;
@@ -94,15 +125,13 @@ impl FieldType for bool {
         *self
     }
 
-    fn get_field(field: &[u8], bit_range: (usize, usize)) -> Self {
+    fn get_field_ordered(field: &[u8], bit_range: (usize, usize), order: BitOrder) -> Self {
         let (lhs_bit, rhs_bit) = bit_range;
         let mut val = false;
 
-        for bit_index in lhs_bit..=rhs_bit {
-            let byte_index = bit_index / 8;
-            let byte = field[byte_index];
-            let bit = 1 << (bit_index % 8);
-            let read_bit = byte & bit;
+        for i in 0..=(rhs_bit - lhs_bit) {
+            let (byte_index, bit) = order.locate(bit_range, i);
+            let read_bit = field[byte_index] & bit;
 
             if read_bit != 0 {
                 val = true;
@@ -1,12 +1,14 @@
 use std::mem;
 use std::ptr::{self, NonNull};
 use libc;
+use c2rust_analysis_rt::allocations;
 
 use util::Nullable;
 
 
 /// A pointer to the start of a `malloc`'ed block.  Provides rudimentary bounds checking based on
-/// `malloc_usable_size`.  Dereferencing is still unsafe since there is no guarantee that the
+/// the size recorded by the `c2rust_analysis_rt::allocations` shim, falling back to
+/// `malloc_usable_size` for blocks allocated elsewhere.  Dereferencing is still unsafe since there is no guarantee that the
 /// contents are initialized.
 #[derive(Debug)]
 pub struct CBlockPtr<T>(
@@ -41,7 +43,7 @@ impl<T> CBlockPtr<T> {
                 .unwrap_or_else(|| panic!("overflow when allocating {} elements of size {}",
                                           len, size));
             unsafe {
-                let ptr = libc::malloc(byte_len as libc::size_t);
+                let ptr = allocations::malloc(byte_len as libc::c_ulong);
                 assert!(!ptr.is_null(), "malloc of {} bytes failed", byte_len);
                 CBlockPtr(ptr as *mut T)
             }
@@ -63,7 +65,7 @@ impl<T> CBlockPtr<T> {
     }
 
     pub unsafe fn free(self) {
-        libc::free(self.0 as *mut libc::c_void)
+        allocations::free(self.0 as *mut libc::c_void)
     }
 
     pub fn offset(self, off: isize) -> CBlockOffset<T> {
@@ -78,7 +80,7 @@ impl<T> CBlockPtr<T> {
         if self.0.is_null() {
             return 0;
         }
-        unsafe { libc::malloc_usable_size(self.0 as *mut libc::c_void) as usize }
+        unsafe { allocations::malloc_usable_size(self.0 as *mut libc::c_void) as usize }
     }
 
     pub fn len(self) -> usize {
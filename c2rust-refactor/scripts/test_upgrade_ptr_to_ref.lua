@@ -193,6 +193,16 @@ node_descriptions = {
                 [12] = ConvConfig.new{"byteswap", 1242, 1270},
             },
         },
+        alloc_pairs = {
+            locl = {
+                pairs = ConvConfig.new{"box_slice"},
+            },
+        },
+        alloc_nodes = {
+            locl = {
+                nodes = ConvConfig.new{"box_slice"},
+            },
+        },
         byteswap2 = {
             param = {
                 hashp = ConvConfig.new{"ref"},
@@ -235,6 +245,11 @@ node_descriptions = {
                 mapp = ConvConfig.new{"opt_box_slice"},
             },
         },
+        Node = {
+            field = {
+                payload = ConvConfig.new{"box"},
+            },
+        },
         HashHDR = {
             field = {
                 bitmaps = ConvConfig.new{"array"},
@@ -46,11 +46,12 @@ end
 
 Struct = {}
 
-function Struct.new(lifetimes, is_copy)
+function Struct.new(lifetimes, is_copy, is_zeroable)
     local self = {}
 
     self.lifetimes = lifetimes
     self.is_copy = is_copy
+    self.is_zeroable = is_zeroable
 
     setmetatable(self, Struct)
     Struct.__index = Struct
@@ -100,9 +101,14 @@ function ConvConfig:is_mut()
     return self.extra_data.mutability == "mut"
 end
 
+function ConvConfig:is_restrict()
+    return self.extra_data.restrict == true
+end
+
 function ConvConfig.from_marks_and_attrs(marks, attrs)
     local opt = true
     local slice = false
+    local restrict = false
     local mutability = nil
     local binding = nil
     local conv_type = ""
@@ -112,11 +118,14 @@ function ConvConfig.from_marks_and_attrs(marks, attrs)
     local box = marks["box"]
 
     for _, attr in ipairs(attrs) do
-        local attr_ident = attr:ident():get_name()
+        local attr_ident = attr:ident()
 
-        if attr_ident == "nonnull" then
+        -- Path attributes such as #[c2rust::restrict] have no single ident
+        if not attr_ident then
+            restrict = restrict or attr:is_c2rust_attr("restrict")
+        elseif attr_ident:get_name() == "nonnull" then
             opt = false
-        elseif attr_ident == "slice" then
+        elseif attr_ident:get_name() == "slice" then
             slice = true
         end
     end
@@ -166,7 +175,7 @@ function ConvConfig.from_marks_and_attrs(marks, attrs)
         return
     end
 
-    return ConvConfig.new{conv_type, mutability=mutability, binding=binding}
+    return ConvConfig.new{conv_type, mutability=mutability, binding=binding, restrict=restrict}
 end
 
 function ConvConfig:failed_rewrite()
@@ -267,6 +276,8 @@ function Visitor.new(tctx, node_id_cfgs)
     self.fns = {}
     -- Expr NodeId -> Arg NodeId
     self.call_param_expr_to_arg_id = {}
+    -- PatHirId -> bool, for non-optional vars assigned a malloc/calloc
+    self.heap_allocated = {}
 
     setmetatable(self, Visitor)
     Visitor.__index = Visitor
@@ -900,6 +911,9 @@ function Visitor:rewrite_assign_expr(expr)
 
         -- p = malloc(X) as *mut T -> p = Some(vec![0; X / size_of<T>].into_boxed_slice())
         -- or p = vec![0; X / size_of<T>].into_boxed_slice()
+        -- p = calloc(N, S) as *mut T -> p = vec![T::default(); N * S / size_of<T>].into_boxed_slice()
+        -- where N * S is overflow checked, and T::default() is ::core::mem::zeroed::<T>() unless T
+        -- is primitive
         if cast_ty:kind_name() == "Ptr" and cast_expr:kind_name() == "Call" then
             local call_exprs = cast_expr:get_exprs()
             local path_expr = call_exprs[1]
@@ -913,13 +927,42 @@ function Visitor:rewrite_assign_expr(expr)
             end
 
             -- In case malloc is called from another module check the last segment
-            if conversion_cfg and segment_idents[#segment_idents] == "malloc" then
+            local alloc_fn = segment_idents[#segment_idents]
+
+            if conversion_cfg and (alloc_fn == "malloc" or alloc_fn == "calloc") then
                 local mut_ty = cast_ty:get_mut_ty()
                 local pointee_ty = mut_ty:get_ty()
                 local new_rhs = nil
+                local usize_ty = Ty.new{"Path", nil, Path.new{"usize"}}
+                local byte_count = Expr.new{"Cast", param_expr, usize_ty}
                 -- TODO: zero-init will only work for numbers, not structs/unions
                 local init = self.tctx:int_lit_expr(0, nil)
 
+                if alloc_fn == "calloc" then
+                    local size_expr = Expr.new{"Cast", call_exprs[3], usize_ty}
+
+                    byte_count = Expr.new{"MethodCall", PathSegment.new("checked_mul"), {byte_count, size_expr}}
+                    byte_count = Expr.new{"MethodCall", PathSegment.new("unwrap"), {byte_count}}
+
+                    -- Translated structs don't derive Default, so they are zeroed like calloc
+                    -- does, which the unsafe fns calling calloc may do, as long as zero is a
+                    -- valid value of all their fields
+                    if is_primitive_ty(pointee_ty) then
+                        init = default_expr(pointee_ty)
+                    elseif self:is_zeroable_ty(pointee_ty) then
+                        local zeroed_path = path_expr:get_path()
+                        zeroed_path:set_segments{"", "core", "mem", "zeroed"}
+                        zeroed_path:set_generic_angled_arg_tys(4, {pointee_ty})
+                        init = self.tctx:int_lit_expr(0, nil)
+                        init:to_path(zeroed_path)
+                        init:to_call{init}
+                    else
+                        conversion_cfg.extra_data.failed_rewrite = true
+                        log_error("Failed to rewrite calloc of a type that can't be zeroed: " .. tostring(expr))
+                        return
+                    end
+                end
+
                 -- For slices we want to use vec![init; num].into_boxed_slice
                 if conversion_cfg:is_slice_any() then
                     path:set_segments{"", "core", "mem", "size_of"}
@@ -927,12 +970,10 @@ function Visitor:rewrite_assign_expr(expr)
                     path_expr:to_path(path)
                     path_expr:to_call{path_expr}
 
-                    local usize_ty = Ty.new{"Path", nil, Path.new{"usize"}}
-                    local cast_expr = Expr.new{"Cast", param_expr, usize_ty}
                     local binary_expr = Expr.new{
                         "Binary",
                         {"Spanned", node="Div", span=DUMMY_SP},
-                        cast_expr,
+                        byte_count,
                         path_expr,
                     }
 
@@ -955,6 +996,12 @@ function Visitor:rewrite_assign_expr(expr)
                     rhs = new_rhs
                 end
 
+                -- A null check on a non-optional allocation can never succeed,
+                -- since the allocation aborts on OOM instead of returning null
+                if not conversion_cfg:is_opt_any() then
+                    self.heap_allocated[tostring(hirid)] = true
+                end
+
                 expr:set_exprs{lhs, rhs}
             end
         -- p = 0 as *mut/const T -> p = None
@@ -1086,7 +1133,7 @@ function Visitor:rewrite_call_expr(expr)
             end
         end
     -- Skip; handled elsewhere by local conversion
-    elseif segment_idents[#segment_idents] == "malloc" then
+    elseif segment_idents[#segment_idents] == "malloc" or segment_idents[#segment_idents] == "calloc" then
     -- Generic function call param conversions
     -- NOTE: Some(x) counts as a function call on x, so we skip Some
     -- so as to not recurse when we generate that expr
@@ -1304,6 +1351,7 @@ function Visitor:flat_map_item(item, walk)
         local lifetimes = OrderedMap()
         local fields = item:get_fields()
         local is_copy = true
+        local is_zeroable = true
 
         for _, field in ipairs(fields) do
             local field_id = field:get_id()
@@ -1312,6 +1360,11 @@ function Visitor:flat_map_item(item, walk)
 
             self:add_field(field_hrid, Field.new(field_id))
 
+            -- Fields upgraded to references or boxes can't be zeroed
+            if cfg or not self:is_zeroable_ty(field:get_ty()) then
+                is_zeroable = false
+            end
+
             if cfg then
                 if cfg:is_box_any() then
                     is_copy = false
@@ -1331,7 +1384,7 @@ function Visitor:flat_map_item(item, walk)
 
         local hirid = self.tctx:nodeid_to_hirid(item:get_id())
 
-        self:add_struct(hirid, Struct.new(lifetimes, is_copy))
+        self:add_struct(hirid, Struct.new(lifetimes, is_copy, is_zeroable))
     elseif item_kind == "Fn" then
         self:clear_nonstatic_vars()
 
@@ -1420,6 +1473,12 @@ end
 function Visitor:flat_map_stmt(stmt, walk)
     local cfg = self.node_id_cfgs[stmt:get_id()]
 
+    -- if p.is_null() { return ...; } following an allocation of a non-optional
+    -- p is dead code, so we remove it rather than leave an `if false` behind
+    if self:is_alloc_null_guard(stmt) then
+        return {}
+    end
+
     if not cfg then
         walk(stmt)
         return {stmt}
@@ -1525,6 +1584,30 @@ function Visitor:flat_map_stmt(stmt, walk)
     return {stmt}
 end
 
+function Visitor:is_alloc_null_guard(stmt)
+    local stmt_kind = stmt:kind_name()
+
+    if stmt_kind ~= "Expr" and stmt_kind ~= "Semi" then return false end
+
+    local expr = stmt:get_node()
+
+    if expr:kind_name() ~= "If" then return false end
+
+    local exprs = expr:get_exprs()
+    local cond = exprs[1]
+
+    -- An else branch may still be reachable
+    if #exprs ~= 1 or cond:kind_name() ~= "MethodCall" or cond:get_method_name() ~= "is_null" then
+        return false
+    end
+
+    local callee = cond:get_exprs()[1]
+    local hirid = self.tctx:resolve_path_hirid(callee)
+    local cfg = self:get_expr_cfg(callee)
+
+    return hirid ~= nil and self.heap_allocated[tostring(hirid)] and cfg and not cfg:is_opt_any()
+end
+
 function Visitor:flat_map_struct_field(field)
     local field_id = field:get_id()
     local field_ty = field:get_ty()
@@ -1553,6 +1636,62 @@ function Visitor:flat_map_struct_field(field)
     return {field}
 end
 
+-- Primitive types, and the libc types aliasing them, which implement Default
+local primitive_tys = tablex.makeset{
+    "i8", "i16", "i32", "i64", "i128", "isize",
+    "u8", "u16", "u32", "u64", "u128", "usize",
+    "f32", "f64", "bool", "char",
+}
+
+function is_primitive_ty(ty)
+    local path = ty:get_path()
+
+    if not path then return false end
+
+    local segment_idents = tablex.map(function(x) return x:get_ident():get_name() end, path:get_segments())
+    local name = segment_idents[#segment_idents]
+
+    if #segment_idents == 1 then
+        return primitive_tys[name] == true
+    end
+
+    return #segment_idents == 2 and segment_idents[1] == "libc" and name:sub(1, 2) == "c_"
+end
+
+-- Whether zero is a valid value of ty, as for numbers, raw pointers, and arrays and structs of
+-- them. Structs (but not unions) are known once visited, and only if none of their fields are
+-- upgraded.
+function Visitor:is_zeroable_ty(ty)
+    local ty_kind = ty:kind_name()
+
+    if ty_kind == "Ptr" or is_primitive_ty(ty) then
+        return true
+    elseif ty_kind == "Array" then
+        return self:is_zeroable_ty(ty:get_tys()[1])
+    elseif ty_kind == "Path" then
+        local struct = self:get_struct(self.tctx:resolve_ty_hirid(ty))
+
+        return struct ~= nil and struct.is_zeroable
+    end
+
+    return false
+end
+
+-- Builds T::default() for a path type T, or nil for any other type
+function default_expr(ty)
+    local path = ty:get_path()
+
+    if not path then return nil end
+
+    local segment_idents = tablex.map(function(x) return x:get_ident():get_name() end, path:get_segments())
+
+    table.insert(segment_idents, "default")
+
+    local default_path_expr = Expr.new{"Path", nil, Path.new(segment_idents)}
+
+    return Expr.new{"Call", default_path_expr, {}}
+end
+
 function is_null_ptr(expr)
     if expr and expr:kind_name() == "Cast" then
         local cast_expr = expr:get_exprs()[1]
@@ -1757,6 +1896,7 @@ function ConfigBuilder:flat_map_item(item, walk)
         local fn_sig = item:child(1)
         local decl = fn_sig:get_decl()
         local params = decl:get_inputs()
+        local has_restrict = false
 
         for _, param in ipairs(params) do
             local param_id = param:get_id()
@@ -1775,12 +1915,31 @@ function ConfigBuilder:flat_map_item(item, walk)
             end
 
             local attrs = param:get_attrs()
+            local cfg = ConvConfig.from_marks_and_attrs(marks, attrs)
 
             self.pat_to_var_id[param:get_pat():get_id()] = param_id
-            self.node_id_cfgs[param_id] = ConvConfig.from_marks_and_attrs(marks, attrs)
+            self.node_id_cfgs[param_id] = cfg
+            has_restrict = has_restrict or (cfg and cfg:is_restrict())
 
             ::continue::
         end
+
+        -- Once some params are marked restrict, the unmarked ones may alias each other
+        -- (or a restrict one outside of its accesses), so only restrict params are
+        -- upgraded to &mut
+        if has_restrict then
+            for _, param in ipairs(params) do
+                local param_id = param:get_id()
+                local cfg = self.node_id_cfgs[param_id]
+
+                if cfg and cfg:is_mut() and not cfg:is_restrict() and not cfg:is_box_any() then
+                    log_warn("Not upgrading possibly aliased param to &mut: " .. tostring(param:get_pat()))
+
+                    self.pat_to_var_id[param:get_pat():get_id()] = nil
+                    self.node_id_cfgs[param_id] = nil
+                end
+            end
+        end
     elseif item_kind == "Static" then
         local ty = item:get_kind():child(1)
 
@@ -1028,6 +1028,8 @@ impl AddMoreMethods for LuaAstNode<P<Expr>> {
                 ExprKind::Assign(lhs, rhs)
                 | ExprKind::AssignOp(_, lhs, rhs)
                 | ExprKind::Binary(_, lhs, rhs) => vec![lhs.clone(), rhs.clone()],
+                ExprKind::If(cond, _, None) => vec![cond.clone()],
+                ExprKind::If(cond, _, Some(els)) => vec![cond.clone(), els.clone()],
                 ExprKind::Call(func, params) => {
                     let mut exprs = Vec::with_capacity(params.len() + 1);
 
@@ -1957,6 +1959,10 @@ impl AddMoreMethods for LuaAstNode<Attribute> {
                 Ok(None)
             }
         });
+
+        methods.add_method("is_c2rust_attr", |_lua_ctx, this, name: LuaString| {
+            Ok(util::is_c2rust_attr(&this.borrow(), name.to_str()?))
+        });
     }
 }
 
@@ -0,0 +1,287 @@
+use std::collections::HashSet;
+use rustc::hir::HirId;
+use syntax::ast::*;
+use syntax::ptr::P;
+use syntax::visit::{self, Visitor};
+
+use c2rust_ast_builder::mk;
+use crate::ast_manip::{MutVisitNodes, visit_nodes};
+use crate::command::{CommandState, Registry};
+use crate::driver::Phase;
+use crate::matcher::{MatchCtxt, Subst, mut_visit_match_with};
+use crate::transform::Transform;
+use crate::RefactorCtxt;
+
+
+/// # `index_writes_to_push` Command
+///
+/// Usage: `index_writes_to_push [string]`
+///
+/// Marks: `target`
+///
+/// Convert local array buffers marked `target` that are filled in with the C
+/// `buf[i++] = c; buf[i] = '\0';` pattern into `Vec`s that grow with `push`, or
+/// into `String`s if the `string` argument is given.
+///
+/// Example:
+///
+/// ```ignore
+///     let mut buf: [libc::c_char; 16] = [0; 16];  // buf: target
+///     let mut i = 0;
+///     while i < 15 && *s != 0 {
+///         let fresh0 = i;
+///         i = i + 1;
+///         buf[fresh0 as usize] = *s;
+///         s = s.offset(1);
+///     }
+///     if i < 16 {
+///         buf[i as usize] = '!' as i32 as libc::c_char;
+///         i = i + 1;
+///     }
+///     buf[i as usize] = 0;
+///     puts(buf.as_ptr());
+/// ```
+///
+/// After running `index_writes_to_push`:
+///
+/// ```ignore
+///     let mut buf: Vec<libc::c_char> = Vec::with_capacity(16 as usize);
+///     let mut i = 0;
+///     while i < 15 && *s != 0 {
+///         buf.push(*s);
+///         i = i + 1;
+///         s = s.offset(1);
+///     }
+///     buf.push('!' as i32 as libc::c_char);
+///     i = i + 1;
+///     buf.push(0);
+///     puts(buf.as_ptr());
+/// ```
+///
+/// Each write to the element at the index followed by an increment of the index
+/// becomes a `push`, and a write of zero without an increment is the terminator.
+/// An `if` that only checks the index against the capacity before pushing is
+/// removed, since the buffer now grows as needed.  Capacity checks in loop
+/// conditions are kept, as they still bound the length of the result.
+///
+/// With `string`, elements are pushed as `char`s, the terminator is dropped, and
+/// `buf.as_ptr()` becomes a pointer into a temporary `CString` copy of the
+/// buffer, which is only valid until the end of the enclosing statement.
+///
+/// The index is assumed to start at zero and to only be advanced by these
+/// writes.  Buffers with any other use (reading an element, taking a pointer
+/// into the middle, `len()`, ...) are left unchanged.
+pub struct IndexWritesToPush {
+    string: bool,
+}
+
+impl Transform for IndexWritesToPush {
+    fn transform(&self, krate: &mut Crate, st: &CommandState, cx: &RefactorCtxt) {
+        // (1) Find the marked array buffers.
+
+        let mut bufs = HashSet::new();
+        visit_nodes(krate, |l: &Local| {
+            if !st.marked(l.id, "target") && !st.marked(l.pat.id, "target") {
+                return;
+            }
+            let is_array_buf = match (&l.pat.kind,
+                                      l.ty.as_ref().map(|ty| &ty.kind),
+                                      l.init.as_ref().map(|e| &e.kind)) {
+                (&PatKind::Ident(BindingMode::ByValue(Mutability::Mutable), _, None),
+                 Some(&TyKind::Array(..)),
+                 Some(&ExprKind::Repeat(..))) => true,
+                _ => false,
+            };
+            if is_array_buf {
+                bufs.insert(cx.hir_map().node_to_hir_id(l.pat.id));
+            }
+        });
+
+        // (2) Rewrite a copy of the crate first, and give up on the buffers that are still used
+        // in a way the growable buffer doesn't support.
+
+        let mut trial = krate.clone();
+        self.rewrite_uses(&mut trial, st, cx, &bufs);
+        let allowed: &[&str] = if self.string {
+            &["push", "clone"]
+        } else {
+            &["push", "as_ptr", "as_mut_ptr"]
+        };
+        let mut v = BufUsesVisitor {
+            cx,
+            bufs: &bufs,
+            allowed,
+            rejected: HashSet::new(),
+        };
+        visit::walk_crate(&mut v, &trial);
+        let rejected = v.rejected;
+        bufs.retain(|id| !rejected.contains(id));
+
+        // (3) Rewrite the declarations and uses of the remaining buffers.
+
+        MutVisitNodes::visit(krate, |l: &mut P<Local>| {
+            if !bufs.contains(&cx.hir_map().node_to_hir_id(l.pat.id)) {
+                return;
+            }
+            let elem_ty = expect!([l.ty.as_ref().unwrap().kind]
+                                  TyKind::Array(ref elem_ty, _) => elem_ty.clone());
+            let len = expect!([l.init.as_ref().unwrap().kind]
+                              ExprKind::Repeat(_, ref len) => len.value.clone());
+            let (ty, ctor) = if self.string {
+                (mk().ident_ty("String"), "String")
+            } else {
+                let args = mk().angle_bracketed_args(vec![elem_ty]);
+                (mk().path_ty(vec![mk().path_segment_with_args("Vec", args)]), "Vec")
+            };
+            l.ty = Some(ty);
+            l.init = Some(mk().call_expr(
+                mk().path_expr(vec![ctor, "with_capacity"]),
+                vec![mk().cast_expr(len, mk().ident_ty("usize"))]));
+        });
+        self.rewrite_uses(krate, st, cx, &bufs);
+    }
+
+    fn min_phase(&self) -> Phase {
+        Phase::Phase3
+    }
+}
+
+impl IndexWritesToPush {
+    fn rewrite_uses(&self,
+                    krate: &mut Crate,
+                    st: &CommandState,
+                    cx: &RefactorCtxt,
+                    bufs: &HashSet<HirId>) {
+        let is_buf = |mcx: &MatchCtxt| {
+            let buf = mcx.bindings.get::<_, P<Expr>>("$buf").unwrap();
+            cx.try_resolve_expr_to_hid(buf).map_or(false, |id| bufs.contains(&id))
+        };
+
+        let mut mcx = MatchCtxt::new(st, cx);
+        let push = if self.string {
+            "$buf.push($c as u8 as char);"
+        } else {
+            "$buf.push($c);"
+        };
+
+        // `buf[i++] = c`, as translated from C, and `buf[i] = c; i++`
+        for incr in &["$i = $i + 1;", "$i += 1;"] {
+            let post_incr = mcx.parse_stmts(&format!(
+                "let $fresh:Ident = $i:Expr; {} $buf:Expr[cast!($fresh)] = $c:Expr;", incr));
+            let write_incr = mcx.parse_stmts(&format!(
+                "$buf:Expr[cast!($i:Expr)] = $c:Expr; {}", incr));
+            let repl = mcx.parse_stmts(&format!("{} {}", push, incr));
+            for pat in vec![post_incr, write_incr] {
+                mut_visit_match_with(mcx.clone(), pat, krate, |orig, mcx| {
+                    if is_buf(&mcx) {
+                        *orig = repl.clone().subst(st, cx, &mcx.bindings);
+                    }
+                });
+            }
+        }
+
+        // The terminator, `buf[i] = 0`
+        let term = mcx.parse_stmts("$buf:Expr[cast!($i:Expr)] = $c:Expr;");
+        let term_repl = if self.string {
+            vec![]
+        } else {
+            mcx.parse_stmts(push)
+        };
+        mut_visit_match_with(mcx.clone(), term, krate, |orig, mcx| {
+            let c = mcx.bindings.get::<_, P<Expr>>("$c").unwrap();
+            if is_buf(&mcx) && is_zero(c) {
+                *orig = term_repl.clone().subst(st, cx, &mcx.bindings);
+            }
+        });
+
+        // Capacity checks guarding pushes
+        let incrs = vec![mcx.parse_expr("$i = $i + 1"), mcx.parse_expr("$i += 1")];
+        for cmp in &["<", "<="] {
+            let guard = mcx.parse_stmts(&format!(
+                "if cast!($i:Expr) {} $n:Expr {{ $body:MultiStmt; }}", cmp));
+            mut_visit_match_with(mcx.clone(), guard, krate, |orig, mut mcx| {
+                let body = mcx.bindings.get::<_, Vec<Stmt>>("$body").unwrap().clone();
+                let mut pushes = 0;
+                for stmt in &body {
+                    let e = match stmt.kind {
+                        StmtKind::Semi(ref e) | StmtKind::Expr(ref e) => e,
+                        _ => return,
+                    };
+                    match e.kind {
+                        ExprKind::MethodCall(ref seg, ref args) if seg.ident.as_str() == "push" &&
+                            cx.try_resolve_expr_to_hid(&args[0])
+                                .map_or(false, |id| bufs.contains(&id)) => pushes += 1,
+                        _ if incrs.iter().any(|incr| mcx.try_match(&**incr, e).is_ok()) => {}
+                        _ => return,
+                    }
+                }
+                if pushes > 0 {
+                    *orig = body;
+                }
+            });
+        }
+
+        // Pointers to a `String` buffer go through a NUL-terminated copy
+        if self.string {
+            let as_ptr = mcx.parse_expr("$buf:Expr.as_ptr()");
+            let repl = mcx.parse_expr("::std::ffi::CString::new($buf.clone()).unwrap().as_ptr()");
+            mut_visit_match_with(mcx.clone(), as_ptr, krate, |orig, mcx| {
+                if is_buf(&mcx) {
+                    *orig = repl.clone().subst(st, cx, &mcx.bindings);
+                }
+            });
+        }
+    }
+}
+
+fn is_zero(e: &Expr) -> bool {
+    match e.kind {
+        ExprKind::Cast(ref e, _) | ExprKind::Paren(ref e) => is_zero(e),
+        ExprKind::Lit(ref l) => match l.kind {
+            LitKind::Int(0, _) | LitKind::Byte(0) | LitKind::Char('\0') => true,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Collects the buffers that are used other than as the receiver of one of the `allowed`
+/// methods.
+struct BufUsesVisitor<'a, 'tcx: 'a> {
+    cx: &'a RefactorCtxt<'a, 'tcx>,
+    bufs: &'a HashSet<HirId>,
+    allowed: &'a [&'a str],
+    rejected: HashSet<HirId>,
+}
+
+impl<'a, 'tcx> BufUsesVisitor<'a, 'tcx> {
+    fn buf(&self, e: &Expr) -> Option<HirId> {
+        self.cx.try_resolve_expr_to_hid(e).filter(|id| self.bufs.contains(id))
+    }
+}
+
+impl<'a, 'tcx, 'ast> Visitor<'ast> for BufUsesVisitor<'a, 'tcx> {
+    fn visit_expr(&mut self, e: &'ast Expr) {
+        if let ExprKind::MethodCall(ref seg, ref args) = e.kind {
+            if self.allowed.contains(&&*seg.ident.as_str()) && self.buf(&args[0]).is_some() {
+                for arg in &args[1..] {
+                    self.visit_expr(arg);
+                }
+                return;
+            }
+        }
+        if let Some(id) = self.buf(e) {
+            self.rejected.insert(id);
+        }
+        visit::walk_expr(self, e);
+    }
+}
+
+
+pub fn register_commands(reg: &mut Registry) {
+    use super::mk;
+
+    reg.register("index_writes_to_push", |args| mk(IndexWritesToPush {
+        string: args.get(0).map_or(false, |arg| arg == "string"),
+    }));
+}
@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet};
+use rustc::hir::HirId;
 use rustc::hir::def_id::DefId;
 use rustc::ty::TyKind;
 use syntax::ast;
@@ -6,12 +7,14 @@ use syntax::ast::*;
 use syntax::attr;
 use syntax::mut_visit::{self, MutVisitor};
 use syntax::ptr::P;
+use syntax::symbol::Symbol;
 use syntax_pos::sym;
 use smallvec::{smallvec, SmallVec};
 
 use c2rust_ast_builder::{mk, IntoSymbol};
-use crate::ast_manip::{FlatMapNodes, MutVisitNodes, fold_modules, visit_nodes, MutVisit};
-use crate::command::{CommandState, Registry};
+use crate::ast_manip::{FlatMapNodes, MutVisitNodes, fold_modules, visit_nodes, MutVisit, AstEquiv};
+use crate::ast_manip::fn_edit::{mut_visit_fns, FnKind};
+use crate::command::{Command, CommandState, RefactorState, Registry};
 use crate::driver::{Phase, parse_expr};
 use crate::matcher::{BindingType, MatchCtxt, Subst, mut_visit_match_with};
 use crate::path_edit::{fold_resolved_paths, fold_resolved_paths_with_id};
@@ -263,6 +266,617 @@ impl Transform for ToMethod {
 }
 
 
+/// # `group_methods` Command
+///
+/// Usage: `group_methods`
+///
+/// Marks: `target`
+///
+/// Turn functions marked `target` whose first argument is a raw pointer to a
+/// struct defined in the crate, the C idiom for methods, into methods of that
+/// struct.  The methods are added to its inherent `impl`, which is created after
+/// the struct if there is none.
+///
+/// The pointer argument becomes `&mut self` if the function writes through it,
+/// borrows from it mutably or passes it on as a pointer, and `&self` otherwise.
+/// A `*const` argument always becomes `&self`.  Functions named after the
+/// struct, like `list_push` for `struct list`, drop that prefix.  Calls
+/// `f(p, ...)` are rewritten to `(*p).f(...)`, or `x.f(...)` when `p` is `&mut
+/// x` or `&x`.
+///
+/// Functions that reassign the pointer, are exported with `#[no_mangle]`, or are
+/// used other than by calling them are left alone.
+///
+/// Example:
+///
+/// ```ignore
+///     struct list { len: i32 }
+///
+///     unsafe fn list_len(l: *const list) -> i32 {  // list_len: target
+///         (*l).len
+///     }
+///
+///     unsafe fn f(l: *mut list) -> i32 {
+///         list_len(l)
+///     }
+/// ```
+///
+/// After running `group_methods`:
+///
+/// ```ignore
+///     struct list { len: i32 }
+///     impl list {
+///         unsafe fn len(&self) -> i32 {
+///             self.len
+///         }
+///     }
+///
+///     unsafe fn f(l: *mut list) -> i32 {
+///         (*l).len()
+///     }
+/// ```
+pub struct GroupMethods;
+
+impl Transform for GroupMethods {
+    fn transform(&self, krate: &mut Crate, st: &CommandState, cx: &RefactorCtxt) {
+        // (1) Find the structs defined in the crate, and the methods of their inherent impls.
+
+        let mut structs: HashMap<DefId, Ident> = HashMap::new();
+        let mut method_names: HashMap<DefId, HashSet<Symbol>> = HashMap::new();
+        let mut impls = HashSet::new();
+        visit_nodes(krate, |i: &Item| {
+            match i.kind {
+                ItemKind::Struct(..) => {
+                    structs.insert(cx.node_def_id(i.id), i.ident);
+                }
+                ItemKind::Impl(_, _, _, _, None, ref ty, ref items) => {
+                    if let Some(def_id) = cx.try_resolve_ty(ty) {
+                        impls.insert(def_id);
+                        method_names.entry(def_id).or_insert_with(HashSet::new)
+                            .extend(items.iter().map(|item| item.ident.name));
+                    }
+                }
+                _ => {}
+            }
+        });
+
+        // (2) Find the marked functions taking a pointer to one of the structs first, and decide
+        // how each borrows `self`.
+
+        struct MethodInfo {
+            struct_id: DefId,
+            name: Ident,
+            mutbl: Mutability,
+            /// The expressions referring to the pointer argument
+            self_uses: HashSet<NodeId>,
+        }
+        let mut methods: HashMap<DefId, MethodInfo> = HashMap::new();
+        visit_nodes(krate, |i: &Item| {
+            if !st.marked(i.id, "target") || attr::contains_name(&i.attrs, sym::no_mangle) {
+                return;
+            }
+            let (sig, body) = match_or!([i.kind] ItemKind::Fn(ref sig, _, ref body) =>
+                                        (sig, body); return);
+            let arg = match_or!([sig.decl.inputs.first()] Some(x) => x; return);
+            if !matches!([arg.pat.kind] PatKind::Ident(BindingMode::ByValue(_), _, None)) {
+                return;
+            }
+            let (pointee, ptr_mutbl) = match_or!([arg.ty.kind] ast::TyKind::Ptr(ref mt) =>
+                                                 (&mt.ty, mt.mutbl); return);
+            let struct_id = match cx.try_resolve_ty(pointee) {
+                Some(def_id) if structs.contains_key(&def_id) => def_id,
+                _ => return,
+            };
+
+            let arg_hir_id = cx.hir_map().node_to_hir_id(arg.pat.id);
+            let (self_uses, mutated) = match_or!([self_pointer_uses(cx, body, arg_hir_id)]
+                                                 Some(x) => x; return);
+            let mutbl = if ptr_mutbl == Mutability::Mutable && mutated {
+                Mutability::Mutable
+            } else {
+                Mutability::Immutable
+            };
+
+            // Drop the struct name from the method name, unless that makes it clash with
+            // another method
+            let names = method_names.entry(struct_id).or_insert_with(HashSet::new);
+            let fn_name = i.ident.as_str().to_string();
+            let prefix = format!("{}_", structs[&struct_id].as_str().to_lowercase());
+            let mut name = i.ident;
+            if fn_name.to_lowercase().starts_with(&prefix) {
+                let short = Ident::from_str(&fn_name[prefix.len()..]);
+                let is_ident = fn_name[prefix.len()..].chars().next()
+                    .map_or(false, |c| c.is_alphabetic() || c == '_');
+                if is_ident && !short.is_reserved() && !names.contains(&short.name) {
+                    name = short;
+                }
+            }
+            names.insert(name.name);
+
+            methods.insert(cx.node_def_id(i.id), MethodInfo {
+                struct_id, name, mutbl, self_uses,
+            });
+        });
+
+        // Functions used other than by calling them stay functions
+        let mut callees = HashSet::new();
+        visit_nodes(krate, |e: &Expr| {
+            if let ExprKind::Call(ref func, _) = e.kind {
+                callees.insert(func.id);
+            }
+        });
+        visit_nodes(krate, |e: &Expr| {
+            if !matches!([e.kind] ExprKind::Path(..)) || callees.contains(&e.id) {
+                return;
+            }
+            if let Some(def_id) = cx.try_resolve_expr(e) {
+                methods.remove(&def_id);
+            }
+        });
+        if methods.is_empty() {
+            return;
+        }
+
+        // (3) Rewrite the calls into method calls.  This happens first so that calls inside the
+        // methods themselves get rewritten along with their bodies.
+
+        MutVisitNodes::visit(krate, |e: &mut P<Expr>| {
+            let (func, args) = match_or!([e.kind] ExprKind::Call(ref func, ref args) =>
+                                         (func, args); return);
+            let info = match_or!([cx.try_resolve_expr(func).and_then(|id| methods.get(&id))]
+                                 Some(x) => x; return);
+            let mut args = args.clone();
+            let ptr = args.remove(0);
+            let receiver = match ptr.kind {
+                ExprKind::AddrOf(_, _, ref place) => place.clone(),
+                _ => mk().paren_expr(mk().unary_expr("*", ptr)),
+            };
+            args.insert(0, receiver);
+            e.kind = ExprKind::MethodCall(mk().path_segment(&info.name), args);
+        });
+
+        // (4) Take the functions out of their modules, turning them into methods.
+
+        let mut new_methods: HashMap<DefId, Vec<ImplItem>> = HashMap::new();
+        FlatMapNodes::visit(krate, |i: P<Item>| {
+            let info = match cx.hir_map().opt_local_def_id_from_node_id(i.id)
+                .and_then(|def_id| methods.get(&def_id)) {
+                Some(info) => info,
+                None => return smallvec![i],
+            };
+            unpack!([i.kind.clone()] ItemKind::Fn(sig, generics, block));
+            let mut sig = sig;
+            let mut block = block;
+
+            let mut inputs = sig.decl.inputs.clone();
+            inputs[0] = mk().self_arg(SelfKind::Region(None, info.mutbl));
+            sig.decl = sig.decl.clone().map(|fd| FnDecl { inputs, .. fd });
+
+            // `(*p).x` and `(*p).f()` become `self.x` and `self.f()`, `*p` becomes `*self`, and
+            // other uses of the pointer become `self as *mut Self`.
+            fn is_deref_self(e: &Expr, self_uses: &HashSet<NodeId>) -> bool {
+                match e.kind {
+                    ExprKind::Paren(ref e) => is_deref_self(e, self_uses),
+                    ExprKind::Unary(UnOp::Deref, ref p) => self_uses.contains(&p.id),
+                    _ => false,
+                }
+            }
+            MutVisitNodes::visit(&mut block, |e: &mut P<Expr>| {
+                match e.kind {
+                    ExprKind::Field(ref mut base, _) if is_deref_self(base, &info.self_uses) => {
+                        *base = mk().ident_expr("self");
+                    }
+                    ExprKind::MethodCall(_, ref mut args) if is_deref_self(&args[0], &info.self_uses) => {
+                        args[0] = mk().ident_expr("self");
+                    }
+                    _ => {}
+                }
+            });
+            MutVisitNodes::visit(&mut block, |e: &mut P<Expr>| {
+                match e.kind {
+                    ExprKind::Unary(UnOp::Deref, ref p) if info.self_uses.contains(&p.id) => {
+                        *e = mk().unary_expr("*", mk().ident_expr("self"));
+                    }
+                    ExprKind::Path(..) if info.self_uses.contains(&e.id) => {
+                        *e = mk().cast_expr(
+                            mk().ident_expr("self"),
+                            mk().set_mutbl(info.mutbl).ptr_ty(mk().ident_ty("Self")));
+                    }
+                    _ => {}
+                }
+            });
+
+            new_methods.entry(info.struct_id).or_insert_with(Vec::new).push(ImplItem {
+                id: DUMMY_NODE_ID,
+                ident: info.name,
+                vis: i.vis.clone(),
+                defaultness: Defaultness::Final,
+                attrs: i.attrs.clone(),
+                generics,
+                kind: ImplItemKind::Method(sig, block),
+                span: i.span,
+                tokens: None,
+            });
+            smallvec![]
+        });
+
+        // (5) Add the methods to the first inherent impl of each struct, or to a new impl after
+        // the struct.
+
+        FlatMapNodes::visit(krate, |i: P<Item>| {
+            let struct_id = match i.kind {
+                ItemKind::Impl(_, _, _, _, None, ref ty, _) => cx.try_resolve_ty(ty),
+                _ => None,
+            };
+            if let Some(items) = struct_id.and_then(|id| new_methods.remove(&id)) {
+                return smallvec![i.map(|mut i| {
+                    if let ItemKind::Impl(_, _, _, _, _, _, ref mut impl_items) = i.kind {
+                        impl_items.extend(items);
+                    }
+                    i
+                })];
+            }
+
+            let def_id = match_or!([i.kind] ItemKind::Struct(..) => cx.node_def_id(i.id);
+                                   return smallvec![i]);
+            // Structs with an impl get the methods there, wherever it is
+            if impls.contains(&def_id) {
+                return smallvec![i];
+            }
+            let items = match_or!([new_methods.remove(&def_id)] Some(x) => x;
+                                  return smallvec![i]);
+            let mut imp = st.parse_items(cx, &format!("impl {} {{}}", i.ident)).remove(0);
+            if let ItemKind::Impl(_, _, _, _, _, _, ref mut impl_items) = imp.kind {
+                impl_items.extend(items);
+            }
+            smallvec![i, imp]
+        });
+    }
+
+    fn min_phase(&self) -> Phase {
+        Phase::Phase3
+    }
+}
+
+/// The expressions referring to the pointer argument `id` in `body`, and whether the function
+/// writes through it or passes it on, or `None` if the argument is reassigned.
+pub fn self_pointer_uses(cx: &RefactorCtxt, body: &Block, id: HirId) -> Option<(HashSet<NodeId>, bool)> {
+    let is_arg = |e: &Expr| {
+        matches!([e.kind] ExprKind::Path(..)) && cx.try_resolve_expr_to_hid(e) == Some(id)
+    };
+    // Is `e` a place inside `*p`?
+    fn is_in_deref(e: &Expr, is_arg: &dyn Fn(&Expr) -> bool) -> bool {
+        match e.kind {
+            ExprKind::Field(ref e, _) | ExprKind::Index(ref e, _) | ExprKind::Paren(ref e) =>
+                is_in_deref(e, is_arg),
+            ExprKind::Unary(UnOp::Deref, ref p) => is_arg(p),
+            _ => false,
+        }
+    }
+
+    let mut uses = HashSet::new();
+    let mut derefs = HashSet::new();
+    let mut reassigned = false;
+    let mut mutated = false;
+    visit_nodes(body, |e: &Expr| {
+        match e.kind {
+            ExprKind::Assign(ref lhs, _) |
+            ExprKind::AssignOp(_, ref lhs, _) |
+            ExprKind::AddrOf(_, Mutability::Mutable, ref lhs) => {
+                if is_arg(lhs) {
+                    reassigned = true;
+                } else if is_in_deref(lhs, &is_arg) {
+                    mutated = true;
+                }
+            }
+            ExprKind::Unary(UnOp::Deref, ref p) if is_arg(p) => {
+                derefs.insert(p.id);
+            }
+            _ => {}
+        }
+        if is_arg(e) {
+            uses.insert(e.id);
+        }
+    });
+    if reassigned {
+        return None;
+    }
+    // Any use other than `*p` passes the pointer on
+    mutated |= uses.iter().any(|id| !derefs.contains(id));
+    Some((uses, mutated))
+}
+
+/// # `ptr_len_to_slice` Command
+///
+/// Usage: `ptr_len_to_slice`
+///
+/// Marks: `target`
+///
+/// For functions marked `target`, turn each pointer argument followed by an
+/// integer length, the C idiom `(T *buf, size_t n)`, into a single slice
+/// argument: `&mut [T]` for a `*mut T` and `&[T]` for a `*const T`.  The length
+/// becomes a local initialized with the length of the slice.  In the body,
+/// `*p.offset(i)`, `*p.add(i)` and `*p` become `p[i as usize]`, `p[i]` and
+/// `p[0]`, and other uses of `p`, such as passing it to a foreign function, pass
+/// `p.as_mut_ptr()` or `p.as_ptr()` instead.
+///
+/// Calls passing `x.as_mut_ptr(), x.len()` pass `&mut x` instead, and other
+/// calls build the slice from the pointer and length with
+/// `slice::from_raw_parts_mut` or `slice::from_raw_parts`.
+///
+/// Pointers that are reassigned or point to `c_void`, and functions that are
+/// exported with `#[no_mangle]` or used other than by calling them, are left
+/// alone.
+///
+/// Example:
+///
+/// ```ignore
+///     unsafe fn sum(xs: *const i32, n: usize) -> i32 {  // sum: target
+///         let mut s = 0;
+///         let mut i = 0;
+///         while i < n {
+///             s += *xs.offset(i as isize);
+///             i += 1;
+///         }
+///         s
+///     }
+///
+///     sum(v.as_ptr(), v.len());
+/// ```
+///
+/// After running `ptr_len_to_slice`:
+///
+/// ```ignore
+///     unsafe fn sum(xs: &[i32]) -> i32 {
+///         let n: usize = xs.len() as usize;
+///         let mut s = 0;
+///         let mut i = 0;
+///         while i < n {
+///             s += xs[i as usize];
+///             i += 1;
+///         }
+///         s
+///     }
+///
+///     sum(&v);
+/// ```
+pub struct PtrLenToSlice;
+
+/// A pointer argument and the length following it, turned into a slice argument
+struct SliceArg {
+    /// The index of the pointer argument
+    idx: usize,
+    /// The name of the pointer argument
+    name: Ident,
+    mutbl: Mutability,
+    /// The type of the elements
+    elem: P<Ty>,
+    /// The expressions referring to the pointer in the body
+    uses: HashSet<NodeId>,
+    /// The pattern and type of the length argument
+    len_pat: P<Pat>,
+    len_ty: P<Ty>,
+}
+
+impl Transform for PtrLenToSlice {
+    fn transform(&self, krate: &mut Crate, st: &CommandState, cx: &RefactorCtxt) {
+        // (1) Find the pointer and length arguments of the marked functions
+        let mut fns: HashMap<DefId, Vec<SliceArg>> = HashMap::new();
+        visit_nodes(krate, |i: &Item| {
+            if !st.marked(i.id, "target") || attr::contains_name(&i.attrs, sym::no_mangle) {
+                return;
+            }
+            let (sig, body) = match_or!([i.kind] ItemKind::Fn(ref sig, _, ref body) =>
+                                        (sig, body); return);
+            let inputs = &sig.decl.inputs;
+            let mut slice_args = vec![];
+            let mut idx = 0;
+            while idx + 1 < inputs.len() {
+                match slice_arg(cx, idx, &inputs[idx], &inputs[idx + 1], body) {
+                    Some(arg) => {
+                        slice_args.push(arg);
+                        idx += 2;
+                    }
+                    None => idx += 1,
+                }
+            }
+            if !slice_args.is_empty() {
+                fns.insert(cx.node_def_id(i.id), slice_args);
+            }
+        });
+
+        // Functions used other than by calling them keep their signatures
+        let mut callees = HashSet::new();
+        visit_nodes(krate, |e: &Expr| {
+            if let ExprKind::Call(ref func, _) = e.kind {
+                callees.insert(func.id);
+            }
+        });
+        visit_nodes(krate, |e: &Expr| {
+            if !matches!([e.kind] ExprKind::Path(..)) || callees.contains(&e.id) {
+                return;
+            }
+            if let Some(def_id) = cx.try_resolve_expr(e) {
+                fns.remove(&def_id);
+            }
+        });
+
+        // (2) Pass slices in place of the pointers and lengths
+        MutVisitNodes::visit(krate, |e: &mut P<Expr>| {
+            let callee = match_or!([e.kind] ExprKind::Call(ref func, _) =>
+                                   cx.try_resolve_expr(func); return);
+            let slice_args = match_or!([callee.and_then(|id| fns.get(&id))] Some(x) => x; return);
+            let args = match_or!([e.kind] ExprKind::Call(_, ref mut args) => args; return);
+            // Go from the last argument, so removing lengths doesn't move the others
+            for arg in slice_args.iter().rev() {
+                if arg.idx + 1 >= args.len() {
+                    continue;
+                }
+                let len = args.remove(arg.idx + 1);
+                let slice = slice_expr(cx, &args[arg.idx], &len, arg.mutbl);
+                args[arg.idx] = slice;
+            }
+        });
+
+        // (3) Retype the pointers, and define the lengths at the start of the body
+        mut_visit_fns(krate, |fl| {
+            if fl.kind != FnKind::Normal {
+                return;
+            }
+            let slice_args = match_or!([fns.get(&cx.node_def_id(fl.id))] Some(x) => x; return);
+            let block = match_or!([fl.block] Some(ref mut x) => x; return);
+            let mut len_stmts = vec![];
+            for arg in slice_args.iter().rev() {
+                fl.decl.inputs[arg.idx].ty = mk().set_mutbl(arg.mutbl)
+                    .ref_ty(mk().slice_ty(&arg.elem));
+                fl.decl.inputs.remove(arg.idx + 1);
+                let len = mk().method_call_expr(
+                    mk().ident_expr(arg.name), "len", Vec::<P<Expr>>::new());
+                let len = mk().cast_expr(len, &arg.len_ty);
+                let local = mk().local(arg.len_pat.clone(), Some(arg.len_ty.clone()), Some(len));
+                len_stmts.push(mk().local_stmt(P(local)));
+            }
+            len_stmts.reverse();
+            block.stmts.splice(0..0, len_stmts);
+        });
+
+        // (4) Index the slices, and pass on pointers to them elsewhere
+        let mut uses = HashMap::new();
+        for arg in fns.values().flatten() {
+            uses.extend(arg.uses.iter().map(|&id| (id, arg)));
+        }
+        // The element accesses, and the pointers they go through
+        let mut elems = HashSet::new();
+        let mut elem_ptrs = HashSet::new();
+        visit_nodes(krate, |e: &Expr| {
+            if let Some((p, _)) = elem_access(e) {
+                if uses.contains_key(&p.id) {
+                    elems.insert(e.id);
+                    elem_ptrs.insert(p.id);
+                }
+            }
+        });
+        MutVisitNodes::visit(krate, |e: &mut P<Expr>| {
+            if elems.contains(&e.id) {
+                let (p, idx) = elem_access(e).unwrap();
+                *e = mk().index_expr(p.clone(), idx);
+            } else if let Some(arg) = uses.get(&e.id) {
+                if elem_ptrs.contains(&e.id) {
+                    return;
+                }
+                let method = match arg.mutbl {
+                    Mutability::Mutable => "as_mut_ptr",
+                    Mutability::Immutable => "as_ptr",
+                };
+                *e = mk().method_call_expr(e.clone(), method, Vec::<P<Expr>>::new());
+            }
+        });
+    }
+
+    fn min_phase(&self) -> Phase {
+        Phase::Phase3
+    }
+}
+
+/// The slice argument replacing the pointer argument `ptr`, at index `idx`, and the length
+/// argument `len` following it, if they are turned into one
+fn slice_arg(cx: &RefactorCtxt,
+             idx: usize,
+             ptr: &Param,
+             len: &Param,
+             body: &Block) -> Option<SliceArg> {
+    let name = match_or!([ptr.pat.kind] PatKind::Ident(BindingMode::ByValue(_), ident, None) =>
+                         ident; return None);
+    let mt = match_or!([ptr.ty.kind] ast::TyKind::Ptr(ref mt) => mt; return None);
+    // Pointers to `c_void` are untyped buffers, measured in bytes
+    if let ast::TyKind::Path(_, ref path) = mt.ty.kind {
+        if path.segments.last().map_or(false, |seg| seg.ident.as_str() == "c_void") {
+            return None;
+        }
+    }
+    if !matches!([len.pat.kind] PatKind::Ident(BindingMode::ByValue(_), _, None)) ||
+       !cx.node_type(len.pat.id).is_integral() {
+        return None;
+    }
+    let (uses, _) = self_pointer_uses(cx, body, cx.hir_map().node_to_hir_id(ptr.pat.id))?;
+    Some(SliceArg {
+        idx,
+        name,
+        mutbl: mt.mutbl,
+        elem: mt.ty.clone(),
+        uses,
+        len_pat: len.pat.clone(),
+        len_ty: len.ty.clone(),
+    })
+}
+
+/// The slice to pass in place of the pointer `ptr` and the length `len`
+fn slice_expr(cx: &RefactorCtxt, ptr: &P<Expr>, len: &P<Expr>, mutbl: Mutability) -> P<Expr> {
+    fn strip_casts(mut e: &P<Expr>) -> &P<Expr> {
+        while let ExprKind::Cast(ref x, _) | ExprKind::Paren(ref x) = e.kind {
+            e = x;
+        }
+        e
+    }
+
+    // `x.as_ptr(), x.len()` passes `x` itself
+    if let (ExprKind::MethodCall(ref ptr_seg, ref ptr_args),
+            ExprKind::MethodCall(ref len_seg, ref len_args)) =
+           (&strip_casts(ptr).kind, &strip_casts(len).kind) {
+        let ptr_method = ptr_seg.ident.as_str();
+        let is_ptr = ptr_method == "as_mut_ptr" ||
+            (ptr_method == "as_ptr" && mutbl == Mutability::Immutable);
+        if is_ptr && len_seg.ident.as_str() == "len" && ptr_args[0].ast_equiv(&len_args[0]) {
+            let x = ptr_args[0].clone();
+            // Reborrow slices, rather than borrowing the reference
+            let x = match cx.opt_node_type(x.id).map(|ty| &ty.kind) {
+                Some(TyKind::Ref(..)) => mk().unary_expr("*", x),
+                _ => x,
+            };
+            return mk().set_mutbl(mutbl).addr_of_expr(x);
+        }
+    }
+
+    let from_raw_parts = match mutbl {
+        Mutability::Mutable => "from_raw_parts_mut",
+        Mutability::Immutable => "from_raw_parts",
+    };
+    mk().call_expr(
+        mk().path_expr(vec!["", "std", "slice", from_raw_parts]),
+        vec![ptr.clone(), mk().cast_expr(len.clone(), mk().ident_ty("usize"))],
+    )
+}
+
+/// The pointer and the index of the element `e` accesses, if it is `*p`, `*p.offset(i)` or
+/// `*p.add(i)`
+fn elem_access(e: &Expr) -> Option<(&P<Expr>, P<Expr>)> {
+    let mut ptr = match_or!([e.kind] ExprKind::Unary(UnOp::Deref, ref x) => x; return None);
+    while let ExprKind::Paren(ref x) = ptr.kind {
+        ptr = x;
+    }
+    match ptr.kind {
+        ExprKind::MethodCall(ref seg, ref args)
+            if args.len() == 2 && seg.ident.as_str() == "offset" =>
+        {
+            // `i as isize` indexes with `i as usize`
+            let idx = match args[1].kind {
+                ExprKind::Cast(ref i, _) => i.clone(),
+                _ => args[1].clone(),
+            };
+            Some((&args[0], mk().cast_expr(idx, mk().ident_ty("usize"))))
+        }
+        ExprKind::MethodCall(ref seg, ref args)
+            if args.len() == 2 && seg.ident.as_str() == "add" =>
+        {
+            Some((&args[0], args[1].clone()))
+        }
+        ExprKind::Path(..) => Some((ptr, mk().lit_expr(mk().int_lit(0, LitIntType::Unsuffixed)))),
+        _ => None,
+    }
+}
+
+
+
 /// # `fix_unused_unsafe` Command
 ///
 /// Usage: `fix_unused_unsafe`
@@ -357,6 +971,266 @@ impl Transform for SinkUnsafe {
     }
 }
 
+/// # `shrink_unsafe` Command
+///
+/// Usage: `shrink_unsafe`
+///
+/// Marks: `target`
+///
+/// For functions marked `target`, narrow `unsafe` blocks down to the
+/// statements and expressions that need them, and turn `unsafe fn`s that no
+/// longer do anything unsafe into safe `fn`s.
+///
+/// The statements of `unsafe` blocks, and of the bodies of `unsafe fn`s, are
+/// each wrapped in an `unsafe` block of their own, going into the bodies of
+/// `if`, `while`, `loop` and `for`, and into nested blocks.  The crate is then
+/// typechecked again, and the new blocks that aren't needed are removed.  An
+/// `unsafe fn` with no `unsafe` block left becomes a safe `fn`, and the
+/// `unsafe` blocks its callers no longer need are removed as well.  Otherwise,
+/// it stays an `unsafe fn`, with no `unsafe` blocks in its body.
+///
+/// `unsafe fn`s used other than by calling them keep their `unsafe`.
+///
+/// Example:
+///
+/// ```ignore
+///     unsafe fn f(p: *mut i32) {  // f: target
+///         let x = 1 + 2;
+///         *p = x;
+///     }
+///
+///     fn g() {    // g: target
+///         let mut y = 0;
+///         unsafe {
+///             y += 1;
+///             f(&mut y);
+///         }
+///     }
+/// ```
+///
+/// After running `shrink_unsafe`:
+///
+/// ```ignore
+///     unsafe fn f(p: *mut i32) {
+///         let x = 1 + 2;
+///         *p = x;
+///     }
+///
+///     fn g() {
+///         let mut y = 0;
+///         y += 1;
+///         unsafe { f(&mut y) };
+///     }
+/// ```
+pub struct ShrinkUnsafe;
+
+impl Command for ShrinkUnsafe {
+    fn run(&mut self, state: &mut RefactorState) {
+        let (fns, unsafe_fns) = state.transform_crate(Phase::Phase3, |st, cx| {
+            narrow_unsafe(st, cx)
+        }).expect("Failed to run compiler");
+        let demoted = state.transform_crate(Phase::Phase3, |st, cx| {
+            remove_unneeded_unsafe(st, cx, &fns, &unsafe_fns)
+        }).expect("Failed to run compiler");
+        state.transform_crate(Phase::Phase3, |st, cx| {
+            remove_caller_unsafe(st, cx, &demoted)
+        }).expect("Failed to run compiler");
+    }
+}
+
+/// Narrow the `unsafe` blocks of the marked functions, making the `unsafe fn`s safe for now.
+/// Returns the marked functions, and those that were `unsafe fn`s.
+fn narrow_unsafe(st: &CommandState, cx: &RefactorCtxt) -> (HashSet<NodeId>, HashSet<NodeId>) {
+    let krate = &mut *st.krate_mut();
+
+    // `unsafe fn`s used other than by calling them keep their type
+    let mut callees = HashSet::new();
+    visit_nodes(krate, |e: &Expr| {
+        if let ExprKind::Call(ref func, _) = e.kind {
+            callees.insert(func.id);
+        }
+    });
+    let mut fn_uses = HashSet::new();
+    visit_nodes(krate, |e: &Expr| {
+        if matches!([e.kind] ExprKind::Path(..)) && !callees.contains(&e.id) {
+            fn_uses.extend(cx.try_resolve_expr(e));
+        }
+    });
+
+    let mut fns = HashSet::new();
+    let mut unsafe_fns = HashSet::new();
+    FlatMapNodes::visit(krate, |i: P<Item>| {
+        if !st.marked(i.id, "target") {
+            return smallvec![i];
+        }
+        let def_id = cx.node_def_id(i.id);
+        smallvec![i.map(|mut i| {
+            if let ItemKind::Fn(ref mut sig, _, ref mut body) = i.kind {
+                match sig.header.unsafety {
+                    Unsafety::Unsafe if fn_uses.contains(&def_id) => return i,
+                    Unsafety::Unsafe => {
+                        sig.header.unsafety = Unsafety::Normal;
+                        unsafe_fns.insert(i.id);
+                        narrow_unsafe_block(body);
+                    }
+                    Unsafety::Normal => {
+                        MutVisitNodes::visit(body, |b: &mut P<Block>| {
+                            if let BlockCheckMode::Unsafe(UnsafeSource::UserProvided) = b.rules {
+                                b.rules = BlockCheckMode::Default;
+                                narrow_unsafe_block(b);
+                            }
+                        });
+                    }
+                }
+                fns.insert(i.id);
+            }
+            i
+        })]
+    });
+    (fns, unsafe_fns)
+}
+
+/// Wrap each statement of `b` that may need `unsafe` into an `unsafe` block of its own
+fn narrow_unsafe_block(b: &mut P<Block>) {
+    for stmt in &mut b.stmts {
+        match stmt.kind {
+            StmtKind::Local(ref mut l) => {
+                if let Some(ref mut init) = l.init {
+                    *init = unsafe_expr(init.clone());
+                }
+            }
+            StmtKind::Expr(ref mut e) | StmtKind::Semi(ref mut e) => narrow_unsafe_expr(e),
+            StmtKind::Item(_) | StmtKind::Mac(_) => {}
+        }
+    }
+}
+
+/// Wrap `e` into an `unsafe` block, or the parts of it that may need one if it is a control flow
+/// expression or a block
+fn narrow_unsafe_expr(e: &mut P<Expr>) {
+    match e.kind {
+        ExprKind::If(ref mut cond, ref mut then, ref mut els) => {
+            narrow_unsafe_cond(cond);
+            narrow_unsafe_block(then);
+            if let Some(ref mut els) = *els {
+                narrow_unsafe_expr(els);
+            }
+        }
+        ExprKind::While(ref mut cond, ref mut body, _) => {
+            narrow_unsafe_cond(cond);
+            narrow_unsafe_block(body);
+        }
+        ExprKind::ForLoop(_, ref mut iter, ref mut body, _) => {
+            *iter = unsafe_expr(iter.clone());
+            narrow_unsafe_block(body);
+        }
+        ExprKind::Loop(ref mut body, _) => narrow_unsafe_block(body),
+        ExprKind::Block(ref mut b, _) => {
+            b.rules = BlockCheckMode::Default;
+            narrow_unsafe_block(b);
+        }
+        _ => *e = unsafe_expr(e.clone()),
+    }
+}
+
+/// Wrap the condition of an `if` or `while` into an `unsafe` block
+fn narrow_unsafe_cond(cond: &mut P<Expr>) {
+    match cond.kind {
+        // Only the scrutinee of `if let` is an expression
+        ExprKind::Let(_, ref mut scrutinee) => *scrutinee = unsafe_expr(scrutinee.clone()),
+        _ => *cond = unsafe_expr(cond.clone()),
+    }
+}
+
+fn unsafe_expr(e: P<Expr>) -> P<Expr> {
+    mk().block_expr(mk().unsafe_().block(vec![mk().expr_stmt(e)]))
+}
+
+fn is_unsafe_block(b: &Block) -> bool {
+    matches!([b.rules] BlockCheckMode::Unsafe(UnsafeSource::UserProvided))
+}
+
+/// Check whether the `unsafe` block `b` is needed, by the last typecheck
+fn unsafe_block_used(cx: &RefactorCtxt, b: &Block) -> bool {
+    let hir_id = cx.hir_map().node_to_hir_id(b.id);
+    let parent = cx.hir_map().get_parent_did(hir_id);
+    let result = cx.ty_ctxt().unsafety_check_result(parent);
+    result.unsafe_blocks.iter().any(|&(id, used)| id == hir_id && used)
+}
+
+/// Replace the `unsafe` block expression `e` by its expression, if it has a single one, or by an
+/// ordinary block
+fn remove_unsafe_block(e: &mut P<Expr>) {
+    let b = match_or!([e.kind] ExprKind::Block(ref mut b, _) => b; return);
+    if b.stmts.len() == 1 {
+        if let StmtKind::Expr(ref x) = b.stmts[0].kind {
+            let x = x.clone();
+            *e = x;
+            return;
+        }
+    }
+    b.rules = BlockCheckMode::Default;
+}
+
+/// Remove the `unsafe` blocks of the functions in `fns` that aren't needed.  The functions of
+/// `unsafe_fns` that still need some become `unsafe fn`s again, and the others are returned.
+fn remove_unneeded_unsafe(st: &CommandState,
+                          cx: &RefactorCtxt,
+                          fns: &HashSet<NodeId>,
+                          unsafe_fns: &HashSet<NodeId>) -> HashSet<DefId> {
+    let mut demoted = HashSet::new();
+    FlatMapNodes::visit(&mut *st.krate_mut(), |i: P<Item>| {
+        if !fns.contains(&i.id) {
+            return smallvec![i];
+        }
+        let def_id = cx.node_def_id(i.id);
+        smallvec![i.map(|mut i| {
+            if let ItemKind::Fn(ref mut sig, _, ref mut body) = i.kind {
+                let mut needs_unsafe = false;
+                visit_nodes(&**body, |b: &Block| {
+                    needs_unsafe |= is_unsafe_block(b) && unsafe_block_used(cx, b);
+                });
+                // `unsafe` blocks in an `unsafe fn` are all redundant
+                let is_unsafe_fn = needs_unsafe && unsafe_fns.contains(&i.id);
+                if is_unsafe_fn {
+                    sig.header.unsafety = Unsafety::Unsafe;
+                } else if unsafe_fns.contains(&i.id) {
+                    demoted.insert(def_id);
+                }
+                MutVisitNodes::visit(body, |e: &mut P<Expr>| {
+                    let b = match_or!([e.kind] ExprKind::Block(ref b, None) => b; return);
+                    if is_unsafe_block(b) && (is_unsafe_fn || !unsafe_block_used(cx, b)) {
+                        remove_unsafe_block(e);
+                    }
+                });
+            }
+            i
+        })]
+    });
+    demoted
+}
+
+/// Remove the `unsafe` blocks that were only needed to call the functions of `demoted`
+fn remove_caller_unsafe(st: &CommandState, cx: &RefactorCtxt, demoted: &HashSet<DefId>) {
+    MutVisitNodes::visit(&mut *st.krate_mut(), |e: &mut P<Expr>| {
+        let b = match_or!([e.kind] ExprKind::Block(ref b, None) => b; return);
+        if !is_unsafe_block(b) || unsafe_block_used(cx, b) {
+            return;
+        }
+        let mut calls_demoted = false;
+        visit_nodes(&**b, |e: &Expr| {
+            if let ExprKind::Call(ref func, _) = e.kind {
+                calls_demoted |= cx.try_resolve_expr(func)
+                    .map_or(false, |id| demoted.contains(&id));
+            }
+        });
+        if calls_demoted {
+            remove_unsafe_block(e);
+        }
+    });
+}
+
+
 
 /// # `wrap_extern` Command
 ///
@@ -801,8 +1675,11 @@ pub fn register_commands(reg: &mut Registry) {
     use super::mk;
 
     reg.register("func_to_method", |_args| mk(ToMethod));
+    reg.register("group_methods", |_args| mk(GroupMethods));
+    reg.register("ptr_len_to_slice", |_args| mk(PtrLenToSlice));
     reg.register("fix_unused_unsafe", |_args| mk(FixUnusedUnsafe));
     reg.register("sink_unsafe", |_args| mk(SinkUnsafe));
+    reg.register("shrink_unsafe", |_args| Box::new(ShrinkUnsafe));
     reg.register("wrap_extern", |_args| mk(WrapExtern));
     reg.register("wrap_api", |_args| mk(WrapApi));
     reg.register("abstract", |args| mk(Abstract {
@@ -0,0 +1,207 @@
+use rustc::ty;
+use syntax::ast::*;
+use syntax::ptr::P;
+
+use crate::ast_manip::MutVisitNodes;
+use crate::command::{CommandState, Registry};
+use crate::driver::Phase;
+use crate::matcher::{MatchCtxt, Subst, mut_visit_match_with};
+use crate::transform::Transform;
+use crate::RefactorCtxt;
+
+
+/// A check of the bindings of a match, on top of the pattern
+type Guard = fn(&MatchCtxt, &RefactorCtxt) -> bool;
+
+/// The libc calls `libc_to_std` rewrites: the name of the libc function, the call pattern
+/// (`$f` is the callee), its replacement, and the check a match has to pass, if any.  Forms on
+/// slices come before the general forms on raw pointers.
+const REWRITES: &[(&str, &str, &str, Option<Guard>)] = &[
+    ("memcpy",
+     "$f:Expr(cast!($dst:Expr.as_mut_ptr()), cast!($src:Expr.as_ptr()), $n:Expr);",
+     "$dst[..$n as usize].copy_from_slice(&$src[..$n as usize]);",
+     Some(same_byte_slices)),
+    ("memcpy",
+     "$f:Expr($dst:Expr, $src:Expr, $n:Expr);",
+     "::std::ptr::copy_nonoverlapping($src as *const u8, $dst as *mut u8, $n as usize);",
+     None),
+    ("memmove",
+     "$f:Expr($dst:Expr, $src:Expr, $n:Expr);",
+     "::std::ptr::copy($src as *const u8, $dst as *mut u8, $n as usize);",
+     None),
+    ("memset",
+     "$f:Expr($dst:Expr, $c:Expr, $n:Expr);",
+     "::std::ptr::write_bytes($dst as *mut u8, $c as u8, $n as usize);",
+     None),
+    ("strlen",
+     "$f:Expr($s:Expr as *const u8 as *const libc::c_char)",
+     "($s.len() - 1) as libc::c_ulong",
+     Some(nul_terminated_lit)),
+    ("strlen",
+     "$f:Expr($s:Expr)",
+     "::std::ffi::CStr::from_ptr($s).to_bytes().len() as libc::c_ulong",
+     None),
+    ("abs",
+     "$f:Expr($x:Expr)",
+     "$x.wrapping_abs()",
+     None),
+    ("qsort",
+     "$f:Expr(cast!($base:Expr), $n:Expr, cast!(::std::mem::size_of::<$t:Ty>()), \
+      Some(cast!($cmp:Expr)));",
+     "::std::slice::from_raw_parts_mut($base as *mut $t, $n as usize).sort_by(|a, b| \
+      $cmp(a as *const $t as *const libc::c_void, b as *const $t as *const libc::c_void) \
+      .cmp(&0));",
+     None),
+];
+
+/// Both `$dst` and `$src` are arrays or slices of the same byte type, so that `$n` counts their
+/// elements.
+fn same_byte_slices(mcx: &MatchCtxt, cx: &RefactorCtxt) -> bool {
+    let dst = mcx.bindings.get::<_, P<Expr>>("$dst").unwrap();
+    let src = mcx.bindings.get::<_, P<Expr>>("$src").unwrap();
+    match (byte_elem_ty(cx, dst), byte_elem_ty(cx, src)) {
+        (Some(dst), Some(src)) => dst == src,
+        _ => false,
+    }
+}
+
+/// The element type of `e`, if it's an array or slice of bytes, or a reference to one.
+fn byte_elem_ty<'tcx>(cx: &RefactorCtxt<'_, 'tcx>, e: &Expr) -> Option<ty::Ty<'tcx>> {
+    let mut ty = cx.opt_node_type(e.id)?;
+    while let ty::TyKind::Ref(_, inner, _) = ty.kind {
+        ty = inner;
+    }
+    match ty.kind {
+        ty::TyKind::Array(elem, _) | ty::TyKind::Slice(elem) => match elem.kind {
+            ty::TyKind::Uint(UintTy::U8) | ty::TyKind::Int(IntTy::I8) => Some(elem),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// `$s` is a byte string literal whose only NUL is its terminator, so that its length is known.
+fn nul_terminated_lit(mcx: &MatchCtxt, _cx: &RefactorCtxt) -> bool {
+    let s = mcx.bindings.get::<_, P<Expr>>("$s").unwrap();
+    match s.kind {
+        ExprKind::Lit(ref lit) => match lit.kind {
+            LitKind::ByteStr(ref bytes) => {
+                bytes.iter().position(|&b| b == 0) == bytes.len().checked_sub(1)
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// A parsed entry of `REWRITES`
+enum Rewrite {
+    Expr(P<Expr>, P<Expr>),
+    Stmts(Vec<Stmt>, Vec<Stmt>),
+}
+
+/// # `libc_to_std` Command
+///
+/// Usage: `libc_to_std`
+///
+/// Marks: `target`
+///
+/// In functions marked `target`, replace calls to libc functions with their
+/// `std` equivalents:
+///
+///  * `memcpy(dst.as_mut_ptr(), src.as_ptr(), n);` becomes
+///    `dst[..n].copy_from_slice(&src[..n]);` when `dst` and `src` are arrays
+///    or slices of the same byte type, and `memcpy(dst, src, n);` becomes
+///    `ptr::copy_nonoverlapping(src, dst, n);` otherwise
+///  * `memmove(dst, src, n);` becomes `ptr::copy(src, dst, n);`
+///  * `memset(dst, c, n);` becomes `ptr::write_bytes(dst, c, n);`
+///  * `strlen(s)` becomes `s.len() - 1` when `s` is a byte string literal
+///    with no NUL before its terminator, and `CStr::from_ptr(s).to_bytes().len()`
+///    otherwise
+///  * `abs(x)` becomes `x.wrapping_abs()`
+///  * `qsort(base, n, size_of::<T>(), Some(cmp));` becomes
+///    `slice::from_raw_parts_mut(base as *mut T, n).sort_by(...)`, comparing
+///    with `cmp`
+///
+/// The `mem*` functions are only rewritten when their result is unused, and
+/// `qsort` only when the element size is a `size_of`.  Other functions are left
+/// alone, so the translation only changes where it's asked to.
+///
+/// Example:
+///
+/// ```ignore
+///     memset(buf as *mut libc::c_void, 0 as libc::c_int,
+///            ::std::mem::size_of::<[u8; 16]>() as libc::c_ulong);
+/// ```
+///
+/// After running `libc_to_std`:
+///
+/// ```ignore
+///     ::std::ptr::write_bytes(buf as *mut libc::c_void as *mut u8, 0 as libc::c_int as u8,
+///                             ::std::mem::size_of::<[u8; 16]>() as libc::c_ulong as usize);
+/// ```
+pub struct LibcToStd;
+
+impl Transform for LibcToStd {
+    fn transform(&self, krate: &mut Crate, st: &CommandState, cx: &RefactorCtxt) {
+        let is_libc_fn = |mcx: &MatchCtxt, name: &str| {
+            let f = mcx.bindings.get::<_, P<Expr>>("$f").unwrap();
+            let tcx = cx.ty_ctxt();
+            cx.try_resolve_expr(f).map_or(false, |def_id| {
+                tcx.is_foreign_item(def_id) && tcx.item_name(def_id).as_str() == name
+            })
+        };
+
+        let mut mcx = MatchCtxt::new(st, cx);
+        let rewrites = REWRITES.iter().map(|&(name, pat, repl, guard)| {
+            let rewrite = if pat.ends_with(';') {
+                Rewrite::Stmts(mcx.parse_stmts(pat), mcx.parse_stmts(repl))
+            } else {
+                Rewrite::Expr(mcx.parse_expr(pat), mcx.parse_expr(repl))
+            };
+            (name, rewrite, guard)
+        }).collect::<Vec<_>>();
+        let applies = |mcx: &MatchCtxt, name: &str, guard: Option<Guard>| {
+            is_libc_fn(mcx, name) && guard.map_or(true, |guard| guard(mcx, cx))
+        };
+
+        MutVisitNodes::visit(krate, |i: &mut P<Item>| {
+            if !st.marked(i.id, "target") {
+                return;
+            }
+            match i.kind {
+                ItemKind::Fn(..) => {}
+                _ => return,
+            }
+            for &(name, ref rewrite, guard) in &rewrites {
+                match rewrite {
+                    Rewrite::Expr(pat, repl) => {
+                        mut_visit_match_with(mcx.clone(), pat.clone(), i, |orig, mcx| {
+                            if applies(&mcx, name, guard) {
+                                *orig = repl.clone().subst(st, cx, &mcx.bindings);
+                            }
+                        });
+                    }
+                    Rewrite::Stmts(pat, repl) => {
+                        mut_visit_match_with(mcx.clone(), pat.clone(), i, |orig, mcx| {
+                            if applies(&mcx, name, guard) {
+                                *orig = repl.clone().subst(st, cx, &mcx.bindings);
+                            }
+                        });
+                    }
+                }
+            }
+        });
+    }
+
+    fn min_phase(&self) -> Phase {
+        Phase::Phase3
+    }
+}
+
+
+pub fn register_commands(reg: &mut Registry) {
+    use super::mk;
+
+    reg.register("libc_to_std", |_args| mk(LibcToStd));
+}
@@ -49,6 +49,7 @@ macro_rules! transform_modules {
 }
 
 transform_modules! {
+    buffers,
     canonicalize_refs,
     casts,
     char_literals,
@@ -59,14 +60,18 @@ transform_modules! {
     generics,
     ionize,
     items,
+    libc_to_std,
     lifetime_analysis,
     linkage,
     literals,
     reorganize_definitions,
     ownership,
+    pthread_to_std,
     retype,
     rewrite,
+    specialize,
     statics,
+    status,
     structs,
     test,
     vars,
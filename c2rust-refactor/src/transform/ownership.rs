@@ -3,24 +3,28 @@ use std::collections::HashSet;
 
 use arena::SyncDroplessArena;
 use rustc::hir::def_id::DefId;
+use rustc::ty;
 use rustc_index::vec::IndexVec;
 use syntax::ast::*;
+use syntax::attr;
 use syntax::source_map::DUMMY_SP;
 use syntax::mut_visit::{self, MutVisitor};
 use syntax::token::{self, Token, TokenKind, DelimToken};
 use syntax::ptr::P;
 use syntax::symbol::Symbol;
 use syntax::tokenstream::{TokenTree, TokenStream, DelimSpan};
+use syntax_pos::sym;
 use smallvec::{smallvec, SmallVec};
 
-use crate::ast_manip::{MutVisitNodes, MutVisit};
-use crate::ast_manip::fn_edit::flat_map_fns;
+use crate::ast_manip::{MutVisitNodes, MutVisit, visit_nodes};
+use crate::ast_manip::fn_edit::{flat_map_fns, mut_visit_fns};
 use crate::analysis::labeled_ty::LabeledTyCtxt;
 use crate::analysis::ownership::{self, ConcretePerm, Var, PTy};
 use crate::analysis::ownership::constraint::{ConstraintSet, Perm};
-use crate::command::{CommandState, Registry, DriverCommand};
+use crate::command::{Command, CommandState, Registry, DriverCommand, RefactorState};
 use crate::context::HirMap;
 use crate::driver::{Phase};
+use crate::transform::funcs::self_pointer_uses;
 use crate::RefactorCtxt;
 use crate::type_map;
 use c2rust_ast_builder::{mk, IntoSymbol};
@@ -47,6 +51,11 @@ pub fn register_commands(reg: &mut Registry) {
             do_mark_pointers(st, cx);
         }))
     });
+
+    reg.register("ownership_upgrade_ptrs", |args| {
+        let label = args.get(0).map_or("target", |x| x).into_symbol();
+        Box::new(OwnershipUpgradePtrs { label })
+    });
 }
 
 /// # `ownership_annotate` Command
@@ -600,3 +609,306 @@ fn do_mark_pointers(st: &CommandState, cx: &RefactorCtxt) {
         st.add_mark(ast_ty.id, label);
     });
 }
+
+
+/// # `ownership_upgrade_ptrs` Command
+///
+/// Usage: `ownership_upgrade_ptrs [MARK]`
+///
+/// Marks: reads `MARK`/`target`
+///
+/// Run ownership analysis on functions bearing `MARK` (default: `target`),
+/// then turn their raw pointer parameters and locals into references where
+/// the pointer is known to be unique and non-null.  A pointer the function
+/// writes through (`WRITE`) becomes `&mut T`, and one it only reads through
+/// (`READ`) becomes `&T`.
+///
+/// A parameter is non-null if it has the `#[nonnull]` attribute or every call
+/// passes it the address of a place, and a local is non-null if it is
+/// initialized with the address of a place.  Pointers that are reassigned,
+/// and parameters of functions that are `#[no_mangle]` or used other than by
+/// calling them, are left alone.
+///
+/// Calls pass `&mut x` instead of `&mut x as *mut T`, and reborrow any other
+/// pointer with `&mut *p`.  In the body, `*p` is kept and other uses of the
+/// pointer are cast back to a raw pointer.  Finally, `unsafe` blocks in the
+/// marked functions that are no longer needed become ordinary blocks.
+/// See `analysis/ownership/README.md` for details on ownership inference.
+///
+/// Example:
+///
+/// ```ignore
+///     unsafe fn add(acc: *mut i32, x: *const i32) {  // add: target
+///         *acc += *x;
+///     }
+///
+///     add(&mut total as *mut i32, &n as *const i32);
+/// ```
+///
+/// After running `ownership_upgrade_ptrs`:
+///
+/// ```ignore
+///     unsafe fn add(acc: &mut i32, x: &i32) {
+///         *acc += *x;
+///     }
+///
+///     add(&mut total, &n);
+/// ```
+pub struct OwnershipUpgradePtrs {
+    label: Symbol,
+}
+
+impl Command for OwnershipUpgradePtrs {
+    fn run(&mut self, state: &mut RefactorState) {
+        let label = self.label;
+        state.transform_crate(Phase::Phase3, |st, cx| do_upgrade_ptrs(st, cx, label))
+            .expect("Failed to run compiler");
+        // Typecheck the upgraded crate to find the `unsafe` blocks it no longer needs
+        state.transform_crate(Phase::Phase3, |st, cx| remove_unused_unsafe(st, cx, label))
+            .expect("Failed to run compiler");
+    }
+}
+
+/// A pointer variable being upgraded to a reference
+struct UpgradedPtr {
+    /// The mutability of the reference
+    mutbl: Mutability,
+    /// The mutability of the original pointer
+    ptr_mutbl: Mutability,
+    /// The type the pointer points to
+    pointee: P<Ty>,
+    /// The path expressions referring to the variable
+    uses: HashSet<NodeId>,
+}
+
+fn do_upgrade_ptrs(st: &CommandState, cx: &RefactorCtxt, label: Symbol) {
+    let arena = SyncDroplessArena::default();
+    let ana = ownership::analyze(&st, &cx, &arena);
+    let s_nonnull = "nonnull".into_symbol();
+
+    // (1) Find the pointer parameters and locals of the marked functions that are unique, by the
+    // ownership analysis.  Pointers are identified by the node ID of their binding.
+    let mut ptrs: HashMap<NodeId, UpgradedPtr> = HashMap::new();
+    // For each function, its pointer parameters: index, binding, and whether it's `#[nonnull]`
+    let mut params: HashMap<DefId, Vec<(usize, NodeId, bool)>> = HashMap::new();
+    let mut all_params = HashSet::new();
+    {
+        let krate = st.krate();
+        visit_nodes(&*krate, |i: &Item| {
+            if !st.marked(i.id, label) || attr::contains_name(&i.attrs, sym::no_mangle) {
+                return;
+            }
+            let (sig, body) = match_or!([i.kind] ItemKind::Fn(ref sig, _, ref body) =>
+                                        (sig, body); return);
+            let def_id = cx.node_def_id(i.id);
+            let fr = match_or!([ana.funcs.get(&def_id)] Some(x) => x; return);
+            // Only monomorphic functions have a single permission for each pointer
+            if fr.variants.is_some() || fr.num_monos != 1 {
+                return;
+            }
+            let mr = &ana.monos[&(def_id, 0)];
+
+            for (idx, arg) in sig.decl.inputs.iter().enumerate() {
+                let perm = fr.sig.inputs.get(idx)
+                    .and_then(|lty| lty.label)
+                    .map(|v| mr.assign[v]);
+                if let Some(ptr) = upgraded_ptr(cx, &arg.pat, &arg.ty, perm, body) {
+                    let nonnull = attr::contains_name(&arg.attrs, s_nonnull);
+                    params.entry(def_id).or_insert_with(Vec::new).push((idx, arg.pat.id, nonnull));
+                    all_params.insert(arg.pat.id);
+                    ptrs.insert(arg.pat.id, ptr);
+                }
+            }
+
+            visit_nodes(&**body, |l: &Local| {
+                let ty = match_or!([l.ty] Some(ref x) => x; return);
+                // Locals must start out pointing to a place
+                if l.init.as_ref().and_then(|init| addr_of_place(cx, init)).is_none() {
+                    return;
+                }
+                let perm = fr.locals.get(&l.pat.span)
+                    .and_then(|lty| lty.label)
+                    .and_then(|v| fr.local_assign.get(v).copied());
+                if let Some(ptr) = upgraded_ptr(cx, &l.pat, ty, perm, body) {
+                    ptrs.insert(l.pat.id, ptr);
+                }
+            });
+        });
+
+        // (2) Keep the signatures of functions used other than by calling them, and the
+        // parameters that some call may pass a null pointer to.
+        let mut callees = HashSet::new();
+        visit_nodes(&*krate, |e: &Expr| {
+            if let ExprKind::Call(ref func, _) = e.kind {
+                callees.insert(func.id);
+            }
+        });
+        visit_nodes(&*krate, |e: &Expr| {
+            if !matches!([e.kind] ExprKind::Path(..)) || callees.contains(&e.id) {
+                return;
+            }
+            if let Some(def_id) = cx.try_resolve_expr(e) {
+                params.remove(&def_id);
+            }
+        });
+        visit_nodes(&*krate, |e: &Expr| {
+            let (func, args) = match_or!([e.kind] ExprKind::Call(ref func, ref args) =>
+                                         (func, args); return);
+            let fn_params = match_or!([cx.try_resolve_expr(func).and_then(|id| params.get_mut(&id))]
+                                      Some(x) => x; return);
+            fn_params.retain(|&(idx, _, nonnull)| {
+                nonnull || args.get(idx).and_then(|arg| addr_of_place(cx, arg)).is_some()
+            });
+        });
+    }
+    let kept_params = params.values()
+        .flat_map(|fn_params| fn_params.iter().map(|&(_, id, _)| id))
+        .collect::<HashSet<_>>();
+    ptrs.retain(|id, _| !all_params.contains(id) || kept_params.contains(id));
+
+    st.map_krate(|krate| {
+        // (3) Pass references to the upgraded parameters
+        MutVisitNodes::visit(krate, |e: &mut P<Expr>| {
+            let callee = match_or!([e.kind] ExprKind::Call(ref func, _) =>
+                                   cx.try_resolve_expr(func); return);
+            let fn_params = match_or!([callee.and_then(|id| params.get(&id))] Some(x) => x; return);
+            let args = match_or!([e.kind] ExprKind::Call(_, ref mut args) => args; return);
+            for &(idx, id, _) in fn_params {
+                if let Some(arg) = args.get_mut(idx) {
+                    *arg = reborrow(cx, arg, ptrs[&id].mutbl);
+                }
+            }
+        });
+
+        // (4) Retype the upgraded parameters and locals
+        mut_visit_fns(krate, |fl| {
+            for arg in &mut fl.decl.inputs {
+                if let Some(ptr) = ptrs.get(&arg.pat.id) {
+                    arg.ty = mk().set_mutbl(ptr.mutbl).ref_ty(&ptr.pointee);
+                }
+            }
+        });
+        MutVisitNodes::visit(krate, |l: &mut P<Local>| {
+            let ptr = match_or!([ptrs.get(&l.pat.id)] Some(x) => x; return);
+            let place = match_or!([l.init.as_ref().and_then(|init| addr_of_place(cx, init))]
+                                  Some(x) => x.clone(); return);
+            l.ty = Some(mk().set_mutbl(ptr.mutbl).ref_ty(&ptr.pointee));
+            l.init = Some(mk().set_mutbl(ptr.mutbl).addr_of_expr(place));
+        });
+
+        // (5) Turn the uses of the upgraded pointers other than `*p`, including those the
+        // rewritten calls don't reborrow, back into raw pointers
+        let mut derefs = HashSet::new();
+        visit_nodes(&*krate, |e: &Expr| {
+            if let ExprKind::Unary(UnOp::Deref, ref p) = e.kind {
+                derefs.insert(p.id);
+            }
+        });
+        let uses = ptrs.values()
+            .flat_map(|ptr| ptr.uses.iter().map(move |&id| (id, ptr)))
+            .filter(|(id, _)| !derefs.contains(id))
+            .collect::<HashMap<_, _>>();
+        MutVisitNodes::visit(krate, |e: &mut P<Expr>| {
+            let ptr = match_or!([uses.get(&e.id)] Some(&x) => x; return);
+            let raw = match ptr.mutbl {
+                Mutability::Mutable => mk().mutbl().addr_of_expr(mk().unary_expr("*", e.clone())),
+                Mutability::Immutable => e.clone(),
+            };
+            let raw = mk().cast_expr(raw, mk().set_mutbl(ptr.mutbl).ptr_ty(&ptr.pointee));
+            *e = match (ptr.mutbl, ptr.ptr_mutbl) {
+                (Mutability::Immutable, Mutability::Mutable) =>
+                    mk().cast_expr(raw, mk().mutbl().ptr_ty(&ptr.pointee)),
+                _ => raw,
+            };
+        });
+    });
+}
+
+/// Check whether the pointer variable `pat: ty`, with the permission `perm` from the ownership
+/// analysis, can be upgraded to a reference, and find its uses in `body`
+fn upgraded_ptr(cx: &RefactorCtxt,
+                pat: &Pat,
+                ty: &Ty,
+                perm: Option<ConcretePerm>,
+                body: &Block) -> Option<UpgradedPtr> {
+    if !matches!([pat.kind] PatKind::Ident(BindingMode::ByValue(_), _, None)) {
+        return None;
+    }
+    let (pointee, ptr_mutbl) = match_or!([ty.kind] TyKind::Ptr(ref mt) =>
+                                         (&mt.ty, mt.mutbl); return None);
+    let mutbl = match perm? {
+        ConcretePerm::Read => Mutability::Immutable,
+        ConcretePerm::Write if ptr_mutbl == Mutability::Mutable => Mutability::Mutable,
+        _ => return None,
+    };
+    // Pointers that are reassigned may not stay unique
+    let (uses, _) = self_pointer_uses(cx, body, cx.hir_map().node_to_hir_id(pat.id))?;
+    Some(UpgradedPtr {
+        mutbl,
+        ptr_mutbl,
+        pointee: pointee.clone(),
+        uses,
+    })
+}
+
+/// The place `e` takes the address of, if it is `&x` or `&mut x`, possibly cast to a pointer to
+/// the type of `x`
+fn addr_of_place<'a>(cx: &RefactorCtxt, e: &'a Expr) -> Option<&'a P<Expr>> {
+    let mut inner = e;
+    while let ExprKind::Cast(ref x, _) | ExprKind::Paren(ref x) = inner.kind {
+        inner = x;
+    }
+    let place = match_or!([inner.kind] ExprKind::AddrOf(_, _, ref x) => x; return None);
+    let place_ty = cx.opt_node_type(place.id)?;
+    match cx.opt_node_type(e.id)?.kind {
+        ty::TyKind::RawPtr(mt) if mt.ty == place_ty => Some(place),
+        ty::TyKind::Ref(_, pointee, _) if pointee == place_ty => Some(place),
+        _ => None,
+    }
+}
+
+/// The argument passed for a parameter upgraded to a reference of mutability `mutbl` instead of
+/// the pointer `arg`
+fn reborrow(cx: &RefactorCtxt, arg: &P<Expr>, mutbl: Mutability) -> P<Expr> {
+    let mut inner = &**arg;
+    while let ExprKind::Cast(ref x, _) | ExprKind::Paren(ref x) = inner.kind {
+        inner = x;
+    }
+    match inner.kind {
+        ExprKind::AddrOf(_, addr_mutbl, _)
+            if addr_mutbl == mutbl || mutbl == Mutability::Immutable =>
+        {
+            if let Some(place) = addr_of_place(cx, arg) {
+                return mk().set_mutbl(mutbl).addr_of_expr(place.clone());
+            }
+        }
+        _ => {}
+    }
+    mk().set_mutbl(mutbl).addr_of_expr(mk().unary_expr("*", arg.clone()))
+}
+
+/// Turn the `unsafe` blocks of functions bearing `label` that are no longer needed into
+/// ordinary blocks
+fn remove_unused_unsafe(st: &CommandState, cx: &RefactorCtxt, label: Symbol) {
+    let mut fns = HashSet::new();
+    visit_nodes(&*st.krate(), |i: &Item| {
+        if st.marked(i.id, label) && matches!([i.kind] ItemKind::Fn(..)) {
+            fns.insert(cx.node_def_id(i.id));
+        }
+    });
+
+    MutVisitNodes::visit(&mut *st.krate_mut(), |b: &mut P<Block>| {
+        if !matches!([b.rules] BlockCheckMode::Unsafe(UnsafeSource::UserProvided)) {
+            return;
+        }
+        let hir_id = cx.hir_map().node_to_hir_id(b.id);
+        let parent = cx.hir_map().get_parent_did(hir_id);
+        if !fns.contains(&parent) {
+            return;
+        }
+        let result = cx.ty_ctxt().unsafety_check_result(parent);
+        if result.unsafe_blocks.iter().any(|&(id, used)| id == hir_id && !used) {
+            b.rules = BlockCheckMode::Default;
+        }
+    });
+}
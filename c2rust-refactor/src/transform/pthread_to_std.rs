@@ -0,0 +1,559 @@
+use std::collections::{HashMap, HashSet};
+use rustc::hir::HirId;
+use syntax::ast::*;
+use syntax::print::pprust;
+use syntax::ptr::P;
+use syntax_pos::sym;
+
+use crate::ast_manip::{MutVisitNodes, visit_nodes};
+use crate::ast_manip::number_nodes::number_nodes_with;
+use crate::command::{CommandState, Registry};
+use crate::driver::{parse_stmts, Phase};
+use crate::transform::Transform;
+use crate::RefactorCtxt;
+
+
+/// The pthread objects `pthread_to_std` replaces
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Kind {
+    Mutex,
+    Cond,
+    Once,
+    Thread,
+}
+
+impl Kind {
+    /// The kind of object a C type holds
+    fn from_ty(ty: &Ty) -> Option<Kind> {
+        let path = match ty.kind {
+            TyKind::Path(None, ref path) => path,
+            _ => return None,
+        };
+        match &*path.segments.last()?.ident.as_str() {
+            "pthread_mutex_t" => Some(Kind::Mutex),
+            "pthread_cond_t" => Some(Kind::Cond),
+            "pthread_once_t" => Some(Kind::Once),
+            "pthread_t" => Some(Kind::Thread),
+            _ => None,
+        }
+    }
+
+    /// The `std` type replacing a static of this kind, and its initializer
+    fn std_static(self) -> Option<(&'static str, &'static str)> {
+        match self {
+            Kind::Mutex => Some(("::std::sync::Mutex<()>", "::std::sync::Mutex::new(())")),
+            Kind::Cond => Some(("::std::sync::Condvar", "::std::sync::Condvar::new()")),
+            Kind::Once => Some(("::std::sync::Once", "::std::sync::Once::new()")),
+            Kind::Thread => None,
+        }
+    }
+}
+
+/// A mutex, condition variable, once or thread handle that could be replaced
+struct Object {
+    kind: Kind,
+    name: Ident,
+    /// The expressions referring to the object
+    uses: Vec<NodeId>,
+}
+
+/// The replacement for one statement using pthread objects
+struct Rewrite {
+    /// The objects that must all be replaced for the rewrite to apply
+    deps: Vec<HirId>,
+    /// The uses of the objects the rewrite takes care of
+    uses: Vec<NodeId>,
+    /// The replacement statements
+    repl: String,
+}
+
+/// # `pthread_to_std` Command
+///
+/// Usage: `pthread_to_std`
+///
+/// Marks: `target`
+///
+/// In functions marked `target`, replace pthread mutexes, condition variables,
+/// `pthread_once` and threads with their `std` equivalents:
+///
+///  * A `static mut` `pthread_mutex_t` becomes a `static` `Mutex<()>`.  Each
+///    `pthread_mutex_lock` must be followed by a `pthread_mutex_unlock` later in
+///    the same block; the pair becomes a guard that is dropped at the unlock.
+///    Unlocks right before a `return`, `break` or `continue` in between drop the
+///    guard early.
+///  * A `static mut` `pthread_cond_t` becomes a `static` `Condvar`.  Waits must
+///    happen while its mutex is locked as described above, and always with the
+///    same mutex.  Signals and broadcasts become `notify_one` and `notify_all`.
+///  * A `static mut` `pthread_once_t` becomes a `static` `Once`, and
+///    `pthread_once` becomes `call_once`.
+///  * A local `pthread_t` becomes an `Option<JoinHandle<()>>`.  It must be
+///    started by `pthread_create` with no attributes and joined by
+///    `pthread_join` without reading the thread's result.  The argument of the
+///    thread is sent to it as a `usize`.
+///
+/// Calls whose results are used, mutexes or condition variables that are
+/// initialized with attributes, and objects used any other way, including in
+/// functions that are not marked, keep their FFI translation, along with the
+/// objects they are used with.  The mutex protects no data: moving the data it
+/// protects into it is left to the user.
+///
+/// Example:
+///
+/// ```ignore
+///     static mut lock: pthread_mutex_t = ...;
+///
+///     unsafe fn bump() {  // bump: target
+///         pthread_mutex_lock(&mut lock);
+///         COUNT += 1;
+///         pthread_mutex_unlock(&mut lock);
+///     }
+/// ```
+///
+/// After running `pthread_to_std`:
+///
+/// ```ignore
+///     static lock: ::std::sync::Mutex<()> = ::std::sync::Mutex::new(());
+///
+///     unsafe fn bump() {
+///         let mut lock_guard = lock.lock().unwrap();
+///         COUNT += 1;
+///         drop(lock_guard);
+///     }
+/// ```
+pub struct PthreadToStd;
+
+impl Transform for PthreadToStd {
+    fn transform(&self, krate: &mut Crate, st: &CommandState, cx: &RefactorCtxt) {
+        // (1) Find the statics and the locals in marked functions holding pthread objects.
+
+        let mut objects: HashMap<HirId, Object> = HashMap::new();
+        let mut local_stmts: HashMap<HirId, NodeId> = HashMap::new();
+        visit_nodes(krate, |i: &Item| {
+            if let ItemKind::Static(ref ty, Mutability::Mutable, _) = i.kind {
+                let is_exported = i.vis.node.is_pub() ||
+                    i.attrs.iter().any(|attr| attr.check_name(sym::no_mangle));
+                match Kind::from_ty(ty) {
+                    Some(Kind::Thread) | None => {}
+                    Some(_) if is_exported => {}
+                    Some(kind) => {
+                        let hir_id = cx.hir_map().node_to_hir_id(i.id);
+                        objects.insert(hir_id, Object { kind, name: i.ident, uses: vec![] });
+                    }
+                }
+            }
+            if !st.marked(i.id, "target") {
+                return;
+            }
+            visit_nodes(i, |s: &Stmt| {
+                if let StmtKind::Local(ref l) = s.kind {
+                    let ident = match l.pat.kind {
+                        PatKind::Ident(BindingMode::ByValue(_), ident, None) => ident,
+                        _ => return,
+                    };
+                    if l.ty.as_ref().and_then(|ty| Kind::from_ty(ty)) == Some(Kind::Thread) {
+                        let hir_id = cx.hir_map().node_to_hir_id(l.pat.id);
+                        objects.insert(hir_id, Object {
+                            kind: Kind::Thread,
+                            name: ident,
+                            uses: vec![],
+                        });
+                        local_stmts.insert(hir_id, s.id);
+                    }
+                }
+            });
+        });
+
+        visit_nodes(krate, |e: &Expr| {
+            if let ExprKind::Path(..) = e.kind {
+                if let Some(obj) = cx.try_resolve_expr_to_hid(e)
+                    .and_then(|id| objects.get_mut(&id)) {
+                    obj.uses.push(e.id);
+                }
+            }
+        });
+
+        // (2) Plan the rewrite of each statement using the objects in the marked functions.
+
+        let mut rewrites: HashMap<NodeId, Rewrite> = HashMap::new();
+        for (&id, &stmt_id) in &local_stmts {
+            let name = objects[&id].name;
+            rewrites.insert(stmt_id, Rewrite {
+                deps: vec![id],
+                uses: vec![],
+                repl: format!("let mut {}: Option<::std::thread::JoinHandle<()>> = None;", name),
+            });
+        }
+        // The mutex each condition variable is waited on with
+        let mut cond_mutexes: HashMap<HirId, HashSet<HirId>> = HashMap::new();
+        let planner = Planner { cx, objects: &objects };
+        visit_nodes(krate, |i: &Item| {
+            if !st.marked(i.id, "target") {
+                return;
+            }
+            if let ItemKind::Fn(..) = i.kind {
+                visit_nodes(i, |b: &Block| {
+                    planner.plan_block(b, &mut rewrites, &mut cond_mutexes);
+                });
+            }
+        });
+
+        // (3) Keep the objects whose every use is rewritten, by rewrites whose objects are all
+        // kept.
+
+        let mut kept: HashSet<HirId> = objects.keys().cloned().collect();
+        for (cond, mutexes) in &cond_mutexes {
+            if mutexes.len() > 1 {
+                kept.remove(cond);
+            }
+        }
+        loop {
+            let handled = rewrites.values()
+                .filter(|r| r.deps.iter().all(|id| kept.contains(id)))
+                .flat_map(|r| r.uses.iter().cloned())
+                .collect::<HashSet<_>>();
+            let before = kept.len();
+            kept.retain(|id| objects[id].uses.iter().all(|use_id| handled.contains(use_id)));
+            if kept.len() == before {
+                break;
+            }
+        }
+
+        // (4) Rewrite the statements and the statics.
+
+        MutVisitNodes::visit(krate, |b: &mut P<Block>| {
+            if !b.stmts.iter().any(|s| rewrites.contains_key(&s.id)) {
+                return;
+            }
+            let stmts = std::mem::replace(&mut b.stmts, vec![]);
+            for s in stmts {
+                match rewrites.get(&s.id) {
+                    Some(r) if r.deps.iter().all(|id| kept.contains(id)) => {
+                        for mut new_stmt in parse_stmts(cx.session(), &r.repl) {
+                            number_nodes_with(&mut new_stmt, st.node_id_counter());
+                            b.stmts.push(new_stmt);
+                        }
+                    }
+                    _ => b.stmts.push(s),
+                }
+            }
+        });
+
+        MutVisitNodes::visit(krate, |i: &mut P<Item>| {
+            let hir_id = match cx.hir_map().opt_local_def_id_from_node_id(i.id) {
+                Some(_) => cx.hir_map().node_to_hir_id(i.id),
+                None => return,
+            };
+            if !kept.contains(&hir_id) {
+                return;
+            }
+            let (ty, init) = match objects[&hir_id].kind.std_static() {
+                Some(x) => x,
+                None => return,
+            };
+            let mut new_item = st.parse_items(cx, &format!(
+                "static {}: {} = {};", i.ident, ty, init)).remove(0);
+            new_item.vis = i.vis.clone();
+            new_item.attrs = i.attrs.clone();
+            *i = new_item;
+        });
+    }
+
+    fn min_phase(&self) -> Phase {
+        Phase::Phase3
+    }
+}
+
+
+/// Finds the statements using pthread objects that can be rewritten
+struct Planner<'a, 'tcx> {
+    cx: &'a RefactorCtxt<'a, 'tcx>,
+    objects: &'a HashMap<HirId, Object>,
+}
+
+impl<'a, 'tcx> Planner<'a, 'tcx> {
+    /// Plan the rewrites of the statements of `b`
+    fn plan_block(&self,
+                  b: &Block,
+                  rewrites: &mut HashMap<NodeId, Rewrite>,
+                  cond_mutexes: &mut HashMap<HirId, HashSet<HirId>>) {
+        for (idx, s) in b.stmts.iter().enumerate() {
+            let (name, args) = match self.pthread_call(s) {
+                Some(call) => call,
+                None => continue,
+            };
+            let rewrite = match (&*name, args) {
+                ("pthread_mutex_lock", [m]) => {
+                    let (m, m_use) = match self.object_ref(m, Kind::Mutex, true) {
+                        Some(m) => m,
+                        None => continue,
+                    };
+                    let guard = self.guard_name(m);
+                    let end = b.stmts[idx + 1..].iter().position(|s| {
+                        match self.pthread_call(s) {
+                            Some((ref name, [arg])) if name == "pthread_mutex_unlock" =>
+                                self.object_ref(arg, Kind::Mutex, true).map(|r| r.0) == Some(m),
+                            _ => false,
+                        }
+                    });
+                    let end = match end {
+                        Some(end) => idx + 1 + end,
+                        None => continue,
+                    };
+                    let unlock_use = self.call_uses(&b.stmts[end]);
+                    rewrites.insert(b.stmts[end].id, Rewrite {
+                        deps: vec![m],
+                        uses: unlock_use,
+                        repl: format!("drop({});", guard),
+                    });
+                    for s in &b.stmts[idx + 1..end] {
+                        visit_nodes(s, |inner: &Block| {
+                            self.plan_locked_block(inner, m, &guard, rewrites, cond_mutexes);
+                        });
+                    }
+                    Rewrite {
+                        deps: vec![m],
+                        uses: vec![m_use],
+                        repl: format!("let mut {} = {}.lock().unwrap();",
+                                      guard, self.objects[&m].name),
+                    }
+                }
+                ("pthread_mutex_init", [m, attr]) |
+                ("pthread_cond_init", [m, attr]) if is_null(attr) => {
+                    let kind = if name == "pthread_mutex_init" { Kind::Mutex } else { Kind::Cond };
+                    let (m, m_use) = match self.object_ref(m, kind, true) {
+                        Some(m) => m,
+                        None => continue,
+                    };
+                    Rewrite { deps: vec![m], uses: vec![m_use], repl: String::new() }
+                }
+                ("pthread_mutex_destroy", [m]) |
+                ("pthread_cond_destroy", [m]) => {
+                    let kind = if name == "pthread_mutex_destroy" { Kind::Mutex } else { Kind::Cond };
+                    let (m, m_use) = match self.object_ref(m, kind, true) {
+                        Some(m) => m,
+                        None => continue,
+                    };
+                    Rewrite { deps: vec![m], uses: vec![m_use], repl: String::new() }
+                }
+                ("pthread_cond_signal", [c]) |
+                ("pthread_cond_broadcast", [c]) => {
+                    let (c, c_use) = match self.object_ref(c, Kind::Cond, true) {
+                        Some(c) => c,
+                        None => continue,
+                    };
+                    let method = if name == "pthread_cond_signal" { "notify_one" } else { "notify_all" };
+                    Rewrite {
+                        deps: vec![c],
+                        uses: vec![c_use],
+                        repl: format!("{}.{}();", self.objects[&c].name, method),
+                    }
+                }
+                ("pthread_once", [o, f]) => {
+                    let (o, o_use) = match self.object_ref(o, Kind::Once, true) {
+                        Some(o) => o,
+                        None => continue,
+                    };
+                    let f = match some_arg(f) {
+                        Some(f) => f,
+                        None => continue,
+                    };
+                    Rewrite {
+                        deps: vec![o],
+                        uses: vec![o_use],
+                        repl: format!("{}.call_once(|| {}());",
+                                      self.objects[&o].name, pprust::expr_to_string(f)),
+                    }
+                }
+                ("pthread_create", [t, attr, f, arg]) if is_null(attr) => {
+                    let (t, t_use) = match self.object_ref(t, Kind::Thread, true) {
+                        Some(t) => t,
+                        None => continue,
+                    };
+                    let f = match some_arg(f) {
+                        Some(f) => f,
+                        None => continue,
+                    };
+                    Rewrite {
+                        deps: vec![t],
+                        uses: vec![t_use],
+                        repl: format!(
+                            "{} = Some({{ let arg = ({}) as usize; \
+                             ::std::thread::spawn(move || {{ {}(arg as *mut _); }}) }});",
+                            self.objects[&t].name,
+                            pprust::expr_to_string(arg),
+                            pprust::expr_to_string(f)),
+                    }
+                }
+                ("pthread_join", [t, ret]) if is_null(ret) => {
+                    let (t, t_use) = match self.object_ref(t, Kind::Thread, false) {
+                        Some(t) => t,
+                        None => continue,
+                    };
+                    Rewrite {
+                        deps: vec![t],
+                        uses: vec![t_use],
+                        repl: format!("{}.take().unwrap().join().unwrap();",
+                                      self.objects[&t].name),
+                    }
+                }
+                _ => continue,
+            };
+            rewrites.entry(s.id).or_insert(rewrite);
+        }
+    }
+
+    /// Plan the rewrites of the statements of a block nested in the region where `m` is locked,
+    /// which can wait on a condition variable or unlock `m` before leaving the block.
+    fn plan_locked_block(&self,
+                         b: &Block,
+                         m: HirId,
+                         guard: &str,
+                         rewrites: &mut HashMap<NodeId, Rewrite>,
+                         cond_mutexes: &mut HashMap<HirId, HashSet<HirId>>) {
+        for (idx, s) in b.stmts.iter().enumerate() {
+            let (name, args) = match self.pthread_call(s) {
+                Some(call) => call,
+                None => continue,
+            };
+            let rewrite = match (&*name, args) {
+                ("pthread_mutex_unlock", [arg]) if b.stmts.get(idx + 1).map_or(false, leaves) => {
+                    match self.object_ref(arg, Kind::Mutex, true) {
+                        Some((m2, m_use)) if m2 == m => Rewrite {
+                            deps: vec![m],
+                            uses: vec![m_use],
+                            repl: format!("drop({});", guard),
+                        },
+                        _ => continue,
+                    }
+                }
+                ("pthread_cond_wait", [c, arg]) => {
+                    let (c, c_use) = match self.object_ref(c, Kind::Cond, true) {
+                        Some(c) => c,
+                        None => continue,
+                    };
+                    let m_use = match self.object_ref(arg, Kind::Mutex, true) {
+                        Some((m2, m_use)) if m2 == m => m_use,
+                        _ => continue,
+                    };
+                    cond_mutexes.entry(c).or_insert_with(HashSet::new).insert(m);
+                    Rewrite {
+                        deps: vec![c, m],
+                        uses: vec![c_use, m_use],
+                        repl: format!("{0} = {1}.wait({0}).unwrap();",
+                                      guard, self.objects[&c].name),
+                    }
+                }
+                _ => continue,
+            };
+            rewrites.entry(s.id).or_insert(rewrite);
+        }
+    }
+
+    /// If `s` is a statement calling a pthread function and ignoring the result, the name of the
+    /// function and the arguments
+    fn pthread_call<'s>(&self, s: &'s Stmt) -> Option<(String, &'s [P<Expr>])> {
+        let (callee, args) = match s.kind {
+            StmtKind::Semi(ref e) => match e.kind {
+                ExprKind::Call(ref callee, ref args) => (callee, args),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let tcx = self.cx.ty_ctxt();
+        let def_id = self.cx.try_resolve_expr(callee)?;
+        if !tcx.is_foreign_item(def_id) {
+            return None;
+        }
+        let name = tcx.item_name(def_id).as_str().to_string();
+        if name.starts_with("pthread_") {
+            Some((name, &args[..]))
+        } else {
+            None
+        }
+    }
+
+    /// The uses of pthread objects in the arguments of a call statement
+    fn call_uses(&self, s: &Stmt) -> Vec<NodeId> {
+        match self.pthread_call(s) {
+            Some((_, args)) => args.iter()
+                .filter_map(|arg| self.object_ref(arg, Kind::Mutex, true))
+                .map(|(_, use_id)| use_id)
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// If `e` refers to an object of the given kind, by `&mut` if `by_ref`, the object and the
+    /// use
+    fn object_ref(&self, e: &Expr, kind: Kind, by_ref: bool) -> Option<(HirId, NodeId)> {
+        let e = match (by_ref, &e.kind) {
+            (true, ExprKind::AddrOf(_, Mutability::Mutable, ref e)) => e,
+            (true, _) => return None,
+            (false, _) => e,
+        };
+        match e.kind {
+            ExprKind::Path(..) => {}
+            _ => return None,
+        }
+        let id = self.cx.try_resolve_expr_to_hid(e)?;
+        match self.objects.get(&id) {
+            Some(obj) if obj.kind == kind => Some((id, e.id)),
+            _ => None,
+        }
+    }
+
+    /// The name of the guard of the mutex `m`
+    fn guard_name(&self, m: HirId) -> String {
+        format!("{}_guard", self.objects[&m].name.as_str().to_lowercase())
+    }
+}
+
+/// Is `e` a null pointer, as translated by the transpiler?
+fn is_null(e: &Expr) -> bool {
+    match e.kind {
+        ExprKind::Cast(ref e, _) | ExprKind::Paren(ref e) => is_null(e),
+        ExprKind::Lit(ref lit) => match lit.kind {
+            LitKind::Int(0, _) => true,
+            _ => false,
+        },
+        ExprKind::Call(ref f, ref args) if args.is_empty() => match f.kind {
+            ExprKind::Path(None, ref path) => path.segments.last().map_or(false, |seg| {
+                seg.ident.as_str() == "null" || seg.ident.as_str() == "null_mut"
+            }),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// The function in a function pointer argument `Some(f)`
+fn some_arg(e: &Expr) -> Option<&Expr> {
+    match e.kind {
+        ExprKind::Call(ref f, ref args) if args.len() == 1 => match f.kind {
+            ExprKind::Path(None, ref path)
+                if path.segments.last().map_or(false, |seg| seg.ident.as_str() == "Some") => {
+                Some(&args[0])
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Does the statement `s` leave the block?
+fn leaves(s: &Stmt) -> bool {
+    match s.kind {
+        StmtKind::Semi(ref e) | StmtKind::Expr(ref e) => match e.kind {
+            ExprKind::Ret(..) | ExprKind::Break(..) | ExprKind::Continue(..) => true,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+
+pub fn register_commands(reg: &mut Registry) {
+    use super::mk;
+
+    reg.register("pthread_to_std", |_args| mk(PthreadToStd));
+}
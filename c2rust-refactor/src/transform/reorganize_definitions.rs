@@ -1136,15 +1136,16 @@ impl From<&Attribute> for SrcLoc {
             .value_str()
             .expect("Expected a value for src_loc attribute")
             .as_str();
-        let mut iter = value_str.split(':');
-        let line: usize = iter
-            .next()
-            .and_then(|x| x.parse().ok())
-            .expect("Expected a line number in src_loc attribute");
+        // Either "line:col" or, with `--emit-src-locs`, "file:line:col"
+        let mut iter = value_str.rsplit(':');
         let col: usize = iter
             .next()
             .and_then(|x| x.parse().ok())
             .expect("Expected an column number in src_loc attribute");
+        let line: usize = iter
+            .next()
+            .and_then(|x| x.parse().ok())
+            .expect("Expected a line number in src_loc attribute");
         Self { line, col }
     }
 }
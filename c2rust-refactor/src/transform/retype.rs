@@ -4,18 +4,19 @@ use rustc::hir;
 use rustc::hir::def_id::DefId;
 use rustc::ty::{self, TyKind, TyCtxt, ParamEnv};
 use syntax::ast::*;
+use syntax::attr;
 use syntax::mut_visit::{self, MutVisitor};
 use rustc_errors::PResult;
 use rustc_parse::parser::Parser;
 use syntax::token::{TokenKind, BinOpToken};
 use syntax::print::pprust;
 use syntax::ptr::P;
-use syntax_pos::Span;
+use syntax_pos::{sym, Span};
 use smallvec::{smallvec, SmallVec};
 
 use c2rust_ast_builder::{mk, IntoSymbol};
-use crate::ast_manip::{FlatMapNodes, MutVisit, MutVisitNodes, fold_output_exprs};
-use crate::ast_manip::fn_edit::{mut_visit_fns, visit_fns};
+use crate::ast_manip::{FlatMapNodes, MutVisit, MutVisitNodes, fold_output_exprs, visit_nodes};
+use crate::ast_manip::fn_edit::{mut_visit_fns, visit_fns, FnKind};
 use crate::ast_manip::lr_expr::{self, fold_expr_with_context, fold_exprs_with_context};
 use crate::command::{Command, CommandState, RefactorState, Registry, TypeckLoopResult};
 use crate::driver::{self, Phase, parse_ty, parse_expr};
@@ -190,6 +191,372 @@ impl Transform for RetypeReturn {
 }
 
 
+/// # `retcode_to_result` Command
+///
+/// Usage: `retcode_to_result CONVENTION`
+///
+/// Marks: `target`
+///
+/// For each function marked `target`, which returns an error code following
+/// `CONVENTION`, change its return type to a `Result`.  `CONVENTION` is one of:
+///
+///  * `negative`: negative values are error codes, and the function returns
+///    `Result<T, T>`
+///  * `-1`: `-1` is the error, and the function returns `Result<T, ()>`
+///  * `nonzero`: `0` is success and other values are error codes, and the
+///    function returns `Result<(), T>`
+///  * `null`: a null pointer is the error, and the function returns
+///    `Result<*mut U, ()>`
+///
+/// Literal return values become `Ok(..)` or `Err(..)`, and other return values
+/// are checked with a `match`.  In callers, checks of the result like `f() < 0`
+/// become `f().is_err()`, and `if f() < 0 { return CODE; }` in a function that
+/// now returns a `Result` becomes `f().map_err(|_| CODE)?;`, or `f()?;` for the
+/// `-1` and `null` conventions.  Other calls turn the result back into the error
+/// code with a `match`.
+///
+/// Functions that are exported with `#[no_mangle]`, used other than by calling
+/// them, or whose return type doesn't fit `CONVENTION` are left alone.
+///
+/// Example:
+///
+/// ```ignore
+///     fn digit(c: u8) -> i32 {    // digit: target
+///         if c < b'0' || c > b'9' {
+///             return -1;
+///         }
+///         0
+///     }
+///
+///     fn number(s: &[u8]) -> i32 {    // number: target
+///         if digit(s[0]) < 0 {
+///             return -2;
+///         }
+///         0
+///     }
+/// ```
+///
+/// After running `retcode_to_result negative`:
+///
+/// ```ignore
+///     fn digit(c: u8) -> Result<i32, i32> {
+///         if c < b'0' || c > b'9' {
+///             return Err(-1);
+///         }
+///         Ok(0)
+///     }
+///
+///     fn number(s: &[u8]) -> Result<i32, i32> {
+///         digit(s[0]).map_err(|_| -2)?;
+///         Ok(0)
+///     }
+/// ```
+pub struct RetcodeToResult {
+    pub convention: ErrorConvention,
+}
+
+/// How a function returning an error code signals an error
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ErrorConvention {
+    /// Negative values are error codes
+    Negative,
+    /// `-1` is the error
+    MinusOne,
+    /// `0` is success, and other values are error codes
+    Nonzero,
+    /// A null pointer is the error
+    Null,
+}
+
+impl ErrorConvention {
+    pub fn from_arg(arg: &str) -> ErrorConvention {
+        match arg {
+            "negative" => ErrorConvention::Negative,
+            "-1" => ErrorConvention::MinusOne,
+            "nonzero" => ErrorConvention::Nonzero,
+            "null" => ErrorConvention::Null,
+            _ => panic!("unknown error convention {:?} (expected negative, -1, nonzero or null)",
+                        arg),
+        }
+    }
+
+    /// The `Result` replacing the return type `ty`
+    fn result_ty(self, ty: &str) -> String {
+        match self {
+            ErrorConvention::Negative => format!("Result<{0}, {0}>", ty),
+            ErrorConvention::MinusOne | ErrorConvention::Null => format!("Result<{}, ()>", ty),
+            ErrorConvention::Nonzero => format!("Result<(), {}>", ty),
+        }
+    }
+
+    /// Whether the return value `e` is an error, if it is a literal
+    fn is_err(self, e: &Expr) -> Option<bool> {
+        if self == ErrorConvention::Null {
+            return match strip_casts(e).kind {
+                ExprKind::Lit(ref lit) => Some(matches!([lit.kind] LitKind::Int(0, _))),
+                ExprKind::Call(ref func, ref args) if args.is_empty() => {
+                    let name = match_or!([func.kind] ExprKind::Path(_, ref path) =>
+                                         path.segments.last()?.ident.as_str(); return None);
+                    Some(name == "null" || name == "null_mut")
+                }
+                ExprKind::AddrOf(..) => Some(false),
+                _ => None,
+            };
+        }
+
+        let code = int_literal(e)?;
+        Some(match self {
+            ErrorConvention::Negative => code < 0,
+            ErrorConvention::MinusOne => code == -1,
+            ErrorConvention::Nonzero => code != 0,
+            ErrorConvention::Null => unreachable!(),
+        })
+    }
+
+    /// The templates turning the return value `__old` into the result: for a successful value,
+    /// an error, and a value that could be either
+    fn wrap_templates(self) -> [&'static str; 3] {
+        match self {
+            ErrorConvention::Negative => [
+                "Ok(__old)",
+                "Err(__old)",
+                "match __old { ret if ret < 0 => Err(ret), ret => Ok(ret) }",
+            ],
+            ErrorConvention::MinusOne => [
+                "Ok(__old)",
+                "Err(())",
+                "match __old { -1 => Err(()), ret => Ok(ret) }",
+            ],
+            ErrorConvention::Nonzero => [
+                "Ok(())",
+                "Err(__old)",
+                "match __old { 0 => Ok(()), code => Err(code) }",
+            ],
+            ErrorConvention::Null => [
+                "Ok(__old)",
+                "Err(())",
+                "match __old { ret if ret.is_null() => Err(()), ret => Ok(ret) }",
+            ],
+        }
+    }
+
+    /// The template turning the result `__new` back into the return value, where `null` is the
+    /// null pointer of the return type
+    fn unwrap_template(self, null: &str) -> String {
+        match self {
+            ErrorConvention::Negative =>
+                "match __new { Ok(ret) => ret, Err(code) => code }".to_owned(),
+            ErrorConvention::MinusOne =>
+                "match __new { Ok(ret) => ret, Err(()) => -1 }".to_owned(),
+            ErrorConvention::Nonzero =>
+                "match __new { Ok(()) => 0, Err(code) => code }".to_owned(),
+            ErrorConvention::Null =>
+                format!("match __new {{ Ok(ret) => ret, Err(()) => {} }}", null),
+        }
+    }
+
+    /// The call `e` checks the return value of, and whether it checks for an error or for
+    /// success
+    fn check(self, e: &Expr) -> Option<(&P<Expr>, bool)> {
+        if self == ErrorConvention::Null {
+            return match e.kind {
+                ExprKind::MethodCall(ref seg, ref args) if seg.ident.as_str() == "is_null" =>
+                    Some((&args[0], true)),
+                _ => None,
+            };
+        }
+
+        let (op, call, code) = match_or!([e.kind] ExprKind::Binary(op, ref lhs, ref rhs) =>
+                                         (op.node, lhs, int_literal(rhs)?); return None);
+        match (self, op, code) {
+            (ErrorConvention::Negative, BinOpKind::Lt, 0) |
+            (ErrorConvention::MinusOne, BinOpKind::Eq, -1) |
+            (ErrorConvention::Nonzero, BinOpKind::Ne, 0) => Some((call, true)),
+            (ErrorConvention::Negative, BinOpKind::Ge, 0) |
+            (ErrorConvention::MinusOne, BinOpKind::Ne, -1) |
+            (ErrorConvention::Nonzero, BinOpKind::Eq, 0) => Some((call, false)),
+            _ => None,
+        }
+    }
+}
+
+fn strip_casts(mut e: &Expr) -> &Expr {
+    while let ExprKind::Cast(ref x, _) | ExprKind::Paren(ref x) = e.kind {
+        e = x;
+    }
+    e
+}
+
+/// The value of the integer literal `e`, possibly negated and cast
+fn int_literal(e: &Expr) -> Option<i128> {
+    match strip_casts(e).kind {
+        ExprKind::Lit(ref lit) => match lit.kind {
+            LitKind::Int(i, _) => Some(i as i128),
+            _ => None,
+        },
+        ExprKind::Unary(UnOp::Neg, ref x) => int_literal(x).map(|i| -i),
+        _ => None,
+    }
+}
+
+/// The check of a call for an error, and the error returned, if `s` is
+/// `if check { return Err(error); }`
+fn propagated_error(s: &Stmt) -> Option<(&P<Expr>, &P<Expr>)> {
+    let e = match_or!([s.kind] StmtKind::Expr(ref e) | StmtKind::Semi(ref e) => e; return None);
+    let (cond, then) = match_or!([e.kind] ExprKind::If(ref cond, ref then, None) =>
+                                 (cond, then); return None);
+    if then.stmts.len() != 1 {
+        return None;
+    }
+    let ret = match_or!([then.stmts[0].kind] StmtKind::Expr(ref e) | StmtKind::Semi(ref e) => e;
+                        return None);
+    let val = match_or!([ret.kind] ExprKind::Ret(Some(ref x)) => x; return None);
+    let (func, args) = match_or!([val.kind] ExprKind::Call(ref func, ref args) =>
+                                 (func, args); return None);
+    let path = match_or!([func.kind] ExprKind::Path(None, ref path) => path; return None);
+    if args.len() != 1 || path.segments.len() != 1 || path.segments[0].ident.as_str() != "Err" {
+        return None;
+    }
+    Some((cond, &args[0]))
+}
+
+impl Transform for RetcodeToResult {
+    fn transform(&self, krate: &mut Crate, st: &CommandState, cx: &RefactorCtxt) {
+        let conv = self.convention;
+
+        // (1) Find the marked functions returning error codes, and the templates turning their
+        // results back into error codes.
+        let mut callees = HashSet::new();
+        visit_nodes(krate, |e: &Expr| {
+            if let ExprKind::Call(ref func, _) = e.kind {
+                callees.insert(func.id);
+            }
+        });
+        let mut fn_uses = HashSet::new();
+        visit_nodes(krate, |e: &Expr| {
+            if matches!([e.kind] ExprKind::Path(..)) && !callees.contains(&e.id) {
+                fn_uses.extend(cx.try_resolve_expr(e));
+            }
+        });
+
+        let mut mod_fns: HashMap<DefId, (String, P<Expr>)> = HashMap::new();
+        visit_fns(krate, |fl| {
+            if !st.marked(fl.id, "target") || fl.kind != FnKind::Normal ||
+               attr::contains_name(&fl.attrs, sym::no_mangle) {
+                return;
+            }
+            let def_id = cx.node_def_id(fl.id);
+            let ty = match_or!([fl.decl.output] FunctionRetTy::Ty(ref ty) => ty; return);
+            let ret_ty = cx.ty_ctxt().fn_sig(def_id).skip_binder().output();
+            let null = match (conv, &ret_ty.kind) {
+                (ErrorConvention::Null, TyKind::RawPtr(mt)) => match mt.mutbl {
+                    hir::Mutability::Mutable => "::std::ptr::null_mut()",
+                    hir::Mutability::Immutable => "::std::ptr::null()",
+                },
+                (ErrorConvention::Null, _) => return,
+                _ if ret_ty.is_integral() => "",
+                _ => return,
+            };
+            if fn_uses.contains(&def_id) {
+                return;
+            }
+            let unwrap = parse_expr(cx.session(), &conv.unwrap_template(null));
+            mod_fns.insert(def_id, (pprust::ty_to_string(ty), unwrap));
+        });
+
+        // (2) Change the return types, and wrap the return values.  Calls returning the result
+        // of another function with the same return type return its `Result` as is.
+        let [ok, err, either] = conv.wrap_templates();
+        let (ok, err, either) = (
+            parse_expr(cx.session(), ok),
+            parse_expr(cx.session(), err),
+            parse_expr(cx.session(), either),
+        );
+        let mut passed_calls = HashSet::new();
+        mut_visit_fns(krate, |fl| {
+            let ret_ty = match_or!([mod_fns.get(&cx.node_def_id(fl.id))] Some(x) => &x.0; return);
+            fl.decl.output = FunctionRetTy::Ty(parse_ty(cx.session(), &conv.result_ty(ret_ty)));
+            fl.block.as_mut().map(|b| fold_output_exprs(b, true, |e| {
+                let passes_result = matches!([e.kind] ExprKind::Call(..)) &&
+                    cx.opt_callee(e)
+                        .and_then(|id| mod_fns.get(&id))
+                        .map_or(false, |callee| callee.0 == *ret_ty);
+                if passes_result {
+                    passed_calls.insert(e.id);
+                    return;
+                }
+                let template = match conv.is_err(e) {
+                    Some(false) => &ok,
+                    Some(true) => &err,
+                    None => &either,
+                };
+                let mut bnd = Bindings::new();
+                bnd.add("__old", e.clone());
+                *e = template.clone().subst(st, cx, &bnd);
+            }));
+        });
+
+        // (3) Find the checks of the results of the calls
+        let is_mod_call = |e: &Expr| {
+            matches!([e.kind] ExprKind::Call(..)) &&
+                cx.opt_callee(e).map_or(false, |id| mod_fns.contains_key(&id))
+        };
+        let mut checks = HashMap::new();
+        let mut checked_calls = HashSet::new();
+        visit_nodes(krate, |e: &Expr| {
+            if let Some((call, is_err)) = conv.check(e) {
+                if is_mod_call(call) {
+                    checks.insert(e.id, is_err);
+                    checked_calls.insert(call.id);
+                }
+            }
+        });
+
+        // (4) Propagate the errors of the calls that are checked only to return an error
+        FlatMapNodes::visit(krate, |mut s: Stmt| {
+            let propagated = propagated_error(&s)
+                .filter(|(cond, _)| checks.get(&cond.id) == Some(&true))
+                .map(|(cond, err)| (conv.check(cond).unwrap().0.clone(), err.clone()));
+            if let Some((call, err)) = propagated {
+                let mut bnd = Bindings::new();
+                bnd.add("__call", call);
+                let template = match err.kind {
+                    ExprKind::Tup(ref xs) if xs.is_empty() => "__call?",
+                    _ => {
+                        bnd.add("__err", err);
+                        "__call.map_err(|_| __err)?"
+                    }
+                };
+                let e = parse_expr(cx.session(), template).subst(st, cx, &bnd);
+                s.kind = StmtKind::Semi(e);
+            }
+            smallvec![s]
+        });
+
+        // (5) Rewrite the remaining checks, and turn the other results back into error codes
+        MutVisitNodes::visit(krate, |e: &mut P<Expr>| {
+            if let Some(&is_err) = checks.get(&e.id) {
+                let call = conv.check(e).unwrap().0.clone();
+                let method = if is_err { "is_err" } else { "is_ok" };
+                *e = mk().method_call_expr(call, method, Vec::<P<Expr>>::new());
+                return;
+            }
+            if checked_calls.contains(&e.id) || passed_calls.contains(&e.id) || !is_mod_call(e) {
+                return;
+            }
+            let unwrap = &mod_fns[&cx.opt_callee(e).unwrap()].1;
+            let mut bnd = Bindings::new();
+            bnd.add("__new", e.clone());
+            *e = unwrap.clone().subst(st, cx, &bnd);
+        });
+    }
+
+    fn min_phase(&self) -> Phase {
+        Phase::Phase3
+    }
+}
+
+
 /// # `retype_static` Command
 ///
 /// Usage: `retype_static NEW_TY REV_CONV_ASSIGN CONV_RVAL CONV_LVAL [CONV_LVAL_MUT]`
@@ -1414,6 +1781,10 @@ pub fn register_commands(reg: &mut Registry) {
         unwrap: args[2].clone(),
     }));
 
+    reg.register("retcode_to_result", |args| mk(RetcodeToResult {
+        convention: ErrorConvention::from_arg(&args[0]),
+    }));
+
     reg.register("retype_static", |args| mk(RetypeStatic {
         new_ty: args[0].clone(),
         rev_conv_assign: args[1].clone(),
@@ -0,0 +1,391 @@
+use std::collections::{HashMap, HashSet};
+use indexmap::IndexMap;
+use rustc::hir::HirId;
+use rustc::hir::def_id::DefId;
+use syntax::ast::*;
+use syntax::print::pprust;
+use syntax::ptr::P;
+use syntax_pos::sym;
+use smallvec::{smallvec, SmallVec};
+
+use c2rust_ast_builder::mk;
+use crate::ast_manip::{FlatMapNodes, MutVisitNodes, visit_nodes};
+use crate::ast_manip::number_nodes::number_nodes_with;
+use crate::command::{CommandState, Registry};
+use crate::driver::Phase;
+use crate::transform::Transform;
+use crate::RefactorCtxt;
+
+
+/// # `specialize_const_params` Command
+///
+/// Usage: `specialize_const_params [MAX]`
+///
+/// Marks: `target`
+///
+/// Specialize the functions marked `target` for the integer parameters they
+/// are passed a constant for at every call, such as C `flags` parameters.  The
+/// function is cloned once for each combination of constants it is called with,
+/// as long as there are at most `MAX` (default: 4) combinations.  In each clone,
+/// the parameters are replaced by their constants, and `if`s whose condition
+/// becomes constant are replaced by the branch taken.  Calls are redirected to
+/// the clone for their constants, and the original function is removed unless
+/// it is `pub` or `#[no_mangle]`.
+///
+/// Example:
+///
+/// ```ignore
+///     unsafe fn draw(mut buf: *mut u8, mut flags: libc::c_int) {  // draw: target
+///         if flags & 1 as libc::c_int != 0 {
+///             clear(buf);
+///         }
+///         render(buf);
+///     }
+///
+///     draw(a, 1 as libc::c_int);
+///     draw(b, 0 as libc::c_int);
+/// ```
+///
+/// After running `specialize_const_params`:
+///
+/// ```ignore
+///     unsafe fn draw_1(mut buf: *mut u8) {
+///         clear(buf);
+///         render(buf);
+///     }
+///     unsafe fn draw_0(mut buf: *mut u8) {
+///         render(buf);
+///     }
+///
+///     draw_1(a);
+///     draw_0(b);
+/// ```
+///
+/// Functions whose address is taken, and parameters that are assigned to or
+/// borrowed in the body, are not specialized.
+pub struct SpecializeConstParams {
+    max_clones: usize,
+}
+
+/// A function being specialized
+struct Specialized {
+    /// The indices of the constant parameters
+    params: Vec<usize>,
+    /// The name of the clone for each combination of constants
+    clones: IndexMap<Vec<i128>, Ident>,
+}
+
+impl Transform for SpecializeConstParams {
+    fn transform(&self, krate: &mut Crate, st: &CommandState, cx: &RefactorCtxt) {
+        // (1) Find the marked functions and their parameters that are never modified.
+
+        let mut fns: HashMap<DefId, Vec<Option<HirId>>> = HashMap::new();
+        visit_nodes(krate, |i: &Item| {
+            if !st.marked(i.id, "target") {
+                return;
+            }
+            if let ItemKind::Fn(ref sig, _, ref body) = i.kind {
+                let params = sig.decl.inputs.iter().map(|param| {
+                    match param.pat.kind {
+                        PatKind::Ident(BindingMode::ByValue(_), _, None) => {
+                            let hir_id = cx.hir_map().node_to_hir_id(param.pat.id);
+                            Some(hir_id).filter(|&id| !is_modified(cx, body, id))
+                        }
+                        _ => None,
+                    }
+                }).collect();
+                fns.insert(cx.node_def_id(i.id), params);
+            }
+        });
+
+        // (2) Collect the constants passed at every call, giving up on functions that are used
+        // other than by calling them.
+
+        let mut calls: HashMap<DefId, Vec<(NodeId, Vec<Option<i128>>)>> = HashMap::new();
+        let mut callees = HashSet::new();
+        visit_nodes(krate, |e: &Expr| {
+            if let ExprKind::Call(ref callee, ref args) = e.kind {
+                if let Some(def_id) = cx.try_resolve_expr(callee).filter(|id| fns.contains_key(id)) {
+                    callees.insert(callee.id);
+                    let consts = args.iter().map(|arg| const_int(arg)).collect();
+                    calls.entry(def_id).or_insert_with(Vec::new).push((e.id, consts));
+                }
+            }
+        });
+        visit_nodes(krate, |e: &Expr| {
+            if let ExprKind::Path(..) = e.kind {
+                if callees.contains(&e.id) {
+                    return;
+                }
+                if let Some(def_id) = cx.try_resolve_expr(e) {
+                    fns.remove(&def_id);
+                }
+            }
+        });
+
+        // (3) Pick the parameters to specialize and name the clones.
+
+        let mut specialized: HashMap<DefId, Specialized> = HashMap::new();
+        let mut call_clones: HashMap<NodeId, (Ident, Vec<usize>)> = HashMap::new();
+        visit_nodes(krate, |i: &Item| {
+            let def_id = match cx.hir_map().opt_local_def_id_from_node_id(i.id) {
+                Some(def_id) => def_id,
+                None => return,
+            };
+            let (params, fn_calls) = match (fns.get(&def_id), calls.get(&def_id)) {
+                (Some(params), Some(fn_calls)) => (params, fn_calls),
+                _ => return,
+            };
+            let const_params = (0 .. params.len()).filter(|&idx| {
+                params[idx].is_some() &&
+                    fn_calls.iter().all(|(_, consts)| consts.get(idx).map_or(false, Option::is_some))
+            }).collect::<Vec<_>>();
+            if const_params.is_empty() {
+                return;
+            }
+
+            let mut clones = IndexMap::new();
+            for (_, consts) in fn_calls {
+                let values = const_params.iter().map(|&idx| consts[idx].unwrap()).collect::<Vec<_>>();
+                let name = values.iter().fold(i.ident.to_string(), |name, v| {
+                    if *v < 0 {
+                        format!("{}_neg{}", name, -v)
+                    } else {
+                        format!("{}_{}", name, v)
+                    }
+                });
+                clones.entry(values).or_insert_with(|| Ident::from_str(&name));
+            }
+            if clones.len() > self.max_clones {
+                return;
+            }
+            for (call_id, consts) in fn_calls {
+                let values = const_params.iter().map(|&idx| consts[idx].unwrap()).collect::<Vec<_>>();
+                call_clones.insert(*call_id, (clones[&values], const_params.clone()));
+            }
+            specialized.insert(def_id, Specialized { params: const_params, clones });
+        });
+
+        // (4) Call the clones.  Recursive calls are rewritten before the functions are cloned.
+
+        MutVisitNodes::visit(krate, |e: &mut P<Expr>| {
+            let (name, const_params) = match call_clones.get(&e.id) {
+                Some(clone) => clone,
+                None => return,
+            };
+            if let ExprKind::Call(ref mut callee, ref mut args) = e.kind {
+                if let ExprKind::Path(_, ref mut path) = callee.kind {
+                    path.segments.last_mut().unwrap().ident = *name;
+                }
+                let mut idx = 0;
+                args.retain(|_| {
+                    idx += 1;
+                    !const_params.contains(&(idx - 1))
+                });
+            }
+        });
+
+        // (5) Replace the functions by their clones.  The references to the constant parameters
+        // are found in the original function, which has type information.
+
+        FlatMapNodes::visit(krate, |i: P<Item>| -> SmallVec<[P<Item>; 1]> {
+            let def_id = match cx.hir_map().opt_local_def_id_from_node_id(i.id) {
+                Some(def_id) => def_id,
+                None => return smallvec![i],
+            };
+            let spec = match specialized.get(&def_id) {
+                Some(spec) => spec,
+                None => return smallvec![i],
+            };
+            let params = &fns[&def_id];
+            let mut param_refs: HashMap<NodeId, usize> = HashMap::new();
+            if let ItemKind::Fn(_, _, ref body) = i.kind {
+                visit_nodes(&**body, |e: &Expr| {
+                    if let ExprKind::Path(..) = e.kind {
+                        if let Some(hir_id) = cx.try_resolve_expr_to_hid(e) {
+                            if let Some(pos) = spec.params.iter()
+                                .position(|&idx| params[idx] == Some(hir_id)) {
+                                param_refs.insert(e.id, pos);
+                            }
+                        }
+                    }
+                });
+            }
+
+            let mut items = SmallVec::new();
+            let is_exported = i.vis.node.is_pub() ||
+                i.attrs.iter().any(|attr| attr.check_name(sym::no_mangle));
+            for (values, &name) in &spec.clones {
+                let mut clone = i.clone();
+                clone.ident = name;
+                clone.attrs.retain(|attr| !attr.check_name(sym::no_mangle));
+                if let ItemKind::Fn(ref mut sig, _, ref mut body) = clone.kind {
+                    let decl = &mut sig.decl;
+                    let tys = spec.params.iter().map(|&idx| decl.inputs[idx].ty.clone())
+                        .collect::<Vec<_>>();
+                    let mut idx = 0;
+                    decl.inputs.retain(|_| {
+                        idx += 1;
+                        !spec.params.contains(&(idx - 1))
+                    });
+                    MutVisitNodes::visit(body, |e: &mut P<Expr>| {
+                        if let Some(&pos) = param_refs.get(&e.id) {
+                            *e = st.parse_expr(cx, &format!(
+                                "({} as {})", values[pos], pprust::ty_to_string(&tys[pos])));
+                        }
+                    });
+                    simplify_const_ifs(body);
+                }
+                number_nodes_with(&mut clone, st.node_id_counter());
+                items.push(clone);
+            }
+            if is_exported {
+                items.insert(0, i);
+            }
+            items
+        });
+    }
+
+    fn min_phase(&self) -> Phase {
+        Phase::Phase3
+    }
+}
+
+/// Is the local `id` assigned to or borrowed in `body`?
+fn is_modified(cx: &RefactorCtxt, body: &Block, id: HirId) -> bool {
+    let is_local = |e: &Expr| cx.try_resolve_expr_to_hid(e) == Some(id);
+    let mut modified = false;
+    visit_nodes(body, |e: &Expr| {
+        match e.kind {
+            ExprKind::Assign(ref lhs, _) |
+            ExprKind::AssignOp(_, ref lhs, _) |
+            ExprKind::AddrOf(_, _, ref lhs) if is_local(lhs) => modified = true,
+            _ => {}
+        }
+    });
+    modified
+}
+
+/// The value of an integer constant expression, looking through casts to integer types that
+/// can hold it.
+fn const_int(e: &Expr) -> Option<i128> {
+    match e.kind {
+        ExprKind::Lit(ref lit) => match lit.kind {
+            LitKind::Int(v, _) => Some(v as i128),
+            LitKind::Bool(b) => Some(b as i128),
+            _ => None,
+        },
+        ExprKind::Paren(ref e) => const_int(e),
+        ExprKind::Cast(ref e, ref ty) => {
+            let v = const_int(e)?;
+            let (min, max) = int_ty_range(ty)?;
+            if min <= v && v <= max {
+                Some(v)
+            } else {
+                None
+            }
+        }
+        ExprKind::Unary(UnOp::Neg, ref e) => const_int(e).map(|v| -v),
+        ExprKind::Unary(UnOp::Not, ref e) => match e.kind {
+            // Only `!` on `bool`s has the same meaning for every integer type
+            ExprKind::Binary(op, _, _) if is_comparison(op.node) => {
+                const_int(e).map(|v| (v == 0) as i128)
+            }
+            _ => None,
+        },
+        ExprKind::Binary(op, ref lhs, ref rhs) => {
+            let l = const_int(lhs)?;
+            let r = const_int(rhs)?;
+            match op.node {
+                BinOpKind::Add => l.checked_add(r),
+                BinOpKind::Sub => l.checked_sub(r),
+                BinOpKind::Mul => l.checked_mul(r),
+                BinOpKind::BitAnd => Some(l & r),
+                BinOpKind::BitOr => Some(l | r),
+                BinOpKind::BitXor if l >= 0 && r >= 0 => Some(l ^ r),
+                BinOpKind::Shl if (0 .. 32).contains(&r) && l >= 0 => l.checked_shl(r as u32),
+                BinOpKind::Shr if (0 .. 32).contains(&r) && l >= 0 => Some(l >> r),
+                BinOpKind::Eq => Some((l == r) as i128),
+                BinOpKind::Ne => Some((l != r) as i128),
+                BinOpKind::Lt => Some((l < r) as i128),
+                BinOpKind::Le => Some((l <= r) as i128),
+                BinOpKind::Gt => Some((l > r) as i128),
+                BinOpKind::Ge => Some((l >= r) as i128),
+                BinOpKind::And => Some((l != 0 && r != 0) as i128),
+                BinOpKind::Or => Some((l != 0 || r != 0) as i128),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn is_comparison(op: BinOpKind) -> bool {
+    match op {
+        BinOpKind::Eq | BinOpKind::Ne | BinOpKind::Lt | BinOpKind::Le | BinOpKind::Gt |
+        BinOpKind::Ge | BinOpKind::And | BinOpKind::Or => true,
+        _ => false,
+    }
+}
+
+/// The range of values of an integer type on every target, limited to 32 bits, which is plenty
+/// for flags.
+fn int_ty_range(ty: &Ty) -> Option<(i128, i128)> {
+    let name = match ty.kind {
+        TyKind::Path(None, ref path) => path.segments.last()?.ident.name,
+        _ => return None,
+    };
+    Some(match &*name.as_str() {
+        "i8" | "c_schar" => (i8::min_value() as i128, i8::max_value() as i128),
+        "u8" | "c_uchar" => (0, u8::max_value() as i128),
+        // `c_char` is signed on some targets and unsigned on others
+        "c_char" => (0, i8::max_value() as i128),
+        "i16" | "c_short" => (i16::min_value() as i128, i16::max_value() as i128),
+        "u16" | "c_ushort" => (0, u16::max_value() as i128),
+        "i32" | "c_int" | "isize" | "c_long" | "i64" | "c_longlong" | "i128" =>
+            (i32::min_value() as i128, i32::max_value() as i128),
+        "u32" | "c_uint" | "usize" | "c_ulong" | "u64" | "c_ulonglong" | "u128" =>
+            (0, u32::max_value() as i128),
+        "bool" => (0, 1),
+        _ => return None,
+    })
+}
+
+/// Replace `if`s with constant conditions by the branch taken.
+fn simplify_const_ifs(body: &mut P<Block>) {
+    FlatMapNodes::visit(body, |s: Stmt| -> SmallVec<[Stmt; 1]> {
+        let taken = match s.kind {
+            StmtKind::Expr(ref e) | StmtKind::Semi(ref e) => match e.kind {
+                ExprKind::If(ref cond, ref then, ref els) => match const_int(cond) {
+                    Some(0) => els.clone(),
+                    Some(_) => Some(mk().block_expr(then.clone())),
+                    None => return smallvec![s],
+                },
+                _ => return smallvec![s],
+            },
+            _ => return smallvec![s],
+        };
+        match taken {
+            None => smallvec![],
+            Some(e) => match e.kind {
+                // The statements of a block without `let`s can be spliced into the enclosing block
+                ExprKind::Block(ref b, None)
+                    if b.rules == BlockCheckMode::Default &&
+                        !b.stmts.iter().any(|s| matches!(s.kind, StmtKind::Local(_))) =>
+                {
+                    b.stmts.iter().cloned().collect()
+                }
+                _ => smallvec![mk().semi_stmt(e)],
+            },
+        }
+    });
+}
+
+
+pub fn register_commands(reg: &mut Registry) {
+    use super::mk;
+
+    reg.register("specialize_const_params", |args| mk(SpecializeConstParams {
+        max_clones: args.get(0).map_or(4, |arg| arg.parse().unwrap()),
+    }));
+}
@@ -0,0 +1,172 @@
+//! Commands for tracking the migration status of translated items.  The transpiler's
+//! `--migration-status` option marks every translated definition `#[c2rust::status = "raw"]`.
+//! `set_status` updates the status as items are cleaned up, `status_report` summarizes it, and
+//! `check_status` fails if any item is still below a required status, for use in CI.
+use std::collections::BTreeMap;
+use std::process;
+use std::str::FromStr;
+use syntax::ast::*;
+use syntax::ptr::P;
+
+use c2rust_ast_builder::mk;
+use crate::ast_manip::util::is_c2rust_attr;
+use crate::ast_manip::{visit_nodes, MutVisitNodes};
+use crate::command::{CommandState, DriverCommand, Registry};
+use crate::driver::Phase;
+use crate::transform::Transform;
+use crate::RefactorCtxt;
+
+
+/// The migration status of an item, from least to most cleaned up
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Status {
+    /// As translated
+    Raw,
+    /// Rewritten into idiomatic code, possibly still `unsafe`
+    Cleaned,
+    /// Safe Rust
+    Safe,
+}
+
+impl Status {
+    fn as_str(self) -> &'static str {
+        match self {
+            Status::Raw => "raw",
+            Status::Cleaned => "cleaned",
+            Status::Safe => "safe",
+        }
+    }
+}
+
+impl FromStr for Status {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Status, String> {
+        match s {
+            "raw" => Ok(Status::Raw),
+            "cleaned" => Ok(Status::Cleaned),
+            "safe" => Ok(Status::Safe),
+            _ => Err(format!("unknown status `{}`, expected raw, cleaned or safe", s)),
+        }
+    }
+}
+
+/// The status recorded on an item, if it has a valid one
+fn item_status(attrs: &[Attribute]) -> Option<Status> {
+    attrs.iter()
+        .find(|attr| is_c2rust_attr(attr, "status"))
+        .and_then(|attr| attr.value_str())
+        .and_then(|s| s.as_str().parse().ok())
+}
+
+/// Every item with a status, with its location
+fn collect_statuses(krate: &Crate, cx: &RefactorCtxt) -> Vec<(Status, String)> {
+    let cm = cx.session().source_map();
+    let mut statuses = vec![];
+    visit_nodes(krate, |i: &Item| {
+        if let Some(status) = item_status(&i.attrs) {
+            statuses.push((status, format!("{} ({})", i.ident, cm.span_to_string(i.span))));
+        }
+    });
+    statuses
+}
+
+
+/// # `set_status` Command
+///
+/// Usage: `set_status STATUS`
+///
+/// Marks: `target`
+///
+/// Record `STATUS` (`raw`, `cleaned` or `safe`) as the migration status of each
+/// item marked `target`, replacing the status it had before:
+///
+/// ```ignore
+///     #[c2rust::status = "cleaned"]
+///     unsafe fn parse_header(buf: &[u8]) -> Header { ... }
+/// ```
+pub struct SetStatus {
+    status: Status,
+}
+
+impl Transform for SetStatus {
+    fn transform(&self, krate: &mut Crate, st: &CommandState, _cx: &RefactorCtxt) {
+        MutVisitNodes::visit(krate, |i: &mut P<Item>| {
+            if !st.marked(i.id, "target") {
+                return;
+            }
+            i.attrs.retain(|attr| !is_c2rust_attr(attr, "status"));
+            i.attrs.push(mk().str_attr(vec!["c2rust", "status"], self.status.as_str())
+                         .into_attrs().remove(0));
+        });
+    }
+}
+
+
+/// # `status_report` Command
+///
+/// Usage: `status_report`
+///
+/// Log the number of items with each migration status, and the items that
+/// aren't `safe` yet, at level `info`.
+fn status_report(st: &CommandState, cx: &RefactorCtxt) {
+    let statuses = collect_statuses(&st.krate(), cx);
+    let mut counts = BTreeMap::new();
+    for &(status, _) in &statuses {
+        *counts.entry(status).or_insert(0) += 1;
+    }
+    for status in &[Status::Raw, Status::Cleaned, Status::Safe] {
+        info!("{}: {} of {} items", status.as_str(),
+              counts.get(status).unwrap_or(&0), statuses.len());
+    }
+    for (status, item) in &statuses {
+        if *status != Status::Safe {
+            info!("  {}: {}", status.as_str(), item);
+        }
+    }
+}
+
+
+/// # `check_status` Command
+///
+/// Usage: `check_status STATUS`
+///
+/// Fail with the list of items whose migration status is below `STATUS`, so a
+/// CI job can check that cleaned up code doesn't regress.  Items without a
+/// status are not checked.
+fn check_status(st: &CommandState, cx: &RefactorCtxt, min: Status) {
+    let behind = collect_statuses(&st.krate(), cx)
+        .into_iter()
+        .filter(|&(status, _)| status < min)
+        .collect::<Vec<_>>();
+    if behind.is_empty() {
+        return;
+    }
+    for (status, item) in &behind {
+        error!("{} is {}, expected at least {}", item, status.as_str(), min.as_str());
+    }
+    error!("{} items are not {} yet", behind.len(), min.as_str());
+    process::exit(1);
+}
+
+
+pub fn register_commands(reg: &mut Registry) {
+    use super::mk;
+
+    reg.register("set_status", |args| mk(SetStatus {
+        status: args[0].parse().unwrap_or_else(|e: String| panic!("{}", e)),
+    }));
+
+    reg.register("status_report", |_args| {
+        Box::new(DriverCommand::new(Phase::Phase2, move |st, cx| {
+            status_report(st, cx);
+        }))
+    });
+
+    reg.register("check_status", |args| {
+        let min: Status = args[0].parse().unwrap_or_else(|e: String| panic!("{}", e));
+        Box::new(DriverCommand::new(Phase::Phase2, move |st, cx| {
+            check_status(st, cx, min);
+        }))
+    });
+}
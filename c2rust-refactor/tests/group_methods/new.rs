@@ -0,0 +1,31 @@
+#[derive(Copy, Clone)]
+pub struct counter {
+    pub count: i32,
+    pub step: i32,
+}
+impl counter {
+    unsafe fn bump(&mut self) {
+        self.count += self.step;
+    }
+    unsafe fn get(&self) -> i32 {
+        return self.count;
+    }
+    unsafe fn bump_twice(&mut self) {
+        self.bump();
+        self.bump();
+    }
+}
+
+unsafe fn verbatim(mut c: *mut counter) -> i32 {
+    return (*c).count;
+}
+
+fn main() {
+    let mut c = counter { count: 0, step: 2 };
+    unsafe {
+        c.bump_twice();
+        let p: *mut counter = &mut c;
+        assert_eq!((*p).get(), 4);
+        assert_eq!(verbatim(p), 4);
+    }
+}
@@ -0,0 +1,32 @@
+#[derive(Copy, Clone)]
+pub struct counter {
+    pub count: i32,
+    pub step: i32,
+}
+
+unsafe fn counter_bump(mut c: *mut counter) {
+    (*c).count += (*c).step;
+}
+
+unsafe fn counter_get(mut c: *mut counter) -> i32 {
+    return (*c).count;
+}
+
+unsafe fn counter_bump_twice(mut c: *mut counter) {
+    counter_bump(c);
+    counter_bump(c);
+}
+
+unsafe fn verbatim(mut c: *mut counter) -> i32 {
+    return (*c).count;
+}
+
+fn main() {
+    let mut c = counter { count: 0, step: 2 };
+    unsafe {
+        counter_bump_twice(&mut c);
+        let p: *mut counter = &mut c;
+        assert_eq!(counter_get(p), 4);
+        assert_eq!(verbatim(p), 4);
+    }
+}
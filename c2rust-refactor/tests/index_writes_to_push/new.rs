@@ -0,0 +1,45 @@
+extern "C" {
+    fn puts(s: *const u8) -> i32;
+}
+
+unsafe fn greet(mut name: *const u8) {
+    let mut buf: Vec<u8> = Vec::with_capacity(16 as usize);
+    let mut i = 0;
+    let hello = b"hi ";
+    while i < 3 {
+        buf.push(hello[i as usize]);
+        i = i + 1;
+    }
+    while i < 14 && *name != 0 {
+        buf.push(*name);
+        i = i + 1;
+        name = name.offset(1);
+    }
+    buf.push('!' as i32 as u8);
+    i = i + 1;
+    buf.push(0);
+    puts(buf.as_ptr());
+}
+
+unsafe fn shout(mut name: *const u8) {
+    let mut buf: [u8; 16] = [0; 16];
+    let mut i = 0;
+    while i < 15 && *name != 0 {
+        let fresh2 = i;
+        i = i + 1;
+        buf[fresh2 as usize] = *name;
+        name = name.offset(1);
+    }
+    buf[i as usize] = 0;
+    // Reading an element back keeps the array
+    if buf[0] != 0 {
+        puts(buf.as_ptr());
+    }
+}
+
+fn main() {
+    unsafe {
+        greet(b"world\0".as_ptr());
+        shout(b"world\0".as_ptr());
+    }
+}
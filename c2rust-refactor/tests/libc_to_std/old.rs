@@ -0,0 +1,31 @@
+extern "C" {
+    fn memcpy(_: *mut libc::c_void, _: *const libc::c_void, _: libc::c_ulong)
+     -> *mut libc::c_void;
+    fn memset(_: *mut libc::c_void, _: libc::c_int, _: libc::c_ulong)
+     -> *mut libc::c_void;
+    fn strlen(_: *const libc::c_char) -> libc::c_ulong;
+    fn abs(_: libc::c_int) -> libc::c_int;
+}
+
+unsafe fn converted(mut dst: *mut u8, mut src: *const u8, mut s: *const libc::c_char) -> libc::c_int {
+    let mut buf: [u8; 4] = [0; 4];
+    let name: [u8; 4] = *b"abc\0";
+    memset(dst as *mut libc::c_void, 0 as libc::c_int, 4 as libc::c_ulong);
+    memcpy(dst as *mut libc::c_void, src as *const libc::c_void, 2 as libc::c_ulong);
+    memcpy(buf.as_mut_ptr() as *mut libc::c_void, name.as_ptr() as *const libc::c_void, 3 as libc::c_ulong);
+    abs(strlen(s) as libc::c_int - strlen(b"hello\0" as *const u8 as *const libc::c_char) as libc::c_int)
+}
+
+unsafe fn verbatim(mut dst: *mut u8, mut s: *const libc::c_char) -> libc::c_int {
+    memset(dst as *mut libc::c_void, 0 as libc::c_int, 4 as libc::c_ulong);
+    abs(strlen(s) as libc::c_int)
+}
+
+fn main() {
+    let mut dst = [1u8; 4];
+    let src = [2u8; 2];
+    unsafe {
+        converted(dst.as_mut_ptr(), src.as_ptr(), b"abc\0".as_ptr() as *const libc::c_char);
+        verbatim(dst.as_mut_ptr(), b"abc\0".as_ptr() as *const libc::c_char);
+    }
+}
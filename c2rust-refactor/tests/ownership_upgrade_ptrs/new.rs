@@ -10,6 +10,7 @@
 
 extern "C" {
     fn malloc(_: libc::c_ulong) -> *mut libc::c_void;
+    fn calloc(_: libc::c_ulong, _: libc::c_ulong) -> *mut libc::c_void;
     #[ownership_constraints(le(WRITE, _0), le(_0, WRITE))]
     fn memset(_: *mut libc::c_void, _: libc::c_int, _: libc::c_ulong);
     fn free(_: *mut libc::c_void);
@@ -103,9 +104,6 @@ pub(crate) unsafe extern "C" fn __ibitmap(
             / ::core::mem::size_of::<libc::c_uint>()
     ]
     .into_boxed_slice();
-    if false {
-        return 1i32;
-    }
     (hashp.as_mut().unwrap()).nmaps += 1;
     clearints = (nbits - 1i32 >> 5i32) + 1i32;
     clearbytes = clearints << 2i32;
@@ -172,6 +170,43 @@ pub(crate) unsafe extern "C" fn __ibitmap2(
     return 0i32;
 }
 
+pub(crate) unsafe extern "C" fn __ibitmap3(
+    mut hashp: Option<&mut HTAB>,
+    nbits: libc::c_int,
+    ndx: libc::c_int,
+) -> libc::c_int {
+    #[slice]
+    #[nonnull]
+    let mut ip;
+    let mut clearints: libc::c_int = (nbits - 1i32 >> 5i32) + 1i32;
+    ip = vec![
+        libc::c_uint::default();
+        (clearints as libc::c_ulong as usize)
+            .checked_mul(::core::mem::size_of::<libc::c_uint>() as libc::c_ulong as usize)
+            .unwrap()
+            / ::core::mem::size_of::<libc::c_uint>()
+    ]
+    .into_boxed_slice();
+    ip[(clearints - 1i32) as usize] = 0xffffffffu32;
+    (hashp.as_mut().unwrap()).mapp[ndx as usize] = Some(ip);
+    return 0i32;
+}
+
+pub(crate) unsafe extern "C" fn __ihdrs(nhdrs: libc::c_int) -> libc::c_int {
+    #[slice]
+    #[nonnull]
+    let mut hdrs;
+    hdrs = vec![
+        ::core::mem::zeroed::<HASHHDR>();
+        (nhdrs as libc::c_ulong as usize)
+            .checked_mul(::core::mem::size_of::<HASHHDR>() as libc::c_ulong as usize)
+            .unwrap()
+            / ::core::mem::size_of::<HASHHDR>()
+    ]
+    .into_boxed_slice();
+    return 0i32;
+}
+
 unsafe fn move_ptr(mut ptr: Option<Box<u32>>) {
     ptr.take();
 }
@@ -362,6 +397,11 @@ unsafe fn rewritten_calls(
     );
 }
 
+unsafe fn restrict_params(#[c2rust::restrict] mut dst: Option<&mut u32>, src: *mut u32) {
+    **dst.as_mut().unwrap() = *src;
+    *src = 0;
+}
+
 static mut categories: [_category; 2129] = [_category {
     cat_first: [0; 4],
     delta: 0,
@@ -10,6 +10,7 @@
 
 extern "C" {
     fn malloc(_: libc::c_ulong) -> *mut libc::c_void;
+    fn calloc(_: libc::c_ulong, _: libc::c_ulong) -> *mut libc::c_void;
     #[ownership_constraints(le(WRITE, _0), le(_0, WRITE))]
     fn memset(_: *mut libc::c_void, _: libc::c_int, _: libc::c_ulong);
     fn free(_: *mut libc::c_void);
@@ -135,6 +136,33 @@ pub(crate) unsafe extern "C" fn __ibitmap2(mut hashp: *mut HTAB,
     return 0i32;
 }
 
+pub(crate) unsafe extern "C" fn __ibitmap3(mut hashp: *mut HTAB,
+                                           nbits: libc::c_int,
+                                           ndx: libc::c_int) -> libc::c_int {
+    #[slice]
+    #[nonnull]
+    let mut ip: *mut libc::c_uint = 0 as *mut libc::c_uint;
+    let mut clearints: libc::c_int = (nbits - 1i32 >> 5i32) + 1i32;
+    ip = calloc(clearints as libc::c_ulong,
+                ::core::mem::size_of::<libc::c_uint>() as libc::c_ulong) as
+             *mut libc::c_uint;
+    if ip.is_null() { return 1i32 }
+    *ip.offset((clearints - 1i32) as isize) = 0xffffffffu32;
+    (*hashp).mapp[ndx as usize] = ip;
+    return 0i32;
+}
+
+pub(crate) unsafe extern "C" fn __ihdrs(nhdrs: libc::c_int) -> libc::c_int {
+    #[slice]
+    #[nonnull]
+    let mut hdrs: *mut HASHHDR = 0 as *mut HASHHDR;
+    hdrs = calloc(nhdrs as libc::c_ulong,
+                  ::core::mem::size_of::<HASHHDR>() as libc::c_ulong) as
+               *mut HASHHDR;
+    if hdrs.is_null() { return 1i32 }
+    return 0i32;
+}
+
 unsafe fn move_ptr(mut ptr: *mut u32) {
     free(ptr as *mut libc::c_void);
 }
@@ -282,6 +310,11 @@ unsafe fn rewritten_calls(
     decay_calls(nncp, nnmp, nncs, nnms, nncp, nnmp2, nncs, nnms2);
 }
 
+unsafe fn restrict_params(#[c2rust::restrict] dst: *mut u32, src: *mut u32) {
+    *dst = *src;
+    *src = 0;
+}
+
 static mut categories: [_category; 2129] = [_category{cat_first: [0; 4], delta: 0,}; 2129];
 
 pub unsafe extern "C" fn category(ucs: libc::c_uint) -> category {
@@ -0,0 +1,27 @@
+#![feature(register_attr)]
+#![register_attr(nonnull)]
+
+unsafe fn add(#[nonnull] acc: &mut i32, x: *const i32) {
+    *acc += *x;
+}
+
+unsafe fn sum(out: &mut i32, xs: *const i32, n: usize) {
+    let mut s = 0;
+    let acc: &mut i32 = &mut s;
+    let mut i = 0;
+    while i < n {
+        add(&mut *acc, xs.offset(i as isize));
+        i += 1;
+    }
+    *out = s;
+}
+
+fn main() {
+    let mut total = 0;
+    let n = 2;
+    let xs = [1, 2, 3];
+    unsafe {
+        add(&mut total, &n as *const i32);
+        sum(&mut total, xs.as_ptr(), 3);
+    }
+}
@@ -0,0 +1,54 @@
+extern "C" {
+    fn pthread_mutex_lock(_: *mut libc::pthread_mutex_t) -> libc::c_int;
+    fn pthread_mutex_unlock(_: *mut libc::pthread_mutex_t) -> libc::c_int;
+    fn pthread_cond_wait(_: *mut libc::pthread_cond_t, _: *mut libc::pthread_mutex_t)
+     -> libc::c_int;
+    fn pthread_cond_signal(_: *mut libc::pthread_cond_t) -> libc::c_int;
+    fn pthread_create(_: *mut libc::pthread_t, _: *const libc::pthread_attr_t,
+                      _: Option<unsafe extern "C" fn(_: *mut libc::c_void) -> *mut libc::c_void>,
+                      _: *mut libc::c_void) -> libc::c_int;
+    fn pthread_join(_: libc::pthread_t, _: *mut *mut libc::c_void) -> libc::c_int;
+}
+
+static lock: ::std::sync::Mutex<()> = ::std::sync::Mutex::new(());
+static ready: ::std::sync::Condvar = ::std::sync::Condvar::new();
+static mut other: libc::pthread_mutex_t = libc::PTHREAD_MUTEX_INITIALIZER;
+static mut count: libc::c_int = 0;
+
+unsafe extern "C" fn worker(mut arg: *mut libc::c_void) -> *mut libc::c_void {
+    let mut lock_guard = lock.lock().unwrap();
+    count += *(arg as *mut libc::c_int);
+    ready.notify_one();
+    drop(lock_guard);
+    return 0 as *mut libc::c_void;
+}
+
+unsafe fn run(mut n: *mut libc::c_int) -> libc::c_int {
+    let mut t: Option<::std::thread::JoinHandle<()>> = None;
+    t =
+        Some({
+                 let arg = (n as *mut libc::c_void) as usize;
+                 ::std::thread::spawn(move || { worker(arg as *mut _); })
+             });
+    let mut lock_guard = lock.lock().unwrap();
+    while count == 0 as libc::c_int {
+        lock_guard = ready.wait(lock_guard).unwrap();
+    }
+    drop(lock_guard);
+    t.take().unwrap().join().unwrap();
+    return count;
+}
+
+unsafe fn verbatim() -> libc::c_int {
+    if pthread_mutex_lock(&mut other) != 0 as libc::c_int { return -1; }
+    pthread_mutex_unlock(&mut other);
+    return 0;
+}
+
+fn main() {
+    let mut n = 3;
+    unsafe {
+        assert_eq!(run(&mut n), 3);
+        assert_eq!(verbatim(), 0);
+    }
+}
@@ -0,0 +1,51 @@
+extern "C" {
+    fn pthread_mutex_lock(_: *mut libc::pthread_mutex_t) -> libc::c_int;
+    fn pthread_mutex_unlock(_: *mut libc::pthread_mutex_t) -> libc::c_int;
+    fn pthread_cond_wait(_: *mut libc::pthread_cond_t, _: *mut libc::pthread_mutex_t)
+     -> libc::c_int;
+    fn pthread_cond_signal(_: *mut libc::pthread_cond_t) -> libc::c_int;
+    fn pthread_create(_: *mut libc::pthread_t, _: *const libc::pthread_attr_t,
+                      _: Option<unsafe extern "C" fn(_: *mut libc::c_void) -> *mut libc::c_void>,
+                      _: *mut libc::c_void) -> libc::c_int;
+    fn pthread_join(_: libc::pthread_t, _: *mut *mut libc::c_void) -> libc::c_int;
+}
+
+static mut lock: libc::pthread_mutex_t = libc::PTHREAD_MUTEX_INITIALIZER;
+static mut ready: libc::pthread_cond_t = libc::PTHREAD_COND_INITIALIZER;
+static mut other: libc::pthread_mutex_t = libc::PTHREAD_MUTEX_INITIALIZER;
+static mut count: libc::c_int = 0;
+
+unsafe extern "C" fn worker(mut arg: *mut libc::c_void) -> *mut libc::c_void {
+    pthread_mutex_lock(&mut lock);
+    count += *(arg as *mut libc::c_int);
+    pthread_cond_signal(&mut ready);
+    pthread_mutex_unlock(&mut lock);
+    return 0 as *mut libc::c_void;
+}
+
+unsafe fn run(mut n: *mut libc::c_int) -> libc::c_int {
+    let mut t: libc::pthread_t = 0;
+    pthread_create(&mut t, 0 as *const libc::pthread_attr_t, Some(worker),
+                   n as *mut libc::c_void);
+    pthread_mutex_lock(&mut lock);
+    while count == 0 as libc::c_int {
+        pthread_cond_wait(&mut ready, &mut lock);
+    }
+    pthread_mutex_unlock(&mut lock);
+    pthread_join(t, 0 as *mut *mut libc::c_void);
+    return count;
+}
+
+unsafe fn verbatim() -> libc::c_int {
+    if pthread_mutex_lock(&mut other) != 0 as libc::c_int { return -1; }
+    pthread_mutex_unlock(&mut other);
+    return 0;
+}
+
+fn main() {
+    let mut n = 3;
+    unsafe {
+        assert_eq!(run(&mut n), 3);
+        assert_eq!(verbatim(), 0);
+    }
+}
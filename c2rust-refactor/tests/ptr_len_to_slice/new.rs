@@ -0,0 +1,31 @@
+extern "C" {
+    fn qsort_ints(base: *mut i32, n: usize);
+}
+
+unsafe fn sum(xs: &[i32]) -> i32 {
+    let n: usize = xs.len() as usize;
+    let mut s = 0;
+    let mut i = 0;
+    while i < n {
+        s += xs[i as usize];
+        i += 1;
+    }
+    s
+}
+
+unsafe fn fill(buf: &mut [i32], v: i32) {
+    let mut len: i32 = buf.len() as i32;
+    while len > 0 {
+        len -= 1;
+        buf[len as usize] = v;
+    }
+    buf[0] = v + 1;
+    qsort_ints(buf.as_mut_ptr(), 1);
+}
+
+unsafe fn total(v: &mut Vec<i32>, p: *const i32, n: usize) -> i32 {
+    fill(&mut *v, 2);
+    sum(&*v) + sum(::std::slice::from_raw_parts(p, n as usize))
+}
+
+fn main() {}
@@ -0,0 +1,29 @@
+extern "C" {
+    fn qsort_ints(base: *mut i32, n: usize);
+}
+
+unsafe fn sum(xs: *const i32, n: usize) -> i32 {
+    let mut s = 0;
+    let mut i = 0;
+    while i < n {
+        s += *xs.offset(i as isize);
+        i += 1;
+    }
+    s
+}
+
+unsafe fn fill(buf: *mut i32, mut len: i32, v: i32) {
+    while len > 0 {
+        len -= 1;
+        *buf.add(len as usize) = v;
+    }
+    *buf = v + 1;
+    qsort_ints(buf, 1);
+}
+
+unsafe fn total(v: &mut Vec<i32>, p: *const i32, n: usize) -> i32 {
+    fill(v.as_mut_ptr(), v.len() as i32, 2);
+    sum(v.as_ptr(), v.len()) + sum(p, n)
+}
+
+fn main() {}
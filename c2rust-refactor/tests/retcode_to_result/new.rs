@@ -0,0 +1,25 @@
+fn digit(c: u8) -> Result<i32, i32> {
+    if c < b'0' || c > b'9' {
+        return Err(-1);
+    }
+    match (c - b'0') as i32 {
+        ret if ret < 0 => Err(ret),
+        ret => Ok(ret),
+    }
+}
+
+fn number(s: &[u8]) -> Result<i32, i32> {
+    digit(s[0]).map_err(|_| -2)?;
+    digit(s[1]).map_err(|_| -2)?;
+    Ok(0)
+}
+
+fn main() {
+    let d = match digit(b'7') {
+        Ok(ret) => ret,
+        Err(code) => code,
+    };
+    if number(b"42").is_ok() {
+        println!("{}", d);
+    }
+}
@@ -0,0 +1,23 @@
+fn digit(c: u8) -> i32 {
+    if c < b'0' || c > b'9' {
+        return -1;
+    }
+    (c - b'0') as i32
+}
+
+fn number(s: &[u8]) -> i32 {
+    if digit(s[0]) < 0 {
+        return -2;
+    }
+    if digit(s[1]) < 0 {
+        return -2;
+    }
+    0
+}
+
+fn main() {
+    let d = digit(b'7');
+    if number(b"42") >= 0 {
+        println!("{}", d);
+    }
+}
@@ -0,0 +1,29 @@
+#![feature(register_tool)]
+#![register_tool(c2rust)]
+
+#[c2rust::status = "raw"]
+unsafe fn raw_fn(p: *const i32) -> i32 {
+    *p
+}
+
+#[c2rust::status = "raw"]
+fn cleaned_fn(x: &i32) -> i32 {
+    *x
+}
+
+#[c2rust::status = "cleaned"]
+fn safe_fn(x: i32) -> i32 {
+    x + 1
+}
+
+fn untracked() {}
+
+fn main() {
+    let x = 1;
+    unsafe {
+        raw_fn(&x);
+    }
+    cleaned_fn(&x);
+    safe_fn(x);
+    untracked();
+}
@@ -0,0 +1,12 @@
+#!/bin/sh
+
+# work around System Integrity Protection on macOS
+if [ `uname` = 'Darwin' ]; then
+    export LD_LIBRARY_PATH=$not_LD_LIBRARY_PATH
+fi
+
+$refactor \
+    select target 'crate; desc(fn && name("cleaned_fn"));' \; set_status cleaned \; clear_marks \; \
+    select target 'crate; desc(fn && name("safe_fn"));' \; set_status safe \; clear_marks \; \
+    check_status raw \; status_report \
+    -- old.rs $rustflags
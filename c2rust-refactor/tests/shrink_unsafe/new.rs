@@ -0,0 +1,28 @@
+unsafe fn get(p: *const i32) -> i32 {
+    *p
+}
+
+fn twice(x: i32) -> i32 {
+    let y = x * 2;
+    y
+}
+
+fn sum(xs: &[i32]) -> i32 {
+    let mut s = 0;
+    {
+        let p = xs.as_ptr();
+        let mut i = 0;
+        while i < xs.len() {
+            unsafe { s += get(p.add(i)) };
+            i += 1;
+        }
+        s = twice(s);
+    }
+    s
+}
+
+fn main() {
+    let xs = [1, 2, 3];
+    let s = twice(sum(&xs));
+    println!("{}", s);
+}
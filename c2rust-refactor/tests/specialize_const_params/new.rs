@@ -0,0 +1,38 @@
+#![allow(unused_mut)]
+
+unsafe fn clear(buf: *mut u8) {
+    *buf = 0;
+}
+
+unsafe fn border(buf: *mut u8) {
+    *buf.offset(1) = b'#';
+}
+
+unsafe fn render(buf: *mut u8) {
+    *buf.offset(2) = b'.';
+}
+
+unsafe fn draw_1(mut buf: *mut u8) {
+    clear(buf);
+    render(buf);
+}
+unsafe fn draw_3(mut buf: *mut u8) {
+    clear(buf);
+    border(buf);
+}
+
+unsafe fn scale(mut x: i32, mut factor: i32) -> i32 {
+    factor = factor + 1;
+    x * factor
+}
+
+fn main() {
+    let mut a = [1u8; 3];
+    let mut b = [1u8; 3];
+    unsafe {
+        draw_1(a.as_mut_ptr());
+        draw_3(b.as_mut_ptr());
+        draw_1(a.as_mut_ptr());
+        scale(a[0] as i32, 3);
+    }
+}
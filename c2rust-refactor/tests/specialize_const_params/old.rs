@@ -0,0 +1,40 @@
+#![allow(unused_mut)]
+
+unsafe fn clear(buf: *mut u8) {
+    *buf = 0;
+}
+
+unsafe fn border(buf: *mut u8) {
+    *buf.offset(1) = b'#';
+}
+
+unsafe fn render(buf: *mut u8) {
+    *buf.offset(2) = b'.';
+}
+
+unsafe fn draw(mut buf: *mut u8, mut flags: i32) {
+    if flags & 1 as i32 != 0 {
+        clear(buf);
+    }
+    if flags & 2 as i32 != 0 {
+        border(buf);
+    } else {
+        render(buf);
+    }
+}
+
+unsafe fn scale(mut x: i32, mut factor: i32) -> i32 {
+    factor = factor + 1;
+    x * factor
+}
+
+fn main() {
+    let mut a = [1u8; 3];
+    let mut b = [1u8; 3];
+    unsafe {
+        draw(a.as_mut_ptr(), 1 as i32);
+        draw(b.as_mut_ptr(), 3 as i32);
+        draw(a.as_mut_ptr(), 1 as i32);
+        scale(a[0] as i32, 3);
+    }
+}
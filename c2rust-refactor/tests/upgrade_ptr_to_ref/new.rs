@@ -2,6 +2,7 @@
 
 extern "C" {
     fn malloc(_: libc::c_ulong) -> *mut libc::c_void;
+    fn calloc(_: libc::c_ulong, _: libc::c_ulong) -> *mut libc::c_void;
 }
 
 #[no_mangle]
@@ -273,3 +274,34 @@ unsafe extern "C" fn opt_params(mut p1: Option<&mut u32>, p2: Option<&u32>, p3:
 
     **p1.as_mut().unwrap() = *p2.unwrap() + p3.unwrap()[0];
 }
+
+#[derive(Copy, Clone)]
+struct Pair {
+    count: u32,
+    next: *mut Pair,
+}
+
+unsafe fn alloc_pairs(n: libc::c_ulong) {
+    let mut pairs;
+
+    pairs = vec![
+        ::core::mem::zeroed::<Pair>();
+        (n as usize)
+            .checked_mul(::core::mem::size_of::<Pair>() as libc::c_ulong as usize)
+            .unwrap()
+            / ::core::mem::size_of::<Pair>()
+    ]
+    .into_boxed_slice();
+}
+
+#[derive(Clone)]
+struct Node {
+    value: u32,
+    payload: Box<u32>,
+}
+
+unsafe fn alloc_nodes(n: libc::c_ulong) {
+    let mut nodes;
+
+    nodes = calloc(n, ::core::mem::size_of::<Node>() as libc::c_ulong) as *mut Node;
+}
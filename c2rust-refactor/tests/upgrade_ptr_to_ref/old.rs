@@ -2,6 +2,7 @@
 
 extern "C" {
     fn malloc(_: libc::c_ulong) -> *mut libc::c_void;
+    fn calloc(_: libc::c_ulong, _: libc::c_ulong) -> *mut libc::c_void;
 }
 
 #[no_mangle]
@@ -299,3 +300,26 @@ unsafe extern "C" fn opt_params(mut p1: *mut u32, p2: *const u32, p3: *const u32
 
     *p1 = *p2 + *p3.offset(0);
 }
+
+#[derive(Copy, Clone)]
+struct Pair {
+    count: u32,
+    next: *mut Pair,
+}
+
+unsafe fn alloc_pairs(n: libc::c_ulong) {
+    let mut pairs: *mut Pair = 0 as *mut Pair;
+
+    pairs = calloc(n, ::core::mem::size_of::<Pair>() as libc::c_ulong) as *mut Pair;
+}
+
+struct Node {
+    value: u32,
+    payload: *mut u32,
+}
+
+unsafe fn alloc_nodes(n: libc::c_ulong) {
+    let mut nodes: *mut Node = 0 as *mut Node;
+
+    nodes = calloc(n, ::core::mem::size_of::<Node>() as libc::c_ulong) as *mut Node;
+}
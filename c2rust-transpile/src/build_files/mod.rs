@@ -1,7 +1,7 @@
 use std::collections::BTreeMap;
-use std::fs::{self, File};
-use std::io::Write;
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::str::FromStr;
 
 use handlebars::Handlebars;
@@ -9,9 +9,11 @@ use pathdiff::diff_paths;
 use serde_derive::Serialize;
 use serde_json::json;
 
-use super::compile_cmds::LinkCmd;
+use super::compile_cmds::{CompileCmd, LinkCmd};
 use super::TranspilerConfig;
 use crate::get_module_name;
+use crate::str_to_ident;
+use crate::write_if_changed;
 use crate::CrateSet;
 use crate::ExternCrateDetails;
 use crate::PragmaSet;
@@ -45,7 +47,7 @@ pub fn get_build_dir(tcfg: &TranspilerConfig, cc_db: &Path) -> PathBuf {
     match &tcfg.output_dir {
         Some(dir) => {
             let output_dir = dir.clone();
-            if !output_dir.exists() {
+            if !output_dir.exists() && tcfg.dry_run.is_none() {
                 fs::create_dir(&output_dir).expect(&format!(
                     "couldn't create build directory: {}",
                     output_dir.display()
@@ -63,6 +65,11 @@ pub struct CrateConfig<'lcmd> {
     pub pragmas: PragmaSet,
     pub crates: CrateSet,
     pub link_cmd: &'lcmd LinkCmd,
+    /// C files compiled into the crate by `build.rs` (see `--hybrid-build`)
+    pub c_inputs: Vec<Rc<CompileCmd>>,
+    /// C files that include a translated C file under renamed symbols for the FFI tests, and
+    /// the command the included file was compiled with (see `--ffi-tests`)
+    pub ffi_sources: Vec<(Rc<CompileCmd>, PathBuf)>,
 }
 
 /// Emit `Cargo.toml` and `lib.rs` for a library or `main.rs` for a binary.
@@ -83,7 +90,7 @@ pub fn emit_build_files<'lcmd>(
     reg.register_template_string("build.rs", include_str!("build.rs.hbs"))
         .unwrap();
 
-    if !build_dir.exists() {
+    if !build_dir.exists() && tcfg.dry_run.is_none() {
         fs::create_dir_all(&build_dir).expect(&format!(
             "couldn't create build directory: {}",
             build_dir.display()
@@ -95,7 +102,14 @@ pub fn emit_build_files<'lcmd>(
         emit_rust_toolchain(tcfg, &build_dir);
     }
     crate_cfg.and_then(|ccfg| {
-        emit_build_rs(tcfg, &reg, &build_dir, ccfg.link_cmd);
+        emit_build_rs(
+            tcfg,
+            &reg,
+            &build_dir,
+            ccfg.link_cmd,
+            &ccfg.c_inputs,
+            &ccfg.ffi_sources,
+        );
         emit_lib_rs(
             tcfg,
             &reg,
@@ -103,6 +117,7 @@ pub fn emit_build_files<'lcmd>(
             ccfg.modules,
             ccfg.pragmas,
             &ccfg.crates,
+            &ccfg.link_cmd.deps,
         )
     })
 }
@@ -221,19 +236,162 @@ fn get_lib_rs_file_name(tcfg: &TranspilerConfig) -> &str {
     }
 }
 
-/// Emit `build.rs` to make it easier to link in native libraries
+/// A C file compiled by `build.rs` with the `cc` crate. The file, include paths and flags are
+/// taken from the original compile command, and are rendered as Rust string literals.
+#[derive(Serialize)]
+struct CSource {
+    file: String,
+    includes: Vec<String>,
+    /// Macro names and `Option` values
+    defines: Vec<(String, String)>,
+    flags: Vec<String>,
+    lib_name: String,
+}
+
+impl CSource {
+    fn new(cmd: &CompileCmd, build_dir: &Path, lib_name: String) -> CSource {
+        // Paths are relative to the crate if possible, since `build.rs` runs in its directory
+        let path_literal = |path: &str| {
+            let path = cmd.directory().join(path);
+            let path = diff_paths(&path, build_dir).unwrap_or(path);
+            format!("{:?}", path.display().to_string())
+        };
+
+        let mut includes = vec![];
+        let mut defines = vec![];
+        let mut flags = vec![];
+        let args = cmd.args();
+        let mut args = args.iter().skip(1); // the compiler
+        while let Some(arg) = args.next() {
+            // The value of a flag given either as `-Ivalue` or as `-I value`
+            let value = |args: &mut dyn Iterator<Item = &String>| match &arg[2..] {
+                "" => args.next().cloned(),
+                value => Some(value.to_owned()),
+            };
+            match arg.as_str() {
+                // Outputs of the original compilation, and the input, which `cc` takes care of
+                "-c" | "-MD" | "-MMD" | "-MP" => {}
+                "-o" | "-MF" | "-MT" | "-MQ" => {
+                    args.next();
+                }
+                _ if arg.starts_with("-o") => {}
+                _ if cmd.directory().join(arg) == cmd.directory().join(&cmd.file) => {}
+
+                "-include" | "-isystem" | "-iquote" | "-idirafter" => {
+                    flags.push(format!("{:?}", arg));
+                    if let Some(path) = args.next() {
+                        flags.push(path_literal(path));
+                    }
+                }
+                _ if arg.starts_with("-I") => {
+                    includes.extend(value(&mut args).map(|path| path_literal(&path)));
+                }
+                _ if arg.starts_with("-D") => {
+                    if let Some(define) = value(&mut args) {
+                        let (name, value) = match define.split_once('=') {
+                            Some((name, value)) => (name, format!("Some({:?})", value)),
+                            None => (define.as_str(), "None".to_owned()),
+                        };
+                        defines.push((format!("{:?}", name), value));
+                    }
+                }
+                _ => flags.push(format!("{:?}", arg)),
+            }
+        }
+
+        CSource {
+            file: path_literal(cmd.file.to_str().unwrap()),
+            includes,
+            defines,
+            flags,
+            lib_name: format!("{:?}", lib_name),
+        }
+    }
+}
+
+/// Convert the C files of a hybrid crate into sources for `build.rs`, giving each one a static
+/// library of its own so that they are compiled with their own flags
+fn convert_c_sources(build_dir: &Path, c_inputs: &[Rc<CompileCmd>]) -> Vec<CSource> {
+    let mut lib_names = BTreeMap::new();
+    c_inputs
+        .iter()
+        .map(|cmd| {
+            let stem = cmd.file.file_stem().unwrap().to_string_lossy();
+            let mut lib_name = format!("c2rust_c_{}", str_to_ident(stem));
+            let count = lib_names.entry(lib_name.clone()).or_insert(0);
+            if *count > 0 {
+                lib_name.push_str(&format!("_{}", count));
+            }
+            *count += 1;
+            CSource::new(cmd, build_dir, lib_name)
+        })
+        .collect()
+}
+
+/// Convert the C files that include translated C files for the FFI tests into sources for
+/// `build.rs`. The included file is found through its directory, which comes first in the
+/// include path, and is compiled with the flags of its original build.
+fn convert_ffi_sources(
+    build_dir: &Path,
+    ffi_sources: &[(Rc<CompileCmd>, PathBuf)],
+) -> Vec<CSource> {
+    let mut lib_names = BTreeMap::new();
+    ffi_sources
+        .iter()
+        .map(|(cmd, ffi_file)| {
+            let stem = ffi_file.file_stem().unwrap().to_string_lossy();
+            let mut lib_name = format!("c2rust_{}", str_to_ident(stem));
+            let count = lib_names.entry(lib_name.clone()).or_insert(0);
+            if *count > 0 {
+                lib_name.push_str(&format!("_{}", count));
+            }
+            *count += 1;
+            let mut source = CSource::new(cmd, build_dir, lib_name);
+
+            let ffi_file = diff_paths(ffi_file, build_dir).unwrap_or_else(|| ffi_file.clone());
+            source.file = format!("{:?}", ffi_file.display().to_string());
+            let c_file = cmd.abs_file();
+            let c_dir = c_file.parent().unwrap();
+            let c_dir = diff_paths(c_dir, build_dir).unwrap_or_else(|| c_dir.to_path_buf());
+            source
+                .includes
+                .insert(0, format!("{:?}", c_dir.display().to_string()));
+            let c_file_name = c_file.file_name().unwrap().to_string_lossy();
+            source.defines.push((
+                format!("{:?}", "C2RUST_FFI_SOURCE"),
+                format!("Some({:?})", format!("\"{}\"", c_file_name)),
+            ));
+            source
+        })
+        .collect()
+}
+
+/// Emit `build.rs` to link in the native libraries of the original link command, searching the
+/// directories it did, and to compile the C files of hybrid crates and of FFI tests. The C files
+/// are compiled first, so that the libraries they use are linked after them.
 fn emit_build_rs(
     tcfg: &TranspilerConfig,
     reg: &Handlebars,
     build_dir: &Path,
     link_cmd: &LinkCmd,
+    c_inputs: &[Rc<CompileCmd>],
+    ffi_sources: &[(Rc<CompileCmd>, PathBuf)],
 ) -> Option<PathBuf> {
+    let mut c_sources = convert_c_sources(build_dir, c_inputs);
+    c_sources.extend(convert_ffi_sources(build_dir, ffi_sources));
+    let lib_dirs = link_cmd
+        .lib_dirs
+        .iter()
+        .map(|dir| format!("{:?}", dir.display().to_string()))
+        .collect::<Vec<_>>();
     let json = json!({
         "libraries": link_cmd.libs,
+        "lib_dirs": lib_dirs,
+        "c_sources": c_sources,
     });
     let output = reg.render("build.rs", &json).unwrap();
     let output_path = build_dir.join("build.rs");
-    maybe_write_to_file(&output_path, output, tcfg.overwrite_existing)
+    maybe_write_to_file(tcfg, &output_path, output)
 }
 
 /// Emit lib.rs (main.rs) for a library (binary). Returns `Some(path)`
@@ -245,23 +403,31 @@ fn emit_lib_rs(
     modules: Vec<PathBuf>,
     pragmas: PragmaSet,
     crates: &CrateSet,
+    crate_deps: &[String],
 ) -> Option<PathBuf> {
     let modules = convert_module_list(tcfg, build_dir, modules, ModuleSubset::Libraries);
     let crates = convert_dependencies_list(crates.clone());
+    // The crates of linked libraries have to be named to be linked in
+    let crate_deps = crate_deps
+        .iter()
+        .map(|name| name.replace('-', "_"))
+        .collect::<Vec<_>>();
     let file_name = get_lib_rs_file_name(tcfg);
     let json = json!({
         "lib_rs_file": file_name,
         "reorganize_definitions": tcfg.reorganize_definitions,
         "translate_valist": tcfg.translate_valist,
+        "no_std": tcfg.emit_no_std,
         "modules": modules,
         "pragmas": pragmas,
         "crates": crates,
+        "crate_deps": crate_deps,
     });
 
     let output_path = build_dir.join(file_name);
     let output = reg.render("lib.rs", &json).unwrap();
 
-    maybe_write_to_file(&output_path, output, tcfg.overwrite_existing)
+    maybe_write_to_file(tcfg, &output_path, output)
 }
 
 /// If we translate variadic functions, the output will only compile
@@ -269,7 +435,7 @@ fn emit_lib_rs(
 fn emit_rust_toolchain(tcfg: &TranspilerConfig, build_dir: &Path) {
     let output_path = build_dir.join("rust-toolchain");
     let output = include_str!("../../rust-toolchain").to_string();
-    maybe_write_to_file(&output_path, output, tcfg.overwrite_existing);
+    maybe_write_to_file(tcfg, &output_path, output);
 }
 
 fn emit_cargo_toml<'lcmd>(
@@ -294,6 +460,21 @@ fn emit_cargo_toml<'lcmd>(
             ModuleSubset::Binaries,
         );
         let dependencies = convert_dependencies_list(ccfg.crates.clone());
+        // Member crates are siblings of the crates they depend on, and the crate at the root of
+        // the workspace is their parent
+        let crate_deps = ccfg
+            .link_cmd
+            .deps
+            .iter()
+            .map(|name| {
+                let path = if ccfg.link_cmd.top_level {
+                    name.clone()
+                } else {
+                    format!("../{}", name)
+                };
+                json!({ "name": name, "path": path })
+            })
+            .collect::<Vec<_>>();
         let crate_json = json!({
             "crate_name": ccfg.crate_name,
             "crate_rust_name": ccfg.crate_name.replace('-', "_"),
@@ -302,6 +483,8 @@ fn emit_cargo_toml<'lcmd>(
             "lib_rs_file": get_lib_rs_file_name(tcfg),
             "binaries": binaries,
             "dependencies": dependencies,
+            "crate_deps": crate_deps,
+            "has_c_sources": !ccfg.c_inputs.is_empty() || !ccfg.ffi_sources.is_empty(),
         });
         json.as_object_mut().unwrap().extend(
             crate_json
@@ -315,23 +498,59 @@ fn emit_cargo_toml<'lcmd>(
     let file_name = "Cargo.toml";
     let output_path = build_dir.join(file_name);
     let output = reg.render(file_name, &json).unwrap();
-    maybe_write_to_file(&output_path, output, tcfg.overwrite_existing);
+    maybe_write_to_file(tcfg, &output_path, output);
 }
 
-fn maybe_write_to_file(output_path: &Path, output: String, overwrite: bool) -> Option<PathBuf> {
-    if output_path.exists() && !overwrite {
+fn maybe_write_to_file(
+    tcfg: &TranspilerConfig,
+    output_path: &Path,
+    output: String,
+) -> Option<PathBuf> {
+    // A dry run diffs existing files instead of skipping them
+    if output_path.exists() && !tcfg.overwrite_existing && tcfg.dry_run.is_none() {
         eprintln!("Skipping existing file {}", output_path.display());
         return None;
     }
 
-    let mut file = match File::create(&output_path) {
-        Ok(file) => file,
-        Err(e) => panic!("Unable to open file for writing: {}", e),
-    };
-    match file.write_all(output.as_bytes()) {
-        Ok(()) => (),
-        Err(e) => panic!("Unable to write translation to file: {}", e),
-    };
+    if let Err(e) = write_if_changed(tcfg.dry_run, output_path, &output) {
+        panic!("Unable to write translation to file: {}", e);
+    }
 
     Some(PathBuf::from(output_path))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn link_search_before_link_lib() {
+        // As captured from `cc -o prog main.o -L/opt/zlib/lib -lz -L/opt/ssl/lib -lssl -lcrypto`
+        let record = "d6:inputsl11:/src/main.oe8:lib_dirsl13:/opt/zlib/lib12:/opt/ssl/libe\
+                      4:libsl1:z3:ssl6:cryptoe4:type3:exee";
+        let link_cmd: LinkCmd = serde_bencode::from_str(record).unwrap();
+
+        let mut reg = Handlebars::new();
+        reg.register_template_string("build.rs", include_str!("build.rs.hbs"))
+            .unwrap();
+        let build_dir = tempfile::tempdir().unwrap();
+        let tcfg = TranspilerConfig::default();
+        let path = emit_build_rs(&tcfg, &reg, build_dir.path(), &link_cmd, &[], &[]).unwrap();
+
+        let build_rs = fs::read_to_string(path).unwrap();
+        let links = build_rs
+            .lines()
+            .filter(|line| line.contains("cargo:rustc-link"))
+            .map(str::trim)
+            .collect::<Vec<_>>();
+        let main_links = [
+            "println!(\"cargo:rustc-link-search=native={}\", \"/opt/zlib/lib\");",
+            "println!(\"cargo:rustc-link-search=native={}\", \"/opt/ssl/lib\");",
+            "println!(\"cargo:rustc-link-lib=z\");",
+            "println!(\"cargo:rustc-link-lib=ssl\");",
+            "println!(\"cargo:rustc-link-lib=crypto\");",
+        ];
+        // Once in the `main` of unix and once in that of macOS
+        assert_eq!(links, [main_links, main_links].concat());
+    }
+}
@@ -0,0 +1,258 @@
+//! Bazel targets.
+//!
+//! Bazel doesn't write a compilation database, so one is reconstructed from the compile actions
+//! of the target and its dependencies listed by `bazel aquery`. The actions run in the execution
+//! root, and refer to generated headers under `bazel-out`, so those are built before translating.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use failure::{format_err, Error};
+use serde_derive::Deserialize;
+
+use super::{run, write_compile_commands, BuildOptions, CompileEntry, LinkType, Project, Target};
+
+/// The directory the compilation database is written to, relative to the workspace
+const DEFAULT_BUILD_DIR: &str = "build-c2rust";
+
+/// Flags of the GCC toolchain Bazel may configure that clang rejects
+const GCC_ONLY_FLAGS: &[&str] = &[
+    "-fno-canonical-system-headers",
+    "-fno-tree-loop-distribute-patterns",
+];
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ActionGraph {
+    #[serde(default)]
+    actions: Vec<Action>,
+    #[serde(default)]
+    targets: Vec<ActionTarget>,
+    #[serde(default)]
+    rule_classes: Vec<RuleClass>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Action {
+    target_id: u32,
+    mnemonic: String,
+    #[serde(default)]
+    arguments: Vec<String>,
+    #[serde(default)]
+    param_files: Vec<ParamFile>,
+}
+
+#[derive(Deserialize)]
+struct ParamFile {
+    #[serde(default)]
+    arguments: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ActionTarget {
+    id: u32,
+    label: String,
+    rule_class_id: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct RuleClass {
+    id: u32,
+    name: String,
+}
+
+/// Reconstruct the compilation database of the Bazel target `label` and its dependencies
+pub fn load_project(label: &str, options: &BuildOptions) -> Result<Project, Error> {
+    let info = |key: &str| -> Result<PathBuf, Error> {
+        let value = run(Command::new("bazel").arg("info").arg(key))?;
+        Ok(PathBuf::from(value.trim()))
+    };
+    let workspace = info("workspace")?;
+    let execution_root = info("execution_root")?;
+
+    // Generate the headers the compile actions include
+    run(Command::new("bazel")
+        .arg("build")
+        .arg("--output_groups=compilation_prerequisites_INTERNAL_")
+        .args(&options.args)
+        .arg(label))?;
+
+    let query = format!("mnemonic('CppCompile|CppLink|CppArchive', deps({}))", label);
+    let graph = run(Command::new("bazel")
+        .arg("aquery")
+        .arg("--output=jsonproto")
+        .arg("--include_param_files")
+        .args(&options.args)
+        .arg(query))?;
+    let graph: ActionGraph = serde_json::from_str(&graph)?;
+
+    let (entries, targets) = compile_entries(&graph, &execution_root);
+    if entries.is_empty() {
+        return Err(format_err!("{} doesn't compile any C files", label));
+    }
+
+    let build_dir = options
+        .build_dir
+        .clone()
+        .unwrap_or_else(|| workspace.join(DEFAULT_BUILD_DIR));
+    let compile_commands = write_compile_commands(&build_dir, &entries)?;
+    Ok(Project {
+        compile_commands,
+        targets,
+    })
+}
+
+/// The compilation database entries of the C compile actions of `graph`, and the libraries and
+/// binaries they are compiled into
+fn compile_entries(
+    graph: &ActionGraph,
+    execution_root: &Path,
+) -> (Vec<CompileEntry>, Vec<Target>) {
+    let rule_classes = graph
+        .rule_classes
+        .iter()
+        .map(|rule_class| (rule_class.id, rule_class.name.as_str()))
+        .collect::<HashMap<_, _>>();
+
+    let mut entries = vec![];
+    let mut targets: Vec<Target> = vec![];
+    for target in &graph.targets {
+        let r#type = match target.rule_class_id.and_then(|id| rule_classes.get(&id)) {
+            Some(&"cc_binary") => LinkType::Exe,
+            Some(&"cc_library") => LinkType::Static,
+            // Tests and other rules aren't translated into crates of their own
+            _ => continue,
+        };
+
+        let mut sources = vec![];
+        let mut libs = vec![];
+        let mut lib_dirs = vec![];
+        let actions = graph.actions.iter().filter(|action| action.target_id == target.id);
+        for action in actions {
+            let arguments = action
+                .arguments
+                .iter()
+                .chain(action.param_files.iter().flat_map(|file| &file.arguments));
+            match action.mnemonic.as_str() {
+                "CppCompile" => {
+                    let arguments = arguments
+                        .filter(|arg| !GCC_ONLY_FLAGS.contains(&arg.as_str()))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    let file = match argument_of(&arguments, "-c") {
+                        Some(file) if file.ends_with(".c") => PathBuf::from(file),
+                        _ => continue,
+                    };
+                    let source = execution_root.join(&file);
+                    sources.push(source.canonicalize().unwrap_or(source));
+                    entries.push(CompileEntry {
+                        directory: execution_root.to_owned(),
+                        file,
+                        output: argument_of(&arguments, "-o").map(String::from),
+                        arguments,
+                    });
+                }
+                "CppLink" => {
+                    for arg in arguments {
+                        if let Some(lib) = arg.strip_prefix("-l") {
+                            libs.push(lib.to_owned());
+                        } else if let Some(dir) = arg.strip_prefix("-L") {
+                            lib_dirs.push(execution_root.join(dir));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if sources.is_empty() {
+            continue;
+        }
+        libs.dedup();
+        lib_dirs.dedup();
+
+        // Labels like `//src/util:util` become crate names like `src_util_util`
+        let name = target
+            .label
+            .trim_start_matches('@')
+            .trim_start_matches("//")
+            .replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+        targets.push(Target {
+            name,
+            r#type,
+            sources,
+            libs,
+            lib_dirs,
+            dependencies: vec![],
+        });
+    }
+    (entries, targets)
+}
+
+/// The argument following the flag `flag`
+fn argument_of<'a>(arguments: &'a [String], flag: &str) -> Option<&'a str> {
+    let idx = arguments.iter().position(|arg| arg == flag)?;
+    arguments.get(idx + 1).map(String::as_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compile_actions() {
+        let graph: ActionGraph = serde_json::from_str(
+            r#"{
+                "actions": [
+                    {
+                        "targetId": 1,
+                        "mnemonic": "CppCompile",
+                        "arguments": ["/usr/bin/gcc", "-fno-canonical-system-headers",
+                            "-iquote", "bazel-out/k8-fastbuild/bin", "-c", "src/util.c",
+                            "-o", "bazel-out/k8-fastbuild/bin/src/_objs/util/util.o"]
+                    },
+                    {
+                        "targetId": 2,
+                        "mnemonic": "CppLink",
+                        "arguments": ["/usr/bin/gcc", "@bazel-out/main-2.params"],
+                        "paramFiles": [{"arguments": ["-o", "main", "-lm", "-lpthread"]}]
+                    },
+                    {
+                        "targetId": 2,
+                        "mnemonic": "CppCompile",
+                        "arguments": ["/usr/bin/gcc", "-c", "src/main.c", "-o", "main.o"]
+                    }
+                ],
+                "targets": [
+                    {"id": 1, "label": "//src:util", "ruleClassId": 1},
+                    {"id": 2, "label": "//src:main", "ruleClassId": 2}
+                ],
+                "ruleClasses": [{"id": 1, "name": "cc_library"}, {"id": 2, "name": "cc_binary"}]
+            }"#,
+        )
+        .unwrap();
+        let (entries, targets) = compile_entries(&graph, Path::new("/nonexistent/execroot"));
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].file, PathBuf::from("src/util.c"));
+        assert_eq!(entries[0].directory, PathBuf::from("/nonexistent/execroot"));
+        assert_eq!(entries[0].arguments[1], "-iquote");
+        assert_eq!(
+            entries[0].output.as_deref(),
+            Some("bazel-out/k8-fastbuild/bin/src/_objs/util/util.o")
+        );
+
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[0].name, "src_util");
+        assert_eq!(targets[0].r#type, LinkType::Static);
+        assert_eq!(targets[1].name, "src_main");
+        assert_eq!(targets[1].r#type, LinkType::Exe);
+        assert_eq!(targets[1].libs, vec!["m", "pthread"]);
+        assert_eq!(
+            targets[1].sources,
+            vec![PathBuf::from("/nonexistent/execroot/src/main.c")]
+        );
+    }
+}
@@ -0,0 +1,51 @@
+//! Buck2 targets.
+//!
+//! The compilation database of a target is generated by the BXL script shipped with the Buck2
+//! prelude. Buck doesn't describe how the target is linked along with it, so the target is
+//! translated into a single crate.
+
+use std::path::PathBuf;
+use std::process::Command;
+
+use failure::{format_err, Error};
+
+use super::{run, BuildOptions, Project};
+
+/// The BXL script of the prelude generating compilation databases
+const COMPILATION_DATABASE_BXL: &str = "prelude//cxx/tools/compilation_database.bxl:generate";
+
+/// Generate the compilation database of the Buck2 target `label`
+pub fn load_project(label: &str, options: &BuildOptions) -> Result<Project, Error> {
+    let root = run(Command::new("buck2").arg("root").arg("--kind=project"))?;
+    let root = PathBuf::from(root.trim());
+
+    let output = run(Command::new("buck2")
+        .arg("bxl")
+        .args(&options.args)
+        .arg(COMPILATION_DATABASE_BXL)
+        .arg("--")
+        .arg("--targets")
+        .arg(label))?;
+    // The script prints the path of the database it generated
+    let compile_commands = output
+        .lines()
+        .last()
+        .map(|path| root.join(path.trim()))
+        .filter(|path| path.is_file())
+        .ok_or_else(|| format_err!("buck2 did not generate a compilation database"))?;
+
+    // The database is in `buck-out`; translate into the build directory instead, if given
+    let compile_commands = match options.build_dir {
+        Some(ref build_dir) => {
+            std::fs::create_dir_all(build_dir)?;
+            let copy = build_dir.join("compile_commands.json");
+            std::fs::copy(&compile_commands, &copy)?;
+            copy
+        }
+        None => compile_commands,
+    };
+    Ok(Project {
+        compile_commands,
+        targets: vec![],
+    })
+}
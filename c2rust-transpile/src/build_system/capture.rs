@@ -0,0 +1,396 @@
+//! Capturing the compile commands of arbitrary builds, such as those of Autotools projects.
+//!
+//! The build runs with a directory of compiler and archiver shims at the front of its `PATH`,
+//! each a link to the capturing executable, and `CC` pointing at the `cc` shim unless it's
+//! already set. Invoked through a shim, the executable records its arguments in the log named by
+//! `LOG_VAR` and runs the tool it stands in for. Once the build is over, the invocations in the
+//! log become a `compile_commands.json` with one entry per C source, keeping the defines and
+//! include paths of each file, and one link entry per executable, shared or static library, with
+//! the objects and libraries linked into it. Configure tests build files that are gone by then,
+//! and are left out.
+
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+use failure::{format_err, Error};
+use serde_derive::{Deserialize, Serialize};
+
+use super::{write_compile_commands, CompileEntry};
+
+/// The environment variable naming the log of compiler invocations
+pub const LOG_VAR: &str = "C2RUST_CAPTURE_LOG";
+
+/// The environment variable naming the directory of compiler shims
+const SHIMS_VAR: &str = "C2RUST_CAPTURE_SHIMS";
+
+/// The compilers shims are installed for
+const COMPILERS: &[&str] = &["cc", "gcc", "clang", "c89", "c99"];
+
+/// The archivers shims are installed for, to record the static libraries of the build
+const ARCHIVERS: &[&str] = &["ar"];
+
+/// Compiler flags followed by a value that isn't a source file
+const FLAGS_WITH_VALUES: &[&str] = &[
+    "-o", "-MF", "-MT", "-MQ", "-include", "-imacros", "-x", "-I", "-isystem", "-iquote", "-D",
+    "-U",
+];
+
+/// The prefix of the file of compilation database entries describing link commands, followed by
+/// the bencoded `LinkRecord`
+const LINK_PREFIX: &str = "/c2rust/link/";
+
+#[derive(Serialize, Deserialize)]
+struct Invocation {
+    directory: PathBuf,
+    arguments: Vec<String>,
+}
+
+/// A link command, as read back into a `LinkCmd` by `compile_cmds`. Bencoded dictionaries have
+/// sorted keys, and so do its fields.
+#[derive(Serialize)]
+struct LinkRecord {
+    inputs: Vec<String>,
+    lib_dirs: Vec<String>,
+    libs: Vec<String>,
+    r#type: &'static str,
+}
+
+/// Was this executable invoked through a compiler shim?
+pub fn is_shim_invocation() -> bool {
+    let name = env::args_os().next().map(PathBuf::from);
+    let name = name.as_ref().and_then(|name| name.file_name()?.to_str());
+    env::var_os(LOG_VAR).is_some()
+        && name.map_or(false, |name| {
+            COMPILERS.contains(&name) || ARCHIVERS.contains(&name)
+        })
+}
+
+/// Record the invocation of the compiler or archiver this executable stands in for and run it,
+/// exiting with its status
+pub fn run_shim() -> ! {
+    let mut args = env::args().collect::<Vec<_>>();
+    let name = Path::new(&args[0]).file_name().unwrap().to_owned();
+    let compiler = find_compiler(&name).unwrap_or_else(|| {
+        eprintln!("c2rust capture-build: no {:?} in PATH", name);
+        process::exit(127)
+    });
+    args[0] = compiler.display().to_string();
+
+    let invocation = Invocation {
+        directory: env::current_dir().expect("Unable to get the working directory"),
+        arguments: args,
+    };
+    if let Some(log) = env::var_os(LOG_VAR) {
+        // Each invocation is a single appended line, so parallel builds don't interleave them
+        let line = serde_json::to_string(&invocation).unwrap() + "\n";
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log)
+            .and_then(|mut file| file.write_all(line.as_bytes()));
+        if let Err(e) = written {
+            eprintln!("c2rust capture-build: unable to record invocation: {}", e);
+        }
+    }
+
+    let status = Command::new(&compiler)
+        .args(&invocation.arguments[1..])
+        .status()
+        .unwrap_or_else(|e| {
+            eprintln!("c2rust capture-build: unable to run {}: {}", compiler.display(), e);
+            process::exit(127)
+        });
+    process::exit(status.code().unwrap_or(1))
+}
+
+/// The compiler `name` in `PATH`, skipping the shims
+fn find_compiler(name: &std::ffi::OsStr) -> Option<PathBuf> {
+    let shims = env::var_os(SHIMS_VAR).map(PathBuf::from);
+    env::split_paths(&env::var_os("PATH")?)
+        .filter(|dir| Some(dir) != shims.as_ref())
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Run the build `command`, writing the compile commands it runs to `compile_commands.json` in
+/// `output_dir`. Returns the exit code of the build.
+pub fn capture(command: &[String], output_dir: &Path) -> Result<i32, Error> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| format_err!("No build command given"))?;
+
+    let shims = output_dir.join(".c2rust-capture");
+    fs::create_dir_all(&shims)?;
+    let exe = env::current_exe()?;
+    for tool in COMPILERS.iter().chain(ARCHIVERS) {
+        let shim = shims.join(tool);
+        let _ = fs::remove_file(&shim);
+        link(&exe, &shim)?;
+    }
+    let log = shims.join("invocations.jsonl");
+    fs::write(&log, "")?;
+
+    let mut path = vec![shims.clone()];
+    path.extend(env::split_paths(&env::var_os("PATH").unwrap_or_default()));
+    let mut build = Command::new(program);
+    build
+        .args(args)
+        .env("PATH", env::join_paths(path)?)
+        .env(LOG_VAR, &log)
+        .env(SHIMS_VAR, &shims);
+    if env::var_os("CC").is_none() {
+        build.env("CC", OsString::from("cc"));
+    }
+    let status = build
+        .status()
+        .map_err(|e| format_err!("Unable to run {}: {}", program, e))?;
+
+    let invocations = fs::read_to_string(&log)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<Invocation>, _>>()?;
+    let entries = database_entries(&invocations)
+        .into_iter()
+        .filter(|entry| match entry.file.starts_with(LINK_PREFIX) {
+            true => entry.output.as_ref().map_or(false, |output| Path::new(output).is_file()),
+            false => entry.directory.join(&entry.file).is_file(),
+        })
+        .collect::<Vec<_>>();
+    fs::remove_dir_all(&shims)?;
+
+    write_compile_commands(output_dir, &entries)?;
+    Ok(status.code().unwrap_or(1))
+}
+
+#[cfg(unix)]
+fn link(exe: &Path, shim: &Path) -> Result<(), Error> {
+    Ok(std::os::unix::fs::symlink(exe, shim)?)
+}
+
+#[cfg(not(unix))]
+fn link(exe: &Path, shim: &Path) -> Result<(), Error> {
+    Ok(fs::copy(exe, shim).map(|_| ())?)
+}
+
+/// One compilation database entry per C source compiled and per file linked by `invocations`,
+/// keeping the last invocation building the same output
+fn database_entries(invocations: &[Invocation]) -> Vec<CompileEntry> {
+    let mut entries: Vec<CompileEntry> = vec![];
+    let mut index = HashMap::new();
+    for (key, entry) in invocations.iter().flat_map(invocation_entries) {
+        match index.get(&key) {
+            Some(&idx) => entries[idx] = entry,
+            None => {
+                index.insert(key, entries.len());
+                entries.push(entry);
+            }
+        }
+    }
+    entries
+}
+
+/// The compilation database entries of an invocation, keyed by their source and output. Paths
+/// of objects and libraries are made absolute, so that links find the objects of compiles run
+/// in other directories.
+fn invocation_entries(invocation: &Invocation) -> Vec<((PathBuf, PathBuf), CompileEntry)> {
+    let args = &invocation.arguments;
+    let tool = Path::new(&args[0]).file_name().and_then(|name| name.to_str());
+    if tool.map_or(false, |tool| ARCHIVERS.contains(&tool)) {
+        return archive_entry(invocation).into_iter().collect();
+    }
+    // Preprocessing and dependency generation alone don't compile anything
+    if args.iter().any(|arg| arg == "-E" || arg == "-M" || arg == "-MM") {
+        return vec![];
+    }
+
+    let abs = |path: &str| invocation.directory.join(path).display().to_string();
+    let mut sources = vec![];
+    let mut flags = vec![];
+    let mut output = None;
+    let mut record = LinkRecord {
+        inputs: vec![],
+        lib_dirs: vec![],
+        libs: vec![],
+        r#type: if args.iter().any(|arg| arg == "-shared") {
+            "shared"
+        } else {
+            "exe"
+        },
+    };
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        // Link flags are left out of the compiles
+        if arg == "-o" {
+            output = iter.next().cloned();
+        } else if arg == "-l" {
+            record.libs.extend(iter.next().cloned());
+        } else if arg == "-L" {
+            record.lib_dirs.extend(iter.next().map(|dir| abs(dir)));
+        } else if let Some(lib) = arg.strip_prefix("-l") {
+            record.libs.push(lib.to_owned());
+        } else if let Some(dir) = arg.strip_prefix("-L") {
+            record.lib_dirs.push(abs(dir));
+        } else if FLAGS_WITH_VALUES.contains(&arg.as_str()) {
+            flags.push(arg.clone());
+            flags.extend(iter.next().cloned());
+        } else if arg.ends_with(".c") && !arg.starts_with('-') {
+            sources.push(arg.clone());
+        } else if !arg.starts_with('-') {
+            // Objects and libraries
+            record.inputs.push(abs(arg));
+        } else if arg != "-c" {
+            if arg == "-pthread" {
+                record.libs.push("pthread".to_owned());
+            }
+            flags.push(arg.clone());
+        }
+    }
+
+    // The output only belongs to the source if it's the only one compiled to an object
+    let compiles_only = args.iter().any(|arg| arg == "-c");
+    let object = output.clone().filter(|_| compiles_only && sources.len() == 1);
+    let mut res = vec![];
+    for source in sources {
+        let object = abs(&object.clone().unwrap_or_else(|| {
+            let stem = Path::new(&source).file_stem().unwrap().to_string_lossy();
+            format!("{}.o", stem)
+        }));
+        let mut arguments = vec![args[0].clone()];
+        arguments.extend(flags.iter().cloned());
+        arguments.extend(vec![
+            "-c".to_owned(),
+            source.clone(),
+            "-o".to_owned(),
+            object.clone(),
+        ]);
+        let key = (invocation.directory.join(&source), PathBuf::from(&object));
+        res.push((
+            key,
+            CompileEntry {
+                directory: invocation.directory.clone(),
+                file: PathBuf::from(&source),
+                arguments,
+                output: Some(object.clone()),
+            },
+        ));
+        record.inputs.push(object);
+    }
+
+    if !compiles_only {
+        if let Some(output) = output {
+            res.push(link_entry(invocation, &record, abs(&output)));
+        }
+    }
+    res
+}
+
+/// The link entry of an archiver invocation adding objects to a static library
+fn archive_entry(invocation: &Invocation) -> Option<((PathBuf, PathBuf), CompileEntry)> {
+    let mut args = invocation.arguments.iter().skip(1);
+    // Modifiers come with the operation, or as separate flags before the archive
+    let operation = args.next()?.trim_start_matches('-');
+    if !operation.contains(|c: char| c == 'r' || c == 'q') {
+        return None;
+    }
+    let mut args = args.skip_while(|arg| arg.starts_with('-'));
+    let abs = |path: &str| invocation.directory.join(path).display().to_string();
+    let archive = abs(args.next()?);
+    let record = LinkRecord {
+        inputs: args.map(|arg| abs(arg)).collect(),
+        lib_dirs: vec![],
+        libs: vec![],
+        r#type: "static",
+    };
+    Some(link_entry(invocation, &record, archive))
+}
+
+/// The compilation database entry of the link command `record` producing `output`
+fn link_entry(
+    invocation: &Invocation,
+    record: &LinkRecord,
+    output: String,
+) -> ((PathBuf, PathBuf), CompileEntry) {
+    let record = serde_bencode::to_string(record).expect("Unable to encode link command");
+    let entry = CompileEntry {
+        directory: invocation.directory.clone(),
+        file: PathBuf::from(format!("{}{}", LINK_PREFIX, record)),
+        arguments: invocation.arguments.clone(),
+        output: Some(output.clone()),
+    };
+    ((PathBuf::from(LINK_PREFIX), PathBuf::from(output)), entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invocation(directory: &str, args: &str) -> Invocation {
+        Invocation {
+            directory: PathBuf::from(directory),
+            arguments: args.split_whitespace().map(String::from).collect(),
+        }
+    }
+
+    #[test]
+    fn entries() {
+        let invocations = vec![
+            invocation("/src", "/usr/bin/gcc -E -P conftest.c"),
+            invocation("/src", "/usr/bin/gcc -DHAVE_CONFIG_H -I . -O2 -c -o util.o util.c"),
+            invocation("/src/lib", "/usr/bin/gcc -DNDEBUG -o prog main.c extra.c -lm"),
+            invocation("/src", "/usr/bin/gcc -DHAVE_CONFIG_H -I . -O0 -c -o util.o util.c"),
+            invocation("/src", "/usr/bin/ar cru libutil.a util.o"),
+            invocation("/src", "/usr/bin/gcc -shared -o libz2.so util.o -L /opt/lib -lz"),
+        ];
+        let entries = database_entries(&invocations);
+        assert_eq!(entries.len(), 6);
+
+        assert_eq!(entries[0].file, PathBuf::from("util.c"));
+        assert_eq!(
+            entries[0].arguments,
+            vec![
+                "/usr/bin/gcc",
+                "-DHAVE_CONFIG_H",
+                "-I",
+                ".",
+                "-O0",
+                "-c",
+                "util.c",
+                "-o",
+                "/src/util.o"
+            ]
+        );
+
+        assert_eq!(entries[1].directory, PathBuf::from("/src/lib"));
+        assert_eq!(entries[1].file, PathBuf::from("main.c"));
+        assert_eq!(entries[1].output.as_deref(), Some("/src/lib/main.o"));
+        assert_eq!(
+            entries[2].arguments,
+            vec!["/usr/bin/gcc", "-DNDEBUG", "-c", "extra.c", "-o", "/src/lib/extra.o"]
+        );
+
+        let link = |entry: &CompileEntry| -> String {
+            let file = entry.file.to_str().unwrap();
+            file.strip_prefix(LINK_PREFIX).unwrap().to_owned()
+        };
+        assert_eq!(entries[3].output.as_deref(), Some("/src/lib/prog"));
+        assert_eq!(
+            link(&entries[3]),
+            "d6:inputsl15:/src/lib/main.o16:/src/lib/extra.oe\
+             8:lib_dirsle4:libsl1:me4:type3:exee"
+        );
+        assert_eq!(entries[4].output.as_deref(), Some("/src/libutil.a"));
+        assert_eq!(
+            link(&entries[4]),
+            "d6:inputsl11:/src/util.oe8:lib_dirsle4:libsle4:type6:statice"
+        );
+        assert_eq!(
+            link(&entries[5]),
+            "d6:inputsl11:/src/util.oe8:lib_dirsl8:/opt/libe4:libsl1:ze4:type6:sharede"
+        );
+    }
+}
@@ -0,0 +1,311 @@
+//! CMake projects.
+//!
+//! A project is configured with `CMAKE_EXPORT_COMPILE_COMMANDS` after requesting the code model
+//! from the [file API](https://cmake.org/cmake/help/latest/manual/cmake-file-api.7.html), which
+//! describes the targets of the project, their sources and what they link.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use failure::{format_err, Error};
+use serde::de::DeserializeOwned;
+use serde_derive::Deserialize;
+
+use super::{library_file, BuildOptions, LinkType, Project, Target};
+
+/// The build directory used when configuring a source directory, relative to it
+const DEFAULT_BUILD_DIR: &str = "build-c2rust";
+
+#[derive(Deserialize)]
+struct Index {
+    reply: HashMap<String, ReplyFile>,
+}
+
+#[derive(Deserialize)]
+struct ReplyFile {
+    #[serde(rename = "jsonFile")]
+    json_file: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct Codemodel {
+    paths: Paths,
+    configurations: Vec<Configuration>,
+}
+
+#[derive(Deserialize)]
+struct Paths {
+    source: PathBuf,
+    build: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct Configuration {
+    targets: Vec<TargetRef>,
+}
+
+#[derive(Deserialize)]
+struct TargetRef {
+    id: String,
+    #[serde(rename = "jsonFile")]
+    json_file: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct TargetReply {
+    name: String,
+    r#type: String,
+    #[serde(default)]
+    artifacts: Vec<Artifact>,
+    #[serde(default)]
+    sources: Vec<Source>,
+    #[serde(default, rename = "compileGroups")]
+    compile_groups: Vec<CompileGroup>,
+    #[serde(default)]
+    link: Option<Link>,
+    #[serde(default)]
+    dependencies: Vec<Dependency>,
+}
+
+#[derive(Deserialize)]
+struct Artifact {
+    path: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct Source {
+    path: PathBuf,
+    #[serde(rename = "compileGroupIndex")]
+    compile_group_index: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct CompileGroup {
+    language: String,
+}
+
+#[derive(Deserialize)]
+struct Link {
+    #[serde(default, rename = "commandFragments")]
+    command_fragments: Vec<Fragment>,
+}
+
+#[derive(Deserialize)]
+struct Fragment {
+    fragment: String,
+    role: String,
+}
+
+#[derive(Deserialize)]
+struct Dependency {
+    id: String,
+}
+
+/// Configure the CMake project at `path`, which is either its `CMakeLists.txt`, its source
+/// directory or an existing build directory, or return `None` if it is none of them.
+pub fn load_project(path: &Path, options: &BuildOptions) -> Result<Option<Project>, Error> {
+    let mut cmake = Command::new("cmake");
+    let build_dir = if path.is_dir() && path.join("CMakeCache.txt").is_file() {
+        cmake.arg(path);
+        path.to_owned()
+    } else {
+        let source_dir = if path.file_name().map_or(false, |name| name == "CMakeLists.txt") {
+            path.parent().unwrap_or_else(|| Path::new("."))
+        } else if path.is_dir() && path.join("CMakeLists.txt").is_file() {
+            path
+        } else {
+            return Ok(None);
+        };
+        let build_dir = options
+            .build_dir
+            .clone()
+            .unwrap_or_else(|| source_dir.join(DEFAULT_BUILD_DIR));
+        cmake.arg("-S").arg(source_dir).arg("-B").arg(&build_dir);
+        build_dir
+    };
+
+    // Ask for the code model before configuring
+    let query_dir = build_dir.join(".cmake/api/v1/query");
+    fs::create_dir_all(&query_dir)?;
+    File::create(query_dir.join("codemodel-v2"))?;
+
+    let status = cmake
+        .arg("-DCMAKE_EXPORT_COMPILE_COMMANDS=ON")
+        .args(&options.args)
+        .status()
+        .map_err(|e| format_err!("Unable to run cmake: {}", e))?;
+    if !status.success() {
+        return Err(format_err!("cmake failed to configure {}", path.display()));
+    }
+
+    let compile_commands = build_dir.join("compile_commands.json");
+    if !compile_commands.is_file() {
+        return Err(format_err!(
+            "cmake did not generate {}; only the Makefile and Ninja generators support it",
+            compile_commands.display()
+        ));
+    }
+
+    let targets = read_targets(&build_dir.join(".cmake/api/v1/reply"))?;
+    Ok(Some(Project {
+        compile_commands,
+        targets,
+    }))
+}
+
+/// Read the targets of the project from the code model in `reply_dir`
+fn read_targets(reply_dir: &Path) -> Result<Vec<Target>, Error> {
+    // There is one index per run of cmake, and the latest one sorts last
+    let index = fs::read_dir(reply_dir)?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.starts_with("index-") && name.ends_with(".json"))
+        .max()
+        .ok_or_else(|| format_err!("cmake did not reply to the code model query"))?;
+    let index: Index = read_json(&reply_dir.join(index))?;
+    let codemodel = index
+        .reply
+        .get("codemodel-v2")
+        .ok_or_else(|| format_err!("cmake did not reply to the code model query"))?;
+    let codemodel: Codemodel = read_json(&reply_dir.join(&codemodel.json_file))?;
+
+    // Targets are the same in every configuration, except for their compile flags
+    let configuration = match codemodel.configurations.first() {
+        Some(configuration) => configuration,
+        None => return Ok(vec![]),
+    };
+    let mut replies = vec![];
+    for target in &configuration.targets {
+        let reply: TargetReply = read_json(&reply_dir.join(&target.json_file))?;
+        replies.push((target.id.as_str(), reply));
+    }
+
+    // Artifacts of the project are linked by path, and are its targets rather than libraries
+    let artifacts = replies
+        .iter()
+        .flat_map(|(_, reply)| &reply.artifacts)
+        .map(|artifact| codemodel.paths.build.join(&artifact.path))
+        .collect::<HashSet<_>>();
+    let names = replies
+        .iter()
+        .map(|&(id, ref reply)| (id, reply.name.as_str()))
+        .collect::<HashMap<_, _>>();
+
+    let mut targets = vec![];
+    for (_, reply) in &replies {
+        let r#type = match reply.r#type.as_str() {
+            "EXECUTABLE" => LinkType::Exe,
+            "STATIC_LIBRARY" => LinkType::Static,
+            "SHARED_LIBRARY" | "MODULE_LIBRARY" => LinkType::Shared,
+            // Object, interface and utility libraries don't produce anything to translate
+            _ => continue,
+        };
+
+        let sources = reply
+            .sources
+            .iter()
+            .filter(|source| {
+                source.compile_group_index.map_or(false, |idx| {
+                    reply.compile_groups.get(idx).map_or(false, |group| group.language == "C")
+                })
+            })
+            .map(|source| {
+                let path = codemodel.paths.source.join(&source.path);
+                path.canonicalize().unwrap_or(path)
+            })
+            .collect::<Vec<_>>();
+        if sources.is_empty() {
+            continue;
+        }
+
+        let fragments = reply
+            .link
+            .iter()
+            .flat_map(|link| &link.command_fragments)
+            .filter(|fragment| fragment.role == "libraries" || fragment.role == "libraryPath")
+            .map(|fragment| fragment.fragment.as_str());
+        let (libs, lib_dirs) = link_libraries(fragments, &codemodel.paths.build, &artifacts);
+
+        targets.push(Target {
+            name: reply.name.clone(),
+            r#type,
+            sources,
+            libs,
+            lib_dirs,
+            dependencies: reply
+                .dependencies
+                .iter()
+                .filter_map(|dependency| names.get(dependency.id.as_str()))
+                .map(|&name| name.to_owned())
+                .collect(),
+        });
+    }
+    Ok(targets)
+}
+
+/// The libraries and library directories named by the link command `fragments` of a target,
+/// leaving out the `artifacts` of the project itself
+fn link_libraries<'a>(
+    fragments: impl Iterator<Item = &'a str>,
+    build_dir: &Path,
+    artifacts: &HashSet<PathBuf>,
+) -> (Vec<String>, Vec<PathBuf>) {
+    let mut libs = vec![];
+    let mut lib_dirs = vec![];
+    for fragment in fragments.flat_map(|fragment| fragment.split_whitespace()) {
+        if let Some(lib) = fragment.strip_prefix("-l") {
+            libs.push(lib.to_owned());
+        } else if let Some(dir) = fragment.strip_prefix("-L") {
+            lib_dirs.push(PathBuf::from(dir));
+        } else if fragment == "-pthread" {
+            libs.push("pthread".to_owned());
+        } else if fragment.starts_with('-') {
+            // Linker flags such as `-Wl,-rpath,...`
+        } else if artifacts.iter().any(|artifact| artifact.ends_with(fragment)) {
+            // Linked from the translation of the target instead
+        } else if let Some((lib, dir)) = library_file(Path::new(fragment)) {
+            libs.push(lib);
+            if !dir.as_os_str().is_empty() {
+                lib_dirs.push(build_dir.join(dir));
+            }
+        }
+    }
+    libs.dedup();
+    lib_dirs.dedup();
+    (libs, lib_dirs)
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T, Error> {
+    let file = File::open(path).map_err(|e| format_err!("{}: {}", path.display(), e))?;
+    Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn libraries() {
+        let build_dir = Path::new("/build");
+        let artifacts = vec![PathBuf::from("/build/src/libutil.a")]
+            .into_iter()
+            .collect();
+        let fragments = vec![
+            "-Wl,-rpath,/opt/lib",
+            "src/libutil.a",
+            "/usr/lib/x86_64-linux-gnu/libz.so",
+            "-lm -pthread",
+            "-L/opt/lib",
+        ];
+        let (libs, lib_dirs) = link_libraries(fragments.into_iter(), build_dir, &artifacts);
+        assert_eq!(libs, vec!["z", "m", "pthread"]);
+        assert_eq!(
+            lib_dirs,
+            vec![
+                PathBuf::from("/usr/lib/x86_64-linux-gnu"),
+                PathBuf::from("/opt/lib")
+            ]
+        );
+    }
+}
@@ -0,0 +1,234 @@
+//! Meson build directories.
+//!
+//! A configured build directory describes its targets in the introspection data under
+//! `meson-info`, including the compiler invocation of each group of sources. Meson also writes
+//! the compilation database when it generates Ninja build files, which is preferred when present;
+//! without either, the compile commands are recovered from `build.ninja` with `ninja -t compdb`.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use failure::{format_err, Error};
+use serde_derive::Deserialize;
+
+use super::{
+    library_file, run, write_compile_commands, BuildOptions, CompileEntry, LinkType, Project,
+    Target,
+};
+
+/// The directory a compilation database is written to, relative to the build directory
+const DEFAULT_BUILD_DIR: &str = "build-c2rust";
+
+#[derive(Deserialize)]
+struct IntroTarget {
+    name: String,
+    r#type: String,
+    #[serde(default)]
+    target_sources: Vec<TargetSources>,
+}
+
+/// A group of sources compiled by the same compiler invocation, or the linker invocation
+#[derive(Deserialize)]
+struct TargetSources {
+    language: Option<String>,
+    #[serde(default)]
+    compiler: Vec<String>,
+    #[serde(default)]
+    linker: Vec<String>,
+    #[serde(default)]
+    parameters: Vec<String>,
+    #[serde(default)]
+    sources: Vec<PathBuf>,
+}
+
+/// Is `path` a configured Meson build directory?
+pub fn is_build_dir(path: &Path) -> bool {
+    path.join("meson-info").join("intro-targets.json").is_file()
+}
+
+/// Load the targets and compile commands of the Meson build directory `build_dir`
+pub fn load_project(build_dir: &Path, options: &BuildOptions) -> Result<Project, Error> {
+    let targets = if is_build_dir(build_dir) {
+        let path = build_dir.join("meson-info").join("intro-targets.json");
+        let file = File::open(&path).map_err(|e| format_err!("{}: {}", path.display(), e))?;
+        serde_json::from_reader(BufReader::new(file))?
+    } else if build_dir.join("build.ninja").is_file() {
+        vec![]
+    } else {
+        return Err(format_err!(
+            "{} is not a configured Meson build directory",
+            build_dir.display()
+        ));
+    };
+
+    let output_dir = options
+        .build_dir
+        .clone()
+        .unwrap_or_else(|| build_dir.join(DEFAULT_BUILD_DIR));
+    let compile_commands = build_dir.join("compile_commands.json");
+    let compile_commands = if compile_commands.is_file() {
+        compile_commands
+    } else if !targets.is_empty() {
+        write_compile_commands(&output_dir, &compile_entries(&targets, build_dir))?
+    } else {
+        // Recover the compile commands from the Ninja build file
+        let compdb = run(Command::new("ninja")
+            .arg("-C")
+            .arg(build_dir)
+            .arg("-t")
+            .arg("compdb")
+            .args(&options.args))?;
+        std::fs::create_dir_all(&output_dir)?;
+        let path = output_dir.join("compile_commands.json");
+        std::fs::write(&path, compdb)?;
+        path
+    };
+
+    Ok(Project {
+        compile_commands,
+        targets: project_targets(&targets, build_dir),
+    })
+}
+
+/// The compilation database entries of the C sources of `targets`
+fn compile_entries(targets: &[IntroTarget], build_dir: &Path) -> Vec<CompileEntry> {
+    let mut entries = vec![];
+    for sources in targets.iter().flat_map(|target| &target.target_sources) {
+        if sources.language.as_deref() != Some("c") {
+            continue;
+        }
+        for source in &sources.sources {
+            let mut arguments = sources.compiler.clone();
+            arguments.extend(sources.parameters.iter().cloned());
+            arguments.push("-c".to_owned());
+            arguments.push(source.display().to_string());
+            entries.push(CompileEntry {
+                directory: build_dir.to_owned(),
+                file: source.clone(),
+                arguments,
+                output: None,
+            });
+        }
+    }
+    entries
+}
+
+/// The libraries and executables among `targets` that compile C sources
+fn project_targets(targets: &[IntroTarget], build_dir: &Path) -> Vec<Target> {
+    let mut res = vec![];
+    for target in targets {
+        let r#type = match target.r#type.as_str() {
+            "executable" => LinkType::Exe,
+            "static library" => LinkType::Static,
+            "shared library" | "shared module" => LinkType::Shared,
+            _ => continue,
+        };
+
+        let mut sources = vec![];
+        let mut libs = vec![];
+        let mut lib_dirs = vec![];
+        for group in &target.target_sources {
+            if group.language.as_deref() == Some("c") {
+                sources.extend(
+                    group
+                        .sources
+                        .iter()
+                        .map(|source| source.canonicalize().unwrap_or_else(|_| source.clone())),
+                );
+            }
+            // Link arguments name libraries outside the build directory by path; those inside it
+            // are targets of the project
+            if !group.linker.is_empty() {
+                for arg in &group.parameters {
+                    if let Some(lib) = arg.strip_prefix("-l") {
+                        libs.push(lib.to_owned());
+                    } else if let Some(dir) = arg.strip_prefix("-L") {
+                        lib_dirs.push(PathBuf::from(dir));
+                    } else if arg.starts_with('-') || build_dir.join(arg).exists() {
+                        continue;
+                    } else if let Some((lib, dir)) = library_file(Path::new(arg)) {
+                        libs.push(lib);
+                        lib_dirs.push(dir);
+                    }
+                }
+            }
+        }
+        if sources.is_empty() {
+            continue;
+        }
+        libs.dedup();
+        lib_dirs.dedup();
+
+        res.push(Target {
+            name: target.name.clone(),
+            r#type,
+            sources,
+            libs,
+            lib_dirs,
+            dependencies: vec![],
+        });
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intro_targets() {
+        let targets: Vec<IntroTarget> = serde_json::from_str(
+            r#"[
+                {
+                    "name": "util",
+                    "type": "static library",
+                    "target_sources": [
+                        {
+                            "language": "c",
+                            "compiler": ["cc"],
+                            "parameters": ["-I../include", "-DNDEBUG"],
+                            "sources": ["/nonexistent/src/util.c"],
+                            "generated_sources": []
+                        }
+                    ]
+                },
+                {
+                    "name": "tool",
+                    "type": "executable",
+                    "target_sources": [
+                        {
+                            "language": "c",
+                            "compiler": ["cc"],
+                            "parameters": [],
+                            "sources": ["/nonexistent/src/tool.c"]
+                        },
+                        {
+                            "linker": ["cc"],
+                            "parameters": ["-Wl,--as-needed", "/usr/lib/libz.so", "-lm"]
+                        }
+                    ]
+                },
+                {"name": "docs", "type": "custom", "target_sources": []}
+            ]"#,
+        )
+        .unwrap();
+        let build_dir = Path::new("/nonexistent/build");
+
+        let entries = compile_entries(&targets, build_dir);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0].arguments,
+            vec!["cc", "-I../include", "-DNDEBUG", "-c", "/nonexistent/src/util.c"]
+        );
+
+        let targets = project_targets(&targets, build_dir);
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[0].r#type, LinkType::Static);
+        assert_eq!(targets[1].name, "tool");
+        assert_eq!(targets[1].r#type, LinkType::Exe);
+        assert_eq!(targets[1].libs, vec!["z", "m"]);
+        assert_eq!(targets[1].lib_dirs, vec![PathBuf::from("/usr/lib")]);
+    }
+}
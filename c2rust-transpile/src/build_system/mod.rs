@@ -0,0 +1,171 @@
+//! Ingestion of C projects from their build systems.
+//!
+//! Rather than a `compile_commands.json` recorded beforehand, the transpiler can be pointed at a
+//! project its build system can configure. Configuring the project produces the compilation
+//! database, and the build system's description of its targets takes the place of the link
+//! commands recorded in it: each target becomes a crate of the translated workspace, linking the
+//! same libraries.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use failure::{format_err, Error};
+use serde_derive::Serialize;
+use strum_macros::{Display, EnumString};
+
+pub use crate::compile_cmds::LinkType;
+
+pub mod bazel;
+pub mod buck;
+pub mod capture;
+pub mod cmake;
+pub mod meson;
+pub mod msvc;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Display, EnumString, Serialize)]
+#[strum(serialize_all = "kebab_case")]
+pub enum BuildSystem {
+    Cmake,
+    /// The project is a target label, such as `//src:lib`, built in the current workspace
+    Bazel,
+    /// The project is a target label built by Buck2 in the current project
+    Buck,
+    /// The project is a Meson build directory, or one with just a `build.ninja`
+    Meson,
+}
+
+/// A library or executable built by the project
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub name: String,
+    pub r#type: LinkType,
+    /// The C sources compiled into the target
+    pub sources: Vec<PathBuf>,
+    /// Libraries linked from outside the project (without `-l` prefix)
+    pub libs: Vec<String>,
+    /// Directories the libraries are searched in
+    pub lib_dirs: Vec<PathBuf>,
+    /// Names of the targets of the project linked into this one
+    pub dependencies: Vec<String>,
+}
+
+/// A configured project
+#[derive(Debug)]
+pub struct Project {
+    /// The compilation database the build system generated
+    pub compile_commands: PathBuf,
+    pub targets: Vec<Target>,
+}
+
+/// How to configure projects
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct BuildOptions {
+    /// The directory to configure the project in, if not the build system's default
+    pub build_dir: Option<PathBuf>,
+    /// Extra arguments to the build system, such as definitions of build options
+    pub args: Vec<String>,
+    /// The build system of the project, if not recognized from its files
+    pub build_system: Option<BuildSystem>,
+}
+
+/// Configure the project at `path` if it belongs to a build system we can drive, or return
+/// `None` if it's something else, such as a `compile_commands.json`. Projects of build systems
+/// that work with target labels rather than files have to be given with their build system.
+pub fn load_project(path: &Path, options: &BuildOptions) -> Result<Option<Project>, Error> {
+    let label = || {
+        path.to_str()
+            .ok_or_else(|| format_err!("Invalid target label: {}", path.display()))
+    };
+    match options.build_system {
+        Some(BuildSystem::Bazel) => bazel::load_project(label()?, options).map(Some),
+        Some(BuildSystem::Buck) => buck::load_project(label()?, options).map(Some),
+        Some(BuildSystem::Cmake) => match cmake::load_project(path, options)? {
+            Some(project) => Ok(Some(project)),
+            None => Err(format_err!("{} is not a CMake project", path.display())),
+        },
+        Some(BuildSystem::Meson) => meson::load_project(path, options).map(Some),
+        None if meson::is_build_dir(path) => meson::load_project(path, options).map(Some),
+        None => cmake::load_project(path, options),
+    }
+}
+
+/// An entry of a compilation database we write
+#[derive(Serialize)]
+pub(crate) struct CompileEntry {
+    pub directory: PathBuf,
+    pub file: PathBuf,
+    pub arguments: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+}
+
+/// Write the compilation database `entries` to `compile_commands.json` in `dir`
+pub(crate) fn write_compile_commands(
+    dir: &Path,
+    entries: &[CompileEntry],
+) -> Result<PathBuf, Error> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join("compile_commands.json");
+    fs::write(&path, serde_json::to_string_pretty(entries)?)?;
+    Ok(path)
+}
+
+/// Run a build system command, returning its standard output
+fn run(cmd: &mut Command) -> Result<String, Error> {
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let output = cmd
+        .output()
+        .map_err(|e| format_err!("Unable to run {}: {}", program, e))?;
+    if !output.status.success() {
+        return Err(format_err!(
+            "{} failed:\n{}",
+            program,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Split a static or shared library file name into the name to link it with and its directory,
+/// e.g. `/usr/lib/libz.so.1` into `z` and `/usr/lib`
+fn library_file(path: &Path) -> Option<(String, PathBuf)> {
+    let file_name = path.file_name()?.to_str()?;
+    // Shared libraries may be versioned, as in `libz.so.1`
+    let is_version = |s: &str| s.chars().all(|c| c == '.' || c.is_ascii_digit());
+    let stem = [".a", ".so", ".dylib", ".lib"]
+        .iter()
+        .find_map(|ext| {
+            let pos = file_name.find(ext)?;
+            if is_version(&file_name[pos + ext.len()..]) {
+                Some(&file_name[..pos])
+            } else {
+                None
+            }
+        })?;
+    let name = stem.strip_prefix("lib").unwrap_or(stem);
+    let dir = path.parent().unwrap_or_else(|| Path::new("")).to_owned();
+    Some((name.to_owned(), dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn library_files() {
+        assert_eq!(
+            library_file(Path::new("/usr/lib/libz.so.1.2")),
+            Some(("z".to_owned(), PathBuf::from("/usr/lib")))
+        );
+        assert_eq!(
+            library_file(Path::new("libutil.a")),
+            Some(("util".to_owned(), PathBuf::new()))
+        );
+        assert_eq!(
+            library_file(Path::new("C:/libs/zlib.lib")),
+            Some(("zlib".to_owned(), PathBuf::from("C:/libs")))
+        );
+        assert_eq!(library_file(Path::new("main.o")), None);
+    }
+}
@@ -0,0 +1,287 @@
+//! Compilation databases of MSVC builds.
+//!
+//! Build systems on Windows record `cl.exe` (or `clang-cl.exe`) command lines, which are quoted
+//! with the rules of Windows programs and take slash-prefixed options that the GCC-style clang
+//! driver running the AST exporter doesn't. A database with such commands is rewritten with the
+//! equivalent clang options, targeting the MSVC ABI with Microsoft extensions such as
+//! `__declspec` enabled.
+
+use std::fs;
+use std::iter;
+use std::path::{Path, PathBuf};
+
+use failure::{format_err, Error};
+use serde_derive::Deserialize;
+
+use super::{write_compile_commands, BuildOptions, CompileEntry};
+use crate::compile_cmds::split_command;
+
+/// The directory the rewritten database is written to, relative to the original one
+const DEFAULT_BUILD_DIR: &str = "build-c2rust";
+
+#[derive(Deserialize)]
+struct Entry {
+    directory: PathBuf,
+    file: PathBuf,
+    #[serde(default)]
+    command: Option<String>,
+    #[serde(default)]
+    arguments: Vec<String>,
+    #[serde(default)]
+    output: Option<String>,
+}
+
+impl Entry {
+    fn args(&self) -> Vec<String> {
+        match self.command {
+            Some(ref command) if self.arguments.is_empty() => {
+                let args = split_windows_command(command);
+                if args.first().map_or(false, |compiler| is_cl(compiler)) {
+                    args
+                } else {
+                    split_command(command)
+                }
+            }
+            _ => self.arguments.clone(),
+        }
+    }
+}
+
+/// Rewrite the compilation database `cc_db` if it has `cl.exe` commands, for `target` or the
+/// target of the compiler, returning the path of the rewritten database
+pub fn normalize(
+    cc_db: &Path,
+    options: &BuildOptions,
+    target: Option<&str>,
+) -> Result<Option<PathBuf>, Error> {
+    let file =
+        fs::read_to_string(cc_db).map_err(|e| format_err!("{}: {}", cc_db.display(), e))?;
+    let entries: Vec<Entry> = serde_json::from_str(&file)?;
+    let args = entries.iter().map(Entry::args).collect::<Vec<_>>();
+    if !args.iter().any(|args| args.first().map_or(false, |arg| is_cl(arg))) {
+        return Ok(None);
+    }
+
+    let entries = entries
+        .iter()
+        .zip(args)
+        .map(|(entry, args)| {
+            let arguments = match args.first() {
+                Some(compiler) if is_cl(compiler) => {
+                    clang_args(&args, target.unwrap_or_else(|| cl_target(compiler)))
+                }
+                _ => args,
+            };
+            CompileEntry {
+                directory: entry.directory.clone(),
+                file: entry.file.clone(),
+                arguments,
+                output: entry.output.clone(),
+            }
+        })
+        .collect::<Vec<_>>();
+    let dir = options.build_dir.clone().unwrap_or_else(|| {
+        let parent = cc_db.parent().unwrap_or_else(|| Path::new("."));
+        parent.join(DEFAULT_BUILD_DIR)
+    });
+    write_compile_commands(&dir, &entries).map(Some)
+}
+
+/// The name of the program `path`, which may use either separator whatever the host
+fn program_name(path: &str) -> String {
+    let name = path.rsplit(|c| c == '/' || c == '\\').next().unwrap_or(path);
+    let name = name.to_ascii_lowercase();
+    name.strip_suffix(".exe").map(String::from).unwrap_or(name)
+}
+
+/// Is `compiler` the MSVC compiler driver, or clang's emulation of it?
+fn is_cl(compiler: &str) -> bool {
+    let name = program_name(compiler);
+    name == "cl" || name == "clang-cl"
+}
+
+/// The target of `cl.exe`, which is in a directory named after it, as in `Hostx64\x86\cl.exe`
+fn cl_target(compiler: &str) -> &'static str {
+    let dir = compiler.rsplit(|c| c == '/' || c == '\\').nth(1);
+    match dir.map(|dir| dir.to_ascii_lowercase()).as_deref() {
+        Some("x86") => "i686-pc-windows-msvc",
+        Some("arm64") => "aarch64-pc-windows-msvc",
+        Some("arm") => "thumbv7a-pc-windows-msvc",
+        _ => "x86_64-pc-windows-msvc",
+    }
+}
+
+/// The clang arguments equivalent to the `cl.exe` arguments `args`. Options that don't change
+/// how the code is parsed, such as those for warnings, optimizations and outputs other than the
+/// object file, are left out.
+fn clang_args(args: &[String], target: &str) -> Vec<String> {
+    let mut res = vec![
+        "clang".to_owned(),
+        format!("--target={}", target),
+        "-fms-extensions".to_owned(),
+        "-fms-compatibility".to_owned(),
+    ];
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        // Options start with either `/` or `-`, and so do absolute paths on Unix hosts
+        let opt = match arg.strip_prefix('/').or_else(|| arg.strip_prefix('-')) {
+            Some(opt) if !arg.to_ascii_lowercase().ends_with(".c") => opt,
+            _ => {
+                res.push(arg.clone());
+                continue;
+            }
+        };
+        // The value of an option given as `/Ivalue`, `/I value` or `/Fo:value`
+        let mut value = |name: &str| match opt[name.len()..].trim_start_matches(':') {
+            "" => iter.next().cloned(),
+            value => Some(value.to_owned()),
+        };
+
+        match opt {
+            "c" => res.push("-c".to_owned()),
+            "TC" => res.extend(vec!["-x".to_owned(), "c".to_owned()]),
+            "J" => res.push("-funsigned-char".to_owned()),
+            // The runtime library is announced to the code by macros
+            "MD" | "MDd" | "MT" | "MTd" => {
+                res.push("-D_MT".to_owned());
+                if opt.starts_with("MD") {
+                    res.push("-D_DLL".to_owned());
+                }
+                if opt.ends_with('d') {
+                    res.push("-D_DEBUG".to_owned());
+                }
+            }
+            // The default calling convention
+            "Gz" | "Gr" | "Gv" => {
+                let call_conv = match opt {
+                    "Gz" => "stdcall",
+                    "Gr" => "fastcall",
+                    _ => "vectorcall",
+                };
+                res.push("-Xclang".to_owned());
+                res.push(format!("-fdefault-calling-conv={}", call_conv));
+            }
+            _ if opt.starts_with("std:c") => {
+                let std = match &opt["std:".len()..] {
+                    "clatest" => "c2x",
+                    std => std,
+                };
+                res.push(format!("-std={}", std));
+            }
+            _ if opt.starts_with("Zp") => {
+                if opt.len() > 2 {
+                    res.push(format!("-fpack-struct={}", &opt[2..]));
+                }
+            }
+            _ if opt.starts_with("Tc") => {
+                res.extend(vec!["-x".to_owned(), "c".to_owned()]);
+                res.extend(value("Tc"));
+            }
+            _ if opt.starts_with("Fo") => {
+                if let Some(output) = value("Fo") {
+                    res.push("-o".to_owned());
+                    res.push(output);
+                }
+            }
+            _ if opt.starts_with("FI") => {
+                if let Some(header) = value("FI") {
+                    res.push("-include".to_owned());
+                    res.push(header);
+                }
+            }
+            _ if opt.starts_with("external:I") => {
+                res.extend(value("external:I").map(|dir| format!("-isystem{}", dir)));
+            }
+            _ if opt.starts_with('I') => res.extend(value("I").map(|dir| format!("-I{}", dir))),
+            // `#` stands for `=` in definitions given on the command line
+            _ if opt.starts_with('D') => {
+                res.extend(value("D").map(|def| format!("-D{}", def.replacen('#', "=", 1))))
+            }
+            _ if opt.starts_with('U') => res.extend(value("U").map(|name| format!("-U{}", name))),
+            _ => {}
+        }
+    }
+    res
+}
+
+/// Split a command line with the quoting rules of Windows programs, where backslashes only
+/// escape quotes
+fn split_windows_command(command: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut arg: Option<String> = None;
+    let mut quoted = false;
+    let mut backslashes = 0;
+    for c in command.chars() {
+        if c == '\\' {
+            backslashes += 1;
+            continue;
+        }
+
+        if c == '"' {
+            // Pairs of backslashes before a quote stand for one, and an odd one escapes it
+            let arg = arg.get_or_insert_with(String::new);
+            arg.extend(iter::repeat('\\').take(backslashes / 2));
+            if backslashes % 2 == 1 {
+                arg.push('"');
+            } else {
+                quoted = !quoted;
+            }
+        } else {
+            if backslashes > 0 {
+                let arg = arg.get_or_insert_with(String::new);
+                arg.extend(iter::repeat('\\').take(backslashes));
+            }
+            if c.is_whitespace() && !quoted {
+                args.extend(arg.take());
+            } else {
+                arg.get_or_insert_with(String::new).push(c);
+            }
+        }
+        backslashes = 0;
+    }
+    if backslashes > 0 {
+        arg.get_or_insert_with(String::new)
+            .extend(iter::repeat('\\').take(backslashes));
+    }
+    args.extend(arg);
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cl_command() {
+        let command = r#"C:\VS\bin\Hostx64\x86\cl.exe /nologo /TC -DWIN32 /D "NAME=\"a b\"" /DV#1 /IC:\src\include /I "C:\Program Files\inc" /MDd /O2 /W4 /Fo:obj\util.obj /c C:\src\util.c"#;
+        let args = split_windows_command(command);
+        assert_eq!(args[0], r"C:\VS\bin\Hostx64\x86\cl.exe");
+        assert_eq!(args[5], r#"NAME="a b""#);
+        assert!(is_cl(&args[0]));
+        assert!(!is_cl("/usr/bin/gcc"));
+
+        assert_eq!(
+            clang_args(&args, cl_target(&args[0])),
+            vec![
+                "clang",
+                "--target=i686-pc-windows-msvc",
+                "-fms-extensions",
+                "-fms-compatibility",
+                "-x",
+                "c",
+                "-DWIN32",
+                r#"-DNAME="a b""#,
+                "-DV=1",
+                r"-IC:\src\include",
+                r"-IC:\Program Files\inc",
+                "-D_MT",
+                "-D_DLL",
+                "-D_DEBUG",
+                "-o",
+                r"obj\util.obj",
+                "-c",
+                r"C:\src\util.c",
+            ]
+        );
+    }
+}
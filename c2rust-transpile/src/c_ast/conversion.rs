@@ -1,4 +1,5 @@
 use crate::c_ast::*;
+use crate::convert_type::long_double_is_double;
 use crate::diagnostics::diag;
 use c2rust_ast_exporter::clang_ast::*;
 use failure::err_msg;
@@ -166,6 +167,12 @@ fn parse_cast_kind(kind: &str) -> CastKind {
         "BuiltinFnToFnPtr" => CastKind::BuiltinFnToFnPtr,
         "ConstCast" => CastKind::ConstCast,
         "VectorSplat" => CastKind::VectorSplat,
+        "FixedPointCast"
+        | "FixedPointToBoolean"
+        | "FixedPointToIntegral"
+        | "IntegralToFixedPoint"
+        | "FixedPointToFloating"
+        | "FloatingToFixedPoint" => CastKind::FixedPoint,
         k => panic!("Unsupported implicit cast: {}", k),
     }
 }
@@ -174,29 +181,44 @@ fn parse_attributes(attributes: Vec<Value>) -> IndexSet<Attribute> {
     let mut attrs = IndexSet::new();
     let mut expect_section_value = false;
     let mut expect_alias_value = false;
+    let mut expect_annotate_value = false;
     let mut expect_visibility_value = false;
+    let mut expect_nonnull_value = false;
+    let mut expect_callconv_value = false;
 
     for attr in attributes.into_iter() {
         let attr_str = from_value::<String>(attr).expect("Decl attributes should be strings");
 
         match attr_str.as_str() {
             "alias" => expect_alias_value = true,
+            "annotate" => expect_annotate_value = true,
             "always_inline" => {
                 attrs.insert(Attribute::AlwaysInline);
             }
+            "callconv" => expect_callconv_value = true,
             "cold" => {
                 attrs.insert(Attribute::Cold);
             }
+            "dllexport" => {
+                attrs.insert(Attribute::DllExport);
+            }
+            "dllimport" => {
+                attrs.insert(Attribute::DllImport);
+            }
             "gnu_inline" => {
                 attrs.insert(Attribute::GnuInline);
             }
             "noinline" => {
                 attrs.insert(Attribute::NoInline);
             }
+            "noreturn" | "_Noreturn" => {
+                attrs.insert(Attribute::NoReturn);
+            }
             "used" => {
                 attrs.insert(Attribute::Used);
             }
             "visibility" => expect_visibility_value = true,
+            "nonnull" => expect_nonnull_value = true,
             "section" => expect_section_value = true,
             s if expect_section_value => {
                 attrs.insert(Attribute::Section(s.into()));
@@ -208,11 +230,30 @@ fn parse_attributes(attributes: Vec<Value>) -> IndexSet<Attribute> {
 
                 expect_alias_value = false;
             }
+            s if expect_annotate_value => {
+                attrs.insert(Attribute::Annotate(s.into()));
+
+                expect_annotate_value = false;
+            }
             s if expect_visibility_value => {
                 attrs.insert(Attribute::Visibility(s.into()));
 
                 expect_visibility_value = false;
             }
+            s if expect_callconv_value => {
+                attrs.insert(Attribute::CallConv(s.into()));
+
+                expect_callconv_value = false;
+            }
+            s if expect_nonnull_value => {
+                let indices = s
+                    .split(',')
+                    .filter_map(|index| index.parse().ok())
+                    .collect();
+                attrs.insert(Attribute::NonNull(indices));
+
+                expect_nonnull_value = false;
+            }
             _ => {}
         }
     }
@@ -404,6 +445,27 @@ impl ConversionContext {
         self.typed_context.c_decls.insert(CDeclId(id), decl);
     }
 
+    /// Mark the type of a function pointer parameter `_Nonnull`
+    fn mark_nonnull_param(&mut self, param_id: CDeclId) {
+        let typ = match self.typed_context.c_decls[&param_id].kind {
+            CDeclKind::Variable { typ, .. } => typ,
+            _ => return,
+        };
+        if !self.typed_context.is_function_pointer(typ.ctype)
+            || self.typed_context.is_nonnull_fn_pointer(typ.ctype)
+        {
+            return;
+        }
+
+        let new_id = self.id_mapper.fresh_id();
+        let nonnull = CTypeKind::Attributed(CQualTypeId::new(typ.ctype), Some(Attribute::NotNull));
+        self.add_type(new_id, not_located(nonnull));
+        if let CDeclKind::Variable { ref mut typ, .. } = self.typed_context.c_decls[&param_id].kind
+        {
+            typ.ctype = CTypeId(new_id);
+        }
+    }
+
     /// Clang has `Expression <: Statement`, but we want to make that explicit via the
     /// `CStmtKind::Expr` statement constructor. This function automatically converts expressions
     /// into statements depending on the expected type argument.
@@ -438,6 +500,9 @@ impl ConversionContext {
     ///
     /// This populates the `typed_context` of the `ConversionContext` it is called on.
     fn convert(&mut self, untyped_context: &AstContext) -> () {
+        // Types are converted for the target
+        self.typed_context.target = untyped_context.target.clone();
+
         for raw_comment in &untyped_context.comments {
             let comment = Located {
                 loc: Some(raw_comment.loc.into()),
@@ -446,6 +511,14 @@ impl ConversionContext {
             self.typed_context.comments.push(comment);
         }
 
+        for raw_pragma in &untyped_context.pragmas {
+            let pragma = Located {
+                loc: Some(raw_pragma.loc.into()),
+                kind: raw_pragma.text.clone(),
+            };
+            self.typed_context.pragmas.push(pragma);
+        }
+
         // Continue popping Clang nodes off of the stack of nodes we have promised to visit
         while let Some((node_id, expected_ty)) = self.visit_as.pop() {
             // Check if we've already processed this node. If so, ascertain that it has the right
@@ -485,9 +558,13 @@ impl ConversionContext {
                     .push(*expr_id);
             }
         }
+        // Keep the expansions in a stable order so the expansion a macro's const is translated
+        // from doesn't depend on hash map iteration order
+        for expansions in self.typed_context.macro_expansions.values_mut() {
+            expansions.sort();
+        }
 
         self.typed_context.va_list_kind = untyped_context.va_list_kind;
-        self.typed_context.target = untyped_context.target.clone();
     }
 
     /// Visit one node.
@@ -579,7 +656,12 @@ impl ConversionContext {
                 }
 
                 TypeTag::TagLongDouble if expected_ty & OTHER_TYPE != 0 => {
-                    self.add_type(new_id, not_located(CTypeKind::LongDouble));
+                    let kind = if long_double_is_double(&self.typed_context.target) {
+                        CTypeKind::Double
+                    } else {
+                        CTypeKind::LongDouble
+                    };
+                    self.add_type(new_id, not_located(kind));
                     self.processed_nodes.insert(new_id, OTHER_TYPE);
                 }
 
@@ -703,6 +785,27 @@ impl ConversionContext {
                     self.processed_nodes.insert(new_id, OTHER_TYPE);
                 }
 
+                TypeTag::TagAutoType if expected_ty & TYPE != 0 => {
+                    let deduced_old = from_value(ty_node.extras[0].clone())
+                        .expect("Deduced type of auto type not found");
+                    let deduced = self.visit_type(deduced_old);
+
+                    let auto_ty = CTypeKind::Auto(deduced);
+                    self.add_type(new_id, not_located(auto_ty));
+                    self.processed_nodes.insert(new_id, OTHER_TYPE);
+                }
+
+                TypeTag::TagFixedPointType if expected_ty & OTHER_TYPE != 0 => {
+                    let name = from_value(ty_node.extras[0].clone()).expect("Fixed-point name");
+                    let width = from_value(ty_node.extras[1].clone()).expect("Fixed-point width");
+                    let is_signed =
+                        from_value(ty_node.extras[2].clone()).expect("Fixed-point signedness");
+
+                    let fixed_ty = CTypeKind::FixedPoint(name, width, is_signed);
+                    self.add_type(new_id, not_located(fixed_ty));
+                    self.processed_nodes.insert(new_id, OTHER_TYPE);
+                }
+
                 TypeTag::TagTypedefType => {
                     let decl =
                         from_value(ty_node.extras[0].clone()).expect("Typedef decl not found");
@@ -764,6 +867,11 @@ impl ConversionContext {
                         Some("noreturn") => Some(Attribute::NoReturn),
                         Some("nullable") => Some(Attribute::Nullable),
                         Some("notnull") => Some(Attribute::NotNull),
+                        Some("cdecl") => None,
+                        Some(
+                            cc @ ("stdcall" | "fastcall" | "vectorcall" | "thiscall" | "ms_abi"
+                            | "sysv_abi"),
+                        ) => Some(Attribute::CallConv(cc.to_owned())),
                         Some(other) => panic!("Unknown type attribute: {}", other),
                     };
 
@@ -810,12 +918,6 @@ impl ConversionContext {
                     self.processed_nodes.insert(new_id, OTHER_TYPE);
                 }
 
-                TypeTag::TagBFloat16 => {
-                    let ty = CTypeKind::BuiltinFn;
-                    self.add_type(new_id, not_located(ty));
-                    self.processed_nodes.insert(new_id, OTHER_TYPE);
-                }
-
                 TypeTag::TagVectorType => {
                     let elt =
                         from_value(ty_node.extras[0].clone()).expect("Vector child not found");
@@ -970,6 +1072,18 @@ impl ConversionContext {
                     self.processed_nodes.insert(new_id, OTHER_STMT);
                 }
 
+                ASTEntryTag::TagSEHStmt if expected_ty & OTHER_STMT != 0 => {
+                    let blocks = node
+                        .children
+                        .iter()
+                        .flatten()
+                        .map(|&id| self.visit_stmt(id))
+                        .collect();
+
+                    self.add_stmt(new_id, located(node, CStmtKind::Seh(blocks)));
+                    self.processed_nodes.insert(new_id, OTHER_STMT);
+                }
+
                 ASTEntryTag::TagForStmt if expected_ty & OTHER_STMT != 0 => {
                     let init = node.children[0].map(|id| self.visit_stmt(id));
 
@@ -1065,16 +1179,29 @@ impl ConversionContext {
 
                     let is_signed = from_value(node.extras[0].clone())
                         .expect("Case constant is_signed not found");
-                    let cie = match is_signed {
+                    let const_int = |extra: &Value| match is_signed {
                         false => ConstIntExpr::U(
-                            from_value(node.extras[1].clone()).expect("Case constant not found"),
+                            from_value(extra.clone()).expect("Case constant not found"),
                         ),
                         true => ConstIntExpr::I(
-                            from_value(node.extras[1].clone()).expect("Case constant not found"),
+                            from_value(extra.clone()).expect("Case constant not found"),
                         ),
                     };
-
-                    let case_stmt = CStmtKind::Case(expr, substmt, cie);
+                    let cie = const_int(&node.extras[1]);
+
+                    // GNU case ranges have the upper bound as an extra child
+                    let case_stmt = match node.children.get(2).copied().flatten() {
+                        Some(rhs_old) => {
+                            let rhs = self.visit_expr(rhs_old);
+                            let rhs_cie = const_int(
+                                node.extras
+                                    .get(2)
+                                    .expect("Case range upper bound not found"),
+                            );
+                            CStmtKind::CaseRange(expr, rhs, substmt, cie, rhs_cie)
+                        }
+                        None => CStmtKind::Case(expr, substmt, cie),
+                    };
 
                     self.add_stmt(new_id, located(node, case_stmt));
                     self.processed_nodes.insert(new_id, OTHER_STMT);
@@ -1100,8 +1227,9 @@ impl ConversionContext {
                     let raw_clobbers =
                         from_value::<Vec<Value>>(node.extras[4].clone()).expect("clobber array");
 
-                    let (input_children, output_children) =
-                        node.children.split_at(raw_inputs.len());
+                    let (input_children, other_children) = node.children.split_at(raw_inputs.len());
+                    let (output_children, label_children) =
+                        other_children.split_at(raw_outputs.len());
 
                     let inputs: Vec<AsmOperand> = raw_inputs
                         .into_iter()
@@ -1134,12 +1262,21 @@ impl ConversionContext {
                         .map(|c| from_value(c).expect("clobber string"))
                         .collect();
 
+                    let labels: Vec<CLabelId> = label_children
+                        .iter()
+                        .map(|l| {
+                            let label = l.expect("asm goto label");
+                            CStmtId(self.visit_node_type(label, LABEL_STMT))
+                        })
+                        .collect();
+
                     let stmt = CStmtKind::Asm {
                         is_volatile,
                         asm,
                         inputs,
                         outputs,
                         clobbers,
+                        labels,
                     };
                     self.add_stmt(new_id, located(node, stmt));
                     self.processed_nodes.insert(new_id, OTHER_STMT);
@@ -1169,13 +1306,25 @@ impl ConversionContext {
                         let qty_int = from_value(node.extras[1].clone())
                             .expect("Expected offset of to have struct type");
                         let qty = self.visit_qualified_type(qty_int);
-                        let field =
-                            from_value(node.extras[2].clone()).expect("Expected offset of field");
-                        let field_id = self.visit_decl(field);
-                        let index = from_value(node.extras[3].clone())
-                            .expect("Expected offset of index expr");
-                        let index_expr_id = self.visit_expr(index);
-                        let kind = OffsetOfKind::Variable(qty, field_id, index_expr_id);
+                        let component_cbors = from_value::<Vec<Value>>(node.extras[2].clone())
+                            .expect("Expected offset of components array");
+                        let components = component_cbors
+                            .into_iter()
+                            .map(|x| {
+                                let entry = from_value::<Vec<Value>>(x)
+                                    .expect("expected offset of component array");
+                                let id = from_value(entry[1].clone())
+                                    .expect("expected offset of component id");
+                                match from_value(entry[0].clone())
+                                    .expect("expected offset of component tag")
+                                {
+                                    0 => OffsetOfComponent::Index(self.visit_expr(id)),
+                                    1 => OffsetOfComponent::Field(self.visit_decl(id)),
+                                    n => panic!("invalid offset of component tag: {}", n),
+                                }
+                            })
+                            .collect();
+                        let kind = OffsetOfKind::Variable(qty, components);
 
                         CExprKind::OffsetOf(ty, kind)
                     };
@@ -1690,6 +1839,17 @@ impl ConversionContext {
                     self.expr_possibly_as_stmt(expected_ty, new_id, node, e)
                 }
 
+                ASTEntryTag::TagEmbedExpr => {
+                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty = self.visit_qualified_type(ty_old);
+                    let bytes = from_value::<ByteBuf>(node.extras[0].clone())
+                        .expect("Expected embedded bytes");
+
+                    let e = CExprKind::Embed(ty, bytes.into_vec());
+
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, e)
+                }
+
                 ASTEntryTag::TagConstantExpr => {
                     let expr = node.children[0].expect("Missing ConstantExpr subexpression");
                     let expr = self.visit_expr(expr);
@@ -1827,7 +1987,7 @@ impl ConversionContext {
 
                     let body = body_id.map(|b| self.visit_stmt(b));
 
-                    let parameters = parameter_ids
+                    let parameters: Vec<CDeclId> = parameter_ids
                         .iter()
                         .map(|id| {
                             let param = id.expect("Param field decl not found");
@@ -1835,6 +1995,25 @@ impl ConversionContext {
                         })
                         .collect();
 
+                    // Function pointers that `__attribute__((nonnull))` covers can't be null
+                    for (index, &param_id) in parameters.iter().enumerate() {
+                        let param_nonnull = match self.typed_context.c_decls[&param_id].kind {
+                            CDeclKind::Variable { ref attrs, .. } => attrs
+                                .iter()
+                                .any(|attr| matches!(attr, Attribute::NonNull(_))),
+                            _ => false,
+                        };
+                        let fn_nonnull = attrs.iter().any(|attr| match attr {
+                            Attribute::NonNull(indices) => {
+                                indices.is_empty() || indices.contains(&index)
+                            }
+                            _ => false,
+                        });
+                        if param_nonnull || fn_nonnull {
+                            self.mark_nonnull_param(param_id);
+                        }
+                    }
+
                     let function_decl = CDeclKind::Function {
                         attrs,
                         body,
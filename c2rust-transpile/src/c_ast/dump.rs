@@ -0,0 +1,429 @@
+//! Dumps the typed C AST in a human-readable form for `--dump-c-ast`. Each node is shown with
+//! its kind, its id, its source span, its type and a short detail such as the name it declares
+//! or refers to, either as an indented tree or as JSON.
+
+use super::iterators::{immediate_children, SomeId};
+use super::*;
+use log::warn;
+use serde_derive::Serialize;
+use std::io::{self, Write};
+
+/// Output format of `--dump-c-ast`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum AstDumpFormat {
+    Text,
+    Json,
+}
+
+#[derive(Serialize)]
+struct DumpNode {
+    node: &'static str,
+    id: usize,
+    kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    span: Option<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    ty: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<DumpNode>,
+}
+
+/// Dump the top-level declarations of `main_file`, or only the definition of `function` if given
+pub fn dump_c_ast<W: Write>(
+    context: &TypedAstContext,
+    main_file: &Path,
+    format: AstDumpFormat,
+    function: Option<&str>,
+    out: &mut W,
+) -> io::Result<()> {
+    let main_file_id = context.find_file_id(main_file);
+    let roots = context
+        .c_decls_top
+        .iter()
+        .filter(|&&decl_id| {
+            let decl = &context[decl_id];
+            match function {
+                Some(function) => matches!(
+                    decl.kind,
+                    CDeclKind::Function { ref name, body: Some(_), .. } if name == function
+                ),
+                None => main_file_id.is_some() && context.file_id(decl) == main_file_id,
+            }
+        })
+        .map(|&decl_id| dump_node(context, SomeId::Decl(decl_id)))
+        .collect::<Vec<_>>();
+
+    if let (Some(function), true) = (function, roots.is_empty()) {
+        warn!("--dump-c-ast: no definition of function {} found", function);
+    }
+
+    match format {
+        AstDumpFormat::Text => {
+            for root in &roots {
+                write_text(out, root, 0)?;
+            }
+            Ok(())
+        }
+        AstDumpFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, &roots)?;
+            writeln!(out)
+        }
+    }
+}
+
+fn dump_node(context: &TypedAstContext, id: SomeId) -> DumpNode {
+    let (node, raw_id, kind, loc, ty, detail) = match id {
+        SomeId::Decl(decl_id) => {
+            let decl = &context[decl_id];
+            let ty = match decl.kind {
+                CDeclKind::Function { typ, .. } => Some(describe_type(context, typ)),
+                CDeclKind::Variable { typ, .. }
+                | CDeclKind::Typedef { typ, .. }
+                | CDeclKind::Field { typ, .. } => Some(describe_qual_type(context, typ)),
+                _ => None,
+            };
+            let detail = match decl.kind {
+                // Non-canonical declarations point back at the canonical one, which is
+                // dumped on its own
+                CDeclKind::NonCanonicalDecl { canonical_decl } => {
+                    Some(format!("canonical #{}", canonical_decl.0))
+                }
+                ref kind => kind.get_name().cloned(),
+            };
+            (
+                "Decl",
+                decl_id.0,
+                variant_name(&decl.kind),
+                &decl.loc,
+                ty,
+                detail,
+            )
+        }
+        SomeId::Stmt(stmt_id) => {
+            let stmt = &context[stmt_id];
+            (
+                "Stmt",
+                stmt_id.0,
+                variant_name(&stmt.kind),
+                &stmt.loc,
+                None,
+                None,
+            )
+        }
+        SomeId::Expr(expr_id) => {
+            let expr = &context[expr_id];
+            let ty = expr
+                .kind
+                .get_qual_type()
+                .map(|qty| describe_qual_type(context, qty));
+            let detail = match expr.kind {
+                CExprKind::Literal(_, ref lit) => Some(format!("{:?}", lit)),
+                CExprKind::Unary(_, op, _, _) => Some(format!("{:?}", op)),
+                CExprKind::Binary(_, op, ..) => Some(format!("{:?}", op)),
+                CExprKind::ImplicitCast(_, _, kind, ..)
+                | CExprKind::ExplicitCast(_, _, kind, ..) => Some(format!("{:?}", kind)),
+                CExprKind::DeclRef(_, decl_id, _) => Some(decl_ref(context, decl_id)),
+                CExprKind::Member(_, _, field_id, _, _) => Some(decl_ref(context, field_id)),
+                _ => None,
+            };
+            (
+                "Expr",
+                expr_id.0,
+                variant_name(&expr.kind),
+                &expr.loc,
+                ty,
+                detail,
+            )
+        }
+        SomeId::Type(_) => unreachable!("types are described inline"),
+    };
+
+    let children = match id {
+        SomeId::Decl(decl_id)
+            if matches!(context[decl_id].kind, CDeclKind::NonCanonicalDecl { .. }) =>
+        {
+            vec![]
+        }
+        _ => immediate_children(context, id)
+            .into_iter()
+            .filter(|child| !matches!(child, SomeId::Type(_)))
+            .map(|child| dump_node(context, child))
+            .collect(),
+    };
+
+    DumpNode {
+        node,
+        id: raw_id,
+        kind,
+        span: loc.as_ref().map(|loc| display_span(context, loc)),
+        ty,
+        detail,
+        children,
+    }
+}
+
+fn write_text<W: Write>(out: &mut W, node: &DumpNode, depth: usize) -> io::Result<()> {
+    write!(
+        out,
+        "{:indent$}{} #{} {}",
+        "",
+        node.node,
+        node.id,
+        node.kind,
+        indent = depth * 2
+    )?;
+    if let Some(ref detail) = node.detail {
+        write!(out, " {}", detail)?;
+    }
+    if let Some(ref ty) = node.ty {
+        write!(out, " '{}'", ty)?;
+    }
+    if let Some(ref span) = node.span {
+        write!(out, " <{}>", span)?;
+    }
+    writeln!(out)?;
+    for child in &node.children {
+        write_text(out, child, depth + 1)?;
+    }
+    Ok(())
+}
+
+/// The name of an enum variant, taken from its `Debug` output
+fn variant_name<T: Debug>(kind: &T) -> String {
+    let debug = format!("{:?}", kind);
+    debug
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .next()
+        .unwrap_or_default()
+        .to_owned()
+}
+
+fn decl_ref(context: &TypedAstContext, decl_id: CDeclId) -> String {
+    match context[decl_id].kind.get_name() {
+        Some(name) => format!("{} #{}", name, decl_id.0),
+        None => format!("#{}", decl_id.0),
+    }
+}
+
+fn display_span(context: &TypedAstContext, loc: &SrcSpan) -> String {
+    let file = context.files[context.file_map[loc.fileid as usize]]
+        .path
+        .as_ref()
+        .map_or_else(|| "<unknown>".to_owned(), |path| path.display().to_string());
+    format!(
+        "{}:{}:{}-{}:{}",
+        file, loc.begin_line, loc.begin_column, loc.end_line, loc.end_column
+    )
+}
+
+fn describe_qual_type(context: &TypedAstContext, qty: CQualTypeId) -> String {
+    let mut ty = String::new();
+    if qty.qualifiers.is_const {
+        ty.push_str("const ");
+    }
+    if qty.qualifiers.is_volatile {
+        ty.push_str("volatile ");
+    }
+    ty.push_str(&describe_type(context, qty.ctype));
+    ty
+}
+
+/// Describe a type in C-like syntax, keeping typedef names
+fn describe_type(context: &TypedAstContext, ty: CTypeId) -> String {
+    let record_name = |keyword: &str, decl_id: CDeclId| match context[decl_id].kind.get_name() {
+        Some(name) => format!("{} {}", keyword, name),
+        None => format!("{} <anonymous #{}>", keyword, decl_id.0),
+    };
+    match context[ty].kind {
+        CTypeKind::Void => "void".to_owned(),
+        CTypeKind::Bool => "_Bool".to_owned(),
+        CTypeKind::Char => "char".to_owned(),
+        CTypeKind::SChar => "signed char".to_owned(),
+        CTypeKind::Short => "short".to_owned(),
+        CTypeKind::Int => "int".to_owned(),
+        CTypeKind::Long => "long".to_owned(),
+        CTypeKind::LongLong => "long long".to_owned(),
+        CTypeKind::UChar => "unsigned char".to_owned(),
+        CTypeKind::UShort => "unsigned short".to_owned(),
+        CTypeKind::UInt => "unsigned int".to_owned(),
+        CTypeKind::ULong => "unsigned long".to_owned(),
+        CTypeKind::ULongLong => "unsigned long long".to_owned(),
+        CTypeKind::Float => "float".to_owned(),
+        CTypeKind::Double => "double".to_owned(),
+        CTypeKind::LongDouble => "long double".to_owned(),
+        CTypeKind::Int128 => "__int128".to_owned(),
+        CTypeKind::UInt128 => "unsigned __int128".to_owned(),
+        CTypeKind::Half => "_Float16".to_owned(),
+        CTypeKind::BFloat16 => "__bf16".to_owned(),
+        CTypeKind::Complex(ty) => format!("_Complex {}", describe_type(context, ty)),
+        CTypeKind::Pointer(qty) => format!("{} *", describe_qual_type(context, qty)),
+        CTypeKind::Reference(qty) => format!("{} &", describe_qual_type(context, qty)),
+        CTypeKind::BlockPointer(qty) => format!("{} ^", describe_qual_type(context, qty)),
+        CTypeKind::ConstantArray(ty, len) => format!("{} [{}]", describe_type(context, ty), len),
+        CTypeKind::IncompleteArray(ty) => format!("{} []", describe_type(context, ty)),
+        CTypeKind::VariableArray(ty, _) => format!("{} [*]", describe_type(context, ty)),
+        CTypeKind::TypeOf(ty)
+        | CTypeKind::Auto(ty)
+        | CTypeKind::Decayed(ty)
+        | CTypeKind::Elaborated(ty)
+        | CTypeKind::Paren(ty) => describe_type(context, ty),
+        CTypeKind::Attributed(qty, _) => describe_qual_type(context, qty),
+        CTypeKind::TypeOfExpr(expr_id) => format!("typeof(expr #{})", expr_id.0),
+        CTypeKind::Function(ret, ref params, is_variadic, _, _) => {
+            let mut params = params
+                .iter()
+                .map(|&param| describe_qual_type(context, param))
+                .collect::<Vec<_>>();
+            if is_variadic {
+                params.push("...".to_owned());
+            }
+            format!(
+                "{} ({})",
+                describe_qual_type(context, ret),
+                params.join(", ")
+            )
+        }
+        CTypeKind::Typedef(decl_id) => match context[decl_id].kind.get_name() {
+            Some(name) => name.clone(),
+            None => format!("typedef #{}", decl_id.0),
+        },
+        CTypeKind::Struct(decl_id) => record_name("struct", decl_id),
+        CTypeKind::Union(decl_id) => record_name("union", decl_id),
+        CTypeKind::Enum(decl_id) => record_name("enum", decl_id),
+        CTypeKind::BuiltinFn => "<builtin fn>".to_owned(),
+        CTypeKind::Vector(qty, len) => {
+            format!("{} vector[{}]", describe_qual_type(context, qty), len)
+        }
+        CTypeKind::FixedPoint(ref name, _, _) => name.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    use regex::Regex;
+    use serde_json::{json, Value};
+
+    use crate::transpiler::single_file_db;
+
+    const SOURCE: &str = "int add(int a, int b) { return a + b; }\n";
+
+    /// Dump `SOURCE` with ids replaced by `#N` and the file named by its name alone
+    fn dump(format: AstDumpFormat) -> String {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().canonicalize().unwrap();
+        let path = dir.join("add.c");
+        fs::write(&path, SOURCE).unwrap();
+        let cc_db = single_file_db(&dir, &path, &[]).unwrap();
+        let untyped_context =
+            c2rust_ast_exporter::get_untyped_ast(&path, &cc_db, &[], false).unwrap();
+        let context = ConversionContext::new(&untyped_context).typed_context;
+
+        let mut out = vec![];
+        dump_c_ast(&context, &path, format, None, &mut out).unwrap();
+        let dumped = String::from_utf8(out).unwrap();
+        let dumped = dumped.replace(&format!("{}/", dir.display()), "");
+        Regex::new(r"#\d+")
+            .unwrap()
+            .replace_all(&dumped, "#N")
+            .into_owned()
+    }
+
+    #[test]
+    fn dump_text() {
+        let expected = "\
+Decl #N Function add 'int (int, int)' <add.c:1:1-1:39>
+  Decl #N Variable a 'int' <add.c:1:9-1:13>
+  Decl #N Variable b 'int' <add.c:1:16-1:20>
+  Stmt #N Compound <add.c:1:23-1:39>
+    Stmt #N Return <add.c:1:25-1:36>
+      Expr #N Binary Add 'int' <add.c:1:32-1:36>
+        Expr #N ImplicitCast LValueToRValue 'int' <add.c:1:32-1:32>
+          Expr #N DeclRef a #N 'int' <add.c:1:32-1:32>
+        Expr #N ImplicitCast LValueToRValue 'int' <add.c:1:36-1:36>
+          Expr #N DeclRef b #N 'int' <add.c:1:36-1:36>
+";
+        assert_eq!(dump(AstDumpFormat::Text), expected);
+    }
+
+    #[test]
+    fn dump_json() {
+        let mut dumped: Value = serde_json::from_str(&dump(AstDumpFormat::Json)).unwrap();
+        // Ids are numbers in JSON, so they are left out rather than replaced
+        fn remove_ids(node: &mut Value) {
+            match node {
+                Value::Object(fields) => {
+                    fields.remove("id");
+                    fields.values_mut().for_each(remove_ids);
+                }
+                Value::Array(nodes) => nodes.iter_mut().for_each(remove_ids),
+                _ => {}
+            }
+        }
+        remove_ids(&mut dumped);
+
+        let decl_ref = |name: &str, column: usize| {
+            let span = format!("add.c:1:{}-1:{}", column, column);
+            json!({
+                "node": "Expr",
+                "kind": "ImplicitCast",
+                "span": span,
+                "type": "int",
+                "detail": "LValueToRValue",
+                "children": [{
+                    "node": "Expr",
+                    "kind": "DeclRef",
+                    "span": span,
+                    "type": "int",
+                    "detail": format!("{} #N", name),
+                }],
+            })
+        };
+        let expected = json!([{
+            "node": "Decl",
+            "kind": "Function",
+            "span": "add.c:1:1-1:39",
+            "type": "int (int, int)",
+            "detail": "add",
+            "children": [
+                {
+                    "node": "Decl",
+                    "kind": "Variable",
+                    "span": "add.c:1:9-1:13",
+                    "type": "int",
+                    "detail": "a",
+                },
+                {
+                    "node": "Decl",
+                    "kind": "Variable",
+                    "span": "add.c:1:16-1:20",
+                    "type": "int",
+                    "detail": "b",
+                },
+                {
+                    "node": "Stmt",
+                    "kind": "Compound",
+                    "span": "add.c:1:23-1:39",
+                    "children": [{
+                        "node": "Stmt",
+                        "kind": "Return",
+                        "span": "add.c:1:25-1:36",
+                        "children": [{
+                            "node": "Expr",
+                            "kind": "Binary",
+                            "span": "add.c:1:32-1:36",
+                            "type": "int",
+                            "detail": "Add",
+                            "children": [decl_ref("a", 32), decl_ref("b", 36)],
+                        }],
+                    }],
+                },
+            ],
+        }]);
+        assert_eq!(dumped, expected);
+    }
+}
@@ -42,7 +42,8 @@ fn immediate_expr_children(kind: &CExprKind) -> Vec<SomeId> {
         BadExpr => vec![],
         DesignatedInitExpr(..) => vec![], // the relevant information will be found in the semantic initializer
         ShuffleVector(..) | ConvertVector(..) => vec![],
-        OffsetOf(..) | Literal(..) | ImplicitValueInit(..) => vec![],
+        OffsetOf(_, ref kind) => kind.index_exprs().into_iter().map(SomeId::from).collect(),
+        Literal(..) | ImplicitValueInit(..) | Embed(..) => vec![],
         DeclRef(..) => vec![], // don't follow references back!
         Unary(_, _, subexpr, _) | ConstantExpr(_, subexpr, _) => intos![subexpr],
         UnaryType(_ty, _op, opt_expr_id, _) => opt_expr_id.iter().map(|&x| x.into()).collect(),
@@ -92,8 +93,15 @@ fn immediate_expr_children_all_types(kind: &CExprKind) -> Vec<SomeId> {
         }
         // We need to iterate the struct type if this offsetof is variable,
         // since it may not get instantiated
-        OffsetOf(_, OffsetOfKind::Variable(qty, _, _)) => intos![qty.ctype],
-        OffsetOf(..) | Literal(..) | ImplicitValueInit(..) => vec![],
+        OffsetOf(_, ref kind) => {
+            let mut res = match *kind {
+                OffsetOfKind::Variable(qty, _) => intos![qty.ctype],
+                OffsetOfKind::Constant(_) => vec![],
+            };
+            res.extend(kind.index_exprs().into_iter().map(SomeId::from));
+            res
+        }
+        Literal(..) | ImplicitValueInit(..) | Embed(..) => vec![],
         DeclRef(..) => vec![], // don't follow references back!
         Unary(_, _, subexpr, _) | ConstantExpr(_, subexpr, _) => intos![subexpr],
         UnaryType(_ty, _op, opt_expr_id, qty) => {
@@ -203,6 +211,7 @@ fn immediate_stmt_children(kind: &CStmtKind) -> Vec<SomeId> {
         Expr(e) => intos![e],
         Label(s) => intos![s],
         Case(e, s, _) => intos![e, s],
+        CaseRange(lo, hi, s, _, _) => intos![lo, hi, s],
         Default(s) => intos![s],
 
         // Compound statements (6.8.2)
@@ -275,6 +284,8 @@ fn immediate_stmt_children(kind: &CStmtKind) -> Vec<SomeId> {
         Attributed {
             substatement: s, ..
         } => intos![s],
+
+        Seh(ref blocks) => blocks.iter().map(|&x| x.into()).collect(),
     }
 }
 
@@ -285,7 +296,7 @@ fn immediate_type_children(kind: &CTypeKind) -> Vec<SomeId> {
         TypeOfExpr(e) => intos![e],
         Void | Bool | Short | Int | Long | LongLong | UShort | UInt | ULong | ULongLong | SChar
         | UChar | Char | Double | LongDouble | Float | Int128 | UInt128 | BuiltinFn | Half
-        | BFloat16 => {
+        | BFloat16 | FixedPoint(..) => {
             vec![]
         }
 
@@ -300,6 +311,7 @@ fn immediate_type_children(kind: &CTypeKind) -> Vec<SomeId> {
         Decayed(ctype)
         | Paren(ctype)
         | TypeOf(ctype)
+        | Auto(ctype)
         | Complex(ctype)
         | ConstantArray(ctype, _)
         | IncompleteArray(ctype) => intos![ctype],
@@ -323,7 +335,7 @@ fn immediate_type_children(kind: &CTypeKind) -> Vec<SomeId> {
     }
 }
 
-fn immediate_children(context: &TypedAstContext, s_or_e: SomeId) -> Vec<SomeId> {
+pub(crate) fn immediate_children(context: &TypedAstContext, s_or_e: SomeId) -> Vec<SomeId> {
     match s_or_e {
         SomeId::Stmt(stmt_id) => immediate_stmt_children(&context[stmt_id].kind),
         SomeId::Expr(expr_id) => immediate_expr_children(&context[expr_id].kind),
@@ -357,6 +369,12 @@ impl<'context> DFExpr<'context> {
         let new_len = self.stack.len() - n;
         self.stack.truncate(new_len)
     }
+
+    /// Skip the children of `last`, the node most recently returned by `next`
+    pub fn prune_children(&mut self, last: SomeId) {
+        let n = immediate_children(self.context, last).len();
+        self.prune(n)
+    }
 }
 
 impl<'context> Iterator for DFExpr<'context> {
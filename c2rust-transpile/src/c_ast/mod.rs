@@ -2,7 +2,7 @@ use c2rust_ast_exporter::clang_ast::LRValue;
 use indexmap::{IndexMap, IndexSet};
 use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::collections::{HashMap, HashSet};
+use std::collections::{hash_map, HashMap, HashSet};
 use std::fmt::{self, Debug, Display};
 use std::mem;
 use std::ops::Index;
@@ -34,9 +34,11 @@ pub type CEnumId = CDeclId; // Enum types need to point to 'DeclKind::Enum'
 pub type CEnumConstantId = CDeclId; // Enum's need to point to child 'DeclKind::EnumConstant's
 
 pub use self::conversion::*;
+pub use self::dump::{dump_c_ast, AstDumpFormat};
 pub use self::print::Printer;
 
 mod conversion;
+mod dump;
 pub mod iterators;
 mod print;
 
@@ -82,6 +84,9 @@ pub struct TypedAstContext {
 
     pub comments: Vec<Located<String>>,
 
+    // `#pragma` directives outside of system headers, starting at the `#`
+    pub pragmas: Vec<Located<String>>,
+
     // The key is the typedef decl being squashed away,
     // and the value is the decl id to the corresponding structure
     pub prenamed_decls: IndexMap<CDeclId, CDeclId>,
@@ -102,6 +107,16 @@ pub struct DisplaySrcSpan {
     loc: SrcSpan,
 }
 
+impl DisplaySrcSpan {
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    pub fn span(&self) -> &SrcSpan {
+        &self.loc
+    }
+}
+
 impl Display for DisplaySrcSpan {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if let Some(ref file) = self.file {
@@ -185,6 +200,7 @@ impl TypedAstContext {
             label_names: Default::default(),
 
             comments: vec![],
+            pragmas: vec![],
             prenamed_decls: IndexMap::new(),
             va_list_kind: BuiltinVaListKind::CharPtrBuiltinVaList,
             target: String::new(),
@@ -268,6 +284,14 @@ impl TypedAstContext {
         self.c_decls.iter_mut()
     }
 
+    pub fn iter_exprs(&self) -> hash_map::Iter<CExprId, CExpr> {
+        self.c_exprs.iter()
+    }
+
+    pub fn iter_stmts(&self) -> hash_map::Iter<CStmtId, CStmt> {
+        self.c_stmts.iter()
+    }
+
     pub fn get_decl(&self, key: &CDeclId) -> Option<&CDecl> {
         self.c_decls.get(key)
     }
@@ -380,6 +404,81 @@ impl TypedAstContext {
         }
     }
 
+    /// Whether any of the types is a half-precision floating point type
+    pub fn uses_half_floats(&self) -> bool {
+        self.c_types.values().any(|ty| ty.kind.is_half_float_type())
+    }
+
+    /// The structs and unions that values of type `typ` contain or point to, through pointers,
+    /// array elements and the fields of records
+    pub fn reached_records(&self, typ: CTypeId) -> IndexSet<CRecordId> {
+        let mut records = IndexSet::new();
+        let mut to_walk = vec![typ];
+        while let Some(typ) = to_walk.pop() {
+            match self.resolve_type(typ).kind {
+                CTypeKind::Pointer(pointee) => to_walk.push(pointee.ctype),
+                CTypeKind::ConstantArray(elt, _)
+                | CTypeKind::IncompleteArray(elt)
+                | CTypeKind::VariableArray(elt, _) => to_walk.push(elt),
+                CTypeKind::Struct(record_id) | CTypeKind::Union(record_id) => {
+                    if !records.insert(record_id) {
+                        continue;
+                    }
+                    if let CDeclKind::Struct {
+                        fields: Some(ref fields),
+                        ..
+                    }
+                    | CDeclKind::Union {
+                        fields: Some(ref fields),
+                        ..
+                    } = self[record_id].kind
+                    {
+                        for &field_id in fields {
+                            if let CDeclKind::Field { typ, .. } = self[field_id].kind {
+                                to_walk.push(typ.ctype);
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        records
+    }
+
+    /// The types shared with other translation units, along with the declarations sharing them:
+    /// those in the signatures of the functions defined elsewhere or externally visible, and
+    /// those of externally visible variables
+    pub fn external_types(&self) -> Vec<(CDeclId, CTypeId)> {
+        let mut types = vec![];
+        for (&decl_id, decl) in &self.c_decls {
+            match decl.kind {
+                CDeclKind::Function {
+                    body,
+                    is_global,
+                    is_inline,
+                    is_inline_externally_visible,
+                    typ,
+                    ..
+                } if body.is_none()
+                    || is_global && (!is_inline || is_inline_externally_visible) =>
+                {
+                    if let CTypeKind::Function(ret, ref params, ..) = self.resolve_type(typ).kind {
+                        types.push((decl_id, ret.ctype));
+                        types.extend(params.iter().map(|param| (decl_id, param.ctype)));
+                    }
+                }
+                CDeclKind::Variable {
+                    is_externally_visible: true,
+                    typ,
+                    ..
+                } => types.push((decl_id, typ.ctype)),
+                _ => {}
+            }
+        }
+        types
+    }
+
     /// Predicate for function pointers
     pub fn is_function_pointer(&self, typ: CTypeId) -> bool {
         let resolved_ctype = self.resolve_type(typ);
@@ -394,6 +493,30 @@ impl TypedAstContext {
         }
     }
 
+    /// Predicate for function pointers marked `_Nonnull`, which are translated into plain
+    /// function pointers rather than `Option`s of them. The outermost nullability annotation
+    /// around the pointer type decides.
+    pub fn is_nonnull_fn_pointer(&self, mut typ: CTypeId) -> bool {
+        loop {
+            match self.index(typ).kind {
+                CTypeKind::Attributed(ty, Some(Attribute::NotNull)) => {
+                    return self.is_function_pointer(ty.ctype)
+                }
+                CTypeKind::Attributed(_, Some(Attribute::Nullable)) => return false,
+                CTypeKind::Attributed(ty, _) => typ = ty.ctype,
+                CTypeKind::Elaborated(ty)
+                | CTypeKind::Decayed(ty)
+                | CTypeKind::Paren(ty)
+                | CTypeKind::TypeOf(ty) => typ = ty,
+                CTypeKind::Typedef(decl) => match self.index(decl).kind {
+                    CDeclKind::Typedef { typ: ty, .. } => typ = ty.ctype,
+                    _ => return false,
+                },
+                _ => return false,
+            }
+        }
+    }
+
     /// Can the given field decl be a flexible array member?
     pub fn maybe_flexible_array(&self, typ: CTypeId) -> bool {
         let field_ty = self.resolve_type(typ);
@@ -460,6 +583,7 @@ impl TypedAstContext {
             CTypeKind::Elaborated(ty) => self.resolve_type_id(ty),
             CTypeKind::Decayed(ty) => self.resolve_type_id(ty),
             CTypeKind::TypeOf(ty) => self.resolve_type_id(ty),
+            CTypeKind::Auto(ty) => self.resolve_type_id(ty),
             CTypeKind::Paren(ty) => self.resolve_type_id(ty),
             CTypeKind::Typedef(decl) => match self.index(decl).kind {
                 CDeclKind::Typedef { typ: ty, .. } => self.resolve_type_id(ty.ctype),
@@ -495,6 +619,7 @@ impl TypedAstContext {
             CExprKind::Atomic{..} => false,
 
             CExprKind::Literal(_, _) |
+            CExprKind::Embed(..) |
             CExprKind::DeclRef(_, _, _) |
             CExprKind::UnaryType(_, _, _, _) |
             CExprKind::OffsetOf(..) |
@@ -518,14 +643,37 @@ impl TypedAstContext {
         }
     }
 
+    /// Is this a function declared `_Noreturn` or `__attribute__((noreturn))`? Unlike the
+    /// latter, `_Noreturn` is not part of the function type.
+    pub fn is_noreturn_decl(&self, decl_id: CDeclId) -> bool {
+        match self.index(decl_id).kind {
+            CDeclKind::Function { ref attrs, .. } => attrs.contains(&Attribute::NoReturn),
+            _ => false,
+        }
+    }
+
     // Pessimistically try to check if an expression doesn't return. If it does, or we can't tell
     /// that it doesn't, return `false`.
     pub fn expr_diverges(&self, expr_id: CExprId) -> bool {
         let func_id = match self.index(expr_id).kind {
             CExprKind::Call(_, func_id, _) => func_id,
+            CExprKind::Paren(_, e) | CExprKind::ExplicitCast(_, e, CastKind::ToVoid, _, _) => {
+                return self.expr_diverges(e)
+            }
             _ => return false,
         };
 
+        // Direct calls to functions declared `_Noreturn`
+        if let CExprKind::ImplicitCast(_, fexp, CastKind::FunctionToPointerDecay, _, _) =
+            self.index(func_id).kind
+        {
+            if let CExprKind::DeclRef(_, decl_id, _) = self.index(fexp).kind {
+                if self.is_noreturn_decl(decl_id) {
+                    return true;
+                }
+            }
+        }
+
         let type_id = match self[func_id].kind.get_type() {
             None => return false,
             Some(t) => t,
@@ -541,7 +689,19 @@ impl TypedAstContext {
         }
     }
 
-    pub fn prune_unwanted_decls(&mut self, want_unused_functions: bool) {
+    /// Remove the declarations that can't be reached from a root, returning the top-level ones
+    /// that were removed.
+    ///
+    /// When `main_file` is set and defines `main`, the translation unit is a program, and only the
+    /// externally visible definitions of `main_file` stay roots next to `main`: those of the
+    /// headers it includes are kept only if they are reachable. Every declaration of
+    /// `bound_file`, the header translated into bindings, is a root.
+    pub fn prune_unwanted_decls(
+        &mut self,
+        want_unused_functions: bool,
+        main_file: Option<FileId>,
+        bound_file: Option<FileId>,
+    ) -> Vec<(CDeclId, CDecl)> {
         // Starting from a set of root declarations, walk each one to find declarations it
         // depends on. Then walk each of those, recursively.
 
@@ -554,9 +714,21 @@ impl TypedAstContext {
         // be visible from another compilation unit.
         //
         // In addition, mark any other (unused) function wanted if configured.
+        let main_defn = self.c_main.filter(|&main_id| {
+            matches!(self.index(main_id).kind, CDeclKind::Function { body: Some(_), .. })
+        });
+        let main_file = main_file.filter(|_| main_defn.is_some());
         for &decl_id in &self.c_decls_top {
             let decl = self.index(decl_id);
             let is_wanted = match decl.kind {
+                _ if bound_file.is_some() && self.file_id(decl) == bound_file => true,
+                _ if main_file.is_some() && main_defn == Some(decl_id) => true,
+                CDeclKind::Variable { ref attrs, .. } | CDeclKind::Function { ref attrs, .. }
+                    if attrs.contains(&Attribute::Used) => true,
+                // Static assertions are translated into compile-time checks
+                CDeclKind::StaticAssert { .. } => true,
+                // Other files may still link against the definitions of the program itself
+                _ if main_file.is_some() && self.file_id(decl) != main_file => false,
                 CDeclKind::Function {
                     body: Some(_),
                     is_global: true,
@@ -575,8 +747,6 @@ impl TypedAstContext {
                     is_externally_visible: true,
                     ..
                 } => true,
-                CDeclKind::Variable { ref attrs, .. } | CDeclKind::Function { ref attrs, .. }
-                    if attrs.contains(&Attribute::Used) => true,
                 _ => false,
             };
 
@@ -661,12 +831,22 @@ impl TypedAstContext {
             }
         }
 
+        // Keep the top declarations that are about to be pruned, for reporting
+        let pruned = self
+            .c_decls_top
+            .iter()
+            .filter(|x| !wanted.contains(x))
+            .map(|&decl_id| (decl_id, self.c_decls[&decl_id].clone()))
+            .collect();
+
         // Prune any declaration that isn't considered live
         self.c_decls
             .retain(|&decl_id, _decl| wanted.contains(&decl_id));
 
         // Prune top declarations that are not considered live
         self.c_decls_top.retain(|x| wanted.contains(x));
+
+        pruned
     }
 
     pub fn sort_top_decls(&mut self) {
@@ -1011,9 +1191,33 @@ pub enum OffsetOfKind {
     /// An Integer Constant Expr
     Constant(u64),
     /// Contains more information to generate
-    /// an offset_of! macro invocation
-    /// Struct Type, Field Decl Id, Index Expr
-    Variable(CQualTypeId, CDeclId, CExprId),
+    /// offset_of! macro invocations
+    /// Struct Type, Designator Components
+    Variable(CQualTypeId, Vec<OffsetOfComponent>),
+}
+
+/// A component of the member designator of a non-constant `offsetof`, e.g. `offsetof(struct S,
+/// a.b[i])` has the components `a`, `b` and `[i]`
+#[derive(Debug, Clone, Copy)]
+pub enum OffsetOfComponent {
+    Field(CFieldId),
+    Index(CExprId),
+}
+
+impl OffsetOfKind {
+    /// The array index expressions of a non-constant `offsetof`
+    pub fn index_exprs(&self) -> Vec<CExprId> {
+        match *self {
+            OffsetOfKind::Constant(_) => vec![],
+            OffsetOfKind::Variable(_, ref components) => components
+                .iter()
+                .filter_map(|component| match *component {
+                    OffsetOfComponent::Index(expr_id) => Some(expr_id),
+                    OffsetOfComponent::Field(_) => None,
+                })
+                .collect(),
+        }
+    }
 }
 
 /// Represents an expression in C (6.5 Expressions)
@@ -1107,6 +1311,9 @@ pub enum CExprKind {
     // GNU choose expr. Condition, true expr, false expr, was condition true?
     Choose(CQualTypeId, CExprId, CExprId, CExprId, bool),
 
+    // C23 `#embed`, with the bytes of the file it expands to
+    Embed(CQualTypeId, Vec<u8>),
+
     // GNU/C11 atomic expr
     Atomic {
         typ: CQualTypeId,
@@ -1166,6 +1373,7 @@ impl CExprKind {
             | CExprKind::VAArg(ty, _)
             | CExprKind::ShuffleVector(ty, _)
             | CExprKind::ConvertVector(ty, _)
+            | CExprKind::Embed(ty, _)
             | CExprKind::DesignatedInitExpr(ty, _, _)
             | CExprKind::ConstantExpr(ty, _, _) => Some(ty),
             CExprKind::Choose(ty, _, _, _, _) | CExprKind::Atomic { typ: ty, .. } => Some(ty),
@@ -1218,6 +1426,8 @@ pub enum CastKind {
     BuiltinFnToFnPtr,
     ConstCast,
     VectorSplat,
+    // Any conversion from or to an embedded C fixed-point type
+    FixedPoint,
 }
 
 /// Represents a unary operator in C (6.5.3 Unary operators) and GNU C extensions
@@ -1379,6 +1589,8 @@ pub enum CStmtKind {
     // All of these have a `CStmtId` to represent the substatement that comes after them
     Label(CStmtId),
     Case(CExprId, CStmtId, ConstIntExpr),
+    /// GNU case range extension (`case lo ... hi:`)
+    CaseRange(CExprId, CExprId, CStmtId, ConstIntExpr, ConstIntExpr),
     Default(CStmtId),
 
     // Compound statements (6.8.2)
@@ -1431,6 +1643,8 @@ pub enum CStmtKind {
         outputs: Vec<AsmOperand>,
         clobbers: Vec<String>,
         is_volatile: bool,
+        // Labels an `asm goto` may jump to
+        labels: Vec<CLabelId>,
     },
 
     // Statements annotated with attributes. The substatement can be a NULL
@@ -1440,6 +1654,10 @@ pub enum CStmtKind {
         attributes: Vec<Attribute>,
         substatement: CStmtId,
     },
+
+    // Microsoft structured exception handling, which isn't translated: `__try` with the blocks
+    // of the statement and of its `__except` or `__finally` handler, or `__leave` without any
+    Seh(Vec<CStmtId>),
 }
 
 #[derive(Clone, Debug)]
@@ -1564,6 +1782,10 @@ pub enum CTypeKind {
     TypeOf(CTypeId),
     TypeOfExpr(CExprId),
 
+    // Type deduced from the initializer of a `__auto_type` (GCC extension) or C23 `auto`
+    // declaration
+    Auto(CTypeId),
+
     // Function type (6.7.5.3)
     //
     // Note a function taking no arguments should have one `void` argument. Functions without any
@@ -1598,8 +1820,14 @@ pub enum CTypeKind {
 
     Vector(CQualTypeId, usize),
 
+    // `__fp16` and `_Float16`
     Half,
+    // `__bf16`
     BFloat16,
+
+    // Embedded C fixed-point type (`_Fract`, `_Accum`, `_Sat`), with its C spelling, width in
+    // bits and signedness
+    FixedPoint(String, u64, bool),
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -1614,15 +1842,28 @@ pub enum Designator {
 pub enum Attribute {
     /// __attribute__((alias("foo"), __alias__("foo")))
     Alias(String),
+    /// __attribute__((annotate("foo")))
+    Annotate(String),
     /// __attribute__((always_inline, __always_inline__))
     AlwaysInline,
     /// __attribute__((cold, __cold__))
     Cold,
+    /// The calling convention of a function other than the C one, as named by clang, e.g.
+    /// `stdcall` for `__stdcall` or __attribute__((stdcall))
+    CallConv(String),
+    /// __declspec(dllexport)
+    DllExport,
+    /// __declspec(dllimport)
+    DllImport,
     /// __attribute__((gnu_inline, __gnu_inline__))
     GnuInline,
     /// __attribute__((no_inline, __no_inline__))
     NoInline,
+    /// _Noreturn, __attribute__((noreturn, __noreturn__))
     NoReturn,
+    /// __attribute__((nonnull(1, 2))) with the indices of the parameters counting from 0, which
+    /// are all of them if there are none, or __attribute__((nonnull)) on a parameter
+    NonNull(Vec<usize>),
     NotNull,
     Nullable,
     /// __attribute__((section("foo"), __section__("foo")))
@@ -1692,10 +1933,16 @@ impl CTypeKind {
             CTypeKind::Float => true,
             CTypeKind::Double => true,
             CTypeKind::LongDouble => true,
+            CTypeKind::Half => true,
+            CTypeKind::BFloat16 => true,
             _ => false,
         }
     }
 
+    pub fn is_half_float_type(&self) -> bool {
+        matches!(*self, CTypeKind::Half | CTypeKind::BFloat16)
+    }
+
     pub fn as_underlying_decl(&self) -> Option<CDeclId> {
         match *self {
             CTypeKind::Struct(decl_id) | CTypeKind::Union(decl_id) | CTypeKind::Enum(decl_id) => {
@@ -80,6 +80,7 @@ impl<W: Write> Printer<W> {
             }
             Some(&CExprKind::ShuffleVector(..)) => self.writer.write_all(b"SHUFFLE"),
             Some(&CExprKind::ConvertVector(..)) => self.writer.write_all(b"CONVERT"),
+            Some(&CExprKind::Embed(..)) => self.writer.write_all(b"EMBED"),
 
             Some(&CExprKind::Statements(_, compound_stmt_id)) => {
                 self.writer.write_all(b"(")?;
@@ -106,14 +107,26 @@ impl<W: Write> Printer<W> {
 
             Some(&CExprKind::OffsetOf(_, ref kind)) => match kind {
                 OffsetOfKind::Constant(val) => self.writer.write_fmt(format_args!("{}", val)),
-                OffsetOfKind::Variable(qty, decl_id, expr_id) => {
-                    self.writer.write_all(b"offset_of!(")?;
+                OffsetOfKind::Variable(qty, components) => {
+                    self.writer.write_all(b"offsetof(")?;
                     self.print_qtype(*qty, None, context)?;
                     self.writer.write_all(b", ")?;
-                    self.print_decl_name(*decl_id, context)?;
-                    self.writer.write_all(b"[")?;
-                    self.print_expr(*expr_id, context)?;
-                    self.writer.write_all(b"])")?;
+                    for (i, component) in components.iter().enumerate() {
+                        match *component {
+                            OffsetOfComponent::Field(field_id) => {
+                                if i > 0 {
+                                    self.writer.write_all(b".")?;
+                                }
+                                self.print_decl_name(field_id, context)?;
+                            }
+                            OffsetOfComponent::Index(expr_id) => {
+                                self.writer.write_all(b"[")?;
+                                self.print_expr(expr_id, context)?;
+                                self.writer.write_all(b"]")?;
+                            }
+                        }
+                    }
+                    self.writer.write_all(b")")?;
 
                     Ok(())
                 }
@@ -797,6 +810,7 @@ impl<W: Write> Printer<W> {
 
             Some(&CTypeKind::Elaborated(ref ctype)) => self.print_type(*ctype, ident, context),
             Some(&CTypeKind::Decayed(ref ctype)) => self.print_type(*ctype, ident, context),
+            Some(&CTypeKind::Auto(ref ctype)) => self.print_type(*ctype, ident, context),
             Some(&CTypeKind::Paren(ref ctype)) => {
                 self.parenthesize(true, |slf| slf.print_type(*ctype, ident, context))
             }
@@ -837,6 +851,9 @@ impl<W: Write> Printer<W> {
                     &CTypeKind::LongDouble => self.writer.write_all(b"long double"),
                     &CTypeKind::Int128 => self.writer.write_all(b"__int128"),
                     &CTypeKind::UInt128 => self.writer.write_all(b"unsigned __int128"),
+                    &CTypeKind::Half => self.writer.write_all(b"_Float16"),
+                    &CTypeKind::BFloat16 => self.writer.write_all(b"__bf16"),
+                    &CTypeKind::FixedPoint(ref name, ..) => self.writer.write_all(name.as_bytes()),
                     _ => unimplemented!("Printer::print_type({:?})", ty),
                 }?;
 
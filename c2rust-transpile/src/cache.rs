@@ -0,0 +1,222 @@
+//! The cache of translated files, for incremental translation.
+//!
+//! Translating a file records, in the `.c2rust-cache` directory of the crate, hashes of
+//! everything the translation depends on: the transpiler version and configuration, the compile
+//! command, and the contents of the file and every header it includes. When these still match on
+//! the next run and the outputs are as they were written, the file isn't exported or translated
+//! again, and the results it contributes to the crate are read from the cache instead. The hashes
+//! are SHA-256 hashes, which stay the same across builds of the transpiler, and the configuration
+//! is hashed as JSON.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use failure::Error;
+use regex::Regex;
+use serde::{Serialize, Serializer};
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use syn::__private::ToTokens;
+
+use crate::translator::ErrorCodes;
+use crate::{CrateSet, ExternCrate, PragmaVec, TranspilerConfig};
+
+/// The directory of the cache, relative to the build directory of a crate
+pub const CACHE_DIR: &str = ".c2rust-cache";
+
+/// The cached translation of a file
+#[derive(Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// The hash of the transpiler version and configuration, and of the compile command
+    key: String,
+    /// The file and the headers it includes, with the hashes of their contents
+    inputs: Vec<(PathBuf, String)>,
+    /// The files the translation wrote, with the hashes of their contents
+    outputs: Vec<(PathBuf, String)>,
+    pragmas: PragmaVec,
+    crates: Vec<ExternCrate>,
+    error_codes: ErrorCodes,
+    /// The items of the header modules, as source
+    header_mods: Vec<String>,
+}
+
+/// The results of a translation, as `transpile_single` returns them
+pub type CachedResult = (PragmaVec, CrateSet, ErrorCodes, Vec<Box<syn::Item>>);
+
+impl CacheEntry {
+    pub fn new(
+        key: String,
+        inputs: &[PathBuf],
+        outputs: &[PathBuf],
+        (pragmas, crates, error_codes, header_mods): &CachedResult,
+    ) -> Self {
+        let hashes = |paths: &[PathBuf]| -> Vec<(PathBuf, String)> {
+            let hashes = paths.iter().map(|path| Some((path.clone(), file_hash(path)?)));
+            hashes.flatten().collect()
+        };
+        CacheEntry {
+            key,
+            inputs: hashes(inputs),
+            outputs: hashes(outputs),
+            pragmas: pragmas.clone(),
+            crates: crates.iter().copied().collect(),
+            error_codes: error_codes.clone(),
+            header_mods: header_mods
+                .iter()
+                .map(|item| item.to_token_stream().to_string())
+                .collect(),
+        }
+    }
+
+    /// The results of the cached translation, unless any of its inputs or outputs changed
+    fn into_result(self, key: &str) -> Option<CachedResult> {
+        let unchanged = |files: &[(PathBuf, String)]| {
+            files
+                .iter()
+                .all(|(path, hash)| file_hash(path).as_ref() == Some(hash))
+        };
+        if self.key != key || !unchanged(&self.inputs) || !unchanged(&self.outputs) {
+            return None;
+        }
+
+        let header_mods = self
+            .header_mods
+            .iter()
+            .map(|item| syn::parse_str(item).map(Box::new))
+            .collect::<Result<_, _>>()
+            .ok()?;
+        Some((
+            self.pragmas,
+            self.crates.into_iter().collect(),
+            self.error_codes,
+            header_mods,
+        ))
+    }
+}
+
+/// The hash of everything the translation of `input_path` depends on besides the files it
+/// reads, leaving out `--force`. The header functions kept local to the file (see
+/// `--share-inline-fns`) are part of the key, so that a translation keeping them local is never
+/// reused as the first translation of the file. There is no key, and the translation isn't
+/// cached, when the configuration can't be serialized, such as with paths that aren't UTF-8.
+pub fn key(
+    tcfg: &TranspilerConfig,
+    input_path: &Path,
+    args: &[String],
+    clang_args: &[&str],
+    local_inline_fns: &HashSet<String>,
+) -> Option<String> {
+    let mut local_inline_fns = local_inline_fns.iter().collect::<Vec<_>>();
+    local_inline_fns.sort_unstable();
+    let inputs = (
+        env!("CARGO_PKG_VERSION"),
+        tcfg,
+        input_path,
+        args,
+        clang_args,
+        local_inline_fns,
+    );
+    let inputs = serde_json::to_vec(&inputs).ok()?;
+    Some(sha256(&inputs))
+}
+
+/// The cached translation of `input_path` into the crate in `build_dir`, if it is up to date
+pub fn load(build_dir: &Path, input_path: &Path, key: &str) -> Option<CachedResult> {
+    let contents = fs::read(entry_path(build_dir, input_path)).ok()?;
+    let entry: CacheEntry = serde_json::from_slice(&contents).ok()?;
+    entry.into_result(key)
+}
+
+/// Cache the translation of `input_path` into the crate in `build_dir`
+pub fn store(build_dir: &Path, input_path: &Path, entry: &CacheEntry) -> Result<(), Error> {
+    let path = entry_path(build_dir, input_path);
+    fs::create_dir_all(build_dir.join(CACHE_DIR))?;
+    fs::write(path, serde_json::to_vec(entry)?)?;
+    Ok(())
+}
+
+/// The path of the cache entry of `input_path`, named after the file and the hash of its path
+/// since files in different directories may share a name
+fn entry_path(build_dir: &Path, input_path: &Path) -> PathBuf {
+    let hash = sha256(input_path.to_string_lossy().as_bytes());
+    let stem = input_path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = format!("{}-{}.json", stem, &hash[..16]);
+    build_dir.join(CACHE_DIR).join(name)
+}
+
+fn file_hash(path: &Path) -> Option<String> {
+    let contents = fs::read(path).ok()?;
+    Some(sha256(&contents))
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+// Serializers of the parts of `TranspilerConfig` without a stable serialization of their own
+
+pub fn serialize_regex<S: Serializer>(regex: &Option<Regex>, s: S) -> Result<S::Ok, S::Error> {
+    regex.as_ref().map(Regex::as_str).serialize(s)
+}
+
+pub fn serialize_regexes<S: Serializer>(regexes: &[Regex], s: S) -> Result<S::Ok, S::Error> {
+    s.collect_seq(regexes.iter().map(Regex::as_str))
+}
+
+pub fn serialize_display<T: Display, S: Serializer>(value: &T, s: S) -> Result<S::Ok, S::Error> {
+    s.collect_str(value)
+}
+
+/// Serialize a set by the sorted names of its elements
+pub fn serialize_sorted<T: Display, S: Serializer>(
+    set: &HashSet<T>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    let mut names = set.iter().map(ToString::to_string).collect::<Vec<_>>();
+    names.sort_unstable();
+    names.serialize(s)
+}
+
+pub fn serialize_sorted_map<K: Ord + Serialize, V: Serialize, S: Serializer>(
+    map: &HashMap<K, V>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    map.iter().collect::<BTreeMap<_, _>>().serialize(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_inputs() {
+        let dir = std::env::temp_dir().join(format!("c2rust-cache-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("lib.c");
+        let output = dir.join("lib.rs");
+        fs::write(&input, "int f(void) { return 1; }").unwrap();
+        fs::write(&output, "pub fn f() -> i32 { 1 }").unwrap();
+
+        let item: syn::Item = syn::parse_str("pub mod stdio_h { pub type FILE = u8; }").unwrap();
+        let result = (
+            vec![("feature".to_owned(), vec!["c_variadic".to_owned()])],
+            vec![ExternCrate::Libc].into_iter().collect(),
+            ErrorCodes::default(),
+            vec![Box::new(item)],
+        );
+        let entry = CacheEntry::new("1".to_owned(), &[input.clone()], &[output], &result);
+        store(&dir, &input, &entry).unwrap();
+
+        let (pragmas, crates, _, header_mods) = load(&dir, &input, "1").unwrap();
+        assert_eq!(pragmas, result.0);
+        assert_eq!(crates, result.1);
+        assert_eq!(header_mods, result.3);
+        assert!(load(&dir, &input, "2").is_none());
+
+        fs::write(&input, "int f(void) { return 2; }").unwrap();
+        assert!(load(&dir, &input, "1").is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
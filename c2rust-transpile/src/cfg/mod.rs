@@ -14,6 +14,9 @@
 //!   - simplify that sequence of `Structure<Stmt>`s into another such sequence
 //!   - convert the `Vec<Structure<Stmt>>` back into a `Vec<Stmt>`
 //!
+//! The last four steps are also available on their own, for graphs of Rust statements built by
+//! other tools, through the `structurize` module.
+//!
 
 use crate::c_ast::iterators::{DFExpr, SomeId};
 use crate::c_ast::CLabelId;
@@ -42,6 +45,7 @@ use serde_json;
 
 use crate::c_ast::*;
 use crate::translator::*;
+use crate::unsafe_reasons::UnsafeReason;
 use crate::with_stmts::WithStmts;
 use c2rust_ast_builder::mk;
 
@@ -50,6 +54,7 @@ pub mod loops;
 pub mod multiples;
 pub mod relooper;
 pub mod structures;
+pub mod structurize;
 
 use crate::cfg::inc_cleanup::IncCleanup;
 use crate::cfg::loops::*;
@@ -564,8 +569,11 @@ impl Cfg<Label, StmtOrDecl> {
             .flat_map(|&stmt_id| DFExpr::new(&translator.ast_context, stmt_id.into()))
             .flat_map(SomeId::stmt)
             .flat_map(|x| match translator.ast_context[x].kind {
-                CStmtKind::Goto(target) => Some((target, x)),
-                _ => None,
+                CStmtKind::Goto(target) => vec![(target, x)],
+                CStmtKind::Asm { ref labels, .. } => {
+                    labels.iter().map(|&target| (target, x)).collect()
+                }
+                _ => vec![],
             })
         {
             c_label_to_goto
@@ -784,6 +792,9 @@ impl<Lbl: Clone + Ord + Hash + Debug, Stmt> Cfg<Lbl, Stmt> {
     }
 }
 
+/// Deepest nesting of C statements within a single function that we attempt to translate
+const MAX_STMT_NESTING: usize = 4096;
+
 /// This stores all of the state required to construct a control-flow graph from C statements. Once
 /// the graph is constructed, we only really care about the 'graph' field.
 #[derive(Clone, Debug)]
@@ -1361,6 +1372,31 @@ impl CfgBuilder {
         )
     }
 
+    /// Translate the value of a `case` label into an expression usable in a match pattern.
+    ///
+    /// Literals and paths to constants are kept as written; anything else is replaced by the
+    /// constant value computed by Clang.
+    fn convert_case_bound(
+        &self,
+        translator: &Translation,
+        ctx: ExprContext,
+        case_expr: CExprId,
+        cie: ConstIntExpr,
+    ) -> Result<Box<Expr>, TranslationError> {
+        let (case_expr, case_kind) = translator.ast_context.resolve_expr(case_expr);
+        let branch = match case_kind {
+            CExprKind::Literal(..) | CExprKind::ConstantExpr(_, _, Some(_)) => translator
+                .convert_expr(ctx.used(), case_expr)?
+                .to_pure_expr()
+                .filter(|expr| matches!(**expr, Expr::Lit(..) | Expr::Path(..))),
+            _ => None,
+        };
+        match branch {
+            Some(expr) => Ok(expr),
+            None => translator.convert_constant(cie),
+        }
+    }
+
     /// Translate a C statement, inserting it into the CFG under the label key passed in.
     ///
     /// If the input C statement naturally passes control to the statement that follows it, the
@@ -1382,6 +1418,31 @@ impl CfgBuilder {
 
         // Entry label
         entry: Label,
+    ) -> Result<Option<Label>, TranslationError> {
+        // Every statement being translated has an entry on `per_stmt_stack`, so its length is
+        // the current nesting depth. Generated code can nest blocks deeply enough that the passes
+        // after CFG construction would run out of stack, so give up on the function instead.
+        if self.per_stmt_stack.len() > MAX_STMT_NESTING {
+            return Err(format_err!(
+                "Statement {:?} is nested more than {} levels deep",
+                stmt_id,
+                MAX_STMT_NESTING,
+            )
+            .into());
+        }
+
+        ensure_sufficient_stack(|| {
+            self.convert_stmt_help_inner(translator, ctx, stmt_id, in_tail, entry)
+        })
+    }
+
+    fn convert_stmt_help_inner(
+        &mut self,
+        translator: &Translation,
+        ctx: ExprContext,
+        stmt_id: CStmtId,
+        in_tail: Option<ImplicitReturnType>,
+        entry: Label,
     ) -> Result<Option<Label>, TranslationError> {
         // Add to the per_stmt_stack
         let live_in: IndexSet<CDeclId> = self.currently_live.last().unwrap().clone();
@@ -1403,7 +1464,8 @@ impl CfgBuilder {
 
             CStmtKind::Decls(ref decls) => {
                 for decl in decls {
-                    let info = translator.convert_decl_stmt_info(ctx, *decl)?;
+                    let mut info = translator.convert_decl_stmt_info(ctx, *decl)?;
+                    translator.add_local_src_locs(*decl, &mut info);
                     self.last_per_stmt_mut()
                         .decls_seen
                         .store
@@ -1416,7 +1478,7 @@ impl CfgBuilder {
             }
 
             CStmtKind::Return(expr) => {
-                let val = match expr.map(|i| translator.convert_expr(ctx.used(), i)) {
+                let val = match expr.map(|i| translator.convert_return_value(ctx.used(), i)) {
                     Some(r) => Some(r?),
                     None => None,
                 };
@@ -1601,6 +1663,16 @@ impl CfgBuilder {
                 Ok(Some(self.new_wip_block(next_entry)))
             }
 
+            CStmtKind::ForLoop { .. } if translator.is_parallel_loop(stmt_id) => {
+                wip.extend(translator.convert_parallel_for(ctx, stmt_id)?);
+                Ok(Some(wip))
+            }
+
+            CStmtKind::ForLoop { .. } if translator.is_range_loop(stmt_id) => {
+                wip.extend(translator.convert_range_loop(ctx, stmt_id)?);
+                Ok(Some(wip))
+            }
+
             CStmtKind::ForLoop {
                 init,
                 condition,
@@ -1715,6 +1787,11 @@ impl CfgBuilder {
                 }
             }
 
+            CStmtKind::Seh(..) => Err(format_err!(
+                "Microsoft structured exception handling (`__try`, `__leave`) is not supported"
+            )
+            .into()),
+
             CStmtKind::Goto(label_id) => {
                 let label_name = translator
                     .ast_context
@@ -1828,26 +1905,7 @@ impl CfgBuilder {
                 self.add_wip_block(wip, Jump(this_label.clone()));
 
                 // Case
-                let resolved = translator.ast_context.resolve_expr(case_expr);
-                let branch = match resolved.1 {
-                    CExprKind::Literal(..) | CExprKind::ConstantExpr(_, _, Some(_)) => {
-                        match translator
-                            .convert_expr(ctx.used(), resolved.0)?
-                            .to_pure_expr()
-                        {
-                            Some(expr) => match *expr {
-                                Expr::Lit(..) | Expr::Path(..) => Some(expr),
-                                _ => None,
-                            },
-                            _ => None,
-                        }
-                    }
-                    _ => None,
-                };
-                let branch = match branch {
-                    Some(expr) => expr,
-                    None => translator.convert_constant(cie)?,
-                };
+                let branch = self.convert_case_bound(translator, ctx, case_expr, cie)?;
                 self.switch_expr_cases
                     .last_mut()
                     .ok_or(format_err!(
@@ -1863,6 +1921,42 @@ impl CfgBuilder {
                 Ok(sub_stmt_next.map(|l| self.new_wip_block(l)))
             }
 
+            CStmtKind::CaseRange(lo_expr, hi_expr, sub_stmt, lo_cie, hi_cie) => {
+                self.last_per_stmt_mut().saw_unmatched_case = true;
+                let this_label = Label::FromC(stmt_id, None);
+                self.add_wip_block(wip, Jump(this_label.clone()));
+
+                // Case range. An empty range (`hi < lo`) matches nothing, but its label can still
+                // be reached by falling through from the previous case.
+                let is_empty = match (lo_cie, hi_cie) {
+                    (ConstIntExpr::U(lo), ConstIntExpr::U(hi)) => lo > hi,
+                    (ConstIntExpr::I(lo), ConstIntExpr::I(hi)) => lo > hi,
+                    _ => false,
+                };
+                let pat = if is_empty {
+                    None
+                } else if lo_cie == hi_cie {
+                    let lo = self.convert_case_bound(translator, ctx, lo_expr, lo_cie)?;
+                    Some(mk().lit_pat(lo))
+                } else {
+                    let lo = self.convert_case_bound(translator, ctx, lo_expr, lo_cie)?;
+                    let hi = self.convert_case_bound(translator, ctx, hi_expr, hi_cie)?;
+                    Some(mk().range_pat(lo, hi))
+                };
+                let switch_cases = self.switch_expr_cases.last_mut().ok_or(format_err!(
+                    "Cannot find the 'switch' wrapping this ({:?}) 'case' statement",
+                    stmt_id,
+                ))?;
+                if let Some(pat) = pat {
+                    switch_cases.cases.push((pat, this_label.clone()));
+                }
+
+                // Sub stmt
+                let sub_stmt_next =
+                    self.convert_stmt_help(translator, ctx, sub_stmt, in_tail.clone(), this_label)?;
+                Ok(sub_stmt_next.map(|l| self.new_wip_block(l)))
+            }
+
             CStmtKind::Default(sub_stmt) => {
                 self.last_per_stmt_mut().saw_unmatched_default = true;
                 let this_label = Label::FromC(stmt_id, None);
@@ -1945,17 +2039,60 @@ impl CfgBuilder {
                 ref inputs,
                 ref outputs,
                 ref clobbers,
+                ref labels,
             } => {
-                wip.extend(translator.convert_asm(
-                    ctx,
-                    DUMMY_SP,
-                    is_volatile,
-                    asm,
-                    inputs,
-                    outputs,
-                    clobbers,
-                )?);
-                Ok(Some(wip))
+                let loc = translator.ast_context[stmt_id].loc;
+                translator.record_unsafe_at(loc, UnsafeReason::InlineAsm);
+                if labels.is_empty() {
+                    wip.extend(translator.convert_asm(
+                        ctx,
+                        DUMMY_SP,
+                        is_volatile,
+                        asm,
+                        inputs,
+                        outputs,
+                        clobbers,
+                        None,
+                    )?);
+                    Ok(Some(wip))
+                } else {
+                    // The label operands of an `asm goto` record which label was jumped to
+                    // (counting from 1) in a fresh variable, which we branch on afterwards
+                    let target = translator.renamer.borrow_mut().fresh();
+                    wip.extend(translator.convert_asm(
+                        ctx,
+                        DUMMY_SP,
+                        is_volatile,
+                        asm,
+                        inputs,
+                        outputs,
+                        clobbers,
+                        Some((&target, labels.len())),
+                    )?);
+
+                    let next_label = self.fresh_label();
+                    let mut cases = vec![];
+                    for (i, &label_id) in labels.iter().enumerate() {
+                        let label_name = translator
+                            .ast_context
+                            .label_names
+                            .get(&label_id)
+                            .cloned()
+                            .expect("missing label name for an asm goto label");
+                        let pat = mk().lit_pat(mk().lit_expr(mk().int_lit(i as u128 + 1, "")));
+                        cases.push((pat, Label::FromC(label_id, Some(label_name))));
+                        self.last_per_stmt_mut()
+                            .c_labels_used
+                            .entry(label_id)
+                            .or_insert(IndexSet::new())
+                            .insert(stmt_id);
+                    }
+                    cases.push((mk().wild_pat(), next_label.clone()));
+                    let expr = mk().ident_expr(target);
+                    self.add_wip_block(wip, Switch { expr, cases });
+
+                    Ok(Some(self.new_wip_block(next_label)))
+                }
             }
         };
         let out_wip: Option<WipBlock> = out_wip?; // This statement exists to help type inference...
@@ -1967,7 +2104,7 @@ impl CfgBuilder {
         });
 
         // Is the CFG for this statement self contained so can we reloop it immediately?
-        if translator.tcfg.incremental_relooper
+        if translator.fn_tcfg().incremental_relooper
             && self
                 .per_stmt_stack
                 .last()
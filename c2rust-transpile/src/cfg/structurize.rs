@@ -0,0 +1,412 @@
+//! This module exposes the relooper as a library: other tools, like the refactorer's loop
+//! idiomization passes, can hand it a control-flow graph of Rust statements and get structured
+//! Rust statements back, without going through the C translator.
+//!
+//! ```ignore
+//! let mut graph = Graph::new();
+//! let (head, body, exit) = (graph.fresh_label(), graph.fresh_label(), graph.fresh_label());
+//! graph.add_block(head.clone(), vec![], GenTerminator::Branch(cond, body.clone(), exit.clone()));
+//! graph.add_block(body, vec![step], GenTerminator::Jump(head.clone()));
+//! graph.add_block(exit, vec![], GenTerminator::End);
+//!
+//! // `while cond { step }`
+//! let stmts = structurize(graph, head, &StructurizeConfig::default())?;
+//! ```
+//!
+//! The blocks hold plain Rust statements, so unlike in the translator there are no C
+//! declarations to place: every statement ends up wherever the relooper puts its block.
+
+use super::*;
+use crate::rust_ast::comment_store::CommentStore;
+
+/// A control-flow graph being built by a client of the relooper
+#[derive(Clone, Debug, Default)]
+pub struct Graph {
+    blocks: IndexMap<Label, BasicBlock<Label, StmtOrDecl>>,
+    next_label: u64,
+}
+
+impl Graph {
+    pub fn new() -> Self {
+        Graph::default()
+    }
+
+    /// A label no block of this graph has been added under yet
+    pub fn fresh_label(&mut self) -> Label {
+        loop {
+            let label = Label::Synthetic(self.next_label);
+            self.next_label += 1;
+            if !self.blocks.contains_key(&label) {
+                return label;
+            }
+        }
+    }
+
+    /// Add a block running `body` and then continuing as `terminator` says, replacing any block
+    /// previously added under the same label.
+    pub fn add_block(&mut self, label: Label, body: Vec<Stmt>, terminator: GenTerminator<Label>) {
+        let mut bb = BasicBlock::new(terminator);
+        bb.body = body.into_iter().map(StmtOrDecl::Stmt).collect();
+        self.blocks.insert(label, bb);
+    }
+
+    /// Check that every jump has a target and build the simplified CFG the relooper runs on
+    fn into_cfg(self, entry: Label) -> Result<Cfg<Label, StmtOrDecl>, TranslationError> {
+        let targets = self
+            .blocks
+            .values()
+            .flat_map(|bb| bb.terminator.get_labels())
+            .chain(Some(&entry));
+        for label in targets {
+            if !self.blocks.contains_key(label) {
+                return Err(format_err!("No block for label {}", label.pretty_print()).into());
+            }
+        }
+
+        let mut cfg = Cfg {
+            entries: entry,
+            nodes: self.blocks,
+            loops: LoopInfo::new(),
+            multiples: MultipleInfo::new(),
+        };
+        cfg.prune_empty_blocks_mut();
+        cfg.prune_unreachable_blocks_mut();
+        Ok(cfg)
+    }
+}
+
+/// How to structure a `Graph`
+#[derive(Clone, Debug)]
+pub struct StructurizeConfig {
+    /// Simplify the relooped structures before converting them to statements
+    pub simplify_structures: bool,
+
+    /// Dispatch on the labels' names instead of their hashes when a `current_block` variable is
+    /// needed
+    pub debug_labels: bool,
+
+    /// Name of the variable holding the next block, for graphs that can't be structured with
+    /// loops and conditionals alone
+    pub current_block: String,
+}
+
+impl Default for StructurizeConfig {
+    fn default() -> Self {
+        StructurizeConfig {
+            simplify_structures: true,
+            debug_labels: false,
+            current_block: "current_block".to_string(),
+        }
+    }
+}
+
+/// Reloop a graph starting at `entry`, producing the structure tree that `structurize` turns into
+/// statements
+pub fn reloop_graph(
+    graph: Graph,
+    entry: Label,
+    simplify_structures: bool,
+) -> Result<Vec<Structure<Stmt>>, TranslationError> {
+    let cfg = graph.into_cfg(entry)?;
+    let (lifted, relooped) = relooper::reloop(
+        cfg,
+        DeclStmtStore::new(),
+        simplify_structures,
+        false,
+        false,
+        IndexSet::new(),
+    );
+    debug_assert!(lifted.is_empty(), "graphs of statements have no declarations to lift");
+    Ok(relooped)
+}
+
+/// Convert a graph starting at `entry` into structured statements, declaring the `current_block`
+/// variable first if the graph needs one.
+pub fn structurize(
+    graph: Graph,
+    entry: Label,
+    config: &StructurizeConfig,
+) -> Result<Vec<Stmt>, TranslationError> {
+    let relooped = reloop_graph(graph, entry, config.simplify_structures)?;
+
+    let mut stmts = vec![];
+    if structures::has_multiple(&relooped) {
+        let current_block_ty = if config.debug_labels {
+            mk().ref_lt_ty("static", mk().path_ty(vec!["str"]))
+        } else {
+            mk().path_ty(vec!["u64"])
+        };
+        let local = mk().local(
+            mk().mutbl().ident_pat(&config.current_block),
+            Some(current_block_ty),
+            None as Option<Box<Expr>>,
+        );
+        stmts.push(mk().local_stmt(Box::new(local)));
+    }
+
+    stmts.extend(structures::structured_cfg(
+        &relooped,
+        &mut CommentStore::new(),
+        mk().ident_expr(&config.current_block),
+        config.debug_labels,
+        false,
+    )?);
+    Ok(stmts)
+}
+
+#[cfg(test)]
+mod tests {
+    //! Round-trip random graphs through `structurize` and check that running the structured
+    //! statements visits the same blocks as walking the graph, given the same branch decisions.
+    //!
+    //! Block `n` runs `visit(n);`, and branches on `choose()`, which reads the next decision.
+
+    use super::*;
+    use syn::{ExprLit, Lit};
+
+    /// Blocks visited before a run is cut short, since random graphs often loop forever
+    const MAX_VISITS: usize = 64;
+
+    /// Deterministic xorshift generator, for both the graphs and the branch decisions
+    #[derive(Clone)]
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: u64) -> u64 {
+            self.next() % n
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    enum Term {
+        End,
+        Jump(u64),
+        Branch(u64, u64),
+    }
+
+    fn random_graph(rng: &mut Rng) -> Vec<Term> {
+        let n = 1 + rng.below(8);
+        (0..n)
+            .map(|_| match rng.below(5) {
+                0 => Term::End,
+                1 => Term::Jump(rng.below(n)),
+                _ => Term::Branch(rng.below(n), rng.below(n)),
+            })
+            .collect()
+    }
+
+    fn build(terms: &[Term]) -> Graph {
+        let label = |n: u64| Label::Synthetic(n);
+        let mut graph = Graph::new();
+        for (n, term) in terms.iter().enumerate() {
+            let body = vec![syn::parse_str(&format!("visit({});", n)).unwrap()];
+            let terminator = match *term {
+                Term::End => GenTerminator::End,
+                Term::Jump(to) => GenTerminator::Jump(label(to)),
+                Term::Branch(t, f) => {
+                    GenTerminator::Branch(syn::parse_str("choose()").unwrap(), label(t), label(f))
+                }
+            };
+            graph.add_block(label(n as u64), body, terminator);
+        }
+        graph
+    }
+
+    fn walk_graph(terms: &[Term], mut decisions: Rng) -> Vec<u64> {
+        let mut visits = vec![];
+        let mut n = 0;
+        while visits.len() < MAX_VISITS {
+            visits.push(n);
+            n = match terms[n as usize] {
+                Term::End => break,
+                Term::Jump(to) => to,
+                Term::Branch(t, f) => {
+                    if decisions.next() & 1 == 1 {
+                        t
+                    } else {
+                        f
+                    }
+                }
+            };
+        }
+        visits
+    }
+
+    enum Flow {
+        Normal,
+        Break(Option<String>),
+        Continue(Option<String>),
+        Return,
+    }
+
+    /// Interpreter for the statements `structurize` produces from the graphs above
+    struct Run {
+        decisions: Rng,
+        visits: Vec<u64>,
+        current_block: u64,
+    }
+
+    fn int(expr: &Expr) -> u64 {
+        match expr {
+            Expr::Lit(ExprLit {
+                lit: Lit::Int(i), ..
+            }) => i.base10_parse().unwrap(),
+            _ => panic!("not an integer: {:?}", expr),
+        }
+    }
+
+    fn is_label(label: &Option<syn::Label>, exit: &Option<String>) -> bool {
+        match exit {
+            None => true,
+            Some(exit) => label.as_ref().map(|l| l.name.ident.to_string()).as_ref() == Some(exit),
+        }
+    }
+
+    impl Run {
+        fn stmts(&mut self, stmts: &[Stmt]) -> Flow {
+            for stmt in stmts {
+                let flow = match stmt {
+                    Stmt::Local(_) => Flow::Normal,
+                    Stmt::Expr(e) | Stmt::Semi(e, _) => self.expr(e),
+                    Stmt::Item(_) => panic!("unexpected item"),
+                };
+                if !matches!(flow, Flow::Normal) {
+                    return flow;
+                }
+            }
+            Flow::Normal
+        }
+
+        fn cond(&mut self, expr: &Expr) -> bool {
+            match expr {
+                Expr::Call(..) => self.decisions.next() & 1 == 1,
+                Expr::Unary(syn::ExprUnary { expr, .. }) => !self.cond(expr),
+                Expr::Paren(syn::ExprParen { expr, .. }) => self.cond(expr),
+                _ => panic!("unexpected condition: {:?}", expr),
+            }
+        }
+
+        fn run_loop(
+            &mut self,
+            label: &Option<syn::Label>,
+            cond: Option<&Expr>,
+            body: &[Stmt],
+        ) -> Flow {
+            for _ in 0..MAX_VISITS * 16 {
+                if let Some(cond) = cond {
+                    if !self.cond(cond) {
+                        return Flow::Normal;
+                    }
+                }
+                match self.stmts(body) {
+                    Flow::Break(ref exit) if is_label(label, exit) => return Flow::Normal,
+                    Flow::Continue(ref exit) if is_label(label, exit) => {}
+                    Flow::Normal => {}
+                    flow => return flow,
+                }
+            }
+            panic!("loop runs without visiting any blocks");
+        }
+
+        fn expr(&mut self, expr: &Expr) -> Flow {
+            match expr {
+                Expr::Call(call) => match call.args.first() {
+                    Some(n) => {
+                        self.visits.push(int(n));
+                        if self.visits.len() >= MAX_VISITS {
+                            return Flow::Return;
+                        }
+                    }
+                    None => {
+                        self.cond(expr);
+                    }
+                },
+                Expr::Assign(assign) => self.current_block = int(&assign.right),
+                Expr::If(if_) => {
+                    if self.cond(&if_.cond) {
+                        return self.stmts(&if_.then_branch.stmts);
+                    } else if let Some((_, ref els)) = if_.else_branch {
+                        return self.expr(els);
+                    }
+                }
+                Expr::Block(block) => return self.stmts(&block.block.stmts),
+                Expr::Loop(loop_) => return self.run_loop(&loop_.label, None, &loop_.body.stmts),
+                Expr::While(while_) => {
+                    return self.run_loop(&while_.label, Some(&while_.cond), &while_.body.stmts)
+                }
+                Expr::Match(match_) => {
+                    for arm in &match_.arms {
+                        let matches = match arm.pat {
+                            syn::Pat::Lit(ref lit) => int(&lit.expr) == self.current_block,
+                            syn::Pat::Wild(_) => true,
+                            ref pat => panic!("unexpected pattern: {:?}", pat),
+                        };
+                        if matches {
+                            return self.expr(&arm.body);
+                        }
+                    }
+                    panic!("no arm for block {}", self.current_block);
+                }
+                Expr::Break(break_) => {
+                    return Flow::Break(break_.label.as_ref().map(|l| l.ident.to_string()))
+                }
+                Expr::Continue(continue_) => {
+                    return Flow::Continue(continue_.label.as_ref().map(|l| l.ident.to_string()))
+                }
+                Expr::Return(_) => return Flow::Return,
+                _ => panic!("unexpected expression: {:?}", expr),
+            }
+            Flow::Normal
+        }
+    }
+
+    fn round_trip(simplify_structures: bool) {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..500 {
+            let terms = random_graph(&mut rng);
+            let config = StructurizeConfig {
+                simplify_structures,
+                ..StructurizeConfig::default()
+            };
+            let stmts = structurize(build(&terms), Label::Synthetic(0), &config).unwrap();
+
+            for _ in 0..4 {
+                let decisions = Rng(rng.next() | 1);
+                let expected = walk_graph(&terms, decisions.clone());
+                let mut run = Run {
+                    decisions,
+                    visits: vec![],
+                    current_block: 0,
+                };
+                run.stmts(&stmts);
+                assert_eq!(run.visits, expected, "graph {:?}", terms);
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_random_graphs() {
+        round_trip(true);
+    }
+
+    #[test]
+    fn round_trip_random_graphs_unsimplified() {
+        round_trip(false);
+    }
+
+    #[test]
+    fn missing_block() {
+        let mut graph = Graph::new();
+        let entry = graph.fresh_label();
+        let missing = graph.fresh_label();
+        graph.add_block(entry.clone(), vec![], GenTerminator::Jump(missing));
+        assert!(structurize(graph, entry, &StructurizeConfig::default()).is_err());
+    }
+}
@@ -8,6 +8,9 @@ use log::warn;
 use regex::Regex;
 use serde_derive::Deserialize;
 
+use crate::build_system::Target;
+use crate::filters::Filter;
+
 #[derive(Deserialize, Debug, Default, Clone)]
 pub struct CompileCmd {
     /// The working directory of the compilation. All paths specified in the command
@@ -22,11 +25,11 @@ pub struct CompileCmd {
     /// to rerun the exact compilation step for the translation unit in the environment
     /// the build system uses. Parameters use shell quoting and shell escaping of quotes,
     /// with ‘"’ and ‘\’ being the only special characters. Shell expansion is not supported.
-    #[serde(skip_deserializing)]
-    _command: Option<String>,
+    #[serde(default)]
+    command: Option<String>,
     /// The compile command executed as list of strings. Either arguments or command is required.
-    #[serde(default, skip_deserializing)]
-    _arguments: Vec<String>,
+    #[serde(default)]
+    arguments: Vec<String>,
     /// The name of the output created by this compilation step. This field is optional. It can
     /// be used to distinguish different processing modes of the same input file.
     output: Option<String>,
@@ -43,9 +46,43 @@ impl CompileCmd {
             }
         }
     }
+
+    /// The working directory of the compilation
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// The compile command as a list of arguments, starting with the compiler
+    pub fn args(&self) -> Vec<String> {
+        match self.command {
+            Some(ref command) if self.arguments.is_empty() => split_command(command),
+            _ => self.arguments.clone(),
+        }
+    }
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+/// Split a compile command into arguments, where `"` and `\` are the only special characters
+pub(crate) fn split_command(command: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut arg: Option<String> = None;
+    let mut quoted = false;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => arg.get_or_insert_with(String::new).extend(chars.next()),
+            '"' => {
+                quoted = !quoted;
+                arg.get_or_insert_with(String::new);
+            }
+            c if c.is_whitespace() && !quoted => args.extend(arg.take()),
+            c => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(arg);
+    args
+}
+
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum LinkType {
     Exe,
@@ -65,7 +102,7 @@ impl LinkType {
     pub fn as_cargo_types(&self) -> &str {
         match self {
             LinkType::Exe => "\"rlib\"",
-            LinkType::Shared => "\"cdylib\"",
+            LinkType::Shared => "\"cdylib\", \"rlib\"",
             LinkType::Static => "\"staticlib\", \"rlib\"",
         }
     }
@@ -89,6 +126,22 @@ pub struct LinkCmd {
     pub cmd_inputs: Vec<Rc<CompileCmd>>,
     #[serde(default)]
     pub top_level: bool,
+    /// Input files that aren't translated, and are compiled from C by hybrid crates (see
+    /// `--hybrid-build`)
+    #[serde(default)]
+    pub c_inputs: Vec<Rc<CompileCmd>>,
+    /// Names of the other link commands whose outputs are linked into this one, which become
+    /// dependencies of its crate
+    #[serde(default)]
+    pub deps: Vec<String>,
+}
+
+impl LinkCmd {
+    /// The name of the crate translated from this link command, if it has an output
+    pub fn name(&self) -> Option<String> {
+        let output = Path::new(self.output.as_ref()?);
+        Some(output.file_stem()?.to_str()?.to_owned())
+    }
 }
 
 /// Convert a linear vector of `CompileCmd`s into a DAG of `LinkCmd`s and `CompileCmd`s
@@ -141,13 +194,119 @@ fn build_link_commands(mut v: Vec<Rc<CompileCmd>>) -> Result<Vec<LinkCmd>, Error
             r#type: LinkType::Static,
             cmd_inputs: v,
             top_level: true,
+            c_inputs: vec![],
+            deps: vec![],
         };
         res.push(lcmd);
     }
 
+    link_dependencies(&mut res);
     Ok(res)
 }
 
+/// Find the libraries among `lcmds` linked into the others, either by the path of their output
+/// or with `-l`, and make them dependencies instead of libraries to link
+fn link_dependencies(lcmds: &mut [LinkCmd]) {
+    let mut by_file_name = HashMap::new();
+    let mut by_lib_name = HashMap::new();
+    for lcmd in lcmds.iter().filter(|lcmd| lcmd.r#type.is_library()) {
+        let (output, name) = match (&lcmd.output, lcmd.name()) {
+            (Some(output), Some(name)) => (Path::new(output), name),
+            _ => continue,
+        };
+        if let Some(file_name) = output.file_name() {
+            by_file_name.insert(file_name.to_owned(), name.clone());
+        }
+        let lib_name = name.strip_prefix("lib").unwrap_or(&name).to_owned();
+        by_lib_name.insert(lib_name, name);
+    }
+
+    for lcmd in lcmds.iter_mut() {
+        let name = lcmd.name();
+        let mut deps = lcmd
+            .inputs
+            .iter()
+            .filter_map(|input| by_file_name.get(Path::new(input).file_name()?))
+            .cloned()
+            .collect::<Vec<_>>();
+        lcmd.libs.retain(|lib| match by_lib_name.get(lib) {
+            Some(dep) => {
+                deps.push(dep.clone());
+                false
+            }
+            None => true,
+        });
+        deps.retain(|dep| Some(dep) != name.as_ref());
+        deps.dedup();
+        lcmd.deps = deps;
+    }
+}
+
+/// Group compile commands into the link commands of the `targets` of a build system, leaving the
+/// ones not compiled for any target to the crate itself
+fn target_link_commands(v: Vec<Rc<CompileCmd>>, targets: &[Target]) -> Vec<LinkCmd> {
+    // Only libraries are translated into crates that others can depend on
+    let libraries = targets
+        .iter()
+        .filter(|target| target.r#type.is_library())
+        .map(|target| target.name.as_str())
+        .collect::<HashSet<_>>();
+
+    let mut seen_ccmds = HashSet::new();
+    let mut res = vec![];
+    for target in targets {
+        let sources = target.sources.iter().collect::<HashSet<_>>();
+        let mut cmd_inputs = vec![];
+        for (idx, ccmd) in v.iter().enumerate() {
+            if sources.contains(&ccmd.abs_file()) {
+                cmd_inputs.push(Rc::clone(ccmd));
+                seen_ccmds.insert(idx);
+            }
+        }
+        res.push(LinkCmd {
+            inputs: target
+                .sources
+                .iter()
+                .map(|source| source.display().to_string())
+                .collect(),
+            output: Some(target.name.clone()),
+            libs: target.libs.clone(),
+            lib_dirs: target.lib_dirs.clone(),
+            r#type: target.r#type,
+            cmd_inputs,
+            top_level: false,
+            c_inputs: vec![],
+            deps: target
+                .dependencies
+                .iter()
+                .filter(|dep| libraries.contains(dep.as_str()))
+                .cloned()
+                .collect(),
+        });
+    }
+
+    let rest = v
+        .into_iter()
+        .enumerate()
+        .filter(|(idx, _)| !seen_ccmds.contains(idx))
+        .map(|(_, ccmd)| ccmd)
+        .collect::<Vec<_>>();
+    if !rest.is_empty() {
+        res.push(LinkCmd {
+            inputs: rest.iter().filter_map(|ccmd| ccmd.output.clone()).collect(),
+            output: None,
+            libs: vec![],
+            lib_dirs: vec![],
+            r#type: LinkType::Static,
+            cmd_inputs: rest,
+            top_level: true,
+            c_inputs: vec![],
+            deps: vec![],
+        });
+    }
+    res
+}
+
 /// some build scripts repeatedly compile the same input file with different
 /// command line flags thus creating multiple outputs. We remove any duplicates
 /// in the order we see them and warn the user.
@@ -168,26 +327,36 @@ fn filter_duplicate_cmds(v: Vec<Rc<CompileCmd>>) -> Vec<Rc<CompileCmd>> {
     cmds
 }
 
-/// Read `compile_commands` file and optionally ignore any entries not matching `filter`.
+/// Read `compile_commands` file and optionally ignore any entries not matching `filter` or
+/// `file_filter`. The compile commands are linked into the `targets` of the build system that
+/// generated the file, if given, and as recorded in the file otherwise.
 pub fn get_compile_commands(
     compile_commands: &Path,
     filter: &Option<Regex>,
+    file_filter: &Filter,
+    targets: &[Target],
 ) -> Result<Vec<LinkCmd>, Error> {
     let f = std::io::BufReader::new(File::open(compile_commands)?); // open read-only
 
     // Read the JSON contents of the file as an instance of `Value`
     let v: Vec<Rc<CompileCmd>> = serde_json::from_reader(f)?;
 
-    // apply the filter argument, if any
-    let v = if let &Some(ref re) = filter {
-        v.into_iter()
-            .filter(|c| re.is_match(c.file.to_str().unwrap()))
-            .collect::<Vec<Rc<CompileCmd>>>()
+    // apply the filter argument, if any, keeping the C files that were filtered out
+    let (v, filtered_out) = if filter.is_some() || !file_filter.is_empty() {
+        v.into_iter().partition::<Vec<Rc<CompileCmd>>, _>(|c| {
+            let file = c.file.to_str().unwrap();
+            filter.as_ref().map_or(true, |re| re.is_match(file))
+                && file_filter.is_match(&c.abs_file().to_string_lossy())
+        })
     } else {
-        v
+        (v, vec![])
     };
 
-    let mut lcmds = build_link_commands(v)?;
+    let mut lcmds = if targets.is_empty() {
+        build_link_commands(v)?
+    } else {
+        target_link_commands(v, targets)
+    };
 
     for lcmd in &mut lcmds {
         let inputs = std::mem::replace(&mut lcmd.cmd_inputs, vec![]);
@@ -195,5 +364,70 @@ pub fn get_compile_commands(
         lcmd.cmd_inputs = inputs;
     }
 
+    // Files that were filtered out are left to the crate itself
+    let filtered_out = filtered_out
+        .into_iter()
+        .filter(|c| !c.file.starts_with("/c2rust/link/"))
+        .collect::<Vec<_>>();
+    if let Some(lcmd) = lcmds.iter_mut().find(|lcmd| lcmd.top_level) {
+        lcmd.c_inputs = filter_duplicate_cmds(filtered_out);
+    }
+
     Ok(lcmds)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_quoted() {
+        let args = split_command(r#"cc -c -DNAME="a b" -I "inc dir" -DQ=\"x\" foo.c"#);
+        assert_eq!(
+            args,
+            vec![
+                "cc",
+                "-c",
+                "-DNAME=a b",
+                "-I",
+                "inc dir",
+                "-DQ=\"x\"",
+                "foo.c"
+            ]
+        );
+    }
+
+    fn link_cmd(output: &str, r#type: LinkType, inputs: &[&str], libs: &[&str]) -> LinkCmd {
+        LinkCmd {
+            inputs: inputs.iter().map(|s| s.to_string()).collect(),
+            output: Some(output.to_owned()),
+            libs: libs.iter().map(|s| s.to_string()).collect(),
+            lib_dirs: vec![],
+            r#type,
+            cmd_inputs: vec![],
+            top_level: false,
+            c_inputs: vec![],
+            deps: vec![],
+        }
+    }
+
+    #[test]
+    fn dependencies() {
+        let mut lcmds = vec![
+            link_cmd("lib/libutil.a", LinkType::Static, &["util.o"], &[]),
+            link_cmd("libnet.so", LinkType::Shared, &["net.o"], &["m"]),
+            link_cmd("tool", LinkType::Exe, &["main.o", "lib/libutil.a"], &["net", "z"]),
+        ];
+        link_dependencies(&mut lcmds);
+        assert!(lcmds[0].deps.is_empty());
+        assert_eq!(lcmds[1].libs, vec!["m"]);
+        assert_eq!(lcmds[2].deps, vec!["libutil", "libnet"]);
+        assert_eq!(lcmds[2].libs, vec!["z"]);
+    }
+
+    #[test]
+    fn split_empty_arg() {
+        let args = split_command(r#"cc  -DEMPTY= "" foo.c"#);
+        assert_eq!(args, vec!["cc", "-DEMPTY=", "", "foo.c"]);
+    }
+}
@@ -0,0 +1,174 @@
+//! Project configuration in `c2rust.toml`.
+//!
+//! The file sets options for the whole project, named like the command-line flags, and overrides
+//! them for some files and functions:
+//!
+//! ```toml
+//! crate-name = "zlib"
+//! enum-style = "rust"
+//! derive = ["Debug"]
+//!
+//! [files."contrib/inffast.c"]
+//! translate-asm = false
+//!
+//! [functions.inflate_fast]
+//! simplify-structures = false
+//! ```
+//!
+//! Flags given on the command line take precedence over the options of the whole project, and the
+//! options of files and functions take precedence over both. A switch with a `no-` flag, such as
+//! `--no-simplify-structures`, can be turned off by its positive name. The crates and their build
+//! files follow the options of the whole project, and options of functions only change how their
+//! bodies are translated, such as inline assembly and the relooper.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use failure::{format_err, Error};
+use toml_edit::easy::Value;
+
+/// The name of the configuration file, looked up next to the compilation database or project
+pub const CONFIG_FILE: &str = "c2rust.toml";
+
+/// Options by flag name
+pub type Options = BTreeMap<String, OptionValue>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OptionValue {
+    Switch(bool),
+    Values(Vec<String>),
+}
+
+#[derive(Debug, Default)]
+pub struct ConfigFile {
+    /// Options of the whole project
+    pub options: Options,
+    /// Options of files, by path
+    pub files: Vec<(PathBuf, Options)>,
+    /// Options of functions, by name
+    pub functions: Vec<(String, Options)>,
+}
+
+impl ConfigFile {
+    /// The configuration file of `project`, a compilation database or project directory
+    pub fn find(project: &Path) -> Option<PathBuf> {
+        let dir = if project.is_dir() {
+            project
+        } else {
+            project.parent()?
+        };
+        Some(dir.join(CONFIG_FILE)).filter(|path| path.is_file())
+    }
+
+    /// Load the configuration file `path`, where the paths of files are relative to it
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| format_err!("{}: {}", path.display(), e))?;
+        let table: BTreeMap<String, Value> = toml_edit::easy::from_str(&contents)
+            .map_err(|e| format_err!("{}: {}", path.display(), e))?;
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+        let mut config = ConfigFile::default();
+        for (key, value) in table {
+            match (key.as_str(), value) {
+                ("files", Value::Table(files)) => {
+                    for (file, options) in files {
+                        let path = dir.join(&file);
+                        let path = path.canonicalize().unwrap_or(path);
+                        config.files.push((path, options_of(&file, options)?));
+                    }
+                }
+                ("functions", Value::Table(functions)) => {
+                    for (function, options) in functions {
+                        let options = options_of(&function, options)?;
+                        config.functions.push((function, options));
+                    }
+                }
+                (_, value) => {
+                    let value = option_value(&key, value)?;
+                    config.options.insert(flag_name(&key), value);
+                }
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// The options in the table of the file or function `name`
+fn options_of(name: &str, table: Value) -> Result<Options, Error> {
+    match table {
+        Value::Table(table) => table
+            .into_iter()
+            .map(|(key, value)| Ok((flag_name(&key), option_value(&key, value)?)))
+            .collect(),
+        _ => Err(format_err!("The options of {} are not a table", name)),
+    }
+}
+
+fn option_value(key: &str, value: Value) -> Result<OptionValue, Error> {
+    let scalar = |value: Value| match value {
+        Value::String(value) => Ok(value),
+        Value::Integer(value) => Ok(value.to_string()),
+        Value::Float(value) => Ok(value.to_string()),
+        _ => Err(format_err!("Invalid value for {}: {:?}", key, value)),
+    };
+    match value {
+        Value::Boolean(value) => Ok(OptionValue::Switch(value)),
+        Value::Array(values) => values
+            .into_iter()
+            .map(scalar)
+            .collect::<Result<_, _>>()
+            .map(OptionValue::Values),
+        value => scalar(value).map(|value| OptionValue::Values(vec![value])),
+    }
+}
+
+/// Options are named like their flags, with either dashes or underscores
+fn flag_name(key: &str) -> String {
+    key.replace('_', "-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_config() {
+        let dir = std::env::temp_dir().join(format!("c2rust-config-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(CONFIG_FILE);
+        fs::write(
+            &path,
+            r#"
+                crate_name = "zlib"
+                reorganize-definitions = true
+                derive = ["Debug", "Default"]
+                large-table-threshold = 64
+
+                [files."src/inffast.c"]
+                translate-asm = false
+
+                [functions.inflate_fast]
+                simplify-structures = false
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(ConfigFile::find(&dir), Some(path.clone()));
+        let config = ConfigFile::load(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let values = |values: &[&str]| {
+            OptionValue::Values(values.iter().map(|&value| value.to_owned()).collect())
+        };
+        assert_eq!(config.options["crate-name"], values(&["zlib"]));
+        assert_eq!(config.options["reorganize-definitions"], OptionValue::Switch(true));
+        assert_eq!(config.options["derive"], values(&["Debug", "Default"]));
+        assert_eq!(config.options["large-table-threshold"], values(&["64"]));
+        assert_eq!(config.files[0].0, dir.join("src/inffast.c"));
+        assert_eq!(config.files[0].1["translate-asm"], OptionValue::Switch(false));
+        assert_eq!(config.functions[0].0, "inflate_fast");
+        assert_eq!(config.functions[0].1["simplify-structures"], OptionValue::Switch(false));
+    }
+}
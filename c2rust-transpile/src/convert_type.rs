@@ -1,6 +1,6 @@
 use crate::c_ast::CDeclId;
 use crate::c_ast::*;
-use crate::diagnostics::TranslationError;
+use crate::diagnostics::{TranslationError, TranslationErrorKind};
 use crate::renamer::*;
 use c2rust_ast_builder::{mk, properties::*};
 use failure::format_err;
@@ -22,6 +22,14 @@ pub struct TypeConverter {
     suffix_names: HashMap<(CDeclId, &'static str), String>,
     features: HashSet<&'static str>,
     pub emit_no_std: bool,
+    /// Declare fixed-point types by the integers of the same width, as when falling back to an
+    /// extern declaration of a function that uses them
+    pub fixed_point_as_int: bool,
+    /// Make function pointers `extern "C-unwind"`, following `--c-unwind`
+    pub c_unwind: bool,
+    /// Use the Rust integer and floating point types of the same width as the C types instead
+    /// of their libc aliases, following `--fixed-width-ints`
+    pub fixed_width_ints: bool,
 }
 
 pub const RESERVED_NAMES: [&str; 103] = [
@@ -142,6 +150,27 @@ impl TypeConverter {
             suffix_names: HashMap::new(),
             features: HashSet::new(),
             emit_no_std,
+            fixed_point_as_int: false,
+            c_unwind: false,
+            fixed_width_ints: false,
+        }
+    }
+
+    /// The type of the C numeric type `libc::<name>` on `target`
+    pub fn libc_ty(&self, name: &'static str, target: &str) -> Box<Type> {
+        match fixed_width_type(name, target) {
+            Some(ty) if self.fixed_width_ints => mk().path_ty(mk().path(vec![ty])),
+            _ => mk().path_ty(mk().path(vec!["libc", name])),
+        }
+    }
+
+    /// The type `void` points to, which is the libc one unless `--fixed-width-ints` avoids libc
+    pub fn c_void_ty(&self) -> Box<Type> {
+        if self.fixed_width_ints {
+            let std_or_core = if self.emit_no_std { "core" } else { "std" };
+            mk().path_ty(mk().abs_path(vec![std_or_core, "ffi", "c_void"]))
+        } else {
+            mk().path_ty(vec!["libc", "c_void"])
         }
     }
 
@@ -240,6 +269,19 @@ impl TypeConverter {
         ret: Option<CQualTypeId>,
         params: &Vec<CQualTypeId>,
         is_variadic: bool,
+    ) -> Result<Box<Type>, TranslationError> {
+        self.convert_function_with_call_conv(ctxt, ret, params, is_variadic, None)
+    }
+
+    /// Convert a function type with the calling convention `call_conv`, as named by clang, or
+    /// the C one
+    fn convert_function_with_call_conv(
+        &mut self,
+        ctxt: &TypedAstContext,
+        ret: Option<CQualTypeId>,
+        params: &Vec<CQualTypeId>,
+        is_variadic: bool,
+        call_conv: Option<&str>,
     ) -> Result<Box<Type>, TranslationError> {
         let barefn_inputs = params
             .iter()
@@ -258,7 +300,18 @@ impl TypeConverter {
             variadic,
             ReturnType::Type(Default::default(), output),
         ));
-        return Ok(mk().unsafe_().extern_("C").barefn_ty(fn_ty));
+        let abi = match call_conv.and_then(call_conv_abi) {
+            Some((abi, feature)) => {
+                self.features.extend(feature);
+                abi
+            }
+            None if self.c_unwind => {
+                self.features.insert("c_unwind");
+                "C-unwind"
+            }
+            None => "C",
+        };
+        return Ok(mk().unsafe_().extern_(abi).barefn_ty(fn_ty));
     }
 
     pub fn convert_pointer(
@@ -275,9 +328,7 @@ impl TypeConverter {
         match ctxt.resolve_type(qtype.ctype).kind {
             // While void converts to () in function returns, it converts to c_void
             // in the case of pointers.
-            CTypeKind::Void => Ok(mk()
-                .set_mutbl(mutbl)
-                .ptr_ty(mk().path_ty(vec!["libc", "c_void"]))),
+            CTypeKind::Void => Ok(mk().set_mutbl(mutbl).ptr_ty(self.c_void_ty())),
 
             CTypeKind::VariableArray(mut elt, _len) => {
                 while let CTypeKind::VariableArray(elt_, _) = ctxt.resolve_type(elt).kind {
@@ -316,25 +367,27 @@ impl TypeConverter {
             return Ok(ty);
         }
 
+        if let Some(name) = libc_name(&ctxt.index(ctype).kind) {
+            return Ok(self.libc_ty(name, &ctxt.target));
+        }
+
         match ctxt.index(ctype).kind {
             CTypeKind::Void => Ok(mk().tuple_ty(vec![] as Vec<Box<Type>>)),
             CTypeKind::Bool => Ok(mk().path_ty(mk().path(vec!["bool"]))),
-            CTypeKind::Short => Ok(mk().path_ty(mk().path(vec!["libc", "c_short"]))),
-            CTypeKind::Int => Ok(mk().path_ty(mk().path(vec!["libc", "c_int"]))),
-            CTypeKind::Long => Ok(mk().path_ty(mk().path(vec!["libc", "c_long"]))),
-            CTypeKind::LongLong => Ok(mk().path_ty(mk().path(vec!["libc", "c_longlong"]))),
-            CTypeKind::UShort => Ok(mk().path_ty(mk().path(vec!["libc", "c_ushort"]))),
-            CTypeKind::UInt => Ok(mk().path_ty(mk().path(vec!["libc", "c_uint"]))),
-            CTypeKind::ULong => Ok(mk().path_ty(mk().path(vec!["libc", "c_ulong"]))),
-            CTypeKind::ULongLong => Ok(mk().path_ty(mk().path(vec!["libc", "c_ulonglong"]))),
-            CTypeKind::SChar => Ok(mk().path_ty(mk().path(vec!["libc", "c_schar"]))),
-            CTypeKind::UChar => Ok(mk().path_ty(mk().path(vec!["libc", "c_uchar"]))),
-            CTypeKind::Char => Ok(mk().path_ty(mk().path(vec!["libc", "c_char"]))),
-            CTypeKind::Double => Ok(mk().path_ty(mk().path(vec!["libc", "c_double"]))),
             CTypeKind::LongDouble => Ok(mk().path_ty(mk().path(vec!["f128", "f128"]))),
-            CTypeKind::Float => Ok(mk().path_ty(mk().path(vec!["libc", "c_float"]))),
             CTypeKind::Int128 => Ok(mk().path_ty(mk().path(vec!["i128"]))),
-            CTypeKind::UInt128 => Ok(mk().path_ty(mk().path(vec!["bf16"]))),
+            CTypeKind::UInt128 => Ok(mk().path_ty(mk().path(vec!["u128"]))),
+            CTypeKind::Half => Ok(mk().path_ty(mk().path(vec!["half", "f16"]))),
+            CTypeKind::BFloat16 => Ok(mk().path_ty(mk().path(vec!["half", "bf16"]))),
+            CTypeKind::FixedPoint(_, width, is_signed) if self.fixed_point_as_int => {
+                let prefix = if is_signed { "i" } else { "u" };
+                Ok(mk().path_ty(mk().path(vec![format!("{}{}", prefix, width)])))
+            }
+            CTypeKind::FixedPoint(ref name, ..) => Err(TranslationError::new(
+                None,
+                format_err!("Unsupported fixed-point type {}", name)
+                    .context(TranslationErrorKind::FixedPoint),
+            )),
 
             CTypeKind::Pointer(qtype) => self.convert_pointer(ctxt, qtype),
 
@@ -384,6 +437,39 @@ impl TypeConverter {
                 Ok(mk().mutbl().ptr_ty(child_ty))
             }
 
+            // `_Nonnull` function pointers are plain function pointers instead of `Option`s
+            CTypeKind::Attributed(ty, Some(Attribute::NotNull))
+                if ctxt.is_function_pointer(ty.ctype) =>
+            {
+                match ctxt.resolve_type(ty.ctype).kind {
+                    CTypeKind::Pointer(fn_ty) => self.convert(ctxt, fn_ty.ctype),
+                    _ => unreachable!("Function pointer is not a pointer"),
+                }
+            }
+            CTypeKind::Attributed(ty, Some(Attribute::Nullable))
+                if ctxt.is_nonnull_fn_pointer(ty.ctype) =>
+            {
+                let fn_ty = self.convert(ctxt, ty.ctype)?;
+                let param = mk().angle_bracketed_args(vec![fn_ty]);
+                Ok(mk().path_ty(vec![mk().path_segment_with_args("Option", param)]))
+            }
+            // Function types with a calling convention other than C's, e.g. `__stdcall`
+            CTypeKind::Attributed(ty, Some(Attribute::CallConv(ref call_conv))) => {
+                match ctxt.resolve_type(ty.ctype).kind {
+                    CTypeKind::Function(ret, ref params, is_var, is_noreturn, has_proto) => {
+                        let opt_ret = if is_noreturn { None } else { Some(ret) };
+                        let params = if has_proto { params.clone() } else { vec![] };
+                        self.convert_function_with_call_conv(
+                            ctxt,
+                            opt_ret,
+                            &params,
+                            is_var,
+                            Some(call_conv),
+                        )
+                    }
+                    _ => self.convert(ctxt, ty.ctype),
+                }
+            }
             CTypeKind::Attributed(ty, _) => self.convert(ctxt, ty.ctype),
 
             // ANSI/ISO C-style function
@@ -400,7 +486,24 @@ impl TypeConverter {
                 Ok(fn_ty)
             }
 
-            CTypeKind::TypeOf(ty) => self.convert(ctxt, ty),
+            CTypeKind::TypeOf(ty) | CTypeKind::Auto(ty) => self.convert(ctxt, ty),
+
+            // GCC vector extension types that aren't one of the architecture-specific SIMD
+            // typedefs become portable SIMD vectors
+            CTypeKind::Vector(element, count) => {
+                self.features.insert("portable_simd");
+                let ty = self.convert(ctxt, element.ctype)?;
+                let count = mk().lit_expr(mk().int_unsuffixed_lit(count as u128));
+                let std_or_core = if self.emit_no_std { "core" } else { "std" };
+                let args =
+                    mk().angle_bracketed_args(vec![mk().generic_arg(ty), mk().generic_arg(count)]);
+                let path = vec![
+                    mk().path_segment(std_or_core),
+                    mk().path_segment("simd"),
+                    mk().path_segment_with_args("Simd", args),
+                ];
+                Ok(mk().path_ty(mk().abs_path(path)))
+            }
 
             ref t => Err(format_err!("Unsupported type {:?}", t).into()),
         }
@@ -446,7 +549,9 @@ impl TypeConverter {
             CTypeKind::Paren(ref ctype) => {
                 self.knr_function_type_with_parameters(ctxt, *ctype, params)
             }
-            CTypeKind::TypeOf(ty) => self.knr_function_type_with_parameters(ctxt, ty, params),
+            CTypeKind::TypeOf(ty) | CTypeKind::Auto(ty) => {
+                self.knr_function_type_with_parameters(ctxt, ty, params)
+            }
 
             CTypeKind::Typedef(decl) => match &ctxt.index(decl).kind {
                 CDeclKind::Typedef { typ, .. } => {
@@ -459,3 +564,133 @@ impl TypeConverter {
         }
     }
 }
+
+/// The name of the libc alias of a C numeric type
+pub fn libc_name(kind: &CTypeKind) -> Option<&'static str> {
+    match *kind {
+        CTypeKind::Short => Some("c_short"),
+        CTypeKind::Int => Some("c_int"),
+        CTypeKind::Long => Some("c_long"),
+        CTypeKind::LongLong => Some("c_longlong"),
+        CTypeKind::UShort => Some("c_ushort"),
+        CTypeKind::UInt => Some("c_uint"),
+        CTypeKind::ULong => Some("c_ulong"),
+        CTypeKind::ULongLong => Some("c_ulonglong"),
+        CTypeKind::SChar => Some("c_schar"),
+        CTypeKind::UChar => Some("c_uchar"),
+        CTypeKind::Char => Some("c_char"),
+        CTypeKind::Double => Some("c_double"),
+        CTypeKind::Float => Some("c_float"),
+        _ => None,
+    }
+}
+
+/// The Rust type with the same width and signedness as the libc alias `name` on `target`, which
+/// `--fixed-width-ints` uses instead of the alias
+pub fn fixed_width_type(name: &str, target: &str) -> Option<&'static str> {
+    let mut parts = target.split('-');
+    let arch = parts.next().unwrap_or("");
+    let windows = parts.any(|part| part == "windows");
+    let apple = target.contains("-apple-");
+
+    // `long` has the width of a pointer, except on 64-bit Windows
+    let lp64 = !windows
+        && matches!(
+            arch,
+            "x86_64"
+                | "aarch64"
+                | "aarch64_be"
+                | "powerpc64"
+                | "powerpc64le"
+                | "riscv64"
+                | "riscv64gc"
+                | "s390x"
+                | "sparc64"
+                | "sparcv9"
+                | "mips64"
+                | "mips64el"
+                | "loongarch64"
+        );
+    // Plain `char` is unsigned on most targets other than x86, except on Apple and Windows
+    let unsigned_char = !windows
+        && !apple
+        && (arch.starts_with("aarch64")
+            || arch.starts_with("arm")
+            || arch.starts_with("thumb")
+            || arch.starts_with("powerpc")
+            || arch.starts_with("riscv")
+            || arch == "s390x");
+
+    Some(match name {
+        "c_schar" => "i8",
+        "c_uchar" => "u8",
+        "c_char" if unsigned_char => "u8",
+        "c_char" => "i8",
+        "c_short" => "i16",
+        "c_ushort" => "u16",
+        "c_int" => "i32",
+        "c_uint" => "u32",
+        "c_long" if lp64 => "i64",
+        "c_long" => "i32",
+        "c_ulong" if lp64 => "u64",
+        "c_ulong" => "u32",
+        "c_longlong" | "intmax_t" => "i64",
+        "c_ulonglong" | "uintmax_t" => "u64",
+        "size_t" => "usize",
+        "ssize_t" | "ptrdiff_t" | "intptr_t" => "isize",
+        "uintptr_t" => "usize",
+        "c_float" => "f32",
+        "c_double" => "f64",
+        _ => return None,
+    })
+}
+
+/// Is `long double` as wide as `double` on `target`? It then has the ABI of `double`, and is
+/// translated to it rather than to `f128`.
+pub fn long_double_is_double(target: &str) -> bool {
+    let mut parts = target.split('-');
+    let arch = parts.next().unwrap_or("");
+    let parts = parts.collect::<Vec<_>>();
+    let msvc = parts.contains(&"windows") && !parts.iter().any(|part| part.starts_with("gnu"));
+    let apple = parts.contains(&"apple");
+
+    // So it is with MSVC, on Apple's 64-bit ARM, and on most 32-bit targets other than x86
+    msvc || (apple && matches!(arch, "aarch64" | "arm64" | "arm64e"))
+        || arch.starts_with("arm")
+        || arch.starts_with("thumb")
+        || matches!(arch, "mips" | "mipsel" | "hexagon" | "msp430" | "avr")
+}
+
+/// The Rust ABI of the calling convention `call_conv`, as named by clang, and the feature it
+/// needs, if it isn't the C one
+pub fn call_conv_abi(call_conv: &str) -> Option<(&'static str, Option<&'static str>)> {
+    Some(match call_conv {
+        "stdcall" => ("stdcall", None),
+        "fastcall" => ("fastcall", None),
+        "thiscall" => ("thiscall", Some("abi_thiscall")),
+        "vectorcall" => ("vectorcall", Some("abi_vectorcall")),
+        "ms_abi" => ("win64", None),
+        "sysv_abi" => ("sysv64", None),
+        "aapcs" => ("aapcs", None),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_double_width() {
+        // 80-bit x87 extended precision
+        assert!(!long_double_is_double("x86_64-unknown-linux-gnu"));
+        assert!(!long_double_is_double("x86_64-pc-windows-gnu"));
+        // IEEE quadruple precision
+        assert!(!long_double_is_double("aarch64-unknown-linux-gnu"));
+        // The same as `double`
+        assert!(long_double_is_double("arm64-apple-macosx11.0.0"));
+        assert!(long_double_is_double("aarch64-apple-darwin"));
+        assert!(long_double_is_double("thumbv7em-none-eabihf"));
+        assert!(long_double_is_double("x86_64-pc-windows-msvc"));
+    }
+}
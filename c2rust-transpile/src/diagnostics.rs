@@ -1,74 +1,362 @@
+//! Diagnostics of the translation.
+//!
+//! Warnings are logged in groups, each with a name and a code, such as `atomics` and `C2R0006`.
+//! Groups are enabled with `-W`, suppressed with `--allow` and promoted to errors with `--deny`,
+//! which fail the translation, and are named by either their name or their code. With
+//! `--json-diagnostics`, each diagnostic is written to stderr as a line of JSON:
+//!
+//! ```json
+//! {"severity":"warning","code":"C2R0006","name":"atomics","message":"...",
+//!  "location":{"file":"src/queue.c","line":42,"column":13}}
+//! ```
+
 use colored::Colorize;
 use failure::{err_msg, Backtrace, Context, Error, Fail};
 use fern::colors::ColoredLevelConfig;
 use log::Level;
+use serde_json::json;
+use std::cell::RefCell;
 use std::collections::HashSet;
 use std::fmt::{self, Display};
 use std::io;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use strum_macros::{Display, EnumString};
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter, EnumString};
 
 use crate::c_ast::{ClangAstParseErrorKind, DisplaySrcSpan};
 use c2rust_ast_exporter::get_clang_major_version;
 
-const DEFAULT_WARNINGS: &[Diagnostic] = &[Diagnostic::ClangAst];
+/// Groups that are enabled unless they are allowed
+const DEFAULT_WARNINGS: &[Diagnostic] = &[
+    Diagnostic::ClangAst,
+    Diagnostic::TranslationFailure,
+    Diagnostic::SkippedFile,
+    Diagnostic::InlineAsm,
+    Diagnostic::Atomics,
+    Diagnostic::Openmp,
+    Diagnostic::Builtins,
+    Diagnostic::Unions,
+    Diagnostic::StaticAssert,
+    Diagnostic::Formatting,
+];
 
-#[derive(PartialEq, Eq, Hash, Debug, Display, EnumString, Clone)]
+#[derive(PartialEq, Eq, Hash, Debug, Display, EnumString, EnumIter, Clone, Copy)]
 #[strum(serialize_all = "kebab_case")]
 pub enum Diagnostic {
     All,
     Comments,
     ClangAst,
+    /// Declarations that failed to translate
+    TranslationFailure,
+    /// Input files that were not translated
+    SkippedFile,
+    /// Inline assembly that may not be translated faithfully
+    InlineAsm,
+    /// Atomic operations translated with other orderings or strengths than the C ones
+    Atomics,
+    /// OpenMP directives that were ignored
+    Openmp,
+    /// Builtins without an exact equivalent
+    Builtins,
+    /// Unions that could not be translated as requested
+    Unions,
+    /// Static assertions that were left out
+    StaticAssert,
+    /// Translated files that rustfmt couldn't format with `--fmt`
+    Formatting,
 }
 
+impl Diagnostic {
+    /// The code of the group, which stays the same if the group is renamed
+    pub fn code(self) -> Option<&'static str> {
+        use self::Diagnostic::*;
+        Some(match self {
+            All => return None,
+            Comments => "C2R0001",
+            ClangAst => "C2R0002",
+            TranslationFailure => "C2R0003",
+            SkippedFile => "C2R0004",
+            InlineAsm => "C2R0005",
+            Atomics => "C2R0006",
+            Openmp => "C2R0007",
+            Builtins => "C2R0008",
+            Unions => "C2R0009",
+            StaticAssert => "C2R0010",
+            // C2R0011 was used by the files of `--dry-run`, which are now printed to stdout
+            Formatting => "C2R0012",
+        })
+    }
+
+    /// The level of the diagnostics of the group, unless they are denied
+    pub fn level(self) -> Level {
+        match self {
+            Diagnostic::TranslationFailure => Level::Error,
+            _ => Level::Warn,
+        }
+    }
+
+    /// Parse the name or code of a group
+    pub fn parse(name: &str) -> Result<Self, String> {
+        Diagnostic::iter()
+            .find(|diagnostic| diagnostic.code() == Some(name))
+            .or_else(|| Diagnostic::from_str(name).ok())
+            .ok_or_else(|| format!("Unknown diagnostic: {}", name))
+    }
+}
+
+/// Log a diagnostic of a group, optionally `at` a source location
 macro_rules! diag {
-    ($type:path, $($arg:tt)*) => (log::warn!(target: &$type.to_string(), $($arg)*))
+    ($type:path, at $loc:expr, $($arg:tt)*) => ({
+        $crate::diagnostics::LOCATION.with(|loc| *loc.borrow_mut() = $loc);
+        log::log!(target: &$type.to_string(), $type.level(), $($arg)*);
+        $crate::diagnostics::LOCATION.with(|loc| loc.borrow_mut().take());
+    });
+    ($type:path, $($arg:tt)*) => (log::log!(target: &$type.to_string(), $type.level(), $($arg)*))
 }
 
 pub(crate) use diag;
 
-pub fn init(mut enabled_warnings: HashSet<Diagnostic>, log_level: log::LevelFilter) {
-    enabled_warnings.extend(DEFAULT_WARNINGS.iter().cloned());
+thread_local! {
+    /// The location of the diagnostic being logged
+    pub(crate) static LOCATION: RefCell<Option<DisplaySrcSpan>> = RefCell::new(None);
+}
+
+/// The number of diagnostics that were promoted to errors with `--deny`
+static DENIED: AtomicUsize = AtomicUsize::new(0);
+
+pub fn denied_count() -> usize {
+    DENIED.load(Ordering::Relaxed)
+}
+
+/// The groups of diagnostics that are logged, and those that are errors
+#[derive(Clone)]
+struct Groups {
+    enabled: HashSet<Diagnostic>,
+    allowed: HashSet<Diagnostic>,
+    denied: HashSet<Diagnostic>,
+}
+
+impl Groups {
+    fn new(
+        mut enabled: HashSet<Diagnostic>,
+        allowed: HashSet<Diagnostic>,
+        denied: HashSet<Diagnostic>,
+    ) -> Self {
+        enabled.extend(DEFAULT_WARNINGS.iter().cloned());
+        Groups {
+            enabled,
+            allowed,
+            denied,
+        }
+    }
+
+    fn is_set(set: &HashSet<Diagnostic>, diagnostic: Diagnostic) -> bool {
+        set.contains(&diagnostic) || set.contains(&Diagnostic::All)
+    }
+
+    /// Whether messages logged to `target` are shown. Other messages than diagnostics are.
+    fn is_logged(&self, target: &str) -> bool {
+        let diagnostic = match Diagnostic::from_str(target) {
+            Ok(diagnostic) => diagnostic,
+            Err(_) => return true,
+        };
+        Self::is_set(&self.denied, diagnostic)
+            || Self::is_set(&self.enabled, diagnostic) && !Self::is_set(&self.allowed, diagnostic)
+    }
+
+    /// The diagnostic logged by `record` and its level, counting it if it is denied
+    fn classify(&self, record: &log::Record) -> (Option<Diagnostic>, Level) {
+        let diagnostic = Diagnostic::from_str(record.target()).ok();
+        match diagnostic {
+            Some(diagnostic) if Self::is_set(&self.denied, diagnostic) => {
+                DENIED.fetch_add(1, Ordering::Relaxed);
+                (Some(diagnostic), Level::Error)
+            }
+            _ => (diagnostic, record.level()),
+        }
+    }
+}
+
+pub fn init(
+    enabled_warnings: HashSet<Diagnostic>,
+    allowed_warnings: HashSet<Diagnostic>,
+    denied_warnings: HashSet<Diagnostic>,
+    json_diagnostics: bool,
+    log_level: log::LevelFilter,
+) {
+    let groups = Groups::new(enabled_warnings, allowed_warnings, denied_warnings);
+    // Messages are written as JSON without escape codes
+    if json_diagnostics {
+        colored::control::set_override(false);
+    }
 
     let colors = ColoredLevelConfig::new();
+    let filter_groups = groups.clone();
     let (_log_level, logger) = fern::Dispatch::new()
         .format(move |out, message, record| {
-            let level_label = match record.level() {
+            let (diagnostic, level) = groups.classify(record);
+            let level_label = match level {
                 Level::Error => "error",
                 Level::Warn => "warning",
                 Level::Info => "info",
                 Level::Debug => "debug",
                 Level::Trace => "trace",
             };
-            let target = record.target();
-            let warn_flag = if let Ok(_) = Diagnostic::from_str(target) {
-                format!(" [-W{}]", target)
-            } else {
-                String::new()
+            let location = LOCATION.with(|loc| loc.borrow().clone());
+
+            if json_diagnostics {
+                let location = location.map(|loc| {
+                    json!({
+                        "file": loc.file().map(|file| file.display().to_string()),
+                        "line": loc.span().begin_line,
+                        "column": loc.span().begin_column,
+                    })
+                });
+                let diagnostic = json!({
+                    "severity": level_label,
+                    "code": diagnostic.and_then(Diagnostic::code),
+                    "name": diagnostic.map(|diagnostic| diagnostic.to_string()),
+                    "message": message.to_string(),
+                    "location": location,
+                });
+                return out.finish(format_args!("{}", diagnostic));
+            }
+
+            let code = match diagnostic.and_then(Diagnostic::code) {
+                Some(code) => format!("[{}]", code),
+                None => String::new(),
+            };
+            let location = match location {
+                Some(loc) => format!("{}: ", loc),
+                None => String::new(),
+            };
+            let warn_flag = match diagnostic {
+                Some(diagnostic) => format!(" [-W{}]", diagnostic),
+                None => String::new(),
             };
             out.finish(format_args!(
-                "\x1B[{}m{}:\x1B[0m {}{}",
-                colors.get_color(&record.level()).to_fg_str(),
+                "\x1B[{}m{}{}:\x1B[0m {}{}{}",
+                colors.get_color(&level).to_fg_str(),
                 level_label,
+                code,
+                location,
                 message,
                 warn_flag,
             ))
         })
         .level(log_level)
-        .filter(move |metadata| {
-            if enabled_warnings.contains(&Diagnostic::All) {
-                return true;
-            }
-            Diagnostic::from_str(metadata.target())
-                .map(|d| enabled_warnings.contains(&d))
-                .unwrap_or(true)
-        })
+        .filter(move |metadata| filter_groups.is_logged(metadata.target()))
         .chain(io::stderr())
         .into_log();
     log_reroute::reroute_boxed(logger);
 }
 
+/// A diagnostic, or another message that was logged, as passed to a sink
+#[derive(Clone, Debug)]
+pub struct DiagnosticMessage {
+    /// The group of the diagnostic, if it is one
+    pub diagnostic: Option<Diagnostic>,
+    /// The level of the message, which is `Error` for denied diagnostics
+    pub level: Level,
+    pub message: String,
+    pub location: Option<DisplaySrcSpan>,
+}
+
+/// A callback receiving diagnostics instead of them being written to stderr
+pub type DiagnosticSink = Arc<dyn Fn(&DiagnosticMessage) + Send + Sync>;
+
+struct SinkLogger {
+    groups: Groups,
+    log_level: log::LevelFilter,
+    sink: DiagnosticSink,
+}
+
+impl log::Log for SinkLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= self.log_level && self.groups.is_logged(metadata.target())
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let (diagnostic, level) = self.groups.classify(record);
+        (self.sink)(&DiagnosticMessage {
+            diagnostic,
+            level,
+            message: record.args().to_string(),
+            location: LOCATION.with(|loc| loc.borrow().clone()),
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+thread_local! {
+    /// The logger of the translation running on this thread, if it has a sink
+    static THREAD_SINK: RefCell<Option<Arc<SinkLogger>>> = RefCell::new(None);
+}
+
+/// Passes messages to the sink of the translation running on the thread logging them, and drops
+/// those logged outside of such translations
+struct ThreadSinkLogger;
+
+impl log::Log for ThreadSinkLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        THREAD_SINK.with(|sink| {
+            let sink = sink.borrow();
+            sink.as_ref().map_or(false, |sink| log::Log::enabled(&**sink, metadata))
+        })
+    }
+
+    fn log(&self, record: &log::Record) {
+        // Sinks may log in turn, so the sink isn't borrowed while it runs
+        if let Some(sink) = THREAD_SINK.with(|sink| sink.borrow().clone()) {
+            log::Log::log(&*sink, record);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Restores the sink of the enclosing translation, if any
+struct ThreadSinkGuard(Option<Arc<SinkLogger>>);
+
+impl Drop for ThreadSinkGuard {
+    fn drop(&mut self) {
+        THREAD_SINK.with(|sink| *sink.borrow_mut() = self.0.take());
+    }
+}
+
+/// Like `init`, but pass the diagnostics and other messages logged on the current thread while
+/// `f` runs to `sink`. Only the sink is per thread, so translations with different sinks can run
+/// concurrently, but the process-wide logger is replaced by one dispatching to them, and
+/// messages logged outside of `with_sink` are dropped.
+pub fn with_sink<R>(
+    enabled_warnings: HashSet<Diagnostic>,
+    allowed_warnings: HashSet<Diagnostic>,
+    denied_warnings: HashSet<Diagnostic>,
+    log_level: log::LevelFilter,
+    sink: DiagnosticSink,
+    f: impl FnOnce() -> R,
+) -> R {
+    let groups = Groups::new(enabled_warnings, allowed_warnings, denied_warnings);
+    let logger = Arc::new(SinkLogger {
+        groups,
+        log_level,
+        sink,
+    });
+    let _guard = ThreadSinkGuard(THREAD_SINK.with(|sink| sink.replace(Some(logger))));
+
+    // Each sink filters by its own level
+    let _ = log_reroute::init();
+    log::set_max_level(log::LevelFilter::Trace);
+    log_reroute::reroute(ThreadSinkLogger);
+    f()
+}
+
 #[derive(Debug, Clone)]
 pub struct TranslationError {
     loc: Vec<DisplaySrcSpan>,
@@ -87,6 +375,9 @@ pub enum TranslationErrorKind {
 
     // Clang AST exported by AST-exporter was not valid
     InvalidClangAst(ClangAstParseErrorKind),
+
+    // Embedded C fixed-point types have no Rust equivalent
+    FixedPoint,
 }
 
 /// Constructs a `TranslationError` using the standard string interpolation syntax.
@@ -122,6 +413,10 @@ impl Display for TranslationErrorKind {
             InvalidClangAst(_) => {
                 return write!(f, "Exported Clang AST was invalid. Check warnings above for unimplemented features.");
             }
+
+            FixedPoint => {
+                return write!(f, "Fixed-point types (_Fract, _Accum, _Sat) cannot be translated. Externally visible functions using them are declared extern, so their C definitions must be linked into the crate.");
+            }
         }
         Ok(())
     }
@@ -219,3 +514,16 @@ impl From<Context<TranslationErrorKind>> for TranslationError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_diagnostic() {
+        assert_eq!(Diagnostic::parse("atomics"), Ok(Diagnostic::Atomics));
+        assert_eq!(Diagnostic::parse("C2R0006"), Ok(Diagnostic::Atomics));
+        assert_eq!(Diagnostic::parse("all"), Ok(Diagnostic::All));
+        assert!(Diagnostic::parse("C2R9999").is_err());
+    }
+}
@@ -0,0 +1,151 @@
+//! Translation without writing any files (`--dry-run`).
+//!
+//! Every translated module and build file is written through `write_if_changed`, which shows the
+//! files that would change instead of writing them during a dry run, either as a unified diff
+//! against their current contents or as a list. This makes it safe to re-run the transpiler over
+//! a crate whose translation was edited by hand, to review what a new translation would undo.
+//! Existing outputs are diffed even without `--overwrite-existing`. The files are printed to
+//! stdout, apart from the diagnostics on stderr, so the diffs can be piped to `patch` or a pager;
+//! `--dry-run=json` prints them as JSON lines for tools instead.
+
+use std::fmt::Write;
+use std::path::Path;
+
+use serde_derive::Serialize;
+use serde_json::json;
+
+/// How the files that would be written are shown
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum DryRun {
+    /// A unified diff against the current contents of each file
+    Diff,
+    /// The path of each file
+    List,
+    /// A JSON object with the path, whether the file would be created and the diff, per line
+    Json,
+}
+
+/// Show that `path` would be written with `contents`, replacing `existing`
+pub fn show(mode: DryRun, path: &Path, existing: Option<&str>, contents: &str) {
+    match mode {
+        DryRun::Diff => println!("{}", unified_diff(path, existing, contents)),
+        DryRun::List if existing.is_some() => println!("Would change {}", path.display()),
+        DryRun::List => println!("Would create {}", path.display()),
+        DryRun::Json => {
+            let file = json!({
+                "path": path.display().to_string(),
+                "created": existing.is_none(),
+                "diff": unified_diff(path, existing, contents),
+            });
+            println!("{}", file);
+        }
+    }
+}
+
+/// A unified diff of the lines of `old` and `new`, with three lines of context
+fn unified_diff(path: &Path, old: Option<&str>, new: &str) -> String {
+    const CONTEXT: usize = 3;
+
+    // Unlike `diff::lines`, this doesn't add an empty line after a final newline
+    let old_lines = old.unwrap_or_default().lines().collect::<Vec<_>>();
+    let new_lines = new.lines().collect::<Vec<_>>();
+    let lines = diff::slice(&old_lines, &new_lines);
+    // The line numbers in `old` and `new` before each line of the diff
+    let mut line_nos = Vec::with_capacity(lines.len() + 1);
+    let (mut old_no, mut new_no) = (1, 1);
+    for line in &lines {
+        line_nos.push((old_no, new_no));
+        match line {
+            diff::Result::Left(_) => old_no += 1,
+            diff::Result::Right(_) => new_no += 1,
+            diff::Result::Both(..) => {
+                old_no += 1;
+                new_no += 1;
+            }
+        }
+    }
+    line_nos.push((old_no, new_no));
+
+    let mut out = String::new();
+    match old {
+        Some(_) => writeln!(out, "--- {}", path.display()).unwrap(),
+        None => writeln!(out, "--- /dev/null").unwrap(),
+    }
+    write!(out, "+++ {}", path.display()).unwrap();
+
+    let changes = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, diff::Result::Both(..)))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let mut changes = changes.iter().peekable();
+    while let Some(&first) = changes.next() {
+        // Changes separated by less than twice the context go into the same hunk
+        let mut last = first;
+        while let Some(&&next) = changes.peek() {
+            if next > last + 2 * CONTEXT {
+                break;
+            }
+            last = next;
+            changes.next();
+        }
+        let start = first.saturating_sub(CONTEXT);
+        let end = (last + CONTEXT + 1).min(lines.len());
+
+        let (old_start, new_start) = line_nos[start];
+        let (old_end, new_end) = line_nos[end];
+        // Empty ranges start at the line before them
+        let range = |start: usize, end: usize| match end - start {
+            0 => format!("{},0", start - 1),
+            len => format!("{},{}", start, len),
+        };
+        let (old_range, new_range) = (range(old_start, old_end), range(new_start, new_end));
+        write!(out, "\n@@ -{} +{} @@", old_range, new_range).unwrap();
+        for line in &lines[start..end] {
+            match line {
+                diff::Result::Left(line) => write!(out, "\n-{}", line).unwrap(),
+                diff::Result::Right(line) => write!(out, "\n+{}", line).unwrap(),
+                diff::Result::Both(line, _) => write!(out, "\n {}", line).unwrap(),
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_hunks() {
+        let path = Path::new("lib.rs");
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\nn\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nn\no\n";
+        let expected = "\
+--- lib.rs
++++ lib.rs
+@@ -1,5 +1,5 @@
+ a
+-b
++B
+ c
+ d
+ e
+@@ -10,5 +10,5 @@
+ j
+ k
+ l
+-m
+ n
++o";
+        assert_eq!(unified_diff(path, Some(old), new), expected);
+
+        // Unchanged lines between changes closer than twice the context stay in one hunk
+        let new = "a\nB\nc\nd\ne\nf\nG\nh\ni\nj\nk\nl\nm\nn\n";
+        assert_eq!(unified_diff(path, Some(old), new).matches("@@ -").count(), 1);
+
+        let expected = "--- /dev/null\n+++ lib.rs\n@@ -0,0 +1,2 @@\n+a\n+b";
+        assert_eq!(unified_diff(path, None, "a\nb\n"), expected);
+    }
+}
@@ -0,0 +1,300 @@
+//! Shared error code enums for `--error-enums`.
+//!
+//! Each translation unit reports its negative integer constants and the functions returning them
+//! (see `translator::error_codes`). Once every file of a crate is translated, the constants
+//! sharing a prefix such as `ERR_` form a family if at least two functions return one of them,
+//! and each family becomes a `#[repr(i32)]` enum in the `error_codes` module of the crate:
+//!
+//! ```ignore
+//! #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+//! #[repr(i32)]
+//! pub enum ErrCode {
+//!     Io = -1,
+//!     Nomem = -2,
+//! }
+//! impl std::convert::From<ErrCode> for i32 { /* code as i32 */ }
+//! impl std::convert::TryFrom<i32> for ErrCode { type Error = i32; /* match code { .. } */ }
+//! ```
+//!
+//! The translated functions keep returning plain integers, so the enums are a typed view of
+//! their results for callers and for refactorings into functions returning `Result`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use c2rust_ast_builder::mk;
+use c2rust_ast_printer::pprust;
+use log::warn;
+use syn::{Item, ReturnType};
+
+use crate::translator::{signed_int_expr, ErrorCodes};
+
+/// The name of the module holding the error enums
+pub const ERROR_CODES_MODULE: &str = "error_codes";
+
+/// Generate the contents of the error codes module from the error codes of every translation
+/// unit of a crate, if they have any families of error codes.
+pub fn error_enums_module(units: &[ErrorCodes], no_std: bool) -> Option<String> {
+    // Merge the units, leaving out constants defined differently in different files
+    let mut constants: BTreeMap<&str, i64> = BTreeMap::new();
+    let mut conflicting = BTreeSet::new();
+    let mut returned_by: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for unit in units {
+        for (name, &value) in &unit.constants {
+            if let Some(old) = constants.insert(name.as_str(), value) {
+                if old != value {
+                    conflicting.insert(name.as_str());
+                }
+            }
+        }
+        for (name, fns) in &unit.returned_by {
+            returned_by
+                .entry(name.as_str())
+                .or_default()
+                .extend(fns.iter().map(String::as_str));
+        }
+    }
+    for name in conflicting {
+        warn!(
+            "Error code {} has different values in different files, leaving it out",
+            name
+        );
+        constants.remove(name);
+    }
+
+    let mut families: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for &name in constants.keys() {
+        let is_constant_name = name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+        match name.split_once('_') {
+            Some((family, _)) if is_constant_name && !family.is_empty() => {
+                families.entry(family).or_default().push(name)
+            }
+            _ => {}
+        }
+    }
+
+    let mut items = vec![];
+    for names in families.values() {
+        let fns = names
+            .iter()
+            .filter_map(|name| returned_by.get(name))
+            .flatten()
+            .collect::<BTreeSet<_>>();
+        if names.len() < 2 || fns.len() < 2 {
+            continue;
+        }
+        let prefix = common_prefix(names);
+        let members = names
+            .iter()
+            .filter_map(|&name| {
+                let value = i32::try_from(constants[name]).ok()?;
+                Some((name, &name[prefix.len()..], value))
+            })
+            .collect::<Vec<_>>();
+        items.extend(family_items(prefix, &members, no_std));
+    }
+    if items.is_empty() {
+        return None;
+    }
+
+    let file = pprust::to_string(|| syn::File {
+        shebang: None,
+        attrs: vec![],
+        items: items.into_iter().map(|item| *item).collect(),
+    });
+    Some(format!(
+        "// Error codes returned by the translated functions\n\n{}",
+        file
+    ))
+}
+
+/// The longest prefix of `names` ending in `_`
+fn common_prefix<'a>(names: &[&'a str]) -> &'a str {
+    let first = names[0];
+    let len = names[1..].iter().fold(first.len(), |len, name| {
+        first
+            .bytes()
+            .zip(name.bytes())
+            .take(len)
+            .take_while(|(a, b)| a == b)
+            .count()
+    });
+    match first[..len].rfind('_') {
+        Some(end) => &first[..=end],
+        None => "",
+    }
+}
+
+/// `ERR_NO_MEM` becomes `ErrNoMem`
+fn camel_case(name: &str) -> String {
+    let mut camel = String::new();
+    for word in name.split('_').filter(|word| !word.is_empty()) {
+        let mut chars = word.chars();
+        camel.extend(chars.next());
+        camel.extend(chars.map(|c| c.to_ascii_lowercase()));
+    }
+    if camel.starts_with(|c: char| c.is_ascii_digit()) {
+        camel.insert(0, 'E');
+    }
+    camel
+}
+
+/// The enum of a family of error codes and its conversions from and to `i32`
+fn family_items(prefix: &str, members: &[(&str, &str, i32)], no_std: bool) -> Vec<Box<Item>> {
+    let enum_name = format!("{}Code", camel_case(prefix));
+    let enum_ty = mk().path_ty(vec![enum_name.as_str()]);
+    let int_ty = mk().path_ty(vec!["i32"]);
+
+    let mut variants = vec![];
+    let mut try_from_arms = vec![];
+    let mut seen_names = BTreeSet::new();
+    let mut seen_values = BTreeSet::new();
+    for &(c_name, suffix, value) in members {
+        let name = camel_case(suffix);
+        if name.is_empty() || !seen_names.insert(name.clone()) || !seen_values.insert(value) {
+            warn!(
+                "Leaving error code {} out of {}, which already has its name or value",
+                c_name, enum_name
+            );
+            continue;
+        }
+        let value = signed_int_expr(value.into());
+        let variant = mk().path_expr(vec![enum_name.as_str(), name.as_str()]);
+        try_from_arms.push(mk().arm(
+            mk().lit_pat(value.clone()),
+            None,
+            mk().call_expr(mk().path_expr(vec!["Ok"]), vec![variant]),
+        ));
+        variants.push(mk().unit_variant(name, Some(value)));
+    }
+    if variants.len() < 2 {
+        return vec![];
+    }
+    try_from_arms.push(mk().arm(
+        mk().ident_pat("value"),
+        None,
+        mk().call_expr(
+            mk().path_expr(vec!["Err"]),
+            vec![mk().path_expr(vec!["value"])],
+        ),
+    ));
+
+    let enum_item = mk()
+        .pub_()
+        .call_attr("derive", vec!["Copy", "Clone", "Debug", "PartialEq", "Eq"])
+        .call_attr("repr", vec!["i32"])
+        .enum_item(&enum_name, variants);
+
+    let std_or_core = if no_std { "core" } else { "std" };
+
+    // impl From<Enum> for i32 { fn from(value: Enum) -> Self { value as i32 } }
+    let from_fn = mk().fn_impl_item(
+        mk().fn_decl(
+            "from",
+            vec![mk().arg(enum_ty.clone(), mk().ident_pat("value"))],
+            None,
+            ReturnType::Type(Default::default(), mk().path_ty(vec!["Self"])),
+        ),
+        mk().block(vec![mk().expr_stmt(
+            mk().cast_expr(mk().path_expr(vec!["value"]), int_ty.clone()),
+        )]),
+    );
+    let from_impl = mk().trait_impl_item(
+        mk().abs_path(vec![
+            mk().path_segment(std_or_core),
+            mk().path_segment("convert"),
+            mk().path_segment_with_args("From", mk().angle_bracketed_args(vec![enum_ty.clone()])),
+        ]),
+        int_ty.clone(),
+        vec![from_fn],
+    );
+
+    // impl TryFrom<i32> for Enum { type Error = i32; fn try_from(value: i32) -> .. }
+    let result_ty = mk().path_ty(vec![mk().path_segment_with_args(
+        "Result",
+        mk().angle_bracketed_args(vec![mk().path_ty(vec!["Self"]), int_ty.clone()]),
+    )]);
+    let try_from_fn = mk().fn_impl_item(
+        mk().fn_decl(
+            "try_from",
+            vec![mk().arg(int_ty.clone(), mk().ident_pat("value"))],
+            None,
+            ReturnType::Type(Default::default(), result_ty),
+        ),
+        mk().block(vec![mk().expr_stmt(
+            mk().match_expr(mk().path_expr(vec!["value"]), try_from_arms),
+        )]),
+    );
+    let try_from_impl = mk().trait_impl_item(
+        mk().abs_path(vec![
+            mk().path_segment(std_or_core),
+            mk().path_segment("convert"),
+            mk().path_segment_with_args("TryFrom", mk().angle_bracketed_args(vec![int_ty.clone()])),
+        ]),
+        enum_ty,
+        vec![mk().type_impl_item("Error", int_ty), try_from_fn],
+    );
+
+    vec![enum_item, from_impl, try_from_impl]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(constants: &[(&str, i64)], returns: &[(&str, &str)]) -> ErrorCodes {
+        let mut codes = ErrorCodes::default();
+        for &(name, value) in constants {
+            codes.constants.insert(name.to_string(), value);
+        }
+        for &(name, function) in returns {
+            codes
+                .returned_by
+                .entry(name.to_string())
+                .or_default()
+                .insert(function.to_string());
+        }
+        codes
+    }
+
+    #[test]
+    fn prefixes() {
+        assert_eq!(common_prefix(&["ERR_NO_MEM", "ERR_NO_FILE"]), "ERR_NO_");
+        assert_eq!(common_prefix(&["ERR_IO", "ERR_INVAL"]), "ERR_");
+        assert_eq!(camel_case("NO_MEM"), "NoMem");
+        assert_eq!(camel_case("ERR_"), "Err");
+        assert_eq!(camel_case("404"), "E404");
+    }
+
+    #[test]
+    fn families_span_units() {
+        let units = [
+            unit(
+                &[("ERR_NOMEM", -2), ("ERR_IO", -1), ("LIMIT_LOW", -5)],
+                &[("ERR_NOMEM", "parse")],
+            ),
+            unit(
+                &[("ERR_NOMEM", -2), ("ERR_INVAL", -3), ("LIMIT_HIGH", -6)],
+                &[("ERR_INVAL", "load"), ("LIMIT_LOW", "clamp")],
+            ),
+        ];
+        let module = error_enums_module(&units, false).unwrap();
+        assert!(module.contains("pub enum ErrCode"));
+        assert!(module.contains("Nomem = -2"));
+        assert!(module.contains("Inval = -3"));
+        assert!(module.contains("Io = -1"));
+        // Only one function returns a `LIMIT_` constant
+        assert!(!module.contains("LimitCode"));
+    }
+
+    #[test]
+    fn conflicting_values() {
+        let units = [
+            unit(&[("ERR_A", -1), ("ERR_B", -2)], &[("ERR_A", "f")]),
+            unit(&[("ERR_A", -3), ("ERR_B", -2)], &[("ERR_B", "g")]),
+        ];
+        // Without `ERR_A`, the family has a single member left
+        assert_eq!(error_enums_module(&units, false), None);
+    }
+}
@@ -0,0 +1,130 @@
+//! Include and exclude filters on the names of functions and the paths of files.
+//!
+//! A filter is a list of patterns, which are globs such as `inflate_*` or `src/net/**`, or
+//! regular expressions when prefixed with `re:`. Patterns prefixed with `!` exclude what they
+//! match. A name passes the filter when it matches one of the patterns including names, or there
+//! are none, and none of those excluding them.
+
+use failure::{format_err, Error};
+use regex::Regex;
+use serde_derive::Serialize;
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Filter {
+    #[serde(serialize_with = "crate::cache::serialize_regexes")]
+    include: Vec<Regex>,
+    #[serde(serialize_with = "crate::cache::serialize_regexes")]
+    exclude: Vec<Regex>,
+}
+
+impl Filter {
+    /// Parse the filter `patterns` on paths, where globs match whole components and relative
+    /// globs match the end of paths, or on names
+    pub fn new<'a>(
+        patterns: impl IntoIterator<Item = &'a str>,
+        paths: bool,
+    ) -> Result<Self, Error> {
+        let mut filter = Filter::default();
+        for pattern in patterns {
+            let (excluded, pattern) = match pattern.strip_prefix('!') {
+                Some(pattern) => (true, pattern),
+                None => (false, pattern),
+            };
+            let regex = match pattern.strip_prefix("re:") {
+                Some(regex) => regex.to_owned(),
+                None => glob_regex(pattern, paths),
+            };
+            let regex = Regex::new(&regex)
+                .map_err(|e| format_err!("Invalid filter pattern {}: {}", pattern, e))?;
+            if excluded {
+                filter.exclude.push(regex);
+            } else {
+                filter.include.push(regex);
+            }
+        }
+        Ok(filter)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    pub fn is_match(&self, name: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|re| re.is_match(name)))
+            && !self.exclude.iter().any(|re| re.is_match(name))
+    }
+}
+
+/// The regular expression of `glob`, where `*` and `?` don't match `/` in paths and `**`
+/// matches any number of components
+fn glob_regex(glob: &str, paths: bool) -> String {
+    let (any, one) = if paths { ("[^/]*", "[^/]") } else { (".*", ".") };
+    let mut regex = if !paths || glob.starts_with('/') {
+        "^".to_owned()
+    } else {
+        "(^|/)".to_owned()
+    };
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `**/` also matches no directory at all
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str(any),
+            '?' => regex.push_str(one),
+            '[' => {
+                regex.push('[');
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                    regex.push('^');
+                }
+                for c in chars.by_ref() {
+                    if c == '\\' {
+                        regex.push_str("\\\\");
+                        continue;
+                    }
+                    regex.push(c);
+                    if c == ']' {
+                        break;
+                    }
+                }
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn include_exclude() {
+        let patterns = vec!["inflate*", "re:^deflate_(fast|slow)$", "!inflate_fast"];
+        let fns = Filter::new(patterns, false).unwrap();
+        assert!(fns.is_match("inflate"));
+        assert!(fns.is_match("inflate_table"));
+        assert!(fns.is_match("deflate_slow"));
+        assert!(!fns.is_match("inflate_fast"));
+        assert!(!fns.is_match("deflate_stored"));
+        assert!(Filter::default().is_match("main"));
+        assert!(Filter::new(vec!["!main"], false).unwrap().is_match("inflate"));
+
+        let files = Filter::new(vec!["src/net/**", "!**/*_test.c"], true).unwrap();
+        assert!(files.is_match("/home/me/proj/src/net/tcp.c"));
+        assert!(files.is_match("/home/me/proj/src/net/ipv4/route.c"));
+        assert!(!files.is_match("/home/me/proj/src/net/tcp_test.c"));
+        assert!(!files.is_match("/home/me/proj/src/fs/ext4.c"));
+        assert!(!files.is_match("/home/me/proj/mysrc/net/tcp.c"));
+        assert!(Filter::new(vec!["re:("], false).is_err());
+    }
+}
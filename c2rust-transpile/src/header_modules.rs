@@ -0,0 +1,287 @@
+//! Shared header modules for `--header-modules`.
+//!
+//! With `--reorganize-definitions`, every translated file gets a submodule for each header it
+//! includes, so a header included by many files is translated into each of them. With
+//! `--header-modules`, the translator instead hands the header submodules of each translation
+//! unit back to us. Once every file of a crate is translated, the submodules of the same header
+//! are merged, keeping one copy of each declaration, and written to the `headers` module of the
+//! crate. Declarations that differ between the files, such as a struct whose layout depends on
+//! the macros defined before including its header, can't be shared and fail the translation:
+//!
+//! ```ignore
+//! pub mod stdio_h {
+//!     use super::types_h::__off_t;
+//!     pub type FILE = _IO_FILE;
+//!     // ...
+//! }
+//! ```
+//!
+//! The translated files then import the declarations they use from
+//! `crate::headers::<header module>`, and headers including each other import from their sibling
+//! modules, following the include graph seen by clang.
+
+use std::collections::{HashMap, HashSet};
+
+use c2rust_ast_printer::pprust;
+use indexmap::IndexMap;
+use syn::{ForeignItem, Item, ItemForeignMod, ItemMod};
+
+use crate::translator::{foreign_item_ident_vis, item_ident};
+
+/// The name of the module holding the header modules
+pub const HEADERS_MODULE: &str = "headers";
+
+/// Generate the contents of the headers module from the header submodules of every translation
+/// unit of a crate, if there are any, or the declarations that differ between the units.
+pub fn headers_module(units: Vec<Vec<Box<Item>>>) -> Result<Option<String>, Vec<String>> {
+    let mut modules: IndexMap<String, HeaderModule> = IndexMap::new();
+    let mut differing = vec![];
+    for module in units.into_iter().flatten() {
+        let module = match *module {
+            Item::Mod(module) => module,
+            _ => continue,
+        };
+        let name = module.ident.to_string();
+        match modules.get_mut(&name) {
+            Some(existing) => existing.merge(module, &mut differing),
+            None => {
+                modules.insert(name, HeaderModule::new(module));
+            }
+        }
+    }
+    if !differing.is_empty() {
+        return Err(differing);
+    }
+    if modules.is_empty() {
+        return Ok(None);
+    }
+
+    let file = pprust::to_string(|| syn::File {
+        shebang: None,
+        attrs: vec![],
+        items: modules
+            .into_iter()
+            .map(|(_, module)| Item::Mod(module.module))
+            .collect(),
+    });
+    Ok(Some(format!(
+        "// Declarations from the C headers included by the translated files\n\n{}",
+        file
+    )))
+}
+
+/// The functions of each translation unit whose definition differs from that of an earlier unit
+/// for the same header. Headers can define a function differently in each file including them,
+/// depending on the macros defined before, so these units keep their own copy of the function.
+pub fn differing_fns(units: &[Vec<Box<Item>>]) -> Vec<HashSet<String>> {
+    let mut first_defs: HashMap<(String, String), &Item> = HashMap::new();
+    units
+        .iter()
+        .map(|unit| {
+            let mut differing = HashSet::new();
+            for module in unit {
+                let module = match **module {
+                    Item::Mod(ref module) => module,
+                    _ => continue,
+                };
+                let items = module.content.iter().flat_map(|(_, items)| items);
+                for item in items.filter(|item| matches!(item, Item::Fn(_))) {
+                    let name = item_name(item).expect("functions are named");
+                    let key = (module.ident.to_string(), name.clone());
+                    match first_defs.get(&key) {
+                        Some(&first) if first != item => {
+                            differing.insert(name);
+                        }
+                        Some(_) => {}
+                        None => {
+                            first_defs.insert(key, item);
+                        }
+                    }
+                }
+            }
+            differing
+        })
+        .collect()
+}
+
+/// A header module and the declarations it already contains
+struct HeaderModule {
+    module: ItemMod,
+    names: HashSet<String>,
+    foreign_names: HashSet<String>,
+}
+
+impl HeaderModule {
+    fn new(module: ItemMod) -> Self {
+        let mut header_module = HeaderModule {
+            module,
+            names: HashSet::new(),
+            foreign_names: HashSet::new(),
+        };
+        for item in header_module.items() {
+            match item {
+                Item::ForeignMod(ifm) => {
+                    let names = ifm.items.iter().filter_map(foreign_item_name);
+                    header_module.foreign_names.extend(names);
+                }
+                item => header_module.names.extend(item_name(item)),
+            }
+        }
+        header_module
+    }
+
+    fn items(&self) -> Vec<&Item> {
+        match self.module.content {
+            Some((_, ref items)) => items.iter().collect(),
+            None => vec![],
+        }
+    }
+
+    fn foreign_items(&self) -> Vec<&ForeignItem> {
+        self.items()
+            .into_iter()
+            .flat_map(|item| match item {
+                Item::ForeignMod(ifm) => ifm.items.iter().collect(),
+                _ => vec![],
+            })
+            .collect()
+    }
+
+    fn items_mut(&mut self) -> &mut Vec<Item> {
+        &mut self
+            .module
+            .content
+            .get_or_insert_with(Default::default)
+            .1
+    }
+
+    /// Add the declarations of another translation unit's submodule for the same header that
+    /// this one doesn't have yet. Each translation unit only translates the declarations it
+    /// uses, so neither module is necessarily a superset of the other. Declarations that differ
+    /// from the ones this module has are added to `differing`.
+    fn merge(&mut self, other: ItemMod, differing: &mut Vec<String>) {
+        let header = self.module.ident.to_string();
+        for item in other.content.map(|(_, items)| items).unwrap_or_default() {
+            match item {
+                Item::ForeignMod(ifm) => self.merge_foreign_mod(ifm, differing),
+                item => match item_name(&item) {
+                    Some(name) if self.names.contains(&name) => {
+                        let differs = self
+                            .items()
+                            .into_iter()
+                            .any(|old| item_name(old).as_ref() == Some(&name) && *old != item);
+                        if differs {
+                            differing.push(format!(
+                                "Declaration {} of header {} differs between translation units",
+                                name, header
+                            ));
+                        }
+                    }
+                    Some(name) => {
+                        self.names.insert(name);
+                        self.items_mut().push(item);
+                    }
+                    // Imports and impls are compared in full
+                    None => {
+                        if !self.items().contains(&&item) {
+                            self.items_mut().push(item);
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    fn merge_foreign_mod(&mut self, mut other: ItemForeignMod, differing: &mut Vec<String>) {
+        let header = self.module.ident.to_string();
+        let mut items = vec![];
+        for item in other.items {
+            match foreign_item_name(&item) {
+                Some(name) if self.foreign_names.contains(&name) => {
+                    let differs = self.foreign_items().into_iter().any(|old| {
+                        foreign_item_name(old).as_ref() == Some(&name) && *old != item
+                    });
+                    if differs {
+                        differing.push(format!(
+                            "Declaration {} of header {} differs between translation units",
+                            name, header
+                        ));
+                    }
+                }
+                Some(name) => {
+                    self.foreign_names.insert(name);
+                    items.push(item);
+                }
+                None => items.push(item),
+            }
+        }
+        other.items = items;
+        if other.items.is_empty() {
+            return;
+        }
+
+        let existing = self.items_mut().iter_mut().find_map(|item| match item {
+            Item::ForeignMod(ifm) if ifm.abi == other.abi => Some(ifm),
+            _ => None,
+        });
+        match existing {
+            Some(ifm) => ifm.items.extend(other.items),
+            None => self.items_mut().push(Item::ForeignMod(other)),
+        }
+    }
+}
+
+fn item_name(item: &Item) -> Option<String> {
+    match item {
+        Item::Use(_) | Item::Impl(_) => None,
+        item => item_ident(item).map(ToString::to_string),
+    }
+}
+
+fn foreign_item_name(item: &ForeignItem) -> Option<String> {
+    foreign_item_ident_vis(item).map(|(ident, _)| ident.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(module: &str) -> Vec<Box<Item>> {
+        vec![Box::new(syn::parse_str(module).unwrap())]
+    }
+
+    #[test]
+    fn merge_units() {
+        let point = "pub struct Point { pub x: i32, pub y: i32 }";
+        let units = vec![
+            unit(&format!("pub mod point_h {{ {} }}", point)),
+            unit(&format!("pub mod point_h {{ {} pub type Len = i32; }}", point)),
+        ];
+        let module = headers_module(units).unwrap().unwrap();
+        assert_eq!(module.matches("struct Point").count(), 1);
+        assert!(module.contains("type Len"));
+    }
+
+    #[test]
+    fn differing_declarations() {
+        let units = vec![
+            unit("pub mod point_h { pub struct Point { pub x: i32, pub y: i32 } }"),
+            unit("pub mod point_h { pub struct Point { pub x: i64, pub y: i64 } }"),
+        ];
+        let differing = headers_module(units).unwrap_err();
+        assert_eq!(differing.len(), 1);
+        assert!(differing[0].contains("Point"));
+    }
+
+    #[test]
+    fn differing_functions() {
+        let units = vec![
+            unit("pub mod math_h { pub fn rotl(x: u32) -> u32 { x << 1 | x >> 31 } }"),
+            unit("pub mod math_h { pub fn rotl(x: u32) -> u32 { x << 1 | x >> 31 } }"),
+            unit("pub mod math_h { pub fn rotl(x: u32) -> u32 { x << 1 | x >> 15 } }"),
+        ];
+        let differing = differing_fns(&units);
+        assert!(differing[0].is_empty() && differing[1].is_empty());
+        assert!(differing[2].contains("rotl"));
+    }
+}
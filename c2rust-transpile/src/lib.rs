@@ -1,50 +1,74 @@
+mod cache;
 mod diagnostics;
+mod error_enums;
+mod header_modules;
+mod lint_allows;
+mod transpiler;
 
 pub mod build_files;
+pub mod build_system;
 pub mod c_ast;
 pub mod cfg;
 mod compile_cmds;
+pub mod config_file;
 pub mod convert_type;
+pub mod dry_run;
+pub mod filters;
+pub mod merge;
+pub mod options;
 pub mod renamer;
+pub mod report;
 pub mod rust_ast;
 pub mod translator;
+pub mod unsafe_reasons;
 pub mod with_stmts;
 
-use std::collections::HashSet;
-use std::fs::{self, File};
-use std::io;
-use std::io::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::iter;
 use std::path::{Path, PathBuf};
 use std::process;
+use std::rc::Rc;
 
 use failure::Error;
 use log::warn;
 use regex::Regex;
-use serde_derive::Serialize;
+use serde_derive::{Deserialize, Serialize};
 
+pub use crate::c_ast::AstDumpFormat;
 use crate::c_ast::Printer;
 use crate::c_ast::*;
-pub use crate::diagnostics::Diagnostic;
+use crate::diagnostics::diag;
+pub use crate::diagnostics::{Diagnostic, DiagnosticMessage};
 use c2rust_ast_exporter as ast_exporter;
 
 use crate::build_files::{emit_build_files, get_build_dir, CrateConfig};
 use crate::compile_cmds::get_compile_commands;
 use crate::convert_type::RESERVED_NAMES;
-pub use crate::translator::ReplaceMode;
+use crate::dry_run::DryRun;
+use crate::report::Report;
+use crate::translator::ErrorCodes;
+pub use crate::translator::{BitfieldOrder, DecayRef, EnumStyle, ExprContext, ReplaceMode};
+pub use crate::transpiler::{TranslatedFile, Transpiler};
 use std::prelude::v1::Vec;
 
-type PragmaVec = Vec<(&'static str, Vec<&'static str>)>;
-type PragmaSet = indexmap::IndexSet<(&'static str, &'static str)>;
+type PragmaVec = Vec<(String, Vec<String>)>;
+type PragmaSet = indexmap::IndexSet<(String, String)>;
 type CrateSet = indexmap::IndexSet<ExternCrate>;
-type TranspileResult = Result<(PathBuf, PragmaVec, CrateSet), ()>;
+type TranspileResult =
+    Result<(PathBuf, PragmaVec, CrateSet, ErrorCodes, Vec<Box<syn::Item>>), ()>;
 
-/// Configuration settings for the translation process
-#[derive(Debug)]
+/// Configuration settings for the translation process. It is serialized into the key of the
+/// cache, with its unordered sets and maps sorted.
+#[derive(Debug, Serialize)]
 pub struct TranspilerConfig {
     // Debug output options
     pub dump_untyped_context: bool,
     pub dump_typed_context: bool,
     pub pretty_typed_context: bool,
+    pub dump_c_ast: Option<AstDumpFormat>,
+    pub dump_c_ast_function: Option<String>,
     pub dump_function_cfgs: bool,
     pub json_function_cfgs: bool,
     pub dump_cfg_liveness: bool,
@@ -55,7 +79,12 @@ pub struct TranspilerConfig {
     // Options that control translation
     pub incremental_relooper: bool,
     pub fail_on_multiple: bool,
+    #[serde(serialize_with = "cache::serialize_regex")]
     pub filter: Option<Regex>,
+    /// Functions to translate, declaring the others as extern
+    pub filter_fns: filters::Filter,
+    /// Files to translate, leaving the others to the C build
+    pub filter_files: filters::Filter,
     pub debug_relooper_labels: bool,
     pub prefix_function_names: Option<String>,
     pub translate_asm: bool,
@@ -68,15 +97,76 @@ pub struct TranspilerConfig {
     pub replace_unsupported_decls: ReplaceMode,
     pub translate_valist: bool,
     pub overwrite_existing: bool,
+    /// Translate every file again instead of reusing the translations in the cache
+    #[serde(skip)]
+    pub force: bool,
     pub reduce_type_annotations: bool,
     pub reorganize_definitions: bool,
+    pub header_modules: bool,
+    pub share_inline_fns: bool,
+    pub emit_src_locs: bool,
+    pub migration_status: bool,
+    pub fmt: bool,
+    #[serde(serialize_with = "cache::serialize_sorted")]
     pub enabled_warnings: HashSet<Diagnostic>,
+    /// Diagnostics suppressed with `--allow`
+    #[serde(serialize_with = "cache::serialize_sorted")]
+    pub allowed_warnings: HashSet<Diagnostic>,
+    /// Diagnostics promoted to errors with `--deny`
+    #[serde(serialize_with = "cache::serialize_sorted")]
+    pub denied_warnings: HashSet<Diagnostic>,
+    /// Write diagnostics as lines of JSON
+    pub json_diagnostics: bool,
     pub emit_no_std: bool,
     pub output_dir: Option<PathBuf>,
     pub translate_const_macros: bool,
     pub translate_fn_macros: bool,
+    pub enum_style: EnumStyle,
+    pub ascii_char_classes: bool,
+    pub translate_fmt: bool,
+    pub ndebug_asserts: bool,
+    pub variadic_shims: bool,
+    pub tagged_unions: Vec<String>,
+    pub fn_pointer_unions: bool,
+    pub emit_unsafe_reasons: bool,
+    /// Translate headers into declarations of what C defines, with the types and constants
+    pub bindings: bool,
+    /// Where to write the translation coverage report
+    pub report: Option<PathBuf>,
+    /// Show the files that would change instead of writing them
+    pub dry_run: Option<DryRun>,
+    pub debug_pointers: bool,
+    pub large_table_threshold: usize,
+    pub extract_large_tables: bool,
+    pub outline_threshold: Option<usize>,
+    pub static_cstr_returns: bool,
+    pub box_local_allocs: bool,
+    pub slice_fns: bool,
+    pub range_loops: bool,
+    pub error_enums: bool,
+    pub fixed_width_ints: bool,
+    pub emit_safe_wrappers: bool,
+    pub layout_asserts: bool,
+    pub owned_alloca_strings: bool,
+    pub derives: Vec<String>,
+    #[serde(serialize_with = "cache::serialize_sorted_map")]
+    pub struct_derives: HashMap<String, Vec<String>>,
+    pub frame_address_intrinsics: bool,
+    pub openmp_rayon: bool,
+    pub candidate_functions: Vec<String>,
+    pub restrict_attrs: bool,
+    pub bitfield_order: BitfieldOrder,
+    pub bitfield_tests: bool,
+    pub abi_tests: bool,
+    pub emit_test_stubs: bool,
+    pub ffi_tests: bool,
+    pub hybrid_build: bool,
+    pub c_unwind: bool,
+    pub targeted_allows: bool,
     pub disable_refactoring: bool,
     pub preserve_unused_functions: bool,
+    pub prune_unused: bool,
+    #[serde(serialize_with = "cache::serialize_display")]
     pub log_level: log::LevelFilter,
 
     // Options that control build files
@@ -85,6 +175,123 @@ pub struct TranspilerConfig {
     /// Names of translation units containing main functions that we should make
     /// into binaries
     pub binaries: Vec<String>,
+    /// How to configure projects given in place of a `compile_commands.json`
+    pub build_options: build_system::BuildOptions,
+    /// The target triple to translate for instead of the host's, following `--target`
+    pub target: Option<String>,
+    /// The name of the crate, instead of that of the output directory
+    pub crate_name: Option<String>,
+
+    // Configurations overriding this one, following `c2rust.toml`
+    /// Configurations of files, by path
+    pub file_configs: Vec<(PathBuf, TranspilerConfig)>,
+    /// Configurations of functions, by name
+    #[serde(serialize_with = "cache::serialize_sorted_map")]
+    pub function_configs: HashMap<String, TranspilerConfig>,
+}
+
+/// The defaults of the command line, for configurations built by the library API
+impl Default for TranspilerConfig {
+    fn default() -> Self {
+        TranspilerConfig {
+            dump_untyped_context: false,
+            dump_typed_context: false,
+            pretty_typed_context: false,
+            dump_c_ast: None,
+            dump_c_ast_function: None,
+            dump_function_cfgs: false,
+            json_function_cfgs: false,
+            dump_cfg_liveness: false,
+            dump_structures: false,
+            verbose: false,
+            debug_ast_exporter: false,
+
+            incremental_relooper: true,
+            fail_on_multiple: false,
+            filter: None,
+            filter_fns: filters::Filter::default(),
+            filter_files: filters::Filter::default(),
+            debug_relooper_labels: false,
+            prefix_function_names: None,
+            translate_asm: true,
+            use_c_loop_info: true,
+            use_c_multiple_info: true,
+            simplify_structures: true,
+            panic_on_translator_failure: false,
+            emit_modules: false,
+            fail_on_error: false,
+            replace_unsupported_decls: ReplaceMode::Extern,
+            translate_valist: true,
+            overwrite_existing: false,
+            force: false,
+            reduce_type_annotations: false,
+            reorganize_definitions: false,
+            header_modules: false,
+            share_inline_fns: false,
+            emit_src_locs: false,
+            migration_status: false,
+            fmt: false,
+            enabled_warnings: HashSet::new(),
+            allowed_warnings: HashSet::new(),
+            denied_warnings: HashSet::new(),
+            json_diagnostics: false,
+            emit_no_std: false,
+            output_dir: None,
+            translate_const_macros: false,
+            translate_fn_macros: false,
+            enum_style: EnumStyle::Consts,
+            ascii_char_classes: false,
+            translate_fmt: false,
+            ndebug_asserts: false,
+            variadic_shims: false,
+            tagged_unions: vec![],
+            fn_pointer_unions: false,
+            emit_unsafe_reasons: false,
+            bindings: false,
+            report: None,
+            dry_run: None,
+            debug_pointers: false,
+            large_table_threshold: 256,
+            extract_large_tables: false,
+            outline_threshold: None,
+            static_cstr_returns: false,
+            box_local_allocs: false,
+            slice_fns: false,
+            range_loops: false,
+            error_enums: false,
+            fixed_width_ints: false,
+            emit_safe_wrappers: false,
+            layout_asserts: false,
+            owned_alloca_strings: false,
+            derives: vec![],
+            struct_derives: HashMap::new(),
+            frame_address_intrinsics: false,
+            openmp_rayon: false,
+            candidate_functions: vec![],
+            restrict_attrs: false,
+            bitfield_order: BitfieldOrder::Target,
+            bitfield_tests: false,
+            abi_tests: false,
+            emit_test_stubs: false,
+            ffi_tests: false,
+            hybrid_build: false,
+            c_unwind: false,
+            targeted_allows: false,
+            disable_refactoring: false,
+            preserve_unused_functions: false,
+            prune_unused: false,
+            log_level: log::LevelFilter::Warn,
+
+            emit_build_files: false,
+            binaries: vec![],
+            build_options: build_system::BuildOptions::default(),
+            target: None,
+            crate_name: None,
+
+            file_configs: vec![],
+            function_configs: HashMap::new(),
+        }
+    }
 }
 
 impl TranspilerConfig {
@@ -94,15 +301,47 @@ impl TranspilerConfig {
         self.binaries.contains(&name)
     }
 
+    /// Is the translation for WebAssembly without an OS, and so without libc?
+    fn is_wasm(&self) -> bool {
+        self.target
+            .as_deref()
+            .map_or(false, |target| target.starts_with("wasm32-unknown-unknown"))
+    }
+
+    /// Can translations be reused from the cache? Translating again is needed to dump the ASTs
+    /// and control flow graphs.
+    fn use_cache(&self) -> bool {
+        let dumps = self.dump_untyped_context
+            || self.dump_typed_context
+            || self.pretty_typed_context
+            || self.dump_c_ast.is_some()
+            || self.dump_function_cfgs
+            || self.dump_cfg_liveness
+            || self.dump_structures
+            || self.debug_ast_exporter;
+        !self.force && !dumps
+    }
+
     fn crate_name(&self) -> String {
-        self.output_dir
-            .as_ref()
-            .and_then(|x| x.file_name().map(|x| x.to_string_lossy().into_owned()))
+        self.crate_name
+            .clone()
+            .or_else(|| {
+                let output_dir = self.output_dir.as_ref()?;
+                output_dir.file_name().map(|x| x.to_string_lossy().into_owned())
+            })
             .unwrap_or_else(|| "c2rust_out".into())
     }
+
+    /// The configuration of the file `input_path`, which `c2rust.toml` may override
+    fn file_config(&self, input_path: &Path) -> &TranspilerConfig {
+        self.file_configs
+            .iter()
+            .find(|(path, _)| path == input_path)
+            .map_or(self, |(_, tcfg)| tcfg)
+    }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum ExternCrate {
     C2RustBitfields,
     C2RustAsmCasts,
@@ -110,6 +349,9 @@ pub enum ExternCrate {
     NumTraits,
     Memoffset,
     Libc,
+    C2RustAnalysisRt,
+    Rayon,
+    Half,
 }
 
 #[derive(Serialize)]
@@ -140,6 +382,9 @@ impl From<ExternCrate> for ExternCrateDetails {
             ExternCrate::NumTraits => Self::new("num-traits", "0.2", true),
             ExternCrate::Memoffset => Self::new("memoffset", "0.5", true),
             ExternCrate::Libc => Self::new("libc", "0.2", false),
+            ExternCrate::C2RustAnalysisRt => Self::new("c2rust-analysis-rt", "0.1", false),
+            ExternCrate::Rayon => Self::new("rayon", "1.5", false),
+            ExternCrate::Half => Self::new("half", "2.2", false),
         }
     }
 }
@@ -199,15 +444,108 @@ fn get_module_name(
 /// Main entry point to transpiler. Called from CLI tools with the result of
 /// clap::App::get_matches().
 pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]) {
-    diagnostics::init(tcfg.enabled_warnings.clone(), tcfg.log_level);
+    let report_path = tcfg.report.clone();
+    let dry_run = tcfg.dry_run;
+    let mut report = Report::default();
+    transpile_project(tcfg, cc_db, extra_clang_args, &mut report);
+    if let Some(path) = report_path {
+        if let Err(e) = report.write(dry_run, &path) {
+            warn!("Unable to write the report to {}: {}", path.display(), e);
+        }
+    }
+
+    // Diagnostics promoted to errors fail the translation once it is done
+    let denied = diagnostics::denied_count();
+    if denied > 0 {
+        log::error!("Translation failed with {} denied diagnostics", denied);
+        process::exit(1);
+    }
+}
+
+fn transpile_project(
+    mut tcfg: TranspilerConfig,
+    cc_db: &Path,
+    extra_clang_args: &[&str],
+    report: &mut Report,
+) {
+    diagnostics::init(
+        tcfg.enabled_warnings.clone(),
+        tcfg.allowed_warnings.clone(),
+        tcfg.denied_warnings.clone(),
+        tcfg.json_diagnostics,
+        tcfg.log_level,
+    );
+
+    let errors = iter::once(&tcfg)
+        .chain(tcfg.file_configs.iter().map(|(_, file_tcfg)| file_tcfg))
+        .filter_map(|tcfg| tcfg.validate().err())
+        .flatten()
+        .collect::<Vec<_>>();
+    if !errors.is_empty() {
+        for error in errors {
+            log::error!("{}", error);
+        }
+        process::exit(1);
+    }
+    for name in tcfg.experimental_options() {
+        log::info!("--{} is experimental", name);
+    }
+
+    // `--fmt` runs the `rustfmt` found on the `PATH`, so check for it before translating
+    let fmt = iter::once(&tcfg)
+        .chain(tcfg.file_configs.iter().map(|(_, file_tcfg)| file_tcfg))
+        .any(|tcfg| tcfg.fmt);
+    if fmt {
+        if let Err(e) = rustfmt("") {
+            log::error!("--fmt needs rustfmt on the PATH: {}", e);
+            process::exit(1);
+        }
+    }
 
-    let lcmds = get_compile_commands(cc_db, &tcfg.filter).expect(&format!(
-        "Could not parse compile commands from {}",
-        cc_db.to_string_lossy()
-    ));
+    // Projects given in place of a compilation database are configured by their build system,
+    // and each of their targets is translated into a crate
+    let (cc_db, targets) = match build_system::load_project(cc_db, &tcfg.build_options) {
+        Ok(Some(project)) => (project.compile_commands, project.targets),
+        Ok(None) => (cc_db.to_owned(), vec![]),
+        Err(e) => {
+            log::error!("Unable to configure {}: {}", cc_db.display(), e);
+            process::exit(1);
+        }
+    };
+    // Rewrite `cl.exe` commands for the GCC-style clang driver
+    let normalized =
+        build_system::msvc::normalize(&cc_db, &tcfg.build_options, tcfg.target.as_deref());
+    let cc_db = match normalized {
+        Ok(Some(normalized)) => normalized,
+        Ok(None) => cc_db,
+        Err(e) => {
+            log::error!("Unable to read {}: {}", cc_db.display(), e);
+            process::exit(1);
+        }
+    };
+    let cc_db = cc_db.as_path();
+    if !targets.is_empty() {
+        tcfg.emit_build_files = true;
+        tcfg.emit_modules = true;
+        for (_, file_tcfg) in &mut tcfg.file_configs {
+            file_tcfg.emit_build_files = true;
+            file_tcfg.emit_modules = true;
+        }
+    }
+
+    let lcmds = get_compile_commands(cc_db, &tcfg.filter, &tcfg.filter_files, &targets)
+        .expect(&format!(
+            "Could not parse compile commands from {}",
+            cc_db.to_string_lossy()
+        ));
 
     // Specify path to system include dir on macOS 10.14 and later. Disable the blocks extension.
-    let clang_args: Vec<String> = get_extra_args_macos();
+    let mut clang_args: Vec<String> = match tcfg.target {
+        Some(ref target) if !target.contains("-apple-") => vec![],
+        _ => get_extra_args_macos(),
+    };
+    // Cross translations are preprocessed for the target, and its type sizes are exported
+    clang_args.extend(tcfg.target.iter().map(|target| format!("--target={}", target)));
     let mut clang_args: Vec<&str> = clang_args.iter().map(AsRef::as_ref).collect();
     clang_args.extend_from_slice(extra_clang_args);
 
@@ -217,19 +555,7 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
     let build_dir = get_build_dir(&tcfg, cc_db);
     for lcmd in &lcmds {
         let cmds = &lcmd.cmd_inputs;
-        let lcmd_name = lcmd
-            .output
-            .as_ref()
-            .map(|output| {
-                let output_path = Path::new(output);
-                output_path
-                    .file_stem()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .to_owned()
-            })
-            .unwrap_or_else(|| tcfg.crate_name());
+        let lcmd_name = lcmd.name().unwrap_or_else(|| tcfg.crate_name());
         let build_dir = if lcmd.top_level {
             build_dir.to_path_buf()
         } else {
@@ -260,13 +586,16 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
         let results = cmds
             .iter()
             .map(|cmd| {
+                let input_path = cmd.abs_file();
                 transpile_single(
-                    &tcfg,
-                    cmd.abs_file(),
+                    tcfg.file_config(&input_path),
+                    input_path,
+                    &cmd.args(),
                     &ancestor_path,
                     &build_dir,
                     cc_db,
                     &clang_args,
+                    &HashSet::new(),
                 )
             })
             .collect::<Vec<TranspileResult>>();
@@ -274,16 +603,82 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
         let mut modules_skipped = false;
         let mut pragmas = PragmaSet::new();
         let mut crates = CrateSet::new();
-        for res in results {
+        let mut c_inputs = if tcfg.hybrid_build {
+            lcmd.c_inputs.clone()
+        } else {
+            vec![]
+        };
+        let mut ffi_sources = vec![];
+        let mut error_codes = vec![];
+        let mut header_mods = vec![];
+        let mut header_mod_cmds = vec![];
+        for (cmd, res) in cmds.iter().zip(results) {
+            if tcfg.report.is_some() {
+                let module = res.as_ref().ok().map(|(module, ..)| module.as_path());
+                report.add(&cmd.abs_file(), module);
+            }
             match res {
-                Ok((module, pragma_vec, crate_set)) => {
+                Ok((module, pragma_vec, crate_set, unit_error_codes, unit_header_mods)) => {
+                    // The translator only emits the C side of the FFI tests for modules that
+                    // have functions to test
+                    let ffi_file = module.with_extension("ffi.c");
+                    if tcfg.ffi_tests && ffi_file.exists() {
+                        ffi_sources.push((Rc::clone(cmd), ffi_file));
+                    }
                     modules.push(module);
                     crates.extend(crate_set);
+                    error_codes.push(unit_error_codes);
+                    header_mods.push(unit_header_mods);
+                    header_mod_cmds.push(Rc::clone(cmd));
 
                     num_transpiled_files += 1;
                     for (key, vals) in pragma_vec {
                         for val in vals {
-                            pragmas.insert((key, val));
+                            pragmas.insert((key.clone(), val));
+                        }
+                    }
+                }
+                // Hybrid crates compile the files that failed to translate from C, unless they
+                // were translated by a previous run
+                Err(_)
+                    if tcfg.hybrid_build
+                        && !get_output_path(&tcfg, &cmd.abs_file(), &ancestor_path, &build_dir)
+                            .exists() =>
+                {
+                    c_inputs.push(Rc::clone(cmd));
+                }
+                Err(_) => {
+                    modules_skipped = true;
+                }
+            }
+        }
+
+        // Files defining header functions differently from the files translated before them are
+        // translated again, keeping their own copies of these functions
+        let differing = header_modules::differing_fns(&header_mods);
+        let units = header_mod_cmds.iter().zip(&mut header_mods).zip(differing);
+        for ((cmd, unit_header_mods), local_inline_fns) in units {
+            if local_inline_fns.is_empty() {
+                continue;
+            }
+            let input_path = cmd.abs_file();
+            let res = transpile_single(
+                tcfg.file_config(&input_path),
+                input_path,
+                &cmd.args(),
+                &ancestor_path,
+                &build_dir,
+                cc_db,
+                &clang_args,
+                &local_inline_fns,
+            );
+            match res {
+                Ok((_, pragma_vec, crate_set, _, retranslated_header_mods)) => {
+                    *unit_header_mods = retranslated_header_mods;
+                    crates.extend(crate_set);
+                    for (key, vals) in pragma_vec {
+                        for val in vals {
+                            pragmas.insert((key.clone(), val));
                         }
                     }
                 }
@@ -295,6 +690,52 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
         pragmas.sort();
         crates.sort();
 
+        // Error code families are shared by all the modules of the crate
+        if let Some(contents) = error_enums::error_enums_module(&error_codes, tcfg.emit_no_std) {
+            let input = ancestor_path.join(format!("{}.c", error_enums::ERROR_CODES_MODULE));
+            let path = get_output_path(&tcfg, &input, &ancestor_path, &build_dir);
+            if modules.contains(&path) {
+                warn!(
+                    "Not writing error enums, {} is already a translated module",
+                    path.display()
+                );
+            } else if let Err(e) =
+                write_if_changed(tcfg.dry_run, &path, &format_output(&tcfg, &path, contents))
+            {
+                warn!("Unable to write error enums to {}: {}", path.display(), e);
+            } else {
+                modules.push(path);
+            }
+        }
+
+        // Header modules are shared by all the modules of the crate, which must agree on their
+        // declarations
+        let headers_module = match header_modules::headers_module(header_mods) {
+            Ok(headers_module) => headers_module,
+            Err(differing) => {
+                for error in differing {
+                    log::error!("{}, so the header can't be shared", error);
+                }
+                process::exit(1);
+            }
+        };
+        if let Some(contents) = headers_module {
+            let input = ancestor_path.join(format!("{}.c", header_modules::HEADERS_MODULE));
+            let path = get_output_path(&tcfg, &input, &ancestor_path, &build_dir);
+            if modules.contains(&path) {
+                warn!(
+                    "Not writing header modules, {} is already a translated module",
+                    path.display()
+                );
+            } else if let Err(e) =
+                write_if_changed(tcfg.dry_run, &path, &format_output(&tcfg, &path, contents))
+            {
+                warn!("Unable to write header modules to {}: {}", path.display(), e);
+            } else {
+                modules.push(path);
+            }
+        }
+
         if tcfg.emit_build_files {
             if modules_skipped {
                 // If we skipped a file, we may not have collected all required pragmas
@@ -308,6 +749,8 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
                 pragmas,
                 crates,
                 link_cmd: lcmd,
+                c_inputs,
+                ffi_sources,
             };
             if lcmd.top_level {
                 top_level_ccfg = Some(ccfg);
@@ -330,6 +773,11 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
             emit_build_files(&tcfg, &build_dir, top_level_ccfg, Some(workspace_members));
         reorganize_definitions(&tcfg, &build_dir, crate_file)
             .unwrap_or_else(|e| warn!("Reorganizing definitions failed: {}", e));
+        // The crate to check wasn't written by a dry run
+        if tcfg.targeted_allows && tcfg.dry_run.is_none() {
+            lint_allows::insert_targeted_allows(&build_dir)
+                .unwrap_or_else(|e| warn!("Inserting lint allows failed: {}", e));
+        }
     }
 }
 
@@ -375,7 +823,11 @@ fn reorganize_definitions(
     crate_file: Option<PathBuf>,
 ) -> Result<(), Error> {
     // We only run the reorganization refactoring if we emitted a fresh crate file
-    if crate_file.is_none() || tcfg.disable_refactoring || !tcfg.reorganize_definitions {
+    if crate_file.is_none()
+        || tcfg.disable_refactoring
+        || !tcfg.reorganize_definitions
+        || tcfg.dry_run.is_some()
+    {
         return Ok(());
     }
 
@@ -394,20 +846,49 @@ fn reorganize_definitions(
 fn transpile_single(
     tcfg: &TranspilerConfig,
     input_path: PathBuf,
+    compile_args: &[String],
     ancestor_path: &Path,
     build_dir: &Path,
     cc_db: &Path,
     extra_clang_args: &[&str],
+    local_inline_fns: &HashSet<String>,
 ) -> TranspileResult {
     let output_path = get_output_path(tcfg, &input_path, ancestor_path, build_dir);
-    if output_path.exists() && !tcfg.overwrite_existing {
-        warn!("Skipping existing file {}", output_path.display());
+    let cache_key = cache::key(
+        tcfg,
+        &input_path,
+        compile_args,
+        extra_clang_args,
+        local_inline_fns,
+    );
+    if let Some(cache_key) = cache_key.as_deref().filter(|_| tcfg.use_cache()) {
+        if let Some((pragmas, crates, error_codes, header_mods)) =
+            cache::load(build_dir, &input_path, cache_key)
+        {
+            log::info!("Reusing the translation of {}", input_path.display());
+            return Ok((output_path, pragmas, crates, error_codes, header_mods));
+        }
+    }
+
+    // A dry run diffs existing outputs instead of skipping them, and files translated again to
+    // keep their own copies of header functions replace their first translation
+    if output_path.exists()
+        && !tcfg.overwrite_existing
+        && tcfg.dry_run.is_none()
+        && local_inline_fns.is_empty()
+    {
+        diag!(
+            Diagnostic::SkippedFile,
+            "Skipping existing file {}",
+            output_path.display()
+        );
         return Err(());
     }
 
     let file = input_path.file_name().unwrap().to_str().unwrap();
     if !input_path.exists() {
-        warn!(
+        diag!(
+            Diagnostic::SkippedFile,
             "Input C file {} does not exist, skipping!",
             input_path.display()
         );
@@ -426,7 +907,8 @@ fn transpile_single(
         tcfg.debug_ast_exporter,
     ) {
         Err(e) => {
-            warn!(
+            diag!(
+                Diagnostic::SkippedFile,
                 "Error: {}. Skipping {}; is it well-formed C?",
                 e,
                 input_path.display()
@@ -438,6 +920,13 @@ fn transpile_single(
 
     println!("Transpiling {}", file);
 
+    // The file and the headers it includes, whose changes invalidate the cached translation
+    let inputs = untyped_context
+        .files
+        .iter()
+        .filter_map(|file| file.path.clone())
+        .collect::<Vec<_>>();
+
     if tcfg.dump_untyped_context {
         println!("CBOR Clang AST");
         println!("{:#?}", untyped_context);
@@ -462,29 +951,115 @@ fn transpile_single(
         println!("{:#?}", Printer::new(io::stdout()).print(&typed_context));
     }
 
-    // Perform the translation
-    let (translated_string, pragmas, crates) =
-        translator::translate(typed_context, &tcfg, input_path);
+    if let Some(format) = tcfg.dump_c_ast {
+        let function = tcfg.dump_c_ast_function.as_deref();
+        if let Err(e) = dump_c_ast(
+            &typed_context,
+            &input_path,
+            format,
+            function,
+            &mut io::stdout(),
+        ) {
+            warn!("Failed to dump the C AST: {}", e);
+        }
+    }
 
-    let mut file = match File::create(&output_path) {
-        Ok(file) => file,
-        Err(e) => panic!(
-            "Unable to open file {} for writing: {}",
-            output_path.display(),
-            e
-        ),
-    };
+    // Perform the translation
+    let (translated_string, pragmas, crates, extra_files, error_codes, header_mods) =
+        translator::translate(typed_context, &tcfg, input_path.clone(), local_inline_fns)
+            .unwrap_or_else(|e| panic!("Translation failed: {}", e));
 
-    match file.write_all(translated_string.as_bytes()) {
-        Ok(()) => (),
-        Err(e) => panic!(
+    let translated_string = format_output(tcfg, &output_path, translated_string);
+    if let Err(e) = write_if_changed(tcfg.dry_run, &output_path, &translated_string) {
+        panic!(
             "Unable to write translation to file {}: {}",
             output_path.display(),
             e
-        ),
-    };
+        );
+    }
+
+    // Tables extracted with `--extract-large-tables` are `include!`d relative to the module, and
+    // the `--candidate-function` report is written next to it
+    let mut outputs = vec![output_path.clone()];
+    for (file_name, contents) in extra_files {
+        let table_path = output_path.with_file_name(file_name);
+        if let Err(e) = write_if_changed(tcfg.dry_run, &table_path, &contents) {
+            panic!("Unable to write file {}: {}", table_path.display(), e);
+        }
+        outputs.push(table_path);
+    }
 
-    Ok((output_path, pragmas, crates))
+    let result = (pragmas, crates, error_codes, header_mods);
+    // Nothing was written by a dry run, so there is nothing to cache either
+    if let Some(cache_key) = cache_key.filter(|_| tcfg.dry_run.is_none()) {
+        let entry = cache::CacheEntry::new(cache_key, &inputs, &outputs, &result);
+        if let Err(e) = cache::store(build_dir, &input_path, &entry) {
+            warn!("Unable to cache the translation of {}: {}", file, e);
+        }
+    }
+    let (pragmas, crates, error_codes, header_mods) = result;
+    Ok((output_path, pragmas, crates, error_codes, header_mods))
+}
+
+/// With `--fmt`, run the translated module to be written to `path` through `rustfmt`. The
+/// source locations from `--emit-src-locs` are attributes, so they stay attached to the same
+/// items. `rustfmt` must be on the `PATH`, which `transpile` checks before translating. A module
+/// that `rustfmt` rejects is reported and written as printed.
+fn format_output(tcfg: &TranspilerConfig, path: &Path, contents: String) -> String {
+    if !tcfg.fmt {
+        return contents;
+    }
+    match rustfmt(&contents) {
+        Ok(formatted) => formatted,
+        Err(e) => {
+            diag!(
+                Diagnostic::Formatting,
+                "Unable to format {} with rustfmt, writing it unformatted: {}",
+                path.display(),
+                e
+            );
+            contents
+        }
+    }
+}
+
+/// Format Rust source by piping it through the `rustfmt` on the `PATH`
+fn rustfmt(contents: &str) -> io::Result<String> {
+    let mut child = process::Command::new("rustfmt")
+        .args(&["--edition", "2021", "--quiet"])
+        .stdin(process::Stdio::piped())
+        .stdout(process::Stdio::piped())
+        .stderr(process::Stdio::piped())
+        .spawn()?;
+    // Write from another thread so a full stdout pipe can't deadlock us
+    let mut stdin = child.stdin.take().expect("rustfmt stdin is piped");
+    let input = contents.to_owned();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output()?;
+    writer.join().expect("rustfmt writer panicked")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::new(io::ErrorKind::Other, stderr.trim().to_owned()));
+    }
+    String::from_utf8(output.stdout).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Write `contents` to `path` unless the file already has exactly these contents. Leaving
+/// unchanged files alone keeps their modification times, so re-running the transpiler over a
+/// project doesn't make cargo rebuild it or show the files as modified. Returns whether the
+/// file was written, or would have been by a dry run.
+fn write_if_changed(dry_run: Option<DryRun>, path: &Path, contents: &str) -> io::Result<bool> {
+    let existing = fs::read(path).ok();
+    if existing.as_deref() == Some(contents.as_bytes()) {
+        return Ok(false);
+    }
+    if let Some(mode) = dry_run {
+        let existing = existing.map(|existing| String::from_utf8_lossy(&existing).into_owned());
+        dry_run::show(mode, path, existing.as_deref(), contents);
+        return Ok(true);
+    }
+    fs::write(path, contents)?;
+    Ok(true)
 }
 
 fn get_output_path(
@@ -523,7 +1098,7 @@ fn get_output_path(
 
         // Create the parent directory if it doesn't exist
         let parent = output_path.parent().unwrap();
-        if !parent.exists() {
+        if !parent.exists() && tcfg.dry_run.is_none() {
             fs::create_dir_all(&parent).expect(&format!(
                 "couldn't create source directory: {}",
                 parent.display()
@@ -534,3 +1109,28 @@ fn get_output_path(
         path_buf
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Needs the `rustfmt` of the toolchain on the `PATH`
+    #[test]
+    fn format_with_rustfmt() {
+        let path = Path::new("f.rs");
+        let printed = "pub unsafe extern \"C\" fn f(mut x: i32) -> i32 { return x+1; }\n";
+        let tcfg = TranspilerConfig::default();
+        assert_eq!(format_output(&tcfg, path, printed.to_owned()), printed);
+
+        let tcfg = TranspilerConfig {
+            fmt: true,
+            ..TranspilerConfig::default()
+        };
+        let formatted = "pub unsafe extern \"C\" fn f(mut x: i32) -> i32 {\n    return x + 1;\n}\n";
+        assert_eq!(format_output(&tcfg, path, printed.to_owned()), formatted);
+
+        // Modules that don't parse are written as printed
+        let invalid = "pub fn f( {}\n";
+        assert_eq!(format_output(&tcfg, path, invalid.to_owned()), invalid);
+    }
+}
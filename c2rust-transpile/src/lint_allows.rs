@@ -0,0 +1,245 @@
+//! Targeted lint allows for emitted crates.
+//!
+//! Rather than blanket-allowing every lint that translated code tends to trigger at the crate
+//! level, we run `cargo check` on the emitted crate, collect the lints it reports, and put an
+//! `#[allow(...)]` on each top-level item that actually triggers them.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use failure::{format_err, Error};
+use log::{info, warn};
+use serde_json::Value;
+use syn::spanned::Spanned;
+
+use crate::write_if_changed;
+
+/// Check the crate (or workspace) in `build_dir` and annotate the items that trigger lints.
+pub fn insert_targeted_allows(build_dir: &Path) -> Result<(), Error> {
+    let output = process::Command::new("cargo")
+        .args(&["check", "--workspace", "--message-format=json"])
+        .current_dir(build_dir)
+        .stderr(process::Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        // Deny-by-default lints fail the check too, so keep going with whatever we collected.
+        warn!("cargo check failed, lint allows may be incomplete");
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lints: HashMap<PathBuf, Vec<(usize, String)>> = HashMap::new();
+    for line in stdout.lines() {
+        let msg: Value = match serde_json::from_str(line) {
+            Ok(msg) => msg,
+            Err(_) => continue,
+        };
+        if let Some((file, line, lint)) = lint_location(&msg) {
+            lints
+                .entry(build_dir.join(file))
+                .or_default()
+                .push((line, lint));
+        }
+    }
+
+    for (file, file_lints) in lints {
+        // Lints reported against files outside the crate, e.g. in dependencies, are not ours.
+        if !file.starts_with(build_dir) || !file.exists() {
+            continue;
+        }
+        annotate_file(&file, &file_lints)?;
+    }
+    Ok(())
+}
+
+/// Extract the file, line and lint name from a `cargo check` JSON message, if it is a lint.
+fn lint_location(msg: &Value) -> Option<(String, usize, String)> {
+    if msg["reason"] != "compiler-message" {
+        return None;
+    }
+    let diag = &msg["message"];
+    if diag["level"] != "warning" && diag["level"] != "error" {
+        return None;
+    }
+    let lint = diag["code"]["code"].as_str()?;
+    // Error codes (E0XXX) aren't lints and can't be allowed.
+    if lint.starts_with('E') && lint[1..].chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let mut span = diag["spans"]
+        .as_array()?
+        .iter()
+        .find(|span| span["is_primary"] == true)?;
+    // Attribute lints inside macro expansions to the outermost invocation site.
+    while span["expansion"]["span"].is_object() {
+        span = &span["expansion"]["span"];
+    }
+    let file = span["file_name"].as_str()?.to_owned();
+    let line = span["line_start"].as_u64()? as usize;
+    Some((file, line, lint.to_owned()))
+}
+
+/// Line ranges of an item that can carry an `#[allow]` attribute.
+struct ItemLines {
+    /// First line of the item, including its outer attributes.
+    start: usize,
+    end: usize,
+    indent: usize,
+}
+
+fn annotate_file(file: &Path, file_lints: &[(usize, String)]) -> Result<(), Error> {
+    let source = fs::read_to_string(file)?;
+    let annotated = annotate_source(file, &source, file_lints)
+        .map_err(|e| format_err!("Could not parse {}: {}", file.display(), e))?;
+    if let Some(annotated) = annotated {
+        // Lint allows are only inserted into crates that were written, never by a dry run
+        write_if_changed(None, file, &annotated)?;
+    }
+    Ok(())
+}
+
+/// Put an `#[allow]` on the innermost item of the `source` of `file` containing each of the
+/// `file_lints`, or return `None` if none of them are inside an item.
+fn annotate_source(
+    file: &Path,
+    source: &str,
+    file_lints: &[(usize, String)],
+) -> syn::Result<Option<String>> {
+    let parsed = syn::parse_file(source)?;
+
+    let mut items = vec![];
+    collect_item_lines(&parsed.items, &mut items);
+
+    // Map each lint to the innermost item containing it, keyed by the item's first line.
+    let mut allows: BTreeMap<usize, (usize, BTreeSet<&str>)> = BTreeMap::new();
+    for (line, lint) in file_lints {
+        let item = items
+            .iter()
+            .filter(|item| item.start <= *line && *line <= item.end)
+            .min_by_key(|item| item.end - item.start);
+        match item {
+            Some(item) => {
+                allows
+                    .entry(item.start)
+                    .or_insert_with(|| (item.indent, BTreeSet::new()))
+                    .1
+                    .insert(lint);
+            }
+            None => info!(
+                "{}:{}: lint {} is not inside an item, ignoring",
+                file.display(),
+                line,
+                lint
+            ),
+        }
+    }
+    if allows.is_empty() {
+        return Ok(None);
+    }
+
+    let mut lines: Vec<String> = source.lines().map(String::from).collect();
+    // Insert from the bottom up so earlier line numbers stay valid.
+    for (start, (indent, lints)) in allows.into_iter().rev() {
+        let lints = lints.into_iter().collect::<Vec<_>>().join(", ");
+        let attr = format!("{:indent$}#[allow({})]", "", lints, indent = indent);
+        lines.insert(start - 1, attr);
+    }
+    let mut annotated = lines.join("\n");
+    annotated.push('\n');
+    Ok(Some(annotated))
+}
+
+fn collect_item_lines(items: &[syn::Item], out: &mut Vec<ItemLines>) {
+    for item in items {
+        push_item_lines(item.span(), out);
+        match item {
+            syn::Item::Mod(syn::ItemMod {
+                content: Some((_, items)),
+                ..
+            }) => collect_item_lines(items, out),
+            syn::Item::ForeignMod(fm) => {
+                for foreign_item in &fm.items {
+                    push_item_lines(foreign_item.span(), out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn push_item_lines(span: proc_macro2::Span, out: &mut Vec<ItemLines>) {
+    let (start, end) = (span.start(), span.end());
+    out.push(ItemLines {
+        start: start.line,
+        end: end.line,
+        indent: start.column,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A lint reported by `cargo check --message-format=json` inside a macro expansion
+    const MESSAGE: &str = r#"{"reason":"compiler-message","package_id":"demo 0.1.0 (path+file:///tmp/demo)","target":{"kind":["lib"],"crate_types":["lib"],"name":"demo","src_path":"/tmp/demo/src/lib.rs","edition":"2021","doc":true,"doctest":true,"test":true},"message":{"rendered":"warning: unnecessary parentheses around assigned value\n","children":[],"code":{"code":"unused_parens","explanation":null},"level":"warning","message":"unnecessary parentheses around assigned value","spans":[{"byte_end":93,"byte_start":88,"column_end":22,"column_start":17,"expansion":{"def_site_span":{"byte_end":40,"byte_start":0,"column_end":2,"column_start":1,"expansion":null,"file_name":"src/demo.rs","is_primary":false,"label":null,"line_end":3,"line_start":1,"suggested_replacement":null,"suggestion_applicability":null,"text":[]},"macro_decl_name":"assign!","span":{"byte_end":120,"byte_start":104,"column_end":21,"column_start":5,"expansion":null,"file_name":"src/demo.rs","is_primary":false,"label":null,"line_end":7,"line_start":7,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}},"file_name":"src/demo.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}]}}"#;
+
+    #[test]
+    fn lint_location_of_message() {
+        let mut msg: Value = serde_json::from_str(MESSAGE).unwrap();
+        // The lint is reported at the invocation of the macro, not in its definition
+        assert_eq!(
+            lint_location(&msg),
+            Some(("src/demo.rs".to_owned(), 7, "unused_parens".to_owned()))
+        );
+
+        msg["message"]["code"]["code"] = "E0308".into();
+        assert_eq!(lint_location(&msg), None);
+        msg["reason"] = "build-finished".into();
+        assert_eq!(lint_location(&msg), None);
+    }
+
+    #[test]
+    fn allows_on_innermost_items() {
+        let source = "\
+mod inner {
+    pub fn f() {
+        let x = 1;
+    }
+}
+
+extern \"C\" {
+    fn g(x: i32);
+}
+";
+        let mut items = vec![];
+        collect_item_lines(&syn::parse_file(source).unwrap().items, &mut items);
+        let items = items
+            .iter()
+            .map(|item| (item.start, item.end, item.indent))
+            .collect::<Vec<_>>();
+        assert_eq!(items, [(1, 5, 0), (2, 4, 4), (7, 9, 0), (8, 8, 4)]);
+
+        let lints = [
+            (3, "unused_variables".to_owned()),
+            (8, "improper_ctypes".to_owned()),
+            (8, "dead_code".to_owned()),
+        ];
+        let annotated = annotate_source(Path::new("src/demo.rs"), source, &lints).unwrap();
+        let expected = "\
+mod inner {
+    #[allow(unused_variables)]
+    pub fn f() {
+        let x = 1;
+    }
+}
+
+extern \"C\" {
+    #[allow(dead_code, improper_ctypes)]
+    fn g(x: i32);
+}
+";
+        assert_eq!(annotated.as_deref(), Some(expected));
+        assert_eq!(annotate_source(Path::new("src/demo.rs"), source, &[]).unwrap(), None);
+    }
+}
@@ -0,0 +1,447 @@
+//! Merging translations of a project for several platforms.
+//!
+//! The C preprocessor specializes a translation to the platform it is made for (see `--target`).
+//! Translations of the same project for several platforms are merged into a single tree: items
+//! that are the same on every platform are kept as they are, and each version of an item that
+//! differs is gated with `#[cfg(..)]` on the platforms it was translated for. Inline modules are
+//! merged item by item, and so are the declarations of `extern` blocks. Files missing on some
+//! platforms are copied as they are, since their `mod` declarations are gated.
+//!
+//! The merged files are printed from their syntax trees, so the comments of differing files are
+//! lost.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use c2rust_ast_printer::pprust;
+use failure::{format_err, Error};
+use indexmap::IndexMap;
+use log::warn;
+use proc_macro2::{Delimiter, Group, Punct, Spacing, Span, TokenStream, TokenTree};
+use syn::__private::ToTokens;
+use syn::{AttrStyle, Attribute, ForeignItem, Item, ItemForeignMod, ItemMacro, ItemMod};
+
+use crate::cache::CACHE_DIR;
+
+/// The translation of the project for one platform
+pub struct Platform {
+    /// The `cfg` predicate of the platform, such as `target_os = "linux"`
+    cfg: String,
+    /// The directory of the translation
+    dir: PathBuf,
+}
+
+impl FromStr for Platform {
+    type Err = Error;
+
+    /// Parse `NAME=DIR`, where `NAME` is either a `target_os` such as `linux` or a `cfg`
+    /// predicate such as `all(windows, target_arch = "x86")`
+    fn from_str(arg: &str) -> Result<Self, Error> {
+        let (name, dir) = arg
+            .rsplit_once('=')
+            .ok_or_else(|| format_err!("{} is not of the form NAME=DIR", arg))?;
+        let is_os = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        let cfg = if is_os && !name.is_empty() {
+            format!("target_os = \"{}\"", name)
+        } else {
+            TokenStream::from_str(name)
+                .map_err(|_| format_err!("{} is not a cfg predicate", name))?;
+            name.to_owned()
+        };
+        Ok(Platform {
+            cfg,
+            dir: PathBuf::from(dir),
+        })
+    }
+}
+
+/// Merge the translations for `platforms` into `output_dir`
+pub fn merge(platforms: &[Platform], output_dir: &Path) -> Result<(), Error> {
+    if platforms.len() < 2 {
+        return Err(format_err!("merging needs the translations for at least two platforms"));
+    }
+
+    // The files of the translations, with the platforms they are translated for
+    let mut files: IndexMap<PathBuf, Vec<usize>> = IndexMap::new();
+    for (idx, platform) in platforms.iter().enumerate() {
+        for file in list_files(&platform.dir, Path::new(""))? {
+            files.entry(file).or_default().push(idx);
+        }
+    }
+
+    for (file, idxs) in &files {
+        let mut contents = vec![];
+        for &idx in idxs {
+            let path = platforms[idx].dir.join(file);
+            contents.push(fs::read(&path).map_err(|e| format_err!("{}: {}", path.display(), e))?);
+        }
+        let output = output_dir.join(file);
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        if contents.iter().all(|version| *version == contents[0]) {
+            fs::write(&output, &contents[0])?;
+        } else if file.extension().map_or(false, |ext| ext == "rs") {
+            let mut versions = vec![];
+            for (&idx, version) in idxs.iter().zip(&contents) {
+                let path = platforms[idx].dir.join(file);
+                let parsed = std::str::from_utf8(version)
+                    .map_err(Error::from)
+                    .and_then(|version| syn::parse_file(version).map_err(Error::from))
+                    .map_err(|e| format_err!("{}: {}", path.display(), e))?;
+                versions.push((idx, parsed));
+            }
+            fs::write(&output, merge_file(&versions, platforms))?;
+        } else {
+            warn!(
+                "{} differs between the translations, keeping that of {}",
+                file.display(),
+                platforms[idxs[0]].dir.display()
+            );
+            fs::write(&output, &contents[0])?;
+        }
+    }
+    Ok(())
+}
+
+/// The files under `root.join(dir)`, relative to `root`, leaving out Cargo's `target` directory
+/// and the cache of the translations
+fn list_files(root: &Path, dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let path = root.join(dir);
+    let mut entries = fs::read_dir(&path)
+        .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format_err!("{}: {}", path.display(), e))?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    let mut files = vec![];
+    for entry in entries {
+        let name = dir.join(entry.file_name());
+        if !entry.file_type()?.is_dir() {
+            files.push(name);
+        } else if name != Path::new("target") && name != Path::new(CACHE_DIR) {
+            files.extend(list_files(root, &name)?);
+        }
+    }
+    Ok(files)
+}
+
+/// Merge the versions of a Rust file
+fn merge_file(versions: &[(usize, syn::File)], platforms: &[Platform]) -> String {
+    let attrs = versions
+        .iter()
+        .map(|(idx, file)| (*idx, file.attrs.clone()))
+        .collect::<Vec<_>>();
+    let items = versions
+        .iter()
+        .map(|(idx, file)| (*idx, file.items.clone()))
+        .collect::<Vec<_>>();
+    pprust::to_string(|| syn::File {
+        shebang: None,
+        attrs: merge_inner_attrs(&attrs, platforms),
+        items: merge_items(&items, platforms),
+    })
+}
+
+/// Merge the inner attributes of the versions of a file, making those missing on some platforms
+/// conditional with `cfg_attr`
+fn merge_inner_attrs(
+    versions: &[(usize, Vec<Attribute>)],
+    platforms: &[Platform],
+) -> Vec<Attribute> {
+    let scope = versions.iter().map(|&(idx, _)| idx).collect::<Vec<_>>();
+    let attrs = versions
+        .iter()
+        .flat_map(|(idx, attrs)| attrs.iter().map(move |attr| (*idx, attr.clone())));
+    group_versions(attrs)
+        .into_iter()
+        .map(|(attr, idxs)| {
+            if idxs == scope {
+                return attr;
+            }
+            let mut args = cfg_predicate(&idxs, platforms);
+            args.extend(Some(TokenTree::Punct(Punct::new(',', Spacing::Alone))));
+            args.extend(attr.path.to_token_stream());
+            args.extend(attr.tokens);
+            make_attr(AttrStyle::Inner(Default::default()), "cfg_attr", args)
+        })
+        .collect()
+}
+
+/// Merge the items of the versions of a file or inline module. Items keep the order they first
+/// appear in, and those only on some platforms follow the items they follow there.
+fn merge_items(versions: &[(usize, Vec<Item>)], platforms: &[Platform]) -> Vec<Item> {
+    let scope = versions.iter().map(|&(idx, _)| idx).collect::<Vec<_>>();
+    let mut order: Vec<String> = vec![];
+    let mut items: HashMap<String, Vec<(usize, Item)>> = HashMap::new();
+    for (idx, version) in versions {
+        let mut pos = 0;
+        for item in version.iter().flat_map(split_foreign_mod) {
+            let key = item_key(&item);
+            match order.iter().position(|other| *other == key) {
+                Some(other) => pos = other + 1,
+                None => {
+                    order.insert(pos, key.clone());
+                    pos += 1;
+                }
+            }
+            items.entry(key).or_default().push((*idx, item));
+        }
+    }
+
+    let mut merged = vec![];
+    for key in &order {
+        let versions = &items[key];
+        let groups = group_versions(versions.iter().cloned());
+        if groups.len() > 1 {
+            if let Some(item) = merge_inline_mods(versions, platforms) {
+                let mut idxs = versions.iter().map(|&(idx, _)| idx).collect::<Vec<_>>();
+                idxs.dedup();
+                merged.push(gate(item, &idxs, &scope, platforms));
+                continue;
+            }
+        }
+        for (item, idxs) in groups {
+            merged.push(gate(item, &idxs, &scope, platforms));
+        }
+    }
+    join_foreign_mods(merged)
+}
+
+/// Merge the versions of an inline module item by item, if every version is one
+fn merge_inline_mods(versions: &[(usize, Item)], platforms: &[Platform]) -> Option<Item> {
+    let mut contents = vec![];
+    for (idx, item) in versions {
+        match item {
+            Item::Mod(ItemMod {
+                content: Some((_, items)),
+                ..
+            }) => contents.push((*idx, items.clone())),
+            _ => return None,
+        }
+    }
+    let mut merged = match versions[0].1 {
+        Item::Mod(ref module) => module.clone(),
+        _ => return None,
+    };
+    merged.content.as_mut()?.1 = merge_items(&contents, platforms);
+    Some(Item::Mod(merged))
+}
+
+/// The distinct versions of something, with the platforms each one is translated for
+fn group_versions<T: ToTokens>(
+    versions: impl Iterator<Item = (usize, T)>,
+) -> Vec<(T, Vec<usize>)> {
+    let mut groups: IndexMap<String, (T, Vec<usize>)> = IndexMap::new();
+    for (idx, version) in versions {
+        let key = version.to_token_stream().to_string();
+        let (_, idxs) = groups.entry(key).or_insert_with(|| (version, vec![]));
+        if idxs.last() != Some(&idx) {
+            idxs.push(idx);
+        }
+    }
+    groups.into_iter().map(|(_, group)| group).collect()
+}
+
+/// The name items are merged by. Items of the same namespace and name are versions of each
+/// other, while unnamed items such as `use` declarations are only merged if they are the same.
+fn item_key(item: &Item) -> String {
+    fn tokens<T: ToTokens>(tokens: &T) -> String {
+        tokens.to_token_stream().to_string()
+    }
+
+    let (namespace, name) = match item {
+        Item::Fn(f) => ("value", f.sig.ident.to_string()),
+        Item::Const(c) => ("value", c.ident.to_string()),
+        Item::Static(s) => ("value", s.ident.to_string()),
+        Item::Struct(s) => ("type", s.ident.to_string()),
+        Item::Enum(e) => ("type", e.ident.to_string()),
+        Item::Union(u) => ("type", u.ident.to_string()),
+        Item::Type(t) => ("type", t.ident.to_string()),
+        Item::Trait(t) => ("type", t.ident.to_string()),
+        Item::Mod(m) => ("type", m.ident.to_string()),
+        Item::Macro(ItemMacro {
+            ident: Some(ident), ..
+        }) => ("macro", ident.to_string()),
+        Item::Impl(i) => {
+            let trait_ = i.trait_.as_ref().map(|(_, path, _)| tokens(path));
+            let name = format!("{} for {}", trait_.unwrap_or_default(), tokens(&i.self_ty));
+            ("impl", name)
+        }
+        // Split into one declaration each by `split_foreign_mod`
+        Item::ForeignMod(fm) => {
+            let names = fm.items.iter().map(|item| match item {
+                ForeignItem::Fn(f) => f.sig.ident.to_string(),
+                ForeignItem::Static(s) => s.ident.to_string(),
+                ForeignItem::Type(t) => t.ident.to_string(),
+                _ => tokens(item),
+            });
+            let name = format!("{} {}", tokens(&fm.abi), names.collect::<Vec<_>>().join(" "));
+            ("extern", name)
+        }
+        _ => ("", tokens(item)),
+    };
+    format!("{} {}", namespace, name)
+}
+
+/// Split an `extern` block into one per declaration, so that the declarations are merged one by
+/// one
+fn split_foreign_mod(item: &Item) -> Vec<Item> {
+    match item {
+        Item::ForeignMod(fm) => fm
+            .items
+            .iter()
+            .map(|foreign_item| {
+                Item::ForeignMod(ItemForeignMod {
+                    items: vec![foreign_item.clone()],
+                    ..fm.clone()
+                })
+            })
+            .collect(),
+        _ => vec![item.clone()],
+    }
+}
+
+/// Join adjacent `extern` blocks with the same ABI and attributes, including the `cfg`s of their
+/// declarations
+fn join_foreign_mods(items: Vec<Item>) -> Vec<Item> {
+    let mut res: Vec<Item> = vec![];
+    for item in items {
+        if let (Some(Item::ForeignMod(last)), Item::ForeignMod(fm)) = (res.last_mut(), &item) {
+            if last.abi == fm.abi && last.attrs == fm.attrs {
+                last.items.extend(fm.items.iter().cloned());
+                continue;
+            }
+        }
+        res.push(item);
+    }
+    res
+}
+
+/// Make `item` conditional on the platforms `idxs`, unless it is on every platform of `scope`
+fn gate(mut item: Item, idxs: &[usize], scope: &[usize], platforms: &[Platform]) -> Item {
+    if idxs == scope {
+        return item;
+    }
+    let cfg = make_attr(AttrStyle::Outer, "cfg", cfg_predicate(idxs, platforms));
+    let attrs = match item {
+        Item::Const(ref mut i) => &mut i.attrs,
+        Item::Enum(ref mut i) => &mut i.attrs,
+        Item::ExternCrate(ref mut i) => &mut i.attrs,
+        Item::Fn(ref mut i) => &mut i.attrs,
+        Item::ForeignMod(ref mut i) => &mut i.attrs,
+        Item::Impl(ref mut i) => &mut i.attrs,
+        Item::Macro(ref mut i) => &mut i.attrs,
+        Item::Macro2(ref mut i) => &mut i.attrs,
+        Item::Mod(ref mut i) => &mut i.attrs,
+        Item::Static(ref mut i) => &mut i.attrs,
+        Item::Struct(ref mut i) => &mut i.attrs,
+        Item::Trait(ref mut i) => &mut i.attrs,
+        Item::TraitAlias(ref mut i) => &mut i.attrs,
+        Item::Type(ref mut i) => &mut i.attrs,
+        Item::Union(ref mut i) => &mut i.attrs,
+        Item::Use(ref mut i) => &mut i.attrs,
+        _ => {
+            warn!("Unable to make {} conditional", item.to_token_stream());
+            return item;
+        }
+    };
+    attrs.insert(0, cfg);
+    item
+}
+
+/// The `cfg` predicate of the platforms `idxs`
+fn cfg_predicate(idxs: &[usize], platforms: &[Platform]) -> TokenStream {
+    let cfg = match idxs {
+        [idx] => platforms[*idx].cfg.clone(),
+        _ => {
+            let cfgs = idxs.iter().map(|&idx| platforms[idx].cfg.as_str());
+            format!("any({})", cfgs.collect::<Vec<_>>().join(", "))
+        }
+    };
+    cfg.parse().expect("cfg predicates are checked when parsing platforms")
+}
+
+/// The attribute `#[name(args)]`
+fn make_attr(style: AttrStyle, name: &str, args: TokenStream) -> Attribute {
+    Attribute {
+        pound_token: Default::default(),
+        style,
+        bracket_token: Default::default(),
+        path: syn::Ident::new(name, Span::call_site()).into(),
+        tokens: TokenTree::Group(Group::new(Delimiter::Parenthesis, args)).into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_versions() {
+        let platforms = ["linux=a", "macos=b", "all(windows, target_arch = \"x86\")=c"]
+            .iter()
+            .map(|arg| arg.parse().unwrap())
+            .collect::<Vec<Platform>>();
+        let linux = r#"
+            #![feature(c_variadic)]
+            pub type off_t = i64;
+            extern "C" {
+                fn open(path: *const i8, flags: i32, ...) -> i32;
+                fn lseek(fd: i32, offset: off_t, whence: i32) -> off_t;
+            }
+            pub fn page_size() -> usize { 4096 }
+        "#;
+        let macos = r#"
+            #![feature(c_variadic)]
+            pub type off_t = i64;
+            extern "C" {
+                fn open(path: *const i8, flags: i32, ...) -> i32;
+                fn lseek(fd: i32, offset: off_t, whence: i32) -> off_t;
+            }
+            pub fn page_size() -> usize { 16384 }
+        "#;
+        let windows = r#"
+            pub type off_t = i32;
+            extern "C" {
+                fn _open(path: *const i8, flags: i32, ...) -> i32;
+                fn lseek(fd: i32, offset: off_t, whence: i32) -> off_t;
+            }
+            pub fn page_size() -> usize { 4096 }
+        "#;
+        let versions = [linux, macos, windows]
+            .iter()
+            .enumerate()
+            .map(|(idx, src)| (idx, syn::parse_file(src).unwrap()))
+            .collect::<Vec<_>>();
+        let merged = merge_file(&versions, &platforms);
+        let expected = syn::parse_file(
+            r#"
+            #![cfg_attr(any(target_os = "linux", target_os = "macos"), feature(c_variadic))]
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            pub type off_t = i64;
+            #[cfg(all(windows, target_arch = "x86"))]
+            pub type off_t = i32;
+            #[cfg(all(windows, target_arch = "x86"))]
+            extern "C" {
+                fn _open(path: *const i8, flags: i32, ...) -> i32;
+            }
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            extern "C" {
+                fn open(path: *const i8, flags: i32, ...) -> i32;
+            }
+            extern "C" {
+                fn lseek(fd: i32, offset: off_t, whence: i32) -> off_t;
+            }
+            #[cfg(any(target_os = "linux", all(windows, target_arch = "x86")))]
+            pub fn page_size() -> usize { 4096 }
+            #[cfg(target_os = "macos")]
+            pub fn page_size() -> usize { 16384 }
+        "#,
+        )
+        .unwrap();
+        assert_eq!(merged, pprust::to_string(|| expected));
+    }
+}
@@ -0,0 +1,367 @@
+//! The registry of translation options.
+//!
+//! Every command-line option that shapes a translation is listed in `OPTIONS` with its
+//! stability and the options it implies, which `TranspilerConfig::apply_implications` turns on.
+//! Options that have been part of the transpiler for a long time are stable; the idiomization
+//! passes and other recent additions are experimental, and the shape of their output may still
+//! change between releases. Combinations that can't produce a working translation are listed in
+//! `CONFLICTS`, and `TranspilerConfig::validate` rejects them before anything is translated.
+
+use std::fmt::Write;
+
+use strum_macros::Display;
+
+use crate::{BitfieldOrder, EnumStyle, TranspilerConfig};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Display)]
+#[strum(serialize_all = "kebab_case")]
+pub enum Stability {
+    Stable,
+    Experimental,
+}
+
+/// A translation option, named after its command-line flag
+pub struct OptionInfo {
+    pub name: &'static str,
+    pub stability: Stability,
+    /// Options turned on by this one
+    pub implies: &'static [&'static str],
+    /// The condition under which the implied options are turned on, and its description, if
+    /// not whenever this option is set
+    pub implies_when: Option<(fn(&TranspilerConfig) -> bool, &'static str)>,
+    /// Is the option given, or set to something other than its default?
+    pub is_set: fn(&TranspilerConfig) -> bool,
+    /// Turn the option on, for the options implied by others
+    pub set: Option<fn(&mut TranspilerConfig)>,
+}
+
+/// Two options that can't be used together
+pub struct Conflict {
+    pub options: [&'static str; 2],
+    pub reason: &'static str,
+}
+
+macro_rules! optional {
+    () => {
+        None
+    };
+    ($($value:tt)+) => {
+        Some($($value)+)
+    };
+}
+
+macro_rules! options {
+    ($($name:literal: $stability:ident
+        $([implies $($implied:literal),* $(when $when:ident $description:literal)?])?,
+        |$c:ident| $is_set:expr $(, set |$s:ident| $set:expr)?;)*) => {
+        pub static OPTIONS: &[OptionInfo] = &[$(
+            OptionInfo {
+                name: $name,
+                stability: Stability::$stability,
+                implies: &[$($($implied),*)?],
+                implies_when: optional!($($((
+                    |config: &TranspilerConfig| config.$when(),
+                    $description,
+                ))?)?),
+                is_set: |$c: &TranspilerConfig| $is_set,
+                set: optional!($(|$s: &mut TranspilerConfig| $set)?),
+            },
+        )*];
+    };
+}
+
+options! {
+    "prefix-function-names": Stable, |c| c.prefix_function_names.is_some();
+    "dump-untyped-clang-ast": Stable, |c| c.dump_untyped_context;
+    "dump-typed-clang-ast": Stable, |c| c.dump_typed_context;
+    "pretty-typed-clang-ast": Stable, |c| c.pretty_typed_context;
+    "dump-c-ast": Experimental, |c| c.dump_c_ast.is_some();
+    "dump-c-ast-function": Experimental, |c| c.dump_c_ast_function.is_some();
+    "debug-ast-exporter": Stable, |c| c.debug_ast_exporter;
+    "verbose": Stable, |c| c.verbose;
+    "translate-const-macros": Stable, |c| c.translate_const_macros,
+        set |c| c.translate_const_macros = true;
+    "translate-fn-macros": Stable, |c| c.translate_fn_macros;
+    "enum-style": Experimental, |c| c.enum_style == EnumStyle::Rust;
+    "ascii-char-classes": Experimental, |c| c.ascii_char_classes;
+    "translate-fmt": Experimental, |c| c.translate_fmt;
+    "ndebug-asserts": Experimental, |c| c.ndebug_asserts;
+    "variadic-shims": Experimental, |c| c.variadic_shims;
+    "tagged-union": Experimental, |c| !c.tagged_unions.is_empty();
+    "fn-pointer-unions": Experimental, |c| c.fn_pointer_unions;
+    "emit-unsafe-reasons": Experimental, |c| c.emit_unsafe_reasons;
+    "bindings": Experimental [implies "translate-const-macros"], |c| c.bindings;
+    "report": Experimental, |c| c.report.is_some();
+    "dry-run": Experimental, |c| c.dry_run.is_some();
+    "debug-pointers": Experimental, |c| c.debug_pointers;
+    "large-table-threshold": Experimental, |c| c.large_table_threshold != 256;
+    "extract-large-tables": Experimental, |c| c.extract_large_tables;
+    "outline-threshold": Experimental, |c| c.outline_threshold.is_some();
+    "static-cstr-returns": Experimental, |c| c.static_cstr_returns;
+    "box-local-allocs": Experimental, |c| c.box_local_allocs;
+    "slice-fns": Experimental, |c| c.slice_fns;
+    "range-loops": Experimental, |c| c.range_loops;
+    "error-enums": Experimental, |c| c.error_enums;
+    "fixed-width-ints": Experimental, |c| c.fixed_width_ints, set |c| c.fixed_width_ints = true;
+    "emit-safe-wrappers": Experimental, |c| c.emit_safe_wrappers;
+    "layout-asserts": Experimental, |c| c.layout_asserts;
+    "owned-alloca-strings": Experimental, |c| c.owned_alloca_strings;
+    "derive": Experimental, |c| !c.derives.is_empty();
+    "struct-derive": Experimental, |c| !c.struct_derives.is_empty();
+    "frame-address-intrinsics": Experimental, |c| c.frame_address_intrinsics;
+    "openmp-rayon": Experimental, |c| c.openmp_rayon;
+    "candidate-function": Experimental, |c| !c.candidate_functions.is_empty();
+    "restrict-attrs": Experimental, |c| c.restrict_attrs;
+    "bitfield-order": Experimental, |c| c.bitfield_order != BitfieldOrder::Target;
+    "bitfield-tests": Experimental, |c| c.bitfield_tests;
+    "abi-tests": Experimental, |c| c.abi_tests;
+    "emit-test-stubs": Experimental, |c| c.emit_test_stubs;
+    "ffi-tests": Experimental [implies "emit-build-files"], |c| c.ffi_tests;
+    "hybrid-build": Experimental [implies "emit-build-files"], |c| c.hybrid_build;
+    "c-unwind": Experimental, |c| c.c_unwind;
+    "targeted-allows": Experimental [implies "emit-build-files"], |c| c.targeted_allows;
+    "no-incremental-relooper": Stable, |c| !c.incremental_relooper;
+    "no-simplify-structures": Stable, |c| !c.simplify_structures;
+    "ignore-c-loop-info": Stable, |c| !c.use_c_loop_info;
+    "ignore-c-multiple-info": Stable, |c| !c.use_c_multiple_info;
+    "dump-function-cfgs": Stable, |c| c.dump_function_cfgs;
+    "json-function-cfgs": Stable, |c| c.json_function_cfgs;
+    "dump-cfgs-liveness": Stable, |c| c.dump_cfg_liveness;
+    "dump-structures": Stable, |c| c.dump_structures;
+    "debug-labels": Stable, |c| c.debug_relooper_labels;
+    "invalid-code": Stable, |c| c.panic_on_translator_failure;
+    "emit-modules": Stable, |c| c.emit_modules, set |c| c.emit_modules = true;
+    "emit-build-files": Stable [implies "emit-modules"], |c| c.emit_build_files,
+        set |c| c.emit_build_files = true;
+    "output-dir": Stable, |c| c.output_dir.is_some();
+    "filter": Stable, |c| c.filter.is_some();
+    "filter-fn": Experimental, |c| !c.filter_fns.is_empty();
+    "filter-file": Experimental, |c| !c.filter_files.is_empty();
+    "fail-on-error": Stable, |c| c.fail_on_error;
+    "binary": Stable [implies "emit-build-files"], |c| !c.binaries.is_empty();
+    "overwrite-existing": Stable, |c| c.overwrite_existing;
+    "force": Experimental, |c| c.force;
+    "reduce-type-annotations": Stable, |c| c.reduce_type_annotations;
+    "reorganize-definitions": Stable, |c| c.reorganize_definitions,
+        set |c| c.reorganize_definitions = true;
+    "header-modules": Experimental [implies "reorganize-definitions"], |c| c.header_modules;
+    "share-inline-fns": Experimental, |c| c.share_inline_fns;
+    "emit-src-locs": Experimental, |c| c.emit_src_locs;
+    "migration-status": Experimental, |c| c.migration_status;
+    "fmt": Experimental, |c| c.fmt;
+    "warn": Stable, |c| !c.enabled_warnings.is_empty();
+    "allow": Experimental, |c| !c.allowed_warnings.is_empty();
+    "deny": Experimental, |c| !c.denied_warnings.is_empty();
+    "json-diagnostics": Experimental, |c| c.json_diagnostics;
+    "emit-no-std": Stable, |c| c.emit_no_std;
+    "disable-refactoring": Stable, |c| c.disable_refactoring;
+    "preserve-unused-functions": Stable, |c| c.preserve_unused_functions;
+    "prune-unused": Experimental, |c| c.prune_unused;
+    "build-system": Experimental, |c| c.build_options.build_system.is_some();
+    "build-dir": Experimental, |c| c.build_options.build_dir.is_some();
+    "build-arg": Experimental, |c| !c.build_options.args.is_empty();
+    // wasm32-unknown-unknown has no libc, so its types are the Rust ones
+    "target": Experimental
+        [implies "fixed-width-ints" when is_wasm "targeting wasm32-unknown-unknown"],
+        |c| c.target.is_some();
+    "crate-name": Experimental, |c| c.crate_name.is_some();
+    "no-translate-asm": Experimental, |c| !c.translate_asm;
+    "log-level": Stable, |c| c.log_level != log::LevelFilter::Warn;
+}
+
+pub static CONFLICTS: &[Conflict] = &[
+    Conflict {
+        options: ["emit-no-std", "translate-fmt"],
+        reason: "formatted output is printed with `print!`, which needs the standard library",
+    },
+    Conflict {
+        options: ["emit-no-std", "owned-alloca-strings"],
+        reason: "`strdupa` copies are made with `CString`, which needs the standard library",
+    },
+    Conflict {
+        options: ["emit-no-std", "openmp-rayon"],
+        reason: "Rayon needs the standard library",
+    },
+    Conflict {
+        options: ["share-inline-fns", "header-modules"],
+        reason: "header modules already translate each inline function once",
+    },
+    Conflict {
+        options: ["prune-unused", "preserve-unused-functions"],
+        reason: "unused static functions can't be both kept and pruned",
+    },
+];
+
+/// Look up an option by the name of its flag
+pub fn option_info(name: &str) -> Option<&'static OptionInfo> {
+    OPTIONS.iter().find(|option| option.name == name)
+}
+
+/// Describe the option `name`, starting with its command-line help if given, or return `None`
+/// if there is no such option.
+pub fn explain_option(name: &str, help: Option<&str>) -> Option<String> {
+    let option = option_info(name)?;
+
+    let mut text = format!("--{} ({})\n", option.name, option.stability);
+    if let Some(help) = help {
+        writeln!(text, "    {}", help).unwrap();
+    }
+    if !option.implies.is_empty() {
+        let implied = option.implies.iter().map(|name| format!("--{}", name));
+        write!(text, "Implies: {}", implied.collect::<Vec<_>>().join(", ")).unwrap();
+        if let Some((_, description)) = option.implies_when {
+            write!(text, " when {}", description).unwrap();
+        }
+        writeln!(text).unwrap();
+    }
+    for conflict in CONFLICTS {
+        let other = match conflict.options {
+            [a, b] if a == name => b,
+            [a, b] if b == name => a,
+            _ => continue,
+        };
+        writeln!(text, "Conflicts with --{}: {}", other, conflict.reason).unwrap();
+    }
+    Some(text)
+}
+
+impl TranspilerConfig {
+    /// Turn on the options implied by the ones that are set, as listed in `OPTIONS`.
+    pub fn apply_implications(&mut self) {
+        // Implied options may imply others in turn, so repeat until nothing changes
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for option in OPTIONS {
+                let applies = match option.implies_when {
+                    Some((when, _)) => when(self),
+                    None => (option.is_set)(self),
+                };
+                if !applies {
+                    continue;
+                }
+                for implied in option.implies.iter().filter_map(|name| option_info(name)) {
+                    if !(implied.is_set)(self) {
+                        let set = implied.set.expect("implied options can be set");
+                        set(self);
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Check that no conflicting options are set, returning a message for each conflict.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let is_set = |name| option_info(name).map_or(false, |option| (option.is_set)(self));
+        let errors = CONFLICTS
+            .iter()
+            .filter(|conflict| conflict.options.iter().all(|&name| is_set(name)))
+            .map(|conflict| {
+                let [a, b] = conflict.options;
+                format!("--{} conflicts with --{}: {}", a, b, conflict.reason)
+            })
+            .collect::<Vec<_>>();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// The experimental options that are set
+    pub fn experimental_options(&self) -> Vec<&'static str> {
+        OPTIONS
+            .iter()
+            .filter(|option| option.stability == Stability::Experimental && (option.is_set)(self))
+            .map(|option| option.name)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn registry_is_consistent() {
+        let mut names = HashSet::new();
+        for option in OPTIONS {
+            assert!(names.insert(option.name), "--{} registered twice", option.name);
+        }
+        for option in OPTIONS {
+            for implied in option.implies {
+                assert!(names.contains(implied), "--{} implies unknown --{}", option.name, implied);
+                let implied = option_info(implied).unwrap();
+                let set = implied.set.unwrap_or_else(|| panic!("--{} can't be set", implied.name));
+                let mut tcfg = TranspilerConfig::default();
+                set(&mut tcfg);
+                assert!((implied.is_set)(&tcfg), "setting --{} has no effect", implied.name);
+            }
+        }
+        for conflict in CONFLICTS {
+            for name in &conflict.options {
+                assert!(names.contains(name), "conflict with unknown --{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn every_flag_is_registered() {
+        let yaml = include_str!("../../c2rust/src/transpile.yaml");
+        for line in yaml.lines() {
+            if let Some(name) = line.trim().strip_prefix("long: ") {
+                // Not options of the translation, but of the command
+                if name == "explain-option" || name == "config" {
+                    continue;
+                }
+                assert!(option_info(name).is_some(), "--{} is not registered", name);
+            }
+        }
+    }
+
+    #[test]
+    fn explain() {
+        let text = explain_option("emit-no-std", Some("Emit code using core rather than std"))
+            .unwrap();
+        assert!(text.starts_with("--emit-no-std (stable)\n    Emit code using core"));
+        assert!(text.contains("Conflicts with --openmp-rayon: "));
+        let text = explain_option("binary", None).unwrap();
+        assert!(text.contains("Implies: --emit-build-files\n"));
+        let text = explain_option("target", None).unwrap();
+        assert!(text.contains("Implies: --fixed-width-ints when targeting wasm32-unknown-unknown"));
+        assert!(explain_option("no-such-option", None).is_none());
+    }
+
+    #[test]
+    fn wasm_implies_fixed_width_ints() {
+        let mut tcfg = TranspilerConfig {
+            target: Some("wasm32-unknown-unknown".to_owned()),
+            ..TranspilerConfig::default()
+        };
+        tcfg.apply_implications();
+        assert!(tcfg.fixed_width_ints);
+
+        let mut tcfg = TranspilerConfig {
+            target: Some("x86_64-unknown-linux-gnu".to_owned()),
+            ..TranspilerConfig::default()
+        };
+        tcfg.apply_implications();
+        assert!(!tcfg.fixed_width_ints);
+    }
+
+    #[test]
+    fn implications_are_transitive() {
+        let mut tcfg = TranspilerConfig {
+            targeted_allows: true,
+            header_modules: true,
+            ..TranspilerConfig::default()
+        };
+        tcfg.apply_implications();
+        assert!(tcfg.emit_build_files);
+        assert!(tcfg.emit_modules);
+        assert!(tcfg.reorganize_definitions);
+        assert!(!tcfg.translate_const_macros);
+    }
+}
@@ -0,0 +1,266 @@
+//! Translation coverage report (`--report`).
+//!
+//! The report estimates the effort of migrating a project before committing to it. For each
+//! translated file, it counts the C constructs that were encountered, lists the functions that
+//! were translated, stubbed after failing to translate or declared extern, and counts the
+//! operations needing `unsafe` and the TODOs left in the translation:
+//!
+//! ```json
+//! {
+//!   "totals": {"files": 2, "skipped": 0, "translated": 41, "stubbed": 1, ...},
+//!   "files": [{"file": "src/inflate.c", "constructs": {"stmt:Goto": 3, ...}, ...}],
+//!   "skipped": []
+//! }
+//! ```
+//!
+//! The report of each file is written next to its module, so that it is reused along with a
+//! cached translation, and the reports of all files are gathered into the project report.
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::c_ast::iterators::{DFNodes, SomeId};
+use crate::c_ast::*;
+use crate::dry_run::DryRun;
+use crate::write_if_changed;
+
+/// The extension of the report of a module
+pub const REPORT_EXTENSION: &str = "report.json";
+
+/// What became of a function
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeclStatus {
+    Translated,
+    /// Replaced with a stub after failing to translate
+    Stubbed,
+    /// Left out after failing to translate
+    Failed,
+    /// Declared extern instead of translated, as with `--filter-fn`
+    Extern,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeclReport {
+    pub name: String,
+    pub status: DeclStatus,
+    /// The first line of the error, if it failed to translate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FileReport {
+    pub file: String,
+    /// The number of occurrences of each kind of declaration, statement and expression
+    pub constructs: BTreeMap<String, usize>,
+    pub declarations: Vec<DeclReport>,
+    /// The number of operations needing `unsafe`, as with `--emit-unsafe-reasons`
+    pub unsafe_operations: usize,
+    /// The number of lines of the translation
+    pub lines: usize,
+    /// The number of lines of the translation with TODOs, FIXMEs or `unimplemented!()`
+    pub todos: usize,
+    /// TODOs per thousand lines
+    pub todo_density: f64,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Totals {
+    pub files: usize,
+    pub skipped: usize,
+    pub constructs: BTreeMap<String, usize>,
+    pub translated: usize,
+    pub stubbed: usize,
+    pub failed: usize,
+    #[serde(rename = "extern")]
+    pub extern_: usize,
+    pub unsafe_operations: usize,
+    pub lines: usize,
+    pub todos: usize,
+    pub todo_density: f64,
+}
+
+/// The report of a whole project
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub totals: Totals,
+    pub files: Vec<FileReport>,
+    /// The files that were not translated
+    pub skipped: Vec<String>,
+}
+
+impl FileReport {
+    /// The report of the translation of `file` into `translation`, with the functions of
+    /// `declarations` and the number of operations needing `unsafe` emitted for it
+    pub fn new(
+        ast_context: &TypedAstContext,
+        file: &Path,
+        declarations: Vec<DeclReport>,
+        unsafe_operations: usize,
+        translation: &str,
+    ) -> Self {
+        let lines = translation.lines().count();
+        let todos = translation
+            .lines()
+            .filter(|line| {
+                line.contains("TODO") || line.contains("FIXME") || line.contains("unimplemented!")
+            })
+            .count();
+        FileReport {
+            file: file.display().to_string(),
+            constructs: count_constructs(ast_context),
+            declarations,
+            unsafe_operations,
+            lines,
+            todos,
+            todo_density: density(todos, lines),
+        }
+    }
+}
+
+impl Report {
+    /// Add the translation of `input` into `module`, or `None` if it was not translated
+    pub fn add(&mut self, input: &Path, module: Option<&Path>) {
+        let module = match module {
+            Some(module) => module,
+            None => {
+                self.skipped.push(input.display().to_string());
+                return;
+            }
+        };
+        let path = module.with_extension(REPORT_EXTENSION);
+        let file = fs::read(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_slice::<FileReport>(&contents).ok());
+        match file {
+            Some(file) => self.files.push(file),
+            None => log::warn!("Unable to read the report of {}", input.display()),
+        }
+    }
+
+    /// Sum up the reports of the files and write the report to `path`
+    pub fn write(mut self, dry_run: Option<DryRun>, path: &Path) -> io::Result<()> {
+        let mut totals = Totals {
+            files: self.files.len(),
+            skipped: self.skipped.len(),
+            ..Totals::default()
+        };
+        for file in &self.files {
+            for (construct, count) in &file.constructs {
+                *totals.constructs.entry(construct.clone()).or_default() += count;
+            }
+            for decl in &file.declarations {
+                *match decl.status {
+                    DeclStatus::Translated => &mut totals.translated,
+                    DeclStatus::Stubbed => &mut totals.stubbed,
+                    DeclStatus::Failed => &mut totals.failed,
+                    DeclStatus::Extern => &mut totals.extern_,
+                } += 1;
+            }
+            totals.unsafe_operations += file.unsafe_operations;
+            totals.lines += file.lines;
+            totals.todos += file.todos;
+        }
+        totals.todo_density = density(totals.todos, totals.lines);
+        self.totals = totals;
+
+        let json = serde_json::to_string_pretty(&self)?;
+        write_if_changed(dry_run, path, &(json + "\n"))?;
+        Ok(())
+    }
+}
+
+/// Count the kinds of the declarations in `ast_context` and of the statements and expressions
+/// in the bodies of its functions
+fn count_constructs(ast_context: &TypedAstContext) -> BTreeMap<String, usize> {
+    let mut constructs = BTreeMap::new();
+    let mut count = |category: &str, kind: &dyn Debug| {
+        *constructs.entry(format!("{}:{}", category, variant_name(kind))).or_default() += 1;
+    };
+    for &decl_id in &ast_context.c_decls_top {
+        let kind = &ast_context[decl_id].kind;
+        count("decl", kind);
+        let body = match *kind {
+            CDeclKind::Function {
+                body: Some(body), ..
+            } => body,
+            _ => continue,
+        };
+        for id in DFNodes::new(ast_context, SomeId::Stmt(body)) {
+            match id {
+                SomeId::Stmt(stmt_id) => count("stmt", &ast_context[stmt_id].kind),
+                SomeId::Expr(expr_id) => count("expr", &ast_context[expr_id].kind),
+                SomeId::Decl(decl_id) => count("decl", &ast_context[decl_id].kind),
+                SomeId::Type(_) => {}
+            }
+        }
+    }
+    constructs
+}
+
+/// The name of the enum variant `kind`, such as `Goto` for `CStmtKind::Goto(..)`
+fn variant_name(kind: &dyn Debug) -> String {
+    let debug = format!("{:?}", kind);
+    let end = debug
+        .find(|c: char| !c.is_alphanumeric() && c != '_')
+        .unwrap_or(debug.len());
+    debug[..end].to_owned()
+}
+
+/// The number of TODOs per thousand lines
+fn density(todos: usize, lines: usize) -> f64 {
+    if lines == 0 {
+        return 0.0;
+    }
+    todos as f64 * 1000.0 / lines as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn totals() {
+        let decl = |name: &str, status| DeclReport {
+            name: name.to_owned(),
+            status,
+            error: None,
+        };
+        let mut report = Report::default();
+        report.files.push(FileReport {
+            file: "inflate.c".to_owned(),
+            constructs: vec![("stmt:Goto".to_owned(), 3)].into_iter().collect(),
+            declarations: vec![
+                decl("inflate", DeclStatus::Translated),
+                decl("inflate_fast", DeclStatus::Stubbed),
+            ],
+            unsafe_operations: 12,
+            lines: 400,
+            todos: 2,
+            ..FileReport::default()
+        });
+        report.skipped.push("broken.c".to_owned());
+
+        let path = std::env::temp_dir().join(format!("c2rust-report-{}.json", std::process::id()));
+        report.write(None, &path).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let totals = &json["totals"];
+        assert_eq!(totals["files"], 1);
+        assert_eq!(totals["skipped"], 1);
+        assert_eq!(totals["constructs"]["stmt:Goto"], 3);
+        assert_eq!(totals["translated"], 1);
+        assert_eq!(totals["stubbed"], 1);
+        assert_eq!(totals["extern"], 0);
+        assert_eq!(totals["unsafe_operations"], 12);
+        assert_eq!(totals["todo_density"], 5.0);
+        assert_eq!(variant_name(&CStmtKind::Break), "Break");
+    }
+}
@@ -127,25 +127,24 @@ impl CommentStore {
         pos: Option<BytePos>,
         //style: comments::CommentStyle,
     ) -> Option<BytePos> {
-        fn translate_comment(comment: &String) -> String {
-            comment
-                .lines()
-                .map(|line: &str| {
-                    let mut line = line.to_owned();
-                    let begin = line.trim_start();
-                    if begin.starts_with("//!") || begin.starts_with("///") {
-                        let begin_loc = line.len() - begin.len();
-                        line.insert(2 + begin_loc, ' ');
-                    };
-                    line
-                })
-                .join("\n")
-                .replace("/**", "/* *")
-                .replace("/*!", "/* !")
-        }
+        let lines = lines.iter().map(|comment| escape_doc_comment(comment));
+        self.insert_lines(lines.collect(), pos)
+    }
 
-        let lines: Vec<String> = lines.into_iter().map(translate_comment).collect();
+    /// Like `extend_existing_comments`, but for the comments before an item, field or variant,
+    /// which turns Doxygen comments into rustdoc comments.
+    pub fn extend_existing_doc_comments(
+        &mut self,
+        lines: &[String],
+        pos: Option<BytePos>,
+    ) -> Option<BytePos> {
+        let lines = lines
+            .iter()
+            .map(|comment| rustdoc_comment(comment).unwrap_or_else(|| escape_doc_comment(comment)));
+        self.insert_lines(lines.collect(), pos)
+    }
 
+    fn insert_lines(&mut self, lines: Vec<String>, pos: Option<BytePos>) -> Option<BytePos> {
         if lines.is_empty() {
             None
         } else {
@@ -209,6 +208,134 @@ impl CommentStore {
     }
 }
 
+/// Keep a C comment that looks like a Rust doc comment from becoming one.
+fn escape_doc_comment(comment: &str) -> String {
+    comment
+        .lines()
+        .map(|line: &str| {
+            let mut line = line.to_owned();
+            let begin = line.trim_start();
+            if begin.starts_with("//!") || begin.starts_with("///") {
+                let begin_loc = line.len() - begin.len();
+                line.insert(2 + begin_loc, ' ');
+            };
+            line
+        })
+        .join("\n")
+        .replace("/**", "/* *")
+        .replace("/*!", "/* !")
+}
+
+/// Doxygen commands of comments that don't document the following declaration
+const FILE_LEVEL_COMMANDS: &[&str] = &[
+    "file",
+    "mainpage",
+    "page",
+    "defgroup",
+    "addtogroup",
+    "ingroup",
+    "{",
+    "}",
+];
+
+/// Convert a Doxygen comment (`/** ... */`, `/*! ... */`, `/// ...` or `//! ...`) into `///`
+/// rustdoc lines, turning its commands into Markdown. Returns `None` for other comments, for
+/// file-level blocks, and for `/**<` and `///<` comments, which document the preceding member.
+fn rustdoc_comment(comment: &str) -> Option<String> {
+    let text: Vec<String> =
+        if let Some(block) = comment.strip_prefix("/**").or(comment.strip_prefix("/*!")) {
+            // Skip `/***` banners, empty `/**/` comments and member comments
+            if block.starts_with(&['*', '/', '<'][..]) {
+                return None;
+            }
+            let block = block.strip_suffix("*/")?;
+            block
+                .lines()
+                .enumerate()
+                .map(|(i, line)| {
+                    // Strip the ` * ` decoration of continuation lines
+                    let line = if i == 0 {
+                        line
+                    } else {
+                        let trimmed = line.trim_start();
+                        trimmed.strip_prefix('*').unwrap_or(trimmed)
+                    };
+                    line.strip_prefix(' ').unwrap_or(line).trim_end().to_owned()
+                })
+                .collect()
+        } else {
+            comment
+                .lines()
+                .map(|line| {
+                    let line = line.trim_start();
+                    let rest = line
+                        .strip_prefix("///")
+                        .or(line.strip_prefix("//!"))
+                        .filter(|rest| !rest.starts_with(&['/', '<'][..]))?;
+                    Some(rest.strip_prefix(' ').unwrap_or(rest).trim_end().to_owned())
+                })
+                .collect::<Option<_>>()?
+        };
+
+    let mut lines = vec![];
+    for line in &text {
+        let (command, rest) = match line.strip_prefix(&['@', '\\'][..]) {
+            Some(command) => command.split_at(
+                command
+                    .find(|c: char| c.is_whitespace() || c == '[')
+                    .unwrap_or(command.len()),
+            ),
+            None => {
+                lines.push(line.clone());
+                continue;
+            }
+        };
+        // Drop the `[in]`/`[out]` direction of parameters
+        let rest = match rest.strip_prefix('[') {
+            Some(rest) => rest.split_once(']').map_or(rest, |(_, rest)| rest),
+            None => rest,
+        }
+        .trim_start();
+        match command {
+            _ if FILE_LEVEL_COMMANDS.contains(&command) => return None,
+            "brief" | "short" | "details" => lines.push(rest.to_owned()),
+            "param" | "tparam" => {
+                let (name, desc) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                lines.push(format!("- `{}`: {}", name, desc.trim_start()));
+            }
+            "return" | "returns" | "result" | "retval" => lines.push(format!("Returns: {}", rest)),
+            "note" | "warning" | "see" | "sa" | "deprecated" => {
+                let label = match command {
+                    "note" => "Note",
+                    "warning" => "Warning",
+                    "deprecated" => "Deprecated",
+                    _ => "See also",
+                };
+                lines.push(format!("{}: {}", label, rest));
+            }
+            "code" => lines.push("```c".to_owned()),
+            "endcode" => lines.push("```".to_owned()),
+            _ => lines.push(line.clone()),
+        }
+    }
+
+    // Trim blank lines around the text
+    let start = lines.iter().position(|line| !line.trim().is_empty())?;
+    let end = lines.iter().rposition(|line| !line.trim().is_empty())? + 1;
+    Some(
+        lines[start..end]
+            .iter()
+            .map(|line| {
+                if line.is_empty() {
+                    "///".to_owned()
+                } else {
+                    format!("/// {}", line)
+                }
+            })
+            .join("\n"),
+    )
+}
+
 pub struct CommentTraverser {
     old_comments: BTreeMap<BytePos, SmallVec<[comments::Comment; 1]>>,
     old_to_new_pos: BTreeMap<BytePos, BytePos>,
@@ -0,0 +1,117 @@
+//! This module implements `--abi-tests`, which writes a test module next to each translated
+//! module as `<module>_abi_tests.rs`, checking the layout of each of its structs against the one
+//! clang computed for the C struct:
+//!
+//! ```ignore
+//! #[test]
+//! fn point_abi() {
+//!     assert_eq!(core::mem::size_of::<point>(), 16);
+//!     assert_eq!(core::mem::align_of::<point>(), 8);
+//!     assert_eq!(offset_of!(point, tag), 0);
+//!     assert_eq!(offset_of!(point, x), 8);
+//! }
+//! ```
+//!
+//! Unlike `--layout-asserts`, the checks are kept out of the translated module, so they survive
+//! manual edits to it and catch the layout regressions these introduce. Offsets aren't checked
+//! for structs with bitfields, whose fields are merged into byte arrays, and structs with a
+//! `va_list` field are left alone.
+
+use super::*;
+
+/// Name of the test module included into the translated module
+const TEST_MODULE_NAME: &str = "c2rust_abi_tests";
+
+impl<'c> Translation<'c> {
+    /// Record the struct `decl_id`, translated as `name` with its fields in the struct
+    /// `fields_name`, to be checked by the ABI tests
+    pub fn add_abi_test(&self, decl_id: CRecordId, name: &str, fields_name: &str) {
+        // Tests refer to the struct from the main module
+        let in_main_module = self
+            .cur_file
+            .borrow()
+            .map_or(true, |id| id == self.main_file);
+        if self.tcfg.abi_tests && in_main_module {
+            self.abi_test_structs
+                .borrow_mut()
+                .push((decl_id, name.to_owned(), fields_name.to_owned()));
+        }
+    }
+
+    /// Generate the test module item for the translated module, and the file of the ABI tests
+    /// of the structs recorded during translation
+    pub fn abi_tests(&self) -> Option<(Box<Item>, Vec<(String, String)>)> {
+        let structs = self.abi_test_structs.borrow();
+        if structs.is_empty() {
+            return None;
+        }
+
+        let file_name = format!("{}_abi_tests.rs", self.main_module_name);
+        let mut file = format!(
+            "// Checks of the layouts of the structs in {}.rs against their C layouts.\n\
+             #![allow(non_snake_case)]\n\
+             \n\
+             use super::*;\n",
+            self.main_module_name,
+        );
+        for (decl_id, name, fields_name) in structs.iter() {
+            self.abi_test(*decl_id, name, fields_name, &mut file);
+        }
+
+        let test_mod = mk()
+            .call_attr("cfg", vec!["test"])
+            .str_attr("path", &file_name)
+            .mod_item(TEST_MODULE_NAME, None);
+        Some((test_mod, vec![(file_name, file)]))
+    }
+
+    /// Append the test of the layout of struct `decl_id` to `file`
+    fn abi_test(&self, decl_id: CRecordId, name: &str, fields_name: &str, file: &mut String) {
+        let (fields, size, align) = match self.ast_context[decl_id].kind {
+            CDeclKind::Struct {
+                fields: Some(ref fields),
+                platform_byte_size,
+                platform_alignment,
+                ..
+            } => (fields, platform_byte_size, platform_alignment),
+            _ => return,
+        };
+
+        file.push_str(&format!("\n#[test]\nfn {}_abi() {{\n", name));
+        file.push_str(&format!("    assert_eq!(core::mem::size_of::<{}>(), {});\n", name, size));
+        file.push_str(&format!("    assert_eq!(core::mem::align_of::<{}>(), {});\n", name, align));
+
+        let mut offsets = vec![];
+        for &field_id in fields {
+            match self.ast_context[field_id].kind {
+                CDeclKind::Field {
+                    bitfield_width: None,
+                    platform_bit_offset,
+                    ..
+                } => {
+                    let field_name = self
+                        .type_converter
+                        .borrow()
+                        .resolve_field_name(Some(decl_id), field_id)
+                        .expect("Struct fields should already be named");
+                    offsets.push(format!(
+                        "    assert_eq!(offset_of!({}, {}), {});\n",
+                        fields_name,
+                        field_name,
+                        platform_bit_offset / 8
+                    ));
+                }
+                // The fields of bitfield structs don't line up with the C fields
+                _ => {
+                    offsets.clear();
+                    break;
+                }
+            }
+        }
+        if !offsets.is_empty() {
+            self.use_crate(ExternCrate::Memoffset);
+        }
+        file.extend(offsets);
+        file.push_str("}\n");
+    }
+}
@@ -4,6 +4,7 @@
 use super::*;
 use log::warn;
 use proc_macro2::{TokenStream, TokenTree};
+use regex::Regex;
 use syn::__private::ToTokens;
 
 /// An argument direction specifier for a Rust asm! expression
@@ -160,7 +161,8 @@ fn parse_constraints(
                     constraints = machine_constraints.into();
                     mem_only = is_mem;
                 } else {
-                    warn!(
+                    diag!(
+                        Diagnostic::InlineAsm,
                         "Did not recognize inline asm constraint: {}\n\
                     It is likely that this will cause compilation errors or \
                     incorrect semantics in the translated program; please \
@@ -219,7 +221,8 @@ fn translate_machine_constraint(constraint: &str, arch: Arch) -> Option<(&str, b
             "D" => "\"di\"",
             // "A" => "a_and_d", // rust does not support this
             "U" => {
-                warn!(
+                diag!(
+                    Diagnostic::InlineAsm,
                     "the x86 'U' inline assembly operand constraint cannot \
                 be translated correctly. It corresponds to the `clobber_abi` \
                 option for `asm!`, but c2rust does not know the ABI being \
@@ -640,6 +643,10 @@ impl<'c> Translation<'c> {
     /// directly) the resulting translated assembly statements will be unlikely to work
     /// without further manual translation. The translator will properly translate
     /// the arguments to the assembly statement, however.
+    ///
+    /// For `asm goto`, `labels_target` names a local variable to declare and the number of
+    /// labels; each label becomes a `label` operand setting that variable to its 1-based index,
+    /// which the caller then dispatches on.
     pub fn convert_asm(
         &self,
         ctx: ExprContext,
@@ -649,8 +656,9 @@ impl<'c> Translation<'c> {
         inputs: &[AsmOperand],
         outputs: &[AsmOperand],
         clobbers: &[String],
+        labels_target: Option<(&str, usize)>,
     ) -> Result<Vec<Stmt>, TranslationError> {
-        if !self.tcfg.translate_asm {
+        if !self.fn_tcfg().translate_asm {
             return Err(TranslationError::generic(
                 "Inline assembly translation not enabled.",
             ));
@@ -703,6 +711,34 @@ impl<'c> Translation<'c> {
             }
         };
 
+        // `asm goto` label references come after all other operands and carry an `l` modifier;
+        // they become references to named label operands
+        let asm = match labels_target {
+            Some(_) => {
+                let label_ref = Regex::new(r"\$\{(\d+):l\}").unwrap();
+                let first_label = inputs.len() + outputs.len();
+                let mut bad_ref = false;
+                let asm = label_ref.replace_all(asm, |caps: &regex::Captures| {
+                    let idx: usize = caps[1].parse().unwrap();
+                    match idx.checked_sub(first_label) {
+                        Some(label_idx) => format!("${{asm_label{}}}", label_idx),
+                        None => {
+                            bad_ref = true;
+                            caps[0].to_string()
+                        }
+                    }
+                });
+                if bad_ref {
+                    return Err(TranslationError::generic(
+                        "asm goto label modifier applied to a non-label operand",
+                    ));
+                }
+                asm.into_owned()
+            }
+            None => asm.to_owned(),
+        };
+        let asm = asm.as_str();
+
         // Rewrite arg references in assembly template
         let rewritten_asm = rewrite_asm(
             asm,
@@ -769,7 +805,7 @@ impl<'c> Translation<'c> {
                     });
                 }
                 // Constraint could not be parsed, drop it
-                Err(e) => eprintln!("{}", e),
+                Err(e) => diag!(Diagnostic::InlineAsm, "{}", e),
             }
         }
         // Add unmatched inputs
@@ -780,7 +816,7 @@ impl<'c> Translation<'c> {
             let (dir_spec, mem_only, parsed) = match parse_constraints(&input.constraints, arch) {
                 Ok(x) => x,
                 Err(e) => {
-                    eprintln!("{}", e);
+                    diag!(Diagnostic::InlineAsm, "{}", e);
                     continue;
                 }
             };
@@ -943,6 +979,33 @@ impl<'c> Translation<'c> {
             }
         }
 
+        // `asm goto` labels: `asm_labelN = label { target = N + 1; }`
+        if let Some((target, num_labels)) = labels_target {
+            self.use_feature("asm_goto");
+
+            let target_local = mk().local(
+                mk().mutbl().ident_pat(target),
+                None as Option<Box<Type>>,
+                Some(mk().lit_expr(mk().int_unsuffixed_lit(0))),
+            );
+            stmts.insert(0, mk().local_stmt(Box::new(target_local)));
+
+            for i in 0..num_labels {
+                tokens.push(TokenTree::Punct(Punct::new(',', Alone)));
+                push_expr(&mut tokens, mk().ident_expr(format!("asm_label{}", i)));
+                tokens.push(TokenTree::Punct(Punct::new('=', Alone)));
+                push_expr(&mut tokens, mk().ident_expr("label"));
+                let set_target = mk().assign_expr(
+                    mk().ident_expr(target),
+                    mk().lit_expr(mk().int_unsuffixed_lit(i as u128 + 1)),
+                );
+                push_expr(
+                    &mut tokens,
+                    mk().block_expr(mk().block(vec![mk().semi_stmt(set_target)])),
+                );
+            }
+        }
+
         let mut preserves_flags = true;
         let mut read_only = true;
 
@@ -963,7 +1026,8 @@ impl<'c> Translation<'c> {
             // overwritten. Warn verbosely.
             let quoted = format!("\"{}\"", clobber);
             if reg_is_reserved(&quoted, arch).is_some() {
-                warn!(
+                diag!(
+                    Diagnostic::InlineAsm,
                     "Attempting to clobber reserved register ({}), dropping clobber! \
                 This likely means the potential for miscompilation has been introduced. \
                 Please rewrite this assembly to save/restore the value of this register \
@@ -987,8 +1051,8 @@ impl<'c> Translation<'c> {
                 options.push(mk().ident_expr("preserves_flags"));
             }
             if !is_volatile {
-                // Pure cannot be applied if we have no outputs
-                if read_only && (outputs.len() + clobbers.len()) > 0 {
+                // Pure cannot be applied if we have no outputs or may jump to a label
+                if read_only && (outputs.len() + clobbers.len()) > 0 && labels_target.is_none() {
                     options.push(mk().ident_expr("pure"));
                     options.push(mk().ident_expr("readonly"));
                 }
@@ -0,0 +1,191 @@
+//! This module translates the expansions of the C `assert` macro into `assert!` (or
+//! `debug_assert!` with `--ndebug-asserts`), and calls to `abort` into
+//! `std::process::abort()`:
+//!
+//! ```c
+//! assert(n > 0 && "n must be positive");  // assert!(n > 0 && ..., "n > 0 && \"n must be positive\"");
+//! abort();                                // ::std::process::abort();
+//! ```
+//!
+//! `assert` is a macro that calls the libc function reporting the failure, and each libc expands
+//! it differently. The expansions of glibc, musl and the BSD libcs (including macOS) are
+//! recognized; the text of the assertion that C would print becomes the panic message.
+
+use super::*;
+use syn::__private::ToTokens;
+
+impl<'c> Translation<'c> {
+    /// Try to translate an expanded `assert` whose value is unused.
+    pub fn convert_assert(
+        &self,
+        ctx: ExprContext,
+        expr_id: CExprId,
+    ) -> Result<Option<WithStmts<Box<Expr>>>, TranslationError> {
+        let (cond, target, msg) = match self.assert_expansion(expr_id) {
+            Some(assert) => assert,
+            None => return Ok(None),
+        };
+        let cond = self.convert_condition(ctx.used(), target, cond)?;
+        let macro_name = if self.tcfg.ndebug_asserts {
+            "debug_assert"
+        } else {
+            "assert"
+        };
+        Ok(Some(cond.and_then(|cond| {
+            let mut tokens = cond.to_token_stream().into_iter().collect::<Vec<_>>();
+            if let Some(msg) = msg {
+                // The message is a format string
+                let msg = msg.replace('{', "{{").replace('}', "}}");
+                tokens.push(TokenTree::Punct(Punct::new(',', Alone)));
+                tokens.push(TokenTree::Literal(proc_macro2::Literal::string(&msg)));
+            }
+            let assert = mk().mac_expr(mk().mac(
+                vec![macro_name],
+                tokens.into_iter().collect::<TokenStream>(),
+                MacroDelimiter::Paren(Default::default()),
+            ));
+            Ok::<_, TranslationError>(WithStmts::new(
+                vec![mk().semi_stmt(assert)],
+                self.panic_or_err("assert result is not supposed to be used"),
+            ))
+        })?))
+    }
+
+    /// Recognize an expanded `assert`, returning the condition, whether the assertion holds when
+    /// the condition is true or when it's false, and the text of the assertion.
+    fn assert_expansion(&self, expr_id: CExprId) -> Option<(CExprId, bool, Option<String>)> {
+        match *self.peel_assert(expr_id) {
+            // `e ? (void)0 : __assert_fail(#e, ...)`, used by glibc in strict ANSI mode
+            CExprKind::Conditional(_, cond, then, els) => {
+                if let Some(msg) = self.assert_fail_call(els) {
+                    return Some((cond, true, msg));
+                }
+                // `__builtin_expect(!(e), 0) ? __assert_rtn(..., #e) : (void)0` on macOS
+                let msg = self.assert_fail_call(then)?;
+                let cond = match *self.ast_context.resolve_expr(cond).1 {
+                    CExprKind::Call(_, func, ref args)
+                        if args.len() == 2 && self.is_builtin_expect(func) =>
+                    {
+                        args[0]
+                    }
+                    _ => cond,
+                };
+                match *self.ast_context.resolve_expr(cond).1 {
+                    CExprKind::Unary(_, c_ast::UnOp::Not, e, _) => Some((e, true, msg)),
+                    _ => Some((cond, false, msg)),
+                }
+            }
+            // `(void) sizeof (e ? 1 : 0), ({ if (e) ; else __assert_fail(#e, ...); })` in glibc
+            CExprKind::Binary(_, c_ast::BinOp::Comma, _, rhs, _, _) => {
+                let stmt = match *self.peel_assert(rhs) {
+                    CExprKind::Statements(_, stmt) => stmt,
+                    _ => return None,
+                };
+                let stmts = match self.ast_context[stmt].kind {
+                    CStmtKind::Compound(ref stmts) if stmts.len() == 1 => stmts,
+                    _ => return None,
+                };
+                match self.ast_context[stmts[0]].kind {
+                    CStmtKind::If {
+                        scrutinee,
+                        true_variant,
+                        false_variant: Some(false_variant),
+                    } => {
+                        if !matches!(self.ast_context[true_variant].kind, CStmtKind::Empty) {
+                            return None;
+                        }
+                        match self.ast_context[false_variant].kind {
+                            CStmtKind::Expr(fail) => {
+                                Some((scrutinee, true, self.assert_fail_call(fail)?))
+                            }
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                }
+            }
+            // `(void)(e || (__assert_fail(#e, ...), 0))` in musl
+            CExprKind::Binary(_, c_ast::BinOp::Or, cond, rhs, _, _) => {
+                match *self.peel_assert(rhs) {
+                    CExprKind::Binary(_, c_ast::BinOp::Comma, fail, _, _, _) => {
+                        Some((cond, true, self.assert_fail_call(fail)?))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Is `func` `__builtin_expect`, which wraps the condition of some expansions?
+    fn is_builtin_expect(&self, func: CExprId) -> bool {
+        match *self.ast_context.resolve_expr(func).1 {
+            CExprKind::DeclRef(_, decl_id, _) => matches!(
+                self.ast_context[decl_id].kind,
+                CDeclKind::Function { ref name, .. } if name == "__builtin_expect"
+            ),
+            _ => false,
+        }
+    }
+
+    /// Look through parentheses, casts and `__extension__`
+    fn peel_assert(&self, expr_id: CExprId) -> &CExprKind {
+        match *self.ast_context.resolve_expr(expr_id).1 {
+            CExprKind::Unary(_, c_ast::UnOp::Extension, e, _) => self.peel_assert(e),
+            ref kind => kind,
+        }
+    }
+
+    /// If `expr_id` calls the libc function reporting a failed assertion, the assertion text,
+    /// if it's a string literal
+    fn assert_fail_call(&self, expr_id: CExprId) -> Option<Option<String>> {
+        let (func, args) = match *self.ast_context.resolve_expr(expr_id).1 {
+            CExprKind::Call(_, func, ref args) => (func, args),
+            _ => return None,
+        };
+        let name = match *self.ast_context.resolve_expr(func).1 {
+            CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[decl_id].kind {
+                CDeclKind::Function {
+                    ref name,
+                    body: None,
+                    ..
+                } => name.as_str(),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let msg = match (name, &args[..]) {
+            ("__assert_fail", [msg, _, _, _]) => msg,
+            ("__assert_rtn", [_, _, _, msg]) | ("__assert", [_, _, _, msg]) => msg,
+            _ => return None,
+        };
+        Some(match *self.ast_context.resolve_expr(*msg).1 {
+            CExprKind::Literal(_, CLiteral::String(ref bytes, 1)) => {
+                let bytes = bytes.split(|&b| b == 0).next().unwrap_or_default();
+                Some(String::from_utf8_lossy(bytes).into_owned())
+            }
+            _ => None,
+        })
+    }
+
+    /// Translate a call to `abort()` into `std::process::abort()`.
+    pub fn convert_abort_call(&self, callee: CExprId, args: &[CExprId]) -> Option<Box<Expr>> {
+        if self.tcfg.emit_no_std || !args.is_empty() {
+            return None;
+        }
+        match *self.ast_context.resolve_expr(callee).1 {
+            CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[decl_id].kind {
+                CDeclKind::Function {
+                    ref name,
+                    body: None,
+                    ..
+                } if name == "abort" => Some(mk().call_expr(
+                    mk().abs_path_expr(vec!["std", "process", "abort"]),
+                    Vec::<Box<Expr>>::new(),
+                )),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
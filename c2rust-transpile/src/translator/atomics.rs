@@ -14,8 +14,8 @@ impl<'c> Translation<'c> {
     }
 
     fn convert_memordering(&self, expr: CExprId) -> Option<Ordering> {
-        let memorder = &self.ast_context[expr];
-        match memorder.kind {
+        let memorder = self.ast_context.resolve_expr(expr).1;
+        match *memorder {
             CExprKind::Literal(_, CLiteral::Integer(i, _)) => match i {
                 0 => Some(Ordering::Relaxed),
                 1 => Some(Ordering::Acquire),
@@ -29,6 +29,54 @@ impl<'c> Translation<'c> {
         }
     }
 
+    /// Get the memory ordering of an atomic builtin. Orderings that are only known at runtime
+    /// are strengthened to `SeqCst`, which is valid wherever the builtin accepts one.
+    pub(crate) fn atomic_memordering(&self, expr: CExprId) -> Ordering {
+        self.convert_memordering(expr).unwrap_or_else(|| {
+            diag!(
+                Diagnostic::Atomics,
+                at self.expr_loc(expr),
+                "Memory ordering is not a constant, using SeqCst instead",
+            );
+            Ordering::SeqCst
+        })
+    }
+
+    /// Get the failure ordering of a compare-and-exchange. Orderings that are only known at
+    /// runtime are replaced with the strongest failure ordering allowed for `success`.
+    fn atomic_failure_memordering(&self, expr: CExprId, success: Ordering) -> Ordering {
+        self.convert_memordering(expr).unwrap_or_else(|| {
+            let order = match success {
+                Ordering::SeqCst => Ordering::SeqCst,
+                Ordering::AcqRel | Ordering::Acquire => Ordering::Acquire,
+                _ => Ordering::Relaxed,
+            };
+            diag!(
+                Diagnostic::Atomics,
+                at self.expr_loc(expr),
+                "Failure memory ordering is not a constant, using {:?} instead",
+                order,
+            );
+            order
+        })
+    }
+
+    fn expr_loc(&self, expr: CExprId) -> Option<DisplaySrcSpan> {
+        self.ast_context.display_loc(&self.ast_context[expr].loc)
+    }
+
+    /// Append the suffix selecting `order` to the name of an atomic intrinsic
+    pub(crate) fn atomic_intrinsic_name(intrinsic_name: &str, order: Ordering) -> String {
+        match order {
+            Ordering::SeqCst => intrinsic_name.to_string(),
+            Ordering::AcqRel => format!("{}_acqrel", intrinsic_name),
+            Ordering::Acquire => format!("{}_acq", intrinsic_name),
+            Ordering::Release => format!("{}_rel", intrinsic_name),
+            Ordering::Relaxed => format!("{}_relaxed", intrinsic_name),
+            _ => unreachable!("Unknown memory ordering"),
+        }
+    }
+
     pub fn convert_atomic(
         &self,
         ctx: ExprContext,
@@ -42,28 +90,23 @@ impl<'c> Translation<'c> {
     ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
         let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
         let ptr = self.convert_expr(ctx.used(), ptr_id)?;
-        let order = self.convert_memordering(order_id);
+        let order = self.atomic_memordering(order_id);
         let val1 = val1_id
             .map(|x| self.convert_expr(ctx.used(), x))
             .transpose()?;
-        let order_fail = order_fail_id.and_then(|x| self.convert_memordering(x));
         let val2 = val2_id
             .map(|x| self.convert_expr(ctx.used(), x))
             .transpose()?;
-        let weak = weak_id.and_then(|x| self.convert_constant_bool(x));
 
         match name {
             "__atomic_load" | "__atomic_load_n" => ptr.and_then(|ptr| {
                 let intrinsic_name = match order {
-                    None => {
-                        unimplemented!("Dynamic memory consistency arguments are not yet supported")
-                    }
-                    Some(Ordering::SeqCst) => Some("atomic_load"),
-                    Some(Ordering::AcqRel) => None,
-                    Some(Ordering::Acquire) => Some("atomic_load_acq"),
-                    Some(Ordering::Release) => None,
-                    Some(Ordering::Relaxed) => Some("atomic_load_relaxed"),
-                    Some(_) => unreachable!("Did we not handle a case above??"),
+                    Ordering::SeqCst => Some("atomic_load"),
+                    Ordering::AcqRel => None,
+                    Ordering::Acquire => Some("atomic_load_acq"),
+                    Ordering::Release => None,
+                    Ordering::Relaxed => Some("atomic_load_relaxed"),
+                    _ => unreachable!("Did we not handle a case above??"),
                 }
                 .ok_or_else(|| {
                     format_translation_err!(
@@ -105,15 +148,12 @@ impl<'c> Translation<'c> {
                 ptr.and_then(|ptr| {
                     val.and_then(|val| {
                         let intrinsic_name = match order {
-                            None => unimplemented!(
-                                "Dynamic memory consistency arguments are not yet supported"
-                            ),
-                            Some(Ordering::SeqCst) => Some("atomic_store"),
-                            Some(Ordering::AcqRel) => None,
-                            Some(Ordering::Acquire) => None,
-                            Some(Ordering::Release) => Some("atomic_store_rel"),
-                            Some(Ordering::Relaxed) => Some("atomic_store_relaxed"),
-                            Some(_) => unreachable!("Did we not handle a case above??"),
+                            Ordering::SeqCst => Some("atomic_store"),
+                            Ordering::AcqRel => None,
+                            Ordering::Acquire => None,
+                            Ordering::Release => Some("atomic_store_rel"),
+                            Ordering::Relaxed => Some("atomic_store_relaxed"),
+                            _ => unreachable!("Did we not handle a case above??"),
                         }
                         .ok_or_else(|| {
                             format_translation_err!(
@@ -146,29 +186,12 @@ impl<'c> Translation<'c> {
                 let val = val1.expect("__atomic_store must have a val argument");
                 ptr.and_then(|ptr| {
                     val.and_then(|val| {
-                        let intrinsic_name = match order {
-                            None => unimplemented!(
-                                "Dynamic memory consistency arguments are not yet supported"
-                            ),
-                            Some(Ordering::SeqCst) => Some("atomic_xchg"),
-                            Some(Ordering::AcqRel) => Some("atomic_xchg_acqrel"),
-                            Some(Ordering::Acquire) => Some("atomic_xchg_acq"),
-                            Some(Ordering::Release) => Some("atomic_xchg_rel"),
-                            Some(Ordering::Relaxed) => Some("atomic_xchg_relaxed"),
-                            Some(_) => unreachable!("Did we not handle a case above??"),
-                        }
-                        .ok_or_else(|| {
-                            format_translation_err!(
-                                self.ast_context
-                                    .display_loc(&self.ast_context[order_id].loc),
-                                "Invalid memory ordering for __atomic_exchange",
-                            )
-                        })?;
+                        let intrinsic_name = Self::atomic_intrinsic_name("atomic_xchg", order);
 
                         self.use_feature("core_intrinsics");
 
                         let fn_path =
-                            mk().abs_path_expr(vec![std_or_core, "intrinsics", intrinsic_name]);
+                            mk().abs_path_expr(vec![std_or_core, "intrinsics", &intrinsic_name]);
                         let val = if name == "__atomic_exchange" {
                             mk().unary_expr(UnOp::Deref(Default::default()), val)
                         } else {
@@ -207,83 +230,103 @@ impl<'c> Translation<'c> {
                 let expected =
                     val1.expect("__atomic_compare_exchange must have a expected argument");
                 let desired = val2.expect("__atomic_compare_exchange must have a desired argument");
+                let order_fail = self.atomic_failure_memordering(
+                    order_fail_id.expect("__atomic_compare_exchange must have a failure order"),
+                    order,
+                );
+                let weak = weak_id.map_or(false, |x| {
+                    self.convert_constant_bool(x).unwrap_or_else(|| {
+                        diag!(
+                            Diagnostic::Atomics,
+                            at self.expr_loc(x),
+                            "Weak argument is not a constant, using a strong compare-and-exchange",
+                        );
+                        false
+                    })
+                });
                 ptr.and_then(|ptr| {
                     expected.and_then(|expected| {
                         desired.and_then(|desired| {
                             let intrinsic_name = match (weak, order, order_fail) {
-                                (None, _, _) | (_, None, _) | (_, _, None) => {
-                                    // We have to select which intrinsic to use at runtime
-                                    unimplemented!("Dynamic memory consistency arguments are not yet supported");
+                                (_, _, Ordering::Release) | (_, _, Ordering::AcqRel) => None,
+
+                                (false, Ordering::SeqCst, Ordering::SeqCst) => Some("atomic_cxchg"),
+                                (false, Ordering::SeqCst, Ordering::Acquire) => {
+                                    Some("atomic_cxchg_failacq")
+                                }
+                                (false, Ordering::SeqCst, Ordering::Relaxed) => {
+                                    Some("atomic_cxchg_failrelaxed")
+                                }
+                                (false, Ordering::SeqCst, _) => None,
+                                (false, Ordering::AcqRel, Ordering::Acquire) => {
+                                    Some("atomic_cxchg_acqrel")
+                                }
+                                (false, Ordering::AcqRel, Ordering::Relaxed) => {
+                                    Some("atomic_cxchg_acqrel_failrelaxed")
                                 }
-                                (_, _, Some(Ordering::Release)) | (_, _, Some(Ordering::AcqRel)) =>
-                                    None,
-
-                                (Some(false), Some(Ordering::SeqCst), Some(Ordering::SeqCst)) =>
-                                    Some("atomic_cxchg"),
-                                (Some(false), Some(Ordering::SeqCst), Some(Ordering::Acquire)) =>
-                                    Some("atomic_cxchg_failacq"),
-                                (Some(false), Some(Ordering::SeqCst), Some(Ordering::Relaxed)) =>
-                                    Some("atomic_cxchg_failrelaxed"),
-                                (Some(false), Some(Ordering::SeqCst), Some(_)) =>
-                                    None,
-                                (Some(false), Some(Ordering::AcqRel), Some(Ordering::Acquire)) =>
-                                    Some("atomic_cxchg_acqrel"),
-                                (Some(false), Some(Ordering::AcqRel), Some(Ordering::Relaxed)) =>
-                                    Some("atomic_cxchg_acqrel_failrelaxed"),
-                                (Some(false), Some(Ordering::AcqRel), Some(_)) =>
-                                    None,
-                                (Some(false), Some(Ordering::Release), Some(Ordering::Relaxed)) =>
-                                    Some("atomic_cxchg_rel"),
-                                (Some(false), Some(Ordering::Release), Some(_)) =>
-                                    None,
-                                (Some(false), Some(Ordering::Acquire), Some(Ordering::Acquire)) =>
-                                    Some("atomic_cxchg_acq"),
-                                (Some(false), Some(Ordering::Acquire), Some(Ordering::Relaxed)) =>
-                                    Some("atomic_cxchg_acq_failrelaxed"),
-                                (Some(false), Some(Ordering::Acquire), Some(_)) =>
-                                    None,
-                                (Some(false), Some(Ordering::Relaxed), Some(Ordering::Relaxed)) =>
-                                    Some("atomic_cxchg_relaxed"),
-                                (Some(false), Some(Ordering::Relaxed), Some(_)) =>
-                                    None,
-
-                                (Some(true), Some(Ordering::SeqCst), Some(Ordering::SeqCst)) =>
-                                    Some("atomic_cxchgweak"),
-                                (Some(true), Some(Ordering::SeqCst), Some(Ordering::Acquire)) =>
-                                    Some("atomic_cxchgweak_failacq"),
-                                (Some(true), Some(Ordering::SeqCst), Some(Ordering::Relaxed)) =>
-                                    Some("atomic_cxchgweak_failrelaxed"),
-                                (Some(true), Some(Ordering::SeqCst), Some(_)) =>
-                                    None,
-                                (Some(true), Some(Ordering::AcqRel), Some(Ordering::Acquire)) =>
-                                    Some("atomic_cxchgweak_acqrel"),
-                                (Some(true), Some(Ordering::AcqRel), Some(Ordering::Relaxed)) =>
-                                    Some("atomic_cxchgweak_acqrel_failrelaxed"),
-                                (Some(true), Some(Ordering::AcqRel), Some(_)) =>
-                                    None,
-                                (Some(true), Some(Ordering::Release), Some(Ordering::Relaxed)) =>
-                                    Some("atomic_cxchgweak_rel"),
-                                (Some(true), Some(Ordering::Release), Some(_)) =>
-                                    None,
-                                (Some(true), Some(Ordering::Acquire), Some(Ordering::Acquire)) =>
-                                    Some("atomic_cxchgweak_acq"),
-                                (Some(true), Some(Ordering::Acquire), Some(Ordering::Relaxed)) =>
-                                    Some("atomic_cxchgweak_acq_failrelaxed"),
-                                (Some(true), Some(Ordering::Acquire), Some(_)) =>
-                                    None,
-                                (Some(true), Some(Ordering::Relaxed), Some(Ordering::Relaxed)) =>
-                                    Some("atomic_cxchgweak_relaxed"),
-                                (Some(true), Some(Ordering::Relaxed), Some(_)) =>
-                                    None,
-
-                                (Some(_), Some(_), Some(_)) => unreachable!("Did we not handle a case above??"),
-                            }.ok_or_else(|| format_translation_err!(
-                                self.ast_context.display_loc(&self.ast_context[order_fail_id.unwrap()].loc),
-                                "Invalid failure memory ordering",
-                            ))?;
+                                (false, Ordering::AcqRel, _) => None,
+                                (false, Ordering::Release, Ordering::Relaxed) => {
+                                    Some("atomic_cxchg_rel")
+                                }
+                                (false, Ordering::Release, _) => None,
+                                (false, Ordering::Acquire, Ordering::Acquire) => {
+                                    Some("atomic_cxchg_acq")
+                                }
+                                (false, Ordering::Acquire, Ordering::Relaxed) => {
+                                    Some("atomic_cxchg_acq_failrelaxed")
+                                }
+                                (false, Ordering::Acquire, _) => None,
+                                (false, Ordering::Relaxed, Ordering::Relaxed) => {
+                                    Some("atomic_cxchg_relaxed")
+                                }
+                                (false, Ordering::Relaxed, _) => None,
+
+                                (true, Ordering::SeqCst, Ordering::SeqCst) => {
+                                    Some("atomic_cxchgweak")
+                                }
+                                (true, Ordering::SeqCst, Ordering::Acquire) => {
+                                    Some("atomic_cxchgweak_failacq")
+                                }
+                                (true, Ordering::SeqCst, Ordering::Relaxed) => {
+                                    Some("atomic_cxchgweak_failrelaxed")
+                                }
+                                (true, Ordering::SeqCst, _) => None,
+                                (true, Ordering::AcqRel, Ordering::Acquire) => {
+                                    Some("atomic_cxchgweak_acqrel")
+                                }
+                                (true, Ordering::AcqRel, Ordering::Relaxed) => {
+                                    Some("atomic_cxchgweak_acqrel_failrelaxed")
+                                }
+                                (true, Ordering::AcqRel, _) => None,
+                                (true, Ordering::Release, Ordering::Relaxed) => {
+                                    Some("atomic_cxchgweak_rel")
+                                }
+                                (true, Ordering::Release, _) => None,
+                                (true, Ordering::Acquire, Ordering::Acquire) => {
+                                    Some("atomic_cxchgweak_acq")
+                                }
+                                (true, Ordering::Acquire, Ordering::Relaxed) => {
+                                    Some("atomic_cxchgweak_acq_failrelaxed")
+                                }
+                                (true, Ordering::Acquire, _) => None,
+                                (true, Ordering::Relaxed, Ordering::Relaxed) => {
+                                    Some("atomic_cxchgweak_relaxed")
+                                }
+                                (true, Ordering::Relaxed, _) => None,
+
+                                _ => unreachable!("Did we not handle a case above??"),
+                            }
+                            .ok_or_else(|| {
+                                format_translation_err!(
+                                    self.ast_context
+                                        .display_loc(&self.ast_context[order_fail_id.unwrap()].loc),
+                                    "Invalid failure memory ordering",
+                                )
+                            })?;
 
                             self.use_feature("core_intrinsics");
-                            let expected = mk().unary_expr(UnOp::Deref(Default::default()), expected);
+                            let expected =
+                                mk().unary_expr(UnOp::Deref(Default::default()), expected);
                             let desired = if name == "__atomic_compare_exchange_n" {
                                 desired
                             } else {
@@ -291,8 +334,9 @@ impl<'c> Translation<'c> {
                             };
 
                             let atomic_cxchg =
-                                mk().abs_path_expr(vec![ std_or_core, "intrinsics", intrinsic_name]);
-                            let call = mk().call_expr(atomic_cxchg, vec![ptr, expected.clone(), desired]);
+                                mk().abs_path_expr(vec![std_or_core, "intrinsics", intrinsic_name]);
+                            let call =
+                                mk().call_expr(atomic_cxchg, vec![ptr, expected.clone(), desired]);
                             let res_name = self.renamer.borrow_mut().fresh();
                             let res_let = mk().local_stmt(Box::new(mk().local(
                                 mk().ident_pat(&res_name),
@@ -340,21 +384,7 @@ impl<'c> Translation<'c> {
                     "atomic_and"
                 };
 
-                let intrinsic_name = match order {
-                    None => {
-                        unimplemented!(
-                            "Dynamic memory consistency arguments are not yet supported"
-                        );
-                    }
-
-                    Some(Ordering::SeqCst) => format!("{}", intrinsic_name),
-                    Some(Ordering::AcqRel) => format!("{}_acqrel", intrinsic_name),
-                    Some(Ordering::Acquire) => format!("{}_acq", intrinsic_name),
-                    Some(Ordering::Release) => format!("{}_rel", intrinsic_name),
-                    Some(Ordering::Relaxed) => format!("{}_relaxed", intrinsic_name),
-
-                    _ => unreachable!("Unknown memory ordering"),
-                };
+                let intrinsic_name = Self::atomic_intrinsic_name(intrinsic_name, order);
 
                 let fetch_first = name.starts_with("__atomic_fetch");
                 let val = val1.expect("__atomic arithmetic operations must have a val argument");
@@ -365,6 +395,31 @@ impl<'c> Translation<'c> {
                 })
             }
 
+            "__atomic_fetch_min" | "__atomic_fetch_max" => {
+                let is_unsigned = self.ast_context[ptr_id]
+                    .kind
+                    .get_type()
+                    .and_then(|ty| self.ast_context.get_pointee_qual_type(ty))
+                    .map_or(false, |pointee| {
+                        self.ast_context
+                            .resolve_type(pointee.ctype)
+                            .kind
+                            .is_unsigned_integral_type()
+                    });
+                let intrinsic_name = match (name, is_unsigned) {
+                    ("__atomic_fetch_min", false) => "atomic_min",
+                    ("__atomic_fetch_min", true) => "atomic_umin",
+                    (_, false) => "atomic_max",
+                    (_, true) => "atomic_umax",
+                };
+                let intrinsic_name = Self::atomic_intrinsic_name(intrinsic_name, order);
+
+                let val = val1.expect("__atomic min/max operations must have a val argument");
+                ptr.and_then(|ptr| {
+                    val.and_then(|val| self.convert_atomic_op(ctx, &intrinsic_name, ptr, val, true))
+                })
+            }
+
             _ => unimplemented!("atomic not implemented"),
         }
     }
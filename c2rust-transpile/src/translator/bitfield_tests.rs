@@ -0,0 +1,312 @@
+//! This module implements `--bitfield-tests`, which checks the accessors generated for bitfield
+//! structs against the C compiler's own layout. For each bitfield struct, a C file written next
+//! to the translated module as `<module>.bitfields.c` declares a mirror struct that places the
+//! same bitfields at the bit offsets clang computed for the original, along with functions that
+//! set and get them:
+//!
+//! ```c
+//! struct c2rust_bf_date {
+//!     unsigned char day : 5;
+//!     unsigned char month : 4;
+//!     unsigned short year : 15;
+//!     unsigned char c2rust_padding0[1];
+//! } __attribute__((packed));
+//!
+//! void c2rust_bf_module_date_set_day(void *s, long long value) { ... }
+//! long long c2rust_bf_module_date_get_day(const void *s) { ... }
+//! ```
+//!
+//! Regular fields are replaced with padding, and the mirror is packed so that each bitfield lands
+//! exactly where the padding puts it. Tests in `<module>_bitfield_tests.rs` then set each bitfield
+//! of a zeroed struct through both the translated setter and the C setter, and check that the
+//! bytes and the values read back by both getters agree. The tests link against the C file,
+//! which has to be compiled into the crate, e.g. with the `cc` crate in `build.rs`.
+
+use crate::format_translation_err;
+
+use super::*;
+
+/// Name of the test module included into the translated module
+const TEST_MODULE_NAME: &str = "c2rust_bitfield_tests";
+
+/// A bitfield checked against its C counterpart
+struct TestedField {
+    name: String,
+    c_ty: &'static str,
+    rust_ty: String,
+    bit_offset: u64,
+    width: u64,
+    /// Values to store, cast to the field's type
+    values: Vec<i128>,
+}
+
+impl<'c> Translation<'c> {
+    /// Generate the test module item for the translated module, and the C and Rust files for
+    /// the bitfield structs recorded during translation.
+    pub fn bitfield_tests(&self) -> Option<(Box<Item>, Vec<(String, String)>)> {
+        let structs = self.bitfield_test_structs.borrow();
+        if structs.is_empty() {
+            return None;
+        }
+
+        let c_file_name = format!("{}.bitfields.c", self.main_module_name);
+        let rust_file_name = format!("{}_bitfield_tests.rs", self.main_module_name);
+
+        let mut c_file = format!(
+            "// Mirrors of the bitfield structs in {0}.rs, checked by {1}.\n\
+             // Compile this file into the crate to run them, e.g. with the `cc` crate in build.rs.\n",
+            self.main_module_name, rust_file_name,
+        );
+        let mut rust_file = String::from(
+            "#![allow(non_snake_case)]\n\
+             \n\
+             use super::*;\n\
+             \n\
+             fn bytes_of<T>(s: &T) -> &[u8] {\n    \
+                 unsafe {\n        \
+                     core::slice::from_raw_parts(s as *const T as *const u8, core::mem::size_of::<T>())\n    \
+                 }\n\
+             }\n",
+        );
+
+        for &decl_id in structs.iter() {
+            let name = self
+                .type_converter
+                .borrow()
+                .resolve_decl_name(decl_id)
+                .unwrap();
+            if let Err(e) = self.bitfield_test(decl_id, &name, &mut c_file, &mut rust_file) {
+                warn!("Skipping bitfield tests for {}: {}", name, e);
+            }
+        }
+
+        let test_mod = mk()
+            .call_attr("cfg", vec!["test"])
+            .str_attr("path", &rust_file_name)
+            .mod_item(TEST_MODULE_NAME, None);
+        Some((
+            test_mod,
+            vec![(c_file_name, c_file), (rust_file_name, rust_file)],
+        ))
+    }
+
+    /// Append the mirror of struct `decl_id` to the C file and its test to the Rust file
+    fn bitfield_test(
+        &self,
+        decl_id: CRecordId,
+        name: &str,
+        c_file: &mut String,
+        rust_file: &mut String,
+    ) -> Result<(), TranslationError> {
+        let (field_ids, platform_byte_size) = match self.ast_context[decl_id].kind {
+            CDeclKind::Struct {
+                fields: Some(ref fields),
+                platform_byte_size,
+                ..
+            } => (fields, platform_byte_size),
+            _ => return Err(TranslationError::generic("not a struct definition")),
+        };
+
+        let mut fields = vec![];
+        for &field_id in field_ids {
+            if let CDeclKind::Field {
+                name: ref field_name,
+                typ,
+                bitfield_width: Some(width),
+                platform_bit_offset,
+                ..
+            } = self.ast_context[field_id].kind
+            {
+                // Zero-width and unnamed bitfields only affect the layout, which the mirror
+                // reproduces with padding
+                if width == 0 || field_name.is_empty() {
+                    continue;
+                }
+                fields.push(self.tested_field(
+                    decl_id,
+                    field_id,
+                    typ.ctype,
+                    platform_bit_offset,
+                    width,
+                )?);
+            }
+        }
+
+        // The mirror struct, with the bitfields at their original offsets
+        let prefix = format!("c2rust_bf_{}_{}", self.main_module_name, name);
+        let mut mirror = format!("\nstruct c2rust_bf_{} {{\n", name);
+        let mut next_bit = 0;
+        let mut padding_count = 0;
+        for field in &fields {
+            pad_bits(&mut mirror, next_bit, field.bit_offset, &mut padding_count);
+            mirror.push_str(&format!(
+                "    {} {} : {};\n",
+                field.c_ty, field.name, field.width
+            ));
+            next_bit = field.bit_offset + field.width;
+        }
+        pad_bits(
+            &mut mirror,
+            next_bit,
+            platform_byte_size * 8,
+            &mut padding_count,
+        );
+        mirror.push_str("} __attribute__((packed));\n");
+        mirror.push_str(&format!(
+            "_Static_assert(sizeof(struct c2rust_bf_{0}) == {1}, \"layout of {0}\");\n",
+            name, platform_byte_size,
+        ));
+        c_file.push_str(&mirror);
+
+        for field in &fields {
+            c_file.push_str(&format!(
+                "\nvoid {0}_set_{1}(void *s, long long value) {{\n    \
+                     ((struct c2rust_bf_{2} *)s)->{1} = value;\n\
+                 }}\n\
+                 \n\
+                 long long {0}_get_{1}(const void *s) {{\n    \
+                     return ((const struct c2rust_bf_{2} *)s)->{1};\n\
+                 }}\n",
+                prefix, field.name, name,
+            ));
+        }
+
+        // The test, which goes through the inner struct of aligned structs
+        rust_file.push_str("\nextern \"C\" {\n");
+        for field in &fields {
+            rust_file.push_str(&format!(
+                "    fn {0}_set_{1}(s: *mut u8, value: i64);\n    \
+                     fn {0}_get_{1}(s: *const u8) -> i64;\n",
+                prefix, field.name,
+            ));
+        }
+        rust_file.push_str("}\n");
+
+        let accessor_base = if self.ast_context.has_inner_struct_decl(decl_id) {
+            "s.0"
+        } else {
+            "s"
+        };
+        rust_file.push_str(&format!("\n#[test]\nfn test_{}_bitfields() {{\n", name));
+        for field in &fields {
+            let values = field
+                .values
+                .iter()
+                .map(|&value| match &*field.rust_ty {
+                    "bool" => (value != 0).to_string(),
+                    _ => value.to_string(),
+                })
+                .collect::<Vec<_>>();
+            rust_file.push_str(&format!(
+                "    let values: [{ty}; {len}] = [{values}];\n    \
+                     for &value in values.iter() {{\n        \
+                         let mut s: {name} = unsafe {{ core::mem::zeroed() }};\n        \
+                         let mut c = [0u8; {size}];\n        \
+                         {base}.set_{field}(value);\n        \
+                         unsafe {{ {prefix}_set_{field}(c.as_mut_ptr(), value as i64) }};\n        \
+                         assert_eq!(bytes_of(&s), &c[..], \"{field} = {{}}\", value);\n        \
+                         let c_value = unsafe {{ {prefix}_get_{field}(c.as_ptr()) }};\n        \
+                         assert_eq!({base}.{field}() as i64, c_value, \"{field} = {{}}\", value);\n    \
+                     }}\n",
+                ty = field.rust_ty,
+                len = values.len(),
+                values = values.join(", "),
+                name = name,
+                size = platform_byte_size,
+                base = accessor_base,
+                field = field.name,
+                prefix = prefix,
+            ));
+        }
+        rust_file.push_str("}\n");
+
+        Ok(())
+    }
+
+    fn tested_field(
+        &self,
+        record_id: CRecordId,
+        field_id: CFieldId,
+        ctype: CTypeId,
+        bit_offset: u64,
+        width: u64,
+    ) -> Result<TestedField, TranslationError> {
+        let name = self
+            .type_converter
+            .borrow()
+            .resolve_field_name(Some(record_id), field_id)
+            .unwrap();
+
+        // Plain `char` may be signed or unsigned, so it is only given values that fit either way
+        let (c_ty, signed) = match self.ast_context.resolve_type(ctype).kind {
+            CTypeKind::Bool => ("_Bool", false),
+            CTypeKind::Char => ("char", false),
+            CTypeKind::SChar => ("signed char", true),
+            CTypeKind::Short => ("short", true),
+            CTypeKind::Int => ("int", true),
+            CTypeKind::Long => ("long", true),
+            CTypeKind::LongLong => ("long long", true),
+            CTypeKind::UChar => ("unsigned char", false),
+            CTypeKind::UShort => ("unsigned short", false),
+            CTypeKind::UInt => ("unsigned int", false),
+            CTypeKind::ULong => ("unsigned long", false),
+            CTypeKind::ULongLong => ("unsigned long long", false),
+            _ => {
+                return Err(format_translation_err!(
+                    None,
+                    "bitfield {} has an unsupported type",
+                    name
+                ));
+            }
+        };
+        let max_unsigned = (1i128 << width) - 1;
+        let mut values = match self.ast_context.resolve_type(ctype).kind {
+            CTypeKind::Bool => vec![1],
+            CTypeKind::Char => vec![1, max_unsigned >> 1],
+            _ if signed => vec![1, -1, -(1 << (width - 1)), (1 << (width - 1)) - 1],
+            _ => vec![1, max_unsigned, 0x5555_5555_5555_5555 & max_unsigned],
+        };
+        values.retain(|&value| value != 0);
+        values.sort();
+        values.dedup();
+
+        let rust_ty = match *self.convert_type(ctype)? {
+            Type::Path(syn::TypePath { ref path, .. }) => pprust::path_to_string(path),
+            _ => unreachable!("Found type other than path"),
+        };
+
+        Ok(TestedField {
+            name,
+            c_ty,
+            rust_ty,
+            bit_offset,
+            width,
+            values,
+        })
+    }
+}
+
+/// Pad the mirror struct from bit `from` to bit `to`, filling up the current byte with an unnamed
+/// bitfield before padding whole bytes
+fn pad_bits(mirror: &mut String, mut from: u64, to: u64, padding_count: &mut usize) {
+    if from >= to {
+        return;
+    }
+    if from % 8 != 0 {
+        let bits = (8 - from % 8).min(to - from);
+        mirror.push_str(&format!("    unsigned char : {};\n", bits));
+        from += bits;
+    }
+    let bytes = (to - from) / 8;
+    if bytes > 0 {
+        mirror.push_str(&format!(
+            "    unsigned char c2rust_padding{}[{}];\n",
+            padding_count, bytes
+        ));
+        *padding_count += 1;
+        from += bytes * 8;
+    }
+    if from < to {
+        mirror.push_str(&format!("    unsigned char : {};\n", to - from));
+    }
+}
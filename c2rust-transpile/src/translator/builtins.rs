@@ -4,6 +4,7 @@
 use crate::format_translation_err;
 
 use super::*;
+use std::sync::atomic::Ordering;
 
 impl<'c> Translation<'c> {
     /// Convert a call to a builtin function to a Rust expression
@@ -88,7 +89,7 @@ impl<'c> Translation<'c> {
                     let val =
                         mk().method_call_expr(v, "is_sign_negative", vec![] as Vec<Box<Expr>>);
 
-                    mk().cast_expr(val, mk().path_ty(vec!["libc", "c_int"]))
+                    mk().cast_expr(val, self.libc_ty("c_int"))
                 }))
             }
             "__builtin_ffs" | "__builtin_ffsl" | "__builtin_ffsll" => {
@@ -178,6 +179,19 @@ impl<'c> Translation<'c> {
                 Ok(WithStmts::new_val(mk().lit_expr(mk().int_lit(1, "i32"))))
             }
             "__builtin_expect" => self.convert_expr(ctx.used(), args[0]),
+            "__builtin_expect_with_probability" | "__builtin_unpredictable" => {
+                // Rust has no stable equivalent of these branch prediction hints, so like
+                // `__builtin_expect`, they are replaced by the expression they apply to
+                let loc = self
+                    .ast_context
+                    .display_loc(src_loc)
+                    .map_or_else(|| "<unknown location>".to_string(), |loc| loc.to_string());
+                info!(
+                    "{}: dropping the branch prediction hint of {}",
+                    loc, builtin_name
+                );
+                self.convert_expr(ctx.used(), args[0])
+            }
 
             "__builtin_popcount" | "__builtin_popcountl" | "__builtin_popcountll" => {
                 let val = self.convert_expr(ctx.used(), args[0])?;
@@ -186,10 +200,69 @@ impl<'c> Translation<'c> {
                     mk().cast_expr(zeros, mk().path_ty(vec!["i32"]))
                 }))
             }
+            "__builtin_parity" | "__builtin_parityl" | "__builtin_parityll" => {
+                let val = self.convert_expr(ctx.used(), args[0])?;
+                Ok(val.map(|x| {
+                    let ones = mk().method_call_expr(x, "count_ones", vec![] as Vec<Box<Expr>>);
+                    let one = mk().lit_expr(mk().int_lit(1, ""));
+                    let parity = mk().binary_expr(BinOp::BitAnd(Default::default()), ones, one);
+                    mk().cast_expr(parity, mk().path_ty(vec!["i32"]))
+                }))
+            }
+            "__builtin_clrsb" | "__builtin_clrsbl" | "__builtin_clrsbll" => {
+                // The redundant sign bits are the leading zeros of `x ^ (x >> 1)`, less the one
+                // that is the sign bit itself
+                let val = self.convert_expr(ctx.used(), args[0])?;
+                let is_pure = self.ast_context.is_expr_pure(args[0]);
+                val.and_then(|x| {
+                    let (stmts, x) = if is_pure {
+                        (vec![], x)
+                    } else {
+                        let name = self.renamer.borrow_mut().fresh();
+                        let x_let = mk().local_stmt(Box::new(mk().local(
+                            mk().ident_pat(&name),
+                            None as Option<Box<Type>>,
+                            Some(x),
+                        )));
+                        (vec![x_let], mk().ident_expr(name))
+                    };
+                    let one = mk().lit_expr(mk().int_lit(1, ""));
+                    let shifted = mk().binary_expr(BinOp::Shr(Default::default()), x.clone(), one);
+                    let bits = mk().binary_expr(BinOp::BitXor(Default::default()), x, shifted);
+                    let zeros =
+                        mk().method_call_expr(bits, "leading_zeros", vec![] as Vec<Box<Expr>>);
+                    let zeros = mk().cast_expr(zeros, mk().path_ty(vec!["i32"]));
+                    let one = mk().lit_expr(mk().int_lit(1, ""));
+                    let val = mk().binary_expr(BinOp::Sub(Default::default()), zeros, one);
+                    Ok(WithStmts::new(stmts, val))
+                })
+            }
+            "__builtin_rotateleft8"
+            | "__builtin_rotateleft16"
+            | "__builtin_rotateleft32"
+            | "__builtin_rotateleft64"
+            | "__builtin_rotateright8"
+            | "__builtin_rotateright16"
+            | "__builtin_rotateright32"
+            | "__builtin_rotateright64" => {
+                let method = if builtin_name.starts_with("__builtin_rotateleft") {
+                    "rotate_left"
+                } else {
+                    "rotate_right"
+                };
+                let val = self.convert_expr(ctx.used(), args[0])?;
+                let amount = self.convert_expr(ctx.used(), args[1])?;
+                val.and_then(|x| {
+                    Ok(amount.map(|n| {
+                        let n = mk().cast_expr(n, mk().path_ty(vec!["u32"]));
+                        mk().method_call_expr(x, method, vec![n])
+                    }))
+                })
+            }
             "__builtin_bzero" => {
                 let ptr_stmts = self.convert_expr(ctx.used(), args[0])?;
                 let n_stmts = self.convert_expr(ctx.used(), args[1])?;
-                let write_bytes = mk().abs_path_expr(vec!["std", "ptr", "write_bytes"]);
+                let write_bytes = mk().abs_path_expr(vec![std_or_core, "ptr", "write_bytes"]);
                 let zero = mk().lit_expr(mk().int_lit(0, "u8"));
                 ptr_stmts.and_then(|ptr| {
                     Ok(n_stmts.map(|n| mk().call_expr(write_bytes, vec![ptr, zero, n])))
@@ -208,6 +281,9 @@ impl<'c> Translation<'c> {
             | "__builtin_strdup" | "__builtin_strndup" | "__builtin_strlen"
             | "__builtin_strnlen" | "__builtin_strpbrk" | "__builtin_strrchr"
             | "__builtin_strspn" | "__builtin_strstr" => {
+                if self.tcfg.is_wasm() {
+                    return self.convert_wasm_builtin(builtin_name, ctx, args);
+                }
                 self.convert_libc_fns(builtin_name, ctx, args)
             }
 
@@ -273,7 +349,7 @@ impl<'c> Translation<'c> {
                             mk().block(vec![mk().expr_stmt(minus_one)]),
                             Some(mk().lit_expr(mk().int_lit(0, "isize"))),
                         );
-                        let size_t = mk().path_ty(vec!["libc", "size_t"]);
+                        let size_t = self.libc_ty("size_t");
                         mk().cast_expr(if_expr, size_t)
                     }))
                 })
@@ -334,15 +410,30 @@ impl<'c> Translation<'c> {
             }
 
             "__builtin_alloca" => {
+                // Without an allocator, allocations of a constant size are stack arrays
+                let const_size = match self.const_int(args[0]) {
+                    Some(size) if self.tcfg.emit_no_std && size > 0 => Some(size as u128),
+                    _ => None,
+                };
                 let count = self.convert_expr(ctx.used(), args[0])?;
                 count.and_then(|count| {
                     let alloca_name = self.renamer.borrow_mut().fresh();
-                    let zero_elem = mk().lit_expr(mk().int_unsuffixed_lit(0));
+                    let init = match const_size {
+                        Some(size) => mk().repeat_expr(
+                            mk().lit_expr(mk().int_lit(0, "u8")),
+                            mk().lit_expr(mk().int_unsuffixed_lit(size)),
+                        ),
+                        None => vec_expr(
+                            mk().lit_expr(mk().int_unsuffixed_lit(0)),
+                            cast_int(count, "usize", false),
+                            self.tcfg.emit_no_std,
+                        ),
+                    };
                     Ok(WithStmts::new(
                         vec![mk().local_stmt(Box::new(mk().local(
                             mk().mutbl().ident_pat(&alloca_name),
                             None as Option<Box<Type>>,
-                            Some(vec_expr(zero_elem, cast_int(count, "usize", false))),
+                            Some(init),
                         )))],
                         mk().method_call_expr(
                             mk().ident_expr(&alloca_name),
@@ -437,6 +528,174 @@ impl<'c> Translation<'c> {
             "__builtin_ia32_pcmpestris128" => self.convert_simd_builtin(ctx, "_mm_cmpestrs", args),
             "__builtin_ia32_pcmpestriz128" => self.convert_simd_builtin(ctx, "_mm_cmpestrz", args),
 
+            // AVX-512 builtins:
+            "__builtin_ia32_pshufd512" => {
+                self.convert_simd_builtin(ctx, "_mm512_shuffle_epi32", args)
+            }
+            "__builtin_ia32_pshufhw512" => {
+                self.convert_simd_builtin(ctx, "_mm512_shufflehi_epi16", args)
+            }
+            "__builtin_ia32_pshuflw512" => {
+                self.convert_simd_builtin(ctx, "_mm512_shufflelo_epi16", args)
+            }
+            "__builtin_ia32_shufps512" => self.convert_simd_builtin(ctx, "_mm512_shuffle_ps", args),
+            "__builtin_ia32_shufpd512" => self.convert_simd_builtin(ctx, "_mm512_shuffle_pd", args),
+            "__builtin_ia32_shuf_f32x4" => {
+                self.convert_simd_builtin(ctx, "_mm512_shuffle_f32x4", args)
+            }
+            "__builtin_ia32_shuf_f64x2" => {
+                self.convert_simd_builtin(ctx, "_mm512_shuffle_f64x2", args)
+            }
+            "__builtin_ia32_shuf_i32x4" => {
+                self.convert_simd_builtin(ctx, "_mm512_shuffle_i32x4", args)
+            }
+            "__builtin_ia32_shuf_i64x2" => {
+                self.convert_simd_builtin(ctx, "_mm512_shuffle_i64x2", args)
+            }
+            "__builtin_ia32_shuf_f32x4_256" => {
+                self.convert_simd_builtin(ctx, "_mm256_shuffle_f32x4", args)
+            }
+            "__builtin_ia32_shuf_f64x2_256" => {
+                self.convert_simd_builtin(ctx, "_mm256_shuffle_f64x2", args)
+            }
+            "__builtin_ia32_shuf_i32x4_256" => {
+                self.convert_simd_builtin(ctx, "_mm256_shuffle_i32x4", args)
+            }
+            "__builtin_ia32_shuf_i64x2_256" => {
+                self.convert_simd_builtin(ctx, "_mm256_shuffle_i64x2", args)
+            }
+            "__builtin_ia32_palignr512" => {
+                self.convert_simd_builtin(ctx, "_mm512_alignr_epi8", args)
+            }
+            "__builtin_ia32_alignd512" => {
+                self.convert_simd_builtin(ctx, "_mm512_alignr_epi32", args)
+            }
+            "__builtin_ia32_alignq512" => {
+                self.convert_simd_builtin(ctx, "_mm512_alignr_epi64", args)
+            }
+            "__builtin_ia32_alignd256" => {
+                self.convert_simd_builtin(ctx, "_mm256_alignr_epi32", args)
+            }
+            "__builtin_ia32_alignq256" => {
+                self.convert_simd_builtin(ctx, "_mm256_alignr_epi64", args)
+            }
+            "__builtin_ia32_alignd128" => self.convert_simd_builtin(ctx, "_mm_alignr_epi32", args),
+            "__builtin_ia32_alignq128" => self.convert_simd_builtin(ctx, "_mm_alignr_epi64", args),
+            "__builtin_ia32_pslldqi512_byteshift" => {
+                self.convert_simd_builtin(ctx, "_mm512_bslli_epi128", args)
+            }
+            "__builtin_ia32_psrldqi512_byteshift" => {
+                self.convert_simd_builtin(ctx, "_mm512_bsrli_epi128", args)
+            }
+            "__builtin_ia32_permdf512" => {
+                self.convert_simd_builtin(ctx, "_mm512_permutex_pd", args)
+            }
+            "__builtin_ia32_permdi512" => {
+                self.convert_simd_builtin(ctx, "_mm512_permutex_epi64", args)
+            }
+            "__builtin_ia32_vpermilps512" => {
+                self.convert_simd_builtin(ctx, "_mm512_permute_ps", args)
+            }
+            "__builtin_ia32_vpermilpd512" => {
+                self.convert_simd_builtin(ctx, "_mm512_permute_pd", args)
+            }
+            "__builtin_ia32_insertf32x4" => {
+                self.convert_simd_builtin(ctx, "_mm512_insertf32x4", args)
+            }
+            "__builtin_ia32_inserti32x4" => {
+                self.convert_simd_builtin(ctx, "_mm512_inserti32x4", args)
+            }
+            "__builtin_ia32_insertf64x4" => {
+                self.convert_simd_builtin(ctx, "_mm512_insertf64x4", args)
+            }
+            "__builtin_ia32_inserti64x4" => {
+                self.convert_simd_builtin(ctx, "_mm512_inserti64x4", args)
+            }
+            "__builtin_ia32_extractf32x4_mask" => {
+                self.convert_simd_mask_builtin(ctx, "_mm512_extractf32x4_ps", args)
+            }
+            "__builtin_ia32_extracti32x4_mask" => {
+                self.convert_simd_mask_builtin(ctx, "_mm512_extracti32x4_epi32", args)
+            }
+            "__builtin_ia32_extractf64x4_mask" => {
+                self.convert_simd_mask_builtin(ctx, "_mm512_extractf64x4_pd", args)
+            }
+            "__builtin_ia32_extracti64x4_mask" => {
+                self.convert_simd_mask_builtin(ctx, "_mm512_extracti64x4_epi64", args)
+            }
+            "__builtin_ia32_cmpb512_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm512_cmp_epi8_mask", args)
+            }
+            "__builtin_ia32_ucmpb512_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm512_cmp_epu8_mask", args)
+            }
+            "__builtin_ia32_cmpw512_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm512_cmp_epi16_mask", args)
+            }
+            "__builtin_ia32_ucmpw512_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm512_cmp_epu16_mask", args)
+            }
+            "__builtin_ia32_cmpd512_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm512_cmp_epi32_mask", args)
+            }
+            "__builtin_ia32_ucmpd512_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm512_cmp_epu32_mask", args)
+            }
+            "__builtin_ia32_cmpq512_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm512_cmp_epi64_mask", args)
+            }
+            "__builtin_ia32_ucmpq512_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm512_cmp_epu64_mask", args)
+            }
+            "__builtin_ia32_cmpb256_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm256_cmp_epi8_mask", args)
+            }
+            "__builtin_ia32_ucmpb256_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm256_cmp_epu8_mask", args)
+            }
+            "__builtin_ia32_cmpw256_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm256_cmp_epi16_mask", args)
+            }
+            "__builtin_ia32_ucmpw256_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm256_cmp_epu16_mask", args)
+            }
+            "__builtin_ia32_cmpd256_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm256_cmp_epi32_mask", args)
+            }
+            "__builtin_ia32_ucmpd256_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm256_cmp_epu32_mask", args)
+            }
+            "__builtin_ia32_cmpq256_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm256_cmp_epi64_mask", args)
+            }
+            "__builtin_ia32_ucmpq256_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm256_cmp_epu64_mask", args)
+            }
+            "__builtin_ia32_cmpb128_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm_cmp_epi8_mask", args)
+            }
+            "__builtin_ia32_ucmpb128_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm_cmp_epu8_mask", args)
+            }
+            "__builtin_ia32_cmpw128_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm_cmp_epi16_mask", args)
+            }
+            "__builtin_ia32_ucmpw128_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm_cmp_epu16_mask", args)
+            }
+            "__builtin_ia32_cmpd128_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm_cmp_epi32_mask", args)
+            }
+            "__builtin_ia32_ucmpd128_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm_cmp_epu32_mask", args)
+            }
+            "__builtin_ia32_cmpq128_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm_cmp_epi64_mask", args)
+            }
+            "__builtin_ia32_ucmpq128_mask" => {
+                self.convert_simd_cmp_mask_builtin(ctx, "_mm_cmp_epu64_mask", args)
+            }
+
             "__sync_val_compare_and_swap_1"
             | "__sync_val_compare_and_swap_2"
             | "__sync_val_compare_and_swap_4"
@@ -609,11 +868,141 @@ impl<'c> Translation<'c> {
                     )
                 })
             }
+            "__atomic_thread_fence" | "__atomic_signal_fence" => {
+                let order = self.atomic_memordering(args[0]);
+                // A relaxed fence has no effect
+                if order == Ordering::Relaxed {
+                    return self.convert_expr(ctx.unused(), args[0]);
+                }
+
+                self.use_feature("core_intrinsics");
+
+                let intrinsic_name = if builtin_name == "__atomic_thread_fence" {
+                    "atomic_fence"
+                } else {
+                    "atomic_singlethreadfence"
+                };
+                let intrinsic_name = Self::atomic_intrinsic_name(intrinsic_name, order);
+                let atomic_func =
+                    mk().abs_path_expr(vec![std_or_core, "intrinsics", &intrinsic_name]);
+                let call_expr = mk().call_expr(atomic_func, vec![] as Vec<Box<Expr>>);
+                self.convert_side_effects_expr(
+                    ctx,
+                    WithStmts::new_val(call_expr),
+                    "Builtin is not supposed to be used",
+                )
+            }
+
+            "__atomic_test_and_set" | "__atomic_clear" => {
+                let order = self.atomic_memordering(args[1]);
+                let is_clear = builtin_name == "__atomic_clear";
+                let intrinsic_name = match (is_clear, order) {
+                    (true, Ordering::Acquire) | (true, Ordering::AcqRel) => {
+                        return Err(format_translation_err!(
+                            self.ast_context.display_loc(&self.ast_context[args[1]].loc),
+                            "Invalid memory ordering for __atomic_clear",
+                        ));
+                    }
+                    (true, order) => Self::atomic_intrinsic_name("atomic_store", order),
+                    (false, order) => Self::atomic_intrinsic_name("atomic_xchg", order),
+                };
+
+                self.use_feature("core_intrinsics");
+
+                // Emit `atomic_xchg(arg0 as *mut u8, 1) != 0` or `atomic_store(arg0 as *mut u8, 0)`
+                let atomic_func =
+                    mk().abs_path_expr(vec![std_or_core, "intrinsics", &intrinsic_name]);
+                let arg0 = self.convert_expr(ctx.used(), args[0])?;
+                arg0.and_then(|arg0| {
+                    let ptr = mk().cast_expr(arg0, mk().mutbl().ptr_ty(mk().path_ty(vec!["u8"])));
+                    let val = mk().lit_expr(mk().int_lit(if is_clear { 0 } else { 1 }, ""));
+                    let call_expr = mk().call_expr(atomic_func, vec![ptr, val]);
+                    let call_expr = if is_clear {
+                        call_expr
+                    } else {
+                        mk().binary_expr(
+                            BinOp::Ne(Default::default()),
+                            call_expr,
+                            mk().lit_expr(mk().int_lit(0, "")),
+                        )
+                    };
+                    self.convert_side_effects_expr(
+                        ctx,
+                        WithStmts::new_val(call_expr),
+                        "Builtin is not supposed to be used",
+                    )
+                })
+            }
+
+            // Objects are assumed to be suitably aligned, so whether atomic operations on them
+            // are lock-free only depends on their size.
+            "__atomic_always_lock_free" | "__atomic_is_lock_free" => {
+                let size = self.convert_expr(ctx.used(), args[0])?;
+                let ptr = self.convert_expr(ctx.unused(), args[1])?;
+                ptr.and_then(|_| {
+                    size.and_then(|size| {
+                        // Emit `size.is_power_of_two() && size <= size_of::<usize>()`
+                        let size_name = self.renamer.borrow_mut().fresh();
+                        let size_let = mk().local_stmt(Box::new(mk().local(
+                            mk().ident_pat(&size_name),
+                            None as Option<Box<Type>>,
+                            Some(mk().cast_expr(size, mk().path_ty(vec!["usize"]))),
+                        )));
+                        let is_power_of_two = mk().method_call_expr(
+                            mk().ident_expr(&size_name),
+                            "is_power_of_two",
+                            vec![] as Vec<Box<Expr>>,
+                        );
+                        let max_size = self
+                            .compute_size_of_ty(mk().path_ty(vec!["usize"]))?
+                            .to_expr();
+                        let fits = mk().binary_expr(
+                            BinOp::Le(Default::default()),
+                            mk().ident_expr(&size_name),
+                            max_size,
+                        );
+                        let val =
+                            mk().binary_expr(BinOp::And(Default::default()), is_power_of_two, fits);
+                        self.convert_side_effects_expr(
+                            ctx,
+                            WithStmts::new(vec![size_let], val),
+                            "Builtin is not supposed to be used",
+                        )
+                    })
+                })
+            }
+
             // There's currently no way to replicate this functionality in Rust, so we just
             // pass the ptr input param in its place.
             "__builtin_assume_aligned" => Ok(self.convert_expr(ctx.used(), args[0])?),
             // Skip over, there's no way to implement it in Rust
             "__builtin_unwind_init" => Ok(WithStmts::new_val(self.panic_or_err("no value"))),
+            "__builtin_frame_address" | "__builtin_return_address" => {
+                let ret_ty = match self.ast_context[decl_id].kind {
+                    CDeclKind::Function { typ, .. } => {
+                        match self.ast_context.resolve_type(typ).kind {
+                            CTypeKind::Function(ret, ..) => self.convert_type(ret.ctype)?,
+                            _ => {
+                                return Err(TranslationError::generic("Builtin is not a function"))
+                            }
+                        }
+                    }
+                    _ => unreachable!(),
+                };
+                if !self.tcfg.frame_address_intrinsics {
+                    diag!(
+                        Diagnostic::Builtins,
+                        at self.ast_context.display_loc(src_loc),
+                        "{} is translated to a null pointer; use --frame-address-intrinsics \
+                         to call the LLVM intrinsic instead",
+                        builtin_name
+                    );
+                    // The level must be a constant, so there are no side effects to keep
+                    let zero = mk().lit_expr(mk().int_unsuffixed_lit(0));
+                    return Ok(WithStmts::new_val(mk().cast_expr(zero, ret_ty)));
+                }
+                self.convert_frame_address(ctx, builtin_name, args[0], ret_ty)
+            }
             "__builtin_unreachable" => Ok(WithStmts::new(
                 vec![mk().semi_stmt(mk().mac_expr(mk().mac(
                     vec!["unreachable"],
@@ -623,27 +1012,8 @@ impl<'c> Translation<'c> {
                 self.panic_or_err("unreachable stub"),
             )),
 
-            "__builtin_rotateleft8"
-            | "__builtin_rotateleft16"
-            | "__builtin_rotateleft32"
-            | "__builtin_rotateleft64" => {
-                self.use_feature("core_intrinsics");
-
-                // Emit `rotate_left(arg0, arg1)`
-                let rotate_func =
-                    mk().abs_path_expr(vec![std_or_core, "intrinsics", "rotate_left"]);
-                let arg0 = self.convert_expr(ctx.used(), args[0])?;
-                let arg1 = self.convert_expr(ctx.used(), args[1])?;
-                arg0.and_then(|arg0| {
-                    arg1.and_then(|arg1| {
-                        let call_expr = mk().call_expr(rotate_func, vec![arg0, arg1]);
-                        self.convert_side_effects_expr(
-                            ctx,
-                            WithStmts::new_val(call_expr),
-                            "Builtin is not supposed to be used",
-                        )
-                    })
-                })
+            _ if builtin_name.starts_with("__builtin_neon_") => {
+                self.convert_neon_builtin(ctx, builtin_name, args)
             }
 
             _ => Err(format_translation_err!(
@@ -654,6 +1024,56 @@ impl<'c> Translation<'c> {
         }
     }
 
+    /// Call the LLVM intrinsic behind `__builtin_frame_address` or `__builtin_return_address`
+    /// through a block-local declaration:
+    ///
+    /// ```ignore
+    /// {
+    ///     extern "C" {
+    ///         #[link_name = "llvm.frameaddress.p0i8"]
+    ///         fn frame_address(level: i32) -> *mut i8;
+    ///     }
+    ///     frame_address(0) as *mut libc::c_void
+    /// }
+    /// ```
+    fn convert_frame_address(
+        &self,
+        ctx: ExprContext,
+        builtin_name: &str,
+        level: CExprId,
+        ret_ty: Box<Type>,
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        self.use_feature("link_llvm_intrinsics");
+        let (intrinsic, name) = match builtin_name {
+            "__builtin_frame_address" => ("llvm.frameaddress.p0i8", "frame_address"),
+            _ => ("llvm.returnaddress", "return_address"),
+        };
+        let i32_ty = mk().path_ty(vec!["i32"]);
+        let decl = mk().fn_decl(
+            name,
+            vec![mk().arg(i32_ty.clone(), mk().ident_pat("level"))],
+            None,
+            ReturnType::Type(
+                Default::default(),
+                mk().mutbl().ptr_ty(mk().path_ty(vec!["i8"])),
+            ),
+        );
+        let intrinsic_decl = mk().str_attr("link_name", intrinsic).fn_foreign_item(decl);
+        let extern_block = mk().extern_("C").foreign_items(vec![intrinsic_decl]);
+
+        let level = self.convert_expr(ctx.used(), level)?;
+        Ok(level.map(|level| {
+            let call = mk().call_expr(
+                mk().path_expr(vec![name]),
+                vec![mk().cast_expr(level, i32_ty)],
+            );
+            mk().block_expr(mk().block(vec![
+                mk().item_stmt(extern_block),
+                mk().expr_stmt(mk().cast_expr(call, ret_ty)),
+            ]))
+        }))
+    }
+
     // This translation logic handles converting code that uses
     // https://gcc.gnu.org/onlinedocs/gcc/Integer-Overflow-Builtins.html
     fn convert_overflow_arith(
@@ -719,7 +1139,7 @@ impl<'c> Translation<'c> {
             let len = args
                 .next()
                 .ok_or("Missing len argument to convert_libc_fns")?;
-            let size_t = mk().path_ty(vec!["libc", "size_t"]);
+            let size_t = self.libc_ty("size_t");
             let len1 = mk().cast_expr(len, size_t);
             let mem_expr = mk().call_expr(mem, vec![dst, c, len1]);
 
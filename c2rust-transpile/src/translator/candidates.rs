@@ -0,0 +1,178 @@
+//! This module implements `--candidate-function`, an experimental mode for comparing translation
+//! strategies on selected functions. Each selected function is translated twice, and the two
+//! versions are emitted side by side under `cfg` flags:
+//!
+//! ```ignore
+//! #[cfg(not(c2rust_candidate))]
+//! pub unsafe extern "C" fn count_digits(mut s: *const c_char) -> c_int { /* literal */ }
+//! #[cfg(c2rust_candidate)]
+//! pub unsafe extern "C" fn count_digits(mut s: *const c_char) -> c_int { /* idiomatic */ }
+//! ```
+//!
+//! The literal version is translated with the options given on the command line. The idiomatic
+//! version additionally translates `<ctype.h>` calls into ASCII methods (as with
+//! `--ascii-char-classes`), counted loops into range loops (as with `--range-loops`) and omits
+//! inferable type annotations on locals (as with `--reduce-type-annotations`). Building with `RUSTFLAGS="--cfg c2rust_candidate"` selects the
+//! idiomatic versions, so both can be tested and benchmarked against the same callers.
+//!
+//! A report of the candidates is written next to the translated module as
+//! `<module>.candidates.json`:
+//!
+//! ```json
+//! [{"function": "count_digits", "variants": [
+//!     {"strategy": "literal", "cfg": "not(c2rust_candidate)", "lines": 14},
+//!     {"strategy": "idiomatic", "cfg": "c2rust_candidate", "lines": 11}]}]
+//! ```
+
+use super::*;
+
+use serde_derive::Serialize;
+
+/// The `cfg` flag selecting the idiomatic versions of candidate functions
+const CANDIDATE_CFG: &str = "c2rust_candidate";
+
+/// A function translated into multiple candidate versions
+#[derive(Serialize)]
+pub struct CandidateReport {
+    function: String,
+    variants: Vec<CandidateVariant>,
+}
+
+/// One translated version of a candidate function
+#[derive(Serialize)]
+struct CandidateVariant {
+    strategy: &'static str,
+    cfg: String,
+    /// Lines of Rust in the pretty-printed version
+    lines: usize,
+}
+
+impl<'c> Translation<'c> {
+    /// Find the functions selected by `--candidate-function`, which are translated by
+    /// `convert_candidate_fn`.
+    pub fn find_candidate_fns(&self) -> HashSet<CDeclId> {
+        let mut candidates = HashSet::new();
+        if self.tcfg.candidate_functions.is_empty() {
+            return candidates;
+        }
+
+        let mut found = HashSet::new();
+        for (&decl_id, decl) in self.ast_context.iter_decls() {
+            if let CDeclKind::Function {
+                ref name,
+                body: Some(_),
+                ..
+            } = decl.kind
+            {
+                if self.tcfg.candidate_functions.contains(name) {
+                    candidates.insert(decl_id);
+                    found.insert(name.as_str());
+                }
+            }
+        }
+
+        for name in &self.tcfg.candidate_functions {
+            if !found.contains(name.as_str()) {
+                warn!("No definition of candidate function {} found", name);
+            }
+        }
+
+        candidates
+    }
+
+    /// Whether calls to `<ctype.h>` functions are translated into ASCII methods in the function
+    /// being translated.
+    pub fn ascii_char_classes(&self) -> bool {
+        self.tcfg.ascii_char_classes || self.idiomatic_candidate.get()
+    }
+
+    /// Whether counted `for` loops are translated into range loops in the function being
+    /// translated.
+    pub fn range_loops(&self) -> bool {
+        self.tcfg.range_loops || self.idiomatic_candidate.get()
+    }
+
+    /// Whether inferable type annotations on locals are omitted in the function being translated.
+    pub fn reduce_type_annotations(&self) -> bool {
+        self.fn_tcfg().reduce_type_annotations || self.idiomatic_candidate.get()
+    }
+
+    /// Translate a function selected by `--candidate-function` into its literal and idiomatic
+    /// versions. If only the idiomatic translation fails, the literal version is emitted alone.
+    pub fn convert_candidate_fn(
+        &self,
+        ctx: ExprContext,
+        decl_id: CDeclId,
+    ) -> Result<ConvertedDecl, TranslationError> {
+        let name = match self.ast_context[decl_id].kind {
+            CDeclKind::Function { ref name, .. } => name.clone(),
+            _ => return self.convert_decl(ctx, decl_id),
+        };
+
+        let literal = self.convert_decl(ctx, decl_id)?;
+
+        self.idiomatic_candidate.set(true);
+        let idiomatic = self.convert_decl(ctx, decl_id);
+        self.idiomatic_candidate.set(false);
+
+        let (mut literal, mut idiomatic) = match (literal, idiomatic) {
+            (ConvertedDecl::Item(literal), Ok(ConvertedDecl::Item(idiomatic))) => {
+                (literal, idiomatic)
+            }
+            (literal, Err(e)) => {
+                warn!("Failed to translate idiomatic candidate of {}: {}", name, e);
+                return Ok(literal);
+            }
+            (literal, _) => return Ok(literal),
+        };
+
+        let not_candidate = mk().meta_list(
+            "not",
+            vec![mk().nested_meta_item(mk().meta_path(CANDIDATE_CFG))],
+        );
+        add_cfg_attr(&mut literal, mk().nested_meta_item(not_candidate));
+        add_cfg_attr(
+            &mut idiomatic,
+            mk().nested_meta_item(mk().meta_path(CANDIDATE_CFG)),
+        );
+
+        self.candidate_reports.borrow_mut().push(CandidateReport {
+            function: name,
+            variants: vec![
+                CandidateVariant {
+                    strategy: "literal",
+                    cfg: format!("not({})", CANDIDATE_CFG),
+                    lines: printed_lines(&literal),
+                },
+                CandidateVariant {
+                    strategy: "idiomatic",
+                    cfg: CANDIDATE_CFG.to_string(),
+                    lines: printed_lines(&idiomatic),
+                },
+            ],
+        });
+
+        Ok(ConvertedDecl::Items(vec![literal, idiomatic]))
+    }
+
+    /// The name and contents of the report on candidate functions, if any were translated.
+    pub fn candidate_report(&self) -> Option<(String, String)> {
+        let reports = self.candidate_reports.borrow();
+        if reports.is_empty() {
+            return None;
+        }
+
+        let file_name = format!("{}.candidates.json", self.main_module_name);
+        let contents = serde_json::to_string_pretty(&*reports).expect("Unable to encode report");
+        Some((file_name, contents + "\n"))
+    }
+}
+
+fn printed_lines(item: &Item) -> usize {
+    let printed = pprust::to_string(|| syn::File {
+        shebang: None,
+        attrs: vec![],
+        items: vec![item.clone()],
+    });
+    printed.lines().count()
+}
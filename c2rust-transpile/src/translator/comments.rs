@@ -94,17 +94,26 @@ impl<'c> NodeVisitor for CommentLocator<'c> {
                     id = SomeId::Decl(*canonical_decl);
                 }
             }
-            if let Some(existing) = self.spans.get(&id) {
-                let new_pos = self.comment_store.extend_existing_comments(
-                    &comments,
-                    Some(existing.lo()),
-                    //CommentStyle::Isolated,
-                );
+            // Doxygen comments become doc comments of the items, fields and variants that
+            // declarations translate to
+            let documented = match id {
+                SomeId::Decl(decl_id) => is_documented(&self.ast_context[decl_id].kind),
+                _ => false,
+            };
+            let existing_pos = self.spans.get(&id).map(|span| span.lo());
+            let pos = if documented {
+                self.comment_store
+                    .extend_existing_doc_comments(&comments, existing_pos)
+            } else {
+                self.comment_store
+                    .extend_existing_comments(&comments, existing_pos)
+            };
+            if existing_pos.is_some() {
                 debug!(
                     "Attaching more comments {:?} to id {:?} at pos {:?}",
-                    comments, id, new_pos
+                    comments, id, pos
                 );
-            } else if let Some(pos) = self.comment_store.add_comments(&comments) {
+            } else if let Some(pos) = pos {
                 debug!(
                     "Attaching comments {:?} to id {:?} at pos {:?}",
                     comments, id, pos
@@ -155,6 +164,26 @@ impl<'c> NodeVisitor for CommentLocator<'c> {
     }
 }
 
+/// Whether a declaration translates to something that can have a doc comment
+fn is_documented(kind: &CDeclKind) -> bool {
+    match *kind {
+        CDeclKind::Function { .. }
+        | CDeclKind::Typedef { .. }
+        | CDeclKind::Enum { .. }
+        | CDeclKind::EnumConstant { .. }
+        | CDeclKind::Struct { .. }
+        | CDeclKind::Union { .. }
+        | CDeclKind::Field { .. }
+        | CDeclKind::MacroObject { .. } => true,
+        CDeclKind::Variable {
+            has_static_duration,
+            has_thread_duration,
+            ..
+        } => has_static_duration || has_thread_duration,
+        _ => false,
+    }
+}
+
 impl<'c> Translation<'c> {
     /// Create spans for each C AST node that has a comment attached to it.
     pub fn locate_comments(&mut self) {
@@ -0,0 +1,273 @@
+//! This module lowers address constants in static initializers into Rust constant expressions.
+//! C allows statics to be initialized with the address of an element or field of another
+//! static, possibly offset by a constant:
+//!
+//! ```c
+//! int *p = &arr[4];               // or `arr + 4`
+//! char *q = (char *)&s.field;
+//! const char *r = "hello" + 2;
+//! ```
+//!
+//! These are translated into the addresses of the projected places, cast to the pointer type,
+//! which are accepted in static initializers:
+//!
+//! ```ignore
+//! pub static mut p: *mut c_int = unsafe { addr_of!(arr[4]) as *const _ as *mut c_int };
+//! pub static mut q: *mut c_char = unsafe { addr_of!(s.field) as *const _ as *mut c_char };
+//! pub static mut r: *const c_char = &b"hello\0"[2] as *const _ as *const c_char;
+//! ```
+//!
+//! Address constants of other forms, such as one-past-the-end pointers, are still computed at
+//! startup by `run_static_initializers`.
+
+use super::*;
+
+/// A place in static memory, whose address is a constant
+struct ConstPlace {
+    expr: Box<Expr>,
+    ctype: CTypeId,
+    /// Whether the place is an element or field of an object, rather than the whole object
+    projected: bool,
+    /// Whether the place is inside a Rust static, which must be accessed in an `unsafe` block
+    is_unsafe: bool,
+}
+
+impl<'c> Translation<'c> {
+    /// Whether `expr_id` is an address constant that `convert_const_address` translates.
+    pub fn is_const_address(&self, expr_id: CExprId) -> bool {
+        self.const_address_place(ExprContext::default().static_(), expr_id)
+            .map_or(false, |place| place.is_some())
+    }
+
+    /// Translate the address of an element or field of a static, or of a string literal, into a
+    /// constant expression. Returns `None` if `expr_id` isn't such an address.
+    pub fn convert_const_address(
+        &self,
+        ctx: ExprContext,
+        expr_id: CExprId,
+    ) -> Result<Option<WithStmts<Box<Expr>>>, TranslationError> {
+        let ty = match self.ast_context[expr_id].kind.get_qual_type() {
+            Some(ty) => ty,
+            None => return Ok(None),
+        };
+        let place = match self.const_address_place(ctx, expr_id)? {
+            Some(place) => place,
+            None => return Ok(None),
+        };
+
+        let target_ty = self.convert_type(ty.ctype)?;
+        // Places in statics are `static mut`s, which can't be borrowed
+        let addr = if place.is_unsafe {
+            self.static_addr_of(place.expr)
+        } else {
+            mk().addr_of_expr(place.expr)
+        };
+        let addr = mk().cast_expr(addr, mk().ptr_ty(mk().infer_ty()));
+        let addr = mk().cast_expr(addr, target_ty);
+        Ok(Some(if place.is_unsafe {
+            WithStmts::new_unsafe_val(addr)
+        } else {
+            WithStmts::new_val(addr)
+        }))
+    }
+
+    /// The projected place that the pointer `expr_id` points to, if it is an address constant.
+    /// Addresses of whole objects are already translated as constants by `convert_expr`.
+    fn const_address_place(
+        &self,
+        ctx: ExprContext,
+        expr_id: CExprId,
+    ) -> Result<Option<ConstPlace>, TranslationError> {
+        let ty = match self.ast_context[expr_id].kind.get_qual_type() {
+            Some(ty) => ty,
+            None => return Ok(None),
+        };
+        if !self.ast_context.resolve_type(ty.ctype).kind.is_pointer()
+            || self.ast_context.is_function_pointer(ty.ctype)
+        {
+            return Ok(None);
+        }
+
+        Ok(self
+            .const_pointer_place(ctx, expr_id)?
+            .filter(|place| place.projected))
+    }
+
+    /// The place that the pointer `expr_id` points to
+    fn const_pointer_place(
+        &self,
+        ctx: ExprContext,
+        expr_id: CExprId,
+    ) -> Result<Option<ConstPlace>, TranslationError> {
+        use c_ast::BinOp::{Add, Subtract};
+
+        match self.ast_context[expr_id].kind {
+            CExprKind::ImplicitCast(_, expr, CastKind::ArrayToPointerDecay, _, _) => {
+                self.const_element_place(ctx, expr, 0, false)
+            }
+
+            CExprKind::ImplicitCast(_, expr, CastKind::BitCast | CastKind::NoOp, _, _)
+            | CExprKind::ExplicitCast(_, expr, CastKind::BitCast | CastKind::NoOp, _, _) => {
+                self.const_pointer_place(ctx, expr)
+            }
+
+            CExprKind::Unary(_, c_ast::UnOp::AddressOf, expr, _) => self.const_place(ctx, expr),
+
+            CExprKind::Binary(_, op @ (Add | Subtract), lhs, rhs, _, _) => {
+                let (ptr, offset) = match (self.const_int(lhs), self.const_int(rhs)) {
+                    (None, Some(offset)) => (lhs, offset),
+                    (Some(offset), None) if matches!(op, Add) => (rhs, offset),
+                    _ => return Ok(None),
+                };
+                let offset = if matches!(op, Subtract) {
+                    -offset
+                } else {
+                    offset
+                };
+
+                // Only decayed arrays are offset, so that the result is an element of the array
+                match self.ast_context[ptr].kind {
+                    CExprKind::ImplicitCast(_, array, CastKind::ArrayToPointerDecay, _, _) => {
+                        self.const_element_place(ctx, array, offset, true)
+                    }
+                    _ => Ok(None),
+                }
+            }
+
+            _ => Ok(None),
+        }
+    }
+
+    /// The element `index` of the array place `array_id`
+    fn const_element_place(
+        &self,
+        ctx: ExprContext,
+        array_id: CExprId,
+        index: i128,
+        projected: bool,
+    ) -> Result<Option<ConstPlace>, TranslationError> {
+        let array = match self.const_place(ctx, array_id)? {
+            Some(array) => array,
+            None => return Ok(None),
+        };
+        let elt = match self.ast_context.resolve_type(array.ctype).kind {
+            CTypeKind::ConstantArray(elt, len) if index >= 0 && (index as usize) < len => elt,
+            // One-past-the-end and out of bounds pointers have no place to refer to
+            _ => return Ok(None),
+        };
+
+        let index = mk().lit_expr(mk().int_unsuffixed_lit(index as u128));
+        Ok(Some(ConstPlace {
+            expr: mk().index_expr(array.expr, index),
+            ctype: elt,
+            projected: projected || array.projected,
+            is_unsafe: array.is_unsafe,
+        }))
+    }
+
+    /// The place that the lvalue `expr_id` refers to, if it is in static memory
+    fn const_place(
+        &self,
+        ctx: ExprContext,
+        expr_id: CExprId,
+    ) -> Result<Option<ConstPlace>, TranslationError> {
+        match self.ast_context[expr_id].kind {
+            CExprKind::DeclRef(ty, decl_id, _) => match self.ast_context[decl_id].kind {
+                CDeclKind::Variable {
+                    has_static_duration: true,
+                    ..
+                } => Ok(self
+                    .convert_expr(ctx, expr_id)?
+                    .to_pure_expr()
+                    .map(|expr| ConstPlace {
+                        expr,
+                        ctype: ty.ctype,
+                        projected: false,
+                        is_unsafe: true,
+                    })),
+                _ => Ok(None),
+            },
+
+            CExprKind::Literal(ty, CLiteral::String(ref bytes, 1)) => {
+                let mut bytes = bytes.to_owned();
+                bytes.push(0);
+                Ok(Some(ConstPlace {
+                    expr: mk().lit_expr(bytes),
+                    ctype: ty.ctype,
+                    projected: false,
+                    is_unsafe: false,
+                }))
+            }
+
+            CExprKind::Member(_, base, field_id, MemberKind::Dot, _) => {
+                let field_ty = match self.ast_context[field_id].kind {
+                    CDeclKind::Field {
+                        typ,
+                        bitfield_width: None,
+                        ..
+                    } => typ,
+                    _ => return Ok(None),
+                };
+                // Members of unions and tagged unions aren't plain fields in Rust
+                let record_id = self.ast_context.parents[&field_id];
+                if matches!(self.ast_context[record_id].kind, CDeclKind::Union { .. })
+                    || self.tagged_unions.contains_key(&record_id)
+                {
+                    return Ok(None);
+                }
+
+                let base = match self.const_place(ctx, base)? {
+                    Some(base) => base,
+                    None => return Ok(None),
+                };
+                let mut expr = base.expr;
+                if self.ast_context.has_inner_struct_decl(record_id) {
+                    expr = mk().anon_field_expr(expr, 0);
+                }
+                let field_name = self
+                    .type_converter
+                    .borrow()
+                    .resolve_field_name(None, field_id)
+                    .unwrap();
+
+                Ok(Some(ConstPlace {
+                    expr: mk().field_expr(expr, field_name),
+                    ctype: field_ty.ctype,
+                    projected: true,
+                    is_unsafe: base.is_unsafe,
+                }))
+            }
+
+            CExprKind::ArraySubscript(_, lhs, rhs, _) => {
+                let (ptr, index) = match (self.const_int(lhs), self.const_int(rhs)) {
+                    (None, Some(index)) => (lhs, index),
+                    (Some(index), None) => (rhs, index),
+                    _ => return Ok(None),
+                };
+                match self.ast_context[ptr].kind {
+                    CExprKind::ImplicitCast(_, array, CastKind::ArrayToPointerDecay, _, _) => {
+                        self.const_element_place(ctx, array, index, true)
+                    }
+                    _ => Ok(None),
+                }
+            }
+
+            _ => Ok(None),
+        }
+    }
+
+    /// The value of an integer constant expression, if it is a literal or was folded by clang
+    pub fn const_int(&self, expr_id: CExprId) -> Option<i128> {
+        match self.ast_context[expr_id].kind {
+            CExprKind::Literal(_, CLiteral::Integer(value, _)) => Some(value as i128),
+            CExprKind::ConstantExpr(_, _, Some(ConstIntExpr::U(value))) => Some(value as i128),
+            CExprKind::ConstantExpr(_, _, Some(ConstIntExpr::I(value))) => Some(value as i128),
+            CExprKind::ConstantExpr(_, expr, None)
+            | CExprKind::ImplicitCast(_, expr, CastKind::IntegralCast, _, _) => {
+                self.const_int(expr)
+            }
+            CExprKind::Unary(_, c_ast::UnOp::Negate, expr, _) => self.const_int(expr).map(|v| -v),
+            _ => None,
+        }
+    }
+}
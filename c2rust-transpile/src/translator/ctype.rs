@@ -0,0 +1,145 @@
+//! This module translates calls to the `<ctype.h>` character classification and case conversion
+//! functions into the ASCII methods on `u8` (`--ascii-char-classes`).
+//!
+//! The libc functions consult the current locale, while the `u8` methods only know about ASCII.
+//! The classifications agree on ASCII characters in the locales in common use, so a
+//! classification of a character literal is translated into a direct method call. Any other
+//! argument is checked at runtime and falls back to the libc function outside of the ASCII
+//! range, which also covers `EOF`.
+//!
+//! Case conversions may differ on ASCII characters too: in single-byte Turkish locales,
+//! `toupper('i')` is a dotted capital I rather than `'I'`. `toupper` and `tolower` therefore
+//! always check their argument at runtime, even if it is a literal, and fall back to libc for
+//! `'i'` and `'I'` as well.
+//!
+//! Note that some C libraries implement these functions as macros rather than functions, in
+//! which case there is no call left to translate.
+
+use super::*;
+
+/// `<ctype.h>` functions and their `u8` equivalents. `isspace` is left out because
+/// `u8::is_ascii_whitespace` does not include vertical tab, and `isprint` because there is no
+/// equivalent method.
+static CTYPE_METHODS: &[(&str, &str)] = &[
+    ("isalnum", "is_ascii_alphanumeric"),
+    ("isalpha", "is_ascii_alphabetic"),
+    ("iscntrl", "is_ascii_control"),
+    ("isdigit", "is_ascii_digit"),
+    ("isgraph", "is_ascii_graphic"),
+    ("islower", "is_ascii_lowercase"),
+    ("ispunct", "is_ascii_punctuation"),
+    ("isupper", "is_ascii_uppercase"),
+    ("isxdigit", "is_ascii_hexdigit"),
+    ("tolower", "to_ascii_lowercase"),
+    ("toupper", "to_ascii_uppercase"),
+];
+
+impl<'c> Translation<'c> {
+    /// Try to translate a call to a `<ctype.h>` function. Returns `None` if `callee` is not a
+    /// direct reference to one of the supported functions.
+    pub fn convert_ctype_call(
+        &self,
+        ctx: ExprContext,
+        callee: CExprId,
+        args: &[CExprId],
+    ) -> Result<Option<WithStmts<Box<Expr>>>, TranslationError> {
+        let func = match self.ast_context[callee].kind {
+            CExprKind::ImplicitCast(_, func, CastKind::FunctionToPointerDecay, _, _) => func,
+            _ => return Ok(None),
+        };
+        let (name, ret_ty) = match self.ast_context[func].kind {
+            CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[decl_id].kind {
+                CDeclKind::Function {
+                    ref name,
+                    body: None,
+                    typ,
+                    ..
+                } => match self.ast_context.resolve_type(typ).kind {
+                    CTypeKind::Function(ret, ..) => (name, ret),
+                    _ => return Ok(None),
+                },
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        let method = match CTYPE_METHODS.iter().find(|m| m.0 == name.as_str()) {
+            Some(&(_, method)) => method,
+            None => return Ok(None),
+        };
+        let arg = match *args {
+            [arg] => arg,
+            _ => return Ok(None),
+        };
+        let ret_ty = self.convert_type(ret_ty.ctype)?;
+        let ascii_method = |byte: Box<Expr>| {
+            let res = mk().method_call_expr(byte, method, Vec::<Box<Expr>>::new());
+            mk().cast_expr(res, ret_ty.clone())
+        };
+
+        // The case of `'i'` and `'I'` depends on the locale
+        let is_case_conversion = matches!(name.as_str(), "tolower" | "toupper");
+
+        // Character literals are known to be ASCII, so their classification doesn't need a
+        // fallback
+        let mut lit_id = arg;
+        let ascii_lit = loop {
+            match self.ast_context[lit_id].kind {
+                CExprKind::ImplicitCast(_, e, CastKind::IntegralCast, _, _)
+                | CExprKind::Paren(_, e) => lit_id = e,
+                CExprKind::Literal(_, CLiteral::Character(c)) if c < 0x80 => break Some(c as u8),
+                _ => break None,
+            }
+        }
+        .filter(|_| !is_case_conversion);
+
+        let val = if let Some(c) = ascii_lit {
+            WithStmts::new_val(ascii_method(mk().lit_expr(c)))
+        } else {
+            // match c {
+            //     c @ 0..=127 => (c as u8).is_ascii_digit() as c_int,
+            //     c => isdigit(c),
+            // }
+            //
+            // with a guard `if c != 73 && c != 105` on the first arm for case conversions
+            let callee = self.convert_expr(ctx.used(), func)?;
+            let arg = self.convert_expr(ctx.used(), arg)?;
+            callee.and_then(|callee| -> Result<_, TranslationError> {
+                Ok(arg.map(|arg| {
+                    let ascii_pat = mk().ident_binding_pat(
+                        "c",
+                        mk().range_pat(
+                            mk().lit_expr(mk().int_unsuffixed_lit(0)),
+                            mk().lit_expr(mk().int_unsuffixed_lit(0x7f)),
+                        ),
+                    );
+                    let guard = if is_case_conversion {
+                        let not = |c: char| {
+                            mk().binary_expr(
+                                BinOp::Ne(Default::default()),
+                                mk().ident_expr("c"),
+                                mk().lit_expr(mk().int_unsuffixed_lit(c as u128)),
+                            )
+                        };
+                        Some(mk().binary_expr(BinOp::And(Default::default()), not('I'), not('i')))
+                    } else {
+                        None
+                    };
+                    let byte = mk().cast_expr(mk().ident_expr("c"), mk().path_ty(vec!["u8"]));
+                    let fallback = mk().call_expr(callee, vec![mk().ident_expr("c")]);
+                    let arms = vec![
+                        mk().arm(ascii_pat, guard, ascii_method(byte)),
+                        mk().arm(mk().ident_pat("c"), None, fallback),
+                    ];
+                    mk().match_expr(arg, arms)
+                }))
+            })?
+        };
+
+        self.convert_side_effects_expr(
+            ctx,
+            val,
+            "Function call expression is not supposed to be used",
+        )
+        .map(Some)
+    }
+}
@@ -0,0 +1,151 @@
+//! This module translates C23 `#embed`, which expands to the bytes of a file as the elements of
+//! an initializer:
+//!
+//! ```c
+//! static const unsigned char logo[] = {
+//! #embed "logo.png"
+//! };
+//! ```
+//!
+//! An array of bytes initialized with nothing but the embedded file becomes
+//!
+//! ```ignore
+//! static logo: [libc::c_uchar; 2716] = *include_bytes!("logo.png");
+//! ```
+//!
+//! as long as the file named by the directive can be found next to the file containing it and
+//! holds exactly the embedded bytes, and a byte string literal otherwise. In any other
+//! initializer, the bytes become the integer elements C expands them to.
+//!
+//! Files embedded with the assembler's `.incbin` directive are not supported, as Clang passes the
+//! assembly through without reading them.
+
+use super::*;
+
+use std::fs;
+
+impl<'c> Translation<'c> {
+    /// Translate an array initializer containing `#embed` expansions, or return `None` if
+    /// there are none.
+    pub fn convert_embed_init_list(
+        &self,
+        ctx: ExprContext,
+        elt: CTypeId,
+        len: usize,
+        ids: &[CExprId],
+    ) -> Result<Option<WithStmts<Box<Expr>>>, TranslationError> {
+        if !ids
+            .iter()
+            .any(|&id| matches!(self.ast_context[id].kind, CExprKind::Embed(..)))
+        {
+            return Ok(None);
+        }
+
+        let is_byte = matches!(
+            self.ast_context.resolve_type(elt).kind,
+            CTypeKind::Char | CTypeKind::SChar | CTypeKind::UChar
+        );
+        if let [id] = *ids {
+            if let CExprKind::Embed(_, ref bytes) = self.ast_context[id].kind {
+                if is_byte && bytes.len() == len {
+                    return Ok(Some(self.convert_embedded_bytes(id, elt, bytes)?));
+                }
+            }
+        }
+
+        let elt_ty = self.convert_type(elt)?;
+        let mut elems = vec![];
+        for &id in ids {
+            match self.ast_context[id].kind {
+                CExprKind::Embed(_, ref bytes) => {
+                    elems.extend(bytes.iter().map(|&b| {
+                        let lit = mk().lit_expr(mk().int_unsuffixed_lit(b as u128));
+                        WithStmts::new_val(mk().cast_expr(lit, elt_ty.clone()))
+                    }));
+                }
+                _ => elems.push(self.convert_expr(ctx.used(), id)?),
+            }
+        }
+        for _ in elems.len()..len {
+            elems.push(self.implicit_default_expr(elt, ctx.is_static)?);
+        }
+
+        let elems: WithStmts<Vec<_>> = elems.into_iter().collect();
+        Ok(Some(elems.map(|elems| mk().array_expr(elems))))
+    }
+
+    /// Translate an array of bytes initialized with the embedded `bytes`
+    fn convert_embedded_bytes(
+        &self,
+        expr_id: CExprId,
+        elt: CTypeId,
+        bytes: &[u8],
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        // Both forms are references to `[u8; N]`
+        let source = match self.embedded_file(expr_id, bytes) {
+            Some(path) => mk().mac_expr(mk().mac(
+                vec!["include_bytes"],
+                vec![TokenTree::Literal(proc_macro2::Literal::string(&path))]
+                    .into_iter()
+                    .collect::<TokenStream>(),
+                MacroDelimiter::Paren(Default::default()),
+            )),
+            None => mk().lit_expr(bytes.to_vec()),
+        };
+
+        if let CTypeKind::UChar = self.ast_context.resolve_type(elt).kind {
+            return Ok(WithStmts::new_val(
+                mk().unary_expr(UnOp::Deref(Default::default()), source),
+            ));
+        }
+
+        let len = mk().lit_expr(mk().int_unsuffixed_lit(bytes.len() as u128));
+        let source_ty = mk().ref_ty(mk().array_ty(mk().path_ty(vec!["u8"]), len.clone()));
+        let target_ty = mk().ref_ty(mk().array_ty(self.convert_type(elt)?, len));
+        let array = transmute_expr(source_ty, target_ty, source, self.tcfg.emit_no_std);
+        Ok(WithStmts::new_unsafe_val(mk().unary_expr(
+            UnOp::Deref(Default::default()),
+            array,
+        )))
+    }
+
+    /// The path to pass to `include_bytes!` for the file embedded by the directive `expr_id`
+    /// expands, if it can be found and holds exactly `bytes`. Paths are relative to the
+    /// translated module when it is written next to the C file, as it is by default.
+    fn embedded_file(&self, expr_id: CExprId, bytes: &[u8]) -> Option<String> {
+        let expr = &self.ast_context[expr_id];
+        let line = expr.loc.as_ref()?.begin_line as usize;
+        let file_id = self.ast_context.file_id(expr)?;
+        let c_file = self.ast_context.get_file_path(file_id)?;
+        let src = fs::read_to_string(c_file).ok()?;
+
+        // The expression is located at the directive
+        let directive = src.lines().nth(line.checked_sub(1)?)?.trim_start();
+        let directive = directive.strip_prefix('#')?.trim_start();
+        let directive = directive.strip_prefix("embed")?.trim_start();
+        let name = match directive.chars().next()? {
+            '"' => directive[1..].split('"').next()?,
+            '<' => directive[1..].split('>').next()?,
+            _ => return None,
+        };
+
+        let path = c_file.parent()?.join(name);
+        if fs::read(&path).ok()? != bytes {
+            return None;
+        }
+
+        let main_dir = self
+            .ast_context
+            .get_file_path(self.main_file)
+            .and_then(|main_file| main_file.parent());
+        match main_dir {
+            Some(main_dir) if self.tcfg.output_dir.is_none() => {
+                match path.strip_prefix(main_dir) {
+                    Ok(relative) => Some(relative.to_str()?.to_owned()),
+                    Err(_) => Some(fs::canonicalize(&path).ok()?.to_str()?.to_owned()),
+                }
+            }
+            _ => Some(fs::canonicalize(&path).ok()?.to_str()?.to_owned()),
+        }
+    }
+}
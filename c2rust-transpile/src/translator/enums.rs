@@ -0,0 +1,277 @@
+//! This module implements the `--enum-style=rust` translation of C enums into `#[repr]` Rust
+//! enums. Enums that cannot be represented faithfully this way keep the default translation into
+//! a type alias plus one constant per enumerator.
+
+use super::*;
+
+impl<'c> Translation<'c> {
+    /// Find the enums that can be translated into Rust enums.
+    ///
+    /// A Rust enum may only ever hold one of its declared discriminants, so an enum qualifies
+    /// only if:
+    ///
+    ///   * its enumerators have pairwise distinct values, one of which is zero (for implicit
+    ///     zero-initialization),
+    ///   * its underlying integer type has a fixed width that can be used in `#[repr]`,
+    ///   * every cast producing a value of the enum type comes straight from one of its own
+    ///     enumerators (or an integer literal equal to one), which are exactly the casts that
+    ///     `Translation::enum_cast` is able to elide,
+    ///   * no arithmetic is done in place on values of the enum type (`++`, `+=`, ...), and
+    ///   * it is not the type of a bitfield.
+    pub fn find_rust_enums(&self) -> HashSet<CEnumId> {
+        let mut candidates = HashSet::new();
+        for (&decl_id, decl) in self.ast_context.iter_decls() {
+            if let CDeclKind::Enum {
+                ref variants,
+                integral_type: Some(integral_type),
+                ..
+            } = decl.kind
+            {
+                if self.enum_repr(integral_type.ctype).is_none() {
+                    continue;
+                }
+                let values: Vec<ConstIntExpr> = variants
+                    .iter()
+                    .map(|&variant_id| self.enum_constant_value(variant_id))
+                    .collect();
+                let distinct = values
+                    .iter()
+                    .enumerate()
+                    .all(|(i, v)| !values[..i].contains(v));
+                let has_zero =
+                    values.contains(&ConstIntExpr::I(0)) || values.contains(&ConstIntExpr::U(0));
+                if !variants.is_empty() && distinct && has_zero {
+                    candidates.insert(decl_id);
+                }
+            }
+        }
+
+        let enum_of = |ty: CQualTypeId| match self.ast_context.resolve_type(ty.ctype).kind {
+            CTypeKind::Enum(enum_id) => Some(enum_id),
+            _ => None,
+        };
+
+        for (_, decl) in self.ast_context.iter_decls() {
+            if let CDeclKind::Field {
+                typ,
+                bitfield_width: Some(_),
+                ..
+            } = decl.kind
+            {
+                if let Some(enum_id) = enum_of(typ) {
+                    candidates.remove(&enum_id);
+                }
+            }
+        }
+
+        for (_, expr) in self.ast_context.iter_exprs() {
+            match expr.kind {
+                CExprKind::ImplicitCast(ty, sub_expr, _, _, _)
+                | CExprKind::ExplicitCast(ty, sub_expr, _, _, _) => {
+                    if let Some(enum_id) = enum_of(ty) {
+                        if !self.is_enum_variant_expr(enum_id, sub_expr) {
+                            candidates.remove(&enum_id);
+                        }
+                    }
+                }
+                CExprKind::Unary(_, op, arg, _) => match op {
+                    c_ast::UnOp::PreIncrement
+                    | c_ast::UnOp::PreDecrement
+                    | c_ast::UnOp::PostIncrement
+                    | c_ast::UnOp::PostDecrement => {
+                        if let Some(enum_id) =
+                            self.ast_context[arg].kind.get_qual_type().and_then(enum_of)
+                        {
+                            candidates.remove(&enum_id);
+                        }
+                    }
+                    _ => {}
+                },
+                CExprKind::Binary(_, op, lhs, _, _, _)
+                    if op.is_assignment() && op != c_ast::BinOp::Assign =>
+                {
+                    if let Some(enum_id) =
+                        self.ast_context[lhs].kind.get_qual_type().and_then(enum_of)
+                    {
+                        candidates.remove(&enum_id);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        candidates
+    }
+
+    /// Translate an enum selected by `find_rust_enums` into a Rust enum together with
+    /// conversions to and from its underlying integer type.
+    pub fn convert_rust_enum(
+        &self,
+        span: Span,
+        enum_id: CEnumId,
+        enum_name: &str,
+        integral_type: CQualTypeId,
+    ) -> Result<ConvertedDecl, TranslationError> {
+        let variant_ids = match self.ast_context[enum_id].kind {
+            CDeclKind::Enum { ref variants, .. } => variants,
+            _ => panic!("{:?} does not point to an `enum` declaration", enum_id),
+        };
+        let repr = self
+            .enum_repr(integral_type.ctype)
+            .expect("Rust enums must have a fixed width representation");
+        let int_ty = self.convert_type(integral_type.ctype)?;
+        let enum_ty = mk().path_ty(vec![enum_name]);
+
+        let mut variants = vec![];
+        let mut try_from_arms = vec![];
+        for &variant_id in variant_ids {
+            let name = self
+                .renamer
+                .borrow()
+                .get(&variant_id)
+                .expect("Enum constant not named");
+            let value = match self.enum_constant_value(variant_id) {
+                ConstIntExpr::I(value) => signed_int_expr(value),
+                ConstIntExpr::U(value) => mk().lit_expr(mk().int_unsuffixed_lit(value as u128)),
+            };
+            let variant = mk().path_expr(vec![enum_name, name.as_str()]);
+            try_from_arms.push(mk().arm(
+                mk().lit_pat(value.clone()),
+                None,
+                mk().call_expr(mk().path_expr(vec!["Ok"]), vec![variant]),
+            ));
+            variants.push(mk().unit_variant(name, Some(value)));
+        }
+        try_from_arms.push(mk().arm(
+            mk().ident_pat("value"),
+            None,
+            mk().call_expr(
+                mk().path_expr(vec!["Err"]),
+                vec![mk().path_expr(vec!["value"])],
+            ),
+        ));
+
+        let enum_item = mk()
+            .span(span)
+            .pub_()
+            .call_attr("derive", vec!["Copy", "Clone", "Debug", "PartialEq", "Eq"])
+            .call_attr("repr", vec![repr])
+            .enum_item(enum_name, variants);
+
+        let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+
+        // impl From<Enum> for c_int { fn from(value: Enum) -> Self { value as c_int } }
+        let from_fn = mk().fn_impl_item(
+            mk().fn_decl(
+                "from",
+                vec![mk().arg(enum_ty.clone(), mk().ident_pat("value"))],
+                None,
+                ReturnType::Type(Default::default(), mk().path_ty(vec!["Self"])),
+            ),
+            mk().block(vec![mk().expr_stmt(
+                mk().cast_expr(mk().path_expr(vec!["value"]), int_ty.clone()),
+            )]),
+        );
+        let from_impl = mk().trait_impl_item(
+            mk().abs_path(vec![
+                mk().path_segment(std_or_core),
+                mk().path_segment("convert"),
+                mk().path_segment_with_args(
+                    "From",
+                    mk().angle_bracketed_args(vec![enum_ty.clone()]),
+                ),
+            ]),
+            int_ty.clone(),
+            vec![from_fn],
+        );
+
+        // impl TryFrom<c_int> for Enum {
+        //     type Error = c_int;
+        //     fn try_from(value: c_int) -> Result<Self, c_int> { match value { .. } }
+        // }
+        let result_ty = mk().path_ty(vec![mk().path_segment_with_args(
+            "Result",
+            mk().angle_bracketed_args(vec![mk().path_ty(vec!["Self"]), int_ty.clone()]),
+        )]);
+        let try_from_fn = mk().fn_impl_item(
+            mk().fn_decl(
+                "try_from",
+                vec![mk().arg(int_ty.clone(), mk().ident_pat("value"))],
+                None,
+                ReturnType::Type(Default::default(), result_ty),
+            ),
+            mk().block(vec![mk().expr_stmt(
+                mk().match_expr(mk().path_expr(vec!["value"]), try_from_arms),
+            )]),
+        );
+        let try_from_impl = mk().trait_impl_item(
+            mk().abs_path(vec![
+                mk().path_segment(std_or_core),
+                mk().path_segment("convert"),
+                mk().path_segment_with_args(
+                    "TryFrom",
+                    mk().angle_bracketed_args(vec![int_ty.clone()]),
+                ),
+            ]),
+            enum_ty,
+            vec![mk().type_impl_item("Error", int_ty), try_from_fn],
+        );
+
+        Ok(ConvertedDecl::Items(vec![
+            enum_item,
+            from_impl,
+            try_from_impl,
+        ]))
+    }
+
+    /// Get the value of an enumerator.
+    pub fn enum_constant_value(&self, variant_id: CEnumConstantId) -> ConstIntExpr {
+        match self.ast_context[variant_id].kind {
+            CDeclKind::EnumConstant { value, .. } => value,
+            _ => panic!("{:?} does not point to an enum variant", variant_id),
+        }
+    }
+
+    /// Name of the primitive integer type to use in `#[repr]` for an enum with the given
+    /// underlying type, if it has the same width on every platform.
+    fn enum_repr(&self, integral_type: CTypeId) -> Option<&'static str> {
+        Some(match self.ast_context.resolve_type(integral_type).kind {
+            CTypeKind::SChar => "i8",
+            CTypeKind::UChar => "u8",
+            CTypeKind::Short => "i16",
+            CTypeKind::UShort => "u16",
+            CTypeKind::Int => "i32",
+            CTypeKind::UInt => "u32",
+            CTypeKind::LongLong => "i64",
+            CTypeKind::ULongLong => "u64",
+            _ => return None,
+        })
+    }
+
+    /// Does this expression translate directly into a variant of the given enum? This mirrors
+    /// the cases handled in `Translation::enum_cast`.
+    fn is_enum_variant_expr(&self, enum_id: CEnumId, expr_id: CExprId) -> bool {
+        let variants = match self.ast_context[enum_id].kind {
+            CDeclKind::Enum { ref variants, .. } => variants,
+            _ => return false,
+        };
+        let is_variant_value = |value: i64| {
+            variants.iter().any(|&variant_id| {
+                let v = self.enum_constant_value(variant_id);
+                v == ConstIntExpr::I(value) || v == ConstIntExpr::U(value as u64)
+            })
+        };
+
+        match self.ast_context[expr_id].kind {
+            CExprKind::DeclRef(_, decl_id, _) => variants.contains(&decl_id),
+            CExprKind::Literal(_, CLiteral::Integer(i, _)) => is_variant_value(i as i64),
+            CExprKind::Unary(_, c_ast::UnOp::Negate, sub_expr, _) => {
+                match self.ast_context[sub_expr].kind {
+                    CExprKind::Literal(_, CLiteral::Integer(i, _)) => is_variant_value(-(i as i64)),
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! This module collects the error code constants of a translation unit for `--error-enums`,
+//! which turns the families of them used across the translated crate into Rust enums (see
+//! `crate::error_enums`). An error code is a negative integer macro or enum constant, such as
+//! `#define ERR_NOMEM -2`, and the functions returning each of them tell which ones are actually
+//! used as error codes.
+
+use super::*;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_derive::{Deserialize, Serialize};
+
+/// The error code constants of a translation unit
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ErrorCodes {
+    /// Negative integer constants by name
+    pub constants: BTreeMap<String, i64>,
+    /// The functions returning each constant
+    pub returned_by: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl<'c> Translation<'c> {
+    /// Collect the error code constants for `--error-enums`.
+    pub fn find_error_codes(&self) -> ErrorCodes {
+        let mut codes = ErrorCodes::default();
+        if !self.tcfg.error_enums {
+            return codes;
+        }
+
+        for (&decl_id, decl) in self.ast_context.iter_decls() {
+            let (name, value) = match decl.kind {
+                CDeclKind::MacroObject { ref name } => {
+                    let value = self
+                        .ast_context
+                        .macro_expansions
+                        .get(&decl_id)
+                        .and_then(|expansions| expansions.first())
+                        .and_then(|&expr_id| self.const_int_value(expr_id));
+                    (name, value)
+                }
+                CDeclKind::EnumConstant {
+                    ref name,
+                    value: ConstIntExpr::I(value),
+                } => (name, Some(value)),
+                _ => continue,
+            };
+            match value {
+                Some(value) if value < 0 => {
+                    codes.constants.insert(name.clone(), value);
+                }
+                _ => {}
+            }
+        }
+
+        for (_, decl) in self.ast_context.iter_decls() {
+            let (fn_name, body) = match decl.kind {
+                CDeclKind::Function {
+                    ref name,
+                    body: Some(body),
+                    ..
+                } => (name, body),
+                _ => continue,
+            };
+            for node in DFExpr::new(&self.ast_context, body.into()) {
+                let returned = match node {
+                    SomeId::Stmt(stmt_id) => match self.ast_context[stmt_id].kind {
+                        CStmtKind::Return(Some(expr_id)) => expr_id,
+                        _ => continue,
+                    },
+                    _ => continue,
+                };
+                if let Some(name) = self.returned_constant(returned) {
+                    if codes.constants.contains_key(name) {
+                        codes
+                            .returned_by
+                            .entry(name.to_string())
+                            .or_default()
+                            .insert(fn_name.clone());
+                    }
+                }
+            }
+        }
+        codes
+    }
+
+    /// The name of the macro or enum constant `expr_id` consists of, if any
+    fn returned_constant(&self, mut expr_id: CExprId) -> Option<&str> {
+        loop {
+            if let Some(macros) = self.ast_context.macro_invocations.get(&expr_id) {
+                if let Some(CDeclKind::MacroObject { name }) =
+                    macros.first().map(|&id| &self.ast_context[id].kind)
+                {
+                    return Some(name);
+                }
+            }
+            match self.ast_context[expr_id].kind {
+                CExprKind::ImplicitCast(_, e, _, _, _) | CExprKind::Paren(_, e) => expr_id = e,
+                CExprKind::DeclRef(_, decl_id, _) => {
+                    return match self.ast_context[decl_id].kind {
+                        CDeclKind::EnumConstant { ref name, .. } => Some(name),
+                        _ => None,
+                    }
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// The value of a constant integer expression made of a literal and an optional sign
+    fn const_int_value(&self, expr_id: CExprId) -> Option<i64> {
+        match *self.ast_context.resolve_expr(expr_id).1 {
+            CExprKind::Literal(_, CLiteral::Integer(value, _)) => i64::try_from(value).ok(),
+            CExprKind::Unary(_, c_ast::UnOp::Negate, e, _) => {
+                self.const_int_value(e)?.checked_neg()
+            }
+            CExprKind::Unary(_, c_ast::UnOp::Plus, e, _) => self.const_int_value(e),
+            CExprKind::ConstantExpr(_, _, Some(ConstIntExpr::I(value))) => Some(value),
+            CExprKind::ConstantExpr(_, _, Some(ConstIntExpr::U(value))) => {
+                i64::try_from(value).ok()
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,359 @@
+//! This module implements `--ffi-tests`, which checks the translated functions against the
+//! original C implementation. Each exported function whose parameters are scalars or pointers
+//! and which returns a scalar is called through both implementations with boundary inputs:
+//! 0, ±1, `MIN` and `MAX` for numbers, and NULL for pointers. Tests in `<module>_ffi_tests.rs`
+//! assert that both return the same:
+//!
+//! ```ignore
+//! #[test]
+//! fn test_add_matches_c() {
+//!     for &a in [0, 1, -1, libc::c_int::MIN, libc::c_int::MAX].iter() {
+//!         for &b in [0, 1, -1, libc::c_int::MIN, libc::c_int::MAX].iter() {
+//!             let rust = unsafe { add(a, b) };
+//!             let c = unsafe { c2rust_ffi_add(a, b) };
+//!             assert_eq!(rust, c, "add({:?}, {:?})", a, b);
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! The C implementation is linked into the crate by `build.rs`, which compiles
+//! `<module>.ffi.c` with the flags of the original C file. That file includes the original one
+//! after renaming its global definitions with a `c2rust_ffi_` prefix, so that they don't clash
+//! with the translated ones.
+
+use super::*;
+
+/// Name of the test module included into the translated module
+const TEST_MODULE_NAME: &str = "c2rust_ffi_tests";
+
+/// Prefix of the renamed global symbols of the C implementation
+const C_SYMBOL_PREFIX: &str = "c2rust_ffi_";
+
+/// Number of calls up to which a test tries all combinations of the boundary inputs. Functions
+/// that would need more are called with the inputs of one parameter at a time, and the first
+/// input of all others.
+const MAX_COMBINATIONS: usize = 1024;
+
+/// A function checked against its C counterpart
+struct FfiTestedFn {
+    rust_name: String,
+    c_symbol: String,
+    /// Parameter names and the inputs they are called with
+    params: Vec<(String, Vec<String>)>,
+    returns_float: bool,
+}
+
+impl<'c> Translation<'c> {
+    /// Generate the test module item for the translated module, and the C and Rust files for
+    /// the FFI tests of the exported functions recorded during translation.
+    pub fn ffi_tests(&self) -> Option<(Box<Item>, Vec<(String, String)>)> {
+        if !self.tcfg.ffi_tests {
+            return None;
+        }
+
+        let mut extern_fns = vec![];
+        let mut fns = vec![];
+        for &decl_id in self.tested_fns.borrow().iter() {
+            match self.ffi_tested_fn(decl_id) {
+                Ok(Some((extern_fn, tested))) => {
+                    extern_fns.push(extern_fn);
+                    fns.push(tested);
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Skipping FFI test: {}", e),
+            }
+        }
+        if fns.is_empty() {
+            return None;
+        }
+
+        let c_file_name = format!("{}.ffi.c", self.main_module_name);
+        let rust_file_name = format!("{}_ffi_tests.rs", self.main_module_name);
+
+        let mut c_file = format!(
+            "// The C implementation checked by {0}, with its global symbols renamed so that it\n\
+             // can be linked next to {1}.rs. build.rs defines C2RUST_FFI_SOURCE as the C file.\n",
+            rust_file_name, self.main_module_name,
+        );
+        for name in self.exported_c_symbols() {
+            c_file.push_str(&format!("#define {0} {1}{0}\n", name, C_SYMBOL_PREFIX));
+        }
+        c_file.push_str("#include C2RUST_FFI_SOURCE\n");
+
+        let mut rust_file = String::from("use super::*;\n\n");
+        rust_file.push_str(&pprust::to_string(|| syn::File {
+            shebang: None,
+            attrs: vec![],
+            items: vec![*mk().extern_("C").foreign_items(extern_fns)],
+        }));
+        for tested in &fns {
+            rust_file.push_str(&ffi_test(tested));
+        }
+
+        let test_mod = mk()
+            .call_attr("cfg", vec!["test"])
+            .str_attr("path", &rust_file_name)
+            .mod_item(TEST_MODULE_NAME, None);
+        Some((
+            test_mod,
+            vec![(c_file_name, c_file), (rust_file_name, rust_file)],
+        ))
+    }
+
+    /// The declaration of the C implementation of `decl_id` and its test, if it is an exported
+    /// function with a scalar or pointer signature
+    fn ffi_tested_fn(
+        &self,
+        decl_id: CDeclId,
+    ) -> Result<Option<(ForeignItem, FfiTestedFn)>, TranslationError> {
+        let rust_name = match self.tested_fn_name(decl_id) {
+            Some(rust_name) => rust_name,
+            None => return Ok(None),
+        };
+        let (typ, name, parameters) = match self.ast_context[decl_id].kind {
+            CDeclKind::Function {
+                typ,
+                ref name,
+                ref parameters,
+                ..
+            } => (typ, name, parameters),
+            _ => return Ok(None),
+        };
+        let ret = match self.ast_context.resolve_type(typ).kind {
+            CTypeKind::Function(ret, _, false, false, _) => ret,
+            _ => return Ok(None),
+        };
+        let ret_kind = &self.ast_context.resolve_type(ret.ctype).kind;
+        let returns_float = match ret_kind {
+            CTypeKind::Float | CTypeKind::Double => true,
+            kind if kind.is_integral_type() => false,
+            _ => return Ok(None),
+        };
+
+        let mut params = vec![];
+        let mut inputs = vec![];
+        for (i, &param_id) in parameters.iter().enumerate() {
+            let typ = match self.ast_context[param_id].kind {
+                CDeclKind::Variable { typ, .. } => typ,
+                _ => {
+                    return Err(TranslationError::generic(
+                        "Parameter is not variable declaration",
+                    ))
+                }
+            };
+            let ty = self.convert_type(typ.ctype)?;
+            let values = match self.boundary_values(typ.ctype, &ty) {
+                Some(values) => values,
+                None => return Ok(None),
+            };
+            let param = format!("a{}", i);
+            inputs.push(mk().arg(ty, mk().ident_pat(param.as_str())));
+            params.push((param, values));
+        }
+
+        let c_symbol = format!("{}{}", C_SYMBOL_PREFIX, name);
+        let output = ReturnType::Type(Default::default(), self.convert_type(ret.ctype)?);
+        let extern_fn = mk().fn_foreign_item(mk().fn_decl(c_symbol.as_str(), inputs, None, output));
+        Ok(Some((
+            extern_fn,
+            FfiTestedFn {
+                rust_name,
+                c_symbol,
+                params,
+                returns_float,
+            },
+        )))
+    }
+
+    /// The boundary inputs of a parameter of type `ctype`, translated to `ty`, as Rust
+    /// expressions
+    fn boundary_values(&self, ctype: CTypeId, ty: &Type) -> Option<Vec<String>> {
+        let kind = &self.ast_context.resolve_type(ctype).kind;
+        if let CTypeKind::Pointer(pointee) = *kind {
+            // Function pointers are translated to `Option`s
+            let null = if let CTypeKind::Function(..) =
+                self.ast_context.resolve_type(pointee.ctype).kind
+            {
+                "None"
+            } else if pointee.qualifiers.is_const {
+                "core::ptr::null()"
+            } else {
+                "core::ptr::null_mut()"
+            };
+            return Some(vec![null.to_owned()]);
+        }
+
+        let path = match *ty {
+            Type::Path(syn::TypePath { ref path, .. }) => pprust::path_to_string(path),
+            _ => return None,
+        };
+        let values = match kind {
+            CTypeKind::Bool => vec!["false".to_owned(), "true".to_owned()],
+            // Plain `char` may be signed or unsigned
+            CTypeKind::Char => vec![
+                "0".to_owned(),
+                "1".to_owned(),
+                format!("{}::MIN", path),
+                format!("{}::MAX", path),
+            ],
+            CTypeKind::Float | CTypeKind::Double => vec![
+                "0.0".to_owned(),
+                "1.0".to_owned(),
+                "-1.0".to_owned(),
+                format!("{}::MIN", path),
+                format!("{}::MAX", path),
+            ],
+            kind if kind.is_signed_integral_type() => vec![
+                "0".to_owned(),
+                "1".to_owned(),
+                "-1".to_owned(),
+                format!("{}::MIN", path),
+                format!("{}::MAX", path),
+            ],
+            kind if kind.is_unsigned_integral_type() => {
+                vec!["0".to_owned(), "1".to_owned(), format!("{}::MAX", path)]
+            }
+            _ => return None,
+        };
+        Some(values)
+    }
+
+    /// The names of the functions and variables that this translation unit defines with
+    /// external linkage, which the C implementation renames
+    fn exported_c_symbols(&self) -> Vec<&str> {
+        let mut names = self
+            .ast_context
+            .c_decls_top
+            .iter()
+            .filter_map(|&decl_id| match self.ast_context[decl_id].kind {
+                CDeclKind::Function {
+                    is_global: true,
+                    body: Some(_),
+                    ref name,
+                    ..
+                } => Some(name.as_str()),
+                CDeclKind::Variable {
+                    is_defn: true,
+                    is_externally_visible: true,
+                    ref ident,
+                    ..
+                } => Some(ident.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+}
+
+/// The test comparing a translated function with its C counterpart
+fn ffi_test(tested: &FfiTestedFn) -> String {
+    let args = tested
+        .params
+        .iter()
+        .map(|(param, _)| param.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let placeholders = vec!["{:?}"; tested.params.len()].join(", ");
+    let format_args = if tested.params.is_empty() {
+        String::new()
+    } else {
+        format!(", {}", args)
+    };
+    let check = if tested.returns_float {
+        format!(
+            "assert!(\n    \
+                 rust == c || rust.is_nan() && c.is_nan(),\n    \
+                 \"{}({}): {{:?}} != {{:?}}\"{},\n    \
+                 rust,\n    \
+                 c,\n\
+             );\n",
+            tested.rust_name, placeholders, format_args,
+        )
+    } else {
+        format!(
+            "assert_eq!(rust, c, \"{}({})\"{});\n",
+            tested.rust_name, placeholders, format_args,
+        )
+    };
+    let call = format!(
+        "let rust = unsafe {{ {}({}) }};\n\
+         let c = unsafe {{ {}({}) }};\n\
+         {}",
+        tested.rust_name, args, tested.c_symbol, args, check,
+    );
+
+    let combinations = tested
+        .params
+        .iter()
+        .try_fold(1usize, |n, (_, values)| n.checked_mul(values.len()));
+    let body = match combinations {
+        Some(n) if n <= MAX_COMBINATIONS => {
+            tested
+                .params
+                .iter()
+                .rev()
+                .fold(call, |inner, (param, values)| {
+                    format!(
+                        "for &{} in [{}].iter() {{\n{}}}\n",
+                        param,
+                        values.join(", "),
+                        indent(&inner),
+                    )
+                })
+        }
+        _ => {
+            let mut body = String::new();
+            for (i, (param, values)) in tested.params.iter().enumerate() {
+                let others = tested
+                    .params
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .collect::<Vec<_>>();
+                let names = others
+                    .iter()
+                    .map(|(_, (other, _))| other.as_str())
+                    .collect::<Vec<_>>();
+                let firsts = others
+                    .iter()
+                    .map(|(_, (_, values))| values[0].as_str())
+                    .collect::<Vec<_>>();
+                let inner = format!(
+                    "let ({},) = ({},);\n{}",
+                    names.join(", "),
+                    firsts.join(", "),
+                    call,
+                );
+                body.push_str(&format!(
+                    "for &{} in [{}].iter() {{\n{}}}\n",
+                    param,
+                    values.join(", "),
+                    indent(&inner),
+                ));
+            }
+            body
+        }
+    };
+
+    format!(
+        "\n#[test]\nfn test_{}_matches_c() {{\n{}}}\n",
+        tested.rust_name,
+        indent(&body),
+    )
+}
+
+/// Indent every non-empty line of `code` by one level
+fn indent(code: &str) -> String {
+    code.lines()
+        .map(|line| {
+            if line.is_empty() {
+                "\n".to_owned()
+            } else {
+                format!("    {}\n", line)
+            }
+        })
+        .collect()
+}
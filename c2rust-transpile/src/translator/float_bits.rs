@@ -0,0 +1,263 @@
+//! This module translates the C idioms for reinterpreting the bits of a float as an integer of
+//! the same size, and vice versa, to the `to_bits` and `from_bits` methods of `f32` and `f64`:
+//!
+//! ```c
+//! uint32_t i = *(uint32_t *)&f;                   // let i = f.to_bits();
+//! float g = *(float *)&i;                         // let g = f32::from_bits(i);
+//! i = ((union { float f; uint32_t i; }){ g }).i;  // i = g.to_bits();
+//! memcpy(&i, &g, sizeof i);                       // i = g.to_bits();
+//! ```
+//!
+//! Unlike the pointer casts, transmutes and unions these otherwise translate to, the methods
+//! don't need `unsafe` and say what the code does. Only reads of whole floats and integers are
+//! recognized; writes through a pointer cast are translated as before.
+
+use super::*;
+
+/// A type whose bits may be reinterpreted
+#[derive(Clone, Copy)]
+enum BitsType {
+    Float { bits: u8 },
+    Int { bits: u8, signed: bool },
+}
+
+/// Architectures on which `long` is 64 bits wide, except on Windows
+const LP64_ARCHS: &[&str] = &[
+    "x86_64",
+    "aarch64",
+    "arm64",
+    "powerpc64",
+    "mips64",
+    "riscv64",
+    "s390x",
+    "sparcv9",
+];
+
+impl<'c> Translation<'c> {
+    /// Translate a read that reinterprets the bits of a float as an integer or vice versa, if
+    /// `expr_id` is one.
+    pub fn convert_float_bit_cast(
+        &self,
+        ctx: ExprContext,
+        expr_id: CExprId,
+    ) -> Result<Option<WithStmts<Box<Expr>>>, TranslationError> {
+        match self.ast_context[expr_id].kind {
+            CExprKind::ImplicitCast(qty, place, CastKind::LValueToRValue, _, _) => {
+                let (source, from) = match self.float_bits_source(place) {
+                    Some(source) => source,
+                    None => return Ok(None),
+                };
+                if !self.is_float_bit_cast(from, qty.ctype) {
+                    return Ok(None);
+                }
+                let val = self.convert_expr(ctx.used(), source)?;
+                Ok(Some(
+                    val.result_map(|val| self.bit_cast(val, from, qty.ctype))?,
+                ))
+            }
+            CExprKind::Call(_, func, ref args) if ctx.is_unused() && args.len() == 3 => {
+                self.convert_memcpy_bit_cast(ctx, func, args)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// The expression whose bits the place `place` reinterprets, and its type, for
+    /// `*(T *)&source` and `((union U){ source }).field`
+    fn float_bits_source(&self, place: CExprId) -> Option<(CExprId, CTypeId)> {
+        match self.ast_context[self.skip_parens(place)].kind {
+            CExprKind::Unary(_, c_ast::UnOp::Deref, ptr, _) => {
+                let inner = match self.ast_context[self.skip_parens(ptr)].kind {
+                    CExprKind::ExplicitCast(_, inner, CastKind::BitCast, _, _) => inner,
+                    _ => return None,
+                };
+                match self.ast_context[self.skip_parens(inner)].kind {
+                    CExprKind::Unary(_, c_ast::UnOp::AddressOf, source, _) => {
+                        let ty = self.ast_context[source].kind.get_qual_type()?;
+                        // Volatile reads have to stay volatile
+                        if ty.qualifiers.is_volatile {
+                            return None;
+                        }
+                        Some((source, ty.ctype))
+                    }
+                    _ => None,
+                }
+            }
+            CExprKind::Member(_, base, field_id, MemberKind::Dot, _) => {
+                let init = match self.ast_context[self.skip_parens(base)].kind {
+                    CExprKind::CompoundLiteral(ty, init) => {
+                        match self.ast_context.resolve_type(ty.ctype).kind {
+                            CTypeKind::Union(_) => init,
+                            _ => return None,
+                        }
+                    }
+                    _ => return None,
+                };
+                match self.ast_context[init].kind {
+                    CExprKind::InitList(_, ref inits, Some(init_field_id), _)
+                        if inits.len() == 1 && init_field_id != field_id =>
+                    {
+                        let ty = self.ast_context[inits[0]].kind.get_type()?;
+                        Some((inits[0], ty))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Translate `memcpy(&dst, &src, sizeof dst)` between a float and an integer to an
+    /// assignment of the reinterpreted `src` to `dst`
+    fn convert_memcpy_bit_cast(
+        &self,
+        ctx: ExprContext,
+        func: CExprId,
+        args: &[CExprId],
+    ) -> Result<Option<WithStmts<Box<Expr>>>, TranslationError> {
+        let is_memcpy = match self.ast_context.resolve_expr(func).1 {
+            CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[*decl_id].kind {
+                CDeclKind::Function { ref name, .. } => {
+                    name == "memcpy" || name == "__builtin_memcpy"
+                }
+                _ => false,
+            },
+            _ => false,
+        };
+        if !is_memcpy {
+            return Ok(None);
+        }
+
+        let address_of = |arg: CExprId| match *self.ast_context.resolve_expr(arg).1 {
+            CExprKind::Unary(_, c_ast::UnOp::AddressOf, place, _) => {
+                let ty = self.ast_context[place].kind.get_qual_type()?;
+                (!ty.qualifiers.is_volatile).then(|| (place, ty.ctype))
+            }
+            _ => None,
+        };
+        let ((dst, to), (src, from)) = match (address_of(args[0]), address_of(args[1])) {
+            (Some(dst), Some(src)) => (dst, src),
+            _ => return Ok(None),
+        };
+        if !self.is_float_bit_cast(from, to) {
+            return Ok(None);
+        }
+
+        // The whole value has to be copied
+        let size_ty = match *self.ast_context.resolve_expr(args[2]).1 {
+            CExprKind::UnaryType(_, UnTypeOp::SizeOf, _, arg_ty) => arg_ty.ctype,
+            _ => return Ok(None),
+        };
+        let size_ty = self.ast_context.resolve_type_id(size_ty);
+        if size_ty != self.ast_context.resolve_type_id(to)
+            && size_ty != self.ast_context.resolve_type_id(from)
+        {
+            return Ok(None);
+        }
+
+        let dst = self.convert_expr(ctx.used(), dst)?;
+        let src = self.convert_expr(ctx.used(), src)?;
+        let assign = dst.and_then(|dst| {
+            src.result_map(|src| Ok(mk().assign_expr(dst, self.bit_cast(src, from, to)?)))
+        })?;
+        Ok(Some(assign.and_then(|assign| {
+            Ok::<_, TranslationError>(WithStmts::new(
+                vec![mk().semi_stmt(assign)],
+                self.panic_or_err("memcpy result is not supposed to be used"),
+            ))
+        })?))
+    }
+
+    fn skip_parens(&self, mut expr_id: CExprId) -> CExprId {
+        while let CExprKind::Paren(_, inner) = self.ast_context[expr_id].kind {
+            expr_id = inner;
+        }
+        expr_id
+    }
+
+    fn bits_type(&self, ctype: CTypeId) -> Option<BitsType> {
+        let long_bits = if self.is_lp64() { 64 } else { 32 };
+        Some(match self.ast_context.resolve_type(ctype).kind {
+            CTypeKind::Float => BitsType::Float { bits: 32 },
+            CTypeKind::Double => BitsType::Float { bits: 64 },
+            CTypeKind::Int => BitsType::Int {
+                bits: 32,
+                signed: true,
+            },
+            CTypeKind::UInt => BitsType::Int {
+                bits: 32,
+                signed: false,
+            },
+            CTypeKind::Long => BitsType::Int {
+                bits: long_bits,
+                signed: true,
+            },
+            CTypeKind::ULong => BitsType::Int {
+                bits: long_bits,
+                signed: false,
+            },
+            CTypeKind::LongLong => BitsType::Int {
+                bits: 64,
+                signed: true,
+            },
+            CTypeKind::ULongLong => BitsType::Int {
+                bits: 64,
+                signed: false,
+            },
+            _ => return None,
+        })
+    }
+
+    /// Whether `long` is 64 bits wide on the target
+    fn is_lp64(&self) -> bool {
+        let target = &self.ast_context.target;
+        LP64_ARCHS.iter().any(|arch| target.starts_with(arch)) && !target.contains("windows")
+    }
+
+    fn is_float_bit_cast(&self, from: CTypeId, to: CTypeId) -> bool {
+        match (self.bits_type(from), self.bits_type(to)) {
+            (Some(BitsType::Float { bits }), Some(BitsType::Int { bits: int_bits, .. }))
+            | (Some(BitsType::Int { bits: int_bits, .. }), Some(BitsType::Float { bits })) => {
+                bits == int_bits
+            }
+            _ => false,
+        }
+    }
+
+    /// Reinterpret `val` of type `from` as type `to`, as checked by `is_float_bit_cast`.
+    /// Unsigned integers are converted to the Rust types of the same width, so only signed ones
+    /// need a cast.
+    fn bit_cast(
+        &self,
+        val: Box<Expr>,
+        from: CTypeId,
+        to: CTypeId,
+    ) -> Result<Box<Expr>, TranslationError> {
+        match (self.bits_type(from), self.bits_type(to)) {
+            (Some(BitsType::Float { .. }), Some(BitsType::Int { signed, .. })) => {
+                let bits = mk().method_call_expr(val, "to_bits", Vec::<Box<Expr>>::new());
+                if signed {
+                    Ok(mk().cast_expr(bits, self.convert_type(to)?))
+                } else {
+                    Ok(bits)
+                }
+            }
+            (Some(BitsType::Int { bits, signed }), Some(BitsType::Float { .. })) => {
+                let (float, unsigned) = if bits == 32 {
+                    ("f32", "u32")
+                } else {
+                    ("f64", "u64")
+                };
+                let val = if signed {
+                    mk().cast_expr(val, mk().path_ty(vec![unsigned]))
+                } else {
+                    val
+                };
+                Ok(mk().call_expr(mk().path_expr(vec![float, "from_bits"]), vec![val]))
+            }
+            _ => Err(TranslationError::generic(
+                "not a float bit reinterpretation",
+            )),
+        }
+    }
+}
@@ -0,0 +1,490 @@
+//! This module handles unions that have both function pointer and data pointer members.
+//!
+//! Reading a member of such a union after writing the other kind of pointer reinterprets a
+//! function pointer as a data pointer (or vice versa), which is not portable and has no sound
+//! equivalent in Rust. The transpiler always warns about such unions. With
+//! `--fn-pointer-unions`, they are translated into Rust enums with one variant per member
+//! instead:
+//!
+//! ```ignore
+//! #[derive(Copy, Clone)]
+//! pub enum callback {
+//!     func(Option<unsafe extern "C" fn(c_int) -> c_int>),
+//!     data(*mut c_void),
+//! }
+//! ```
+//!
+//! along with an accessor per member returning `Option`. Assigning a member selects its variant,
+//! and other member accesses are rewritten into matches that panic if the member is not the
+//! active one. Unions whose address is cast to another pointer type, or which are shared with
+//! other C code, even within records or arrays, through functions defined elsewhere or externally
+//! visible or through externally visible variables, cannot be translated this way and are
+//! reported instead.
+
+use super::*;
+
+impl<'c> Translation<'c> {
+    /// Find and report the unions mixing function and data pointers. If `--fn-pointer-unions`
+    /// is enabled, also return the ones that can be translated into Rust enums, along with the
+    /// member accesses which are written to (or borrowed mutably).
+    pub fn find_fn_pointer_unions(&self) -> (HashSet<CRecordId>, HashSet<CExprId>) {
+        let mut candidates = IndexSet::new();
+        for (&decl_id, decl) in self.ast_context.iter_decls() {
+            if let CDeclKind::Union {
+                fields: Some(ref fields),
+                ref name,
+                ..
+            } = decl.kind
+            {
+                let mut has_fn_pointer = false;
+                let mut has_data_pointer = false;
+                for &field_id in fields {
+                    if let CDeclKind::Field { typ, .. } = self.ast_context[field_id].kind {
+                        if self.ast_context.is_function_pointer(typ.ctype) {
+                            has_fn_pointer = true;
+                        } else if self.ast_context.resolve_type(typ.ctype).kind.is_pointer() {
+                            has_data_pointer = true;
+                        }
+                    }
+                }
+                if !has_fn_pointer || !has_data_pointer {
+                    continue;
+                }
+
+                let name = name.as_ref().map_or("<anonymous>", String::as_str);
+                if self.tcfg.fn_pointer_unions {
+                    candidates.insert(decl_id);
+                } else {
+                    diag!(
+                        Diagnostic::Unions,
+                        at self.ast_context.display_loc(&decl.loc),
+                        "Union {} mixes function and data pointers, which cannot be portably \
+                         reinterpreted as each other; consider --fn-pointer-unions",
+                        name
+                    );
+                }
+            }
+        }
+
+        let mut writes = HashSet::new();
+        if candidates.is_empty() {
+            return (HashSet::new(), writes);
+        }
+
+        let mut rejected = IndexMap::new();
+
+        // Unions shared with other C code, even within other records or arrays, must keep their
+        // layout
+        for (decl_id, ty) in self.ast_context.external_types() {
+            let reason = match self.ast_context[decl_id].kind {
+                CDeclKind::Function { .. } => {
+                    "it is passed to or returned from a function that is defined elsewhere or \
+                     externally visible"
+                }
+                _ => "it is stored in an externally visible variable",
+            };
+            for union_id in self.ast_context.reached_records(ty) {
+                if candidates.contains(&union_id) {
+                    rejected.entry(union_id).or_insert(reason);
+                }
+            }
+        }
+
+        for (_, expr) in self.ast_context.iter_exprs() {
+            match expr.kind {
+                CExprKind::ImplicitCast(ty, sub_expr, kind, _, _)
+                | CExprKind::ExplicitCast(ty, sub_expr, kind, _, _) => {
+                    if kind == CastKind::ArrayToPointerDecay && self.is_mut_pointer(ty) {
+                        if let Some(member) = self.fn_pointer_union_lvalue(&candidates, sub_expr) {
+                            writes.insert(member);
+                        }
+                    }
+
+                    let from = self.ast_context[sub_expr].kind.get_type();
+                    let is_pointer = |ty| self.ast_context.resolve_type(ty).kind.is_pointer();
+                    if let Some(from) =
+                        from.filter(|&from| is_pointer(from) && is_pointer(ty.ctype))
+                    {
+                        let from = self.ast_context.reached_records(from);
+                        let to = self.ast_context.reached_records(ty.ctype);
+                        for union_id in from.symmetric_difference(&to) {
+                            if candidates.contains(union_id) {
+                                rejected
+                                    .entry(*union_id)
+                                    .or_insert("a pointer to it is cast to another pointer type");
+                            }
+                        }
+                    }
+                }
+                CExprKind::Unary(ty, op, arg, _) => match op {
+                    c_ast::UnOp::AddressOf
+                    | c_ast::UnOp::PreIncrement
+                    | c_ast::UnOp::PreDecrement
+                    | c_ast::UnOp::PostIncrement
+                    | c_ast::UnOp::PostDecrement => {
+                        if op != c_ast::UnOp::AddressOf || self.is_mut_pointer(ty) {
+                            if let Some(member) = self.fn_pointer_union_lvalue(&candidates, arg) {
+                                writes.insert(member);
+                            }
+                        }
+                    }
+                    _ => {}
+                },
+                CExprKind::Binary(_, op, lhs, _, _, _) if op.is_assignment() => {
+                    if let Some(member) = self.fn_pointer_union_lvalue(&candidates, lhs) {
+                        // Plain assignments to a member select the variant instead
+                        if op != c_ast::BinOp::Assign || member != lhs {
+                            writes.insert(member);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for (union_id, reason) in rejected {
+            candidates.remove(&union_id);
+            let name = self.ast_context[union_id]
+                .kind
+                .get_name()
+                .map_or("<anonymous>", String::as_str);
+            diag!(
+                Diagnostic::Unions,
+                at self.ast_context.display_loc(&self.ast_context[union_id].loc),
+                "Union {} mixes function and data pointers, but cannot be translated into an \
+                 enum: {}",
+                name,
+                reason
+            );
+        }
+        writes.retain(|&member| self.fn_pointer_union_member(&candidates, member).is_some());
+
+        (candidates.into_iter().collect(), writes)
+    }
+
+    /// Translate a union selected by `find_fn_pointer_unions` into a Rust enum with one variant
+    /// per member, and an accessor per member that returns its value if it is the active one.
+    pub fn convert_fn_pointer_union(
+        &self,
+        span: Span,
+        union_id: CRecordId,
+        name: &str,
+        fields: &[CFieldId],
+    ) -> Result<ConvertedDecl, TranslationError> {
+        let mut variants = vec![];
+        let mut accessors = vec![];
+        for &field_id in fields {
+            let field_name = self
+                .type_converter
+                .borrow()
+                .resolve_field_name(Some(union_id), field_id)
+                .unwrap();
+            let ty = match self.ast_context[field_id].kind {
+                CDeclKind::Field { typ, .. } => self.convert_type(typ.ctype)?,
+                _ => {
+                    return Err(TranslationError::generic(
+                        "Found non-field in record field list",
+                    ))
+                }
+            };
+            variants
+                .push(mk().tuple_variant(field_name.as_str(), vec![mk().enum_field(ty.clone())]));
+
+            // pub fn member(self) -> Option<T> {
+            //     match self { Union::member(payload) => Some(payload), _ => None }
+            // }
+            let option_ty =
+                mk().path_ty(vec![mk().path_segment_with_args(
+                    "Option",
+                    mk().angle_bracketed_args(vec![ty]),
+                )]);
+            let arms = vec![
+                mk().arm(
+                    mk().tuple_struct_pat(
+                        vec![name, field_name.as_str()],
+                        vec![mk().ident_pat("payload")],
+                    ),
+                    None,
+                    mk().call_expr(
+                        mk().path_expr(vec!["Some"]),
+                        vec![mk().path_expr(vec!["payload"])],
+                    ),
+                ),
+                mk().arm(mk().wild_pat(), None, mk().path_expr(vec!["None"])),
+            ];
+            accessors.push(mk().pub_().fn_impl_item(
+                mk().fn_decl(
+                    field_name.as_str(),
+                    vec![mk().self_arg(SelfKind::Value(Mutability::Immutable))],
+                    None,
+                    ReturnType::Type(Default::default(), option_ty),
+                ),
+                mk().block(vec![
+                    mk().expr_stmt(mk().match_expr(mk().path_expr(vec!["self"]), arms)),
+                ]),
+            ));
+        }
+
+        let enum_item = mk()
+            .span(span)
+            .pub_()
+            .call_attr("derive", vec!["Copy", "Clone"])
+            .enum_item(name, variants);
+        let impl_item = mk().impl_item(mk().path_ty(vec![name]), accessors);
+
+        Ok(ConvertedDecl::Items(vec![enum_item, impl_item]))
+    }
+
+    /// Translate an access to a member of a union that was translated into a Rust enum. Returns
+    /// `None` if `expr_id` is not such an access.
+    pub fn convert_fn_pointer_union_access(
+        &self,
+        ctx: ExprContext,
+        expr_id: CExprId,
+    ) -> Result<Option<WithStmts<Box<Expr>>>, TranslationError> {
+        let (union_id, field_id) = match self.fn_pointer_union_member(&IndexSet::new(), expr_id) {
+            Some(member) => member,
+            None => return Ok(None),
+        };
+        let name = self
+            .type_converter
+            .borrow()
+            .resolve_decl_name(union_id)
+            .unwrap();
+        let field_name = self
+            .type_converter
+            .borrow()
+            .resolve_field_name(Some(union_id), field_id)
+            .unwrap();
+        let mutable = self.fn_pointer_union_writes.contains(&expr_id);
+
+        let val = self.convert_fn_pointer_union_base(ctx, expr_id)?;
+        Ok(Some(val.map(|v| {
+            self.variant_payload_place(
+                v,
+                &name,
+                &[field_name],
+                mutable,
+                false,
+                "Accessed an inactive member of a union",
+            )
+        })))
+    }
+
+    /// Translate an assignment to a member of a union that was translated into a Rust enum,
+    /// which selects the variant of that member. Returns `None` if `lhs` is not such a member.
+    pub fn convert_fn_pointer_union_assignment(
+        &self,
+        ctx: ExprContext,
+        lhs: CExprId,
+        rhs: CExprId,
+    ) -> Result<Option<WithStmts<Box<Expr>>>, TranslationError> {
+        let (union_id, field_id) = match self.fn_pointer_union_member(&IndexSet::new(), lhs) {
+            Some(member) => member,
+            None => return Ok(None),
+        };
+
+        let base = self.convert_fn_pointer_union_base(ctx.used(), lhs)?;
+        let rhs = self.convert_expr(ctx.used(), rhs)?;
+        let assignment = base.and_then(|base| -> Result<_, TranslationError> {
+            Ok(rhs.map(|rhs| {
+                mk().assign_expr(base, self.fn_pointer_union_variant(union_id, field_id, rhs))
+            }))
+        })?;
+
+        if ctx.is_unused() {
+            Ok(Some(assignment))
+        } else {
+            // The value of the assignment is the member that was just assigned
+            let val = self.convert_expr(ctx, lhs)?;
+            assignment
+                .and_then(|assignment| -> Result<_, TranslationError> {
+                    let mut val = val;
+                    val.prepend_stmts(vec![mk().semi_stmt(assignment)]);
+                    Ok(val)
+                })
+                .map(Some)
+        }
+    }
+
+    /// Zero-initialize a union that was translated into a Rust enum, using the variant of its
+    /// first member.
+    pub fn fn_pointer_union_zero_initializer(
+        &self,
+        union_id: CRecordId,
+        fields: &[CFieldId],
+        is_static: bool,
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        let &field_id = fields
+            .first()
+            .ok_or(format_err!("A union should have a field"))?;
+        match self.ast_context[field_id].kind {
+            CDeclKind::Field { typ, .. } => Ok(self
+                .implicit_default_expr(typ.ctype, is_static)?
+                .map(|init| self.fn_pointer_union_variant(union_id, field_id, init))),
+            _ => Err(TranslationError::generic(
+                "Found non-field in record field list",
+            )),
+        }
+    }
+
+    /// Build the enum variant of the given union member: `Union::member(payload)`.
+    pub fn fn_pointer_union_variant(
+        &self,
+        union_id: CRecordId,
+        field_id: CFieldId,
+        payload: Box<Expr>,
+    ) -> Box<Expr> {
+        let name = self
+            .type_converter
+            .borrow()
+            .resolve_decl_name(union_id)
+            .unwrap();
+        let field_name = self
+            .type_converter
+            .borrow()
+            .resolve_field_name(Some(union_id), field_id)
+            .unwrap();
+        mk().call_expr(mk().path_expr(vec![name, field_name]), vec![payload])
+    }
+
+    /// Translate the base of a member access into the enum value.
+    fn convert_fn_pointer_union_base(
+        &self,
+        ctx: ExprContext,
+        member_expr: CExprId,
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        match self.ast_context[member_expr].kind {
+            CExprKind::Member(_, base, _, MemberKind::Dot, _) => self.convert_expr(ctx, base),
+            CExprKind::Member(_, base, _, MemberKind::Arrow, _) => Ok(self
+                .convert_expr(ctx, base)?
+                .map(|v| mk().unary_expr(UnOp::Deref(Default::default()), v))),
+            _ => Err(format_err!("Expected a union member access").into()),
+        }
+    }
+
+    /// If `expr_id` accesses a member of a union that mixes function and data pointers, return
+    /// the union and the member. Uses `candidates` during analysis and the final set of unions
+    /// afterwards.
+    fn fn_pointer_union_member(
+        &self,
+        candidates: &IndexSet<CRecordId>,
+        expr_id: CExprId,
+    ) -> Option<(CRecordId, CFieldId)> {
+        match self.ast_context[expr_id].kind {
+            CExprKind::Member(_, _, field_id, _, _) => {
+                let union_id = *self.ast_context.parents.get(&field_id)?;
+                if candidates.contains(&union_id) || self.fn_pointer_unions.contains(&union_id) {
+                    Some((union_id, field_id))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// If `expr_id` is an lvalue within a member of a union that mixes function and data
+    /// pointers, return the member access.
+    fn fn_pointer_union_lvalue(
+        &self,
+        candidates: &IndexSet<CRecordId>,
+        mut expr_id: CExprId,
+    ) -> Option<CExprId> {
+        loop {
+            if self.fn_pointer_union_member(candidates, expr_id).is_some() {
+                return Some(expr_id);
+            }
+            match self.ast_context[expr_id].kind {
+                CExprKind::Paren(_, sub_expr)
+                | CExprKind::ImplicitCast(_, sub_expr, CastKind::ArrayToPointerDecay, _, _)
+                | CExprKind::ArraySubscript(_, sub_expr, _, _)
+                | CExprKind::Member(_, sub_expr, _, MemberKind::Dot, _) => expr_id = sub_expr,
+                _ => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+
+    use crate::{Diagnostic, Transpiler, TranspilerConfig};
+
+    /// Translate `uses` of `union callback` with `--fn-pointer-unions`, returning why it was not
+    /// translated into an enum, if it wasn't
+    fn rejection(uses: &str) -> Option<String> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("callback.c");
+        let source = format!(
+            "union callback {{ int (*func)(int); const int *value; }};\n\
+             static int call(union callback *cb, int x) {{ return cb->func(x); }}\n\
+             {}\n",
+            uses
+        );
+        fs::write(&path, source).unwrap();
+
+        let messages = Arc::new(Mutex::new(vec![]));
+        let sink_messages = Arc::clone(&messages);
+        let config = TranspilerConfig {
+            fn_pointer_unions: true,
+            ..TranspilerConfig::default()
+        };
+        Transpiler::new(config)
+            .on_diagnostic(move |message| {
+                if message.diagnostic == Some(Diagnostic::Unions) {
+                    sink_messages.lock().unwrap().push(message.message.clone());
+                }
+            })
+            .transpile_file(&path)
+            .unwrap();
+        let prefix = "Union callback mixes function and data pointers, but cannot be translated \
+                      into an enum: ";
+        let messages = messages.lock().unwrap();
+        messages
+            .iter()
+            .find_map(|message| message.strip_prefix(prefix))
+            .map(str::to_owned)
+    }
+
+    #[test]
+    fn accepted() {
+        let uses = "\
+static int twice(int x) { return 2 * x; }
+int entry(void) { union callback cb = { twice }; return call(&cb, 2); }";
+        assert_eq!(rejection(uses), None);
+    }
+
+    #[test]
+    fn shared_with_functions() {
+        let reason = "it is passed to or returned from a function that is defined elsewhere or \
+                      externally visible";
+        // A defined function that is externally visible
+        let uses = "int run(union callback *cb) { return call(cb, 1); }";
+        assert_eq!(rejection(uses).as_deref(), Some(reason));
+        // Within the fields of a struct, and arrays
+        let uses = "\
+struct handlers { union callback cbs[2]; };
+void install(struct handlers *handlers);
+void setup(void) { struct handlers handlers = { 0 }; install(&handlers); }";
+        assert_eq!(rejection(uses).as_deref(), Some(reason));
+    }
+
+    #[test]
+    fn shared_with_variables() {
+        let reason = "it is stored in an externally visible variable";
+        // A variable defined here, rather than only declared
+        assert_eq!(rejection("union callback handlers[4];").as_deref(), Some(reason));
+    }
+
+    #[test]
+    fn cast() {
+        let reason = "a pointer to it is cast to another pointer type";
+        let uses = "\
+static void *erase(union callback *cb) { return cb; }
+void *erased(void) { static union callback cb; return erase(&cb); }";
+        assert_eq!(rejection(uses).as_deref(), Some(reason));
+    }
+}
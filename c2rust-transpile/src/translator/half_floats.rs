@@ -0,0 +1,94 @@
+//! This module provides translation of the half-precision floating point types `_Float16`,
+//! `__fp16` and `__bf16`, which have no Rust primitive equivalent.
+//!
+//! They are translated into the `f16` and `bf16` types of the `half` crate, which wrap a `u16`
+//! and so keep the size and alignment of the C types in structs. Arithmetic between values of the
+//! same type uses the operator impls of the crate, and values are converted at the boundaries
+//! with other arithmetic types:
+//!
+//! ```ignore
+//! half::f16::from_f32_const(x)        // (_Float16)x, x a float
+//! half::f16::from_f64_const(n as f64) // (_Float16)n, n an integer
+//! h.to_f32_const() as libc::c_int     // (int)h
+//! ```
+//!
+//! The `const` conversions are used so that initializers of statics remain constant expressions.
+
+use super::*;
+
+impl<'c> Translation<'c> {
+    /// Path of the `half` type that a half-precision type translates to
+    fn half_float_path(&self, kind: &CTypeKind) -> Vec<&'static str> {
+        match kind {
+            CTypeKind::BFloat16 => vec!["half", "bf16"],
+            _ => vec!["half", "f16"],
+        }
+    }
+
+    /// An associated constant of a half-precision type, such as `ZERO` or `ONE`
+    pub fn half_float_const(&self, kind: &CTypeKind, name: &'static str) -> Box<Expr> {
+        let mut path = self.half_float_path(kind);
+        path.push(name);
+        mk().path_expr(path)
+    }
+
+    /// A floating point literal of a half-precision type
+    pub fn half_float_literal(&self, kind: &CTypeKind, lit: &str) -> Box<Expr> {
+        let mut path = self.half_float_path(kind);
+        path.push("from_f64_const");
+        let lit = mk().lit_expr(mk().float_lit(lit, "f64"));
+        mk().call_expr(mk().path_expr(path), vec![lit])
+    }
+
+    /// Convert `val` between arithmetic types, at least one of which is a half-precision type.
+    pub fn convert_half_float_cast(
+        &self,
+        val: Box<Expr>,
+        source: &CTypeKind,
+        target: &CTypeKind,
+        target_ty: Box<Type>,
+    ) -> Result<Box<Expr>, TranslationError> {
+        let no_args = vec![] as Vec<Box<Expr>>;
+
+        if target.is_half_float_type() {
+            let (from_fn, val) = match source {
+                CTypeKind::Float => ("from_f32_const", val),
+                CTypeKind::Double => ("from_f64_const", val),
+                CTypeKind::LongDouble => {
+                    return Err(
+                        format_err!("Casting long double to {:?} is not supported", target).into(),
+                    )
+                }
+                _ if source.is_half_float_type() => (
+                    "from_f32_const",
+                    mk().method_call_expr(val, "to_f32_const", no_args),
+                ),
+                _ => (
+                    "from_f64_const",
+                    mk().cast_expr(val, mk().path_ty(vec!["f64"])),
+                ),
+            };
+
+            let mut path = self.half_float_path(target);
+            path.push(from_fn);
+            return Ok(mk().call_expr(mk().path_expr(path), vec![val]));
+        }
+
+        Ok(match target {
+            CTypeKind::Float => mk().method_call_expr(val, "to_f32_const", no_args),
+            CTypeKind::Double => mk().method_call_expr(val, "to_f64_const", no_args),
+            CTypeKind::LongDouble => {
+                self.use_crate(ExternCrate::F128);
+
+                let fn_path = mk().path_expr(vec!["f128", "f128", "new"]);
+                let val = mk().method_call_expr(val, "to_f64_const", no_args);
+                mk().call_expr(fn_path, vec![val])
+            }
+            // Every half-precision value is exactly representable as an `f32`
+            _ => mk().cast_expr(
+                mk().method_call_expr(val, "to_f32_const", no_args),
+                target_ty,
+            ),
+        })
+    }
+}
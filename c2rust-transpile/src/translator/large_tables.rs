@@ -0,0 +1,143 @@
+//! This module provides emission of large static array initializers, such as the tables that
+//! X-macros expand to.
+//!
+//! The pretty printer puts each element of an array literal that doesn't fit on one line on a
+//! line of its own, which makes tables with thousands of entries unwieldy. Initializers with more
+//! than `--large-table-threshold` elements are instead printed on their own, packing as many
+//! elements into each line as fit, and are spliced into the module once it has been printed.
+//! With `--extract-large-tables`, they are written to separate files pulled in with `include!`.
+
+use super::*;
+
+/// Width that lines of packed table elements are kept within, not counting indentation
+const TABLE_LINE_WIDTH: usize = 96;
+
+/// Placeholder macro marking where a packed table is spliced into the printed module
+const TABLE_PLACEHOLDER: &str = "__c2rust_large_table";
+
+/// A large array initializer printed outside of the pretty printer
+pub struct LargeTable {
+    /// The array literal, packed into lines
+    text: String,
+    /// The file the table is extracted to, with `--extract-large-tables`
+    file_name: Option<String>,
+}
+
+impl<'c> Translation<'c> {
+    /// Print the initializer of static `name` separately if it is an array literal with more
+    /// elements than the large table threshold, returning the placeholder or `include!` that
+    /// stands in for it.
+    pub fn convert_large_table(&self, name: &str, init: Box<Expr>) -> Box<Expr> {
+        let text = match *init {
+            Expr::Array(ExprArray { ref elems, .. })
+                if elems.len() > self.tcfg.large_table_threshold =>
+            {
+                pack_array(elems.iter())
+            }
+            _ => return init,
+        };
+
+        let mut large_tables = self.large_tables.borrow_mut();
+        let (macro_name, arg) = if self.tcfg.extract_large_tables {
+            let file_name = format!("{}_{}.rs", self.main_module_name, name);
+            let arg = TokenTree::Literal(proc_macro2::Literal::string(&file_name));
+            large_tables.push(LargeTable {
+                text,
+                file_name: Some(file_name),
+            });
+            ("include", arg)
+        } else {
+            let arg =
+                TokenTree::Literal(proc_macro2::Literal::usize_unsuffixed(large_tables.len()));
+            large_tables.push(LargeTable {
+                text,
+                file_name: None,
+            });
+            (TABLE_PLACEHOLDER, arg)
+        };
+
+        mk().mac_expr(mk().mac(
+            vec![macro_name],
+            vec![arg].into_iter().collect::<TokenStream>(),
+            MacroDelimiter::Paren(Default::default()),
+        ))
+    }
+
+    /// Splice the packed tables into the printed module, returning the names and contents of the
+    /// files that tables were extracted to.
+    pub fn splice_large_tables(&self, translation: &mut String) -> Vec<(String, String)> {
+        let mut extracted = vec![];
+        for (idx, table) in self.large_tables.borrow_mut().drain(..).enumerate() {
+            if let Some(file_name) = table.file_name {
+                extracted.push((file_name, table.text + "\n"));
+                continue;
+            }
+
+            let placeholder = format!("{}!({})", TABLE_PLACEHOLDER, idx);
+            let pos = match translation.find(&placeholder) {
+                Some(pos) => pos,
+                None => {
+                    warn!("Missing placeholder for large table {}", idx);
+                    continue;
+                }
+            };
+            let line_start = translation[..pos].rfind('\n').map_or(0, |nl| nl + 1);
+            let indent: String = translation[line_start..pos]
+                .chars()
+                .take_while(|c| *c == ' ')
+                .collect();
+            let text = table.text.replace('\n', &format!("\n{}", indent));
+            translation.replace_range(pos..pos + placeholder.len(), &text);
+        }
+        extracted
+    }
+}
+
+/// Print the elements of an array literal packed into lines
+fn pack_array<'a>(elems: impl Iterator<Item = &'a Expr>) -> String {
+    let mut text = String::from("[\n");
+    let mut line = String::new();
+    let flush = |text: &mut String, line: &mut String| {
+        if !line.is_empty() {
+            text.push_str("    ");
+            text.push_str(line);
+            text.push('\n');
+            line.clear();
+        }
+    };
+
+    for elem in elems {
+        let elem = pprust::expr_to_string(elem);
+
+        // Elements that span several lines, like struct literals, get lines of their own
+        if elem.contains('\n') {
+            flush(&mut text, &mut line);
+            for (i, elem_line) in elem.lines().enumerate() {
+                // Continuation lines are indented as if in a function body
+                let elem_line = if i > 0 {
+                    elem_line.strip_prefix("    ").unwrap_or(elem_line)
+                } else {
+                    elem_line
+                };
+                text.push_str("    ");
+                text.push_str(elem_line);
+                text.push('\n');
+            }
+            text.insert(text.len() - 1, ',');
+            continue;
+        }
+
+        if !line.is_empty() && line.len() + elem.len() + 2 > TABLE_LINE_WIDTH {
+            flush(&mut text, &mut line);
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&elem);
+        line.push(',');
+    }
+
+    flush(&mut text, &mut line);
+    text.push(']');
+    text
+}
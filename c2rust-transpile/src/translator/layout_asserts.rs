@@ -0,0 +1,140 @@
+//! This module implements `--layout-asserts`, which checks the layout of each translated struct
+//! against the one clang computed for the C struct:
+//!
+//! ```ignore
+//! #[repr(C)]
+//! pub struct point {
+//!     pub tag: libc::c_char,
+//!     pub x: libc::c_long,
+//! }
+//! const _: () = assert!(::std::mem::size_of::<point>() == 16);
+//! const _: () = assert!(::std::mem::align_of::<point>() == 8);
+//! #[test]
+//! #[allow(non_snake_case)]
+//! fn point_layout() {
+//!     assert_eq!(offset_of!(point, tag), 0);
+//!     assert_eq!(offset_of!(point, x), 8);
+//! }
+//! ```
+//!
+//! The size and alignment fail the build as soon as they differ, e.g. after a field is edited or
+//! on another target. Field offsets can't be computed in constants yet, so they are checked by a
+//! test using `memoffset`. Offsets aren't checked for structs with bitfields, whose fields are
+//! merged into byte arrays, and structs with a `va_list` field are left alone.
+
+use super::*;
+
+use syn::__private::ToTokens;
+
+/// Suffix of the test checking the field offsets of a struct
+const LAYOUT_SUFFIX: &str = "_layout";
+
+impl<'c> Translation<'c> {
+    /// The layout assertions of the struct `decl_id`, translated as `name`, whose fields are in
+    /// the struct `fields_name`, if `--layout-asserts` is set.
+    pub fn struct_layout_asserts(
+        &self,
+        decl_id: CRecordId,
+        name: &str,
+        fields_name: &str,
+    ) -> Vec<Box<Item>> {
+        if !self.tcfg.layout_asserts {
+            return vec![];
+        }
+        let (fields, size, align) = match self.ast_context[decl_id].kind {
+            CDeclKind::Struct {
+                fields: Some(ref fields),
+                platform_byte_size,
+                platform_alignment,
+                ..
+            } => (fields, platform_byte_size, platform_alignment),
+            _ => return vec![],
+        };
+        let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+        let mem_fn = |fn_name: &str| {
+            let params = mk().angle_bracketed_args(vec![mk().path_ty(vec![name])]);
+            let path = vec![
+                mk().path_segment(std_or_core),
+                mk().path_segment("mem"),
+                mk().path_segment_with_args(fn_name, params),
+            ];
+            mk().call_expr(mk().abs_path_expr(path), vec![] as Vec<Box<Expr>>)
+        };
+
+        let mut items = vec![];
+        for (fn_name, value) in [("size_of", size), ("align_of", align)] {
+            let cond = mk().binary_expr(
+                BinOp::Eq(Default::default()),
+                mem_fn(fn_name),
+                mk().lit_expr(mk().int_unsuffixed_lit(value as u128)),
+            );
+            let assert = mk().mac_expr(mk().mac(
+                vec!["assert"],
+                cond.to_token_stream(),
+                MacroDelimiter::Paren(Default::default()),
+            ));
+            items.push(mk().const_item("_", mk().tuple_ty(vec![] as Vec<Box<Type>>), assert));
+        }
+
+        let mut offsets = vec![];
+        for &field_id in fields {
+            match self.ast_context[field_id].kind {
+                CDeclKind::Field {
+                    bitfield_width: None,
+                    platform_bit_offset,
+                    ..
+                } => {
+                    let field_name = self
+                        .type_converter
+                        .borrow()
+                        .resolve_field_name(Some(decl_id), field_id)
+                        .expect("Struct fields should already be named");
+                    offsets.push((field_name, platform_bit_offset / 8));
+                }
+                // The fields of bitfield structs don't line up with the C fields
+                _ => return items,
+            }
+        }
+        if offsets.is_empty() {
+            return items;
+        }
+
+        self.use_crate(ExternCrate::Memoffset);
+        let stmts = offsets
+            .into_iter()
+            .map(|(field_name, offset)| {
+                let offset_of = mk().mac_expr(mk().mac(
+                    mk().path("offset_of"),
+                    vec![
+                        TokenTree::Ident(mk().ident(fields_name)),
+                        TokenTree::Punct(Punct::new(',', Alone)),
+                        TokenTree::Ident(mk().ident(field_name)),
+                    ],
+                    MacroDelimiter::Paren(Default::default()),
+                ));
+                let mut tokens: Vec<TokenTree> = offset_of.to_token_stream().into_iter().collect();
+                tokens.push(TokenTree::Punct(Punct::new(',', Alone)));
+                tokens.push(TokenTree::Literal(proc_macro2::Literal::u64_unsuffixed(
+                    offset,
+                )));
+                mk().semi_stmt(mk().mac_expr(mk().mac(
+                    vec!["assert_eq"],
+                    tokens,
+                    MacroDelimiter::Paren(Default::default()),
+                )))
+            })
+            .collect();
+        let test_name = self
+            .type_converter
+            .borrow_mut()
+            .resolve_decl_suffix_name(decl_id, LAYOUT_SUFFIX)
+            .to_owned();
+        let test_decl = mk().fn_decl(test_name, vec![], None, ReturnType::Default);
+        items.push(
+            mk().single_attr("test")
+                .call_attr("allow", vec!["non_snake_case"])
+                .fn_item(test_decl, mk().block(stmts)),
+        );
+        items
+    }
+}
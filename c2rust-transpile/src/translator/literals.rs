@@ -5,6 +5,7 @@
 use failure::format_err;
 
 use super::*;
+use crate::convert_type::{fixed_width_type, libc_name};
 use std::iter;
 
 impl<'c> Translation<'c> {
@@ -15,6 +16,16 @@ impl<'c> Translation<'c> {
         val: u64,
         base: IntBase,
     ) -> Result<Box<Expr>, TranslationError> {
+        // With `--fixed-width-ints`, decimal literals get the suffix of their type instead of a cast
+        if self.tcfg.fixed_width_ints && base == IntBase::Dec {
+            let suffix = libc_name(&self.ast_context.resolve_type(ty.ctype).kind)
+                .and_then(|name| fixed_width_type(name, &self.ast_context.target))
+                .filter(|suffix| !suffix.starts_with('f'));
+            if let Some(suffix) = suffix {
+                return Ok(mk().lit_expr(mk().int_lit(val.into(), suffix)));
+            }
+        }
+
         let lit = match base {
             IntBase::Dec => mk().int_unsuffixed_lit(val.into()),
             IntBase::Hex => mk().float_unsuffixed_lit(&format!("0x{:x}", val)),
@@ -123,6 +134,7 @@ impl<'c> Translation<'c> {
                     }
                     CTypeKind::Double => mk().lit_expr(mk().float_lit(&*str, "f64")),
                     CTypeKind::Float => mk().lit_expr(mk().float_lit(&*str, "f32")),
+                    ref k if k.is_half_float_type() => self.half_float_literal(k, &str),
                     ref k => panic!("Unsupported floating point literal type {:?}", k),
                 };
                 Ok(WithStmts::new_val(val))
@@ -174,6 +186,10 @@ impl<'c> Translation<'c> {
     ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
         match self.ast_context.resolve_type(ty.ctype).kind {
             CTypeKind::ConstantArray(ty, n) => {
+                if let Some(embed) = self.convert_embed_init_list(ctx, ty, n, ids)? {
+                    return Ok(embed);
+                }
+
                 // Convert all of the provided initializer values
 
                 // Need to check to see if the next item is a string literal,
@@ -225,6 +241,9 @@ impl<'c> Translation<'c> {
                         .map(|vals| mk().array_expr(vals)))
                 }
             }
+            CTypeKind::Struct(struct_id) if self.tagged_unions.contains_key(&struct_id) => {
+                self.convert_tagged_union_literal(ctx, struct_id, ids)
+            }
             CTypeKind::Struct(struct_id) => {
                 let mut literal = self.convert_struct_literal(ctx, struct_id, ids.as_ref());
                 if self.ast_context.has_inner_struct_decl(struct_id) {
@@ -249,6 +268,11 @@ impl<'c> Translation<'c> {
                 let id = ids.first().unwrap();
                 self.convert_expr(ctx.used(), *id)
             }
+            CTypeKind::Vector(CQualTypeId { ctype, .. }, len)
+                if self.is_generic_vector_type(ty.ctype) =>
+            {
+                self.generic_vector_list_initializer(ctx, ids, ty.ctype, ctype, len)
+            }
             CTypeKind::Vector(CQualTypeId { ctype, .. }, len) => {
                 self.vector_list_initializer(ctx, ids, ctype, len)
             }
@@ -285,6 +309,12 @@ impl<'c> Translation<'c> {
                             self.convert_expr(ctx.used(), ids[0])?
                         };
 
+                        if self.fn_pointer_unions.contains(&union_id) {
+                            return Ok(val.map(|v| {
+                                self.fn_pointer_union_variant(union_id, union_field_id, v)
+                            }));
+                        }
+
                         Ok(val.map(|v| {
                             let name = vec![mk().path_segment(union_name)];
                             let field_name = self
@@ -0,0 +1,481 @@
+//! This module implements `--box-local-allocs`, which translates heap allocations that never
+//! leave the function allocating them into owned `Box`es and `Vec`s:
+//!
+//! ```c
+//! struct point *p = malloc(sizeof(struct point));
+//! int *counts = calloc(n, sizeof(int));
+//! if (!p || !counts) return -1;
+//! p->x = counts[n - 1];
+//! free(counts);
+//! free(p);
+//! ```
+//!
+//! becomes
+//!
+//! ```ignore
+//! let mut p: Box<point> = Box::new(point { x: 0, y: 0 });
+//! let mut counts: Vec<libc::c_int> = vec![0; n as usize];
+//! if false || false {
+//!     return -1;
+//! }
+//! (*p).x = counts[(n - 1) as usize];
+//! drop(counts);
+//! drop(p);
+//! ```
+//!
+//! A local pointer qualifies if it is initialized with `malloc(sizeof(T))` or `calloc(1,
+//! sizeof(T))`, which become a `Box<T>`, or with `malloc(n * sizeof(T))` or `calloc(n,
+//! sizeof(T))`, which become a `Vec<T>` of `n` elements. It must only be dereferenced, indexed
+//! (if it is a `Vec`) or compared against NULL, and freed exactly once by a `free` call in the
+//! block declaring it, after which it is not used anymore. Pointers that are assigned, passed
+//! to other functions, returned or used in pointer arithmetic are left alone, since the
+//! allocation may outlive the function or be freed elsewhere.
+//!
+//! Unlike `malloc`, the allocations are zero-initialized, and allocation failures abort rather
+//! than returning NULL, so NULL checks on them are always false. With `--emit-no-std`, `Box`
+//! and `Vec` are taken from the `alloc` crate, which the generated `lib.rs` links.
+
+use super::*;
+
+/// A local pointer variable translated into an owned allocation
+pub struct LocalAlloc {
+    /// The pointee type
+    elem: CTypeId,
+    /// The number of elements of a `Vec`, or `None` for a `Box`
+    count: Option<CExprId>,
+}
+
+impl<'c> Translation<'c> {
+    /// Find the local pointers selected by `--box-local-allocs`, and the `free` calls that drop
+    /// them.
+    pub fn find_local_allocs(&self) -> (HashMap<CDeclId, LocalAlloc>, HashSet<CExprId>) {
+        if !self.tcfg.box_local_allocs {
+            return (HashMap::new(), HashSet::new());
+        }
+
+        let mut candidates = self
+            .ast_context
+            .iter_decls()
+            .filter_map(|(&decl_id, _)| Some((decl_id, self.local_alloc_init(decl_id)?)))
+            .collect::<HashMap<_, _>>();
+        if candidates.is_empty() {
+            return (HashMap::new(), HashSet::new());
+        }
+
+        // Accesses whose address is taken, which may point past the end of a `Vec`
+        let mut addressed = HashSet::new();
+        for (_, expr) in self.ast_context.iter_exprs() {
+            if let CExprKind::Unary(_, c_ast::UnOp::AddressOf, mut arg, _) = expr.kind {
+                while let CExprKind::Paren(_, e) = self.ast_context[arg].kind {
+                    arg = e;
+                }
+                addressed.insert(arg);
+            }
+        }
+
+        // Count the references to each candidate, and those that the translation supports
+        let mut uses: HashMap<CDeclId, usize> = HashMap::new();
+        let mut supported: HashMap<CDeclId, usize> = HashMap::new();
+        let mut frees: HashMap<CDeclId, Vec<CExprId>> = HashMap::new();
+        let mut support = |ptr: CExprId| {
+            if let Some(decl_id) = self.local_ref(ptr) {
+                *supported.entry(decl_id).or_default() += 1;
+            }
+        };
+        for (_, stmt) in self.ast_context.iter_stmts() {
+            match stmt.kind {
+                CStmtKind::If {
+                    scrutinee: cond, ..
+                }
+                | CStmtKind::While {
+                    condition: cond, ..
+                }
+                | CStmtKind::DoWhile {
+                    condition: cond, ..
+                }
+                | CStmtKind::ForLoop {
+                    condition: Some(cond),
+                    ..
+                } => support(cond),
+                _ => {}
+            }
+        }
+        for (&expr_id, expr) in self.ast_context.iter_exprs() {
+            match expr.kind {
+                CExprKind::DeclRef(_, decl_id, _) if candidates.contains_key(&decl_id) => {
+                    *uses.entry(decl_id).or_default() += 1;
+                }
+                // `&*p` is translated into `p` itself
+                CExprKind::Unary(_, c_ast::UnOp::Deref, ptr, _)
+                    if !addressed.contains(&expr_id) =>
+                {
+                    support(ptr)
+                }
+                CExprKind::Member(_, ptr, _, MemberKind::Arrow, _)
+                | CExprKind::UnaryType(_, UnTypeOp::SizeOf, Some(ptr), _) => support(ptr),
+                CExprKind::ArraySubscript(_, ptr, _, _) if !addressed.contains(&expr_id) => {
+                    let is_vec = self
+                        .local_ref(ptr)
+                        .and_then(|decl_id| candidates.get(&decl_id))
+                        .map_or(false, |alloc| alloc.count.is_some());
+                    if is_vec {
+                        support(ptr);
+                    }
+                }
+                // NULL checks
+                CExprKind::Unary(_, c_ast::UnOp::Not, ptr, _)
+                | CExprKind::Conditional(_, ptr, _, _) => support(ptr),
+                CExprKind::Binary(_, c_ast::BinOp::And | c_ast::BinOp::Or, lhs, rhs, _, _) => {
+                    support(lhs);
+                    support(rhs);
+                }
+                CExprKind::Binary(
+                    _,
+                    c_ast::BinOp::EqualEqual | c_ast::BinOp::NotEqual,
+                    lhs,
+                    rhs,
+                    _,
+                    _,
+                ) => {
+                    if self.ast_context.is_null_expr(rhs) {
+                        support(lhs);
+                    } else if self.ast_context.is_null_expr(lhs) {
+                        support(rhs);
+                    }
+                }
+                CExprKind::Call(_, func, ref args) if self.callee_name(func) == Some("free") => {
+                    if let [ptr] = args[..] {
+                        if let Some(decl_id) = self.local_ref(ptr) {
+                            frees.entry(decl_id).or_default().push(expr_id);
+                            support(ptr);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        candidates.retain(|decl_id, _| uses.get(decl_id) == supported.get(decl_id));
+
+        // The allocation must be freed once, in the block declaring it, and not be used after
+        let mut free_calls = HashSet::new();
+        for (_, stmt) in self.ast_context.iter_stmts() {
+            let stmts = match stmt.kind {
+                CStmtKind::Compound(ref stmts) => stmts,
+                _ => continue,
+            };
+            for (i, &decl_stmt) in stmts.iter().enumerate() {
+                let decls = match self.ast_context[decl_stmt].kind {
+                    CStmtKind::Decls(ref decls) => decls,
+                    _ => continue,
+                };
+                for decl_id in decls {
+                    let free = match frees.get(decl_id).map(Vec::as_slice) {
+                        Some(&[free]) if candidates.contains_key(decl_id) => free,
+                        _ => continue,
+                    };
+                    let rest = &stmts[i + 1..];
+                    let freed_at = rest.iter().position(|&stmt_id| {
+                        matches!(self.ast_context[stmt_id].kind, CStmtKind::Expr(e) if e == free)
+                    });
+                    if let Some(j) = freed_at {
+                        if !rest[j + 1..]
+                            .iter()
+                            .any(|&stmt_id| self.stmt_has_decl_reference(*decl_id, stmt_id))
+                        {
+                            free_calls.insert(free);
+                        }
+                    }
+                }
+            }
+        }
+        candidates.retain(|decl_id, _| {
+            frees.get(decl_id).map_or(false, |calls| {
+                calls.iter().all(|call| free_calls.contains(call))
+            })
+        });
+
+        let free_calls = candidates
+            .keys()
+            .flat_map(|decl_id| frees[decl_id].iter().copied())
+            .collect();
+        (candidates, free_calls)
+    }
+
+    /// The allocation initializing `decl_id`, if it is a local pointer to a type that can be
+    /// owned
+    fn local_alloc_init(&self, decl_id: CDeclId) -> Option<LocalAlloc> {
+        let (initializer, typ) = match self.ast_context[decl_id].kind {
+            CDeclKind::Variable {
+                has_static_duration: false,
+                has_thread_duration: false,
+                is_externally_visible: false,
+                initializer: Some(initializer),
+                typ,
+                ..
+            } => (initializer, typ),
+            _ => return None,
+        };
+        let pointee = match self.ast_context.resolve_type(typ.ctype).kind {
+            CTypeKind::Pointer(pointee) if !pointee.qualifiers.is_volatile => pointee,
+            _ => return None,
+        };
+        match self.ast_context.resolve_type(pointee.ctype).kind {
+            CTypeKind::Void | CTypeKind::Function(..) | CTypeKind::VariableArray(..) => {
+                return None
+            }
+            _ => {}
+        }
+        if self.ast_context.is_va_list(pointee.ctype) {
+            return None;
+        }
+
+        let is_elem_size = |expr_id: CExprId| match *self.ast_context.resolve_expr(expr_id).1 {
+            CExprKind::UnaryType(_, UnTypeOp::SizeOf, _, arg_ty) => {
+                self.ast_context.resolve_type_id(arg_ty.ctype)
+                    == self.ast_context.resolve_type_id(pointee.ctype)
+            }
+            _ => false,
+        };
+        let (func, args) = match *self.ast_context.resolve_expr(initializer).1 {
+            CExprKind::Call(_, func, ref args) => (func, args),
+            _ => return None,
+        };
+        let count = match (self.callee_name(func)?, &args[..]) {
+            ("malloc", &[size]) if is_elem_size(size) => None,
+            ("malloc", &[size]) => match *self.ast_context.resolve_expr(size).1 {
+                CExprKind::Binary(_, c_ast::BinOp::Multiply, lhs, rhs, _, _) => {
+                    if is_elem_size(rhs) {
+                        Some(lhs)
+                    } else if is_elem_size(lhs) {
+                        Some(rhs)
+                    } else {
+                        return None;
+                    }
+                }
+                _ => return None,
+            },
+            ("calloc", &[count, size]) if is_elem_size(size) => {
+                match *self.ast_context.resolve_expr(count).1 {
+                    CExprKind::Literal(_, CLiteral::Integer(1, _)) => None,
+                    _ => Some(count),
+                }
+            }
+            _ => return None,
+        };
+        Some(LocalAlloc {
+            elem: pointee.ctype,
+            count,
+        })
+    }
+
+    /// The name of the function `func` calls, if it is a direct call to a function without a
+    /// body, such as a libc function
+    fn callee_name(&self, func: CExprId) -> Option<&str> {
+        let callee = match self.ast_context[func].kind {
+            CExprKind::ImplicitCast(_, callee, CastKind::FunctionToPointerDecay, _, _) => callee,
+            _ => return None,
+        };
+        match self.ast_context[callee].kind {
+            CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[decl_id].kind {
+                CDeclKind::Function {
+                    ref name,
+                    body: None,
+                    ..
+                } => Some(name.as_str()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The variable `expr_id` refers to, looking through implicit casts and parentheses only
+    pub fn local_ref(&self, mut expr_id: CExprId) -> Option<CDeclId> {
+        loop {
+            match self.ast_context[expr_id].kind {
+                CExprKind::ImplicitCast(_, e, _, _, _) | CExprKind::Paren(_, e) => expr_id = e,
+                CExprKind::DeclRef(_, decl_id, _) => return Some(decl_id),
+                _ => return None,
+            }
+        }
+    }
+
+    fn stmt_has_decl_reference(&self, decl_id: CDeclId, stmt_id: CStmtId) -> bool {
+        DFExpr::new(&self.ast_context, stmt_id.into()).any(|node| match node {
+            SomeId::Expr(e) => {
+                matches!(self.ast_context[e].kind, CExprKind::DeclRef(_, d, _) if d == decl_id)
+            }
+            _ => false,
+        })
+    }
+
+    /// The type `name<elem_ty>`, taken from `alloc::module` in `no_std` crates
+    fn alloc_ty(&self, module: &str, name: &str, elem_ty: Box<Type>) -> Box<Type> {
+        let ty = mk().path_segment_with_args(name, mk().angle_bracketed_args(vec![elem_ty]));
+        if self.tcfg.emit_no_std {
+            let path = vec![mk().path_segment("alloc"), mk().path_segment(module), ty];
+            mk().path_ty(mk().abs_path(path))
+        } else {
+            mk().path_ty(vec![ty])
+        }
+    }
+
+    /// The path `name::function`, taken from `alloc::module` in `no_std` crates
+    fn alloc_path_expr(&self, module: &str, name: &str, function: &str) -> Box<Expr> {
+        if self.tcfg.emit_no_std {
+            mk().abs_path_expr(vec!["alloc", module, name, function])
+        } else {
+            mk().path_expr(vec![name, function])
+        }
+    }
+
+    /// The owned allocation `expr_id` refers to, along with the name of its variable
+    fn local_alloc(&self, expr_id: CExprId) -> Option<(&LocalAlloc, Box<Expr>)> {
+        let decl_id = self.local_ref(expr_id)?;
+        let alloc = self.local_allocs.get(&decl_id)?;
+        let name = self.renamer.borrow().get(&decl_id)?;
+        Some((alloc, mk().ident_expr(name)))
+    }
+
+    /// Translate the declaration of an owned allocation
+    pub fn convert_local_alloc_decl(
+        &self,
+        ctx: ExprContext,
+        decl_id: CDeclId,
+        rust_name: &str,
+    ) -> Result<Option<cfg::DeclStmtInfo>, TranslationError> {
+        let alloc = match self.local_allocs.get(&decl_id) {
+            Some(alloc) => alloc,
+            None => return Ok(None),
+        };
+
+        let elem_ty = self.convert_type(alloc.elem)?;
+        let mut stmts = vec![];
+        let elem = self.implicit_default_expr(alloc.elem, false)?;
+        stmts.extend(elem.stmts().iter().cloned());
+        let elem = elem.into_value();
+
+        let (ty, init, empty) =
+            match alloc.count {
+                None => {
+                    let ty = self.alloc_ty("boxed", "Box", elem_ty);
+                    let new = self.alloc_path_expr("boxed", "Box", "new");
+                    let init = mk().call_expr(new, vec![elem]);
+                    (ty, init, None)
+                }
+                Some(count) => {
+                    let ty = self.alloc_ty("vec", "Vec", elem_ty);
+                    let mut count = self.convert_expr(ctx.used(), count)?;
+                    stmts.append(count.stmts_mut());
+                    let count = mk().cast_expr(count.into_value(), mk().path_ty(vec!["usize"]));
+
+                    let init = if self.tcfg.emit_no_std {
+                        vec_expr(elem, count, true)
+                    } else {
+                        use syn::__private::ToTokens;
+                        let mut tokens = elem.to_token_stream();
+                        tokens.extend(vec![TokenTree::Punct(Punct::new(';', Alone))]);
+                        tokens.extend(count.to_token_stream());
+                        mk().mac_expr(mk().mac(
+                            vec!["vec"],
+                            tokens,
+                            MacroDelimiter::Bracket(Default::default()),
+                        ))
+                    };
+                    let new = self.alloc_path_expr("vec", "Vec", "new");
+                    let empty = mk().call_expr(new, Vec::<Box<Expr>>::new());
+                    (ty, init, Some(empty))
+                }
+            };
+
+        // A `Box` can't be created without allocating, so a hoisted declaration leaves it
+        // uninitialized until the assignment
+        let pat = || mk().set_mutbl("mut").ident_pat(rust_name);
+        let decl = mk().local(pat(), Some(ty.clone()), empty);
+        let assign = mk().assign_expr(mk().ident_expr(rust_name), init.clone());
+        let local = mk().local(pat(), Some(ty), Some(init));
+
+        let mut assign_stmts = stmts.clone();
+        assign_stmts.push(mk().semi_stmt(assign));
+        let mut decl_and_assign = stmts;
+        decl_and_assign.push(mk().local_stmt(Box::new(local)));
+
+        Ok(Some(cfg::DeclStmtInfo::new(
+            vec![mk().local_stmt(Box::new(decl))],
+            assign_stmts,
+            decl_and_assign,
+        )))
+    }
+
+    /// Translate `*p` and the base of `p->field` for an owned allocation `p`
+    pub fn convert_local_alloc_deref(&self, ptr: CExprId) -> Option<WithStmts<Box<Expr>>> {
+        let (alloc, ptr) = self.local_alloc(ptr)?;
+        let val = match alloc.count {
+            None => mk().unary_expr(UnOp::Deref(Default::default()), ptr),
+            Some(_) => mk().index_expr(ptr, mk().lit_expr(mk().int_unsuffixed_lit(0))),
+        };
+        Some(WithStmts::new_val(val))
+    }
+
+    /// Translate `p[index]` for an owned `Vec` `p`
+    pub fn convert_local_alloc_index(
+        &self,
+        ctx: ExprContext,
+        ptr: CExprId,
+        index: CExprId,
+    ) -> Result<Option<WithStmts<Box<Expr>>>, TranslationError> {
+        let ptr = match self.local_alloc(ptr) {
+            Some((LocalAlloc { count: Some(_), .. }, ptr)) => ptr,
+            _ => return Ok(None),
+        };
+        let index = self.convert_expr(ctx.used(), index)?;
+        Ok(Some(index.map(|index| {
+            mk().index_expr(ptr, mk().cast_expr(index, mk().path_ty(vec!["usize"])))
+        })))
+    }
+
+    /// Translate `free(p)` for an owned allocation `p` into `drop(p)`
+    pub fn convert_local_alloc_free(
+        &self,
+        call: CExprId,
+        args: &[CExprId],
+    ) -> Option<WithStmts<Box<Expr>>> {
+        if !self.local_alloc_frees.contains(&call) {
+            return None;
+        }
+        let (_, ptr) = self.local_alloc(args[0])?;
+        Some(WithStmts::new_val(
+            mk().call_expr(mk().path_expr(vec!["drop"]), vec![ptr]),
+        ))
+    }
+
+    /// Whether a NULL check on an owned allocation is true: `p` and `p != NULL` are always true,
+    /// `p == NULL` always false
+    pub fn local_alloc_non_null(&self, expr_id: CExprId) -> Option<bool> {
+        let is_local_alloc = |e| {
+            self.local_ref(e)
+                .map_or(false, |decl_id| self.local_allocs.contains_key(&decl_id))
+        };
+        if is_local_alloc(expr_id) {
+            return Some(true);
+        }
+        let mut expr_id = expr_id;
+        while let CExprKind::Paren(_, e) = self.ast_context[expr_id].kind {
+            expr_id = e;
+        }
+        match self.ast_context[expr_id].kind {
+            CExprKind::Binary(
+                _,
+                op @ (c_ast::BinOp::EqualEqual | c_ast::BinOp::NotEqual),
+                lhs,
+                rhs,
+                _,
+                _,
+            ) if (is_local_alloc(lhs) && self.ast_context.is_null_expr(rhs))
+                || (is_local_alloc(rhs) && self.ast_context.is_null_expr(lhs)) =>
+            {
+                Some(op == c_ast::BinOp::NotEqual)
+            }
+            _ => None,
+        }
+    }
+}
@@ -9,6 +9,10 @@ use proc_macro2::{TokenStream, TokenTree};
 
 impl<'c> Translation<'c> {
     pub fn convert_main(&self, main_id: CDeclId) -> Result<Box<Item>, TranslationError> {
+        // The arguments, environment and exit status of the process come from `std`
+        if self.tcfg.emit_no_std {
+            Err(format_err!("Translating main requires std, which --emit-no-std leaves out"))?;
+        }
         if let CDeclKind::Function {
             ref parameters,
             typ,
@@ -55,7 +59,7 @@ impl<'c> Translation<'c> {
                     Some(mk().path_ty(vec![mk().path_segment_with_args(
                         "Vec",
                         mk().angle_bracketed_args(vec![
-                            mk().mutbl().ptr_ty(mk().path_ty(vec!["libc", "c_char"])),
+                            mk().mutbl().ptr_ty(self.libc_ty("c_char")),
                         ]),
                     )])),
                     Some(
@@ -128,7 +132,7 @@ impl<'c> Translation<'c> {
                     Some(mk().path_ty(vec![mk().path_segment_with_args(
                         "Vec",
                         mk().angle_bracketed_args(vec![
-                            mk().mutbl().ptr_ty(mk().path_ty(vec!["libc", "c_char"])),
+                            mk().mutbl().ptr_ty(self.libc_ty("c_char")),
                         ]),
                     )])),
                     Some(
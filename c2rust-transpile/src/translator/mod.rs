@@ -1,6 +1,6 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::char;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::mem;
 use std::ops::Index;
 use std::path::{self, PathBuf};
@@ -11,8 +11,9 @@ use dtoa;
 use failure::{err_msg, format_err, Fail};
 use indexmap::indexmap;
 use indexmap::{IndexMap, IndexSet};
-use log::{error, info, trace, warn};
+use log::{info, trace, warn};
 use proc_macro2::{Punct, Spacing::*, Span, TokenStream, TokenTree};
+use serde_derive::Serialize;
 use syn::spanned::Spanned as _;
 use syn::*;
 use syn::{BinOp, UnOp}; // To override c_ast::{BinOp,UnOp} from glob import
@@ -21,41 +22,83 @@ use crate::rust_ast::comment_store::CommentStore;
 use crate::rust_ast::item_store::ItemStore;
 use crate::rust_ast::set_span::SetSpan;
 use crate::rust_ast::{pos_to_span, SpanExt, DUMMY_SP};
-use c2rust_ast_builder::{mk, properties::*, Builder};
+use c2rust_ast_builder::{mk, properties::*, Builder, CaptureBy};
 use c2rust_ast_printer::pprust::{self};
 
 use crate::c_ast::iterators::{DFExpr, SomeId};
 use crate::c_ast::*;
 use crate::cfg;
-use crate::convert_type::TypeConverter;
+use crate::convert_type::{call_conv_abi, TypeConverter};
 use crate::renamer::Renamer;
+use crate::report::{DeclReport, DeclStatus, FileReport, REPORT_EXTENSION};
+use crate::unsafe_reasons::{unique_operations, UnsafeOperation, UnsafeReason};
 use crate::with_stmts::WithStmts;
 use crate::{c_ast, format_translation_err};
+use crate::header_modules::HEADERS_MODULE;
 use crate::{ExternCrate, ExternCrateDetails, TranspilerConfig};
 use c2rust_ast_exporter::clang_ast::LRValue;
 
+mod abi_tests;
 mod assembly;
+mod asserts;
 mod atomics;
+mod bitfield_tests;
 mod builtins;
+mod candidates;
 mod comments;
+mod const_addresses;
+mod ctype;
+mod embed;
+mod enums;
+mod error_codes;
+mod ffi_tests;
+mod float_bits;
+mod fn_pointer_unions;
+mod half_floats;
+mod large_tables;
+mod layout_asserts;
 mod literals;
+mod local_allocs;
 mod main_function;
 mod named_references;
+mod nonnull_fn_ptrs;
+mod openmp;
 mod operators;
+mod outline;
+mod owned_alloca_strings;
+mod printf;
+mod pruning;
+mod range_loops;
+mod safe_wrappers;
+mod shared_inline_fns;
 mod simd;
+mod slice_fns;
+mod static_cstrs;
+mod struct_derives;
 mod structs;
+mod stubs;
+mod tagged_unions;
+mod test_stubs;
 mod variadic;
+mod variadic_shims;
+mod wasm;
 
+use crate::diagnostics::{diag, Diagnostic};
 pub use crate::diagnostics::{TranslationError, TranslationErrorKind};
 use crate::CrateSet;
 use crate::PragmaVec;
+pub use error_codes::ErrorCodes;
+use tagged_unions::TaggedUnion;
 
 pub const INNER_SUFFIX: &str = "_Inner";
 pub const PADDING_SUFFIX: &str = "_PADDING";
 
+/// Whether a reference to a C lvalue should be decayed into a raw pointer, e.g. when the
+/// address of an array or of a function is taken.
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum DecayRef {
     Yes,
+    /// No explicit choice was made; this behaves like `Yes`
     Default,
     No,
 }
@@ -92,13 +135,60 @@ impl From<bool> for DecayRef {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize)]
 pub enum ReplaceMode {
     None,
     Extern,
 }
 
+/// How C enums are translated
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub enum EnumStyle {
+    /// A type alias for the underlying integer type plus one `const` per enumerator
+    Consts,
+    /// A `#[repr]` Rust enum, for enums whose values are only ever produced from their own
+    /// enumerators; all other enums fall back to `Consts`
+    Rust,
+}
+
+/// The order in which the bits of bitfield structs are allocated
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub enum BitfieldOrder {
+    /// The order of the target ABI: most significant bit first on big endian targets, least
+    /// significant bit first otherwise
+    Target,
+    /// Least significant bit of the first byte first
+    Lsb0,
+    /// Most significant bit of the first byte first
+    Msb0,
+}
+
+/// The context in which a C expression is translated by `Translation::convert_expr`.
+///
+/// An `ExprContext` is built once per top-level translation with `ExprContext::default()` and then
+/// refined for each subexpression with the builder methods below. Each of these takes the context
+/// by value and returns an updated copy, so a context is never changed behind the back of the
+/// caller that handed it out.
+///
+/// The most important piece of state is whether the value of the expression is used:
+///
+///   * In a *used* context (`ExprContext::used`), the `val` of the resulting `WithStmts` is the
+///     value of the C expression, and its `stmts` are the side effects that have to run first.
+///
+///   * In an *unused* context (`ExprContext::unused`), e.g. for the expression of an expression
+///     statement like `p = 1;`, the caller only cares about the side effects. All of them must
+///     then be in `stmts`, and `val` is expected to be ignored. This lets us translate `p = 1;`
+///     into `p = 1` even though the Rust assignment evaluates to `()`. Translations that would
+///     otherwise drop a side effect use `Translation::convert_side_effects_expr` to move it into
+///     `stmts`.
+///
+/// Whether a `volatile` object is read is not part of the context: clang already marks each
+/// expression as an lvalue or an rvalue, and only rvalue uses of a `volatile` lvalue are
+/// translated into volatile reads. So given `volatile int n, *p;` and `int x;`, `x = n` reads `n`
+/// volatilely while `p = &n` does not, regardless of whether either expression is used. Writes to
+/// `volatile` lvalues are translated into volatile writes by the assignment operators.
 #[derive(Copy, Clone, Debug)]
+#[must_use]
 pub struct ExprContext {
     used: bool,
     is_static: bool,
@@ -120,10 +210,29 @@ pub struct ExprContext {
     expanding_macro: Option<CDeclId>,
 }
 
+impl Default for ExprContext {
+    /// The context of a top-level expression: used, not static and not const.
+    fn default() -> Self {
+        ExprContext {
+            used: true,
+            is_static: false,
+            is_const: false,
+            decay_ref: DecayRef::Default,
+            is_bitfield_write: false,
+            needs_address: false,
+            expecting_valistimpl: false,
+            ternary_needs_parens: false,
+            expanding_macro: None,
+        }
+    }
+}
+
 impl ExprContext {
+    /// The value of the expression is used.
     pub fn used(self) -> Self {
         ExprContext { used: true, ..self }
     }
+    /// Only the side effects of the expression matter.
     pub fn unused(self) -> Self {
         ExprContext {
             used: false,
@@ -136,12 +245,24 @@ impl ExprContext {
     pub fn is_unused(&self) -> bool {
         !self.used
     }
+    /// Decay references to lvalues into raw pointers.
     pub fn decay_ref(self) -> Self {
         ExprContext {
             decay_ref: DecayRef::Yes,
             ..self
         }
     }
+    pub fn decay_ref_mode(&self) -> DecayRef {
+        self.decay_ref
+    }
+    /// Is the expression part of the initializer of a static? Such expressions have to be
+    /// constant in Rust.
+    pub fn is_static(&self) -> bool {
+        self.is_static
+    }
+    pub fn is_const(&self) -> bool {
+        self.is_const
+    }
     pub fn not_static(self) -> Self {
         ExprContext {
             is_static: false,
@@ -210,6 +331,8 @@ pub struct FunContext {
     va_list_arg_name: Option<String>,
     /// The va_list decls that are either `va_start`ed or `va_copy`ed.
     va_list_decl_ids: Option<IndexSet<CDeclId>>,
+    /// Whether the function returns a non-null function pointer
+    returns_nonnull_fn_ptr: bool,
 }
 
 impl FunContext {
@@ -218,6 +341,7 @@ impl FunContext {
             name: None,
             va_list_arg_name: None,
             va_list_decl_ids: None,
+            returns_nonnull_fn_ptr: false,
         }
     }
 
@@ -225,6 +349,7 @@ impl FunContext {
         self.name = Some(fn_name.to_string());
         self.va_list_arg_name = None;
         self.va_list_decl_ids = None;
+        self.returns_nonnull_fn_ptr = false;
     }
 
     pub fn get_name(&self) -> &str {
@@ -258,6 +383,71 @@ pub struct Translation<'c> {
     function_context: RefCell<FunContext>,
     potential_flexible_array_members: RefCell<IndexSet<CDeclId>>,
     macro_expansions: RefCell<IndexMap<CDeclId, Option<MacroExpansion>>>,
+    // Enums translated into Rust enums (see `EnumStyle::Rust`)
+    rust_enums: HashSet<CEnumId>,
+    // Read-only lookup tables translated into immutable statics
+    const_tables: HashSet<CDeclId>,
+    // Structs translated into Rust enums (see `--tagged-union`), and the accesses to their union
+    // members that need a mutable borrow
+    tagged_unions: HashMap<CRecordId, TaggedUnion>,
+    tagged_union_writes: HashSet<CExprId>,
+    // Unions mixing function and data pointers translated into Rust enums (see
+    // `--fn-pointer-unions`), and the accesses to their members that need a mutable borrow
+    fn_pointer_unions: HashSet<CRecordId>,
+    fn_pointer_union_writes: HashSet<CExprId>,
+    // Functions returning only string literals translated to return `&'static CStr` (see
+    // `--static-cstr-returns`), and the values they return
+    static_cstr_fns: HashSet<CDeclId>,
+    static_cstr_returns: HashSet<CExprId>,
+    // `#pragma omp parallel for` loops translated into Rayon parallel iterators (see
+    // `--openmp-rayon`)
+    parallel_loops: HashMap<CStmtId, openmp::ParallelFor>,
+    // Local pointers translated into owned `Box`es and `Vec`s (see `--box-local-allocs`), and the
+    // `free` calls translated into drops
+    local_allocs: HashMap<CDeclId, local_allocs::LocalAlloc>,
+    local_alloc_frees: HashSet<CExprId>,
+    // Loop helpers given a safe slice-based version (see `--slice-fns`), and the function whose
+    // slice-based version is being translated
+    slice_fns: HashMap<CDeclId, slice_fns::SliceFn>,
+    cur_slice_fn: Cell<Option<CDeclId>>,
+    // Counted `for` loops translated into range loops (see `--range-loops`)
+    range_loops: HashMap<CStmtId, range_loops::RangeLoop>,
+    // Functions translated into both a literal and an idiomatic version (see
+    // `--candidate-function`), whether the idiomatic version is being translated, and the report
+    // on the translated candidates
+    candidate_fns: HashSet<CDeclId>,
+    idiomatic_candidate: Cell<bool>,
+    candidate_reports: RefCell<Vec<candidates::CandidateReport>>,
+    // What the function being translated is replaced with after it failed to translate
+    fn_stub: RefCell<Option<stubs::FnStub>>,
+    // The number of declarations that failed to translate
+    failure_count: Cell<usize>,
+    // The operations needing `unsafe` emitted so far, and the location of the innermost C
+    // expression being translated, which they are reported at (see `--emit-unsafe-reasons`)
+    unsafe_operations: RefCell<Vec<UnsafeOperation>>,
+    cur_expr_loc: Cell<Option<SrcSpan>>,
+    // Static array initializers printed separately (see `--large-table-threshold`), and the name
+    // of the translated module used to name the files they are extracted to
+    large_tables: RefCell<Vec<large_tables::LargeTable>>,
+    main_module_name: String,
+    // Bitfield structs checked against their C layout (see `--bitfield-tests`)
+    bitfield_test_structs: RefCell<Vec<CRecordId>>,
+    // Structs checked against their C layout, with the names of the structs holding their fields
+    // (see `--abi-tests`)
+    abi_test_structs: RefCell<Vec<(CRecordId, String, String)>>,
+    // Translated top-level declarations that get tests (see `--emit-test-stubs` and `--ffi-tests`)
+    tested_fns: RefCell<Vec<CDeclId>>,
+    // Header functions translated into the shared header modules by header module name, and the
+    // typedefs already translated into them (see `--share-inline-fns`)
+    shared_inline_fns: RefCell<IndexMap<String, Vec<Box<Item>>>>,
+    shared_typedefs: RefCell<HashSet<(FileId, CDeclId)>>,
+    // Header functions that are translated into this file instead of being shared, because other
+    // files define them differently
+    local_inline_fns: HashSet<String>,
+    // Calls to external variadic functions by function name (see `--variadic-shims`)
+    variadic_shims: RefCell<IndexMap<String, variadic_shims::VariadicShim>>,
+    // libc functions used, defined in the module for WebAssembly (see `--target`)
+    wasm_shims: RefCell<IndexSet<&'static str>>,
 
     // Comment support
     pub comment_context: CommentContext,      // Incoming comments
@@ -313,37 +503,32 @@ fn cast_int(val: Box<Expr>, name: &str, need_lit_suffix: bool) -> Box<Expr> {
     }
 }
 
-/// Pointer offset that casts its argument to isize
-fn pointer_offset(
-    ptr: Box<Expr>,
-    offset: Box<Expr>,
-    multiply_by: Option<Box<Expr>>,
-    neg: bool,
-    mut deref: bool,
-) -> Box<Expr> {
-    let mut offset = cast_int(offset, "isize", false);
+/// Space that must be left on the stack before recursing into the translation of a nested
+/// expression or statement, both of which have large stack frames
+const STACK_RED_ZONE: usize = 1024 * 1024;
 
-    if let Some(mul) = multiply_by {
-        let mul = cast_int(mul, "isize", false);
-        offset = mk().binary_expr(BinOp::Mul(Default::default()), offset, mul);
-        deref = false;
-    }
-
-    if neg {
-        offset = mk().unary_expr(UnOp::Neg(Default::default()), offset);
-    }
+/// Size of the new stack segments allocated when less than `STACK_RED_ZONE` is left
+const STACK_SEGMENT_SIZE: usize = 16 * 1024 * 1024;
 
-    let res = mk().method_call_expr(ptr, "offset", vec![offset]);
-    if deref {
-        mk().unary_expr(UnOp::Deref(Default::default()), res)
-    } else {
-        res
-    }
+/// Run `f`, on a new stack segment if the current one is running out
+pub(crate) fn ensure_sufficient_stack<R>(f: impl FnOnce() -> R) -> R {
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, f)
 }
 
 /// Given an expression with type Option<fn(...)->...>, unwrap
 /// the Option and return the function.
 fn unwrap_function_pointer(ptr: Box<Expr>) -> Box<Expr> {
+    // `Some(f)` comes from reading a non-null function pointer or a function
+    if let Expr::Call(ExprCall {
+        ref func, ref args, ..
+    }) = *ptr
+    {
+        if let (Expr::Path(ExprPath { ref path, .. }), 1) = (&**func, args.len()) {
+            if path.is_ident("Some") {
+                return Box::new(args[0].clone());
+            }
+        }
+    }
     let err_msg = mk().lit_expr("non-null function pointer");
     mk().method_call_expr(ptr, "expect", vec![err_msg])
 }
@@ -370,8 +555,10 @@ fn transmute_expr(
     mk().call_expr(mk().abs_path_expr(path), vec![expr])
 }
 
-fn vec_expr(val: Box<Expr>, count: Box<Expr>) -> Box<Expr> {
-    let from_elem = mk().abs_path_expr(vec!["std", "vec", "from_elem"]);
+/// Generate `vec![val; count]`. `Vec` comes from `alloc` in `no_std` crates.
+fn vec_expr(val: Box<Expr>, count: Box<Expr>, no_std: bool) -> Box<Expr> {
+    let std_or_alloc = if no_std { "alloc" } else { "std" };
+    let from_elem = mk().abs_path_expr(vec![std_or_alloc, "vec", "from_elem"]);
     mk().call_expr(from_elem, vec![val, count])
 }
 
@@ -403,6 +590,56 @@ pub fn stmts_block(mut stmts: Vec<Stmt>) -> Box<Block> {
 
 // Generate link attributes needed to ensure that the generated Rust libraries have the right symbol
 // values.
+/// Decode the text of a static assert message with `width`-byte characters. Bytes and code units
+/// that don't form a valid character are written as escapes, so that the message reads like the
+/// C source rather than losing them.
+fn static_assert_message(bytes: &[u8], width: u8) -> String {
+    let mut message = String::new();
+    if width == 1 {
+        let mut bytes = bytes;
+        while !bytes.is_empty() {
+            match std::str::from_utf8(bytes) {
+                Ok(valid) => {
+                    message.push_str(valid);
+                    break;
+                }
+                Err(e) => {
+                    let (valid, rest) = bytes.split_at(e.valid_up_to());
+                    message.push_str(std::str::from_utf8(valid).unwrap());
+                    let invalid_len = e.error_len().unwrap_or(rest.len());
+                    for b in &rest[..invalid_len] {
+                        message.push_str(&format!("\\x{:02x}", b));
+                    }
+                    bytes = &rest[invalid_len..];
+                }
+            }
+        }
+        return message;
+    }
+
+    let units = bytes.chunks(width as usize).map(|unit| {
+        unit.iter()
+            .rev()
+            .fold(0u32, |acc, &b| (acc << 8) | u32::from(b))
+    });
+    if width == 2 {
+        for c in char::decode_utf16(units.map(|unit| unit as u16)) {
+            match c {
+                Ok(c) => message.push(c),
+                Err(e) => message.push_str(&format!("\\u{{{:x}}}", e.unpaired_surrogate())),
+            }
+        }
+    } else {
+        for unit in units {
+            match char::from_u32(unit) {
+                Some(c) => message.push(c),
+                None => message.push_str(&format!("\\u{{{:x}}}", unit)),
+            }
+        }
+    }
+    message
+}
+
 fn mk_linkage(in_extern_block: bool, new_name: &str, old_name: &str) -> Builder {
     if new_name == old_name {
         if in_extern_block {
@@ -499,33 +736,35 @@ fn clean_path(mod_names: &RefCell<IndexMap<String, PathBuf>>, path: Option<&path
     file_path
 }
 
-pub fn translate_failure(tcfg: &TranspilerConfig, msg: &str) {
-    error!("{}", msg);
-    if tcfg.fail_on_error {
-        panic!("Translation failed, see error above");
-    }
-}
-
+/// Translate the C file `main_file`. With `--fail-on-error`, the translation fails if any of
+/// its declarations failed to translate.
 pub fn translate(
     ast_context: TypedAstContext,
     tcfg: &TranspilerConfig,
     main_file: PathBuf,
-) -> (String, PragmaVec, CrateSet) {
+    local_inline_fns: &HashSet<String>,
+) -> Result<
+    (
+        String,
+        PragmaVec,
+        CrateSet,
+        Vec<(String, String)>,
+        ErrorCodes,
+        Vec<Box<Item>>,
+    ),
+    failure::Error,
+> {
     let mut t = Translation::new(ast_context, tcfg, main_file.as_path());
-    let ctx = ExprContext {
-        used: true,
-        is_static: false,
-        is_const: false,
-        decay_ref: DecayRef::Default,
-        is_bitfield_write: false,
-        needs_address: false,
-        expecting_valistimpl: false,
-        ternary_needs_parens: false,
-        expanding_macro: None,
-    };
+    t.local_inline_fns = local_inline_fns.clone();
+    let ctx = ExprContext::default();
 
     {
-        t.use_crate(ExternCrate::Libc);
+        if !tcfg.is_wasm() {
+            t.use_crate(ExternCrate::Libc);
+        }
+        if t.ast_context.uses_half_floats() {
+            t.use_crate(ExternCrate::Half);
+        }
 
         // Sort the top-level declarations by file and source location so that we
         // preserve the ordering of all declarations in each file.
@@ -535,8 +774,34 @@ pub fn translate(
 
         // Headers often pull in declarations that are unused;
         // we simplify the translator output by omitting those.
-        t.ast_context
-            .prune_unwanted_decls(tcfg.preserve_unused_functions);
+        let bound_file = Some(t.main_file).filter(|_| tcfg.bindings);
+        let main_file = Some(t.main_file).filter(|_| tcfg.prune_unused);
+        let pruned = t.ast_context.prune_unwanted_decls(
+            tcfg.preserve_unused_functions,
+            main_file,
+            bound_file,
+        );
+
+        if tcfg.enum_style == EnumStyle::Rust {
+            t.rust_enums = t.find_rust_enums();
+        }
+        t.const_tables = t.find_const_tables();
+        let (tagged_unions, tagged_union_writes) = t.find_tagged_unions();
+        t.tagged_unions = tagged_unions;
+        t.tagged_union_writes = tagged_union_writes;
+        let (fn_pointer_unions, fn_pointer_union_writes) = t.find_fn_pointer_unions();
+        t.fn_pointer_unions = fn_pointer_unions;
+        t.fn_pointer_union_writes = fn_pointer_union_writes;
+        let (static_cstr_fns, static_cstr_returns) = t.find_static_cstr_fns();
+        t.static_cstr_fns = static_cstr_fns;
+        t.static_cstr_returns = static_cstr_returns;
+        t.parallel_loops = t.find_parallel_loops();
+        t.candidate_fns = t.find_candidate_fns();
+        let (local_allocs, local_alloc_frees) = t.find_local_allocs();
+        t.local_allocs = local_allocs;
+        t.local_alloc_frees = local_alloc_frees;
+        t.slice_fns = t.find_slice_fns();
+        t.range_loops = t.find_range_loops();
 
         enum Name<'a> {
             VarName(&'a str),
@@ -684,7 +949,8 @@ pub fn translate(
                     Err(e) => {
                         let ref k = t.ast_context.get_decl(&decl_id).map(|x| &x.kind);
                         let msg = format!("Skipping declaration {:?} due to error: {}", k, e);
-                        translate_failure(&t.tcfg, &msg);
+                        let loc = t.ast_context.display_loc(&t.ast_context[decl_id].loc);
+                        t.translate_failure(&msg, loc);
                     }
                 }
                 t.cur_file.borrow_mut().take();
@@ -718,25 +984,64 @@ pub fn translate(
         }
 
         // Export top-level value declarations
+        let mut failures = vec![];
+        let mut decl_reports = vec![];
         for top_id in &t.ast_context.c_decls_top {
             let needs_export = match t.ast_context[*top_id].kind {
                 CDeclKind::Function { is_implicit, .. } => !is_implicit,
                 CDeclKind::Variable { .. } => true,
                 CDeclKind::MacroObject { .. } => tcfg.translate_const_macros,
                 CDeclKind::MacroFunction { .. } => tcfg.translate_fn_macros,
+                CDeclKind::StaticAssert { .. } => true,
                 _ => false,
             };
+            if needs_export && t.tcfg.share_inline_fns {
+                if let Some(typedefs) = t.shareable_inline_fn(*top_id) {
+                    if let Err(e) = t.share_inline_fn(ctx, *top_id, &typedefs) {
+                        let msg = format!("Failed to share inline function: {}", e);
+                        let loc = t.ast_context.display_loc(&t.ast_context[*top_id].loc);
+                        t.translate_failure(&msg, loc);
+                    }
+                    continue;
+                }
+            }
             if needs_export {
                 let decl_opt = t.ast_context.get_decl(top_id);
                 let decl = decl_opt.as_ref().unwrap();
-                let decl_file_id = t.ast_context.file_id(decl);
+                let decl_file_id = if t.is_local_inline_fn(*top_id) {
+                    Some(t.main_file)
+                } else {
+                    t.ast_context.file_id(decl)
+                };
 
                 if t.tcfg.reorganize_definitions
                     && decl_file_id.map_or(false, |id| id != t.main_file)
                 {
                     *t.cur_file.borrow_mut() = decl_file_id;
                 }
-                match t.convert_decl(ctx, *top_id) {
+                let unsafe_start = t.unsafe_operations.borrow().len();
+                let converted = if t.candidate_fns.contains(top_id) {
+                    t.convert_candidate_fn(ctx, *top_id)
+                } else if t.slice_fns.contains_key(top_id) {
+                    t.convert_slice_fn(ctx, *top_id)
+                } else {
+                    t.convert_decl(ctx, *top_id)
+                }
+                .and_then(|converted| t.add_safe_wrapper(*top_id, converted));
+                if (t.tcfg.emit_test_stubs || t.tcfg.ffi_tests) && converted.is_ok() {
+                    t.tested_fns.borrow_mut().push(*top_id);
+                }
+                t.name_unsafe_operations(unsafe_start, decl, converted.is_ok());
+                let mut report = DeclReport {
+                    name: decl.kind.get_name().cloned().unwrap_or_default(),
+                    status: match converted {
+                        Ok(ConvertedDecl::ForeignItem(_)) => DeclStatus::Extern,
+                        Ok(_) => DeclStatus::Translated,
+                        Err(_) => DeclStatus::Failed,
+                    },
+                    error: None,
+                };
+                match converted {
                     Ok(ConvertedDecl::Item(item)) => {
                         t.insert_item(item, decl);
                     }
@@ -750,26 +1055,49 @@ pub fn translate(
                     }
                     Ok(ConvertedDecl::NoItem) => {}
                     Err(e) => {
-                        let ref decl = t.ast_context.get_decl(top_id);
-                        let msg = match decl {
-                            Some(decl) => {
-                                let decl_identifier = decl.kind.get_name().map_or_else(
-                                    || {
-                                        t.ast_context
-                                            .display_loc(&decl.loc)
-                                            .map_or("Unknown".to_string(), |l| format!("at {}", l))
-                                    },
-                                    |name| name.clone(),
-                                );
-                                format!("Failed to translate {}: {}", decl_identifier, e)
+                        let decl_identifier = decl.kind.get_name().map_or_else(
+                            || {
+                                t.ast_context
+                                    .display_loc(&decl.loc)
+                                    .map_or("Unknown".to_string(), |l| format!("at {}", l))
+                            },
+                            |name| name.clone(),
+                        );
+                        let msg = format!("Failed to translate {}: {}", decl_identifier, e);
+                        t.translate_failure(&msg, t.ast_context.display_loc(&decl.loc));
+
+                        // Functions are replaced with stubs, so that their callers still build
+                        let stub = t.convert_fn_stub(ctx, *top_id, &e);
+                        let error = e.to_string().lines().next().unwrap_or_default().to_owned();
+                        if stub.is_some() {
+                            report.status = DeclStatus::Stubbed;
+                        }
+                        report.error = Some(error.clone());
+                        failures.push(stubs::Failure {
+                            decl: decl_identifier,
+                            error,
+                            stubbed: stub.is_some(),
+                        });
+                        match stub {
+                            Some(ConvertedDecl::Item(item)) => t.insert_item(item, decl),
+                            Some(ConvertedDecl::Items(items)) => {
+                                for item in items {
+                                    t.insert_item(item, decl);
+                                }
                             }
-                            _ => format!("Failed to translate declaration: {}", e,),
-                        };
-                        translate_failure(&t.tcfg, &msg);
+                            _ => {}
+                        }
                     }
                 }
                 t.cur_file.borrow_mut().take();
 
+                // Only definitions of functions count towards the coverage, along with every
+                // declaration that failed to translate
+                let is_fn_def = matches!(decl.kind, CDeclKind::Function { body: Some(_), .. });
+                if is_fn_def || report.error.is_some() {
+                    decl_reports.push(report);
+                }
+
                 if t.tcfg.reorganize_definitions
                     && decl_file_id.map_or(false, |id| id != t.main_file)
                 {
@@ -778,16 +1106,23 @@ pub fn translate(
             }
         }
 
-        // Add the main entry point
-        if let Some(main_id) = t.ast_context.c_main {
+        // Add the main entry point, which bindings leave to C
+        if let Some(main_id) = t.ast_context.c_main.filter(|_| !tcfg.bindings) {
             match t.convert_main(main_id) {
                 Ok(item) => t.items.borrow_mut()[&t.main_file].add_item(item),
                 Err(e) => {
                     let msg = format!("Failed to translate main: {}", e);
-                    translate_failure(&t.tcfg, &msg)
+                    let loc = t.ast_context.display_loc(&t.ast_context[main_id].loc);
+                    t.translate_failure(&msg, loc);
+                    failures.push(stubs::Failure {
+                        decl: "main".to_owned(),
+                        error: e.to_string().lines().next().unwrap_or_default().to_owned(),
+                        stubbed: false,
+                    });
                 }
             }
         }
+        stubs::summarize_failures(&main_file, &failures);
 
         // Initialize global statics when necessary
         if !t.sectioned_static_initializers.borrow().is_empty() {
@@ -802,10 +1137,18 @@ pub fn translate(
         let crates = t.extern_crates.borrow().clone();
 
         let mut mod_items: Vec<Box<Item>> = Vec::new();
+        let mut header_mods: Vec<Box<Item>> = Vec::new();
 
         // Keep track of new uses we need while building header submodules
         let mut new_uses = ItemStore::new();
 
+        // With `--header-modules`, the header submodules are shared by the whole crate
+        let submodule_parent: &[&str] = if t.tcfg.header_modules {
+            &["crate", HEADERS_MODULE]
+        } else {
+            &["self"]
+        };
+
         // Header Reorganization: Submodule Item Stores
         for (file_id, ref mut mod_item_store) in t.items.borrow_mut().iter_mut() {
             if *file_id != t.main_file {
@@ -815,7 +1158,13 @@ pub fn translate(
                     *file_id,
                     &mut new_uses,
                     &t.mod_names,
+                    submodule_parent,
+                    t.default_abi(),
                 );
+                if t.tcfg.header_modules {
+                    header_mods.push(submodule);
+                    continue;
+                }
                 let comments = t.comment_context.get_remaining_comments(*file_id);
                 submodule.set_span(match t.comment_store.borrow_mut().add_comments(&comments) {
                     Some(pos) => submodule.span().with_hi(pos),
@@ -824,8 +1173,37 @@ pub fn translate(
                 mod_items.push(submodule);
             }
         }
+        header_mods.extend(t.shared_inline_fn_modules());
 
         // Main file item store
+        let bitfield_test_files = match t.bitfield_tests() {
+            Some((test_mod, files)) => {
+                t.items.borrow_mut()[&t.main_file].add_item(test_mod);
+                files
+            }
+            None => vec![],
+        };
+        let abi_test_files = match t.abi_tests() {
+            Some((test_mod, files)) => {
+                t.items.borrow_mut()[&t.main_file].add_item(test_mod);
+                files
+            }
+            None => vec![],
+        };
+        let test_stub_files = match t.test_stubs() {
+            Some((test_mod, files)) => {
+                t.items.borrow_mut()[&t.main_file].add_item(test_mod);
+                files
+            }
+            None => vec![],
+        };
+        let ffi_test_files = match t.ffi_tests() {
+            Some((test_mod, files)) => {
+                t.items.borrow_mut()[&t.main_file].add_item(test_mod);
+                files
+            }
+            None => vec![],
+        };
         let (items, foreign_items, uses) = t.items.borrow_mut()[&t.main_file].drain();
 
         // Re-order comments
@@ -867,7 +1245,7 @@ pub fn translate(
         // let comments = Comments::new(reordered_comment_store.into_comments());
 
         // pass all converted items to the Rust pretty printer
-        let translation = pprust::to_string(|| {
+        let mut translation = pprust::to_string(|| {
             let (attrs, mut all_items) = arrange_header(&t, t.tcfg.is_binary(main_file.as_path()));
 
             all_items.extend(mod_items);
@@ -883,9 +1261,13 @@ pub fn translate(
             all_items.extend(new_uses.into_items());
 
             if !foreign_items.is_empty() {
-                all_items.push(mk().extern_("C").foreign_items(foreign_items));
+                all_items.push(mk().extern_(t.default_abi()).foreign_items(foreign_items));
             }
 
+            // The shims have to be defined before the functions using them
+            all_items.extend(t.variadic_shim_macros());
+            all_items.extend(t.wasm_shim_items());
+
             // Add the items accumulated
             all_items.extend(items);
 
@@ -896,11 +1278,56 @@ pub fn translate(
                 items: all_items.into_iter().map(|x| *x).collect(),
             }
         });
-        (translation, pragmas, crates)
+        let mut extra_files = t.splice_large_tables(&mut translation);
+        extra_files.extend(t.candidate_report());
+        extra_files.extend(t.pruned_report(&pruned));
+        extra_files.extend(bitfield_test_files);
+        extra_files.extend(abi_test_files);
+        extra_files.extend(test_stub_files);
+        extra_files.extend(ffi_test_files);
+        let unsafe_operations = t.unsafe_operations.take();
+        let unsafe_operations = unique_operations(&unsafe_operations)
+            .into_iter()
+            .filter(|operation| !operation.function.is_empty())
+            .collect::<Vec<_>>();
+        if tcfg.emit_unsafe_reasons {
+            let file_name = format!("{}.unsafe.json", t.main_module_name);
+            let contents =
+                serde_json::to_string_pretty(&unsafe_operations).expect("Unable to encode report");
+            extra_files.push((file_name, contents + "\n"));
+        }
+        if tcfg.report.is_some() {
+            let report = FileReport::new(
+                &t.ast_context,
+                &main_file,
+                decl_reports,
+                unsafe_operations.len(),
+                &translation,
+            );
+            let file_name = format!("{}.{}", t.main_module_name, REPORT_EXTENSION);
+            let contents = serde_json::to_string_pretty(&report).expect("Unable to encode report");
+            extra_files.push((file_name, contents + "\n"));
+        }
+        let error_codes = t.find_error_codes();
+        if tcfg.fail_on_error && t.failure_count.get() > 0 {
+            return Err(format_err!(
+                "{} declarations of {} failed to translate, see the errors above",
+                t.failure_count.get(),
+                main_file.display()
+            ));
+        }
+        Ok((
+            translation,
+            pragmas,
+            crates,
+            extra_files,
+            error_codes,
+            header_mods,
+        ))
     }
 }
 
-fn item_ident(i: &Item) -> Option<&Ident> {
+pub(crate) fn item_ident(i: &Item) -> Option<&Ident> {
     Some(match i {
         Item::Const(ic) => &ic.ident,
         Item::Enum(ie) => &ie.ident,
@@ -961,7 +1388,7 @@ fn item_vis(i: &Item) -> Option<Visibility> {
     )
 }
 
-fn foreign_item_ident_vis(fi: &ForeignItem) -> Option<(&Ident, Visibility)> {
+pub(crate) fn foreign_item_ident_vis(fi: &ForeignItem) -> Option<(&Ident, Visibility)> {
     Some(match fi {
         ForeignItem::Fn(ifn) => (&ifn.sig.ident, ifn.vis.clone()),
         ForeignItem::Static(is) => (&is.ident, is.vis.clone()),
@@ -984,6 +1411,8 @@ fn make_submodule(
     file_id: FileId,
     use_item_store: &mut ItemStore,
     mod_names: &RefCell<IndexMap<String, PathBuf>>,
+    parent_path: &[&str],
+    foreign_abi: &'static str,
 ) -> Box<Item> {
     let (mut items, foreign_items, uses) = item_store.drain();
     let file_path = ast_context.get_file_path(file_id);
@@ -997,7 +1426,7 @@ fn make_submodule(
             Some(i) => i.to_string(),
             None => continue,
         };
-        let use_path = vec!["self".into(), mod_name.clone()];
+        let use_path = submodule_path(parent_path, &mod_name);
 
         let vis = match item_vis(item) {
             Some(Visibility::Public(_)) => mk().pub_(),
@@ -1008,12 +1437,17 @@ fn make_submodule(
         use_item_store.add_use_with_attr(use_path, &ident_name, vis);
     }
 
-    for foreign_item in foreign_items.iter() {
+    // Functions with a different ABI than `foreign_abi` are declared in their own extern blocks
+    let other_abi_items = items.iter().flat_map(|item| match &**item {
+        Item::ForeignMod(ifm) => ifm.items.as_slice(),
+        _ => &[],
+    });
+    for foreign_item in foreign_items.iter().chain(other_abi_items) {
         let ident_name = match foreign_item_ident_vis(foreign_item) {
             Some((ident, _vis)) => ident.to_string(),
             None => continue,
         };
-        let use_path = vec!["self".into(), mod_name.clone()];
+        let use_path = submodule_path(parent_path, &mod_name);
 
         use_item_store.add_use(use_path, &ident_name);
     }
@@ -1023,7 +1457,7 @@ fn make_submodule(
     }
 
     if !foreign_items.is_empty() {
-        items.push(mk().extern_("C").foreign_items(foreign_items));
+        items.push(mk().extern_(foreign_abi).foreign_items(foreign_items));
     }
 
     let file_path_str = file_path.map_or(mod_name.as_str(), |path| {
@@ -1037,6 +1471,13 @@ fn make_submodule(
         .mod_item(mod_name, Some(mk().mod_(items)))
 }
 
+/// The path of a header submodule for importing from it in the main module
+fn submodule_path(parent_path: &[&str], mod_name: &str) -> Vec<String> {
+    let mut path: Vec<String> = parent_path.iter().map(|&segment| segment.into()).collect();
+    path.push(mod_name.into());
+    path
+}
+
 // TODO(kkysen) shouldn't need `extern crate`
 /// Pretty-print the leading pragmas and extern crate declarations
 fn arrange_header(t: &Translation, is_binary: bool) -> (Vec<syn::Attribute>, Vec<Box<Item>>) {
@@ -1090,22 +1531,6 @@ fn arrange_header(t: &Translation, is_binary: bool) -> (Vec<syn::Attribute>, Vec
     (out_attrs, out_items)
 }
 
-/// Convert a boolean expression to a c_int
-fn bool_to_int(val: Box<Expr>) -> Box<Expr> {
-    mk().cast_expr(val, mk().path_ty(vec!["libc", "c_int"]))
-}
-
-/// Add a src_loc = "line:col" attribute to an item/foreign_item
-fn add_src_loc_attr(attrs: &mut Vec<syn::Attribute>, src_loc: &Option<SrcLoc>) {
-    if let Some(src_loc) = src_loc.as_ref() {
-        let loc_str = format!("{}:{}", src_loc.line, src_loc.column);
-        let meta = mk().meta_namevalue(vec!["c2rust", "src_loc"], loc_str);
-        let prepared = mk().prepare_meta(meta);
-        let attr = mk().attribute(AttrStyle::Outer, prepared.path, prepared.tokens);
-        attrs.push(attr);
-    }
-}
-
 /// Get a mutable reference to the attributes of a ForeignItem
 fn foreign_item_attrs(item: &mut ForeignItem) -> Option<&mut Vec<syn::Attribute>> {
     Some(match item {
@@ -1142,6 +1567,14 @@ fn item_attrs(item: &mut Item) -> Option<&mut Vec<syn::Attribute>> {
     })
 }
 
+/// Attach `#[cfg(<predicate>)]` to a translated item.
+fn add_cfg_attr(item: &mut Item, predicate: NestedMeta) {
+    if let Some(attrs) = item_attrs(item) {
+        let cfg = mk().meta_item_attr(AttrStyle::Outer, mk().meta_list("cfg", vec![predicate]));
+        attrs.extend(cfg.into_attrs());
+    }
+}
+
 /// Unwrap a layer of parenthesization from an Expr, if present
 fn unparen(expr: &Box<Expr>) -> &Box<Expr> {
     match **expr {
@@ -1150,31 +1583,6 @@ fn unparen(expr: &Box<Expr>) -> &Box<Expr> {
     }
 }
 
-/// This represents all of the ways a C expression can be used in a C program. Making this
-/// distinction is important for:
-///
-///   * not generating a bunch of unnecessary code, e.g., the expression `p = 1` evaluates `1`,
-///     but when used in a statement like `p = 1;`, we don't care about this, so we can translate
-///     to the Rust `p = 1` (even if it evaluates to the unit type). We get this behaviour by
-///     translating expression statements using `ExprUse::Unused`.
-///
-///   * handling `volatile` properly, e.g., suppose `volatile int n, *p;` and `int x;`.
-///     Then, `x = n` is a volatile read of `n` but `p = &n` is not. We get this behaviour
-///     by translating the argument of `&` using `ExprUse::LValue` and the right hand side of `=`
-///
-///     using `ExprUse::RValue`.
-///
-///   * handling `volatile` properly
-///
-/// See `Translation::convert_expr` for more details.
-#[derive(Copy, Clone, Debug, PartialOrd, PartialEq, Ord, Eq)]
-pub enum ExprUse {
-    /// expressions interesting only for their side-effects - we don't care about their values
-    Unused,
-    /// expressions used for their values
-    Used,
-}
-
 /// Declarations can be converted into a normal item, or into a foreign item.
 /// Foreign items are called out specially because we'll combine all of them
 /// into a single extern block at the end of translation.
@@ -1194,11 +1602,17 @@ impl<'c> Translation<'c> {
     ) -> Self {
         let comment_context = CommentContext::new(&mut ast_context);
         let mut type_converter = TypeConverter::new(tcfg.emit_no_std);
+        type_converter.c_unwind = tcfg.c_unwind;
+        type_converter.fixed_width_ints = tcfg.fixed_width_ints;
 
         if tcfg.translate_valist {
             type_converter.translate_valist = true
         }
 
+        let main_module_name = main_file
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().replace('-', "_"))
+            .unwrap_or_default();
         let main_file = ast_context.find_file_id(main_file).unwrap_or(0);
         let items = indexmap! {main_file => ItemStore::new()};
 
@@ -1224,6 +1638,37 @@ impl<'c> Translation<'c> {
             function_context: RefCell::new(FunContext::new()),
             potential_flexible_array_members: RefCell::new(IndexSet::new()),
             macro_expansions: RefCell::new(IndexMap::new()),
+            rust_enums: HashSet::new(),
+            const_tables: HashSet::new(),
+            tagged_unions: HashMap::new(),
+            tagged_union_writes: HashSet::new(),
+            fn_pointer_unions: HashSet::new(),
+            fn_pointer_union_writes: HashSet::new(),
+            static_cstr_fns: HashSet::new(),
+            static_cstr_returns: HashSet::new(),
+            parallel_loops: HashMap::new(),
+            local_allocs: HashMap::new(),
+            local_alloc_frees: HashSet::new(),
+            slice_fns: HashMap::new(),
+            cur_slice_fn: Cell::new(None),
+            range_loops: HashMap::new(),
+            candidate_fns: HashSet::new(),
+            idiomatic_candidate: Cell::new(false),
+            candidate_reports: RefCell::new(Vec::new()),
+            fn_stub: RefCell::new(None),
+            failure_count: Cell::new(0),
+            unsafe_operations: RefCell::new(Vec::new()),
+            cur_expr_loc: Cell::new(None),
+            large_tables: RefCell::new(Vec::new()),
+            main_module_name,
+            bitfield_test_structs: RefCell::new(Vec::new()),
+            abi_test_structs: RefCell::new(Vec::new()),
+            tested_fns: RefCell::new(Vec::new()),
+            shared_inline_fns: RefCell::new(IndexMap::new()),
+            shared_typedefs: RefCell::new(HashSet::new()),
+            local_inline_fns: HashSet::new(),
+            variadic_shims: RefCell::new(IndexMap::new()),
+            wasm_shims: RefCell::new(IndexSet::new()),
             comment_context,
             comment_store: RefCell::new(CommentStore::new()),
             spans: HashMap::new(),
@@ -1236,10 +1681,134 @@ impl<'c> Translation<'c> {
         }
     }
 
+    /// Report a declaration that failed to translate
+    fn translate_failure(&self, msg: &str, loc: Option<DisplaySrcSpan>) {
+        diag!(Diagnostic::TranslationFailure, at loc, "{}", msg);
+        self.failure_count.set(self.failure_count.get() + 1);
+    }
+
+    /// Record an operation needing `unsafe` emitted for the C expression being translated
+    pub fn record_unsafe(&self, reason: UnsafeReason) {
+        self.record_unsafe_at(self.cur_expr_loc.get(), reason)
+    }
+
+    /// Record an operation needing `unsafe` emitted for the C code at `loc`
+    pub fn record_unsafe_at(&self, loc: Option<SrcSpan>, reason: UnsafeReason) {
+        if !self.tcfg.emit_unsafe_reasons && self.tcfg.report.is_none() {
+            return;
+        }
+        let loc = match self.ast_context.display_loc(&loc) {
+            Some(loc) => loc,
+            None => return,
+        };
+        self.unsafe_operations.borrow_mut().push(UnsafeOperation {
+            file: loc.file().map(|path| path.display().to_string()),
+            line: loc.span().begin_line,
+            column: loc.span().begin_column,
+            // Filled in once the enclosing function has translated
+            function: String::new(),
+            reason,
+        });
+    }
+
+    /// Attribute the operations needing `unsafe` recorded since `start` to the function `decl`,
+    /// if it translated. The others are left unnamed and dropped, as they were emitted for
+    /// something other than the body of a translated function, such as a static initializer.
+    fn name_unsafe_operations(&self, start: usize, decl: &CDecl, translated: bool) {
+        if let CDeclKind::Function {
+            ref name,
+            body: Some(_),
+            ..
+        } = decl.kind
+        {
+            if translated {
+                for operation in &mut self.unsafe_operations.borrow_mut()[start..] {
+                    operation.function = name.clone();
+                }
+            }
+        }
+    }
+
     fn use_crate(&self, extern_crate: ExternCrate) {
         self.extern_crates.borrow_mut().insert(extern_crate);
     }
 
+    /// The configuration of the function being translated, which `c2rust.toml` may override
+    pub fn fn_tcfg(&self) -> &'c TranspilerConfig {
+        let tcfg: &'c TranspilerConfig = self.tcfg;
+        let fun_context = self.function_context.borrow();
+        let name = match fun_context.name {
+            Some(ref name) => name,
+            None => return tcfg,
+        };
+        tcfg.function_configs.get(name).unwrap_or(tcfg)
+    }
+
+    /// With `--debug-pointers`, calls to the C allocation functions are redirected to the
+    /// allocation-tracking shim in `c2rust-analysis-rt`, which records the bounds of each
+    /// allocation for `pointer_offset` and answers `malloc_usable_size` from them.
+    fn checked_allocator_fn(&self, fexp: CExprId) -> Option<Box<Expr>> {
+        if !self.tcfg.debug_pointers {
+            return None;
+        }
+        let decl_id = match self.ast_context[fexp].kind {
+            CExprKind::DeclRef(_, decl_id, _) => decl_id,
+            _ => return None,
+        };
+        match self.ast_context[decl_id].kind {
+            CDeclKind::Function {
+                ref name,
+                body: None,
+                ..
+            } if ["malloc", "calloc", "realloc", "free", "malloc_usable_size"]
+                .contains(&name.as_str()) =>
+            {
+                self.use_crate(ExternCrate::C2RustAnalysisRt);
+                Some(mk().abs_path_expr(vec!["c2rust_analysis_rt", "allocations", name.as_str()]))
+            }
+            _ => None,
+        }
+    }
+
+    /// Pointer offset that casts its argument to isize. With `--debug-pointers`, the offset is
+    /// checked against the bounds of the allocation `ptr` points into by `c2rust-analysis-rt`.
+    fn pointer_offset(
+        &self,
+        ptr: Box<Expr>,
+        offset: Box<Expr>,
+        multiply_by: Option<Box<Expr>>,
+        neg: bool,
+        mut deref: bool,
+    ) -> Box<Expr> {
+        let mut offset = cast_int(offset, "isize", false);
+
+        if let Some(mul) = multiply_by {
+            let mul = cast_int(mul, "isize", false);
+            offset = mk().binary_expr(BinOp::Mul(Default::default()), offset, mul);
+            deref = false;
+        }
+
+        if neg {
+            offset = mk().unary_expr(UnOp::Neg(Default::default()), offset);
+        }
+
+        let res = if self.tcfg.debug_pointers {
+            self.use_crate(ExternCrate::C2RustAnalysisRt);
+            let check_fn = if deref { "index" } else { "offset" };
+            mk().call_expr(
+                mk().abs_path_expr(vec!["c2rust_analysis_rt", "bounds", check_fn]),
+                vec![ptr, offset],
+            )
+        } else {
+            mk().method_call_expr(ptr, "offset", vec![offset])
+        };
+        if deref {
+            mk().unary_expr(UnOp::Deref(Default::default()), res)
+        } else {
+            res
+        }
+    }
+
     pub fn cur_file(&self) -> FileId {
         if let Some(cur_file) = *self.cur_file.borrow() {
             cur_file
@@ -1264,22 +1833,70 @@ impl<'c> Translation<'c> {
         self.features.borrow_mut().insert(feature);
     }
 
+    /// The ABI of extern blocks and of functions that aren't annotated with one
+    fn default_abi(&self) -> &'static str {
+        self.abi(self.tcfg.c_unwind)
+    }
+
+    /// The ABI of a function with the given attributes. Functions with a calling convention
+    /// other than C's, such as `__stdcall`, get the Rust ABI of the same name. C code that may
+    /// unwind through Rust frames, e.g. by calling `longjmp`, `pthread_exit` or C++ code that
+    /// throws, needs `extern "C-unwind"`. `--c-unwind` makes it the default, and the
+    /// `__attribute__((annotate("c2rust::unwind")))` and `annotate("c2rust::nounwind")`
+    /// annotations override it for a single function.
+    fn function_abi(&self, attrs: &IndexSet<c_ast::Attribute>) -> &'static str {
+        let call_conv = attrs.iter().find_map(|attr| match attr {
+            c_ast::Attribute::CallConv(call_conv) => call_conv_abi(call_conv),
+            _ => None,
+        });
+        if let Some((abi, feature)) = call_conv {
+            if let Some(feature) = feature {
+                self.use_feature(feature);
+            }
+            return abi;
+        }
+
+        let unwind = attrs
+            .iter()
+            .fold(self.tcfg.c_unwind, |unwind, attr| match attr {
+                c_ast::Attribute::Annotate(a) if a == "c2rust::unwind" => true,
+                c_ast::Attribute::Annotate(a) if a == "c2rust::nounwind" => false,
+                _ => unwind,
+            });
+        self.abi(unwind)
+    }
+
+    fn abi(&self, unwind: bool) -> &'static str {
+        if unwind {
+            self.use_feature("c_unwind");
+            "C-unwind"
+        } else {
+            "C"
+        }
+    }
+
     pub fn get_pragmas(&self) -> PragmaVec {
         let mut features = vec![];
         features.extend(self.features.borrow().iter());
         features.extend(self.type_converter.borrow().features_used());
-        let mut pragmas: PragmaVec = vec![(
-            "allow",
-            vec![
-                "non_upper_case_globals",
-                "non_camel_case_types",
-                "non_snake_case",
-                "dead_code",
-                "mutable_transmutes",
-                "unused_mut",
-                "unused_assignments",
-            ],
-        )];
+        let mut pragmas: Vec<(&str, Vec<&str>)> = vec![];
+
+        // With targeted allows, the lints each item triggers are allowed on that item
+        // after checking the emitted crate.
+        if !self.tcfg.targeted_allows {
+            pragmas.push((
+                "allow",
+                vec![
+                    "non_upper_case_globals",
+                    "non_camel_case_types",
+                    "non_snake_case",
+                    "dead_code",
+                    "mutable_transmutes",
+                    "unused_mut",
+                    "unused_assignments",
+                ],
+            ));
+        }
 
         features.push("register_tool");
         pragmas.push(("register_tool", vec!["c2rust"]));
@@ -1288,6 +1905,9 @@ impl<'c> Translation<'c> {
             pragmas.push(("feature", features));
         }
         pragmas
+            .into_iter()
+            .map(|(key, vals)| (key.to_owned(), vals.into_iter().map(String::from).collect()))
+            .collect()
     }
 
     // This node should _never_ show up in the final generated code. This is an easy way to notice
@@ -1300,6 +1920,33 @@ impl<'c> Translation<'c> {
         self.panic_or_err_helper(msg, true)
     }
 
+    /// The address of `place` in a static initializer. This uses `addr_of!` rather than `&`,
+    /// since references to `static mut`s are not allowed.
+    pub fn static_addr_of(&self, place: Box<Expr>) -> Box<Expr> {
+        use syn::__private::ToTokens;
+        let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+        mk().mac_expr(mk().mac(
+            mk().abs_path(vec![std_or_core, "ptr", "addr_of"]),
+            place.to_token_stream(),
+            MacroDelimiter::Paren(Default::default()),
+        ))
+    }
+
+    /// Whether `expr_id` is an lvalue whose address can be taken with `static_addr_of`
+    pub fn is_static_place(&self, expr_id: CExprId) -> bool {
+        match self.ast_context[expr_id].kind {
+            CExprKind::DeclRef(_, decl_id, _) => matches!(
+                self.ast_context[decl_id].kind,
+                CDeclKind::Variable {
+                    has_static_duration: true,
+                    ..
+                }
+            ),
+            CExprKind::Member(_, base, _, MemberKind::Dot, _) => self.is_static_place(base),
+            _ => false,
+        }
+    }
+
     fn panic_or_err_helper(&self, msg: &str, panic: bool) -> Box<Expr> {
         let macro_name = if panic { "panic" } else { "compile_error" };
         let macro_msg = vec![TokenTree::Literal(proc_macro2::Literal::string(msg))]
@@ -1357,6 +2004,87 @@ impl<'c> Translation<'c> {
         false
     }
 
+    /// Find the `static const` arrays of scalars that can be translated into immutable `static`
+    /// items instead of `static mut` ones, so that reading them needs no `unsafe`.
+    ///
+    /// We only do this for internal, initialized tables whose every use is an element read
+    /// (`table[i]`) or a `sizeof`. Any other use, such as letting the array decay into a pointer
+    /// or taking the address of an element, expects the mutable pointers of a `static mut`.
+    fn find_const_tables(&self) -> HashSet<CDeclId> {
+        let mut candidates = HashSet::new();
+        for (&decl_id, decl) in self.ast_context.iter_decls() {
+            if let CDeclKind::Variable {
+                has_static_duration: true,
+                has_thread_duration: false,
+                is_externally_visible: false,
+                initializer: Some(initializer),
+                typ,
+                ..
+            } = decl.kind
+            {
+                let elt = match self.ast_context.resolve_type(typ.ctype).kind {
+                    CTypeKind::ConstantArray(elt, _) => elt,
+                    _ => continue,
+                };
+                // Clang hoists the qualifiers of the elements onto the array type
+                let elt_ty = &self.ast_context.resolve_type(elt).kind;
+                let is_scalar = elt_ty.is_integral_type() || elt_ty.is_floating_type();
+                if is_scalar
+                    && typ.qualifiers.is_const
+                    && !self.static_initializer_is_unsafe(Some(initializer), typ)
+                    && !self.static_initializer_is_uncompilable(Some(initializer), typ)
+                {
+                    candidates.insert(decl_id);
+                }
+            }
+        }
+
+        // Strip parentheses and the array-to-pointer decay to find the table being indexed
+        let table_ref = |mut expr_id: CExprId| loop {
+            match self.ast_context[expr_id].kind {
+                CExprKind::Paren(_, e)
+                | CExprKind::ImplicitCast(_, e, CastKind::ArrayToPointerDecay, _, _) => expr_id = e,
+                CExprKind::DeclRef(_, decl_id, _) => return Some(decl_id),
+                _ => return None,
+            }
+        };
+
+        // Element accesses whose address is taken
+        let mut addressed = HashSet::new();
+        for (_, expr) in self.ast_context.iter_exprs() {
+            if let CExprKind::Unary(_, c_ast::UnOp::AddressOf, mut arg, _) = expr.kind {
+                while let CExprKind::Paren(_, e) = self.ast_context[arg].kind {
+                    arg = e;
+                }
+                addressed.insert(arg);
+            }
+        }
+
+        let mut uses: HashMap<CDeclId, usize> = HashMap::new();
+        let mut reads: HashMap<CDeclId, usize> = HashMap::new();
+        for (&expr_id, expr) in self.ast_context.iter_exprs() {
+            match expr.kind {
+                CExprKind::DeclRef(_, decl_id, _) if candidates.contains(&decl_id) => {
+                    *uses.entry(decl_id).or_default() += 1;
+                }
+                CExprKind::ArraySubscript(_, lhs, rhs, _) if !addressed.contains(&expr_id) => {
+                    if let Some(decl_id) = table_ref(lhs).or_else(|| table_ref(rhs)) {
+                        *reads.entry(decl_id).or_default() += 1;
+                    }
+                }
+                CExprKind::UnaryType(_, UnTypeOp::SizeOf, Some(arg), _) => {
+                    if let Some(decl_id) = table_ref(arg) {
+                        *reads.entry(decl_id).or_default() += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        candidates.retain(|decl_id| uses.get(decl_id) == reads.get(decl_id));
+        candidates
+    }
+
     /// The purpose of this function is to decide on whether or not a static initializer's
     /// translation is able to be compiled as a valid rust static initializer
     fn static_initializer_is_uncompilable(
@@ -1379,14 +2107,20 @@ impl<'c> Translation<'c> {
             return true;
         }
 
-        let iter = DFExpr::new(&self.ast_context, expr_id.into());
+        let mut iter = DFExpr::new(&self.ast_context, expr_id.into());
 
-        for i in iter {
+        while let Some(i) = iter.next() {
             let expr_id = match i {
                 SomeId::Expr(expr_id) => expr_id,
                 _ => unreachable!("Found static initializer type other than expr"),
             };
 
+            // Addresses of elements and fields of statics are lowered into constants
+            if self.is_const_address(expr_id) {
+                iter.prune_children(i);
+                continue;
+            }
+
             match self.ast_context[expr_id].kind {
                 // Technically we're being conservative here, but it's only the most
                 // contrived array indexing initializers that would be accepted
@@ -1395,12 +2129,8 @@ impl<'c> Translation<'c> {
 
                 CExprKind::Conditional(..) => return true,
                 CExprKind::Unary(typ, Negate, _, _) => {
-                    if self
-                        .ast_context
-                        .resolve_type(typ.ctype)
-                        .kind
-                        .is_unsigned_integral_type()
-                    {
+                    let k = &self.ast_context.resolve_type(typ.ctype).kind;
+                    if k.is_unsigned_integral_type() || k.is_half_float_type() {
                         return true;
                     }
                 }
@@ -1419,7 +2149,9 @@ impl<'c> Translation<'c> {
 
                     if problematic_op {
                         let k = &self.ast_context.resolve_type(typ.ctype).kind;
-                        if k.is_unsigned_integral_type() || k.is_pointer() {
+                        // Arithmetic on `half` types isn't `const`
+                        if k.is_unsigned_integral_type() || k.is_pointer() || k.is_half_float_type()
+                        {
                             return true;
                         }
                     }
@@ -1624,14 +2356,22 @@ impl<'c> Translation<'c> {
                     }
                 }
 
+                if self.tagged_unions.contains_key(&decl_id) {
+                    return self.convert_tagged_union(s, decl_id, &name);
+                }
+
                 // Gather up all the field names and field types
                 let (field_entries, contains_va_list) =
                     self.convert_struct_fields(decl_id, fields, platform_byte_size)?;
 
                 let mut derives = vec![];
+                let mut default_impl = false;
                 if !contains_va_list {
                     derives.push("Copy");
                     derives.push("Clone");
+                    let (extra_derives, needs_default_impl) = self.struct_derives(decl_id);
+                    derives.extend(extra_derives);
+                    default_impl = needs_default_impl;
                 };
                 let has_bitfields =
                     fields
@@ -1640,9 +2380,21 @@ impl<'c> Translation<'c> {
                             CDeclKind::Field { bitfield_width, .. } => bitfield_width.is_some(),
                             _ => unreachable!("Found non-field in record field list"),
                         });
+                let mut bitfield_order = None;
                 if has_bitfields {
                     derives.push("BitfieldStruct");
                     self.use_crate(ExternCrate::C2RustBitfields);
+                    bitfield_order = self.bitfield_order_attr();
+
+                    // Tests refer to the struct from the main module
+                    if self.tcfg.bitfield_tests
+                        && self
+                            .cur_file
+                            .borrow()
+                            .map_or(true, |id| id == self.main_file)
+                    {
+                        self.bitfield_test_structs.borrow_mut().push(decl_id);
+                    }
                 }
 
                 let mut reprs = vec![simple_metaitem("C")];
@@ -1676,14 +2428,34 @@ impl<'c> Translation<'c> {
                     // would significantly complicate the implementation
                     assert!(self.ast_context.has_inner_struct_decl(decl_id));
                     let inner_name = self.resolve_decl_inner_name(decl_id);
+                    let layout_asserts = self.struct_layout_asserts(decl_id, &name, &inner_name);
+                    self.add_abi_test(decl_id, &name, &inner_name);
+                    let default_impl = if default_impl {
+                        Some(self.struct_default_impl(decl_id, &inner_name)?)
+                    } else {
+                        None
+                    };
+                    // The outer struct implements whatever the inner one does
+                    let mut outer_derives = derives
+                        .iter()
+                        .copied()
+                        .filter(|&derive| derive != "BitfieldStruct")
+                        .collect::<Vec<_>>();
+                    if default_impl.is_some() {
+                        outer_derives.push("Default");
+                    }
                     let inner_ty = mk().path_ty(vec![inner_name.clone()]);
                     let inner_repr_attr = mk().meta_list("repr", reprs);
-                    let inner_struct = mk()
+                    let mut inner_mk = mk()
                         .span(s)
                         .pub_()
                         .call_attr("derive", derives)
-                        .meta_item_attr(AttrStyle::Outer, inner_repr_attr)
-                        .struct_item(inner_name.clone(), field_entries, false);
+                        .meta_item_attr(AttrStyle::Outer, inner_repr_attr);
+                    if let Some(order) = bitfield_order {
+                        inner_mk = inner_mk.meta_item_attr(AttrStyle::Outer, order);
+                    }
+                    let inner_struct =
+                        inner_mk.struct_item(inner_name.clone(), field_entries, false);
 
                     // https://github.com/rust-lang/rust/issues/33626
                     let outer_ty = mk().path_ty(vec![name.clone()]);
@@ -1697,7 +2469,7 @@ impl<'c> Translation<'c> {
                     let outer_struct = mk()
                         .span(s)
                         .pub_()
-                        .call_attr("derive", vec!["Copy", "Clone"])
+                        .call_attr("derive", outer_derives)
                         .meta_item_attr(AttrStyle::Outer, repr_attr)
                         .struct_item(name, vec![outer_field], true);
 
@@ -1717,7 +2489,10 @@ impl<'c> Translation<'c> {
                         .call_attr("allow", vec!["dead_code", "non_upper_case_globals"])
                         .const_item(padding_name, padding_ty, padding_value);
 
-                    let structs = vec![outer_struct, inner_struct, padding_const];
+                    let mut structs = vec![outer_struct, inner_struct];
+                    structs.extend(default_impl);
+                    structs.push(padding_const);
+                    structs.extend(layout_asserts);
                     Ok(ConvertedDecl::Items(structs))
                 } else {
                     assert!(!self.ast_context.has_inner_struct_decl(decl_id));
@@ -1729,15 +2504,32 @@ impl<'c> Translation<'c> {
                         .call_attr("derive", derives)
                         .meta_item_attr(AttrStyle::Outer, repr_attr);
 
+                    if let Some(order) = bitfield_order {
+                        mk_ = mk_.meta_item_attr(AttrStyle::Outer, order);
+                    }
+
+                    let mut layout_asserts = vec![];
                     if contains_va_list {
                         mk_ = mk_.generic_over(mk().lt_param(mk().ident("a")))
+                    } else {
+                        layout_asserts = self.struct_layout_asserts(decl_id, &name, &name);
+                        self.add_abi_test(decl_id, &name, &name);
                     }
 
-                    Ok(ConvertedDecl::Item(mk_.struct_item(
-                        name,
-                        field_entries,
-                        false,
-                    )))
+                    let default_impl = if default_impl {
+                        Some(self.struct_default_impl(decl_id, &name)?)
+                    } else {
+                        None
+                    };
+                    let struct_item = mk_.struct_item(name, field_entries, false);
+                    if layout_asserts.is_empty() && default_impl.is_none() {
+                        Ok(ConvertedDecl::Item(struct_item))
+                    } else {
+                        let mut items = vec![struct_item];
+                        items.extend(default_impl);
+                        items.extend(layout_asserts);
+                        Ok(ConvertedDecl::Items(items))
+                    }
                 }
             }
 
@@ -1772,6 +2564,10 @@ impl<'c> Translation<'c> {
                     }
                 }
 
+                if !field_syns.is_empty() && self.fn_pointer_unions.contains(&decl_id) {
+                    return self.convert_fn_pointer_union(s, decl_id, &name, fields);
+                }
+
                 let mut repr = vec!["C"];
                 if is_packed {
                     repr.push("packed");
@@ -1810,6 +2606,9 @@ impl<'c> Translation<'c> {
                     .borrow()
                     .resolve_decl_name(decl_id)
                     .expect("Enums should already be renamed");
+                if self.rust_enums.contains(&decl_id) {
+                    return self.convert_rust_enum(s, decl_id, enum_name, integral_type);
+                }
                 let ty = self.convert_type(integral_type.ctype)?;
                 Ok(ConvertedDecl::Item(
                     mk().span(s).pub_().type_item(enum_name, ty),
@@ -1831,10 +2630,18 @@ impl<'c> Translation<'c> {
                 if let Some(cur_file) = *self.cur_file.borrow() {
                     self.add_import(cur_file, enum_id, &enum_name);
                 }
-                let ty = mk().path_ty(mk().path(vec![enum_name]));
-                let val = match value {
-                    ConstIntExpr::I(value) => signed_int_expr(value),
-                    ConstIntExpr::U(value) => mk().lit_expr(mk().int_unsuffixed_lit(value as u128)),
+                let ty = mk().path_ty(mk().path(vec![enum_name.clone()]));
+                let val = if self.rust_enums.contains(&enum_id) {
+                    // Keep the constant as an alias of the variant, so that references to
+                    // enumerators translate the same way under both enum styles.
+                    mk().path_expr(vec![enum_name, name.clone()])
+                } else {
+                    match value {
+                        ConstIntExpr::I(value) => signed_int_expr(value),
+                        ConstIntExpr::U(value) => {
+                            mk().lit_expr(mk().int_unsuffixed_lit(value as u128))
+                        }
+                    }
                 };
 
                 Ok(ConvertedDecl::Item(
@@ -1862,13 +2669,17 @@ impl<'c> Translation<'c> {
                     .get(&decl_id)
                     .expect("Functions should already be renamed");
 
-                if self.import_simd_function(new_name)? {
+                if self.import_simd_function(new_name)?
+                    || self.import_neon_function(decl_id, new_name)
+                {
                     return Ok(ConvertedDecl::NoItem);
                 }
 
                 let (ret, is_var): (Option<CQualTypeId>, bool) =
                     match self.ast_context.resolve_type(typ).kind {
                         CTypeKind::Function(ret, _, is_var, is_noreturn, _) => {
+                            let is_noreturn =
+                                is_noreturn || attrs.contains(&c_ast::Attribute::NoReturn);
                             (if is_noreturn { None } else { Some(ret) }, is_var)
                         }
                         ref k => {
@@ -1895,17 +2706,77 @@ impl<'c> Translation<'c> {
                 }
 
                 let is_main = self.ast_context.c_main == Some(decl_id);
+                let is_static_cstr = self.returns_static_cstr(decl_id);
+
+                // Functions outside `--filter-fn` are declared for their callers and left to
+                // their C definitions, unless those don't have a symbol to link to, as for
+                // static and inline functions. So are the extern fallbacks of stubs, and all
+                // functions with `--bindings`, which leaves out those without a symbol.
+                let is_extern_stub = matches!(*self.fn_stub.borrow(), Some(stubs::FnStub::Extern));
+                let is_bound = self.tcfg.bindings;
+                if is_bound && body.is_some() && (!is_global || is_inline || is_main) {
+                    return Ok(ConvertedDecl::NoItem);
+                }
+                if body.is_some()
+                    && is_global
+                    && !is_inline
+                    && !is_main
+                    && (is_extern_stub || is_bound || !self.tcfg.filter_fns.is_match(name))
+                {
+                    if !is_extern_stub && !is_bound {
+                        info!("Declaring {} as extern since it is filtered out", name);
+                    }
+                    return self.convert_function(
+                        ctx, s, is_global, false, is_main, is_var, is_extern, false, new_name,
+                        name, &args, ret, None, attrs,
+                    );
+                }
 
                 let converted_function = self.convert_function(
-                    ctx, s, is_global, is_inline, is_main, is_var, is_extern, new_name, name,
-                    &args, ret, body, attrs,
+                    ctx,
+                    s,
+                    is_global,
+                    is_inline,
+                    is_main,
+                    is_var,
+                    is_extern,
+                    is_static_cstr,
+                    new_name,
+                    name,
+                    &args,
+                    ret,
+                    body,
+                    attrs,
                 );
 
                 converted_function.or_else(|e| match self.tcfg.replace_unsupported_decls {
                     ReplaceMode::Extern if body.is_none() => self.convert_function(
-                        ctx, s, is_global, false, is_main, is_var, is_extern, new_name, name,
-                        &args, ret, None, attrs,
+                        ctx, s, is_global, false, is_main, is_var, is_extern, false, new_name,
+                        name, &args, ret, None, attrs,
                     ),
+                    // The C definition of a function using fixed-point types can still be
+                    // linked in, so declare it for its callers with same-width integers
+                    ReplaceMode::Extern
+                        if e.kind() == TranslationErrorKind::FixedPoint
+                            && is_global
+                            && !is_main =>
+                    {
+                        warn!(
+                            "Declaring {} as extern since it uses fixed-point types, \
+                             its C definition must be linked in",
+                            name
+                        );
+                        let fixed_point_as_int = mem::replace(
+                            &mut self.type_converter.borrow_mut().fixed_point_as_int,
+                            true,
+                        );
+                        let declared = self.convert_function(
+                            ctx, s, is_global, false, is_main, is_var, is_extern, false, new_name,
+                            name, &args, ret, None, attrs,
+                        );
+                        self.type_converter.borrow_mut().fixed_point_as_int = fixed_point_as_int;
+                        declared
+                    }
                     _ => Err(e),
                 })
             }
@@ -1958,42 +2829,15 @@ impl<'c> Translation<'c> {
                     "An extern variable that isn't a definition can't have an initializer"
                 );
 
-                if has_thread_duration {
-                    self.use_feature("thread_local");
-                }
-
-                let new_name = self
-                    .renamer
-                    .borrow()
-                    .get(&decl_id)
-                    .expect("Variables should already be renamed");
-                let (ty, mutbl, _) = self.convert_variable(ctx.static_(), None, typ)?;
-                // When putting extern statics into submodules, they need to be public to be accessible
-                let visibility = if self.tcfg.reorganize_definitions {
-                    "pub"
-                } else {
-                    ""
-                };
-                let mut extern_item = mk_linkage(true, &new_name, ident)
-                    .span(s)
-                    .set_mutbl(mutbl)
-                    .vis(visibility);
-                if has_thread_duration {
-                    extern_item = extern_item.single_attr("thread_local");
-                }
-
-                for attr in attrs {
-                    extern_item = match attr {
-                        c_ast::Attribute::Alias(aliasee) => {
-                            extern_item.str_attr("link_name", aliasee)
-                        }
-                        _ => continue,
-                    };
-                }
-
-                Ok(ConvertedDecl::ForeignItem(
-                    extern_item.static_foreign_item(&new_name, ty),
-                ))
+                self.convert_extern_variable(
+                    ctx,
+                    decl_id,
+                    s,
+                    ident,
+                    typ,
+                    has_thread_duration,
+                    attrs,
+                )
             }
 
             // Static-storage or thread-local variable with initializer (definition here)
@@ -2007,6 +2851,23 @@ impl<'c> Translation<'c> {
                 ref attrs,
                 ..
             } if has_static_duration || has_thread_duration => {
+                // With `--bindings`, global variables are declared like those defined elsewhere,
+                // and those without a symbol are left out
+                if self.tcfg.bindings {
+                    if !is_externally_visible {
+                        return Ok(ConvertedDecl::NoItem);
+                    }
+                    return self.convert_extern_variable(
+                        ctx,
+                        decl_id,
+                        s,
+                        ident,
+                        typ,
+                        has_thread_duration,
+                        attrs,
+                    );
+                }
+
                 if has_thread_duration {
                     self.use_feature("thread_local");
                 }
@@ -2051,6 +2912,7 @@ impl<'c> Translation<'c> {
                     if self.static_initializer_is_unsafe(initializer, typ) {
                         init.set_unsafe()
                     }
+                    let init = init.map(|init| self.convert_large_table(new_name, init));
                     let init = init.to_unsafe_pure_expr().ok_or_else(|| {
                         format_err!("Expected no side-effects in static initializer")
                     })?;
@@ -2058,6 +2920,17 @@ impl<'c> Translation<'c> {
                     (ty, init)
                 };
 
+                if self.const_tables.contains(&decl_id) {
+                    let static_def = if self.cur_file.borrow().is_some() {
+                        mk().pub_()
+                    } else {
+                        mk()
+                    };
+                    return Ok(ConvertedDecl::Item(
+                        static_def.span(s).static_item(new_name, ty, init),
+                    ));
+                }
+
                 let static_def = if is_externally_visible {
                     mk_linkage(false, new_name, ident).pub_().extern_("C")
                 } else if self.cur_file.borrow().is_some() {
@@ -2143,11 +3016,113 @@ impl<'c> Translation<'c> {
             // their canonical declaration.
             CDeclKind::NonCanonicalDecl { .. } => Ok(ConvertedDecl::NoItem),
 
-            CDeclKind::StaticAssert { .. } => {
-                warn!("ignoring static assert during translation");
-                Ok(ConvertedDecl::NoItem)
+            CDeclKind::StaticAssert {
+                assert_expr,
+                message,
+            } => self.convert_static_assert(ctx, s, assert_expr, message),
+        }
+    }
+
+    /// Declare the global variable `decl_id`, whose definition is linked in from elsewhere
+    fn convert_extern_variable(
+        &self,
+        ctx: ExprContext,
+        decl_id: CDeclId,
+        s: Span,
+        ident: &str,
+        typ: CQualTypeId,
+        has_thread_duration: bool,
+        attrs: &IndexSet<c_ast::Attribute>,
+    ) -> Result<ConvertedDecl, TranslationError> {
+        if has_thread_duration {
+            self.use_feature("thread_local");
+        }
+
+        let new_name = self
+            .renamer
+            .borrow()
+            .get(&decl_id)
+            .expect("Variables should already be renamed");
+        let (ty, mutbl, _) = self.convert_variable(ctx.static_(), None, typ)?;
+        // When putting extern statics into submodules, they need to be public to be accessible
+        let visibility = if self.tcfg.reorganize_definitions {
+            "pub"
+        } else {
+            ""
+        };
+        let mut extern_item = mk_linkage(true, &new_name, ident)
+            .span(s)
+            .set_mutbl(mutbl)
+            .vis(visibility);
+        if has_thread_duration {
+            extern_item = extern_item.single_attr("thread_local");
+        }
+
+        for attr in attrs {
+            extern_item = match attr {
+                c_ast::Attribute::Alias(aliasee) => extern_item.str_attr("link_name", aliasee),
+                _ => continue,
+            };
+        }
+
+        Ok(ConvertedDecl::ForeignItem(extern_item.static_foreign_item(&new_name, ty)))
+    }
+
+    /// Translate `_Static_assert(cond, "message")` into `const _: () = assert!(cond, "message");`
+    /// so that Rust checks the assertion at compile time as well.
+    fn convert_static_assert(
+        &self,
+        ctx: ExprContext,
+        span: Span,
+        assert_expr: CExprId,
+        message: Option<CExprId>,
+    ) -> Result<ConvertedDecl, TranslationError> {
+        let cond = match self
+            .convert_condition(ctx.used().set_const(true), true, assert_expr)?
+            .to_unsafe_pure_expr()
+        {
+            Some(cond) => cond,
+            None => {
+                diag!(
+                    Diagnostic::StaticAssert,
+                    at self.ast_context.display_loc(&self.ast_context[assert_expr].loc),
+                    "ignoring static assert with a condition that has side effects"
+                );
+                return Ok(ConvertedDecl::NoItem);
+            }
+        };
+
+        use syn::__private::ToTokens;
+        let mut tokens: Vec<TokenTree> = cond.to_token_stream().into_iter().collect();
+        if let Some(message) = message {
+            match self.ast_context[message].kind {
+                CExprKind::Literal(_, CLiteral::String(ref bytes, width)) => {
+                    // The message is used as a format string, which must not have arguments to
+                    // be usable in a constant
+                    let message = static_assert_message(bytes, width)
+                        .replace('{', "{{")
+                        .replace('}', "}}");
+                    tokens.push(TokenTree::Punct(Punct::new(',', Alone)));
+                    tokens.push(TokenTree::Literal(proc_macro2::Literal::string(&message)));
+                }
+                _ => diag!(
+                    Diagnostic::StaticAssert,
+                    at self.ast_context.display_loc(&self.ast_context[message].loc),
+                    "ignoring static assert message that is not a string literal"
+                ),
             }
         }
+
+        let assert = mk().mac_expr(mk().mac(
+            vec!["assert"],
+            tokens,
+            MacroDelimiter::Paren(Default::default()),
+        ));
+        Ok(ConvertedDecl::Item(mk().span(span).const_item(
+            "_",
+            mk().tuple_ty(vec![] as Vec<Box<Type>>),
+            assert,
+        )))
     }
 
     fn canonical_macro_replacement(
@@ -2207,6 +3182,7 @@ impl<'c> Translation<'c> {
         is_main: bool,
         is_variadic: bool,
         is_extern: bool,
+        is_static_cstr: bool,
         new_name: &str,
         name: &str,
         arguments: &[(CDeclId, String, CQualTypeId)],
@@ -2215,6 +3191,8 @@ impl<'c> Translation<'c> {
         attrs: &IndexSet<c_ast::Attribute>,
     ) -> Result<ConvertedDecl, TranslationError> {
         self.function_context.borrow_mut().enter_new(name);
+        self.function_context.borrow_mut().returns_nonnull_fn_ptr =
+            return_type.map_or(false, |ty| self.ast_context.is_nonnull_fn_pointer(ty.ctype));
 
         self.with_scope(|| {
             let mut args: Vec<FnArg> = vec![];
@@ -2245,7 +3223,17 @@ impl<'c> Translation<'c> {
                     mk().set_mutbl(mutbl).ident_pat(new_var)
                 };
 
-                args.push(mk().arg(ty, pat))
+                // With `--restrict-attrs`, `restrict` pointers are marked for the ownership
+                // analysis, which may then upgrade them to `&mut`
+                let arg_builder = if self.tcfg.restrict_attrs && typ.qualifiers.is_restrict {
+                    mk().meta_item_attr(
+                        AttrStyle::Outer,
+                        mk().meta_path(vec!["c2rust", "restrict"]),
+                    )
+                } else {
+                    mk()
+                };
+                args.push(arg_builder.arg(ty, pat))
             }
 
             if is_variadic {
@@ -2266,6 +3254,7 @@ impl<'c> Translation<'c> {
 
             // handle return type
             let ret = match return_type {
+                Some(_) if is_static_cstr => self.static_cstr_ty(),
                 Some(return_type) => self.convert_type(return_type.ctype)?,
                 None => mk().never_ty(),
             };
@@ -2306,35 +3295,55 @@ impl<'c> Translation<'c> {
                     _ => cfg::ImplicitReturnType::Void,
                 };
 
-                let mut body_stmts = vec![];
-                for &(_, _, typ) in arguments {
-                    body_stmts.append(&mut self.compute_variable_array_sizes(ctx, typ.ctype)?);
-                }
+                let stub_message = match *self.fn_stub.borrow() {
+                    Some(stubs::FnStub::Unimplemented(ref message)) => Some(message.clone()),
+                    _ => None,
+                };
+                let mut block = if let Some(ref message) = stub_message {
+                    self.stub_body(message)
+                } else {
+                    let mut body_stmts = vec![];
+                    for &(_, _, typ) in arguments {
+                        body_stmts.append(&mut self.compute_variable_array_sizes(ctx, typ.ctype)?);
+                    }
 
-                let body_ids = match self.ast_context.index(body).kind {
-                    CStmtKind::Compound(ref stmts) => stmts,
-                    _ => panic!("function body expects to be a compound statement"),
+                    let body_ids = match self.ast_context.index(body).kind {
+                        CStmtKind::Compound(ref stmts) => stmts,
+                        _ => panic!("function body expects to be a compound statement"),
+                    };
+                    body_stmts.append(&mut self.convert_function_body(ctx, name, body_ids, ret)?);
+                    stmts_block(body_stmts)
                 };
-                body_stmts.append(&mut self.convert_function_body(ctx, name, body_ids, ret)?);
-                let mut block = stmts_block(body_stmts);
                 if let Some(span) = self.get_span(SomeId::Stmt(body)) {
                     block.set_span(span);
                 }
 
+                let abi = self.function_abi(attrs);
+
                 // Only add linkage attributes if the function is `extern`
                 let mut mk_ = if is_main {
                     mk()
                 } else if is_global && !is_inline {
-                    mk_linkage(false, new_name, name).extern_("C").pub_()
+                    mk_linkage(false, new_name, name).extern_(abi).pub_()
                 } else if is_inline && is_extern && !attrs.contains(&c_ast::Attribute::GnuInline) {
                     // c99 extern inline functions should be pub, but not gnu_inline attributed
                     // extern inlines, which become subject to their gnu89 visibility (private)
 
-                    mk_linkage(false, new_name, name).extern_("C").pub_()
+                    mk_linkage(false, new_name, name).extern_(abi).pub_()
+                } else if is_inline && attrs.contains(&c_ast::Attribute::DllExport) {
+                    // MSVC exports `__declspec(dllexport)` inline functions
+                    mk_linkage(false, new_name, name).extern_(abi).pub_()
+                } else if is_static_cstr {
+                    // `&CStr` is not FFI-safe, but these functions are only ever called directly
+                    if self.cur_file.borrow().is_some() {
+                        mk().pub_()
+                    } else {
+                        mk()
+                    }
                 } else if self.cur_file.borrow().is_some() {
-                    mk().extern_("C").pub_()
+                    mk().extern_(abi).pub_()
                 } else {
-                    mk().extern_("C")
+                    mk().extern_(abi)
                 };
 
                 for attr in attrs {
@@ -2346,6 +3355,11 @@ impl<'c> Translation<'c> {
                     };
                 }
 
+                // The arguments of stubs are unused
+                if stub_message.is_some() {
+                    mk_ = mk_.call_attr("allow", vec!["unused_variables"]);
+                }
+
                 // If this function is just a regular inline
                 if is_inline && !attrs.contains(&c_ast::Attribute::AlwaysInline) {
                     mk_ = mk_.single_attr("inline");
@@ -2372,12 +3386,16 @@ impl<'c> Translation<'c> {
                     // specifies internal linkage in all other cases due to name mangling by rustc.
                 }
 
-                Ok(ConvertedDecl::Item(
-                    mk_.span(span).unsafe_().fn_item(decl, block),
-                ))
+                let item = mk_.span(span).unsafe_().fn_item(decl, block);
+                Ok(self.outline_function(item))
             } else {
                 // Translating an extern function declaration
 
+                // WebAssembly has no libc to link to, so the functions used are defined instead
+                if new_name == name && self.use_wasm_shim(name) {
+                    return Ok(ConvertedDecl::NoItem);
+                }
+
                 // When putting extern fns into submodules, they need to be public to be accessible
                 let visibility = if self.tcfg.reorganize_definitions {
                     "pub"
@@ -2396,7 +3414,15 @@ impl<'c> Translation<'c> {
 
                 let function_decl = mk_.fn_foreign_item(decl);
 
-                Ok(ConvertedDecl::ForeignItem(function_decl))
+                // Declarations with an ABI other than the default one get their own extern block
+                let abi = self.function_abi(attrs);
+                if abi == self.default_abi() {
+                    Ok(ConvertedDecl::ForeignItem(function_decl))
+                } else {
+                    Ok(ConvertedDecl::Item(
+                        mk().extern_(abi).foreign_items(vec![function_decl]),
+                    ))
+                }
             }
         })
     }
@@ -2426,12 +3452,13 @@ impl<'c> Translation<'c> {
                 .expect("Failed to write CFG .json file");
         }
 
+        let tcfg = self.fn_tcfg();
         let (lifted_stmts, relooped) = cfg::relooper::reloop(
             graph,
             store,
-            self.tcfg.simplify_structures,
-            self.tcfg.use_c_loop_info,
-            self.tcfg.use_c_multiple_info,
+            tcfg.simplify_structures,
+            tcfg.use_c_loop_info,
+            tcfg.use_c_multiple_info,
             live_in,
         );
 
@@ -2500,6 +3527,13 @@ impl<'c> Translation<'c> {
             .get_type()
             .ok_or_else(|| format_err!("bad condition type"))?;
 
+        // Owned allocations are never NULL
+        if let Some(non_null) = self.local_alloc_non_null(cond_id) {
+            return Ok(WithStmts::new_val(
+                mk().lit_expr(mk().bool_lit(non_null == target)),
+            ));
+        }
+
         let null_pointer_case =
             |negated: bool, ptr: CExprId| -> Result<WithStmts<Box<Expr>>, TranslationError> {
                 let val = self.convert_expr(ctx.used().decay_ref(), ptr)?;
@@ -2662,6 +3696,10 @@ impl<'c> Translation<'c> {
                     .insert(decl_id, &ident)
                     .expect(&format!("Failed to insert variable '{}'", ident));
 
+                if let Some(info) = self.convert_local_alloc_decl(ctx, decl_id, &rust_name)? {
+                    return Ok(info);
+                }
+
                 if self.ast_context.is_va_list(typ.ctype) {
                     // translate `va_list` variables to `VaListImpl`s and omit the initializer.
                     let pat_mut = mk().set_mutbl("mut").ident_pat(rust_name.clone());
@@ -2720,7 +3758,10 @@ impl<'c> Translation<'c> {
                 } else {
                     let pat = mk().set_mutbl(mutbl).ident_pat(rust_name.clone());
 
-                    let type_annotation = if self.tcfg.reduce_type_annotations
+                    // Like in C, `__auto_type` declarations leave the type to be inferred from
+                    // the initializer if it is unambiguous
+                    let is_auto = matches!(self.ast_context[typ.ctype].kind, CTypeKind::Auto(_));
+                    let type_annotation = if (self.reduce_type_annotations() || is_auto)
                         && !self.should_assign_type_annotation(typ.ctype, initializer)
                     {
                         None
@@ -2770,7 +3811,7 @@ impl<'c> Translation<'c> {
                     let items = match self.convert_decl(ctx, decl_id)? {
                         ConvertedDecl::Item(item) => vec![item],
                         ConvertedDecl::ForeignItem(item) => {
-                            vec![mk().extern_("C").foreign_items(vec![item])]
+                            vec![mk().extern_(self.default_abi()).foreign_items(vec![item])]
                         }
                         ConvertedDecl::Items(items) => items,
                         ConvertedDecl::NoItem => return Ok(cfg::DeclStmtInfo::empty()),
@@ -2891,6 +3932,9 @@ impl<'c> Translation<'c> {
         TranslationError,
     > {
         let init = match initializer {
+            Some(x) if self.ast_context.is_nonnull_fn_pointer(typ.ctype) => self
+                .convert_expr(ctx.used(), x)
+                .map(|init| init.map(unwrap_function_pointer)),
             Some(x) => self.convert_expr(ctx.used(), x),
             None => self.implicit_default_expr(typ.ctype, ctx.is_static),
         };
@@ -2902,9 +3946,13 @@ impl<'c> Translation<'c> {
         {
             elt = self.variable_array_base_type(elt);
             let ty = self.convert_type(elt)?;
-            mk().path_ty(vec![
-                mk().path_segment_with_args("Vec", mk().angle_bracketed_args(vec![ty]))
-            ])
+            let vec = mk().path_segment_with_args("Vec", mk().angle_bracketed_args(vec![ty]));
+            if self.tcfg.emit_no_std {
+                let path = vec![mk().path_segment("alloc"), mk().path_segment("vec"), vec];
+                mk().path_ty(mk().abs_path(path))
+            } else {
+                mk().path_ty(vec![vec])
+            }
         } else {
             self.convert_type(typ.ctype)?
         };
@@ -2927,6 +3975,19 @@ impl<'c> Translation<'c> {
             .convert(&self.ast_context, type_id)
     }
 
+    /// The type of the C numeric type `libc::<name>`, which is a Rust primitive type with
+    /// `--fixed-width-ints`
+    pub fn libc_ty(&self, name: &'static str) -> Box<Type> {
+        self.type_converter
+            .borrow()
+            .libc_ty(name, &self.ast_context.target)
+    }
+
+    /// Convert a boolean expression to a c_int
+    fn bool_to_int(&self, val: Box<Expr>) -> Box<Expr> {
+        mk().cast_expr(val, self.libc_ty("c_int"))
+    }
+
     /// Construct an expression for a NULL at any type, including forward declarations,
     /// function pointers, and normal pointers.
     fn null_ptr(&self, type_id: CTypeId, is_static: bool) -> Result<Box<Expr>, TranslationError> {
@@ -2959,6 +4020,7 @@ impl<'c> Translation<'c> {
         lhs_type: CQualTypeId,
         rhs: Box<Expr>,
     ) -> Result<Box<Expr>, TranslationError> {
+        self.record_unsafe(UnsafeReason::VolatileAccess);
         let addr_lhs = match **lhs {
             Expr::Unary(ExprUnary {
                 op: UnOp::Deref(_),
@@ -2997,6 +4059,7 @@ impl<'c> Translation<'c> {
         lhs: &Box<Expr>,
         lhs_type: CQualTypeId,
     ) -> Result<Box<Expr>, TranslationError> {
+        self.record_unsafe(UnsafeReason::VolatileAccess);
         let addr_lhs = match **lhs {
             Expr::Unary(ExprUnary {
                 op: UnOp::Deref(_),
@@ -3140,6 +4203,111 @@ impl<'c> Translation<'c> {
         self.compute_size_of_ty(ty)
     }
 
+    /// Translate a non-constant `offsetof(S, a.b[i])` into the sum of the offsets of the
+    /// components of its designator:
+    ///
+    /// `(offset_of!(S, a) + offset_of!(A, b) + i as usize * size_of::<B>()) as size_t`
+    fn convert_variable_offset_of(
+        &self,
+        ctx: ExprContext,
+        ty: CQualTypeId,
+        qty: CQualTypeId,
+        components: &[OffsetOfComponent],
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        self.use_crate(ExternCrate::Memoffset);
+
+        // Struct Type
+        let decl_id = {
+            let kind = match self.ast_context[qty.ctype].kind {
+                CTypeKind::Elaborated(ty_id) => &self.ast_context[ty_id].kind,
+                ref kind => kind,
+            };
+
+            kind.as_decl_or_typedef()
+                .expect("Did not find decl_id for offsetof struct")
+        };
+
+        let mut cur_ty = qty.ctype;
+        let mut offsets = vec![];
+        for (i, component) in components.iter().enumerate() {
+            match *component {
+                OffsetOfComponent::Field(field_id) => {
+                    // The first field is named through the type given to `offsetof`, which may
+                    // be a typedef, and any later one through the record it belongs to
+                    let parent_id = if i == 0 {
+                        decl_id
+                    } else {
+                        *self
+                            .ast_context
+                            .parents
+                            .get(&field_id)
+                            .ok_or_else(|| format_err!("Did not find offsetof field parent"))?
+                    };
+                    let name = self.resolve_decl_inner_name(parent_id);
+                    let field_name = self
+                        .type_converter
+                        .borrow()
+                        .resolve_field_name(None, field_id)
+                        .expect("Did not find name for offsetof struct field");
+
+                    // offset_of!(Struct, field)
+                    let macro_body = vec![
+                        TokenTree::Ident(mk().ident(name)),
+                        TokenTree::Punct(Punct::new(',', Alone)),
+                        TokenTree::Ident(mk().ident(field_name)),
+                    ];
+                    offsets.push(mk().mac_expr(mk().mac(
+                        mk().path("offset_of"),
+                        macro_body,
+                        MacroDelimiter::Paren(Default::default()),
+                    )));
+
+                    cur_ty = match self.ast_context[field_id].kind {
+                        CDeclKind::Field { typ, .. } => typ.ctype,
+                        _ => return Err(format_err!("Expected offsetof field").into()),
+                    };
+                }
+
+                OffsetOfComponent::Index(expr_id) => {
+                    let elt = match self.ast_context.resolve_type(cur_ty).kind {
+                        CTypeKind::ConstantArray(elt, _)
+                        | CTypeKind::IncompleteArray(elt)
+                        | CTypeKind::VariableArray(elt, _) => elt,
+                        ref t => {
+                            return Err(format_err!(
+                                "Expected offsetof index into array, got {:?}",
+                                t
+                            )
+                            .into())
+                        }
+                    };
+
+                    // expr as usize * size_of::<Elt>()
+                    let index = self
+                        .convert_expr(ctx.used(), expr_id)?
+                        .to_pure_expr()
+                        .ok_or_else(|| {
+                            format_err!("Expected Variable offsetof to be a side-effect free")
+                        })?;
+                    let index = mk().cast_expr(index, mk().ident_ty("usize"));
+                    let elt_size = self.compute_size_of_ty(self.convert_type(elt)?)?.to_expr();
+                    offsets.push(mk().binary_expr(BinOp::Mul(Default::default()), index, elt_size));
+
+                    cur_ty = elt;
+                }
+            }
+        }
+
+        let offset = offsets
+            .into_iter()
+            .reduce(|lhs, rhs| mk().binary_expr(BinOp::Add(Default::default()), lhs, rhs))
+            .ok_or_else(|| format_err!("Expected offsetof designator"))?;
+
+        // Cast type
+        let cast_ty = self.convert_type(ty.ctype)?;
+        Ok(WithStmts::new_val(mk().cast_expr(offset, cast_ty)))
+    }
+
     fn compute_size_of_ty(&self, ty: Box<Type>) -> Result<WithStmts<Box<Expr>>, TranslationError> {
         let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
         let name = "size_of";
@@ -3195,13 +4363,33 @@ impl<'c> Translation<'c> {
     /// Translate a C expression into a Rust one, possibly collecting side-effecting statements
     /// to run before the expression.
     ///
-    /// The `use_` argument informs us how the C expression we are translating is used in the C
-    /// program. See `ExprUse` for more information.
+    /// The `ctx` argument informs us how the C expression we are translating is used in the C
+    /// program. See `ExprContext` for more information.
     ///
-    /// In the case that `use_` is unused, all side-effecting components will be in the
+    /// In the case that `ctx` is unused, all side-effecting components will be in the
     /// `stmts` field of the output and it is expected that the `val` field of the output will be
     /// ignored.
     pub fn convert_expr(
+        &self,
+        ctx: ExprContext,
+        expr_id: CExprId,
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        let unsafe_start = self.unsafe_operations.borrow().len();
+        let outer_loc = self.cur_expr_loc.replace(self.ast_context[expr_id].loc);
+
+        // Generated C code can nest expressions, such as `?:` chains, thousands of levels deep
+        let converted = ensure_sufficient_stack(|| self.convert_expr_inner(ctx, expr_id));
+
+        // Translations that failed may be retried another way, as for macros, so only the
+        // operations of the one that is emitted count
+        self.cur_expr_loc.set(outer_loc);
+        if converted.is_err() {
+            self.unsafe_operations.borrow_mut().truncate(unsafe_start);
+        }
+        converted
+    }
+
+    fn convert_expr_inner(
         &self,
         mut ctx: ExprContext,
         expr_id: CExprId,
@@ -3231,6 +4419,25 @@ impl<'c> Translation<'c> {
             }
         }
 
+        if ctx.is_static {
+            if let Some(converted) = self.convert_const_address(ctx, expr_id)? {
+                return Ok(converted);
+            }
+        }
+
+        // `to_bits` and `from_bits` aren't `const fn`s
+        if !ctx.is_static && !ctx.is_const {
+            if let Some(converted) = self.convert_float_bit_cast(ctx, expr_id)? {
+                return Ok(converted);
+            }
+        }
+
+        if ctx.is_unused() {
+            if let Some(converted) = self.convert_assert(ctx, expr_id)? {
+                return Ok(converted);
+            }
+        }
+
         match *expr_kind {
             CExprKind::DesignatedInitExpr(..) => {
                 Err(TranslationError::generic("Unexpected designated init expr"))
@@ -3238,6 +4445,11 @@ impl<'c> Translation<'c> {
             CExprKind::BadExpr => Err(TranslationError::generic(
                 "convert_expr: expression kind not supported",
             )),
+            CExprKind::ShuffleVector(ty, ref child_expr_ids)
+                if self.is_generic_vector_type(ty.ctype) =>
+            {
+                self.convert_generic_shuffle_vector(ctx, ty, child_expr_ids)
+            }
             CExprKind::ShuffleVector(_, ref child_expr_ids) => self
                 .convert_shuffle_vector(ctx, child_expr_ids)
                 .map_err(|e| {
@@ -3246,9 +4458,22 @@ impl<'c> Translation<'c> {
                         e.context(TranslationErrorKind::OldLLVMSimd),
                     )
                 }),
-            CExprKind::ConvertVector(..) => {
-                Err(TranslationError::generic("convert vector not supported"))
+            CExprKind::ConvertVector(ty, ref child_expr_ids) => {
+                self.convert_convert_vector(ctx, ty, child_expr_ids)
             }
+            // A single embedded byte can initialize a scalar; longer expansions are only
+            // translated as part of an array initializer
+            CExprKind::Embed(ty, ref bytes) => match **bytes {
+                [b] => {
+                    let lit = mk().lit_expr(mk().int_unsuffixed_lit(b as u128));
+                    Ok(WithStmts::new_val(
+                        mk().cast_expr(lit, self.convert_type(ty.ctype)?),
+                    ))
+                }
+                _ => Err(TranslationError::generic(
+                    "#embed outside of an array initializer not supported",
+                )),
+            },
 
             CExprKind::UnaryType(_ty, kind, opt_expr, arg_ty) => {
                 let result = match kind {
@@ -3272,7 +4497,7 @@ impl<'c> Translation<'c> {
                     UnTypeOp::PreferredAlignOf => self.compute_align_of_type(arg_ty.ctype, true)?,
                 };
 
-                Ok(result.map(|x| mk().cast_expr(x, mk().path_ty(vec!["libc", "c_ulong"]))))
+                Ok(result.map(|x| mk().cast_expr(x, self.libc_ty("c_ulong"))))
             }
 
             CExprKind::ConstantExpr(_ty, child, value) => {
@@ -3302,6 +4527,21 @@ impl<'c> Translation<'c> {
                     }
                 }
 
+                // Every static is translated into a `static mut`, except for the tables translated
+                // into immutable statics
+                if let CDeclKind::Variable {
+                    has_static_duration,
+                    has_thread_duration,
+                    ..
+                } = *decl
+                {
+                    if (has_static_duration || has_thread_duration)
+                        && !self.const_tables.contains(&decl_id)
+                    {
+                        self.record_unsafe(UnsafeReason::StaticMutAccess);
+                    }
+                }
+
                 let varname = decl.get_name().expect("expected variable name").to_owned();
                 let rustname = self
                     .renamer
@@ -3387,63 +4627,8 @@ impl<'c> Translation<'c> {
                     *val,
                     IntBase::Dec,
                 )?)),
-                OffsetOfKind::Variable(qty, field_id, expr_id) => {
-                    self.use_crate(ExternCrate::Memoffset);
-
-                    // Struct Type
-                    let decl_id = {
-                        let kind = match self.ast_context[qty.ctype].kind {
-                            CTypeKind::Elaborated(ty_id) => &self.ast_context[ty_id].kind,
-                            ref kind => kind,
-                        };
-
-                        kind.as_decl_or_typedef()
-                            .expect("Did not find decl_id for offsetof struct")
-                    };
-                    let name = self.resolve_decl_inner_name(decl_id);
-                    let ty_ident = mk().ident(name);
-
-                    // Field name
-                    let field_name = self
-                        .type_converter
-                        .borrow()
-                        .resolve_field_name(None, *field_id)
-                        .expect("Did not find name for offsetof struct field");
-                    let field_ident = mk().ident(field_name);
-
-                    // Index Expr
-                    let expr = self
-                        .convert_expr(ctx, *expr_id)?
-                        .to_pure_expr()
-                        .ok_or_else(|| {
-                            format_err!("Expected Variable offsetof to be a side-effect free")
-                        })?;
-                    let expr = mk().cast_expr(expr, mk().ident_ty("usize"));
-                    use syn::__private::ToTokens;
-                    let index_expr = expr.to_token_stream();
-
-                    // offset_of!(Struct, field[expr as usize]) as ty
-                    let macro_body = vec![
-                        TokenTree::Ident(ty_ident),
-                        TokenTree::Punct(Punct::new(',', Alone)),
-                        TokenTree::Ident(field_ident),
-                        TokenTree::Group(proc_macro2::Group::new(
-                            proc_macro2::Delimiter::Bracket,
-                            index_expr,
-                        )),
-                    ];
-                    let path = mk().path("offset_of");
-                    let mac = mk().mac_expr(mk().mac(
-                        path,
-                        macro_body,
-                        MacroDelimiter::Paren(Default::default()),
-                    ));
-
-                    // Cast type
-                    let cast_ty = self.convert_type(ty.ctype)?;
-                    let cast_expr = mk().cast_expr(mac, cast_ty);
-
-                    Ok(WithStmts::new_val(cast_expr))
+                OffsetOfKind::Variable(qty, ref components) => {
+                    self.convert_variable_offset_of(ctx, ty, qty, components)
                 }
             },
 
@@ -3486,6 +4671,10 @@ impl<'c> Translation<'c> {
                 if self.casting_simd_builtin_call(expr, is_explicit, kind) {
                     return Ok(val);
                 }
+                // Expressions treat function pointers as `Option`s
+                if kind == CastKind::LValueToRValue && self.is_nonnull_fn_ptr_lvalue(expr) {
+                    return Ok(val.map(|v| mk().call_expr(mk().ident_expr("Some"), vec![v])));
+                }
                 self.convert_cast(
                     ctx,
                     source_ty,
@@ -3574,9 +4763,23 @@ impl<'c> Translation<'c> {
                 }
             }
 
-            CExprKind::Binary(type_id, op, lhs, rhs, opt_lhs_type_id, opt_res_type_id) => self
-                .convert_binary_expr(ctx, type_id, op, lhs, rhs, opt_lhs_type_id, opt_res_type_id)
-                .map_err(|e| e.add_loc(self.ast_context.display_loc(src_loc))),
+            CExprKind::Binary(type_id, op, lhs, rhs, opt_lhs_type_id, opt_res_type_id) => {
+                if let Some(non_null) = self.local_alloc_non_null(expr_id) {
+                    return Ok(WithStmts::new_val(
+                        self.bool_to_int(mk().lit_expr(mk().bool_lit(non_null))),
+                    ));
+                }
+                self.convert_binary_expr(
+                    ctx,
+                    type_id,
+                    op,
+                    lhs,
+                    rhs,
+                    opt_lhs_type_id,
+                    opt_res_type_id,
+                )
+                .map_err(|e| e.add_loc(self.ast_context.display_loc(src_loc)))
+            }
 
             CExprKind::ArraySubscript(_, ref lhs, ref rhs, _) => {
                 let lhs_node = &self.ast_context.index(*lhs).kind;
@@ -3595,6 +4798,13 @@ impl<'c> Translation<'c> {
                     (rhs, lhs, rhs_node)
                 };
 
+                if let Some(val) = self.convert_local_alloc_index(ctx, *lhs, *rhs)? {
+                    return Ok(val);
+                }
+                if let Some(val) = self.convert_slice_index(ctx, *lhs, *rhs)? {
+                    return Ok(val);
+                }
+
                 let lhs_node_type = lhs_node
                     .get_type()
                     .ok_or_else(|| format_err!("lhs node bad type"))?;
@@ -3604,6 +4814,9 @@ impl<'c> Translation<'c> {
                     .kind
                     .is_vector()
                 {
+                    if self.is_generic_vector_type(lhs_node_type) {
+                        return self.convert_vector_subscript(ctx, *lhs, *rhs);
+                    }
                     return Err(TranslationError::new(
                         self.ast_context.display_loc(src_loc),
                         err_msg("Attempting to index a vector type")
@@ -3611,7 +4824,25 @@ impl<'c> Translation<'c> {
                     ));
                 }
 
-                let rhs = self.convert_expr(ctx.used(), *rhs)?;
+                // The index is cast to `usize` (or `isize`) below, so an integer promotion from a
+                // narrower unsigned type can be skipped rather than producing `x as c_int as usize`
+                let is_narrow_unsigned = |expr_id: CExprId| {
+                    let ty = self.ast_context[expr_id].kind.get_type();
+                    match ty.map(|ty| &self.ast_context.resolve_type(ty).kind) {
+                        Some(CTypeKind::Bool | CTypeKind::UChar | CTypeKind::UShort) => true,
+                        _ => false,
+                    }
+                };
+                let rhs = match self.ast_context[*rhs].kind {
+                    CExprKind::ImplicitCast(_, promoted, CastKind::IntegralCast, _, _)
+                        if is_narrow_unsigned(promoted) =>
+                    {
+                        promoted
+                    }
+                    _ => *rhs,
+                };
+
+                let rhs = self.convert_expr(ctx.used(), rhs)?;
                 rhs.and_then(|rhs| {
                     let simple_index_array = if ctx.needs_address() {
                         // We can't necessarily index into an array if we're using
@@ -3674,8 +4905,9 @@ impl<'c> Translation<'c> {
 
                             // Don't dereference the offset if we're still within the variable portion
                             if let Some(elt_type_id) = var_elt_type_id {
+                                self.record_unsafe(UnsafeReason::PointerArithmeticDeref);
                                 let mul = self.compute_size_of_expr(elt_type_id);
-                                pointer_offset(lhs, rhs, mul, false, true)
+                                self.pointer_offset(lhs, rhs, mul, false, true)
                             } else {
                                 mk().index_expr(lhs, cast_int(rhs, "usize", false))
                             }
@@ -3704,14 +4936,27 @@ impl<'c> Translation<'c> {
                                     }
                                 };
 
+                            self.record_unsafe(UnsafeReason::PointerArithmeticDeref);
                             let mul = self.compute_size_of_expr(pointee_type_id.ctype);
-                            Ok(pointer_offset(lhs, rhs, mul, false, true))
+                            Ok(self.pointer_offset(lhs, rhs, mul, false, true))
                         })
                     }
                 })
             }
 
             CExprKind::Call(call_expr_ty, func, ref args) => {
+                // Only builtins may translate to something usable in a `const`
+                if ctx.is_const
+                    && !matches!(
+                        self.ast_context[func].kind,
+                        CExprKind::ImplicitCast(_, _, CastKind::BuiltinFnToFnPtr, _, _)
+                    )
+                {
+                    return Err(format_translation_err!(
+                        self.ast_context.display_loc(src_loc),
+                        "Constants cannot contain function calls",
+                    ));
+                }
                 let fn_ty =
                     self.ast_context
                         .get_pointee_qual_type(
@@ -3724,6 +4969,45 @@ impl<'c> Translation<'c> {
                     Some(CTypeKind::Function(_, _, is_variadic, _, _)) => *is_variadic,
                     _ => false,
                 };
+                if self.ascii_char_classes() {
+                    if let Some(val) = self.convert_ctype_call(ctx, func, args)? {
+                        return Ok(val);
+                    }
+                }
+                if self.tcfg.translate_fmt {
+                    if let Some(val) = self.convert_printf_call(ctx, func, args)? {
+                        return Ok(val);
+                    }
+                }
+                if self.tcfg.variadic_shims {
+                    if let Some(val) = self.convert_variadic_shim_call(ctx, func, args)? {
+                        return Ok(val);
+                    }
+                }
+                if let Some(abort) = self.convert_abort_call(func, args) {
+                    return Ok(WithStmts::new_val(abort));
+                }
+                if let Some(val) = self.convert_local_alloc_free(expr_id, args) {
+                    return Ok(val);
+                }
+
+                let is_static_cstr_call = match self.ast_context[func].kind {
+                    CExprKind::ImplicitCast(_, fexp, CastKind::FunctionToPointerDecay, _, _) => {
+                        match self.ast_context[fexp].kind {
+                            CExprKind::DeclRef(_, decl_id, _) => self.returns_static_cstr(decl_id),
+                            _ => false,
+                        }
+                    }
+                    _ => false,
+                };
+                let nonnull_params = self.nonnull_fn_ptr_params(func);
+                let returns_nonnull = match fn_ty {
+                    Some(CTypeKind::Function(ret_ty, ..)) => {
+                        self.ast_context.is_nonnull_fn_pointer(ret_ty.ctype)
+                    }
+                    _ => false,
+                };
+
                 let func = match self.ast_context[func].kind {
                     // Direct function call
                     CExprKind::ImplicitCast(_, fexp, CastKind::FunctionToPointerDecay, _, _)
@@ -3734,7 +5018,24 @@ impl<'c> Translation<'c> {
                             _ => false,
                         } =>
                     {
-                        self.convert_expr(ctx.used(), fexp)?
+                        match self.checked_allocator_fn(fexp) {
+                            Some(allocator) => WithStmts::new_val(allocator),
+                            None => {
+                                let reason = match self.ast_context[fexp].kind {
+                                    CExprKind::DeclRef(_, decl_id, _) => {
+                                        match self.ast_context[decl_id].kind {
+                                            CDeclKind::Function { body: None, .. } => {
+                                                UnsafeReason::ExternCall
+                                            }
+                                            _ => UnsafeReason::UnsafeFnCall,
+                                        }
+                                    }
+                                    _ => UnsafeReason::UnsafeFnCall,
+                                };
+                                self.record_unsafe(reason);
+                                self.convert_expr(ctx.used(), fexp)?
+                            }
+                        }
                     }
 
                     // Builtin function call
@@ -3744,6 +5045,7 @@ impl<'c> Translation<'c> {
 
                     // Function pointer call
                     _ => {
+                        self.record_unsafe(UnsafeReason::FnPointerCall);
                         let callee = self.convert_expr(ctx.used(), func)?;
                         let make_fn_ty = |ret_ty: Box<Type>| {
                             let ret_ty = match *ret_ty {
@@ -3778,7 +5080,11 @@ impl<'c> Translation<'c> {
                             }
                             Some(_) => {
                                 // Normal function pointer
-                                callee.map(unwrap_function_pointer)
+                                callee.map(|fn_ptr| match *unwrap_function_pointer(fn_ptr) {
+                                    // A non-null field must not be called like a method
+                                    field @ Expr::Field(_) => mk().paren_expr(Box::new(field)),
+                                    fn_ptr => Box::new(fn_ptr),
+                                })
                             }
                         }
                     }
@@ -3790,8 +5096,24 @@ impl<'c> Translation<'c> {
 
                     let args = self.convert_exprs(ctx.used(), args)?;
 
-                    let res: Result<_, TranslationError> =
-                        Ok(args.map(|args| mk().call_expr(func, args)));
+                    let res: Result<_, TranslationError> = Ok(args.map(|args| {
+                        let args = args
+                            .into_iter()
+                            .enumerate()
+                            .map(|(i, arg)| match nonnull_params.get(i) {
+                                Some(true) => unwrap_function_pointer(arg),
+                                _ => arg,
+                            })
+                            .collect();
+                        let call = mk().call_expr(func, args);
+                        if is_static_cstr_call {
+                            mk().method_call_expr(call, "as_ptr", Vec::<Box<Expr>>::new())
+                        } else if returns_nonnull {
+                            mk().call_expr(mk().ident_expr("Some"), vec![call])
+                        } else {
+                            call
+                        }
+                    }));
                     res
                 })?;
 
@@ -3805,24 +5127,38 @@ impl<'c> Translation<'c> {
             CExprKind::Member(qual_ty, expr, decl, kind, _) => {
                 if ctx.is_unused() {
                     self.convert_expr(ctx, expr)
+                } else if let Some(val) = self.convert_tagged_union_access(ctx, expr_id)? {
+                    Ok(val)
+                } else if let Some(val) = self.convert_fn_pointer_union_access(ctx, expr_id)? {
+                    Ok(val)
                 } else {
                     let mut val = match kind {
                         MemberKind::Dot => self.convert_expr(ctx, expr)?,
                         MemberKind::Arrow => {
-                            if let CExprKind::Unary(_, c_ast::UnOp::AddressOf, subexpr_id, _) =
-                                self.ast_context[expr].kind
+                            if let Some(val) = self.convert_local_alloc_deref(expr) {
+                                val
+                            } else if let CExprKind::Unary(
+                                _,
+                                c_ast::UnOp::AddressOf,
+                                subexpr_id,
+                                _,
+                            ) = self.ast_context[expr].kind
                             {
                                 // Special-case the `(&x)->field` pattern
                                 // Convert it directly into `x.field`
                                 self.convert_expr(ctx, subexpr_id)?
                             } else {
                                 let val = self.convert_expr(ctx, expr)?;
+                                self.record_unsafe(UnsafeReason::RawPointerDeref);
                                 val.map(|v| mk().unary_expr(UnOp::Deref(Default::default()), v))
                             }
                         }
                     };
 
                     let record_id = self.ast_context.parents[&decl];
+                    if let CDeclKind::Union { .. } = self.ast_context[record_id].kind {
+                        self.record_unsafe(UnsafeReason::UnionFieldAccess);
+                    }
                     if self.ast_context.has_inner_struct_decl(record_id) {
                         // The structure is split into an outer and an inner,
                         // so we need to go through the outer structure to the inner one
@@ -3884,7 +5220,10 @@ impl<'c> Translation<'c> {
 
             CExprKind::Predefined(_, val_id) => self.convert_expr(ctx, val_id),
 
-            CExprKind::Statements(_, compound_stmt_id) => {
+            CExprKind::Statements(ty, compound_stmt_id) => {
+                if let Some(val) = self.convert_owned_alloca_string(ctx, ty, compound_stmt_id)? {
+                    return Ok(val);
+                }
                 self.convert_statement_expression(ctx, compound_stmt_id)
             }
 
@@ -4030,9 +5369,10 @@ impl<'c> Translation<'c> {
         ))))
     }
 
-    /// If `ctx` is unused, convert `expr` to a semi statement, otherwise return
-    /// `expr`.
-    fn convert_side_effects_expr(
+    /// Finish the translation of an expression that has side effects, such as a call. In an unused
+    /// context, the expression is moved into a statement and replaced by a value that panics (or
+    /// fails to compile) if it is ever used, so that the side effects are not lost.
+    pub fn convert_side_effects_expr(
         &self,
         ctx: ExprContext,
         expr: WithStmts<Box<Expr>>,
@@ -4138,6 +5478,32 @@ impl<'c> Translation<'c> {
         }
     }
 
+    /// `_Noreturn` functions are translated to return `!`, but their C type still has a return
+    /// type, so a pointer to one needs to be transmuted into the pointer type C expects.
+    fn convert_noreturn_fn_item(
+        &self,
+        expr: CExprId,
+        fn_ty: CQualTypeId,
+        val: WithStmts<Box<Expr>>,
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        match self.ast_context[expr].kind {
+            CExprKind::DeclRef(_, decl_id, _) if self.ast_context.is_noreturn_decl(decl_id) => {}
+            _ => return Ok(val),
+        }
+        let (params, is_var) = match self.ast_context.resolve_type(fn_ty.ctype).kind {
+            CTypeKind::Function(_, ref params, is_var, false, _) => (params, is_var),
+            _ => return Ok(val),
+        };
+        let source_ty = self.type_converter.borrow_mut().convert_function(
+            &self.ast_context,
+            None,
+            params,
+            is_var,
+        )?;
+        let target_ty = self.convert_type(fn_ty.ctype)?;
+        Ok(val.map(|x| transmute_expr(source_ty, target_ty, x, self.tcfg.emit_no_std)))
+    }
+
     fn convert_cast(
         &self,
         ctx: ExprContext,
@@ -4230,8 +5596,12 @@ impl<'c> Translation<'c> {
         match kind {
             CastKind::BitCast | CastKind::NoOp => {
                 val.and_then(|x| {
+                    // Generic vectors are reinterpreted lane-by-lane, like function pointers
+                    // they can't be converted with `as`
                     if self.ast_context.is_function_pointer(ty.ctype)
                         || self.ast_context.is_function_pointer(source_ty.ctype)
+                        || (self.is_generic_vector_type(ty.ctype)
+                            && self.is_generic_vector_type(source_ty.ctype))
                     {
                         let source_ty = self.convert_type(source_ty.ctype)?;
                         let target_ty = self.convert_type(ty.ctype)?;
@@ -4252,7 +5622,7 @@ impl<'c> Translation<'c> {
             CastKind::IntegralToPointer if self.ast_context.is_function_pointer(ty.ctype) => {
                 let target_ty = self.convert_type(ty.ctype)?;
                 val.and_then(|x| {
-                    let intptr_t = mk().path_ty(vec!["libc", "intptr_t"]);
+                    let intptr_t = self.libc_ty("intptr_t");
                     let intptr = mk().cast_expr(x, intptr_t.clone());
                     Ok(WithStmts::new_unsafe_val(transmute_expr(
                         intptr_t,
@@ -4275,7 +5645,18 @@ impl<'c> Translation<'c> {
                 let source_ty_ctype_id = source_ty.ctype;
 
                 let source_ty = self.convert_type(source_ty_ctype_id)?;
-                if let CTypeKind::LongDouble = target_ty_ctype {
+                let source_ty_ctype = &self.ast_context.resolve_type(source_ty_ctype_id).kind;
+                if target_ty_ctype.is_half_float_type() || source_ty_ctype.is_half_float_type() {
+                    val.and_then(|x| {
+                        let val = self.convert_half_float_cast(
+                            x,
+                            source_ty_ctype,
+                            target_ty_ctype,
+                            target_ty,
+                        )?;
+                        Ok(WithStmts::new_val(val))
+                    })
+                } else if let CTypeKind::LongDouble = target_ty_ctype {
                     self.use_crate(ExternCrate::F128);
 
                     let fn_path = mk().path_expr(vec!["f128", "f128", "new"]);
@@ -4308,6 +5689,10 @@ impl<'c> Translation<'c> {
             CastKind::LValueToRValue | CastKind::ToVoid | CastKind::ConstCast => Ok(val),
 
             CastKind::FunctionToPointerDecay | CastKind::BuiltinFnToFnPtr => {
+                let val = match expr {
+                    Some(expr) => self.convert_noreturn_fn_item(expr, source_ty, val)?,
+                    None => val,
+                };
                 Ok(val.map(|x| mk().call_expr(mk().ident_expr("Some"), vec![x])))
             }
 
@@ -4347,6 +5732,10 @@ impl<'c> Translation<'c> {
                             self.ast_context.resolve_type(source_ty.ctype).kind
                         {
                             Ok(val)
+                        } else if ctx.is_static && expr.map_or(false, |e| self.is_static_place(e)) {
+                            // Arrays in statics can't be borrowed to call `as_ptr`
+                            let target_ty = self.convert_type(ty.ctype)?;
+                            Ok(val.map(|x| mk().cast_expr(self.static_addr_of(x), target_ty)))
                         } else {
                             let method = if is_const || ctx.is_static {
                                 "as_ptr"
@@ -4428,8 +5817,12 @@ impl<'c> Translation<'c> {
                 "TODO casts with complex numbers not supported",
             )),
 
-            CastKind::VectorSplat => Err(TranslationError::generic(
-                "TODO vector splat casts not supported",
+            CastKind::VectorSplat => self.convert_vector_splat(ty.ctype, val),
+
+            CastKind::FixedPoint => Err(TranslationError::new(
+                None,
+                err_msg("Conversions of fixed-point values are not supported")
+                    .context(TranslationErrorKind::FixedPoint),
             )),
         }
     }
@@ -4562,6 +5955,9 @@ impl<'c> Translation<'c> {
                 CTypeKind::LongDouble => Ok(WithStmts::new_val(
                     mk().path_expr(vec!["f128", "f128", "ZERO"]),
                 )),
+                ref k if k.is_half_float_type() => {
+                    Ok(WithStmts::new_val(self.half_float_const(k, "ZERO")))
+                }
                 _ => Ok(WithStmts::new_val(
                     mk().lit_expr(mk().float_unsuffixed_lit("0.")),
                 )),
@@ -4590,9 +5986,13 @@ impl<'c> Translation<'c> {
             let count = self.compute_size_of_expr(ty_id).unwrap();
             Ok(self
                 .implicit_default_expr(inner, is_static)?
-                .map(|val| vec_expr(val, count)))
+                .map(|val| vec_expr(val, count, self.tcfg.emit_no_std)))
         } else if let &CTypeKind::Vector(CQualTypeId { ctype, .. }, len) = resolved_ty {
-            self.implicit_vector_default(ctype, len, is_static)
+            if self.is_generic_vector_type(ty_id) {
+                self.implicit_generic_vector_default(ty_id, ctype, len, is_static)
+            } else {
+                self.implicit_vector_default(ctype, len, is_static)
+            }
         } else {
             Err(format_err!("Unsupported default initializer: {:?}", resolved_ty).into())
         }
@@ -4621,6 +6021,10 @@ impl<'c> Translation<'c> {
 
         // Otherwise, construct the initializer
         let mut init = match self.ast_context.index(decl_id).kind {
+            CDeclKind::Struct { .. } if self.tagged_unions.contains_key(&decl_id) => {
+                self.tagged_union_zero_initializer(decl_id, is_static)?
+            }
+
             // Zero initialize all of the fields
             CDeclKind::Struct {
                 fields: Some(ref fields),
@@ -4643,6 +6047,13 @@ impl<'c> Translation<'c> {
                 ))
             }
 
+            CDeclKind::Union {
+                fields: Some(ref fields),
+                ..
+            } if self.fn_pointer_unions.contains(&decl_id) => {
+                self.fn_pointer_union_zero_initializer(decl_id, fields, is_static)?
+            }
+
             // Zero initialize the first field
             CDeclKind::Union { ref fields, .. } => {
                 let name = self
@@ -4797,7 +6208,9 @@ impl<'c> Translation<'c> {
             };
 
             // The backup is to just compare against zero
-            let zero = if ty.is_floating_type() {
+            let zero = if ty.is_half_float_type() {
+                self.half_float_const(ty, "ZERO")
+            } else if ty.is_floating_type() {
                 mk().lit_expr(mk().float_unsuffixed_lit("0."))
             } else {
                 mk().lit_expr(mk().int_unsuffixed_lit(0))
@@ -4824,11 +6237,24 @@ impl<'c> Translation<'c> {
     /// If we're trying to organize item definitions into submodules, add them to a module
     /// scoped "namespace" if we have a path available, otherwise add it to the global "namespace"
     fn insert_item(&self, mut item: Box<Item>, decl: &CDecl) {
-        let decl_file_id = self.ast_context.file_id(decl);
+        // Header functions kept local to this file are translated into it
+        let is_local = match *item {
+            Item::Fn(ref item_fn) => {
+                let name = item_fn.sig.ident.to_string();
+                self.local_inline_fns.contains(&name)
+            }
+            _ => false,
+        };
+        let decl_file_id = if is_local {
+            Some(self.main_file)
+        } else {
+            self.ast_context.file_id(decl)
+        };
 
         if self.tcfg.reorganize_definitions {
             let attrs = item_attrs(&mut item).expect("no attrs field on unexpected item variant");
-            add_src_loc_attr(attrs, &decl.loc.as_ref().map(|x| x.begin()));
+            self.add_src_loc_attr(attrs, decl);
+            self.add_status_attr(&mut item);
             let mut item_stores = self.items.borrow_mut();
             let items = item_stores
                 .entry(decl_file_id.unwrap())
@@ -4836,6 +6262,12 @@ impl<'c> Translation<'c> {
 
             items.add_item(item);
         } else {
+            if self.tcfg.emit_src_locs {
+                if let Some(attrs) = item_attrs(&mut item) {
+                    self.add_src_loc_attr(attrs, decl);
+                }
+            }
+            self.add_status_attr(&mut item);
             self.items.borrow_mut()[&self.main_file].add_item(item)
         }
     }
@@ -4848,7 +6280,7 @@ impl<'c> Translation<'c> {
         if self.tcfg.reorganize_definitions {
             let attrs = foreign_item_attrs(&mut item)
                 .expect("no attrs field on unexpected foreign item variant");
-            add_src_loc_attr(attrs, &decl.loc.as_ref().map(|x| x.begin()));
+            self.add_src_loc_attr(attrs, decl);
             let mut items = self.items.borrow_mut();
             let mod_block_items = items
                 .entry(decl_file_id.unwrap())
@@ -4856,10 +6288,73 @@ impl<'c> Translation<'c> {
 
             mod_block_items.add_foreign_item(item);
         } else {
+            if self.tcfg.emit_src_locs {
+                if let Some(attrs) = foreign_item_attrs(&mut item) {
+                    self.add_src_loc_attr(attrs, decl);
+                }
+            }
             self.items.borrow_mut()[&self.main_file].add_foreign_item(item)
         }
     }
 
+    /// Add a `#[c2rust::src_loc]` attribute with the C location of `decl`. The
+    /// `reorganize_definitions` refactoring orders the definitions it moves by their "line:col"
+    /// location. With `--emit-src-locs` the location is "file:line:col", so that other tools can
+    /// also map the translation back to the C source.
+    fn add_src_loc_attr(&self, attrs: &mut Vec<syn::Attribute>, decl: &CDecl) {
+        let loc = match decl.begin_loc() {
+            Some(loc) => loc,
+            None => return,
+        };
+        let loc_str = match self.ast_context.get_source_path(decl) {
+            Some(path) if self.tcfg.emit_src_locs => {
+                format!("{}:{}:{}", path.display(), loc.line, loc.column)
+            }
+            _ => format!("{}:{}", loc.line, loc.column),
+        };
+        let meta = mk().meta_namevalue(vec!["c2rust", "src_loc"], loc_str);
+        let prepared = mk().prepare_meta(meta);
+        let attr = mk().attribute(AttrStyle::Outer, prepared.path, prepared.tokens);
+        attrs.push(attr);
+    }
+
+    /// With `--migration-status`, mark a translated definition as `#[c2rust::status = "raw"]`.
+    /// The `set_status` refactoring updates the status, and `status_report` and `check_status`
+    /// read it back.
+    fn add_status_attr(&self, item: &mut Item) {
+        if !self.tcfg.migration_status {
+            return;
+        }
+        let attrs = match item {
+            Item::Const(ItemConst { attrs, .. })
+            | Item::Enum(ItemEnum { attrs, .. })
+            | Item::Fn(ItemFn { attrs, .. })
+            | Item::Static(ItemStatic { attrs, .. })
+            | Item::Struct(ItemStruct { attrs, .. })
+            | Item::Type(ItemType { attrs, .. })
+            | Item::Union(ItemUnion { attrs, .. }) => attrs,
+            _ => return,
+        };
+        let meta = mk().meta_namevalue(vec!["c2rust", "status"], "raw");
+        let prepared = mk().prepare_meta(meta);
+        attrs.push(mk().attribute(AttrStyle::Outer, prepared.path, prepared.tokens));
+    }
+
+    /// With `--emit-src-locs`, add the location of a local variable to the `let` statements
+    /// declaring it
+    pub fn add_local_src_locs(&self, decl_id: CDeclId, info: &mut cfg::DeclStmtInfo) {
+        if !self.tcfg.emit_src_locs {
+            return;
+        }
+        let decl = &self.ast_context[decl_id];
+        let decl_stmts = info.decl.iter_mut().chain(info.decl_and_assign.iter_mut());
+        for stmt in decl_stmts.flatten() {
+            if let Stmt::Local(local) = stmt {
+                self.add_src_loc_attr(&mut local.attrs, decl);
+            }
+        }
+    }
+
     fn add_import(&self, decl_file_id: FileId, decl_id: CDeclId, ident_name: &str) {
         let decl = &self.ast_context[decl_id];
         let import_file_id = self.ast_context.file_id(decl);
@@ -4914,6 +6409,7 @@ impl<'c> Translation<'c> {
             | Reference(CQualTypeId { ctype, .. })
             | BlockPointer(CQualTypeId { ctype, .. })
             | TypeOf(ctype)
+            | Auto(ctype)
             | Complex(ctype) => self.import_type(*ctype, decl_file_id),
             Enum(decl_id) | Typedef(decl_id) | Union(decl_id) | Struct(decl_id) => {
                 let mut decl_id = decl_id.clone();
@@ -4946,7 +6442,7 @@ impl<'c> Translation<'c> {
             Vector(..) => {
                 // Handled in `import_simd_typedef`
             }
-            TypeOfExpr(_) | BuiltinFn => {}
+            TypeOfExpr(_) | BuiltinFn | FixedPoint(..) => {}
         }
     }
 
@@ -5007,3 +6503,93 @@ impl<'c> Translation<'c> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+
+    use crate::{Transpiler, TranspilerConfig};
+
+    #[test]
+    fn bindings_of_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let header = "\
+#define LIB_VERSION 3
+struct point {
+    int x, y;
+};
+typedef enum { RED, GREEN } color;
+extern int counter;
+int origin_x = 0;
+static int hidden = 2;
+int distance(struct point a, struct point b);
+int area(struct point p) { return p.x * p.y; }
+static int helper(int x) { return x; }
+static inline int twice(int x) { return 2 * x; }
+";
+        let path = dir.path().join("lib.h");
+        fs::write(&path, header).unwrap();
+
+        let config = TranspilerConfig {
+            bindings: true,
+            ..TranspilerConfig::default()
+        };
+        let translated = Transpiler::new(config).transpile_file(&path).unwrap();
+
+        let (mut declared, mut defined, mut types) = (vec![], vec![], vec![]);
+        for item in translated.syntax_tree().unwrap().items {
+            match item {
+                syn::Item::ForeignMod(fm) => {
+                    for foreign_item in fm.items {
+                        match foreign_item {
+                            syn::ForeignItem::Fn(f) => declared.push(f.sig.ident.to_string()),
+                            syn::ForeignItem::Static(s) => declared.push(s.ident.to_string()),
+                            _ => {}
+                        }
+                    }
+                }
+                syn::Item::Fn(f) => defined.push(f.sig.ident.to_string()),
+                syn::Item::Static(s) => defined.push(s.ident.to_string()),
+                syn::Item::Struct(s) => types.push(s.ident.to_string()),
+                syn::Item::Type(t) => types.push(t.ident.to_string()),
+                syn::Item::Const(c) => types.push(c.ident.to_string()),
+                _ => {}
+            }
+        }
+        declared.sort();
+        // Static and inline functions and variables have no symbol to link to
+        assert_eq!(declared, ["area", "counter", "distance", "origin_x"]);
+        assert_eq!(defined, Vec::<String>::new());
+        for name in ["point", "color", "RED", "GREEN", "LIB_VERSION"] {
+            assert!(types.iter().any(|ty| ty == name), "{} is not defined", name);
+        }
+    }
+
+    #[test]
+    fn fixed_point_function_declared_extern() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fixed.c");
+        let source = "\
+_Accum scale(_Accum x, int n) { return x * n; }
+int twice(int x) { return x * 2; }
+";
+        fs::write(&path, source).unwrap();
+
+        let messages = Arc::new(Mutex::new(vec![]));
+        let sink_messages = Arc::clone(&messages);
+        let translated = Transpiler::new(TranspilerConfig::default())
+            .clang_args(["-ffixed-point"])
+            .on_diagnostic(move |message| {
+                sink_messages.lock().unwrap().push(message.message.clone())
+            })
+            .transpile_file(&path)
+            .unwrap();
+        // `_Accum` is declared by the integer of its width, for the C definition to be linked in
+        assert!(translated.source.contains("fn scale(x: i32, n: libc::c_int) -> i32;"));
+        assert!(translated.source.contains("extern \"C\" fn twice("));
+        let declared_extern = "Declaring scale as extern since it uses fixed-point types";
+        let messages = messages.lock().unwrap();
+        assert!(messages.iter().any(|message| message.starts_with(declared_extern)));
+    }
+}
@@ -0,0 +1,85 @@
+//! This module handles function pointers that can't be null, which are those marked `_Nonnull`
+//! and the parameters covered by `__attribute__((nonnull))`. While other function pointers are
+//! translated into `Option`s, these become plain function pointers:
+//!
+//! ```c
+//! struct ops { void (* _Nonnull run)(int); void (* _Nullable done)(void); };
+//! void each(const int *xs, int n, void (*f)(int)) __attribute__((nonnull(3)));
+//! ```
+//!
+//! becomes
+//!
+//! ```ignore
+//! pub struct ops {
+//!     pub run: unsafe extern "C" fn(libc::c_int) -> (),
+//!     pub done: Option<unsafe extern "C" fn() -> ()>,
+//! }
+//! pub unsafe extern "C" fn each(mut xs: *const libc::c_int, mut n: libc::c_int,
+//!                               mut f: unsafe extern "C" fn(libc::c_int) -> ()) { ... }
+//! ```
+//!
+//! Expressions keep treating function pointers as `Option`s: reading a non-null one or getting it
+//! from a call wraps it in `Some`, and storing a value into one, passing it as an argument or
+//! returning it unwraps it.
+//! Unwrapping `Some(f)` gives back `f`, so `ops.run = f` and calls through `ops.run` stay plain.
+
+use super::*;
+
+impl<'c> Translation<'c> {
+    /// Whether the lvalue `expr_id` is a non-null function pointer. The declared types of
+    /// variables and fields decide, since parameters covered by `__attribute__((nonnull))` only
+    /// have a `_Nonnull` type in their declaration.
+    pub fn is_nonnull_fn_ptr_lvalue(&self, mut expr_id: CExprId) -> bool {
+        while let CExprKind::Paren(_, e) = self.ast_context[expr_id].kind {
+            expr_id = e;
+        }
+        let typ = match self.ast_context[expr_id].kind {
+            CExprKind::DeclRef(_, decl_id, _) | CExprKind::Member(_, _, decl_id, _, _) => {
+                match self.ast_context[decl_id].kind {
+                    CDeclKind::Variable { typ, .. } | CDeclKind::Field { typ, .. } => typ.ctype,
+                    _ => return false,
+                }
+            }
+            ref kind => match kind.get_type() {
+                Some(typ) => typ,
+                None => return false,
+            },
+        };
+        self.ast_context.is_nonnull_fn_pointer(typ)
+    }
+
+    /// Which parameters of the function called through `func` are non-null function pointers
+    pub fn nonnull_fn_ptr_params(&self, func: CExprId) -> Vec<bool> {
+        // The declaration of a directly called function has `__attribute__((nonnull))` applied
+        if let CExprKind::ImplicitCast(_, fexp, CastKind::FunctionToPointerDecay, _, _) =
+            self.ast_context[func].kind
+        {
+            if let CExprKind::DeclRef(_, decl_id, _) = self.ast_context[fexp].kind {
+                if let CDeclKind::Function { ref parameters, .. } = self.ast_context[decl_id].kind {
+                    return parameters
+                        .iter()
+                        .map(|&param_id| match self.ast_context[param_id].kind {
+                            CDeclKind::Variable { typ, .. } => {
+                                self.ast_context.is_nonnull_fn_pointer(typ.ctype)
+                            }
+                            _ => false,
+                        })
+                        .collect();
+                }
+            }
+        }
+
+        let fn_ty = self.ast_context[func]
+            .kind
+            .get_type()
+            .and_then(|ty| self.ast_context.get_pointee_qual_type(ty))
+            .map(|ty| &self.ast_context.resolve_type(ty.ctype).kind);
+        match fn_ty {
+            Some(CTypeKind::Function(_, params, ..)) => params
+                .iter()
+                .map(|param| self.ast_context.is_nonnull_fn_pointer(param.ctype))
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
@@ -0,0 +1,622 @@
+//! This module implements `--openmp-rayon`, which translates simple `#pragma omp parallel for`
+//! loops into Rayon parallel iterators:
+//!
+//! ```c
+//! #pragma omp parallel for
+//! for (int i = 0; i < n; i++)
+//!     out[i] = in[i] * 2;
+//! ```
+//!
+//! becomes
+//!
+//! ```ignore
+//! let out_addr = out as usize;
+//! let in_addr = in_0 as usize;
+//! (0..n).into_par_iter().for_each(|i| {
+//!     let out = out_addr as *mut c_int;
+//!     let in_0 = in_addr as *const c_int;
+//!     *out.offset(i as isize) = *in_0.offset(i as isize) * 2;
+//! });
+//! ```
+//!
+//! Raw pointers are not `Send`, so the pointers the loop body uses are passed into the closure as
+//! addresses. A loop is only translated if its iterations can't interfere with each other: the
+//! body may only write to its own locals and to elements of pointers indexed by the loop
+//! variable, and it may not leave the loop early. Clang drops OpenMP pragmas from the AST unless
+//! OpenMP is enabled, so they are matched to the loops following them by source location. Every
+//! OpenMP pragma we don't translate is reported.
+
+use super::*;
+
+/// A `#pragma omp parallel for` loop translated into a Rayon parallel iterator.
+pub struct ParallelFor {
+    var: CDeclId,
+    /// Whether the loop variable is declared by the loop
+    declares_var: bool,
+    lo: CExprId,
+    hi: CExprId,
+    inclusive: bool,
+    body: CStmtId,
+    /// Local pointers the body uses, which are passed into the closure as addresses
+    pointers: Vec<CDeclId>,
+}
+
+/// OpenMP clauses that don't affect the meaning of a loop when Rayon schedules it.
+const IGNORED_CLAUSES: &[&str] = &["schedule", "shared", "default", "proc_bind"];
+
+/// The directive of an OpenMP pragma, normalized to single spaces, if `pragma` is one.
+fn omp_directive(pragma: &str) -> Option<String> {
+    let text = pragma.replace("\\\r\n", " ").replace("\\\n", " ");
+    let text = text.trim_start_matches('#').trim_start();
+    let text = text.strip_prefix("pragma")?.trim_start();
+    let text = text.strip_prefix("omp")?;
+    if !text.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let text = match text.find("//").into_iter().chain(text.find("/*")).min() {
+        Some(comment) => &text[..comment],
+        None => text,
+    };
+    Some(text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Split an OpenMP directive into its words, each with its parenthesized argument if it has one.
+fn parse_directive(directive: &str) -> Result<Vec<(&str, Option<&str>)>, String> {
+    let mut words = vec![];
+    let mut rest = directive;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if rest.is_empty() {
+            return Ok(words);
+        }
+        let end = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(rest.len());
+        if end == 0 {
+            return Err(format!("could not parse `{}`", directive));
+        }
+        let word = &rest[..end];
+        rest = rest[end..].trim_start();
+
+        let mut arg = None;
+        if rest.starts_with('(') {
+            let mut depth = 0;
+            let close = rest.find(|c| {
+                match c {
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => {}
+                }
+                depth == 0
+            });
+            match close {
+                Some(close) => {
+                    arg = Some(rest[1..close].trim());
+                    rest = &rest[close + 1..];
+                }
+                None => return Err(format!("unbalanced parentheses in `{}`", directive)),
+            }
+        }
+        words.push((word, arg));
+    }
+}
+
+impl<'c> Translation<'c> {
+    /// Find the loops translated by `--openmp-rayon`, keyed by their `for` statement, reporting
+    /// every OpenMP pragma that isn't translated.
+    pub fn find_parallel_loops(&self) -> HashMap<CStmtId, ParallelFor> {
+        let mut loops = HashMap::new();
+        for pragma in &self.ast_context.pragmas {
+            let directive = match omp_directive(&pragma.kind) {
+                Some(directive) => directive,
+                None => continue,
+            };
+            let loc = self.ast_context.display_loc(&pragma.loc);
+
+            if !self.tcfg.openmp_rayon {
+                diag!(
+                    Diagnostic::Openmp,
+                    at loc,
+                    "ignoring `#pragma omp {}`, the translated code runs sequentially \
+                    (see --openmp-rayon)",
+                    directive
+                );
+                continue;
+            }
+            match self.match_parallel_for(pragma, &directive) {
+                Ok((stmt_id, parallel_for)) => {
+                    loops.insert(stmt_id, parallel_for);
+                }
+                Err(reason) => diag!(
+                    Diagnostic::Openmp,
+                    at loc,
+                    "not translating `#pragma omp {}`, the translated code runs sequentially: {}",
+                    directive,
+                    reason
+                ),
+            }
+        }
+        loops
+    }
+
+    fn match_parallel_for(
+        &self,
+        pragma: &Located<String>,
+        directive: &str,
+    ) -> Result<(CStmtId, ParallelFor), String> {
+        let words = parse_directive(directive)?;
+        match words.as_slice() {
+            [("parallel", None), ("for", None), clauses @ ..] => {
+                for &(clause, arg) in clauses {
+                    if !IGNORED_CLAUSES.contains(&clause) {
+                        return Err(match arg {
+                            Some(arg) => format!("unsupported clause `{}({})`", clause, arg),
+                            None => format!("unsupported clause `{}`", clause),
+                        });
+                    }
+                }
+            }
+            _ => return Err("only `parallel for` directives are supported".to_string()),
+        }
+
+        let stmt_id = self
+            .stmt_after_pragma(pragma)
+            .ok_or("the directive is not inside a function body")?;
+        self.parallel_for(stmt_id)
+            .map(|parallel_for| (stmt_id, parallel_for))
+    }
+
+    /// Find the statement following a pragma inside a function body.
+    fn stmt_after_pragma(&self, pragma: &Located<String>) -> Option<CStmtId> {
+        let loc = pragma.loc?;
+        let pragma_end = (
+            loc.begin_line + pragma.kind.matches('\n').count() as u64,
+            loc.begin_column,
+        );
+        let body = self
+            .ast_context
+            .iter_decls()
+            .find_map(|(_, decl)| match decl.kind {
+                CDeclKind::Function {
+                    body: Some(body), ..
+                } => {
+                    let span = self.ast_context[body].loc?;
+                    let contains = span.fileid == loc.fileid
+                        && (span.begin_line, span.begin_column)
+                            < (loc.begin_line, loc.begin_column)
+                        && (loc.begin_line, loc.begin_column) < (span.end_line, span.end_column);
+                    contains.then(|| body)
+                }
+                _ => None,
+            })?;
+
+        DFExpr::new(&self.ast_context, body.into())
+            .flat_map(SomeId::stmt)
+            .filter_map(|stmt_id| {
+                let span = self.ast_context[stmt_id].loc?;
+                let start = (span.begin_line, span.begin_column);
+                (span.fileid == loc.fileid && start > pragma_end).then(|| (start, stmt_id))
+            })
+            .min_by_key(|&(start, _)| start)
+            .map(|(_, stmt_id)| stmt_id)
+    }
+
+    fn parallel_for(&self, stmt_id: CStmtId) -> Result<ParallelFor, String> {
+        let (init, condition, increment, body) = match self.ast_context[stmt_id].kind {
+            CStmtKind::ForLoop {
+                init: Some(init),
+                condition: Some(condition),
+                increment: Some(increment),
+                body,
+            } => (init, condition, increment, body),
+            CStmtKind::ForLoop { .. } => {
+                return Err("the loop needs an initializer, condition and increment".to_string())
+            }
+            _ => return Err("the directive is not followed by a `for` loop".to_string()),
+        };
+
+        // `int i = lo` or `i = lo`
+        let (var, declares_var, lo) = match self.ast_context[init].kind {
+            CStmtKind::Decls(ref decls) => match decls.as_slice() {
+                &[var] => match self.ast_context[var].kind {
+                    CDeclKind::Variable {
+                        has_static_duration: false,
+                        has_thread_duration: false,
+                        initializer: Some(lo),
+                        ..
+                    } => (var, true, lo),
+                    _ => return Err("unsupported loop variable declaration".to_string()),
+                },
+                _ => return Err("the loop declares more than one variable".to_string()),
+            },
+            CStmtKind::Expr(expr) => match self.ast_context[expr].kind {
+                CExprKind::Binary(_, c_ast::BinOp::Assign, lhs, lo, _, _) => {
+                    match self.ast_context[lhs].kind {
+                        CExprKind::DeclRef(_, var, _) => (var, false, lo),
+                        _ => return Err("unsupported loop initializer".to_string()),
+                    }
+                }
+                _ => return Err("unsupported loop initializer".to_string()),
+            },
+            _ => return Err("unsupported loop initializer".to_string()),
+        };
+        let var_name = match self.ast_context[var].kind {
+            CDeclKind::Variable {
+                ref ident,
+                has_static_duration: false,
+                has_thread_duration: false,
+                typ,
+                ..
+            } if self.is_integral(typ.ctype) => ident,
+            _ => return Err("the loop variable must be a local integer".to_string()),
+        };
+
+        // `i < hi` or `i <= hi`, where `i` isn't converted to another type
+        let (inclusive, lhs, hi) = match self.ast_context[condition].kind {
+            CExprKind::Binary(_, c_ast::BinOp::Less, lhs, hi, _, _) => (false, lhs, hi),
+            CExprKind::Binary(_, c_ast::BinOp::LessEqual, lhs, hi, _, _) => (true, lhs, hi),
+            _ => {
+                return Err(format!(
+                    "the condition must be `{0} < ...` or `{0} <= ...`",
+                    var_name
+                ))
+            }
+        };
+        match self.ast_context[lhs].kind {
+            CExprKind::ImplicitCast(_, e, CastKind::LValueToRValue, _, _)
+                if self.is_decl_ref(e, var) => {}
+            _ => {
+                return Err(format!(
+                    "the condition must compare `{}` to a bound of the same type",
+                    var_name
+                ))
+            }
+        }
+
+        // `i++`, `++i` or `i += 1`
+        let increments_by_one = match self.ast_context[increment].kind {
+            CExprKind::Unary(_, c_ast::UnOp::PostIncrement, e, _)
+            | CExprKind::Unary(_, c_ast::UnOp::PreIncrement, e, _) => self.is_decl_ref(e, var),
+            CExprKind::Binary(_, c_ast::BinOp::AssignAdd, lhs, rhs, _, _) => {
+                self.is_decl_ref(lhs, var)
+                    && matches!(
+                        self.ast_context.resolve_expr(rhs).1,
+                        CExprKind::Literal(_, CLiteral::Integer(1, _))
+                    )
+            }
+            _ => false,
+        };
+        if !increments_by_one {
+            return Err(format!("the loop must increment `{}` by one", var_name));
+        }
+
+        self.check_parallel_jumps(body, false, false)?;
+        let pointers = self.check_parallel_body(var, body)?;
+
+        Ok(ParallelFor {
+            var,
+            declares_var,
+            lo,
+            hi,
+            inclusive,
+            body,
+            pointers,
+        })
+    }
+
+    fn is_integral(&self, typ: CTypeId) -> bool {
+        self.ast_context.resolve_type(typ).kind.is_integral_type()
+    }
+
+    pub fn is_decl_ref(&self, expr: CExprId, decl_id: CDeclId) -> bool {
+        matches!(self.ast_context[expr].kind, CExprKind::DeclRef(_, d, _) if d == decl_id)
+    }
+
+    /// Check that the loop body can't leave the loop or skip to its next iteration. Also used by
+    /// `--range-loops`, which translates loop bodies on their own as well.
+    pub fn check_parallel_jumps(
+        &self,
+        stmt_id: CStmtId,
+        in_loop: bool,
+        in_switch: bool,
+    ) -> Result<(), String> {
+        match self.ast_context[stmt_id].kind {
+            CStmtKind::Break if !in_loop && !in_switch => {
+                Err("the loop body contains a `break`".to_string())
+            }
+            CStmtKind::Continue if !in_loop => {
+                Err("the loop body contains a `continue`".to_string())
+            }
+            CStmtKind::Return(_) => Err("the loop body contains a `return`".to_string()),
+            CStmtKind::Goto(_) | CStmtKind::Label(_) => {
+                Err("the loop body contains a `goto` or label".to_string())
+            }
+            CStmtKind::Asm { .. } => Err("the loop body contains inline assembly".to_string()),
+            CStmtKind::Case(..) | CStmtKind::CaseRange(..) | CStmtKind::Default(_)
+                if !in_switch =>
+            {
+                Err("the loop body contains a label of an enclosing `switch`".to_string())
+            }
+            CStmtKind::Case(_, stmt, _)
+            | CStmtKind::CaseRange(_, _, stmt, _, _)
+            | CStmtKind::Default(stmt)
+            | CStmtKind::Attributed {
+                substatement: stmt, ..
+            } => self.check_parallel_jumps(stmt, in_loop, in_switch),
+            CStmtKind::Compound(ref stmts) => stmts
+                .iter()
+                .try_for_each(|&stmt| self.check_parallel_jumps(stmt, in_loop, in_switch)),
+            CStmtKind::If {
+                true_variant,
+                false_variant,
+                ..
+            } => {
+                self.check_parallel_jumps(true_variant, in_loop, in_switch)?;
+                false_variant.map_or(Ok(()), |stmt| {
+                    self.check_parallel_jumps(stmt, in_loop, in_switch)
+                })
+            }
+            CStmtKind::Switch { body, .. } => self.check_parallel_jumps(body, in_loop, true),
+            CStmtKind::While { body, .. }
+            | CStmtKind::DoWhile { body, .. }
+            | CStmtKind::ForLoop { body, .. } => self.check_parallel_jumps(body, true, in_switch),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that iterations of the loop can't interfere with each other through the variables
+    /// and memory its body writes to, and that all the locals it uses can be shared between
+    /// threads. Returns the pointers the body uses.
+    fn check_parallel_body(&self, var: CDeclId, body: CStmtId) -> Result<Vec<CDeclId>, String> {
+        let mut locals = HashSet::new();
+        for node in DFExpr::new(&self.ast_context, body.into()) {
+            if let SomeId::Decl(decl_id) = node {
+                if let CDeclKind::Variable {
+                    has_static_duration: false,
+                    has_thread_duration: false,
+                    ..
+                } = self.ast_context[decl_id].kind
+                {
+                    locals.insert(decl_id);
+                }
+            }
+        }
+
+        let mut pointers = IndexSet::new();
+        for node in DFExpr::new(&self.ast_context, body.into()) {
+            let expr_id = match node {
+                SomeId::Expr(expr_id) => expr_id,
+                _ => continue,
+            };
+            match self.ast_context[expr_id].kind {
+                CExprKind::Binary(_, op, lhs, _, _, _)
+                    if op == c_ast::BinOp::Assign || op.underlying_assignment().is_some() =>
+                {
+                    self.check_parallel_write(var, &locals, lhs)?
+                }
+                CExprKind::Unary(_, op, e, _)
+                    if matches!(
+                        op,
+                        c_ast::UnOp::PreIncrement
+                            | c_ast::UnOp::PostIncrement
+                            | c_ast::UnOp::PreDecrement
+                            | c_ast::UnOp::PostDecrement
+                            | c_ast::UnOp::AddressOf
+                    ) =>
+                {
+                    self.check_parallel_write(var, &locals, e)?
+                }
+                CExprKind::DeclRef(_, decl_id, _)
+                    if decl_id != var && !locals.contains(&decl_id) =>
+                {
+                    if self.check_parallel_capture(decl_id)? {
+                        pointers.insert(decl_id);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(pointers.into_iter().collect())
+    }
+
+    /// Check that a local the loop body uses from outside of it can be shared between threads.
+    /// Returns whether it is a pointer, which needs to be passed into the closure as an address.
+    fn check_parallel_capture(&self, decl_id: CDeclId) -> Result<bool, String> {
+        let (ident, typ) = match self.ast_context[decl_id].kind {
+            CDeclKind::Variable {
+                has_static_duration: false,
+                has_thread_duration: false,
+                ref ident,
+                typ,
+                ..
+            } => (ident, typ.ctype),
+            // Globals and statics are accessed the same way from any thread
+            _ => return Ok(false),
+        };
+        let is_number = |typ: CTypeId| {
+            let kind = &self.ast_context.resolve_type(typ).kind;
+            kind.is_integral_type() || kind.is_floating_type() || kind.is_bool() || kind.is_enum()
+        };
+        if is_number(typ) || self.ast_context.is_function_pointer(typ) {
+            return Ok(false);
+        }
+        match self.ast_context.resolve_type(typ).kind {
+            CTypeKind::Pointer(_) => Ok(true),
+            CTypeKind::ConstantArray(elt, _) if is_number(elt) => Ok(false),
+            _ => Err(format!(
+                "the loop body uses `{}`, which can't be shared between threads",
+                ident
+            )),
+        }
+    }
+
+    /// Check that a write in the loop body is to one of the body's own locals or to an element of
+    /// a pointer indexed by the loop variable, which no other iteration can write to.
+    fn check_parallel_write(
+        &self,
+        var: CDeclId,
+        locals: &HashSet<CDeclId>,
+        target: CExprId,
+    ) -> Result<(), String> {
+        match *self.ast_context.resolve_expr(target).1 {
+            CExprKind::Member(_, base, _, MemberKind::Dot, _) => {
+                self.check_parallel_write(var, locals, base)
+            }
+            CExprKind::ArraySubscript(_, base, idx, _) => {
+                let indexed_by_var = matches!(
+                    *self.ast_context.resolve_expr(idx).1,
+                    CExprKind::DeclRef(_, decl_id, _) if decl_id == var
+                );
+                let base = match *self.ast_context.resolve_expr(base).1 {
+                    CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[decl_id].kind {
+                        CDeclKind::Variable { typ, .. } => Some((
+                            decl_id,
+                            self.ast_context.resolve_type(typ.ctype).kind.is_pointer(),
+                        )),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                match base {
+                    // The body's own arrays, but not whatever its own pointers point to
+                    Some((decl_id, false)) if locals.contains(&decl_id) => Ok(()),
+                    Some((decl_id, true)) if !locals.contains(&decl_id) && indexed_by_var => Ok(()),
+                    _ => Err(
+                        "the loop body writes to an array element that isn't indexed by \
+                        the loop variable"
+                            .to_string(),
+                    ),
+                }
+            }
+            CExprKind::DeclRef(_, decl_id, _) if locals.contains(&decl_id) => Ok(()),
+            CExprKind::DeclRef(_, decl_id, _) => Err(match self.ast_context[decl_id].kind {
+                CDeclKind::Variable { ref ident, .. } => {
+                    format!("the loop body writes to `{}`", ident)
+                }
+                _ => "the loop body writes to a variable declared outside of it".to_string(),
+            }),
+            _ => Err(
+                "the loop body writes to memory that other iterations may also access".to_string(),
+            ),
+        }
+    }
+
+    /// Whether `stmt_id` is a loop selected by `--openmp-rayon`.
+    pub fn is_parallel_loop(&self, stmt_id: CStmtId) -> bool {
+        self.parallel_loops.contains_key(&stmt_id)
+    }
+
+    /// Convert a loop selected by `--openmp-rayon` into a Rayon parallel iterator.
+    pub fn convert_parallel_for(
+        &self,
+        ctx: ExprContext,
+        stmt_id: CStmtId,
+    ) -> Result<Vec<Stmt>, TranslationError> {
+        let parallel_for = &self.parallel_loops[&stmt_id];
+
+        self.use_crate(ExternCrate::Rayon);
+        self.with_cur_file_item_store(|item_store| {
+            let path = vec!["rayon".to_string(), "iter".to_string()];
+            item_store.add_use(path.clone(), "IntoParallelIterator");
+            item_store.add_use(path, "ParallelIterator");
+        });
+
+        let (mut stmts, lo) = self
+            .convert_expr(ctx.used(), parallel_for.lo)?
+            .discard_unsafe();
+        let (hi_stmts, hi) = self
+            .convert_expr(ctx.used(), parallel_for.hi)?
+            .discard_unsafe();
+        stmts.extend(hi_stmts);
+
+        // Pass pointers into the closure as addresses
+        let mut closure_stmts = vec![];
+        for &decl_id in &parallel_for.pointers {
+            let name = self
+                .renamer
+                .borrow()
+                .get(&decl_id)
+                .ok_or_else(|| format_err!("Parallel loop uses an unnamed variable"))?;
+            let ty = match self.ast_context[decl_id].kind {
+                CDeclKind::Variable { typ, .. } => self.convert_type(typ.ctype)?,
+                _ => return Err(format_err!("Parallel loop uses a non-variable").into()),
+            };
+            let addr_name = self
+                .renamer
+                .borrow_mut()
+                .pick_name(&format!("{}_addr", name));
+
+            let addr = mk().cast_expr(mk().ident_expr(&*name), mk().path_ty(vec!["usize"]));
+            let addr_local = mk().local(
+                mk().ident_pat(&addr_name),
+                None as Option<Box<Type>>,
+                Some(addr),
+            );
+            stmts.push(mk().local_stmt(Box::new(addr_local)));
+
+            let ptr = mk().cast_expr(mk().ident_expr(&addr_name), ty);
+            let ptr_local = mk().local(mk().ident_pat(&name), None as Option<Box<Type>>, Some(ptr));
+            closure_stmts.push(mk().local_stmt(Box::new(ptr_local)));
+        }
+
+        let (var_name, body) = self.with_scope(|| -> Result<_, TranslationError> {
+            let var_name = if parallel_for.declares_var {
+                let ident = match self.ast_context[parallel_for.var].kind {
+                    CDeclKind::Variable { ref ident, .. } => ident,
+                    _ => return Err(format_err!("Parallel loop variable is not a variable").into()),
+                };
+                self.renamer
+                    .borrow_mut()
+                    .insert(parallel_for.var, ident)
+                    .expect(&format!("Failed to insert variable '{}'", ident))
+            } else {
+                self.renamer
+                    .borrow()
+                    .get(&parallel_for.var)
+                    .ok_or_else(|| format_err!("Parallel loop variable is unnamed"))?
+            };
+            let name = format!("<parallel-for_{:?}>", stmt_id);
+            let body = self.convert_function_body(
+                ctx,
+                &name,
+                &[parallel_for.body],
+                cfg::ImplicitReturnType::Void,
+            )?;
+            Ok((var_name, body))
+        })?;
+        closure_stmts.extend(body);
+
+        let limits = if parallel_for.inclusive {
+            RangeLimits::Closed(Default::default())
+        } else {
+            RangeLimits::HalfOpen(Default::default())
+        };
+        let range = Box::new(Expr::Range(ExprRange {
+            attrs: vec![],
+            from: Some(lo),
+            limits,
+            to: Some(hi),
+        }));
+        let iter = mk().method_call_expr(
+            mk().paren_expr(range),
+            "into_par_iter",
+            vec![] as Vec<Box<Expr>>,
+        );
+
+        let closure_decl = mk().fn_decl(
+            "parallel_for",
+            vec![mk().arg(mk().infer_ty(), mk().ident_pat(var_name))],
+            None,
+            ReturnType::Default,
+        );
+        let closure = mk().closure_expr(
+            CaptureBy::Ref,
+            Movability::Movable,
+            closure_decl,
+            mk().block_expr(mk().block(closure_stmts)),
+        );
+        let for_each = mk().method_call_expr(iter, "for_each", vec![closure]);
+        stmts.push(mk().semi_stmt(for_each));
+        Ok(stmts)
+    }
+}
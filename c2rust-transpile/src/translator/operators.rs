@@ -66,7 +66,7 @@ impl<'c> Translation<'c> {
             c_ast::BinOp::And | c_ast::BinOp::Or => {
                 let lhs = self.convert_condition(ctx, true, lhs)?;
                 let rhs = self.convert_condition(ctx, true, rhs)?;
-                lhs.map(|x| bool_to_int(mk().binary_expr(BinOp::from(op), x, rhs.to_expr())))
+                lhs.map(|x| self.bool_to_int(mk().binary_expr(BinOp::from(op), x, rhs.to_expr())))
                     .and_then(|out| {
                         if ctx.is_unused() {
                             Ok(WithStmts::new(
@@ -196,10 +196,20 @@ impl<'c> Translation<'c> {
             )))
         } else {
             let resolved_computed_kind = &self.ast_context.resolve_type(compute_lhs_ty.ctype).kind;
+            let resolved_lhs_kind = &self.ast_context.resolve_type(lhs_ty.ctype).kind;
             let lhs_type = self.convert_type(compute_lhs_ty.ctype)?;
+            let is_half_float = resolved_computed_kind.is_half_float_type()
+                || resolved_lhs_kind.is_half_float_type();
 
             // We can't simply as-cast into a non primitive like f128
-            let lhs = if *resolved_computed_kind == CTypeKind::LongDouble {
+            let lhs = if is_half_float {
+                self.convert_half_float_cast(
+                    read,
+                    resolved_lhs_kind,
+                    resolved_computed_kind,
+                    lhs_type.clone(),
+                )?
+            } else if *resolved_computed_kind == CTypeKind::LongDouble {
                 self.use_crate(ExternCrate::F128);
 
                 let fn_path = mk().path_expr(vec!["f128", "f128", "from"]);
@@ -235,8 +245,14 @@ impl<'c> Translation<'c> {
                 ))
             } else {
                 // We can't as-cast from a non primitive like f128 back to the result_type
-                if *resolved_computed_kind == CTypeKind::LongDouble {
-                    let resolved_lhs_kind = &self.ast_context.resolve_type(lhs_ty.ctype).kind;
+                if is_half_float {
+                    WithStmts::new_val(self.convert_half_float_cast(
+                        val,
+                        resolved_computed_kind,
+                        resolved_lhs_kind,
+                        result_type,
+                    )?)
+                } else if *resolved_computed_kind == CTypeKind::LongDouble {
                     let val = WithStmts::new_val(val);
 
                     self.f128_cast_to(val, resolved_lhs_kind)?
@@ -258,13 +274,25 @@ impl<'c> Translation<'c> {
         compute_type: Option<CQualTypeId>,
         result_type: Option<CQualTypeId>,
     ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        if op == c_ast::BinOp::Assign {
+            if let Some(val) = self.convert_tagged_union_tag_assignment(ctx, lhs, rhs)? {
+                return Ok(val);
+            }
+            if let Some(val) = self.convert_fn_pointer_union_assignment(ctx, lhs, rhs)? {
+                return Ok(val);
+            }
+        }
+
         let rhs_type_id = self
             .ast_context
             .index(rhs)
             .kind
             .get_qual_type()
             .ok_or_else(|| format_err!("bad assignment rhs type"))?;
-        let rhs_translation = self.convert_expr(ctx.used(), rhs)?;
+        let mut rhs_translation = self.convert_expr(ctx.used(), rhs)?;
+        if op == c_ast::BinOp::Assign && self.is_nonnull_fn_ptr_lvalue(lhs) {
+            rhs_translation = rhs_translation.map(unwrap_function_pointer);
+        }
         self.convert_assignment_operator_with_rhs(
             ctx,
             op,
@@ -441,12 +469,12 @@ impl<'c> Translation<'c> {
                     // Everything else
                     c_ast::BinOp::AssignAdd if pointer_lhs.is_some() => {
                         let mul = self.compute_size_of_expr(pointer_lhs.unwrap().ctype);
-                        let ptr = pointer_offset(write.clone(), rhs, mul, false, false);
+                        let ptr = self.pointer_offset(write.clone(), rhs, mul, false, false);
                         WithStmts::new_val(mk().assign_expr(&write, ptr))
                     }
                     c_ast::BinOp::AssignSubtract if pointer_lhs.is_some() => {
                         let mul = self.compute_size_of_expr(pointer_lhs.unwrap().ctype);
-                        let ptr = pointer_offset(write.clone(), rhs, mul, true, false);
+                        let ptr = self.pointer_offset(write.clone(), rhs, mul, true, false);
                         WithStmts::new_val(mk().assign_expr(&write, ptr))
                     }
 
@@ -595,6 +623,10 @@ impl<'c> Translation<'c> {
         rhs: Box<Expr>,
         lhs_rhs_ids: Option<(CExprId, CExprId)>,
     ) -> Result<Box<Expr>, TranslationError> {
+        if self.is_generic_vector_type(lhs_type.ctype) {
+            return self.convert_vector_binary_operator(op, lhs, rhs);
+        }
+
         let is_unsigned_integral_type = self
             .ast_context
             .index(ctype)
@@ -670,7 +702,7 @@ impl<'c> Translation<'c> {
                     mk().binary_expr(BinOp::Eq(Default::default()), lhs, rhs)
                 };
 
-                Ok(bool_to_int(expr))
+                Ok(self.bool_to_int(expr))
             }
             c_ast::BinOp::NotEqual => {
                 // Using is_some method for null comparison means we don't have to
@@ -692,28 +724,20 @@ impl<'c> Translation<'c> {
                     mk().binary_expr(BinOp::Ne(Default::default()), lhs, rhs)
                 };
 
-                Ok(bool_to_int(expr))
+                Ok(self.bool_to_int(expr))
+            }
+            c_ast::BinOp::Less => {
+                Ok(self.bool_to_int(mk().binary_expr(BinOp::Lt(Default::default()), lhs, rhs)))
+            }
+            c_ast::BinOp::Greater => {
+                Ok(self.bool_to_int(mk().binary_expr(BinOp::Gt(Default::default()), lhs, rhs)))
+            }
+            c_ast::BinOp::GreaterEqual => {
+                Ok(self.bool_to_int(mk().binary_expr(BinOp::Ge(Default::default()), lhs, rhs)))
+            }
+            c_ast::BinOp::LessEqual => {
+                Ok(self.bool_to_int(mk().binary_expr(BinOp::Le(Default::default()), lhs, rhs)))
             }
-            c_ast::BinOp::Less => Ok(bool_to_int(mk().binary_expr(
-                BinOp::Lt(Default::default()),
-                lhs,
-                rhs,
-            ))),
-            c_ast::BinOp::Greater => Ok(bool_to_int(mk().binary_expr(
-                BinOp::Gt(Default::default()),
-                lhs,
-                rhs,
-            ))),
-            c_ast::BinOp::GreaterEqual => Ok(bool_to_int(mk().binary_expr(
-                BinOp::Ge(Default::default()),
-                lhs,
-                rhs,
-            ))),
-            c_ast::BinOp::LessEqual => Ok(bool_to_int(mk().binary_expr(
-                BinOp::Le(Default::default()),
-                lhs,
-                rhs,
-            ))),
 
             c_ast::BinOp::BitAnd => {
                 Ok(mk().binary_expr(BinOp::BitAnd(Default::default()), lhs, rhs))
@@ -737,10 +761,10 @@ impl<'c> Translation<'c> {
 
         if let &CTypeKind::Pointer(pointee) = lhs_type {
             let mul = self.compute_size_of_expr(pointee.ctype);
-            Ok(pointer_offset(lhs, rhs, mul, false, false))
+            Ok(self.pointer_offset(lhs, rhs, mul, false, false))
         } else if let &CTypeKind::Pointer(pointee) = rhs_type {
             let mul = self.compute_size_of_expr(pointee.ctype);
-            Ok(pointer_offset(rhs, lhs, mul, false, false))
+            Ok(self.pointer_offset(rhs, lhs, mul, false, false))
         } else if lhs_type.is_unsigned_integral_type() {
             if ctx.is_const {
                 return Err(TranslationError::generic(
@@ -781,7 +805,7 @@ impl<'c> Translation<'c> {
             Ok(mk().cast_expr(offset, ty))
         } else if let &CTypeKind::Pointer(pointee) = lhs_type {
             let mul = self.compute_size_of_expr(pointee.ctype);
-            Ok(pointer_offset(lhs, rhs, mul, true, false))
+            Ok(self.pointer_offset(lhs, rhs, mul, true, false))
         } else if lhs_type.is_unsigned_integral_type() {
             if ctx.is_const {
                 return Err(TranslationError::generic(
@@ -807,8 +831,7 @@ impl<'c> Translation<'c> {
             c_ast::BinOp::AssignSubtract
         };
         let one = match self.ast_context.resolve_type(ty.ctype).kind {
-            // TODO: If rust gets f16 support:
-            // CTypeKind::Half |
+            ref k if k.is_half_float_type() => self.half_float_const(k, "ONE"),
             CTypeKind::Float | CTypeKind::Double => mk().lit_expr(mk().float_unsuffixed_lit("1.")),
             CTypeKind::LongDouble => {
                 self.use_crate(ExternCrate::F128);
@@ -865,8 +888,7 @@ impl<'c> Translation<'c> {
                 )));
 
                 let mut one = match self.ast_context[ty.ctype].kind {
-                    // TODO: If rust gets f16 support:
-                    // CTypeKind::Half |
+                    ref k if k.is_half_float_type() => self.half_float_const(k, "ONE"),
                     CTypeKind::Float | CTypeKind::Double => {
                         mk().lit_expr(mk().float_unsuffixed_lit("1."))
                     }
@@ -965,6 +987,8 @@ impl<'c> Translation<'c> {
                 // In this translation, there are only pointers to functions and
                 // & becomes a no-op when applied to a function.
 
+                // Statics are `static mut`s, which can't be borrowed in static initializers
+                let is_static_place = ctx.is_static && self.is_static_place(arg);
                 let arg = self.convert_expr(ctx.used().set_needs_address(true), arg)?;
 
                 if self.ast_context.is_function_pointer(ctype) {
@@ -986,7 +1010,10 @@ impl<'c> Translation<'c> {
                     arg.result_map(|a| {
                         let mut addr_of_arg: Box<Expr>;
 
-                        if ctx.is_static {
+                        if is_static_place {
+                            // `addr_of!` gives a `*const` pointer, which is cast to `ty` below
+                            addr_of_arg = self.static_addr_of(a);
+                        } else if ctx.is_static {
                             // static variable initializers aren't able to use &mut,
                             // so we work around that by using & and an extra cast
                             // through & to *const to *mut
@@ -1019,6 +1046,9 @@ impl<'c> Translation<'c> {
             c_ast::UnOp::PostIncrement => self.convert_post_increment(ctx, cqual_type, true, arg),
             c_ast::UnOp::PostDecrement => self.convert_post_increment(ctx, cqual_type, false, arg),
             c_ast::UnOp::Deref => {
+                if let Some(val) = self.convert_local_alloc_deref(arg) {
+                    return Ok(val);
+                }
                 match self.ast_context[arg].kind {
                     CExprKind::Unary(_, c_ast::UnOp::AddressOf, arg_, _) => {
                         self.convert_expr(ctx.used(), arg_)
@@ -1033,6 +1063,7 @@ impl<'c> Translation<'c> {
                                 } else if let Some(_vla) = self.compute_size_of_expr(ctype) {
                                     Ok(val)
                                 } else {
+                                    self.record_unsafe(self.deref_unsafe_reason(arg));
                                     let mut val =
                                         mk().unary_expr(UnOp::Deref(Default::default()), val);
 
@@ -1069,7 +1100,7 @@ impl<'c> Translation<'c> {
 
             c_ast::UnOp::Not => {
                 let val = self.convert_condition(ctx, false, arg)?;
-                Ok(val.map(|x| mk().cast_expr(x, mk().path_ty(vec!["libc", "c_int"]))))
+                Ok(val.map(|x| mk().cast_expr(x, self.libc_ty("c_int"))))
             }
             c_ast::UnOp::Extension => {
                 let arg = self.convert_expr(ctx, arg)?;
@@ -1080,4 +1111,26 @@ impl<'c> Translation<'c> {
             }
         }
     }
+
+    /// Why dereferencing `ptr` needs `unsafe`, which depends on whether it is offset by an index
+    /// (`*(p + i)`)
+    fn deref_unsafe_reason(&self, ptr: CExprId) -> UnsafeReason {
+        let is_pointer = |expr_id: CExprId| {
+            self.ast_context[expr_id]
+                .kind
+                .get_type()
+                .map_or(false, |ty| self.ast_context.resolve_type(ty).kind.is_pointer())
+        };
+        match *self.ast_context.resolve_expr(ptr).1 {
+            CExprKind::Binary(_, c_ast::BinOp::Add, lhs, rhs, _, _)
+                if is_pointer(lhs) || is_pointer(rhs) =>
+            {
+                UnsafeReason::PointerArithmeticDeref
+            }
+            CExprKind::Binary(_, c_ast::BinOp::Subtract, lhs, _, _, _) if is_pointer(lhs) => {
+                UnsafeReason::PointerArithmeticDeref
+            }
+            _ => UnsafeReason::RawPointerDeref,
+        }
+    }
 }
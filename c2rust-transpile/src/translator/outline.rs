@@ -0,0 +1,512 @@
+//! This module splits oversized functions, like the ones in generated parsers, into a driver and
+//! helper functions so that they stay within what rustc handles comfortably.
+//!
+//! When a function body has more than `--outline-threshold` statements, runs of top-level
+//! statements are moved into helper functions, each of which gets the locals of the driver that
+//! it uses as references in a context struct. Statements that might return from the function
+//! stay in the driver, as do runs that use locals in ways that can't be rewritten, such as inside
+//! macros. Locals declared in a run move along with it if nothing after the run uses them.
+
+use super::*;
+use crate::rust_ast::traverse::{
+    traverse_arm_def, traverse_expr_def, traverse_field_def, traverse_local_def, traverse_stmt_def,
+    Traversal,
+};
+use syn::__private::ToTokens;
+
+/// A parameter or top-level local of the driver
+struct DriverLocal {
+    /// The type of the local, if it is known and can be stored in a context struct
+    ty: Option<Box<Type>>,
+    mutbl: Mutability,
+    /// The index of the driver statement declaring the local, if it is declared without an
+    /// initializer
+    uninit_decl: Option<usize>,
+}
+
+/// A top-level statement that may be moved into a helper
+struct PartStmt {
+    /// The index of the statement in the original body
+    idx: usize,
+    stmt: Stmt,
+    size: usize,
+    /// Identifiers used as paths, which may refer to locals of the driver
+    uses: HashSet<String>,
+    /// Identifiers that uses of locals can't be rewritten in, such as those in macros and
+    /// bindings that shadow a local
+    opaque: HashSet<String>,
+    /// For a local declaration, the index of the last statement that may use the local
+    live_until: Option<usize>,
+}
+
+/// Builds the driver and helper functions for a single function
+struct Outliner<'a, 'c> {
+    t: &'a Translation<'c>,
+    name: String,
+    threshold: usize,
+    /// The index of the last statement mentioning each identifier
+    last_mention: HashMap<String, usize>,
+    locals: IndexMap<String, DriverLocal>,
+    driver: Vec<Stmt>,
+    items: Vec<Box<Item>>,
+    /// The run of statements that will be moved into the next helper
+    part: Vec<PartStmt>,
+}
+
+impl<'c> Translation<'c> {
+    /// Split function `item` into a driver and helper functions if its body has more statements
+    /// than the outline threshold.
+    pub fn outline_function(&self, item: Box<Item>) -> ConvertedDecl {
+        let threshold = match self.tcfg.outline_threshold {
+            Some(threshold) => threshold,
+            None => return ConvertedDecl::Item(item),
+        };
+        let mut item_fn = match *item {
+            Item::Fn(item_fn) => item_fn,
+            item => return ConvertedDecl::Item(Box::new(item)),
+        };
+
+        // Items declared in the body are only visible to the driver, and variadic functions
+        // have a `VaListImpl` that can't be shared
+        let has_items = item_fn
+            .block
+            .stmts
+            .iter()
+            .any(|stmt| matches!(stmt, Stmt::Item(..)));
+        let size: usize = item_fn.block.stmts.iter().map(stmt_size).sum();
+        if item_fn.sig.variadic.is_some() || has_items || size <= threshold {
+            return ConvertedDecl::Item(Box::new(Item::Fn(item_fn)));
+        }
+
+        let stmts = mem::take(&mut item_fn.block.stmts);
+        let mut last_mention = HashMap::new();
+        for (idx, stmt) in stmts.iter().enumerate() {
+            let mut idents = HashSet::new();
+            collect_idents(stmt.to_token_stream(), &mut idents);
+            last_mention.extend(idents.into_iter().map(|ident| (ident, idx)));
+        }
+
+        let mut outliner = Outliner {
+            t: self,
+            name: item_fn.sig.ident.to_string(),
+            threshold,
+            last_mention,
+            locals: IndexMap::new(),
+            driver: vec![],
+            items: vec![],
+            part: vec![],
+        };
+        for arg in &item_fn.sig.inputs {
+            if let FnArg::Typed(PatType { pat, ty, .. }) = arg {
+                outliner.add_local(pat, Some(ty.clone()), None);
+            }
+        }
+        let len = stmts.len();
+        for (idx, stmt) in stmts.into_iter().enumerate() {
+            outliner.add_stmt(idx, stmt);
+        }
+        outliner.flush(len);
+        let rest = mem::take(&mut outliner.part);
+        outliner.inline(rest);
+
+        item_fn.block.stmts = outliner.driver;
+        let mut items = vec![Box::new(Item::Fn(item_fn))];
+        items.append(&mut outliner.items);
+        ConvertedDecl::Items(items)
+    }
+}
+
+impl<'a, 'c> Outliner<'a, 'c> {
+    fn add_local(&mut self, pat: &Pat, ty: Option<Box<Type>>, uninit_decl: Option<usize>) {
+        let (ident, mutbl, ty) = match simple_binding(pat) {
+            Some((ident, mutbl, pat_ty)) => (ident, mutbl, ty.or(pat_ty)),
+            None => return,
+        };
+
+        // References to types with lifetimes would need more lifetimes on the context struct
+        let ty = ty.filter(|ty| {
+            !ty.to_token_stream()
+                .into_iter()
+                .any(|tt| matches!(tt, TokenTree::Punct(ref p) if p.as_char() == '\''))
+        });
+        self.locals.insert(
+            ident,
+            DriverLocal {
+                ty,
+                mutbl,
+                uninit_decl,
+            },
+        );
+    }
+
+    fn add_stmt(&mut self, idx: usize, stmt: Stmt) {
+        let mut info = StmtInfo {
+            size: 0,
+            uses: HashSet::new(),
+            opaque: HashSet::new(),
+            outlinable: true,
+        };
+        let mut live_until = None;
+        let stmt = match stmt {
+            Stmt::Local(local) => {
+                match simple_binding(&local.pat) {
+                    Some((ident, _, _)) => live_until = self.last_mention.get(&ident).copied(),
+                    None => info.outlinable = false,
+                }
+                // The binding is a new top-level local rather than one shadowing a local
+                info.size += 1;
+                Stmt::Local(traverse_local_def(&mut info, local))
+            }
+            Stmt::Item(item) => {
+                info.outlinable = false;
+                Stmt::Item(item)
+            }
+            stmt => info.traverse_stmt(stmt),
+        };
+        let StmtInfo {
+            size,
+            uses,
+            opaque,
+            outlinable,
+        } = info;
+        let stmt = PartStmt {
+            idx,
+            stmt,
+            size,
+            uses,
+            opaque,
+            live_until,
+        };
+
+        if !outlinable {
+            self.flush(idx);
+            let rest = mem::take(&mut self.part);
+            self.inline(rest);
+            self.inline(vec![stmt]);
+            return;
+        }
+
+        let part_size: usize = self.part.iter().map(|stmt| stmt.size).sum();
+        if part_size + size > self.threshold {
+            self.flush(idx);
+        }
+        self.part.push(stmt);
+    }
+
+    /// Move statements into the driver
+    fn inline(&mut self, stmts: Vec<PartStmt>) {
+        for PartStmt { stmt, .. } in stmts {
+            if let Stmt::Local(ref local) = stmt {
+                let uninit_decl = local.init.is_none().then(|| self.driver.len());
+                self.add_local(&local.pat, None, uninit_decl);
+            }
+            self.driver.push(stmt);
+        }
+    }
+
+    /// Move the longest prefix of the current run that doesn't declare locals used from
+    /// statement `next_idx` on into a helper function, keeping the rest of the run.
+    fn flush(&mut self, next_idx: usize) {
+        let mut live_until = 0;
+        let mut prefix_len = 0;
+        for (i, stmt) in self.part.iter().enumerate() {
+            live_until = live_until.max(stmt.live_until.unwrap_or(0));
+            let end = self.part.get(i + 1).map_or(next_idx, |stmt| stmt.idx);
+            if live_until < end {
+                prefix_len = i + 1;
+            }
+        }
+
+        let rest = self.part.split_off(prefix_len);
+        let prefix = mem::replace(&mut self.part, rest);
+        let prefix_size: usize = prefix.iter().map(|stmt| stmt.size).sum();
+        if prefix.is_empty() || prefix_size * 4 < self.threshold {
+            self.inline(prefix);
+        } else if let Err(prefix) = self.outline(prefix) {
+            self.inline(prefix);
+        }
+    }
+
+    /// Move statements into a new helper function, or hand them back if they use locals of the
+    /// driver that can't be passed to it.
+    fn outline(&mut self, stmts: Vec<PartStmt>) -> Result<(), Vec<PartStmt>> {
+        let captured: Vec<(&String, &DriverLocal)> = self
+            .locals
+            .iter()
+            .filter(|(name, _)| stmts.iter().any(|stmt| stmt.uses.contains(*name)))
+            .collect();
+        let capturable = captured.iter().all(|(name, local)| match local.ty {
+            Some(ref ty) => {
+                (local.uninit_decl.is_none() || is_int_ty(ty))
+                    && stmts.iter().all(|stmt| !stmt.opaque.contains(*name))
+            }
+            None => false,
+        });
+        if !capturable {
+            return Err(stmts);
+        }
+
+        let fn_name = self
+            .t
+            .renamer
+            .borrow_mut()
+            .pick_name_root(&format!("{}_part", self.name));
+        let stmts: Vec<Stmt> = stmts.into_iter().map(|stmt| stmt.stmt).collect();
+        if captured.is_empty() {
+            self.items.push(mk().unsafe_().fn_item(
+                mk().fn_decl(&fn_name, vec![], None, ReturnType::Default),
+                stmts_block(stmts),
+            ));
+            let call = mk().call_expr(mk().ident_expr(&fn_name), vec![] as Vec<Box<Expr>>);
+            self.driver.push(mk().semi_stmt(call));
+            return Ok(());
+        }
+
+        let ctx_name = self
+            .t
+            .renamer
+            .borrow_mut()
+            .pick_name_root(&format!("{}_ctx", fn_name));
+
+        // The helper accesses the locals through `(*ctx.x)`
+        let ctx_param = unused_ident("ctx", &stmts);
+        let mut rewriter = CaptureRewriter {
+            captured: captured.iter().map(|(name, _)| name.as_str()).collect(),
+            ctx: &ctx_param,
+        };
+        let stmts = stmts
+            .into_iter()
+            .map(|stmt| rewriter.traverse_stmt(stmt))
+            .collect();
+
+        let mut fields = vec![];
+        let mut field_values = vec![];
+        let mut uninit_decls = vec![];
+        for (name, local) in captured {
+            let ty = local.ty.clone().expect("captured local without a type");
+            let mk_ref = || mk().set_mutbl(local.mutbl.clone());
+            fields.push(mk().struct_field(name, mk_ref().ref_lt_ty("a", ty)));
+            field_values.push(mk().field(name, mk_ref().addr_of_expr(mk().ident_expr(name))));
+            uninit_decls.extend(local.uninit_decl);
+        }
+
+        // Locals have to be initialized to be borrowed
+        for idx in uninit_decls {
+            if let Stmt::Local(ref mut local) = self.driver[idx] {
+                let zero = mk().lit_expr(mk().int_unsuffixed_lit(0));
+                local.init = Some((Default::default(), zero));
+            }
+        }
+
+        self.items.push(
+            mk().generic_over(mk().lt_param("a"))
+                .struct_item(&ctx_name, fields, false),
+        );
+        let ctx_ty = mk().mutbl().ref_ty(mk().path_ty(vec![&ctx_name]));
+        self.items.push(mk().unsafe_().fn_item(
+            mk().fn_decl(
+                &fn_name,
+                vec![mk().arg(ctx_ty, mk().ident_pat(&ctx_param))],
+                None,
+                ReturnType::Default,
+            ),
+            stmts_block(stmts),
+        ));
+        let ctx = mk()
+            .mutbl()
+            .addr_of_expr(mk().struct_expr(vec![ctx_name], field_values));
+        let call = mk().call_expr(mk().ident_expr(&fn_name), vec![ctx]);
+        self.driver.push(mk().semi_stmt(call));
+        Ok(())
+    }
+}
+
+/// The name, mutability and type of a binding like `mut x: T`
+fn simple_binding(pat: &Pat) -> Option<(String, Mutability, Option<Box<Type>>)> {
+    let (pat, ty) = match pat {
+        Pat::Type(PatType { pat, ty, .. }) => (&**pat, Some(ty.clone())),
+        pat => (pat, None),
+    };
+    match pat {
+        Pat::Ident(PatIdent {
+            ident,
+            mutability,
+            by_ref: None,
+            subpat: None,
+            ..
+        }) => {
+            let mutbl = if mutability.is_some() {
+                Mutability::Mutable
+            } else {
+                Mutability::Immutable
+            };
+            Some((ident.to_string(), mutbl, ty))
+        }
+        _ => None,
+    }
+}
+
+/// The number of statements in `stmt`, including nested ones
+fn stmt_size(stmt: &Stmt) -> usize {
+    let mut info = StmtInfo {
+        size: 0,
+        uses: HashSet::new(),
+        opaque: HashSet::new(),
+        outlinable: true,
+    };
+    info.traverse_stmt(stmt.clone());
+    info.size
+}
+
+/// Pick a name based on `basename` that isn't mentioned anywhere in `stmts`
+fn unused_ident(basename: &str, stmts: &[Stmt]) -> String {
+    let mut idents = HashSet::new();
+    for stmt in stmts {
+        collect_idents(stmt.to_token_stream(), &mut idents);
+    }
+
+    let mut name = basename.to_string();
+    for i in 0.. {
+        if !idents.contains(&name) {
+            break;
+        }
+        name = format!("{}_{}", basename, i);
+    }
+    name
+}
+
+fn collect_idents(tokens: TokenStream, idents: &mut HashSet<String>) {
+    for tt in tokens {
+        match tt {
+            TokenTree::Ident(ident) => {
+                idents.insert(ident.to_string());
+            }
+            TokenTree::Group(group) => collect_idents(group.stream(), idents),
+            _ => {}
+        }
+    }
+}
+
+/// Determines the size of a statement, the identifiers it uses, and whether it can be moved into
+/// a helper function at all
+struct StmtInfo {
+    size: usize,
+    uses: HashSet<String>,
+    opaque: HashSet<String>,
+    outlinable: bool,
+}
+
+impl Traversal for StmtInfo {
+    fn traverse_stmt(&mut self, s: Stmt) -> Stmt {
+        self.size += 1;
+        traverse_stmt_def(self, s)
+    }
+
+    fn traverse_expr(&mut self, e: Expr) -> Expr {
+        match e {
+            Expr::Return(..) | Expr::Try(..) | Expr::Yield(..) => self.outlinable = false,
+            Expr::Path(ExprPath {
+                qself: None,
+                ref path,
+                ..
+            }) => {
+                if let Some(ident) = path.get_ident() {
+                    self.uses.insert(ident.to_string());
+                }
+            }
+            Expr::Macro(ref e) => collect_idents(e.mac.tokens.clone(), &mut self.opaque),
+            Expr::Closure(ref e) => {
+                for input in &e.inputs {
+                    collect_idents(input.to_token_stream(), &mut self.opaque);
+                }
+            }
+            Expr::Let(ref e) => collect_idents(e.pat.to_token_stream(), &mut self.opaque),
+            Expr::ForLoop(ref e) => collect_idents(e.pat.to_token_stream(), &mut self.opaque),
+            _ => {}
+        }
+        traverse_expr_def(self, e)
+    }
+
+    fn traverse_local(&mut self, l: Local) -> Local {
+        collect_idents(l.pat.to_token_stream(), &mut self.opaque);
+        traverse_local_def(self, l)
+    }
+
+    fn traverse_arm(&mut self, a: Arm) -> Arm {
+        collect_idents(a.pat.to_token_stream(), &mut self.opaque);
+        traverse_arm_def(self, a)
+    }
+}
+
+/// Whether uninitialized locals of type `ty` can be zero-initialized with an integer literal
+fn is_int_ty(ty: &Type) -> bool {
+    let name = match ty {
+        Type::Path(TypePath { qself: None, path }) => match path.segments.last() {
+            Some(seg) => seg.ident.to_string(),
+            None => return false,
+        },
+        _ => return false,
+    };
+    let name = name.strip_prefix("c_").unwrap_or(&name);
+    matches!(
+        name,
+        "i8" | "i16"
+            | "i32"
+            | "i64"
+            | "i128"
+            | "isize"
+            | "u8"
+            | "u16"
+            | "u32"
+            | "u64"
+            | "u128"
+            | "usize"
+            | "char"
+            | "schar"
+            | "uchar"
+            | "short"
+            | "ushort"
+            | "int"
+            | "uint"
+            | "long"
+            | "ulong"
+            | "longlong"
+            | "ulonglong"
+            | "size_t"
+            | "ssize_t"
+    )
+}
+
+/// Replaces uses of captured locals with accesses through the context struct
+struct CaptureRewriter<'a> {
+    captured: HashSet<&'a str>,
+    ctx: &'a str,
+}
+
+impl<'a> Traversal for CaptureRewriter<'a> {
+    fn traverse_expr(&mut self, e: Expr) -> Expr {
+        if let Expr::Path(ExprPath {
+            qself: None,
+            ref path,
+            ..
+        }) = e
+        {
+            if let Some(ident) = path.get_ident() {
+                if self.captured.contains(&*ident.to_string()) {
+                    let field = mk().field_expr(mk().ident_expr(self.ctx), ident.clone());
+                    return *mk()
+                        .paren_expr(mk().unary_expr(UnOp::Deref(Default::default()), field));
+                }
+            }
+        }
+        traverse_expr_def(self, e)
+    }
+
+    fn traverse_field(&mut self, mut f: FieldValue) -> FieldValue {
+        // Shorthand fields like `S { x }` need to spell out the rewritten value
+        if f.colon_token.is_none() {
+            f.colon_token = Some(Default::default());
+        }
+        traverse_field_def(self, f)
+    }
+}
@@ -0,0 +1,275 @@
+//! This module implements `--owned-alloca-strings`, which translates the glibc `strdupa` and
+//! `strndupa` macros into owned byte buffers instead of an `alloca`'d vector filled by
+//! `memcpy`:
+//!
+//! ```c
+//! char *path = strdupa(name);
+//! char *prefix = strndupa(name, 3);
+//! ```
+//!
+//! becomes
+//!
+//! ```ignore
+//! let mut dup = ::std::ffi::CStr::from_ptr(name).to_owned().into_bytes_with_nul();
+//! let mut path: *mut libc::c_char = dup.as_mut_ptr() as *mut libc::c_char;
+//! let dup_src = name;
+//! let mut dup_0: Vec<u8> = (0..3 as usize)
+//!     .map(|i| *dup_src.add(i) as u8)
+//!     .take_while(|c| *c != 0)
+//!     .chain(Some(0))
+//!     .collect();
+//! let mut prefix: *mut libc::c_char = dup_0.as_mut_ptr() as *mut libc::c_char;
+//! ```
+//!
+//! Like the `alloca` memory, the buffers live until the end of the enclosing block, and can be
+//! turned into `CString`s or `String`s once the pointers into them are lifted. The macros are
+//! recognized by the statement expressions they expand to; other uses of `alloca` are left
+//! alone. `strdupa` copies are made with `CString`, which needs `std`, so translated `no_std`
+//! crates keep the `alloca` translation.
+
+use super::*;
+
+/// A `strdupa` or `strndupa` expansion
+enum AllocaDup {
+    /// `strdupa(src)`
+    Dup { src: CExprId },
+    /// `strndupa(src, max)`
+    NDup { src: CExprId, max: CExprId },
+}
+
+impl<'c> Translation<'c> {
+    /// The `strdupa` or `strndupa` call the statement expression `compound_stmt_id` expands
+    /// from:
+    ///
+    /// ```c
+    /// ({ const char *__old = (src); size_t __len = strlen(__old) + 1;
+    ///    char *__new = (char *) __builtin_alloca(__len);
+    ///    (char *) memcpy(__new, __old, __len); })
+    /// ({ const char *__old = (src); size_t __len = strnlen(__old, (max));
+    ///    char *__new = (char *) __builtin_alloca(__len + 1);
+    ///    __new[__len] = '\0'; (char *) memcpy(__new, __old, __len); })
+    /// ```
+    fn alloca_dup(&self, compound_stmt_id: CStmtId) -> Option<AllocaDup> {
+        let stmts = match self.ast_context[compound_stmt_id].kind {
+            CStmtKind::Compound(ref stmts) => stmts,
+            _ => return None,
+        };
+        let decl_init = |stmt_id: CStmtId| match self.ast_context[stmt_id].kind {
+            CStmtKind::Decls(ref decls) if decls.len() == 1 => {
+                match self.ast_context[decls[0]].kind {
+                    CDeclKind::Variable {
+                        initializer: Some(init),
+                        ..
+                    } => Some((decls[0], init)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        let (old, src) = decl_init(*stmts.first()?)?;
+        let (len, len_init) = decl_init(*stmts.get(1)?)?;
+        let (new, new_init) = decl_init(*stmts.get(2)?)?;
+
+        match *self.ast_context.resolve_expr(new_init).1 {
+            CExprKind::Call(_, func, _)
+                if self.called_fn_name(func) == Some("__builtin_alloca") => {}
+            _ => return None,
+        }
+        let result = match self.ast_context[*stmts.last()?].kind {
+            CStmtKind::Expr(expr_id) => expr_id,
+            _ => return None,
+        };
+        match *self.ast_context.resolve_expr(result).1 {
+            CExprKind::Call(_, func, ref args)
+                if matches!(
+                    self.called_fn_name(func),
+                    Some("memcpy" | "__builtin_memcpy")
+                ) =>
+            {
+                let refs = args
+                    .iter()
+                    .map(|&arg| self.local_ref(arg))
+                    .collect::<Vec<_>>();
+                if refs != [Some(new), Some(old), Some(len)] {
+                    return None;
+                }
+            }
+            _ => return None,
+        }
+
+        let is_old = |expr_id: CExprId| self.local_ref(expr_id) == Some(old);
+        match (stmts.len(), self.ast_context.resolve_expr(len_init).1) {
+            (4, &CExprKind::Binary(_, c_ast::BinOp::Add, lhs, rhs, _, _)) => {
+                let is_strlen = match *self.ast_context.resolve_expr(lhs).1 {
+                    CExprKind::Call(_, func, ref args) => {
+                        matches!(
+                            self.called_fn_name(func),
+                            Some("strlen" | "__builtin_strlen")
+                        ) && matches!(args[..], [arg] if is_old(arg))
+                    }
+                    _ => false,
+                };
+                match *self.ast_context.resolve_expr(rhs).1 {
+                    CExprKind::Literal(_, CLiteral::Integer(1, _)) if is_strlen => {
+                        Some(AllocaDup::Dup { src })
+                    }
+                    _ => None,
+                }
+            }
+            (5, &CExprKind::Call(_, func, ref args))
+                if matches!(self.called_fn_name(func), Some("strnlen")) =>
+            {
+                match args[..] {
+                    [arg, max] if is_old(arg) => Some(AllocaDup::NDup { src, max }),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// The name of the function or builtin `func` calls directly
+    fn called_fn_name(&self, func: CExprId) -> Option<&str> {
+        let callee = match self.ast_context[func].kind {
+            CExprKind::ImplicitCast(
+                _,
+                callee,
+                CastKind::FunctionToPointerDecay | CastKind::BuiltinFnToFnPtr,
+                _,
+                _,
+            ) => callee,
+            _ => return None,
+        };
+        match self.ast_context[callee].kind {
+            CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[decl_id].kind {
+                CDeclKind::Function { ref name, .. } => Some(name.as_str()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Translate the statement expression `compound_stmt_id` of type `ty` into an owned buffer,
+    /// if `--owned-alloca-strings` is set and it is a `strdupa` or `strndupa` expansion.
+    pub fn convert_owned_alloca_string(
+        &self,
+        ctx: ExprContext,
+        ty: CQualTypeId,
+        compound_stmt_id: CStmtId,
+    ) -> Result<Option<WithStmts<Box<Expr>>>, TranslationError> {
+        if !self.tcfg.owned_alloca_strings || self.tcfg.emit_no_std || ctx.is_const {
+            return Ok(None);
+        }
+        let dup = match self.alloca_dup(compound_stmt_id) {
+            Some(dup) => dup,
+            None => return Ok(None),
+        };
+
+        let buf_name = self.renamer.borrow_mut().pick_name("dup");
+        let val = match dup {
+            AllocaDup::Dup { src } => {
+                let src = self.convert_expr(ctx.used(), src)?;
+                src.map(|src| {
+                    let from_ptr = mk().abs_path_expr(vec!["std", "ffi", "CStr", "from_ptr"]);
+                    let no_args = Vec::<Box<Expr>>::new();
+                    let owned = mk().method_call_expr(
+                        mk().call_expr(from_ptr, vec![src]),
+                        "to_owned",
+                        no_args.clone(),
+                    );
+                    let bytes = mk().method_call_expr(owned, "into_bytes_with_nul", no_args);
+                    mk().local_stmt(Box::new(mk().local(
+                        mk().mutbl().ident_pat(&buf_name),
+                        None as Option<Box<Type>>,
+                        Some(bytes),
+                    )))
+                })
+            }
+            AllocaDup::NDup { src, max } => {
+                let src = self.convert_expr(ctx.used(), src)?;
+                let max = self.convert_expr(ctx.used(), max)?;
+                let src_name = self.renamer.borrow_mut().pick_name("dup_src");
+                src.and_then(|src| -> Result<_, TranslationError> {
+                    let src_decl = mk().local_stmt(Box::new(mk().local(
+                        mk().ident_pat(&src_name),
+                        None as Option<Box<Type>>,
+                        Some(src),
+                    )));
+                    let mut val = max.map(|max| {
+                        let range = Box::new(Expr::Range(ExprRange {
+                            attrs: vec![],
+                            from: Some(mk().lit_expr(mk().int_unsuffixed_lit(0))),
+                            limits: RangeLimits::HalfOpen(Default::default()),
+                            to: Some(mk().cast_expr(max, mk().path_ty(vec!["usize"]))),
+                        }));
+                        let closure = |arg: &str, body: Box<Expr>| {
+                            let decl = mk().fn_decl(
+                                "",
+                                vec![mk().arg(mk().infer_ty(), mk().ident_pat(arg))],
+                                None,
+                                ReturnType::Default,
+                            );
+                            mk().closure_expr(CaptureBy::Ref, Movability::Movable, decl, body)
+                        };
+                        let byte = mk().cast_expr(
+                            mk().unary_expr(
+                                UnOp::Deref(Default::default()),
+                                mk().method_call_expr(
+                                    mk().ident_expr(&src_name),
+                                    "add",
+                                    vec![mk().ident_expr("i")],
+                                ),
+                            ),
+                            mk().path_ty(vec!["u8"]),
+                        );
+                        let not_nul = mk().binary_expr(
+                            BinOp::Ne(Default::default()),
+                            mk().unary_expr(UnOp::Deref(Default::default()), mk().ident_expr("c")),
+                            mk().lit_expr(mk().int_unsuffixed_lit(0)),
+                        );
+                        let nul = mk().call_expr(
+                            mk().ident_expr("Some"),
+                            vec![mk().lit_expr(mk().int_unsuffixed_lit(0))],
+                        );
+                        let iter = mk().method_call_expr(
+                            mk().paren_expr(range),
+                            "map",
+                            vec![closure("i", byte)],
+                        );
+                        let iter =
+                            mk().method_call_expr(iter, "take_while", vec![closure("c", not_nul)]);
+                        let iter = mk().method_call_expr(iter, "chain", vec![nul]);
+                        let bytes = mk().method_call_expr(iter, "collect", Vec::<Box<Expr>>::new());
+                        let vec_ty = mk().path_ty(vec![mk().path_segment_with_args(
+                            "Vec",
+                            mk().angle_bracketed_args(vec![mk().path_ty(vec!["u8"])]),
+                        )]);
+                        mk().local_stmt(Box::new(mk().local(
+                            mk().mutbl().ident_pat(&buf_name),
+                            Some(vec_ty),
+                            Some(bytes),
+                        )))
+                    });
+                    val.prepend_stmts(vec![src_decl]);
+                    Ok(val)
+                })?
+            }
+        };
+
+        // The buffer is declared in the enclosing block, so that it lives as long as the
+        // `alloca` memory would
+        let ptr = mk().cast_expr(
+            mk().method_call_expr(
+                mk().ident_expr(&buf_name),
+                "as_mut_ptr",
+                Vec::<Box<Expr>>::new(),
+            ),
+            self.convert_type(ty.ctype)?,
+        );
+        let (mut stmts, buf_decl) = val.discard_unsafe();
+        stmts.push(buf_decl);
+        let mut val = WithStmts::new(stmts, ptr);
+        val.set_unsafe();
+        Ok(Some(val))
+    }
+}
@@ -0,0 +1,509 @@
+//! This module translates calls to the `printf` family with literal format strings into Rust
+//! formatting macros (`--translate-fmt`):
+//!
+//! ```c
+//! printf("%d items\n", n);                   // print!("{} items\n", n as libc::c_int);
+//! fprintf(stderr, "error: %s\n", msg);       // eprint!("error: {}\n", CStr::from_ptr(msg)...);
+//! snprintf(buf, sizeof buf, "%05.1f", x);    // formats into a `String` and copies it to `buf`
+//! ```
+//!
+//! Only the conversions that Rust formats exactly like C are translated: `%d`, `%i`, `%u`, `%x`,
+//! `%X` and `%o` with any length modifier, `%f`, `%s` without a precision, `%c` with a character
+//! literal, and `%%`, with the `-`, `+` and `0` flags and a literal width. Calls with any other
+//! conversion, or whose result is used (except for `snprintf`), keep calling the libc function.
+//!
+//! Strings are converted lossily to UTF-8 and non-finite floats are spelled the Rust way (`NaN`
+//! rather than `nan`). Rust's standard output is also buffered separately from C's `stdout`, so
+//! output written by calls that are left untranslated may be reordered relative to it.
+
+use super::*;
+
+/// A piece of a parsed format string
+enum FmtPiece {
+    Text(String),
+    Conversion(Conversion),
+}
+
+/// A `%` conversion specification
+struct Conversion {
+    left_align: bool,
+    plus_sign: bool,
+    zero_pad: bool,
+    width: Option<usize>,
+    precision: Option<usize>,
+    length: &'static str,
+    specifier: char,
+}
+
+/// How an argument is passed to the Rust formatting macro
+enum FmtArg {
+    /// An integer, cast to the libc type with this name
+    Int(&'static str),
+    /// A `double`, cast to `f64`
+    Float,
+    /// A C string
+    Str,
+}
+
+impl<'c> Translation<'c> {
+    /// Try to translate a call to `printf`, `fprintf` on `stdout` or `stderr`, or `snprintf`.
+    /// Returns `None` if `callee` is not a direct reference to one of them, or if the format
+    /// can't be translated exactly.
+    pub fn convert_printf_call(
+        &self,
+        ctx: ExprContext,
+        callee: CExprId,
+        args: &[CExprId],
+    ) -> Result<Option<WithStmts<Box<Expr>>>, TranslationError> {
+        // The formatting macros need `std`
+        if self.tcfg.emit_no_std {
+            return Ok(None);
+        }
+        let func = match self.ast_context[callee].kind {
+            CExprKind::ImplicitCast(_, func, CastKind::FunctionToPointerDecay, _, _) => func,
+            _ => return Ok(None),
+        };
+        let name = match self.ast_context[func].kind {
+            CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[decl_id].kind {
+                CDeclKind::Function {
+                    ref name,
+                    body: None,
+                    ..
+                } => name.as_str(),
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+
+        match (name, args) {
+            ("printf", [fmt, fmt_args @ ..]) if ctx.is_unused() => {
+                self.convert_print(ctx, "print", *fmt, fmt_args)
+            }
+            ("fprintf", [stream, fmt, fmt_args @ ..]) if ctx.is_unused() => {
+                let macro_name = match self.std_stream_name(*stream) {
+                    Some("stdout") | Some("__stdoutp") => "print",
+                    Some("stderr") | Some("__stderrp") => "eprint",
+                    _ => return Ok(None),
+                };
+                self.convert_print(ctx, macro_name, *fmt, fmt_args)
+            }
+            ("snprintf", [buf, size, fmt, fmt_args @ ..]) => {
+                self.convert_snprintf(ctx, *buf, *size, *fmt, fmt_args)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Translate a print to `stdout` or `stderr` into `print!` or `eprint!`
+    fn convert_print(
+        &self,
+        ctx: ExprContext,
+        macro_name: &str,
+        fmt: CExprId,
+        fmt_args: &[CExprId],
+    ) -> Result<Option<WithStmts<Box<Expr>>>, TranslationError> {
+        let tokens = match self.convert_format(ctx, fmt, fmt_args)? {
+            Some(tokens) => tokens,
+            None => return Ok(None),
+        };
+        Ok(Some(tokens.and_then(|tokens| {
+            let print = mk().mac_expr(mk().mac(
+                vec![macro_name],
+                tokens,
+                MacroDelimiter::Paren(Default::default()),
+            ));
+            Ok::<_, TranslationError>(WithStmts::new(
+                vec![mk().semi_stmt(print)],
+                self.panic_or_err("printf result is not supposed to be used"),
+            ))
+        })?))
+    }
+
+    /// Translate `snprintf(buf, size, fmt, ...)` into a block that formats into a `String` and
+    /// copies as much of it as fits into `buf`, evaluating to the length of the whole output:
+    ///
+    /// ```ignore
+    /// {
+    ///     let fmt = format!(...);
+    ///     let size = size as usize;
+    ///     if size > 0 {
+    ///         let buf = buf as *mut u8;
+    ///         let len = fmt.len().min(size - 1);
+    ///         ::core::ptr::copy_nonoverlapping(fmt.as_ptr(), buf, len);
+    ///         *buf.add(len) = 0;
+    ///     }
+    ///     fmt.len() as libc::c_int
+    /// }
+    /// ```
+    fn convert_snprintf(
+        &self,
+        ctx: ExprContext,
+        buf: CExprId,
+        size: CExprId,
+        fmt: CExprId,
+        fmt_args: &[CExprId],
+    ) -> Result<Option<WithStmts<Box<Expr>>>, TranslationError> {
+        let tokens = match self.convert_format(ctx, fmt, fmt_args)? {
+            Some(tokens) => tokens,
+            None => return Ok(None),
+        };
+        let buf = self.convert_expr(ctx.used(), buf)?;
+        let size = self.convert_expr(ctx.used(), size)?;
+
+        let mut snprintf = tokens.and_then(|tokens| {
+            size.and_then(|size| {
+                buf.and_then(|buf| {
+                    Ok::<_, TranslationError>(WithStmts::new_val(
+                        self.snprintf_block(tokens, buf, size),
+                    ))
+                })
+            })
+        })?;
+        snprintf.set_unsafe();
+        Ok(Some(snprintf))
+    }
+
+    fn snprintf_block(&self, tokens: TokenStream, buf: Box<Expr>, size: Box<Expr>) -> Box<Expr> {
+        // The names are picked after the arguments are translated, so that they don't shadow
+        // any of the variables the arguments use
+        let fmt_name = &self.renamer.borrow_mut().pick_name("fmt");
+        let size_name = &self.renamer.borrow_mut().pick_name("size");
+        let buf_name = &self.renamer.borrow_mut().pick_name("buf");
+        let len_name = &self.renamer.borrow_mut().pick_name("len");
+
+        let local = |name: &str, init: Box<Expr>| {
+            mk().local_stmt(Box::new(mk().local(
+                mk().ident_pat(name),
+                None as Option<Box<Type>>,
+                Some(init),
+            )))
+        };
+        let no_args = Vec::<Box<Expr>>::new;
+        let fmt_len = || mk().method_call_expr(mk().ident_expr(fmt_name), "len", no_args());
+
+        let format = mk().mac_expr(mk().mac(
+            vec!["format"],
+            tokens,
+            MacroDelimiter::Paren(Default::default()),
+        ));
+        let size = mk().cast_expr(size, mk().path_ty(vec!["usize"]));
+        let buf = mk().cast_expr(buf, mk().mutbl().ptr_ty(mk().path_ty(vec!["u8"])));
+        let last = mk().binary_expr(
+            BinOp::Sub(Default::default()),
+            mk().ident_expr(size_name),
+            mk().lit_expr(mk().int_unsuffixed_lit(1)),
+        );
+        let copy = mk().call_expr(
+            mk().abs_path_expr(vec!["core", "ptr", "copy_nonoverlapping"]),
+            vec![
+                mk().method_call_expr(mk().ident_expr(fmt_name), "as_ptr", no_args()),
+                mk().ident_expr(buf_name),
+                mk().ident_expr(len_name),
+            ],
+        );
+        let terminate = mk().assign_expr(
+            mk().unary_expr(
+                UnOp::Deref(Default::default()),
+                mk().method_call_expr(
+                    mk().ident_expr(buf_name),
+                    "add",
+                    vec![mk().ident_expr(len_name)],
+                ),
+            ),
+            mk().lit_expr(mk().int_unsuffixed_lit(0)),
+        );
+        let non_empty = mk().binary_expr(
+            BinOp::Gt(Default::default()),
+            mk().ident_expr(size_name),
+            mk().lit_expr(mk().int_unsuffixed_lit(0)),
+        );
+        let fill = mk().ifte_expr(
+            non_empty,
+            mk().block(vec![
+                local(buf_name, buf),
+                local(
+                    len_name,
+                    mk().method_call_expr(fmt_len(), "min", vec![last]),
+                ),
+                mk().semi_stmt(copy),
+                mk().semi_stmt(terminate),
+            ]),
+            None as Option<Box<Expr>>,
+        );
+
+        mk().block_expr(mk().block(vec![
+            local(fmt_name, format),
+            local(size_name, size),
+            mk().expr_stmt(fill),
+            mk().expr_stmt(mk().cast_expr(fmt_len(), self.libc_ty("c_int"))),
+        ]))
+    }
+
+    /// The name of the variable `stream` refers to, for recognizing `stdout` and `stderr`
+    fn std_stream_name(&self, stream: CExprId) -> Option<&str> {
+        match *self.ast_context.resolve_expr(stream).1 {
+            CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[decl_id].kind {
+                CDeclKind::Variable { ref ident, .. } => Some(ident.as_str()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Translate a literal format string and its arguments into the tokens of a Rust formatting
+    /// macro invocation, if every conversion can be translated exactly
+    fn convert_format(
+        &self,
+        ctx: ExprContext,
+        fmt: CExprId,
+        fmt_args: &[CExprId],
+    ) -> Result<Option<WithStmts<TokenStream>>, TranslationError> {
+        use syn::__private::ToTokens;
+
+        let bytes = match *self.ast_context.resolve_expr(fmt).1 {
+            CExprKind::Literal(_, CLiteral::String(ref bytes, 1)) => bytes,
+            _ => return Ok(None),
+        };
+        // C stops at the first NUL
+        let bytes = bytes.split(|&b| b == 0).next().unwrap_or_default();
+        let pieces = match std::str::from_utf8(bytes).ok().and_then(parse_format) {
+            Some(pieces) => pieces,
+            None => return Ok(None),
+        };
+
+        let mut rust_fmt = String::new();
+        let mut args = vec![];
+        let mut fmt_args = fmt_args.iter();
+        for piece in &pieces {
+            let conv = match piece {
+                FmtPiece::Text(text) => {
+                    rust_fmt.push_str(&text.replace('{', "{{").replace('}', "}}"));
+                    continue;
+                }
+                FmtPiece::Conversion(conv) => conv,
+            };
+            let arg = match fmt_args.next() {
+                Some(&arg) => arg,
+                None => return Ok(None),
+            };
+
+            // `%c` is only translated for character literals, which are inlined into the format
+            if conv.specifier == 'c' {
+                match self.ascii_char_literal(arg) {
+                    Some(c) if conv.width.is_none() => {
+                        rust_fmt.push_str(&c.to_string().replace('{', "{{").replace('}', "}}"));
+                        continue;
+                    }
+                    _ => return Ok(None),
+                }
+            }
+
+            let (spec, fmt_arg) = match rust_conversion(conv) {
+                Some(conversion) => conversion,
+                None => return Ok(None),
+            };
+            let arg_ty = match self.ast_context[arg].kind.get_type() {
+                Some(ty) => self.ast_context.resolve_type(ty).kind.clone(),
+                None => return Ok(None),
+            };
+            let compatible = match fmt_arg {
+                FmtArg::Int(_) => arg_ty.is_integral_type() || arg_ty.is_enum(),
+                FmtArg::Float => matches!(arg_ty, CTypeKind::Float | CTypeKind::Double),
+                FmtArg::Str => matches!(arg_ty, CTypeKind::Pointer(_)),
+            };
+            if !compatible {
+                return Ok(None);
+            }
+
+            rust_fmt.push_str(&spec);
+            args.push((arg, fmt_arg));
+        }
+        // Extra arguments are evaluated but unused by C, which is easier to leave to libc
+        if fmt_args.next().is_some() {
+            return Ok(None);
+        }
+
+        let reads_cstr = args
+            .iter()
+            .any(|(_, fmt_arg)| matches!(fmt_arg, FmtArg::Str));
+        let mut args = args
+            .into_iter()
+            .map(|(arg, fmt_arg)| {
+                let arg = self.convert_expr(ctx.used(), arg)?;
+                Ok(arg.map(|arg| match fmt_arg {
+                    FmtArg::Int(ty) => mk().cast_expr(arg, self.libc_ty(ty)),
+                    FmtArg::Float => mk().cast_expr(arg, mk().path_ty(vec!["f64"])),
+                    FmtArg::Str => {
+                        let ptr = mk().cast_expr(arg, mk().ptr_ty(self.libc_ty("c_char")));
+                        let cstr = mk().call_expr(
+                            mk().abs_path_expr(vec!["std", "ffi", "CStr", "from_ptr"]),
+                            vec![ptr],
+                        );
+                        mk().method_call_expr(cstr, "to_string_lossy", Vec::<Box<Expr>>::new())
+                    }
+                }))
+            })
+            .collect::<Result<WithStmts<Vec<_>>, TranslationError>>()?;
+        if reads_cstr {
+            args.set_unsafe();
+        }
+
+        Ok(Some(args.map(|args| {
+            let mut tokens = vec![TokenTree::Literal(proc_macro2::Literal::string(&rust_fmt))];
+            for arg in args {
+                tokens.push(TokenTree::Punct(Punct::new(',', Alone)));
+                tokens.extend(arg.to_token_stream());
+            }
+            tokens.into_iter().collect::<TokenStream>()
+        })))
+    }
+
+    /// The character of an ASCII character literal, looking through casts
+    fn ascii_char_literal(&self, expr_id: CExprId) -> Option<char> {
+        match *self.ast_context.resolve_expr(expr_id).1 {
+            CExprKind::Literal(_, CLiteral::Character(c)) if c < 0x80 => Some(char::from(c as u8)),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a C format string into text and conversions. Returns `None` for malformed or
+/// unsupported syntax, such as `*` widths and precisions.
+fn parse_format(fmt: &str) -> Option<Vec<FmtPiece>> {
+    let mut pieces = vec![];
+    let mut text = String::new();
+    let mut chars = fmt.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            text.push('%');
+            continue;
+        }
+
+        let mut conv = Conversion {
+            left_align: false,
+            plus_sign: false,
+            zero_pad: false,
+            width: None,
+            precision: None,
+            length: "",
+            specifier: '\0',
+        };
+        while let Some(&flag) = chars.peek() {
+            match flag {
+                '-' => conv.left_align = true,
+                '+' => conv.plus_sign = true,
+                '0' => conv.zero_pad = true,
+                // The space and `#` flags have no exact Rust equivalent
+                ' ' | '#' => return None,
+                _ => break,
+            }
+            chars.next();
+        }
+        let number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+            let mut digits = String::new();
+            while let Some(&d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                digits.push(d);
+                chars.next();
+            }
+            digits.parse::<usize>().ok()
+        };
+        conv.width = number(&mut chars);
+        if chars.peek() == Some(&'.') {
+            chars.next();
+            conv.precision = Some(number(&mut chars).unwrap_or(0));
+        }
+        for &length in &["hh", "h", "ll", "l", "j", "z", "t", "L"] {
+            if length.chars().zip(chars.clone()).all(|(l, c)| l == c) {
+                conv.length = length;
+                for _ in 0..length.len() {
+                    chars.next();
+                }
+                break;
+            }
+        }
+        conv.specifier = chars.next()?;
+
+        if !text.is_empty() {
+            pieces.push(FmtPiece::Text(std::mem::take(&mut text)));
+        }
+        pieces.push(FmtPiece::Conversion(conv));
+    }
+    if !text.is_empty() {
+        pieces.push(FmtPiece::Text(text));
+    }
+    Some(pieces)
+}
+
+/// The Rust format specification for a C conversion and how its argument is passed, if Rust
+/// formats it the same way
+fn rust_conversion(conv: &Conversion) -> Option<(String, FmtArg)> {
+    let signed = |length: &str| {
+        Some(FmtArg::Int(match length {
+            "hh" => "c_schar",
+            "h" => "c_short",
+            "" => "c_int",
+            "l" => "c_long",
+            "ll" => "c_longlong",
+            "j" => "intmax_t",
+            "z" => "ssize_t",
+            "t" => "ptrdiff_t",
+            _ => return None,
+        }))
+    };
+    let unsigned = |length: &str| {
+        Some(FmtArg::Int(match length {
+            "hh" => "c_uchar",
+            "h" => "c_ushort",
+            "" => "c_uint",
+            "l" => "c_ulong",
+            "ll" => "c_ulonglong",
+            "j" => "uintmax_t",
+            "z" | "t" => "size_t",
+            _ => return None,
+        }))
+    };
+    let (fmt_arg, ty, is_signed) = match conv.specifier {
+        // The precision of integers is their minimum number of digits, which Rust doesn't have
+        'd' | 'i' if conv.precision.is_none() => (signed(conv.length)?, "", true),
+        'u' if conv.precision.is_none() => (unsigned(conv.length)?, "", false),
+        'x' if conv.precision.is_none() => (unsigned(conv.length)?, "x", false),
+        'X' if conv.precision.is_none() => (unsigned(conv.length)?, "X", false),
+        'o' if conv.precision.is_none() => (unsigned(conv.length)?, "o", false),
+        'f' if conv.length.is_empty() || conv.length == "l" => (FmtArg::Float, "", true),
+        // The precision of strings limits how many bytes are read, which may not be terminated
+        's' if conv.length.is_empty() && conv.precision.is_none() => (FmtArg::Str, "", false),
+        _ => return None,
+    };
+
+    let mut spec = String::new();
+    if conv.left_align {
+        spec.push('<');
+    }
+    // C ignores `+` for unsigned conversions
+    if conv.plus_sign && is_signed {
+        spec.push('+');
+    }
+    // and `0` for left-aligned ones
+    if conv.zero_pad && !conv.left_align && !matches!(fmt_arg, FmtArg::Str) {
+        spec.push('0');
+    }
+    if let Some(width) = conv.width {
+        spec.push_str(&width.to_string());
+    }
+    // C prints 6 decimals by default
+    if let FmtArg::Float = fmt_arg {
+        spec.push_str(&format!(".{}", conv.precision.unwrap_or(6)));
+    }
+    spec.push_str(ty);
+
+    if spec.is_empty() {
+        Some(("{}".to_owned(), fmt_arg))
+    } else {
+        Some((format!("{{:{}}}", spec), fmt_arg))
+    }
+}
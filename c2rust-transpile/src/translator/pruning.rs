@@ -0,0 +1,98 @@
+//! This module reports the declarations left out of the translation with `--prune-unused`.
+//!
+//! Headers declare far more than any one translation unit uses, and by default everything an
+//! externally visible definition refers to is kept. With `--prune-unused`, a program defining
+//! `main` keeps only its own externally visible definitions and what they and its entry point
+//! reach, dropping the unused definitions of the headers it includes, and the top-level
+//! declarations that were dropped are listed in `<module>.pruned.txt` next to the translated
+//! module.
+
+use super::*;
+
+impl<'c> Translation<'c> {
+    /// The name and contents of the report on the pruned top-level declarations, `pruned`.
+    pub fn pruned_report(&self, pruned: &[(CDeclId, CDecl)]) -> Option<(String, String)> {
+        if !self.tcfg.prune_unused {
+            return None;
+        }
+
+        let mut lines = vec![];
+        for (_, decl) in pruned {
+            let kind = match decl.kind {
+                CDeclKind::Function { .. } => "function",
+                CDeclKind::Variable { .. } => "variable",
+                CDeclKind::Typedef { .. } => "typedef",
+                CDeclKind::Struct { .. } => "struct",
+                CDeclKind::Union { .. } => "union",
+                CDeclKind::Enum { .. } => "enum",
+                CDeclKind::MacroObject { .. } => "macro",
+                _ => continue,
+            };
+            let name = match decl.kind.get_name() {
+                Some(name) => name,
+                None => continue,
+            };
+            let mut line = format!("{} {}", kind, name);
+            if let Some(loc) = self.ast_context.display_loc(&decl.loc) {
+                line += &format!(" ({})", loc);
+            }
+            lines.push(line);
+        }
+        info!(
+            "Pruned {} unreachable declarations from {}",
+            lines.len(),
+            self.main_module_name
+        );
+
+        let file_name = format!("{}.pruned.txt", self.main_module_name);
+        let mut contents = lines.join("\n");
+        contents.push('\n');
+        Some((file_name, contents))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{Transpiler, TranspilerConfig};
+
+    #[test]
+    fn prune_unused_declarations() {
+        let dir = tempfile::tempdir().unwrap();
+        let header = "int used(int x) { return x + 1; }\nint unused(int x) { return x - 1; }\n";
+        fs::write(dir.path().join("util.h"), header).unwrap();
+        let source = "\\
+#include \"util.h\"
+static int unused_static(void) { return 0; }
+int exported(int x) { return x * 2; }
+int main(void) { return used(0); }
+";
+        let path = dir.path().join("prog.c");
+        fs::write(&path, source).unwrap();
+
+        let config = TranspilerConfig {
+            prune_unused: true,
+            ..TranspilerConfig::default()
+        };
+        let translated = Transpiler::new(config).transpile_file(&path).unwrap();
+        // Externally visible definitions of the program are kept even if `main` doesn't use them
+        assert!(translated.source.contains("fn exported("));
+        assert!(translated.source.contains("fn used("));
+        assert!(!translated.source.contains("fn unused("));
+        assert!(!translated.source.contains("fn unused_static("));
+
+        let (_, report) = translated
+            .extra_files
+            .iter()
+            .find(|(name, _)| name == "prog.pruned.txt")
+            .unwrap();
+        // Clang's implicit typedefs are pruned too
+        let pruned = report
+            .lines()
+            .filter(|line| line.starts_with("function "))
+            .map(|line| line.split(" (").next().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(pruned, ["function unused", "function unused_static"]);
+    }
+}
@@ -0,0 +1,350 @@
+//! This module implements `--range-loops`, which translates counted `for` loops into Rust range
+//! loops instead of `while` loops over a mutable counter:
+//!
+//! ```c
+//! for (int i = 0; i < n; i++) sum += xs[i];
+//! for (int i = n - 1; i >= 0; i--) out[n - 1 - i] = xs[i];
+//! for (int i = 0; i <= n; i += 2) evens++;
+//! ```
+//!
+//! becomes
+//!
+//! ```ignore
+//! for i in 0 as libc::c_int..n {
+//!     sum += *xs.offset(i as isize);
+//! }
+//! for i in (0 as libc::c_int..=n - 1 as libc::c_int).rev() {
+//!     *out.offset((n - 1 as libc::c_int - i) as isize) = *xs.offset(i as isize);
+//! }
+//! for i in (0 as libc::c_int..=n).step_by(2) {
+//!     evens += 1;
+//! }
+//! ```
+//!
+//! A loop qualifies if it declares its integer counter, compares it against a bound with `<` or
+//! `<=` (or `>=` when counting a signed counter down), and steps it by a constant. The body may
+//! not modify or take the address of the counter, nor leave the loop early or skip to its next
+//! iteration, since it is translated on its own. The bound is only evaluated once, so it may
+//! only use constants and locals that the body doesn't modify and whose address is never taken.
+
+use super::*;
+
+/// A `for` loop translated into a range loop
+pub struct RangeLoop {
+    var: CDeclId,
+    /// The initial value of the counter
+    start: CExprId,
+    /// The value the counter is compared against
+    bound: CExprId,
+    /// Whether the bound is part of the range
+    inclusive: bool,
+    /// Whether the counter counts down to the bound
+    reverse: bool,
+    step: u64,
+    body: CStmtId,
+}
+
+impl<'c> Translation<'c> {
+    /// Find the loops translated by `--range-loops`, keyed by their `for` statement.
+    pub fn find_range_loops(&self) -> HashMap<CStmtId, RangeLoop> {
+        if !self.tcfg.range_loops && self.candidate_fns.is_empty() {
+            return HashMap::new();
+        }
+
+        // Locals whose address is taken, which may be modified through pointers
+        let mut addressed = HashSet::new();
+        for (_, expr) in self.ast_context.iter_exprs() {
+            if let CExprKind::Unary(_, c_ast::UnOp::AddressOf, arg, _) = expr.kind {
+                addressed.extend(self.local_ref(arg));
+            }
+        }
+
+        self.ast_context
+            .iter_stmts()
+            .filter_map(|(&stmt_id, _)| Some((stmt_id, self.range_loop(stmt_id, &addressed)?)))
+            .collect()
+    }
+
+    fn range_loop(&self, stmt_id: CStmtId, addressed: &HashSet<CDeclId>) -> Option<RangeLoop> {
+        let (init, condition, increment, body) = match self.ast_context[stmt_id].kind {
+            CStmtKind::ForLoop {
+                init: Some(init),
+                condition: Some(condition),
+                increment: Some(increment),
+                body,
+            } => (init, condition, increment, body),
+            _ => return None,
+        };
+
+        // `int i = start`
+        let (var, start, typ) = match self.ast_context[init].kind {
+            CStmtKind::Decls(ref decls) => match decls[..] {
+                [var] => match self.ast_context[var].kind {
+                    CDeclKind::Variable {
+                        has_static_duration: false,
+                        has_thread_duration: false,
+                        initializer: Some(start),
+                        typ,
+                        ..
+                    } if !typ.qualifiers.is_volatile => (var, start, typ.ctype),
+                    _ => return None,
+                },
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let signed = match self.ast_context.resolve_type(typ).kind {
+            ref kind if kind.is_signed_integral_type() => true,
+            ref kind if kind.is_unsigned_integral_type() => false,
+            _ => return None,
+        };
+        if addressed.contains(&var) || self.has_writes(start) {
+            return None;
+        }
+
+        // `i < bound`, `i <= bound` or `i >= bound`, where `i` isn't converted to another type
+        let (reverse, inclusive, lhs, bound) = match self.ast_context[condition].kind {
+            CExprKind::Binary(_, c_ast::BinOp::Less, lhs, bound, _, _) => {
+                (false, false, lhs, bound)
+            }
+            CExprKind::Binary(_, c_ast::BinOp::LessEqual, lhs, bound, _, _) => {
+                (false, true, lhs, bound)
+            }
+            // An unsigned counter is always `>= 0`, so C wraps it around where the range stops
+            CExprKind::Binary(_, c_ast::BinOp::GreaterEqual, lhs, bound, _, _) if signed => {
+                (true, true, lhs, bound)
+            }
+            _ => return None,
+        };
+        match self.ast_context[lhs].kind {
+            CExprKind::ImplicitCast(_, e, CastKind::LValueToRValue, _, _)
+                if self.is_decl_ref(e, var) => {}
+            _ => return None,
+        }
+
+        // `i++`, `++i` or `i += step`, or their decrementing versions
+        let literal_step = |e: CExprId| match *self.ast_context.resolve_expr(e).1 {
+            CExprKind::Literal(_, CLiteral::Integer(step, _)) if step > 0 => Some(step),
+            _ => None,
+        };
+        let step = match self.ast_context[increment].kind {
+            CExprKind::Unary(_, op, e, _) if self.is_decl_ref(e, var) => match (op, reverse) {
+                (c_ast::UnOp::PostIncrement | c_ast::UnOp::PreIncrement, false)
+                | (c_ast::UnOp::PostDecrement | c_ast::UnOp::PreDecrement, true) => 1,
+                _ => return None,
+            },
+            CExprKind::Binary(_, op, lhs, rhs, _, _) if self.is_decl_ref(lhs, var) => {
+                match (op, reverse) {
+                    (c_ast::BinOp::AssignAdd, false) | (c_ast::BinOp::AssignSubtract, true) => {
+                        literal_step(rhs)?
+                    }
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+
+        self.check_parallel_jumps(body, false, false).ok()?;
+        let body_writes = self.written_locals(body);
+        if body_writes.contains(&var)
+            || !self.is_loop_invariant(bound, var, &body_writes, addressed)
+        {
+            return None;
+        }
+
+        Some(RangeLoop {
+            var,
+            start,
+            bound,
+            inclusive,
+            reverse,
+            step,
+            body,
+        })
+    }
+
+    /// Whether `expr_id` assigns to anything
+    fn has_writes(&self, expr_id: CExprId) -> bool {
+        DFExpr::new(&self.ast_context, expr_id.into()).any(|node| match node {
+            SomeId::Expr(e) => match self.ast_context[e].kind {
+                CExprKind::Binary(_, op, _, _, _, _) => {
+                    op == c_ast::BinOp::Assign || op.underlying_assignment().is_some()
+                }
+                CExprKind::Unary(_, op, _, _) => matches!(
+                    op,
+                    c_ast::UnOp::PreIncrement
+                        | c_ast::UnOp::PostIncrement
+                        | c_ast::UnOp::PreDecrement
+                        | c_ast::UnOp::PostDecrement
+                ),
+                _ => false,
+            },
+            _ => false,
+        })
+    }
+
+    /// The variables `stmt_id` assigns to
+    fn written_locals(&self, stmt_id: CStmtId) -> HashSet<CDeclId> {
+        let mut written = HashSet::new();
+        for node in DFExpr::new(&self.ast_context, stmt_id.into()) {
+            let target = match node {
+                SomeId::Expr(e) => match self.ast_context[e].kind {
+                    CExprKind::Binary(_, op, lhs, _, _, _)
+                        if op == c_ast::BinOp::Assign || op.underlying_assignment().is_some() =>
+                    {
+                        lhs
+                    }
+                    CExprKind::Unary(_, op, arg, _)
+                        if matches!(
+                            op,
+                            c_ast::UnOp::PreIncrement
+                                | c_ast::UnOp::PostIncrement
+                                | c_ast::UnOp::PreDecrement
+                                | c_ast::UnOp::PostDecrement
+                                | c_ast::UnOp::AddressOf
+                        ) =>
+                    {
+                        arg
+                    }
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            written.extend(self.local_ref(target));
+        }
+        written
+    }
+
+    /// Whether the bound of a loop over `var` has the same value in every iteration
+    fn is_loop_invariant(
+        &self,
+        bound: CExprId,
+        var: CDeclId,
+        body_writes: &HashSet<CDeclId>,
+        addressed: &HashSet<CDeclId>,
+    ) -> bool {
+        DFExpr::new(&self.ast_context, bound.into()).all(|node| {
+            let expr_id = match node {
+                SomeId::Expr(expr_id) => expr_id,
+                SomeId::Type(_) => return true,
+                _ => return false,
+            };
+            match self.ast_context[expr_id].kind {
+                CExprKind::Literal(..)
+                | CExprKind::UnaryType(..)
+                | CExprKind::ImplicitCast(..)
+                | CExprKind::ExplicitCast(..)
+                | CExprKind::ConstantExpr(..)
+                | CExprKind::Paren(..) => true,
+                CExprKind::Unary(_, op, _, _) => matches!(
+                    op,
+                    c_ast::UnOp::Plus
+                        | c_ast::UnOp::Negate
+                        | c_ast::UnOp::Complement
+                        | c_ast::UnOp::Not
+                ),
+                CExprKind::Binary(_, op, _, _, _, _) => {
+                    op != c_ast::BinOp::Assign
+                        && op != c_ast::BinOp::Comma
+                        && op.underlying_assignment().is_none()
+                }
+                CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[decl_id].kind {
+                    CDeclKind::EnumConstant { .. } => true,
+                    CDeclKind::Variable {
+                        has_static_duration: false,
+                        has_thread_duration: false,
+                        typ,
+                        ..
+                    } => {
+                        decl_id != var
+                            && !typ.qualifiers.is_volatile
+                            && !body_writes.contains(&decl_id)
+                            && !addressed.contains(&decl_id)
+                    }
+                    _ => false,
+                },
+                _ => false,
+            }
+        })
+    }
+
+    /// Whether `stmt_id` is a loop selected by `--range-loops` in the function being translated.
+    pub fn is_range_loop(&self, stmt_id: CStmtId) -> bool {
+        self.range_loops() && self.range_loops.contains_key(&stmt_id)
+    }
+
+    /// Convert a loop selected by `--range-loops` into a range loop.
+    pub fn convert_range_loop(
+        &self,
+        ctx: ExprContext,
+        stmt_id: CStmtId,
+    ) -> Result<Vec<Stmt>, TranslationError> {
+        let range_loop = &self.range_loops[&stmt_id];
+
+        let (mut stmts, start) = self
+            .convert_expr(ctx.used(), range_loop.start)?
+            .discard_unsafe();
+        let (bound_stmts, bound) = self
+            .convert_expr(ctx.used(), range_loop.bound)?
+            .discard_unsafe();
+        stmts.extend(bound_stmts);
+
+        let (var_name, body) = self.with_scope(|| -> Result<_, TranslationError> {
+            let ident = match self.ast_context[range_loop.var].kind {
+                CDeclKind::Variable { ref ident, .. } => ident,
+                _ => return Err(format_err!("Range loop counter is not a variable").into()),
+            };
+            let var_name = self
+                .renamer
+                .borrow_mut()
+                .insert(range_loop.var, ident)
+                .expect(&format!("Failed to insert variable '{}'", ident));
+            let name = format!("<range-for_{:?}>", stmt_id);
+            let body = self.convert_function_body(
+                ctx,
+                &name,
+                &[range_loop.body],
+                cfg::ImplicitReturnType::Void,
+            )?;
+            Ok((var_name, body))
+        })?;
+
+        let (from, to) = if range_loop.reverse {
+            (bound, start)
+        } else {
+            (start, bound)
+        };
+        let limits = if range_loop.inclusive {
+            RangeLimits::Closed(Default::default())
+        } else {
+            RangeLimits::HalfOpen(Default::default())
+        };
+        let mut range = Box::new(Expr::Range(ExprRange {
+            attrs: vec![],
+            from: Some(from),
+            limits,
+            to: Some(to),
+        }));
+        if range_loop.reverse {
+            range = mk().method_call_expr(mk().paren_expr(range), "rev", vec![] as Vec<Box<Expr>>);
+        }
+        if range_loop.step != 1 {
+            let step = mk().lit_expr(mk().int_unsuffixed_lit(range_loop.step as u128));
+            let iter = if range_loop.reverse {
+                range
+            } else {
+                mk().paren_expr(range)
+            };
+            range = mk().method_call_expr(iter, "step_by", vec![step]);
+        }
+
+        let for_loop = mk().for_expr(
+            mk().ident_pat(var_name),
+            range,
+            mk().block(body),
+            None as Option<Ident>,
+        );
+        stmts.push(mk().expr_stmt(for_loop));
+        Ok(stmts)
+    }
+}
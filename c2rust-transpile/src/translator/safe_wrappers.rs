@@ -0,0 +1,227 @@
+//! This module implements `--emit-safe-wrappers`, which gives exported functions taking only
+//! numbers, or pointers to numbers together with their length, a safe wrapper next to their
+//! `unsafe extern "C"` translation:
+//!
+//! ```c
+//! int clamp(int x, int lo, int hi);
+//! long sum(const int *xs, size_t len);
+//! ```
+//!
+//! gets
+//!
+//! ```ignore
+//! pub fn clamp_safe(x: libc::c_int, lo: libc::c_int, hi: libc::c_int) -> libc::c_int {
+//!     unsafe { clamp(x, lo, hi) }
+//! }
+//! pub fn sum_safe(xs: &[libc::c_int]) -> libc::c_long {
+//!     unsafe { sum(xs.as_ptr(), xs.len() as size_t) }
+//! }
+//! ```
+//!
+//! A pointer parameter must be directly followed by an integer parameter named like a length
+//! (`n`, or containing `len`, `size`, `count` or `num`), and becomes a slice in the wrapper, which
+//! is mutable unless the pointer is to `const`. The wrappers only make the calls themselves safe
+//! to write; they trust the functions to stay within the slices and to not use other memory
+//! unsoundly.
+
+use super::*;
+
+/// A parameter of a safe wrapper
+enum WrapperParam {
+    /// A number passed through as is
+    Scalar(CDeclId),
+    /// A pointer to numbers and its length, passed as a slice
+    Slice { ptr: CDeclId, len: CDeclId },
+}
+
+impl<'c> Translation<'c> {
+    /// The parameters of the safe wrapper of `decl_id`, if it is an exported function that
+    /// qualifies for one
+    fn safe_wrapper_params(&self, decl_id: CDeclId) -> Option<Vec<WrapperParam>> {
+        let (typ, parameters) = match self.ast_context[decl_id].kind {
+            CDeclKind::Function {
+                is_global: true,
+                typ,
+                ref parameters,
+                body: Some(_),
+                ..
+            } => (typ, parameters),
+            _ => return None,
+        };
+        if self.ast_context.c_main == Some(decl_id) {
+            return None;
+        }
+        match self.ast_context.resolve_type(typ).kind {
+            CTypeKind::Function(ret, _, false, _, _) => {
+                let ret = &self.ast_context.resolve_type(ret.ctype).kind;
+                if !(*ret == CTypeKind::Void || is_scalar(ret)) {
+                    return None;
+                }
+            }
+            _ => return None,
+        }
+
+        let mut params = vec![];
+        let mut iter = parameters.iter().peekable();
+        while let Some(&param_id) = iter.next() {
+            let typ = match self.ast_context[param_id].kind {
+                CDeclKind::Variable { ref ident, typ, .. } if !ident.is_empty() => typ,
+                _ => return None,
+            };
+            match self.ast_context.resolve_type(typ.ctype).kind {
+                CTypeKind::Pointer(pointee)
+                    if !pointee.qualifiers.is_volatile
+                        && is_scalar(&self.ast_context.resolve_type(pointee.ctype).kind) =>
+                {
+                    let len = **iter.peek()?;
+                    let is_len = match self.ast_context[len].kind {
+                        CDeclKind::Variable { ref ident, typ, .. } => {
+                            is_length_name(ident)
+                                && self
+                                    .ast_context
+                                    .resolve_type(typ.ctype)
+                                    .kind
+                                    .is_integral_type()
+                        }
+                        _ => false,
+                    };
+                    if !is_len {
+                        return None;
+                    }
+                    iter.next();
+                    params.push(WrapperParam::Slice { ptr: param_id, len });
+                }
+                ref kind if is_scalar(kind) => params.push(WrapperParam::Scalar(param_id)),
+                _ => return None,
+            }
+        }
+        Some(params)
+    }
+
+    /// Add the safe wrapper of `decl_id` to its translation, if `--emit-safe-wrappers` is set
+    /// and the function qualifies for one.
+    pub fn add_safe_wrapper(
+        &self,
+        decl_id: CDeclId,
+        converted: ConvertedDecl,
+    ) -> Result<ConvertedDecl, TranslationError> {
+        if !self.tcfg.emit_safe_wrappers {
+            return Ok(converted);
+        }
+        let params = match self.safe_wrapper_params(decl_id) {
+            Some(params) => params,
+            None => return Ok(converted),
+        };
+        let wrapper = self.convert_safe_wrapper(decl_id, &params)?;
+        Ok(match converted {
+            ConvertedDecl::Item(item) => ConvertedDecl::Items(vec![item, wrapper]),
+            ConvertedDecl::Items(mut items) => {
+                items.push(wrapper);
+                ConvertedDecl::Items(items)
+            }
+            converted => converted,
+        })
+    }
+
+    fn convert_safe_wrapper(
+        &self,
+        decl_id: CDeclId,
+        params: &[WrapperParam],
+    ) -> Result<Box<Item>, TranslationError> {
+        let ret = match self.ast_context[decl_id].kind {
+            CDeclKind::Function { typ, .. } => match self.ast_context.resolve_type(typ).kind {
+                CTypeKind::Function(ret, ..) => ret.ctype,
+                _ => return Err(format_err!("Safe wrapper of a non-function type").into()),
+            },
+            _ => return Err(format_err!("Safe wrapper of a non-function").into()),
+        };
+        let fn_name = self
+            .renamer
+            .borrow()
+            .get(&decl_id)
+            .expect("Functions should already be renamed");
+        let wrapper_name = self
+            .renamer
+            .borrow_mut()
+            .pick_name(&format!("{}_safe", fn_name));
+
+        self.with_scope(|| {
+            let insert_param = |param_id: CDeclId| match self.ast_context[param_id].kind {
+                CDeclKind::Variable { ref ident, typ, .. } => self
+                    .renamer
+                    .borrow_mut()
+                    .insert(param_id, ident)
+                    .map(|name| (name, typ))
+                    .ok_or_else(|| format_err!("Failed to insert argument '{}'", ident)),
+                _ => Err(format_err!("Parameter is not variable declaration")),
+            };
+
+            let mut args = vec![];
+            let mut call_args = vec![];
+            for param in params {
+                match *param {
+                    WrapperParam::Scalar(param_id) => {
+                        let (name, typ) = insert_param(param_id)?;
+                        args.push(mk().arg(self.convert_type(typ.ctype)?, mk().ident_pat(&name)));
+                        call_args.push(mk().ident_expr(name));
+                    }
+                    WrapperParam::Slice { ptr, len } => {
+                        let (name, typ) = insert_param(ptr)?;
+                        let (_, len_typ) = insert_param(len)?;
+                        let pointee = match self.ast_context.resolve_type(typ.ctype).kind {
+                            CTypeKind::Pointer(pointee) => pointee,
+                            _ => return Err(format_err!("Slice parameter is not a pointer").into()),
+                        };
+                        let (mutbl, as_ptr) = if pointee.qualifiers.is_const {
+                            (Mutability::Immutable, "as_ptr")
+                        } else {
+                            (Mutability::Mutable, "as_mut_ptr")
+                        };
+                        let ty = mk()
+                            .set_mutbl(mutbl)
+                            .ref_ty(mk().slice_ty(self.convert_type(pointee.ctype)?));
+                        args.push(mk().arg(ty, mk().ident_pat(&name)));
+
+                        let no_args = Vec::<Box<Expr>>::new();
+                        let slice_len =
+                            mk().method_call_expr(mk().ident_expr(&name), "len", no_args.clone());
+                        call_args.push(mk().method_call_expr(
+                            mk().ident_expr(&name),
+                            as_ptr,
+                            no_args,
+                        ));
+                        call_args
+                            .push(mk().cast_expr(slice_len, self.convert_type(len_typ.ctype)?));
+                    }
+                }
+            }
+
+            let ret_ty = match self.ast_context.resolve_type(ret).kind {
+                CTypeKind::Void => ReturnType::Default,
+                _ => ReturnType::Type(Default::default(), self.convert_type(ret)?),
+            };
+            let call = mk().call_expr(mk().ident_expr(fn_name), call_args);
+            let body = mk().block(vec![mk().expr_stmt(
+                mk().unsafe_block_expr(mk().unsafe_block(vec![mk().expr_stmt(call)])),
+            )]);
+
+            Ok(mk()
+                .pub_()
+                .fn_item(mk().fn_decl(wrapper_name, args, None, ret_ty), body))
+        })
+    }
+}
+
+/// Whether values of the type can be passed to and returned from safe wrappers as is
+fn is_scalar(kind: &CTypeKind) -> bool {
+    kind.is_integral_type() || kind.is_floating_type() || kind.is_enum()
+}
+
+/// Whether a parameter following a pointer is named like the length of its array
+fn is_length_name(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name == "n"
+        || ["len", "size", "count", "num"]
+            .iter()
+            .any(|word| name.contains(word))
+}
@@ -0,0 +1,222 @@
+//! This module implements `--share-inline-fns`, which translates `static inline` functions
+//! defined in headers once for the whole crate instead of once in every file including the
+//! header:
+//!
+//! ```c
+//! // util.h
+//! typedef unsigned int u32;
+//! static inline u32 rotl(u32 x, int n) { return x << n | x >> (32 - n); }
+//! ```
+//!
+//! becomes a submodule of the shared `headers` module (see `crate::header_modules`)
+//!
+//! ```ignore
+//! pub mod util_h {
+//!     pub type u32 = libc::c_uint;
+//!     #[inline]
+//!     pub(crate) unsafe extern "C" fn rotl(mut x: u32, mut n: libc::c_int) -> u32 { /* ... */ }
+//! }
+//! ```
+//!
+//! and every translated file using it gets `pub(crate) use crate::headers::util_h::rotl;`. The
+//! definitions from different files are identified by their header and name. A header may define
+//! a function differently in each file, depending on the macros defined before including it, so
+//! the files whose definition differs from the first file's are translated again with their own
+//! copy of the function (see `crate::header_modules::differing_fns`).
+//!
+//! Only functions that can be moved out of the file are shared: they may only use scalar types,
+//! pointers and arrays of them and typedefs of those, since structs, unions and enums are still
+//! translated separately in every file, and they may not refer to other functions or to
+//! variables with static storage, which are private to each file in C.
+
+use super::*;
+use crate::c_ast::iterators::DFNodes;
+use c2rust_ast_builder::Make;
+
+impl<'c> Translation<'c> {
+    /// The typedefs used by a `static inline` function defined in a header, if the function can
+    /// be shared by all the files including the header.
+    pub fn shareable_inline_fn(&self, decl_id: CDeclId) -> Option<IndexSet<CDeclId>> {
+        let decl = &self.ast_context[decl_id];
+        match decl.kind {
+            CDeclKind::Function {
+                is_global: false,
+                is_inline: true,
+                body: Some(_),
+                ..
+            } => {}
+            _ => return None,
+        }
+        if self
+            .ast_context
+            .file_id(decl)
+            .map_or(true, |file_id| file_id == self.main_file)
+            || self.is_local_inline_fn(decl_id)
+        {
+            return None;
+        }
+
+        let mut typedefs = IndexSet::new();
+        for node in DFNodes::new(&self.ast_context, SomeId::Decl(decl_id)) {
+            let shareable = match node {
+                SomeId::Stmt(_) => true,
+                SomeId::Expr(expr_id) => {
+                    let kind = &self.ast_context[expr_id].kind;
+                    let refers_to_shareable = match *kind {
+                        CExprKind::DeclRef(_, ref_id, _) => self.shareable_ref(decl_id, ref_id),
+                        _ => true,
+                    };
+                    refers_to_shareable
+                        && kind.get_qual_type().map_or(true, |qty| {
+                            self.collect_shareable_type(qty.ctype, &mut typedefs)
+                        })
+                }
+                SomeId::Decl(id) if id == decl_id => true,
+                SomeId::Decl(id) => match self.ast_context[id].kind {
+                    CDeclKind::Variable {
+                        has_static_duration: false,
+                        has_thread_duration: false,
+                        ..
+                    } => true,
+                    // Collected along with the types naming them
+                    CDeclKind::Typedef { .. } => true,
+                    _ => false,
+                },
+                SomeId::Type(type_id) => self.collect_shareable_type(type_id, &mut typedefs),
+            };
+            if !shareable {
+                return None;
+            }
+        }
+        Some(typedefs)
+    }
+
+    /// Is this a header function that other files define differently, which is translated into
+    /// this file instead of being shared?
+    pub fn is_local_inline_fn(&self, decl_id: CDeclId) -> bool {
+        let name = self.renamer.borrow().get(&decl_id);
+        name.map_or(false, |name| self.local_inline_fns.contains(&name))
+    }
+
+    /// Can a shared function refer to this declaration?
+    fn shareable_ref(&self, fn_id: CDeclId, ref_id: CDeclId) -> bool {
+        match self.ast_context[ref_id].kind {
+            _ if ref_id == fn_id => true,
+            CDeclKind::Variable {
+                has_static_duration,
+                has_thread_duration,
+                ..
+            } => !has_static_duration && !has_thread_duration,
+            // Builtins are translated in place
+            CDeclKind::Function { ref name, .. } => name.starts_with("__builtin_"),
+            _ => false,
+        }
+    }
+
+    /// Check that a type means the same thing in every file, adding the typedefs it uses to
+    /// `typedefs`. Typedefs are translated into type aliases, so the shared module can have its
+    /// own copies of them.
+    fn collect_shareable_type(&self, type_id: CTypeId, typedefs: &mut IndexSet<CDeclId>) -> bool {
+        use CTypeKind::*;
+        match self.ast_context[type_id].kind {
+            Struct(..) | Union(..) | Enum(..) | Vector(..) | BlockPointer(..) | TypeOfExpr(..)
+            | FixedPoint(..) => false,
+            Typedef(decl_id) => {
+                if !typedefs.insert(decl_id) {
+                    return true;
+                }
+                match self.ast_context[decl_id].kind {
+                    CDeclKind::Typedef { typ, .. } => {
+                        self.collect_shareable_type(typ.ctype, typedefs)
+                    }
+                    _ => false,
+                }
+            }
+            Elaborated(ctype) | Decayed(ctype) | Paren(ctype) | TypeOf(ctype) | Auto(ctype)
+            | Complex(ctype) | ConstantArray(ctype, _) | IncompleteArray(ctype)
+            | VariableArray(ctype, _) => self.collect_shareable_type(ctype, typedefs),
+            Pointer(qty) | Reference(qty) | Attributed(qty, _) => {
+                self.collect_shareable_type(qty.ctype, typedefs)
+            }
+            Function(ret, ref params, ..) => {
+                self.collect_shareable_type(ret.ctype, typedefs)
+                    && params
+                        .iter()
+                        .all(|param| self.collect_shareable_type(param.ctype, typedefs))
+            }
+            _ => true,
+        }
+    }
+
+    /// Translate a function found by `shareable_inline_fn` into the shared module of its header
+    /// and import it into this file.
+    pub fn share_inline_fn(
+        &self,
+        ctx: ExprContext,
+        decl_id: CDeclId,
+        typedefs: &IndexSet<CDeclId>,
+    ) -> Result<(), TranslationError> {
+        let file_id = self
+            .ast_context
+            .file_id(&self.ast_context[decl_id])
+            .expect("shared functions are defined in headers");
+
+        let mut items = vec![];
+        for &typedef_id in typedefs {
+            let already_shared = self
+                .shared_typedefs
+                .borrow()
+                .contains(&(file_id, typedef_id));
+            if already_shared {
+                continue;
+            }
+            match self.convert_decl(ctx, typedef_id)? {
+                ConvertedDecl::Item(item) => items.push(item),
+                ConvertedDecl::Items(typedef_items) => items.extend(typedef_items),
+                ConvertedDecl::ForeignItem(_) | ConvertedDecl::NoItem => {}
+            }
+            self.shared_typedefs
+                .borrow_mut()
+                .insert((file_id, typedef_id));
+        }
+
+        let fn_items = match self.convert_decl(ctx, decl_id)? {
+            ConvertedDecl::Item(item) => vec![item],
+            ConvertedDecl::Items(items) => items,
+            ConvertedDecl::ForeignItem(_) | ConvertedDecl::NoItem => {
+                return Err(format_err!("Shared function {:?} has no definition", decl_id).into())
+            }
+        };
+        let ident = self.renamer.borrow().get(&decl_id).expect("function name");
+        for mut item in fn_items {
+            if let Item::Fn(ref mut item_fn) = *item {
+                if item_fn.sig.ident == ident {
+                    item_fn.vis = "pub(crate)".make(&mk());
+                }
+            }
+            items.push(item);
+        }
+
+        let mod_name = clean_path(&self.mod_names, self.ast_context.get_file_path(file_id));
+        self.shared_inline_fns
+            .borrow_mut()
+            .entry(mod_name.clone())
+            .or_insert_with(Vec::new)
+            .extend(items);
+        self.items.borrow_mut()[&self.main_file].add_use_with_attr(
+            vec!["crate".into(), HEADERS_MODULE.into(), mod_name],
+            &ident,
+            mk().vis("pub(crate)"),
+        );
+        Ok(())
+    }
+
+    /// The header submodules holding the functions shared by this file
+    pub fn shared_inline_fn_modules(&self) -> Vec<Box<Item>> {
+        self.shared_inline_fns
+            .borrow_mut()
+            .drain(..)
+            .map(|(mod_name, items)| mk().pub_().mod_item(mod_name, Some(mk().mod_(items))))
+            .collect()
+    }
+}
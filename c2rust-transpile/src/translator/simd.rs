@@ -6,7 +6,10 @@ use super::*;
 use crate::c_ast::BinOp::{Add, BitAnd, ShiftRight};
 use crate::c_ast::CExprKind::{Binary, Call, Conditional, ExplicitCast, ImplicitCast, Literal};
 use crate::c_ast::CLiteral::Integer;
-use crate::c_ast::CTypeKind::{Char, Double, Float, Int, LongLong, Short};
+use crate::c_ast::CTypeKind::{
+    Char, Double, Float, Half, Int, Long, LongLong, SChar, Short, UChar, UInt, ULong, ULongLong,
+    UShort,
+};
 use crate::c_ast::CastKind::{BitCast, IntegralCast};
 
 /// As of rustc 1.29, rust is known to be missing some SIMD functions.
@@ -67,6 +70,161 @@ static SIMD_X86_64_ONLY: &[&str] = &[
     "_mm_crc32_u64",
 ];
 
+/// Public x86/64 SIMD typedefs, which have the same names in `core::arch::{x86,x86_64}`
+static X86_SIMD_TYPES: &[&str] = &[
+    "__m128i",
+    "__m128",
+    "__m128d",
+    "__m64",
+    "__m256",
+    "__m256d",
+    "__m256i",
+    "__m512",
+    "__m512d",
+    "__m512i",
+    "__mmask8",
+    "__mmask16",
+    "__mmask32",
+    "__mmask64",
+];
+
+/// x86/64 SIMD typedefs that seem to be C internal types only, and shouldn't need any explicit
+/// support. See <https://internals.rust-lang.org/t/getting-explicit-simd-on-stable-rust/4380/115>
+static X86_INTERNAL_SIMD_TYPES: &[&str] = &[
+    "__v1di",
+    "__v2si",
+    "__v4hi",
+    "__v8qi",
+    "__v4si",
+    "__v4sf",
+    "__v4su",
+    "__v2df",
+    "__v2di",
+    "__v8hi",
+    "__v16qi",
+    "__v2du",
+    "__v8hu",
+    "__v16qu",
+    "__v32qu",
+    "__v4df",
+    "__v8sf",
+    "__v4di",
+    "__v8si",
+    "__v16hi",
+    "__v32qi",
+    "__v4du",
+    "__v8di_aligned",
+    "__v8df_aligned",
+    "__v16sf_aligned",
+    "__v8sf_aligned",
+    "__v4df_aligned",
+    "__v4di_aligned",
+    "__v16qs",
+    "__v32qs",
+    "__v8su",
+    "__v16hu",
+    "__v8di",
+    "__v16si",
+    "__v32hi",
+    "__v64qi",
+    "__v8df",
+    "__v16sf",
+    "__v8du",
+    "__v16su",
+    "__v32hu",
+    "__v64qu",
+    "__v64qs",
+    "__m512_u",
+    "__m512d_u",
+    "__m512i_u",
+    "__mm_loadh_pi_v2f32",
+    "__mm_loadl_pi_v2f32",
+];
+
+/// ARM NEON vector types from `arm_neon.h`. These have the same names in `core::arch::aarch64`.
+static NEON_VECTOR_TYPES: &[&str] = &[
+    "int8x8_t",
+    "int8x16_t",
+    "int16x4_t",
+    "int16x8_t",
+    "int32x2_t",
+    "int32x4_t",
+    "int64x1_t",
+    "int64x2_t",
+    "uint8x8_t",
+    "uint8x16_t",
+    "uint16x4_t",
+    "uint16x8_t",
+    "uint32x2_t",
+    "uint32x4_t",
+    "uint64x1_t",
+    "uint64x2_t",
+    "float32x2_t",
+    "float32x4_t",
+    "float64x1_t",
+    "float64x2_t",
+    "poly8x8_t",
+    "poly8x16_t",
+    "poly16x4_t",
+    "poly16x8_t",
+    "poly64x1_t",
+    "poly64x2_t",
+];
+
+/// Newer versions of `arm_neon.h` define the public types in terms of clang's builtin
+/// `__Int32x4_t`, etc., which need no support of their own.
+fn is_internal_neon_type(name: &str) -> bool {
+    name.starts_with("__")
+        && NEON_VECTOR_TYPES
+            .iter()
+            .any(|ty| ty.eq_ignore_ascii_case(&name[2..]))
+}
+
+/// Determine whether a typedef is one of the architecture-specific SIMD types handled by
+/// `import_simd_typedef`, as opposed to a generic GCC vector type.
+fn is_arch_simd_type(name: &str) -> bool {
+    X86_SIMD_TYPES.contains(&name)
+        || X86_INTERNAL_SIMD_TYPES.contains(&name)
+        || NEON_VECTOR_TYPES.contains(&name)
+        || is_internal_neon_type(name)
+}
+
+/// Get the suffix naming the element type of a NEON intrinsic (as in `vaddq_s32`) from the
+/// type code clang passes as the last argument of overloaded `__builtin_neon_*_v` builtins.
+fn neon_type_code_suffix(code: u64) -> Option<&'static str> {
+    // See `NeonTypeFlags` in clang/Basic/TargetBuiltins.h
+    const UNSIGNED_FLAG: u64 = 0x10;
+    let is_unsigned = code & UNSIGNED_FLAG != 0;
+    Some(match (code & 0xf, is_unsigned) {
+        (0, false) => "s8",
+        (0, true) => "u8",
+        (1, false) => "s16",
+        (1, true) => "u16",
+        (2, false) => "s32",
+        (2, true) => "u32",
+        (3, false) => "s64",
+        (3, true) => "u64",
+        (4, _) => "p8",
+        (5, _) => "p16",
+        (6, _) => "p64",
+        (7, _) => "p128",
+        (8, _) => "f16",
+        (9, _) => "f32",
+        (10, _) => "f64",
+        _ => return None,
+    })
+}
+
+/// Get the name of a masked variant of a SIMD function, as in `_mm512_mask_add_epi32` or
+/// `_mm512_maskz_add_epi32` for `_mm512_add_epi32`.
+fn masked_simd_fn_name(fn_name: &str, mask_kind: &str) -> String {
+    // Split `_mm512_add_epi32` into `_mm512` and `add_epi32`
+    match fn_name[1..].find('_') {
+        Some(idx) => format!("{}_{}_{}", &fn_name[..=idx], mask_kind, &fn_name[idx + 2..]),
+        None => fn_name.to_string(),
+    }
+}
+
 impl<'c> Translation<'c> {
     /// Given the name of a typedef check if its one of the SIMD types.
     /// This function returns `true` when the name of the type is one that
@@ -74,7 +232,7 @@ impl<'c> Translation<'c> {
     pub fn import_simd_typedef(&self, name: &str) -> Result<bool, TranslationError> {
         Ok(match name {
             // Public API SIMD typedefs:
-            "__m128i" | "__m128" | "__m128d" | "__m64" | "__m256" | "__m256d" | "__m256i" => {
+            _ if X86_SIMD_TYPES.contains(&name) => {
                 // __m64 and MMX support were removed from upstream Rust.
                 // See https://github.com/immunant/c2rust/issues/369
                 if name == "__m64" {
@@ -83,6 +241,11 @@ impl<'c> Translation<'c> {
                     ))?;
                 }
 
+                // The AVX-512 types are still behind a feature gate
+                if name.starts_with("__m512") || name.starts_with("__mmask") {
+                    self.use_feature("stdsimd");
+                }
+
                 self.with_cur_file_item_store(|item_store| {
                     let x86_attr = mk()
                         .meta_item_attr(
@@ -121,46 +284,62 @@ impl<'c> Translation<'c> {
 
                 true
             }
-            // These seem to be C internal types only, and shouldn't need any explicit support.
-            // See https://internals.rust-lang.org/t/getting-explicit-simd-on-stable-rust/4380/115
-            "__v1di"
-            | "__v2si"
-            | "__v4hi"
-            | "__v8qi"
-            | "__v4si"
-            | "__v4sf"
-            | "__v4su"
-            | "__v2df"
-            | "__v2di"
-            | "__v8hi"
-            | "__v16qi"
-            | "__v2du"
-            | "__v8hu"
-            | "__v16qu"
-            | "__v32qu"
-            | "__v4df"
-            | "__v8sf"
-            | "__v4di"
-            | "__v8si"
-            | "__v16hi"
-            | "__v32qi"
-            | "__v4du"
-            | "__v8di_aligned"
-            | "__v8df_aligned"
-            | "__v16sf_aligned"
-            | "__v8sf_aligned"
-            | "__v4df_aligned"
-            | "__v4di_aligned"
-            | "__v16qs"
-            | "__v32qs"
-            | "__v8su"
-            | "__v16hu"
-            | "__mm_loadh_pi_v2f32"
-            | "__mm_loadl_pi_v2f32" => true,
+            _ if X86_INTERNAL_SIMD_TYPES.contains(&name) => true,
+            _ if NEON_VECTOR_TYPES.contains(&name) => {
+                self.import_aarch64_item(name);
+
+                true
+            }
+            _ if is_internal_neon_type(name) => true,
             _ => false,
         })
     }
 
+    /// Generate a use statement for an item of `core::arch::aarch64`
+    fn import_aarch64_item(&self, name: &str) {
+        self.with_cur_file_item_store(|item_store| {
+            let aarch64_attr = mk()
+                .meta_item_attr(
+                    AttrStyle::Outer,
+                    mk().meta_list(
+                        "cfg",
+                        vec![NestedMeta::Meta(
+                            mk().meta_namevalue("target_arch", "aarch64"),
+                        )],
+                    ),
+                )
+                .pub_();
+            let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" }.to_string();
+
+            item_store.add_use_with_attr(
+                vec![std_or_core, "arch".into(), "aarch64".into()],
+                name,
+                aarch64_attr,
+            );
+        });
+    }
+
+    /// Determine if a function is an ARM NEON intrinsic defined in `arm_neon.h`. If so an
+    /// appropriate use statement is generated, `true` is returned, and no further processing
+    /// will need to be done.
+    pub fn import_neon_function(&self, decl_id: CDeclId, name: &str) -> bool {
+        let decl = &self.ast_context[decl_id];
+        let in_arm_neon_h = self
+            .ast_context
+            .get_source_path(decl)
+            .and_then(|path| path.file_name())
+            .map_or(false, |file_name| file_name == "arm_neon.h");
+
+        // Helpers such as `__noswap_vaddq_s32` have no Rust counterpart and are translated
+        if !in_arm_neon_h || name.starts_with("__") {
+            return false;
+        }
+
+        self.import_aarch64_item(name);
+
+        true
+    }
+
     /// Determine if a particular function name is an SIMD primitive. If so an appropriate
     /// use statement is generated, `true` is returned, and no further processing will need to be done.
     pub fn import_simd_function(&self, name: &str) -> Result<bool, TranslationError> {
@@ -264,7 +443,125 @@ impl<'c> Translation<'c> {
                 .map(|arg| self.clean_int_or_vector_param(*arg)),
         );
 
-        let param_translation = self.convert_exprs(ctx.used(), &processed_args)?;
+        self.simd_builtin_call(ctx, fn_name, &processed_args)
+    }
+
+    /// Generate a call to a rust AVX-512 function based on a masked builtin. These take the
+    /// inputs of the operation followed by the vector that masked off elements are taken from and
+    /// the mask. Depending on those two, the `_mm512_foo`, `_mm512_mask_foo`, or `_mm512_maskz_foo`
+    /// variant of `fn_name` is called.
+    pub fn convert_simd_mask_builtin(
+        &self,
+        ctx: ExprContext,
+        fn_name: &str,
+        args: &[CExprId],
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        let (inputs, src, mask) = match args {
+            [inputs @ .., src, mask] => (inputs, *src, *mask),
+            _ => Err(format_err!("Missing source and mask for {}", fn_name))?,
+        };
+
+        let mut processed_args = vec![];
+        let fn_name = if self.is_all_ones_mask(mask) && self.is_simd_call_to(src, "undef") {
+            fn_name.to_string()
+        } else if self.is_simd_call_to(src, "setzero") {
+            processed_args.push(mask);
+            masked_simd_fn_name(fn_name, "maskz")
+        } else {
+            processed_args.push(self.clean_int_or_vector_param(src));
+            processed_args.push(mask);
+            masked_simd_fn_name(fn_name, "mask")
+        };
+        processed_args.extend(
+            inputs
+                .iter()
+                .map(|arg| self.clean_int_or_vector_param(*arg)),
+        );
+
+        self.import_simd_function(&fn_name)?;
+        self.simd_builtin_call(ctx, &fn_name, &processed_args)
+    }
+
+    /// Generate a call to a rust AVX-512 comparison function based on a builtin taking the
+    /// inputs of the comparison followed by a mask that the result is and-ed with. Unless the
+    /// mask is all ones, the `_mm512_mask_foo` variant of `fn_name` is called.
+    pub fn convert_simd_cmp_mask_builtin(
+        &self,
+        ctx: ExprContext,
+        fn_name: &str,
+        args: &[CExprId],
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        let (mask, inputs) = args
+            .split_last()
+            .ok_or_else(|| format_err!("Missing mask for {}", fn_name))?;
+
+        let mut processed_args = vec![];
+        let fn_name = if self.is_all_ones_mask(*mask) {
+            fn_name.to_string()
+        } else {
+            processed_args.push(*mask);
+            masked_simd_fn_name(fn_name, "mask")
+        };
+        processed_args.extend(
+            inputs
+                .iter()
+                .map(|arg| self.clean_int_or_vector_param(*arg)),
+        );
+
+        self.import_simd_function(&fn_name)?;
+        self.simd_builtin_call(ctx, &fn_name, &processed_args)
+    }
+
+    /// Determine whether a mask argument is a constant with all bits set, such as `(__mmask16)-1`
+    fn is_all_ones_mask(&self, expr_id: CExprId) -> bool {
+        let bits = match self.ast_context[expr_id].kind.get_type() {
+            Some(ty) => match self.ast_context.resolve_type(ty).kind {
+                UChar => 8,
+                UShort => 16,
+                UInt => 32,
+                ULong | ULongLong => 64,
+                _ => return false,
+            },
+            None => return false,
+        };
+
+        match *self.ast_context.resolve_expr(expr_id).1 {
+            CExprKind::Unary(_, c_ast::UnOp::Negate, arg, _) => {
+                match *self.ast_context.resolve_expr(arg).1 {
+                    Literal(_, Integer(1, _)) => true,
+                    _ => false,
+                }
+            }
+            Literal(_, Integer(value, _)) => value == u64::MAX >> (64 - bits),
+            _ => false,
+        }
+    }
+
+    /// Determine whether an expression is a call to a SIMD function whose name contains `kind`,
+    /// such as `_mm512_undefined_ps()` or `_mm512_setzero_ps()`.
+    fn is_simd_call_to(&self, expr_id: CExprId, kind: &str) -> bool {
+        let func = match *self.ast_context.resolve_expr(expr_id).1 {
+            Call(_, func, _) => func,
+            _ => return false,
+        };
+        match *self.ast_context.resolve_expr(func).1 {
+            CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[decl_id].kind {
+                CDeclKind::Function { ref name, .. } => name.contains(kind),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Generate a call to `fn_name` with arguments that have already had their
+    /// superfluous casts stripped.
+    fn simd_builtin_call(
+        &self,
+        ctx: ExprContext,
+        fn_name: &str,
+        args: &[CExprId],
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        let param_translation = self.convert_exprs(ctx.used(), args)?;
         param_translation.and_then(|call_params| {
             let call = mk().call_expr(mk().ident_expr(fn_name), call_params);
 
@@ -279,6 +576,105 @@ impl<'c> Translation<'c> {
         })
     }
 
+    /// Generate a call to a Rust NEON intrinsic based on a `__builtin_neon_*` builtin, which
+    /// is what the macros in `arm_neon.h` expand to (e.g. `vgetq_lane_s32`).
+    ///
+    /// The builtins operate on signed vectors which the macros cast to and from. These casts
+    /// are stripped and the intrinsic is picked by the element type of the uncast arguments, or
+    /// by the type code that clang passes as the last argument to overloaded builtins.
+    pub fn convert_neon_builtin(
+        &self,
+        ctx: ExprContext,
+        builtin_name: &str,
+        args: &[CExprId],
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        let op = builtin_name.trim_start_matches("__builtin_neon_");
+        let (fn_name, args) = if let Some(base) = op.strip_suffix("_v") {
+            let (code_arg, args) = args
+                .split_last()
+                .ok_or_else(|| format_err!("Missing type code for {}", builtin_name))?;
+            let suffix = match *self.ast_context.resolve_expr(*code_arg).1 {
+                Literal(_, Integer(code, _)) => neon_type_code_suffix(code),
+                _ => None,
+            }
+            .ok_or_else(|| format_err!("Unknown NEON type code for {}", builtin_name))?;
+            (format!("{}_{}", base, suffix), args)
+        } else {
+            let (base, ty) = op
+                .rsplit_once('_')
+                .ok_or_else(|| format_err!("Unknown NEON builtin {}", builtin_name))?;
+            let suffix = args
+                .iter()
+                .find_map(|&arg| self.neon_element_suffix(self.strip_neon_cast(arg)))
+                .map(String::from)
+                .unwrap_or_else(|| ty.replacen('i', "s", 1));
+            (format!("{}_{}", base, suffix), args)
+        };
+
+        self.import_aarch64_item(&fn_name);
+
+        let args: Vec<_> = args.iter().map(|&arg| self.strip_neon_cast(arg)).collect();
+        let param_translation = self.convert_exprs(ctx.used(), &args)?;
+        param_translation.and_then(|call_params| {
+            let call = mk().call_expr(mk().ident_expr(fn_name), call_params);
+
+            if ctx.is_used() {
+                Ok(WithStmts::new_val(call))
+            } else {
+                Ok(WithStmts::new(
+                    vec![mk().semi_stmt(call)],
+                    self.panic_or_err("No value for unused NEON builtin return"),
+                ))
+            }
+        })
+    }
+
+    /// Strip the cast of a NEON builtin argument to the generic vector or `void` pointer type
+    /// the builtin is declared with.
+    fn strip_neon_cast(&self, expr_id: CExprId) -> CExprId {
+        match self.ast_context[expr_id].kind {
+            ExplicitCast(_, inner, BitCast, _, _) | ImplicitCast(_, inner, BitCast, _, _) => inner,
+            _ => expr_id,
+        }
+    }
+
+    /// Get the suffix naming the element type of a NEON intrinsic from the type of a vector
+    /// argument, or `None` if the argument is not a vector.
+    fn neon_element_suffix(&self, expr_id: CExprId) -> Option<&'static str> {
+        let ty = self.ast_context[expr_id].kind.get_type()?;
+        let elt = match self.ast_context.resolve_type(ty).kind {
+            CTypeKind::Vector(elt, _) => elt.ctype,
+            _ => return None,
+        };
+
+        // The polynomial types are typedefs of unsigned integers
+        if let CTypeKind::Typedef(decl_id) = self.ast_context[elt].kind {
+            if let CDeclKind::Typedef { ref name, .. } = self.ast_context[decl_id].kind {
+                match name.as_str() {
+                    "poly8_t" => return Some("p8"),
+                    "poly16_t" => return Some("p16"),
+                    "poly64_t" => return Some("p64"),
+                    _ => {}
+                }
+            }
+        }
+
+        Some(match self.ast_context.resolve_type(elt).kind {
+            Char | SChar => "s8",
+            UChar => "u8",
+            Short => "s16",
+            UShort => "u16",
+            Int => "s32",
+            UInt => "u32",
+            Long | LongLong => "s64",
+            ULong | ULongLong => "u64",
+            Half => "f16",
+            Float => "f32",
+            Double => "f64",
+            _ => return None,
+        })
+    }
+
     /// Generate a zero value to be used for initialization of a given vector type. The type
     /// is specified with the underlying element type and the number of elements in the vector.
     pub fn implicit_vector_default(
@@ -296,6 +692,9 @@ impl<'c> Translation<'c> {
             (Double, 4) => ("_mm256_setzero_pd", 32),
             (Char, 16) | (Int, 4) | (LongLong, 2) => ("_mm_setzero_si128", 16),
             (Char, 32) | (Int, 8) | (LongLong, 4) => ("_mm256_setzero_si256", 32),
+            (Float, 16) => ("_mm512_setzero_ps", 64),
+            (Double, 8) => ("_mm512_setzero_pd", 64),
+            (Char, 64) | (Short, 32) | (Int, 16) | (LongLong, 8) => ("_mm512_setzero_si512", 64),
             (Char, 8) | (Int, 2) | (LongLong, 1) => {
                 // __m64 is still unstable as of rust 1.29
                 self.use_feature("stdsimd");
@@ -367,6 +766,12 @@ impl<'c> Translation<'c> {
                     (Int, 2) => "_mm_setr_pi32",
                     (Int, 4) => "_mm_setr_epi32",
                     (Int, 8) => "_mm256_setr_epi32",
+                    (Float, 16) => "_mm512_setr_ps",
+                    (Double, 8) => "_mm512_setr_pd",
+                    (Char, 64) => "_mm512_set_epi8",
+                    (Short, 32) => "_mm512_set_epi16",
+                    (Int, 16) => "_mm512_setr_epi32",
+                    (LongLong, 8) => "_mm512_setr_epi64",
                     (Short, 4) => "_mm_setr_pi16",
                     (Short, 8) => "_mm_setr_epi16",
                     (Short, 16) => "_mm256_setr_epi16",
@@ -375,9 +780,10 @@ impl<'c> Translation<'c> {
 
                 self.import_simd_function(fn_call_name)?;
 
-                // rust is missing support for _mm_setr_epi64x, so we have to use
-                // the reverse arguments for _mm_set_epi64x
-                if fn_call_name == "_mm_set_epi64x" {
+                // rust is missing support for _mm_setr_epi64x, _mm512_setr_epi8, and
+                // _mm512_setr_epi16, so we have to use the reverse arguments for the `set` versions
+                if ["_mm_set_epi64x", "_mm512_set_epi8", "_mm512_set_epi16"].contains(&fn_call_name)
+                {
                     params.reverse();
                 }
 
@@ -596,7 +1002,9 @@ impl<'c> Translation<'c> {
         use self::CastKind::BuiltinFnToFnPtr;
 
         match self.ast_context[expr_id].kind {
-            CExprKind::ShuffleVector(..) => is_explicit && kind == CastKind::BitCast,
+            CExprKind::ShuffleVector(ty, _) => {
+                is_explicit && kind == CastKind::BitCast && !self.is_generic_vector_type(ty.ctype)
+            }
             CExprKind::Call(_, fn_id, _) => {
                 let fn_expr = &self.ast_context[fn_id].kind;
 
@@ -607,7 +1015,8 @@ impl<'c> Translation<'c> {
                         let decl = &self.ast_context[*decl_id].kind;
 
                         if let CDeclKind::Function { ref name, .. } = decl {
-                            return name.starts_with("__builtin_ia32_");
+                            return name.starts_with("__builtin_ia32_")
+                                || (is_explicit && name.starts_with("__builtin_neon_"));
                         }
                     }
                 }
@@ -617,4 +1026,324 @@ impl<'c> Translation<'c> {
             _ => false,
         }
     }
+
+    /// Determine whether a type is a generic GCC vector type, declared with
+    /// `__attribute__((vector_size(N)))`, rather than one of the architecture-specific SIMD types.
+    /// Generic vectors are translated to `core::simd::Simd`.
+    pub fn is_generic_vector_type(&self, ctype: CTypeId) -> bool {
+        match self.ast_context[ctype].kind {
+            CTypeKind::Vector(..) => true,
+            CTypeKind::Typedef(decl_id) => match self.ast_context[decl_id].kind {
+                CDeclKind::Typedef { ref name, typ, .. } => {
+                    !is_arch_simd_type(name) && self.is_generic_vector_type(typ.ctype)
+                }
+                _ => false,
+            },
+            CTypeKind::Elaborated(ty)
+            | CTypeKind::Decayed(ty)
+            | CTypeKind::Paren(ty)
+            | CTypeKind::TypeOf(ty)
+            | CTypeKind::Auto(ty) => self.is_generic_vector_type(ty),
+            CTypeKind::Attributed(ty, _) => self.is_generic_vector_type(ty.ctype),
+            _ => false,
+        }
+    }
+
+    /// Call an associated function of the `Simd` type a generic vector type is translated to,
+    /// as in `v4si::splat(x)`.
+    fn generic_vector_call(
+        &self,
+        ctype: CTypeId,
+        fn_name: &str,
+        args: Vec<Box<Expr>>,
+    ) -> Result<Box<Expr>, TranslationError> {
+        let mut path = match *self.convert_type(ctype)? {
+            Type::Path(TypePath { qself: None, path }) => path,
+            _ => Err(format_err!(
+                "Generic vector type did not translate to a path"
+            ))?,
+        };
+        path.segments.push(mk().path_segment(fn_name));
+
+        Ok(mk().call_expr(mk().path_expr(path), args))
+    }
+
+    /// Generate the default value of a generic vector, with every lane zeroed
+    pub fn implicit_generic_vector_default(
+        &self,
+        ctype: CTypeId,
+        elt: CTypeId,
+        len: usize,
+        is_static: bool,
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        let len = mk().lit_expr(mk().int_unsuffixed_lit(len as u128));
+        self.implicit_default_expr(elt, is_static)?
+            .result_map(|zero| {
+                let lanes = mk().repeat_expr(zero, len);
+                self.generic_vector_call(ctype, "from_array", vec![lanes])
+            })
+    }
+
+    /// Translate an initializer list for a generic vector. Lanes without an initializer are
+    /// zeroed, as in C.
+    pub fn generic_vector_list_initializer(
+        &self,
+        ctx: ExprContext,
+        ids: &[CExprId],
+        ctype: CTypeId,
+        elt: CTypeId,
+        len: usize,
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        let mut lanes = ids
+            .iter()
+            .map(|id| self.convert_expr(ctx.used(), *id))
+            .collect::<Result<Vec<_>, _>>()?;
+        for _ in ids.len()..len {
+            lanes.push(self.implicit_default_expr(elt, ctx.is_static)?);
+        }
+
+        let lanes: WithStmts<Vec<_>> = lanes.into_iter().collect();
+        lanes.result_map(|lanes| {
+            self.generic_vector_call(ctype, "from_array", vec![mk().array_expr(lanes)])
+        })
+    }
+
+    /// Convert a scalar to a generic vector with the scalar in every lane
+    pub fn convert_vector_splat(
+        &self,
+        ctype: CTypeId,
+        val: WithStmts<Box<Expr>>,
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        if !self.is_generic_vector_type(ctype) {
+            return Err(TranslationError::generic(
+                "TODO vector splat casts not supported",
+            ));
+        }
+
+        val.result_map(|x| self.generic_vector_call(ctype, "splat", vec![x]))
+    }
+
+    /// Translate a binary operator on generic vectors, which `Simd` applies lane-wise just like
+    /// C. Comparisons produce a vector with -1 in the lanes where they hold and 0 elsewhere.
+    pub fn convert_vector_binary_operator(
+        &self,
+        op: c_ast::BinOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    ) -> Result<Box<Expr>, TranslationError> {
+        let method = match op {
+            c_ast::BinOp::EqualEqual => "lanes_eq",
+            c_ast::BinOp::NotEqual => "lanes_ne",
+            c_ast::BinOp::Less => "lanes_lt",
+            c_ast::BinOp::Greater => "lanes_gt",
+            c_ast::BinOp::LessEqual => "lanes_le",
+            c_ast::BinOp::GreaterEqual => "lanes_ge",
+            c_ast::BinOp::Add
+            | c_ast::BinOp::Subtract
+            | c_ast::BinOp::Multiply
+            | c_ast::BinOp::Divide
+            | c_ast::BinOp::Modulus
+            | c_ast::BinOp::ShiftLeft
+            | c_ast::BinOp::ShiftRight
+            | c_ast::BinOp::BitAnd
+            | c_ast::BinOp::BitXor
+            | c_ast::BinOp::BitOr => return Ok(mk().binary_expr(BinOp::from(op), lhs, rhs)),
+            op => Err(format_err!("Unsupported vector operator {:?}", op))?,
+        };
+
+        let mask = mk().method_call_expr(lhs, method, vec![rhs]);
+        Ok(mk().method_call_expr(mask, "to_int", vec![] as Vec<Box<Expr>>))
+    }
+
+    /// Translate subscripting a generic vector, which reads or writes a single lane
+    pub fn convert_vector_subscript(
+        &self,
+        ctx: ExprContext,
+        vector: CExprId,
+        index: CExprId,
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        let vector = self.convert_expr(ctx.used(), vector)?;
+        let index = self.convert_expr(ctx.used(), index)?;
+
+        vector.and_then(|vector| {
+            Ok(index.map(|index| {
+                let index = mk().cast_expr(index, mk().path_ty(vec!["usize"]));
+                mk().index_expr(vector, index)
+            }))
+        })
+    }
+
+    /// Get the constant lane index of a `__builtin_shufflevector` argument, where -1 marks a
+    /// lane whose value is undefined
+    fn shuffle_lane_index(&self, expr_id: CExprId) -> Option<i64> {
+        match *self.ast_context.resolve_expr(expr_id).1 {
+            Literal(_, Integer(i, _)) => Some(i as i64),
+            CExprKind::ConstantExpr(_, _, Some(ConstIntExpr::I(i))) => Some(i),
+            CExprKind::ConstantExpr(_, _, Some(ConstIntExpr::U(u))) => Some(u as i64),
+            CExprKind::ConstantExpr(_, expr_id, None) => self.shuffle_lane_index(expr_id),
+            CExprKind::Unary(_, c_ast::UnOp::Negate, expr_id, _) => {
+                self.shuffle_lane_index(expr_id).map(|i| -i)
+            }
+            _ => None,
+        }
+    }
+
+    /// Translate `__builtin_shufflevector` on generic vectors. The lanes of the result are
+    /// picked out of the concatenation of the two input vectors by constant indices.
+    pub fn convert_generic_shuffle_vector(
+        &self,
+        ctx: ExprContext,
+        ty: CQualTypeId,
+        child_expr_ids: &[CExprId],
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        let (inputs, indices) = match child_expr_ids {
+            [first, second, indices @ ..] => ([*first, *second], indices),
+            _ => Err(format_err!("Shuffle vector is missing its input vectors"))?,
+        };
+
+        let input_ty = self.ast_context[inputs[0]]
+            .kind
+            .get_type()
+            .ok_or_else(|| format_err!("bad shuffle vector input type"))?;
+        let input_len = match self.ast_context.resolve_type(input_ty).kind {
+            CTypeKind::Vector(_, len) => len as i64,
+            _ => Err(format_err!("Shuffle vector input is not a vector"))?,
+        };
+        let elt = match self.ast_context.resolve_type(ty.ctype).kind {
+            CTypeKind::Vector(elt, _) => elt.ctype,
+            _ => Err(format_err!("Shuffle vector result is not a vector"))?,
+        };
+        let indices = indices
+            .iter()
+            .map(|&index| {
+                self.shuffle_lane_index(index)
+                    .ok_or_else(|| format_err!("Shuffle vector index is not a constant"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let elt_ty = self.convert_type(elt)?;
+        self.convert_exprs(ctx.used(), &inputs)?.and_then(|inputs| {
+            // Bind the inputs first so they are only evaluated once
+            let names: Vec<String> = (0..2).map(|_| self.renamer.borrow_mut().fresh()).collect();
+            let stmts = names
+                .iter()
+                .zip(inputs)
+                .map(|(name, input)| {
+                    mk().local_stmt(Box::new(mk().local(
+                        mk().ident_pat(name),
+                        None as Option<Box<Type>>,
+                        Some(input),
+                    )))
+                })
+                .collect();
+
+            let lanes = indices
+                .iter()
+                .map(|&index| {
+                    if index < 0 {
+                        let zero = mk().lit_expr(mk().int_unsuffixed_lit(0));
+                        return mk().cast_expr(zero, elt_ty.clone());
+                    }
+                    let (name, index) = if index < input_len {
+                        (&names[0], index)
+                    } else {
+                        (&names[1], index - input_len)
+                    };
+                    let index = mk().lit_expr(mk().int_unsuffixed_lit(index as u128));
+                    mk().index_expr(mk().ident_expr(name), index)
+                })
+                .collect::<Vec<_>>();
+            let val =
+                self.generic_vector_call(ty.ctype, "from_array", vec![mk().array_expr(lanes)])?;
+
+            Ok(WithStmts::new(stmts, val))
+        })
+    }
+
+    /// Translate `__builtin_convertvector`. Each lane of the input is converted to the element
+    /// type of the result, as C does. Architecture-specific vector types have no lanes to index
+    /// or build from, so they are transmuted to and from arrays of their lanes.
+    pub fn convert_convert_vector(
+        &self,
+        ctx: ExprContext,
+        ty: CQualTypeId,
+        child_expr_ids: &[CExprId],
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        let input = match child_expr_ids {
+            [input, ..] => *input,
+            _ => Err(format_err!("Convert vector is missing its input vector"))?,
+        };
+        let (elt, len) = match self.ast_context.resolve_type(ty.ctype).kind {
+            CTypeKind::Vector(elt, len) => (elt.ctype, len),
+            _ => Err(format_err!("Convert vector result is not a vector"))?,
+        };
+        let input_ty = self.ast_context[input]
+            .kind
+            .get_type()
+            .ok_or_else(|| format_err!("Convert vector input has no type"))?;
+        let input_elt = match self.ast_context.resolve_type(input_ty).kind {
+            CTypeKind::Vector(elt, _) => elt.ctype,
+            _ => Err(format_err!("Convert vector input is not a vector"))?,
+        };
+
+        let lanes_ty = |elt_ty: Box<Type>| {
+            let len = mk().lit_expr(mk().int_unsuffixed_lit(len as u128));
+            mk().array_ty(elt_ty, len)
+        };
+        let input_lanes_ty = if self.is_generic_vector_type(input_ty) {
+            None
+        } else {
+            Some(lanes_ty(self.convert_type(input_elt)?))
+        };
+        let result_ty = if self.is_generic_vector_type(ty.ctype) {
+            None
+        } else {
+            Some(self.convert_type(ty.ctype)?)
+        };
+        let elt_ty = self.convert_type(elt)?;
+        let is_transmuted = input_lanes_ty.is_some() || result_ty.is_some();
+        let no_std = self.tcfg.emit_no_std;
+        self.convert_expr(ctx.used(), input)?.and_then(|input| {
+            // Bind the input first so it is only evaluated once
+            let name = self.renamer.borrow_mut().fresh();
+            let input = match input_lanes_ty {
+                Some(ref lanes_ty) => {
+                    transmute_expr(mk().infer_ty(), lanes_ty.clone(), input, no_std)
+                }
+                None => input,
+            };
+            let stmt = mk().local_stmt(Box::new(mk().local(
+                mk().ident_pat(&name),
+                input_lanes_ty.clone(),
+                Some(input),
+            )));
+
+            let lanes = (0..len)
+                .map(|index| {
+                    let index = mk().lit_expr(mk().int_unsuffixed_lit(index as u128));
+                    let lane = mk().index_expr(mk().ident_expr(&name), index);
+                    mk().cast_expr(lane, elt_ty.clone())
+                })
+                .collect::<Vec<_>>();
+            let val = match result_ty {
+                Some(result_ty) => transmute_expr(
+                    lanes_ty(elt_ty.clone()),
+                    result_ty,
+                    mk().array_expr(lanes),
+                    no_std,
+                ),
+                None => self.generic_vector_call(
+                    ty.ctype,
+                    "from_array",
+                    vec![mk().array_expr(lanes)],
+                )?,
+            };
+
+            let mut converted = WithStmts::new(vec![stmt], val);
+            if is_transmuted {
+                converted.set_unsafe();
+            }
+            Ok(converted)
+        })
+    }
 }
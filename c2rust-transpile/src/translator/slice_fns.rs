@@ -0,0 +1,584 @@
+//! This module implements `--slice-fns`, which gives simple copy and scan helpers a safe,
+//! slice-based version next to their C entry point:
+//!
+//! ```c
+//! void scale(float *restrict dst, const float *src, size_t n, float k) {
+//!     for (size_t i = 0; i < n; i++)
+//!         dst[i] = src[i] * k;
+//! }
+//! ```
+//!
+//! becomes
+//!
+//! ```ignore
+//! #[inline]
+//! pub fn scale_slice(dst: &mut [libc::c_float], src: &[libc::c_float], mut k: libc::c_float) {
+//!     let n: size_t = dst.len() as size_t;
+//!     let mut i: size_t = 0;
+//!     while i < n {
+//!         dst[i as usize] = src[i as usize] * k;
+//!         i = i.wrapping_add(1);
+//!     }
+//! }
+//! #[no_mangle]
+//! pub unsafe extern "C" fn scale(mut dst: *mut libc::c_float, mut src: *const libc::c_float,
+//!                                mut n: size_t, mut k: libc::c_float) {
+//!     scale_slice(
+//!         if dst.is_null() { &mut [] } else { ::core::slice::from_raw_parts_mut(dst, n.max(0) as usize) },
+//!         if src.is_null() { &[] } else { ::core::slice::from_raw_parts(src, n.max(0) as usize) },
+//!         k,
+//!     )
+//! }
+//! ```
+//!
+//! A function qualifies if its pointer parameters point to numbers and are only indexed by the
+//! counter of a `for (i = 0; i < n; i++)` loop, inside that loop, where `n` is an integer
+//! parameter used for nothing else and the counter is not modified elsewhere. Pointers written
+//! through must be `restrict`, since they become `&mut` slices. The body must not call
+//! functions, take addresses, dereference or declare other pointers, or use globals, so that the
+//! slice version needs no `unsafe`. Every slice has the length `n`, so the loops never index
+//! past their ends.
+
+use super::*;
+
+/// A function translated with an additional slice-based version
+pub struct SliceFn {
+    /// The parameter bounding the loops, which becomes the length of the slices
+    len: CDeclId,
+    /// The pointer parameters translated into slices, and whether they are written through
+    slices: Vec<(CDeclId, bool)>,
+}
+
+/// A `for (i = 0; i < n; i++)` loop
+struct CountedLoop {
+    counter: CDeclId,
+    len: CDeclId,
+    /// The assignment and increment of the counter in the loop header, if not a declaration
+    writes: Vec<CExprId>,
+    body: CStmtId,
+}
+
+impl<'c> Translation<'c> {
+    /// Find the functions selected by `--slice-fns`.
+    pub fn find_slice_fns(&self) -> HashMap<CDeclId, SliceFn> {
+        if !self.tcfg.slice_fns {
+            return HashMap::new();
+        }
+
+        self.ast_context
+            .iter_decls()
+            .filter_map(|(&decl_id, _)| Some((decl_id, self.slice_fn(decl_id)?)))
+            .collect()
+    }
+
+    /// The slices and length of `decl_id`, if it is a function that qualifies for a slice-based
+    /// version
+    fn slice_fn(&self, decl_id: CDeclId) -> Option<SliceFn> {
+        let (typ, parameters, body, attrs) = match self.ast_context[decl_id].kind {
+            CDeclKind::Function {
+                typ,
+                ref parameters,
+                body: Some(body),
+                ref attrs,
+                ..
+            } => (typ, parameters, body, attrs),
+            _ => return None,
+        };
+        if self.ast_context.c_main == Some(decl_id)
+            || self.candidate_fns.contains(&decl_id)
+            || self.static_cstr_fns.contains(&decl_id)
+            || attrs.contains(&c_ast::Attribute::NoReturn)
+        {
+            return None;
+        }
+        match self.ast_context.resolve_type(typ).kind {
+            CTypeKind::Function(ret, _, false, false, _) => {
+                let ret = &self.ast_context.resolve_type(ret.ctype).kind;
+                if !(*ret == CTypeKind::Void || is_number(ret)) {
+                    return None;
+                }
+            }
+            _ => return None,
+        }
+
+        // Pointer parameters and whether they are `restrict`
+        let mut pointers = HashMap::new();
+        let mut numbers = HashSet::new();
+        for &param_id in parameters {
+            let (ident, typ) = match self.ast_context[param_id].kind {
+                CDeclKind::Variable { ref ident, typ, .. } => (ident, typ),
+                _ => return None,
+            };
+            if ident.is_empty() {
+                return None;
+            }
+            match self.ast_context.resolve_type(typ.ctype).kind {
+                CTypeKind::Pointer(pointee)
+                    if !pointee.qualifiers.is_volatile
+                        && is_number(&self.ast_context.resolve_type(pointee.ctype).kind) =>
+                {
+                    pointers.insert(param_id, typ.qualifiers.is_restrict);
+                }
+                ref kind if is_number(kind) => {
+                    numbers.insert(param_id);
+                }
+                _ => return None,
+            }
+        }
+        if pointers.is_empty() {
+            return None;
+        }
+
+        let mut locals = HashSet::new();
+        let mut loops = vec![];
+        let mut subscripts = vec![];
+        let mut rvalues = HashSet::new();
+        let mut counter_writes = vec![];
+        let mut refs = vec![];
+        for node in DFExpr::new(&self.ast_context, body.into()) {
+            match node {
+                SomeId::Stmt(stmt_id) => match self.ast_context[stmt_id].kind {
+                    CStmtKind::ForLoop {
+                        init: Some(init),
+                        condition: Some(cond),
+                        increment: Some(inc),
+                        body,
+                    } => loops.extend(self.counted_loop(init, cond, inc, body)),
+                    CStmtKind::Decls(ref decls) => {
+                        for &local_id in decls {
+                            match self.ast_context[local_id].kind {
+                                CDeclKind::Variable {
+                                    has_static_duration: false,
+                                    has_thread_duration: false,
+                                    is_externally_visible: false,
+                                    typ,
+                                    ..
+                                } if is_number(&self.ast_context.resolve_type(typ.ctype).kind) => {
+                                    locals.insert(local_id);
+                                }
+                                _ => return None,
+                            }
+                        }
+                    }
+                    CStmtKind::Asm { .. } => return None,
+                    _ => {}
+                },
+                SomeId::Expr(expr_id) => match self.ast_context[expr_id].kind {
+                    CExprKind::Literal(..)
+                    | CExprKind::UnaryType(..)
+                    | CExprKind::ExplicitCast(..)
+                    | CExprKind::ConstantExpr(..)
+                    | CExprKind::Conditional(..)
+                    | CExprKind::Paren(..) => {}
+                    CExprKind::ImplicitCast(_, e, kind, _, _) => {
+                        if kind == CastKind::LValueToRValue {
+                            rvalues.insert(e);
+                        }
+                    }
+                    CExprKind::Unary(_, op, arg, _) => match op {
+                        c_ast::UnOp::AddressOf | c_ast::UnOp::Deref => return None,
+                        c_ast::UnOp::PreIncrement
+                        | c_ast::UnOp::PostIncrement
+                        | c_ast::UnOp::PreDecrement
+                        | c_ast::UnOp::PostDecrement => counter_writes.push((expr_id, arg)),
+                        _ => {}
+                    },
+                    CExprKind::Binary(_, op, lhs, _, _, _) => {
+                        if op == c_ast::BinOp::Assign || op.underlying_assignment().is_some() {
+                            counter_writes.push((expr_id, lhs));
+                        }
+                    }
+                    CExprKind::ArraySubscript(_, lhs, rhs, _) => {
+                        subscripts.push((expr_id, lhs, rhs))
+                    }
+                    CExprKind::DeclRef(_, ref_id, _) => refs.push(ref_id),
+                    _ => return None,
+                },
+                _ => {}
+            }
+        }
+
+        // Only parameters, locals and enum constants may be referenced
+        if !refs.iter().all(|ref_id| {
+            parameters.contains(ref_id)
+                || locals.contains(ref_id)
+                || matches!(
+                    self.ast_context[*ref_id].kind,
+                    CDeclKind::EnumConstant { .. }
+                )
+        }) {
+            return None;
+        }
+
+        // The loops over the slices must all be bounded by the same parameter, and their
+        // counters must only be assigned in the loop headers
+        loops.retain(|l| numbers.contains(&l.len) && locals.contains(&l.counter));
+        let len = loops.first()?.len;
+        if loops.iter().any(|l| l.len != len) {
+            return None;
+        }
+        let counters = loops.iter().map(|l| l.counter).collect::<HashSet<_>>();
+        let header_writes = loops
+            .iter()
+            .flat_map(|l| l.writes.iter().copied())
+            .collect::<HashSet<_>>();
+        if counter_writes.iter().any(|&(write, target)| {
+            self.local_ref(target)
+                .map_or(false, |decl_id| counters.contains(&decl_id))
+                && !header_writes.contains(&write)
+        }) {
+            return None;
+        }
+
+        // Every use of a pointer must index it by the counter of a loop containing the access
+        let mut uses: HashMap<CDeclId, usize> = HashMap::new();
+        for ref_id in refs {
+            *uses.entry(ref_id).or_default() += 1;
+        }
+        let mut supported: HashMap<CDeclId, usize> = HashMap::new();
+        let mut written = HashSet::new();
+        for (expr_id, lhs, rhs) in subscripts {
+            let (ptr, index) = match (self.local_ref(lhs), self.local_ref(rhs)) {
+                (Some(ptr), Some(index)) if pointers.contains_key(&ptr) => (ptr, index),
+                (Some(index), Some(ptr)) if pointers.contains_key(&ptr) => (ptr, index),
+                _ => return None,
+            };
+            let in_loop = loops
+                .iter()
+                .any(|l| l.counter == index && self.stmt_contains_expr(l.body, expr_id));
+            if !in_loop {
+                return None;
+            }
+            *supported.entry(ptr).or_default() += 1;
+            if !rvalues.contains(&expr_id) {
+                written.insert(ptr);
+            }
+        }
+        *supported.entry(len).or_default() += loops.len();
+        if pointers.keys().chain(Some(&len)).any(|decl_id| {
+            uses.get(decl_id).is_none() || uses.get(decl_id) != supported.get(decl_id)
+        }) {
+            return None;
+        }
+
+        // Slices written through are `&mut`, so they must not alias the other slices
+        if written.iter().any(|ptr| !pointers[ptr]) {
+            return None;
+        }
+
+        let slices = parameters
+            .iter()
+            .filter(|param_id| pointers.contains_key(param_id))
+            .map(|&param_id| (param_id, written.contains(&param_id)))
+            .collect();
+        Some(SliceFn { len, slices })
+    }
+
+    /// Recognize `for (i = 0; i < n; i++)`
+    fn counted_loop(
+        &self,
+        init: CStmtId,
+        cond: CExprId,
+        inc: CExprId,
+        body: CStmtId,
+    ) -> Option<CountedLoop> {
+        let is_zero = |expr_id: CExprId| {
+            matches!(
+                self.ast_context.resolve_expr(expr_id).1,
+                CExprKind::Literal(_, CLiteral::Integer(0, _))
+            )
+        };
+
+        let (counter, len) = match self.ast_context[cond].kind {
+            CExprKind::Binary(_, c_ast::BinOp::Less, lhs, rhs, _, _) => {
+                (self.local_ref(lhs)?, self.local_ref(rhs)?)
+            }
+            _ => return None,
+        };
+
+        let mut writes = vec![];
+        match self.ast_context[init].kind {
+            CStmtKind::Decls(ref decls) if decls[..] == [counter] => {
+                match self.ast_context[counter].kind {
+                    CDeclKind::Variable {
+                        initializer: Some(initializer),
+                        ..
+                    } if is_zero(initializer) => {}
+                    _ => return None,
+                }
+            }
+            CStmtKind::Expr(expr_id) => match self.ast_context[expr_id].kind {
+                CExprKind::Binary(_, c_ast::BinOp::Assign, lhs, rhs, _, _)
+                    if self.local_ref(lhs) == Some(counter) && is_zero(rhs) =>
+                {
+                    writes.push(expr_id)
+                }
+                _ => return None,
+            },
+            _ => return None,
+        }
+
+        match self.ast_context[inc].kind {
+            CExprKind::Unary(_, c_ast::UnOp::PreIncrement | c_ast::UnOp::PostIncrement, arg, _)
+                if self.local_ref(arg) == Some(counter) =>
+            {
+                writes.push(inc)
+            }
+            _ => return None,
+        }
+
+        Some(CountedLoop {
+            counter,
+            len,
+            writes,
+            body,
+        })
+    }
+
+    fn stmt_contains_expr(&self, stmt_id: CStmtId, expr_id: CExprId) -> bool {
+        DFExpr::new(&self.ast_context, stmt_id.into()).any(|node| node == SomeId::Expr(expr_id))
+    }
+
+    /// Translate a function selected by `--slice-fns` into its slice-based version and a C entry
+    /// point wrapping it. If the C function can't be wrapped, it is emitted alone.
+    pub fn convert_slice_fn(
+        &self,
+        ctx: ExprContext,
+        decl_id: CDeclId,
+    ) -> Result<ConvertedDecl, TranslationError> {
+        let mut wrapper = match self.convert_decl(ctx, decl_id)? {
+            ConvertedDecl::Item(item) => item,
+            converted => return Ok(converted),
+        };
+        let param_names = match *wrapper {
+            Item::Fn(ref f) => f
+                .sig
+                .inputs
+                .iter()
+                .map(|arg| match arg {
+                    FnArg::Typed(PatType { pat, .. }) => match **pat {
+                        Pat::Ident(ref pat) => Some(pat.ident.to_string()),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>(),
+            _ => None,
+        };
+        let param_names = match param_names {
+            Some(names) => names,
+            None => return Ok(ConvertedDecl::Item(wrapper)),
+        };
+
+        let (is_global, name, parameters, body, ret) = match self.ast_context[decl_id].kind {
+            CDeclKind::Function {
+                is_global,
+                ref name,
+                ref parameters,
+                body: Some(body),
+                typ,
+                ..
+            } => match self.ast_context.resolve_type(typ).kind {
+                CTypeKind::Function(ret, ..) => (is_global, name, parameters, body, ret),
+                _ => return Ok(ConvertedDecl::Item(wrapper)),
+            },
+            _ => return Ok(ConvertedDecl::Item(wrapper)),
+        };
+        let slice_fn = &self.slice_fns[&decl_id];
+        let wrapper_name = self
+            .renamer
+            .borrow()
+            .get(&decl_id)
+            .expect("Functions should already be renamed");
+        let slice_name = self
+            .renamer
+            .borrow_mut()
+            .pick_name(&format!("{}_slice", wrapper_name));
+
+        // The safe version, with the length taken from the slices
+        self.function_context.borrow_mut().enter_new(name);
+        self.cur_slice_fn.set(Some(decl_id));
+        let slice_item = self.with_scope(|| {
+            let mut args = vec![];
+            let mut len = None;
+            let mut first_slice = None;
+            for &param_id in parameters {
+                let (ident, typ) = match self.ast_context[param_id].kind {
+                    CDeclKind::Variable { ref ident, typ, .. } => (ident, typ),
+                    _ => {
+                        return Err(TranslationError::generic(
+                            "Parameter is not variable declaration",
+                        ))
+                    }
+                };
+                let rust_name = self
+                    .renamer
+                    .borrow_mut()
+                    .insert(param_id, ident)
+                    .ok_or_else(|| format_err!("Failed to insert argument '{}'", ident))?;
+                if param_id == slice_fn.len {
+                    len = Some((rust_name, self.convert_type(typ.ctype)?));
+                    continue;
+                }
+                match slice_fn.slices.iter().find(|&&(id, _)| id == param_id) {
+                    Some(&(_, written)) => {
+                        let elem = match self.ast_context.resolve_type(typ.ctype).kind {
+                            CTypeKind::Pointer(pointee) => pointee.ctype,
+                            _ => return Err(format_err!("Slice parameter is not a pointer").into()),
+                        };
+                        let mutbl = if written {
+                            Mutability::Mutable
+                        } else {
+                            Mutability::Immutable
+                        };
+                        let ty = mk()
+                            .set_mutbl(mutbl)
+                            .ref_ty(mk().slice_ty(self.convert_type(elem)?));
+                        first_slice.get_or_insert_with(|| rust_name.clone());
+                        args.push(mk().arg(ty, mk().ident_pat(rust_name)));
+                    }
+                    None => {
+                        let (ty, mutbl, _) = self.convert_variable(ctx, None, typ)?;
+                        args.push(mk().arg(ty, mk().set_mutbl(mutbl).ident_pat(rust_name)));
+                    }
+                }
+            }
+            let (len_name, len_ty) =
+                len.ok_or_else(|| format_err!("Missing slice length parameter"))?;
+            let first_slice = first_slice.ok_or_else(|| format_err!("Missing slice parameter"))?;
+
+            let slice_len =
+                mk().method_call_expr(mk().ident_expr(first_slice), "len", Vec::<Box<Expr>>::new());
+            let len_local = mk().local(
+                mk().ident_pat(len_name),
+                Some(len_ty.clone()),
+                Some(mk().cast_expr(slice_len, len_ty)),
+            );
+            let mut body_stmts = vec![mk().local_stmt(Box::new(len_local))];
+
+            let is_void_ret = self.ast_context.resolve_type(ret.ctype).kind == CTypeKind::Void;
+            let (ret_ty, implicit_ret) = if is_void_ret {
+                (ReturnType::Default, cfg::ImplicitReturnType::Void)
+            } else {
+                (
+                    ReturnType::Type(Default::default(), self.convert_type(ret.ctype)?),
+                    cfg::ImplicitReturnType::NoImplicitReturnType,
+                )
+            };
+            let body_ids = match self.ast_context[body].kind {
+                CStmtKind::Compound(ref stmts) => stmts,
+                _ => panic!("function body expects to be a compound statement"),
+            };
+            body_stmts.append(&mut self.convert_function_body(
+                ctx,
+                name,
+                body_ids,
+                implicit_ret,
+            )?);
+
+            let decl = mk().fn_decl(&slice_name, args, None, ret_ty);
+            let mk_ = if is_global || self.cur_file.borrow().is_some() {
+                mk().pub_()
+            } else {
+                mk()
+            };
+            Ok(mk_
+                .single_attr("inline")
+                .fn_item(decl, stmts_block(body_stmts)))
+        });
+        self.cur_slice_fn.set(None);
+        let slice_item = match slice_item {
+            Ok(item) => item,
+            Err(e) => {
+                warn!("Failed to translate slice version of {}: {}", name, e);
+                return Ok(ConvertedDecl::Item(wrapper));
+            }
+        };
+
+        // The C entry point, building the slices from the pointers and length
+        let len_name = &param_names[parameters
+            .iter()
+            .position(|&id| id == slice_fn.len)
+            .unwrap()];
+        let slice_len = mk().cast_expr(
+            mk().method_call_expr(
+                mk().ident_expr(len_name),
+                "max",
+                vec![mk().lit_expr(mk().int_unsuffixed_lit(0))],
+            ),
+            mk().path_ty(vec!["usize"]),
+        );
+        let mut args = vec![];
+        for (&param_id, param_name) in parameters.iter().zip(&param_names) {
+            if param_id == slice_fn.len {
+                continue;
+            }
+            let ptr = mk().ident_expr(param_name);
+            let arg = match slice_fn.slices.iter().find(|&&(id, _)| id == param_id) {
+                Some(&(_, written)) => {
+                    let (mutbl, from_raw_parts) = if written {
+                        (Mutability::Mutable, "from_raw_parts_mut")
+                    } else {
+                        (Mutability::Immutable, "from_raw_parts")
+                    };
+                    let is_null =
+                        mk().method_call_expr(ptr.clone(), "is_null", Vec::<Box<Expr>>::new());
+                    let empty = mk()
+                        .set_mutbl(mutbl)
+                        .addr_of_expr(mk().array_expr(Vec::<Box<Expr>>::new()));
+                    let slice = mk().call_expr(
+                        mk().abs_path_expr(vec!["core", "slice", from_raw_parts]),
+                        vec![ptr, slice_len.clone()],
+                    );
+                    mk().ifte_expr(
+                        is_null,
+                        mk().block(vec![mk().expr_stmt(empty)]),
+                        Some(mk().block_expr(mk().block(vec![mk().expr_stmt(slice)]))),
+                    )
+                }
+                None => ptr,
+            };
+            args.push(arg);
+        }
+        let call = mk().call_expr(mk().ident_expr(slice_name), args);
+        if let Item::Fn(ref mut f) = *wrapper {
+            f.block = mk().block(vec![mk().expr_stmt(call)]);
+        }
+
+        Ok(ConvertedDecl::Items(vec![wrapper, slice_item]))
+    }
+
+    /// Translate `p[index]` for a slice `p` in the slice-based version of a function
+    pub fn convert_slice_index(
+        &self,
+        ctx: ExprContext,
+        ptr: CExprId,
+        index: CExprId,
+    ) -> Result<Option<WithStmts<Box<Expr>>>, TranslationError> {
+        let slice_fn = match self.cur_slice_fn.get() {
+            Some(decl_id) => &self.slice_fns[&decl_id],
+            None => return Ok(None),
+        };
+        let decl_id = match self.local_ref(ptr) {
+            Some(decl_id) if slice_fn.slices.iter().any(|&(id, _)| id == decl_id) => decl_id,
+            _ => return Ok(None),
+        };
+        let name = self
+            .renamer
+            .borrow()
+            .get(&decl_id)
+            .ok_or_else(|| format_err!("Slice parameter is not named"))?;
+        let index = self.convert_expr(ctx.used(), index)?;
+        Ok(Some(index.map(|index| {
+            mk().index_expr(
+                mk().ident_expr(name),
+                mk().cast_expr(index, mk().path_ty(vec!["usize"])),
+            )
+        })))
+    }
+}
+
+/// Whether values of the type can be elements of the slices or the other parameters
+fn is_number(kind: &CTypeKind) -> bool {
+    kind.is_integral_type() || kind.is_floating_type()
+}
@@ -0,0 +1,215 @@
+//! This module implements `--static-cstr-returns`, which translates `static` functions returning
+//! `const char *` that only ever return string literals into functions returning
+//! `&'static CStr`:
+//!
+//! ```c
+//! static const char *level_name(int level) {
+//!     return level > 0 ? "error" : "info";
+//! }
+//! ```
+//!
+//! becomes
+//!
+//! ```ignore
+//! unsafe fn level_name(mut level: c_int) -> &'static ::std::ffi::CStr {
+//!     return if level > 0 {
+//!         ::std::ffi::CStr::from_bytes_with_nul_unchecked(b"error\0")
+//!     } else {
+//!         ::std::ffi::CStr::from_bytes_with_nul_unchecked(b"info\0")
+//!     };
+//! }
+//! ```
+//!
+//! Returned values may also be conditionals over such values and calls to other such functions.
+//! Call sites convert the result back into a pointer with `.as_ptr()`. `&CStr` is not FFI-safe,
+//! so these functions lose their `extern "C"` ABI; functions whose address is taken are left
+//! alone for that reason.
+
+use super::*;
+
+impl<'c> Translation<'c> {
+    /// Find the functions selected by `--static-cstr-returns` along with the expressions they
+    /// return, which are translated by `convert_static_cstr`.
+    pub fn find_static_cstr_fns(&self) -> (HashSet<CDeclId>, HashSet<CExprId>) {
+        if !self.tcfg.static_cstr_returns {
+            return (HashSet::new(), HashSet::new());
+        }
+
+        let mut candidates = IndexMap::new();
+        for (&decl_id, decl) in self.ast_context.iter_decls() {
+            let (typ, body) = match decl.kind {
+                CDeclKind::Function {
+                    is_global: false,
+                    typ,
+                    body: Some(body),
+                    ..
+                } => (typ, body),
+                _ => continue,
+            };
+            match self.ast_context.resolve_type(typ).kind {
+                CTypeKind::Function(ret, _, false, _, _) if self.is_const_char_ptr(ret.ctype) => {}
+                _ => continue,
+            }
+
+            let mut returns = Some(vec![]);
+            for node in DFExpr::new(&self.ast_context, SomeId::Stmt(body)) {
+                if let Some(stmt_id) = node.stmt() {
+                    if let CStmtKind::Return(expr) = self.ast_context[stmt_id].kind {
+                        match (expr, returns.as_mut()) {
+                            (Some(expr), Some(returns)) => returns.push(expr),
+                            _ => returns = None,
+                        }
+                    }
+                }
+            }
+            if let Some(returns) = returns {
+                candidates.insert(decl_id, returns);
+            }
+        }
+        if candidates.is_empty() {
+            return (HashSet::new(), HashSet::new());
+        }
+
+        // Functions used other than by calling them directly must keep their C signature
+        let mut callees = HashSet::new();
+        for (_, expr) in self.ast_context.iter_exprs() {
+            if let CExprKind::Call(_, func, _) = expr.kind {
+                if let CExprKind::ImplicitCast(_, callee, CastKind::FunctionToPointerDecay, _, _) =
+                    self.ast_context[func].kind
+                {
+                    callees.insert(callee);
+                }
+            }
+        }
+        for (&expr_id, expr) in self.ast_context.iter_exprs() {
+            if let CExprKind::DeclRef(_, decl_id, _) = expr.kind {
+                if !callees.contains(&expr_id) {
+                    candidates.remove(&decl_id);
+                }
+            }
+        }
+
+        // Returning the result of another candidate only works if that one qualifies too
+        loop {
+            let before = candidates.len();
+            let rejected = candidates
+                .iter()
+                .filter(|(_, returns)| {
+                    !returns
+                        .iter()
+                        .all(|&expr| self.is_static_cstr(&candidates, expr))
+                })
+                .map(|(&decl_id, _)| decl_id)
+                .collect::<Vec<_>>();
+            for decl_id in rejected {
+                candidates.remove(&decl_id);
+            }
+            if candidates.len() == before {
+                break;
+            }
+        }
+
+        let returns = candidates.values().flatten().copied().collect();
+        (candidates.into_keys().collect(), returns)
+    }
+
+    fn is_const_char_ptr(&self, typ: CTypeId) -> bool {
+        match self.ast_context.resolve_type(typ).kind {
+            CTypeKind::Pointer(pointee) => {
+                pointee.qualifiers.is_const
+                    && self.ast_context.resolve_type(pointee.ctype).kind == CTypeKind::Char
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether an expression always evaluates to a nul-terminated string literal without
+    /// interior nul bytes.
+    fn is_static_cstr(&self, candidates: &IndexMap<CDeclId, Vec<CExprId>>, expr: CExprId) -> bool {
+        match *self.ast_context.resolve_expr(expr).1 {
+            CExprKind::Literal(_, CLiteral::String(ref bytes, 1)) => !bytes.contains(&0),
+            CExprKind::Conditional(_, _, lhs, rhs) => {
+                self.is_static_cstr(candidates, lhs) && self.is_static_cstr(candidates, rhs)
+            }
+            CExprKind::Call(_, func, _) => match *self.ast_context.resolve_expr(func).1 {
+                CExprKind::DeclRef(_, decl_id, _) => candidates.contains_key(&decl_id),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether `decl_id` was selected by `--static-cstr-returns`.
+    pub fn returns_static_cstr(&self, decl_id: CDeclId) -> bool {
+        self.static_cstr_fns.contains(&decl_id)
+    }
+
+    /// The `&'static CStr` return type of the functions selected by `--static-cstr-returns`.
+    pub fn static_cstr_ty(&self) -> Box<Type> {
+        let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+        let cstr_ty = mk().path_ty(mk().abs_path(vec![std_or_core, "ffi", "CStr"]));
+        mk().ref_lt_ty("static", cstr_ty)
+    }
+
+    /// Convert the value of a `return` statement, which is a `&'static CStr` if the function was
+    /// selected by `--static-cstr-returns`, and a plain function pointer if the function returns
+    /// a non-null one.
+    pub fn convert_return_value(
+        &self,
+        ctx: ExprContext,
+        expr_id: CExprId,
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        if self.static_cstr_returns.contains(&expr_id) {
+            self.convert_static_cstr(ctx, expr_id)
+        } else if self.function_context.borrow().returns_nonnull_fn_ptr {
+            Ok(self
+                .convert_expr(ctx, expr_id)?
+                .map(unwrap_function_pointer))
+        } else {
+            self.convert_expr(ctx, expr_id)
+        }
+    }
+
+    fn convert_static_cstr(
+        &self,
+        ctx: ExprContext,
+        expr_id: CExprId,
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        let (expr_id, kind) = self.ast_context.resolve_expr(expr_id);
+        match *kind {
+            CExprKind::Literal(_, CLiteral::String(ref bytes, _)) => {
+                let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+                let mut bytes = bytes.clone();
+                bytes.push(0);
+                let from_bytes = mk().abs_path_expr(vec![
+                    std_or_core,
+                    "ffi",
+                    "CStr",
+                    "from_bytes_with_nul_unchecked",
+                ]);
+                Ok(WithStmts::new_unsafe_val(
+                    mk().call_expr(from_bytes, vec![mk().lit_expr(bytes)]),
+                ))
+            }
+            CExprKind::Conditional(_, cond, lhs, rhs) => {
+                let cond = self.convert_condition(ctx, true, cond)?;
+                let lhs = self.convert_static_cstr(ctx, lhs)?;
+                let rhs = self.convert_static_cstr(ctx, rhs)?;
+                let is_unsafe = lhs.is_unsafe() || rhs.is_unsafe();
+                let mut val = cond.map(|c| mk().ifte_expr(c, lhs.to_block(), Some(rhs.to_expr())));
+                val.merge_unsafe(is_unsafe);
+                Ok(val)
+            }
+            CExprKind::Call(..) => {
+                // The call converts the returned `&CStr` into a pointer, which we don't want here
+                Ok(self.convert_expr(ctx, expr_id)?.map(|call| match *call {
+                    Expr::MethodCall(ExprMethodCall {
+                        receiver, method, ..
+                    }) if method == "as_ptr" => receiver,
+                    call => Box::new(call),
+                }))
+            }
+            _ => Err(format_err!("Expression {:?} is not a static C string", expr_id).into()),
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! This module implements `--derive` and `--struct-derive`, which add `Debug`, `Default` and
+//! `PartialEq` to the `Copy` and `Clone` that translated structs derive, for all structs or for
+//! the named ones:
+//!
+//! ```sh
+//! c2rust transpile --derive Debug --struct-derive point=Default,PartialEq ...
+//! ```
+//!
+//! A trait is derived only if the types of all the fields implement it, so structs containing
+//! unions or structs without the trait are left without it. `Default` can't be derived when a
+//! field is a raw pointer or an array of more than 32 elements, so such structs get an `impl
+//! Default` with the zero initializer used for C's implicit initialization instead:
+//!
+//! ```ignore
+//! #[derive(Copy, Clone, PartialEq)]
+//! #[repr(C)]
+//! pub struct node {
+//!     pub value: libc::c_int,
+//!     pub next: *mut node,
+//! }
+//! impl Default for node {
+//!     fn default() -> Self {
+//!         node { value: 0, next: 0 as *mut node }
+//!     }
+//! }
+//! ```
+//!
+//! The same goes for bitfield and packed structs, whose bitfields and padding are byte arrays.
+//! Structs containing a `va_list` don't derive anything.
+
+use super::*;
+
+/// A trait that can be requested for translated structs
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum StructDerive {
+    Debug,
+    Default,
+    PartialEq,
+}
+
+impl StructDerive {
+    fn name(self) -> &'static str {
+        match self {
+            StructDerive::Debug => "Debug",
+            StructDerive::Default => "Default",
+            StructDerive::PartialEq => "PartialEq",
+        }
+    }
+}
+
+const STRUCT_DERIVES: [StructDerive; 3] = [
+    StructDerive::Debug,
+    StructDerive::Default,
+    StructDerive::PartialEq,
+];
+
+impl<'c> Translation<'c> {
+    /// Whether `derive` was requested for the struct `decl_id`, by its tag or a typedef name
+    fn struct_derive_requested(&self, decl_id: CRecordId, derive: StructDerive) -> bool {
+        let requested = |names: &[String]| names.iter().any(|name| name == derive.name());
+        if requested(&self.tcfg.derives) {
+            return true;
+        }
+        if self.tcfg.struct_derives.is_empty() {
+            return false;
+        }
+        let requested_for = |name: &String| {
+            self.tcfg
+                .struct_derives
+                .get(name)
+                .map_or(false, |names| requested(names))
+        };
+        if let CDeclKind::Struct {
+            name: Some(ref name),
+            ..
+        } = self.ast_context[decl_id].kind
+        {
+            if requested_for(name) {
+                return true;
+            }
+        }
+        self.ast_context
+            .iter_decls()
+            .any(|(_, decl)| match decl.kind {
+                CDeclKind::Typedef { ref name, typ, .. } => {
+                    requested_for(name)
+                        && self.ast_context.resolve_type(typ.ctype).kind
+                            == CTypeKind::Struct(decl_id)
+                }
+                _ => false,
+            })
+    }
+
+    /// Whether the translation of the struct `decl_id` implements `derive`, by deriving it or,
+    /// for `Default`, with a manual impl
+    fn struct_implements(&self, decl_id: CRecordId, derive: StructDerive) -> bool {
+        if self.tagged_unions.contains_key(&decl_id)
+            || !self.struct_derive_requested(decl_id, derive)
+        {
+            return false;
+        }
+        match self.ast_context[decl_id].kind {
+            CDeclKind::Struct {
+                fields: Some(ref fields),
+                ..
+            } => {
+                derive == StructDerive::Default
+                    || fields
+                        .iter()
+                        .all(|&field_id| self.field_implements(field_id, derive))
+            }
+            _ => false,
+        }
+    }
+
+    fn field_implements(&self, field_id: CFieldId, derive: StructDerive) -> bool {
+        match self.ast_context[field_id].kind {
+            CDeclKind::Field { typ, .. } => self.type_implements(typ.ctype, derive),
+            _ => false,
+        }
+    }
+
+    /// Whether the translation of `ctype` implements `derive`
+    fn type_implements(&self, ctype: CTypeId, derive: StructDerive) -> bool {
+        if self.ast_context.is_va_list(ctype) {
+            return false;
+        }
+        let is_default = derive == StructDerive::Default;
+        match self.ast_context.resolve_type(ctype).kind {
+            CTypeKind::Enum(_) => !is_default || self.tcfg.enum_style == EnumStyle::Consts,
+            CTypeKind::LongDouble | CTypeKind::Half | CTypeKind::BFloat16 => !is_default,
+            ref kind if kind.is_bool() || kind.is_integral_type() || kind.is_floating_type() => {
+                true
+            }
+            CTypeKind::Pointer(_) => {
+                // Only nullable function pointers are `Option`s
+                !is_default
+                    || (self.ast_context.is_function_pointer(ctype)
+                        && !self.ast_context.is_nonnull_fn_pointer(ctype))
+            }
+            CTypeKind::ConstantArray(elem, len) => {
+                (!is_default || len <= 32) && self.type_implements(elem, derive)
+            }
+            CTypeKind::IncompleteArray(elem) => self.type_implements(elem, derive),
+            CTypeKind::Struct(decl_id) => self.struct_implements(decl_id, derive),
+            _ => false,
+        }
+    }
+
+    /// The traits to derive for the struct `decl_id` besides `Copy` and `Clone`, and whether it
+    /// needs a manual `Default` impl
+    pub fn struct_derives(&self, decl_id: CRecordId) -> (Vec<&'static str>, bool) {
+        if self.tcfg.derives.is_empty() && self.tcfg.struct_derives.is_empty() {
+            return (vec![], false);
+        }
+        let (fields, is_packed) = match self.ast_context[decl_id].kind {
+            CDeclKind::Struct {
+                fields: Some(ref fields),
+                is_packed,
+                max_field_alignment,
+                ..
+            } => (fields, is_packed || max_field_alignment.is_some()),
+            _ => return (vec![], false),
+        };
+        // Bitfields and the padding of packed structs are byte arrays, which may be too long to
+        // implement `Default`
+        let has_byte_arrays = is_packed
+            || fields.iter().any(|&field_id| {
+                matches!(
+                    self.ast_context[field_id].kind,
+                    CDeclKind::Field {
+                        bitfield_width: Some(_),
+                        ..
+                    }
+                )
+            });
+
+        let mut derives = vec![];
+        let mut default_impl = false;
+        for derive in STRUCT_DERIVES {
+            if !self.struct_implements(decl_id, derive) {
+                continue;
+            }
+            let derivable = fields
+                .iter()
+                .all(|&field_id| self.field_implements(field_id, derive));
+            if derivable && !(derive == StructDerive::Default && has_byte_arrays) {
+                derives.push(derive.name());
+            } else {
+                default_impl = true;
+            }
+        }
+        (derives, default_impl)
+    }
+
+    /// The `impl Default` of the struct `decl_id`, translated as `name`, returning its zero
+    /// initializer
+    pub fn struct_default_impl(
+        &self,
+        decl_id: CRecordId,
+        name: &str,
+    ) -> Result<Box<Item>, TranslationError> {
+        let (fields, platform_byte_size) = match self.ast_context[decl_id].kind {
+            CDeclKind::Struct {
+                fields: Some(ref fields),
+                platform_byte_size,
+                ..
+            } => (fields, platform_byte_size),
+            _ => return Err(format_err!("Default impl of a non-struct").into()),
+        };
+        let init = self.convert_struct_zero_initializer(
+            name.to_owned(),
+            decl_id,
+            fields,
+            platform_byte_size,
+            false,
+        )?;
+        let is_unsafe = init.is_unsafe();
+        let (mut stmts, val) = init.discard_unsafe();
+        stmts.push(mk().expr_stmt(val));
+        let block = if is_unsafe {
+            let unsafe_block = mk().unsafe_block_expr(mk().unsafe_block(stmts));
+            mk().block(vec![mk().expr_stmt(unsafe_block)])
+        } else {
+            mk().block(stmts)
+        };
+
+        let default_fn = mk().fn_impl_item(
+            mk().fn_decl(
+                "default",
+                vec![],
+                None,
+                ReturnType::Type(Default::default(), mk().path_ty(vec!["Self"])),
+            ),
+            block,
+        );
+        Ok(mk().trait_impl_item(
+            mk().path("Default"),
+            mk().path_ty(vec![name]),
+            vec![default_fn],
+        ))
+    }
+}
@@ -7,7 +7,9 @@ use std::ops::Index;
 
 use super::TranslationError;
 use crate::c_ast::{BinOp, CDeclId, CDeclKind, CExprId, CRecordId, CTypeId};
-use crate::translator::{ExprContext, Translation, PADDING_SUFFIX};
+use crate::translator::{
+    unwrap_function_pointer, BitfieldOrder, ExprContext, Translation, PADDING_SUFFIX,
+};
 use crate::with_stmts::WithStmts;
 use c2rust_ast_builder::mk;
 use c2rust_ast_printer::pprust;
@@ -75,7 +77,34 @@ fn assignment_metaitem(lhs: &str, rhs: &str) -> NestedMeta {
     NestedMeta::Meta(meta_item)
 }
 
+/// Whether bitfields are allocated most significant bit first on `target`, which is the case
+/// for big endian targets
+fn is_msb0_target(target: &str) -> bool {
+    let arch = target.split('-').next().unwrap_or("");
+    match arch {
+        "powerpc" | "powerpc64" | "s390x" | "sparc" | "sparc64" | "sparcv9" | "m68k" | "armeb"
+        | "armebv7r" | "thumbeb" | "aarch64_be" | "mips" | "mips64" | "mipsisa32r6"
+        | "mipsisa64r6" => true,
+        _ => false,
+    }
+}
+
 impl<'a> Translation<'a> {
+    /// The struct attribute selecting the bit order of bitfield structs, if it isn't the
+    /// default least significant bit first order
+    pub fn bitfield_order_attr(&self) -> Option<Meta> {
+        let msb0 = match self.tcfg.bitfield_order {
+            BitfieldOrder::Target => is_msb0_target(&self.ast_context.target),
+            BitfieldOrder::Lsb0 => false,
+            BitfieldOrder::Msb0 => true,
+        };
+        if !msb0 {
+            return None;
+        }
+
+        Some(mk().meta_list("bitfield", vec![assignment_metaitem("order", "msb0")]))
+    }
+
     /// This method aggregates bitfield struct field information by way of:
     /// 1. Collecting consecutive bytes of bitfields into a single FieldType::BitfieldGroup
     /// 2. Summing up the number of padding bytes between fields (or at the end of a struct)
@@ -549,8 +578,11 @@ impl<'a> Translation<'a> {
                     let field = init.map(|init| mk().field(field_name, init));
                     fields.push(field);
                 }
-                Both(field_id, (field_name, _, bitfield_width, use_inner_type)) => {
+                Both(field_id, (field_name, ty, bitfield_width, use_inner_type)) => {
                     let mut expr = self.convert_expr(ctx.used(), *field_id)?;
+                    if self.ast_context.is_nonnull_fn_pointer(ty.ctype) {
+                        expr = expr.map(unwrap_function_pointer);
+                    }
 
                     if !expr.is_pure() {
                         return Err(TranslationError::generic(
@@ -0,0 +1,161 @@
+//! Recovery from function definitions that fail to translate. Leaving such a function out of its
+//! module would break all of its callers, so it is replaced with a stub of the same signature
+//! that panics, following the original C in a comment:
+//!
+//! ```ignore
+//! // Failed to translate inflate_fast: Unimplemented builtin __builtin_ia32_pause
+//! // void inflate_fast(z_streamp strm, unsigned start) {
+//! //     ...
+//! // }
+//! #[cfg(not(c2rust_extern_fallback))]
+//! #[no_mangle]
+//! #[allow(unused_variables)]
+//! pub unsafe extern "C" fn inflate_fast(mut strm: z_streamp, mut start: c_uint) {
+//!     unimplemented!("inflate_fast: Unimplemented builtin __builtin_ia32_pause")
+//! }
+//! #[cfg(c2rust_extern_fallback)]
+//! extern "C" {
+//!     fn inflate_fast(strm: z_streamp, start: c_uint);
+//! }
+//! ```
+//!
+//! Functions with a symbol to link to are also declared extern under the `c2rust_extern_fallback`
+//! cfg flag, so building with `RUSTFLAGS="--cfg c2rust_extern_fallback"` links them to their C
+//! definitions instead. The declarations of a file that failed to translate are summarized once
+//! the whole file is translated.
+
+use super::*;
+
+/// The `cfg` flag selecting the extern declarations of functions that failed to translate
+const EXTERN_FALLBACK_CFG: &str = "c2rust_extern_fallback";
+
+/// What the function being translated is replaced with after it failed to translate
+pub enum FnStub {
+    /// A definition panicking with this message
+    Unimplemented(String),
+    /// A declaration of its C definition
+    Extern,
+}
+
+/// A top-level declaration that failed to translate
+pub struct Failure {
+    /// The name or location of the declaration
+    pub decl: String,
+    pub error: String,
+    /// Whether the declaration was replaced with a stub
+    pub stubbed: bool,
+}
+
+impl<'c> Translation<'c> {
+    /// Replace the function definition `decl_id`, which failed to translate with `error`, with a
+    /// stub and its extern fallback. Functions whose signatures don't translate either, and
+    /// other declarations, are left out.
+    pub fn convert_fn_stub(
+        &self,
+        ctx: ExprContext,
+        decl_id: CDeclId,
+        error: &TranslationError,
+    ) -> Option<ConvertedDecl> {
+        let decl = &self.ast_context[decl_id];
+        let (name, linkable) = match decl.kind {
+            CDeclKind::Function {
+                ref name,
+                body: Some(_),
+                is_global,
+                is_inline,
+                ..
+            } => (name, is_global && !is_inline && self.ast_context.c_main != Some(decl_id)),
+            _ => return None,
+        };
+        let error = error.to_string();
+        let error = error.lines().next().unwrap_or_default();
+
+        let message = format!("{}: {}", name, error);
+        self.fn_stub.replace(Some(FnStub::Unimplemented(message)));
+        let stub = self.convert_decl(ctx, decl_id);
+        self.fn_stub.replace(Some(FnStub::Extern));
+        let fallback = if linkable {
+            self.convert_decl(ctx, decl_id).ok()
+        } else {
+            None
+        };
+        self.fn_stub.replace(None);
+
+        let mut stub = match stub {
+            Ok(ConvertedDecl::Item(stub)) => stub,
+            _ => return None,
+        };
+        let mut comment = vec![format!("// Failed to translate {}: {}", name, error)];
+        comment.extend(
+            self.c_source(decl)
+                .into_iter()
+                .flatten()
+                .map(|line| format!("// {}", line)),
+        );
+        let span = stub.span();
+        let pos = Some(span.lo()).filter(|_| !span.is_dummy());
+        if let Some(pos) = self
+            .comment_store
+            .borrow_mut()
+            .extend_existing_comments(&comment, pos)
+        {
+            stub.set_span(pos_to_span(pos));
+        }
+
+        let mut fallback = match fallback {
+            Some(ConvertedDecl::ForeignItem(item)) => {
+                mk().extern_(self.default_abi()).foreign_items(vec![item])
+            }
+            Some(ConvertedDecl::Item(item)) => item,
+            _ => return Some(ConvertedDecl::Item(stub)),
+        };
+        let fallback_cfg = mk().nested_meta_item(mk().meta_path(EXTERN_FALLBACK_CFG));
+        let not_fallback = mk().meta_list("not", vec![fallback_cfg.clone()]);
+        add_cfg_attr(&mut stub, mk().nested_meta_item(not_fallback));
+        add_cfg_attr(&mut fallback, fallback_cfg);
+        Some(ConvertedDecl::Items(vec![stub, fallback]))
+    }
+
+    /// The body of a stub, panicking with `message`
+    pub fn stub_body(&self, message: &str) -> Box<Block> {
+        let message = vec![TokenTree::Literal(proc_macro2::Literal::string(message))];
+        let mac = mk().mac(
+            vec!["unimplemented"],
+            message.into_iter().collect::<TokenStream>(),
+            MacroDelimiter::Paren(Default::default()),
+        );
+        mk().block(vec![mk().expr_stmt(mk().mac_expr(mac))])
+    }
+
+    /// The lines of C source of `decl`, if its file can still be read
+    fn c_source(&self, decl: &CDecl) -> Option<Vec<String>> {
+        let loc = decl.loc.as_ref()?;
+        let path = self.ast_context.get_file_path(self.ast_context.file_id(decl)?)?;
+        let src = std::fs::read_to_string(path).ok()?;
+        let lines = src
+            .lines()
+            .skip(loc.begin_line.checked_sub(1)? as usize)
+            .take((loc.end_line + 1).checked_sub(loc.begin_line)? as usize);
+        Some(lines.map(|line| line.trim_end().to_owned()).collect())
+    }
+}
+
+/// Log a summary of the declarations of `file` that failed to translate
+pub fn summarize_failures(file: &path::Path, failures: &[Failure]) {
+    if failures.is_empty() {
+        return;
+    }
+    warn!(
+        "{} declarations of {} failed to translate:",
+        failures.len(),
+        file.display()
+    );
+    for failure in failures {
+        let recovery = if failure.stubbed {
+            "replaced with a stub"
+        } else {
+            "left out"
+        };
+        warn!("  {} ({}): {}", failure.decl, recovery, failure.error);
+    }
+}
@@ -0,0 +1,1132 @@
+//! This module implements the `--tagged-union` translation of C structs that pair an enum tag
+//! with a union into Rust enums:
+//!
+//! ```c
+//! struct shape {
+//!     enum shape_kind kind;
+//!     union { int radius; float side; } u;
+//! };
+//! ```
+//!
+//! becomes
+//!
+//! ```ignore
+//! #[derive(Copy, Clone)]
+//! pub enum shape {
+//!     CIRCLE(c_int),
+//!     SQUARE(f32),
+//! }
+//! ```
+//!
+//! together with a `kind()` method returning the tag. The union member that goes with each
+//! enumerator is inferred from the C code: from initializers, from assignments to the tag that
+//! are directly followed by an assignment to a union member, and from union members accessed
+//! under a `switch` or `if` on the tag. Member accesses are then rewritten into matches on the
+//! enum, which panic if the active variant does not carry the accessed member. Assigning to the
+//! tag replaces the value with a zero-initialized variant, so the tag must be set before its
+//! union member.
+//!
+//! The translated struct no longer has the C layout, so it must not cross the FFI boundary: structs
+//! used by the signatures of functions defined elsewhere or externally visible, stored in
+//! externally visible variables, or whose bytes are accessed through other pointer types are
+//! reported instead.
+
+use super::*;
+use c2rust_ast_builder::SelfKind;
+
+/// A struct selected by `--tagged-union` that can be translated into a Rust enum.
+#[derive(Debug)]
+pub struct TaggedUnion {
+    /// The field holding the enum tag
+    tag_field: CFieldId,
+    /// The field holding the union
+    union_field: CFieldId,
+    /// The enum type of the tag
+    tag_enum: CEnumId,
+    /// Every enumerator of the tag, together with the union member it carries, if any
+    variants: Vec<(CEnumConstantId, Option<CFieldId>)>,
+}
+
+/// An access to one of the fields of a struct translated into a Rust enum.
+#[derive(Copy, Clone, Debug)]
+enum TaggedUnionAccess {
+    Tag,
+    Union,
+    Payload(CFieldId),
+}
+
+/// A tagged union candidate while its variants are being inferred.
+struct Candidate {
+    tag_field: CFieldId,
+    union_field: CFieldId,
+    tag_enum: CEnumId,
+    payloads: HashMap<CEnumConstantId, CFieldId>,
+    members_used: HashSet<CFieldId>,
+}
+
+impl<'c> Translation<'c> {
+    /// Find the structs requested with `--tagged-union` that can be translated into Rust enums,
+    /// along with the union member accesses which are written to (or borrowed mutably).
+    ///
+    /// A struct qualifies if it consists of an enum tag followed by a union, its tag has an
+    /// enumerator with value zero (for zero-initialization), the tag is only ever assigned
+    /// enumerators, the union is only used to access its members, and every member that is
+    /// accessed could be associated with at least one enumerator. It must also not be shared
+    /// with other C code, nor have its bytes accessed through other pointer types.
+    pub fn find_tagged_unions(&self) -> (HashMap<CRecordId, TaggedUnion>, HashSet<CExprId>) {
+        let mut writes = HashSet::new();
+        if self.tcfg.tagged_unions.is_empty() {
+            return (HashMap::new(), writes);
+        }
+
+        let mut requested = IndexMap::new();
+        for (&decl_id, decl) in self.ast_context.iter_decls() {
+            match decl.kind {
+                CDeclKind::Struct {
+                    name: Some(ref name),
+                    ..
+                } if self.tcfg.tagged_unions.contains(name) => {
+                    requested.insert(decl_id, name.clone());
+                }
+                CDeclKind::Typedef { ref name, typ, .. }
+                    if self.tcfg.tagged_unions.contains(name) =>
+                {
+                    if let CTypeKind::Struct(struct_id) =
+                        self.ast_context.resolve_type(typ.ctype).kind
+                    {
+                        requested.insert(struct_id, name.clone());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut candidates = HashMap::new();
+        let mut rejected = IndexMap::new();
+        for (&struct_id, name) in &requested {
+            match self.tagged_union_layout(struct_id) {
+                Ok((tag_field, union_field, tag_enum)) => {
+                    candidates.insert(
+                        struct_id,
+                        Candidate {
+                            tag_field,
+                            union_field,
+                            tag_enum,
+                            payloads: HashMap::new(),
+                            members_used: HashSet::new(),
+                        },
+                    );
+                }
+                Err(reason) => {
+                    rejected.insert(struct_id, reason);
+                }
+            }
+        }
+
+        let mut reject = |candidates: &mut HashMap<CRecordId, Candidate>,
+                          struct_id: CRecordId,
+                          reason: &'static str| {
+            if candidates.remove(&struct_id).is_some() {
+                rejected.insert(struct_id, reason);
+            }
+        };
+
+        // Structs shared with other C code must keep their layout
+        for (decl_id, ty) in self.ast_context.external_types() {
+            let reason = match self.ast_context[decl_id].kind {
+                CDeclKind::Function { .. } => {
+                    "it is passed to or returned from a function that is defined elsewhere or \
+                     externally visible"
+                }
+                _ => "it is stored in an externally visible variable",
+            };
+            for struct_id in self.ast_context.reached_records(ty) {
+                reject(&mut candidates, struct_id, reason);
+            }
+        }
+
+        // Check how the tag and the union are used, and associate enumerators with the members
+        // that are initialized alongside them
+        let mut payload_bases = HashSet::new();
+        let mut union_accesses = vec![];
+        for (&expr_id, expr) in self.ast_context.iter_exprs() {
+            if let Some((struct_id, _, access)) = self.tagged_union_access(&candidates, expr_id) {
+                match access {
+                    TaggedUnionAccess::Payload(member) => {
+                        if let CExprKind::Member(_, union_expr, _, _, _) = expr.kind {
+                            payload_bases.insert(union_expr);
+                        }
+                        if let Some(candidate) = candidates.get_mut(&struct_id) {
+                            candidate.members_used.insert(member);
+                        }
+                    }
+                    TaggedUnionAccess::Union => union_accesses.push((struct_id, expr_id)),
+                    TaggedUnionAccess::Tag => {}
+                }
+                continue;
+            }
+
+            // Structs whose bytes are accessed through other pointer types must keep their layout
+            if let CExprKind::ImplicitCast(ty, sub_expr, _, _, _)
+            | CExprKind::ExplicitCast(ty, sub_expr, _, _, _) = expr.kind
+            {
+                let from = self.ast_context[sub_expr].kind.get_type();
+                let is_pointer = |ty| self.ast_context.resolve_type(ty).kind.is_pointer();
+                if let Some(from) = from.filter(|&from| is_pointer(from) && is_pointer(ty.ctype)) {
+                    let from = self.ast_context.reached_records(from);
+                    let to = self.ast_context.reached_records(ty.ctype);
+                    for &struct_id in from.symmetric_difference(&to) {
+                        reject(
+                            &mut candidates,
+                            struct_id,
+                            "a pointer to it is cast to another pointer type, as for memset, \
+                             memcpy or fwrite",
+                        );
+                    }
+                }
+            }
+
+            match expr.kind {
+                CExprKind::Unary(ty, op, arg, _) => match op {
+                    c_ast::UnOp::AddressOf
+                    | c_ast::UnOp::PreIncrement
+                    | c_ast::UnOp::PreDecrement
+                    | c_ast::UnOp::PostIncrement
+                    | c_ast::UnOp::PostDecrement => {
+                        if let Some((struct_id, _)) = self.tagged_union_tag(&candidates, arg) {
+                            reject(
+                                &mut candidates,
+                                struct_id,
+                                "its tag is modified in place or has its address taken",
+                            );
+                        }
+                        if op != c_ast::UnOp::AddressOf || self.is_mut_pointer(ty) {
+                            if let Some(payload) =
+                                self.tagged_union_payload_lvalue(&candidates, arg)
+                            {
+                                writes.insert(payload);
+                            }
+                        }
+                    }
+                    _ => {}
+                },
+                CExprKind::ImplicitCast(ty, arg, CastKind::ArrayToPointerDecay, _, _) => {
+                    if self.is_mut_pointer(ty) {
+                        if let Some(payload) = self.tagged_union_payload_lvalue(&candidates, arg) {
+                            writes.insert(payload);
+                        }
+                    }
+                }
+                CExprKind::Binary(_, op, lhs, rhs, _, _) if op.is_assignment() => {
+                    if let Some((struct_id, _)) = self.tagged_union_tag(&candidates, lhs) {
+                        let tag_enum = candidates[&struct_id].tag_enum;
+                        if op != c_ast::BinOp::Assign {
+                            reject(
+                                &mut candidates,
+                                struct_id,
+                                "its tag is modified in place or has its address taken",
+                            );
+                        } else if self.tag_enumerator(tag_enum, rhs).is_none() {
+                            reject(
+                                &mut candidates,
+                                struct_id,
+                                "its tag is assigned a value that is not an enumerator",
+                            );
+                        }
+                    }
+                    if let Some(payload) = self.tagged_union_payload_lvalue(&candidates, lhs) {
+                        writes.insert(payload);
+                    }
+                }
+                CExprKind::InitList(ty, ref ids, _, _) => {
+                    let struct_id = match self.ast_context.resolve_type(ty.ctype).kind {
+                        CTypeKind::Struct(struct_id) if candidates.contains_key(&struct_id) => {
+                            struct_id
+                        }
+                        _ => continue,
+                    };
+                    match self.tagged_union_init(candidates[&struct_id].tag_enum, ids) {
+                        Some((variant, Some(member))) => {
+                            if let Err(reason) = Self::add_tagged_union_payload(
+                                &mut candidates,
+                                struct_id,
+                                variant,
+                                member,
+                            ) {
+                                reject(&mut candidates, struct_id, reason);
+                            }
+                        }
+                        Some((_, None)) => {}
+                        None => reject(
+                            &mut candidates,
+                            struct_id,
+                            "it is initialized with a tag that is not an enumerator",
+                        ),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for (struct_id, expr_id) in union_accesses {
+            if !payload_bases.contains(&expr_id) {
+                reject(
+                    &mut candidates,
+                    struct_id,
+                    "its union is used other than to access one of its members",
+                );
+            }
+        }
+
+        // Associate enumerators with the union members accessed under a check of the tag, and
+        // with the members assigned right after the tag
+        let mut guarded = vec![];
+        for (_, stmt) in self.ast_context.iter_stmts() {
+            match stmt.kind {
+                CStmtKind::Switch { scrutinee, body } => {
+                    let (struct_id, base) = match self.tagged_union_tag(&candidates, scrutinee) {
+                        Some(tag) => tag,
+                        None => continue,
+                    };
+                    let tag_enum = candidates[&struct_id].tag_enum;
+                    let stmts = match self.ast_context[body].kind {
+                        CStmtKind::Compound(ref stmts) => stmts,
+                        _ => continue,
+                    };
+
+                    let mut guard = None;
+                    let mut falls_through = false;
+                    for &stmt_id in stmts {
+                        let mut labels = vec![];
+                        let mut stmt_id = stmt_id;
+                        loop {
+                            match self.ast_context[stmt_id].kind {
+                                CStmtKind::Case(expr, sub_stmt, _) => {
+                                    labels.push(Some(expr));
+                                    stmt_id = sub_stmt;
+                                }
+                                CStmtKind::CaseRange(_, _, sub_stmt, _, _)
+                                | CStmtKind::Default(sub_stmt) => {
+                                    labels.push(None);
+                                    stmt_id = sub_stmt;
+                                }
+                                _ => break,
+                            }
+                        }
+
+                        // Only a single `case` that is not fallen into from above guarantees
+                        // the value of the tag
+                        if !labels.is_empty() {
+                            guard = match labels[..] {
+                                [Some(expr)] if !falls_through => {
+                                    self.tag_enumerator(tag_enum, expr)
+                                }
+                                _ => None,
+                            };
+                        }
+                        if let Some(variant) = guard {
+                            guarded.push((struct_id, base, variant, stmt_id));
+                        }
+                        falls_through = !matches!(
+                            self.ast_context[stmt_id].kind,
+                            CStmtKind::Break
+                                | CStmtKind::Continue
+                                | CStmtKind::Return(..)
+                                | CStmtKind::Goto(..)
+                        );
+                    }
+                }
+
+                CStmtKind::If {
+                    scrutinee,
+                    true_variant,
+                    ..
+                } => {
+                    if let CExprKind::Binary(_, c_ast::BinOp::EqualEqual, lhs, rhs, _, _) =
+                        self.ast_context[scrutinee].kind
+                    {
+                        if let Some((struct_id, base)) = self.tagged_union_tag(&candidates, lhs) {
+                            let tag_enum = candidates[&struct_id].tag_enum;
+                            if let Some(variant) = self.tag_enumerator(tag_enum, rhs) {
+                                guarded.push((struct_id, base, variant, true_variant));
+                            }
+                        }
+                    }
+                }
+
+                CStmtKind::Compound(ref stmts) => {
+                    for pair in stmts.windows(2) {
+                        let (tag_lhs, tag_rhs) = match self.assignment_stmt(pair[0]) {
+                            Some(assignment) => assignment,
+                            None => continue,
+                        };
+                        let (struct_id, tag_base) =
+                            match self.tagged_union_tag(&candidates, tag_lhs) {
+                                Some(tag) => tag,
+                                None => continue,
+                            };
+                        let variant =
+                            match self.tag_enumerator(candidates[&struct_id].tag_enum, tag_rhs) {
+                                Some(variant) => variant,
+                                None => continue,
+                            };
+                        let payload = match self
+                            .assignment_stmt(pair[1])
+                            .and_then(|(lhs, _)| self.tagged_union_payload_lvalue(&candidates, lhs))
+                        {
+                            Some(payload) => payload,
+                            None => continue,
+                        };
+                        if let Some((_, base, TaggedUnionAccess::Payload(member))) =
+                            self.tagged_union_access(&candidates, payload)
+                        {
+                            if self.same_lvalue(tag_base, base) {
+                                if let Err(reason) = Self::add_tagged_union_payload(
+                                    &mut candidates,
+                                    struct_id,
+                                    variant,
+                                    member,
+                                ) {
+                                    reject(&mut candidates, struct_id, reason);
+                                }
+                            }
+                        }
+                    }
+                }
+
+                _ => {}
+            }
+        }
+
+        for (struct_id, tag_base, variant, stmt_id) in guarded {
+            for node in DFExpr::new(&self.ast_context, SomeId::Stmt(stmt_id)) {
+                let expr_id = match node {
+                    SomeId::Expr(expr_id) => expr_id,
+                    _ => continue,
+                };
+                if let Some((access_struct_id, base, TaggedUnionAccess::Payload(member))) =
+                    self.tagged_union_access(&candidates, expr_id)
+                {
+                    if access_struct_id == struct_id && self.same_lvalue(tag_base, base) {
+                        if let Err(reason) = Self::add_tagged_union_payload(
+                            &mut candidates,
+                            struct_id,
+                            variant,
+                            member,
+                        ) {
+                            reject(&mut candidates, struct_id, reason);
+                        }
+                    }
+                }
+            }
+        }
+
+        let unassociated: Vec<CRecordId> = candidates
+            .iter()
+            .filter(|(_, candidate)| {
+                let carried: HashSet<CFieldId> = candidate.payloads.values().cloned().collect();
+                !candidate.members_used.is_subset(&carried)
+            })
+            .map(|(&struct_id, _)| struct_id)
+            .collect();
+        for struct_id in unassociated {
+            reject(
+                &mut candidates,
+                struct_id,
+                "some union member could not be associated with an enumerator",
+            );
+        }
+
+        writes.retain(
+            |&expr_id| match self.tagged_union_access(&candidates, expr_id) {
+                Some((struct_id, _, _)) => candidates.contains_key(&struct_id),
+                None => false,
+            },
+        );
+
+        let mut tagged_unions = HashMap::new();
+        for (struct_id, candidate) in candidates {
+            let variants = match self.ast_context[candidate.tag_enum].kind {
+                CDeclKind::Enum { ref variants, .. } => variants
+                    .iter()
+                    .map(|variant| (*variant, candidate.payloads.get(variant).cloned()))
+                    .collect(),
+                _ => continue,
+            };
+            tagged_unions.insert(
+                struct_id,
+                TaggedUnion {
+                    tag_field: candidate.tag_field,
+                    union_field: candidate.union_field,
+                    tag_enum: candidate.tag_enum,
+                    variants,
+                },
+            );
+        }
+
+        for (struct_id, reason) in rejected {
+            diag!(
+                Diagnostic::Unions,
+                at self.ast_context.display_loc(&self.ast_context[struct_id].loc),
+                "Cannot translate struct {} as a tagged union: {}",
+                requested[&struct_id],
+                reason
+            );
+        }
+        (tagged_unions, writes)
+    }
+
+    /// Translate a struct selected by `find_tagged_unions` into a Rust enum with one variant
+    /// per enumerator of its tag, and a method named after the tag field that returns the tag.
+    pub fn convert_tagged_union(
+        &self,
+        span: Span,
+        struct_id: CRecordId,
+        name: &str,
+    ) -> Result<ConvertedDecl, TranslationError> {
+        let tagged = &self.tagged_unions[&struct_id];
+        let (tag_ty, tag_name) = match self.ast_context[tagged.tag_field].kind {
+            CDeclKind::Field { typ, .. } => (
+                self.convert_type(typ.ctype)?,
+                self.type_converter
+                    .borrow()
+                    .resolve_field_name(Some(struct_id), tagged.tag_field)
+                    .unwrap(),
+            ),
+            _ => panic!("Tag of tagged union is not a field"),
+        };
+
+        let mut variants = vec![];
+        let mut tag_arms = vec![];
+        for &(variant_id, payload) in &tagged.variants {
+            let variant_name = self.tagged_union_variant_name(variant_id);
+            let variant_path = vec![name, variant_name.as_str()];
+            let pat =
+                match payload {
+                    Some(member) => {
+                        let payload_ty = match self.ast_context[member].kind {
+                            CDeclKind::Field { typ, .. } => self.convert_type(typ.ctype)?,
+                            _ => panic!("Union member is not a field"),
+                        };
+                        variants.push(mk().tuple_variant(
+                            variant_name.as_str(),
+                            vec![mk().enum_field(payload_ty)],
+                        ));
+                        mk().tuple_struct_pat(variant_path, vec![mk().wild_pat()])
+                    }
+                    None => {
+                        variants.push(mk().unit_variant(variant_name.as_str(), None::<Box<Expr>>));
+                        mk().qpath_pat(None, variant_path)
+                    }
+                };
+            if self.tcfg.reorganize_definitions {
+                if let Some(cur_file) = self.cur_file.borrow().as_ref() {
+                    self.add_import(*cur_file, variant_id, &variant_name);
+                }
+            }
+            tag_arms.push(mk().arm(pat, None, mk().path_expr(vec![variant_name])));
+        }
+
+        let enum_item = mk()
+            .span(span)
+            .pub_()
+            .call_attr("derive", vec!["Copy", "Clone"])
+            .enum_item(name, variants);
+
+        // impl Struct { pub fn tag(self) -> Tag { match self { .. } } }
+        let tag_fn = mk().pub_().fn_impl_item(
+            mk().fn_decl(
+                tag_name,
+                vec![mk().self_arg(SelfKind::Value(Mutability::Immutable))],
+                None,
+                ReturnType::Type(Default::default(), tag_ty),
+            ),
+            mk().block(vec![mk().expr_stmt(
+                mk().match_expr(mk().path_expr(vec!["self"]), tag_arms),
+            )]),
+        );
+        let impl_item = mk().impl_item(mk().path_ty(vec![name]), vec![tag_fn]);
+
+        Ok(ConvertedDecl::Items(vec![enum_item, impl_item]))
+    }
+
+    /// Translate an access to the tag or to a union member of a struct that was translated into
+    /// a Rust enum. Returns `None` if `expr_id` is not such an access.
+    pub fn convert_tagged_union_access(
+        &self,
+        ctx: ExprContext,
+        expr_id: CExprId,
+    ) -> Result<Option<WithStmts<Box<Expr>>>, TranslationError> {
+        let (struct_id, base, access) = match self.tagged_union_access(&HashMap::new(), expr_id) {
+            Some(access) => access,
+            None => return Ok(None),
+        };
+        let tagged = &self.tagged_unions[&struct_id];
+
+        // For a union member, the struct is accessed by the member expression of the union
+        let struct_access = match (access, &self.ast_context[expr_id].kind) {
+            (TaggedUnionAccess::Payload(_), &CExprKind::Member(_, union_expr, _, _, _)) => {
+                union_expr
+            }
+            _ => expr_id,
+        };
+        let val = self.convert_tagged_union_base(ctx, struct_access, base)?;
+
+        match access {
+            TaggedUnionAccess::Tag => {
+                let tag_name = self
+                    .type_converter
+                    .borrow()
+                    .resolve_field_name(Some(struct_id), tagged.tag_field)
+                    .unwrap();
+                Ok(Some(val.map(|v| {
+                    mk().method_call_expr(v, tag_name, Vec::<Box<Expr>>::new())
+                })))
+            }
+
+            TaggedUnionAccess::Payload(member) => {
+                let name = self
+                    .type_converter
+                    .borrow()
+                    .resolve_decl_name(struct_id)
+                    .unwrap();
+                let variant_names: Vec<String> = tagged
+                    .variants
+                    .iter()
+                    .filter(|&&(_, payload)| payload == Some(member))
+                    .map(|&(variant_id, _)| self.tagged_union_variant_name(variant_id))
+                    .collect();
+                let exhaustive = variant_names.len() == tagged.variants.len();
+                let mutable = self.tagged_union_writes.contains(&expr_id);
+                Ok(Some(val.map(|v| {
+                    self.variant_payload_place(
+                        v,
+                        &name,
+                        &variant_names,
+                        mutable,
+                        exhaustive,
+                        "Accessed an inactive member of a tagged union",
+                    )
+                })))
+            }
+
+            TaggedUnionAccess::Union => {
+                Err(format_err!("Unsupported use of the union of a tagged union").into())
+            }
+        }
+    }
+
+    /// Build a place expression for the payload of an enum value that is expected to be one of
+    /// the given single-field variants, panicking with `msg` if it is not:
+    ///
+    /// `*match value { E::A(ref payload) | E::B(ref payload) => payload, _ => panic!(msg) }`
+    pub fn variant_payload_place(
+        &self,
+        value: Box<Expr>,
+        enum_name: &str,
+        variant_names: &[String],
+        mutable: bool,
+        exhaustive: bool,
+        msg: &str,
+    ) -> Box<Expr> {
+        let binding = if mutable {
+            mk().mutbl().ident_ref_pat("payload")
+        } else {
+            mk().ident_ref_pat("payload")
+        };
+        let pats: Vec<Box<Pat>> = variant_names
+            .iter()
+            .map(|variant_name| {
+                mk().tuple_struct_pat(
+                    vec![enum_name, variant_name.as_str()],
+                    vec![binding.clone()],
+                )
+            })
+            .collect();
+        let mut arms = vec![mk().arm(mk().or_pat(pats), None, mk().path_expr(vec!["payload"]))];
+        if !exhaustive {
+            arms.push(mk().arm(mk().wild_pat(), None, self.panic_or_err_helper(msg, true)));
+        }
+        mk().unary_expr(
+            UnOp::Deref(Default::default()),
+            mk().match_expr(value, arms),
+        )
+    }
+
+    /// Translate an assignment to the tag of a struct that was translated into a Rust enum,
+    /// which replaces the whole value with a zero-initialized variant. Returns `None` if `lhs`
+    /// is not such a tag.
+    pub fn convert_tagged_union_tag_assignment(
+        &self,
+        ctx: ExprContext,
+        lhs: CExprId,
+        rhs: CExprId,
+    ) -> Result<Option<WithStmts<Box<Expr>>>, TranslationError> {
+        let (struct_id, base) = match self.tagged_union_tag(&HashMap::new(), lhs) {
+            Some(tag) => tag,
+            None => return Ok(None),
+        };
+        let tag_enum = self.tagged_unions[&struct_id].tag_enum;
+        let variant_id = self
+            .tag_enumerator(tag_enum, rhs)
+            .ok_or_else(|| format_err!("Tag of tagged union assigned a non-enumerator"))?;
+
+        let (lhs, _) = self.ast_context.resolve_expr(lhs);
+        let val = self.convert_tagged_union_base(ctx.used(), lhs, base)?;
+        let variant = self.tagged_union_variant(struct_id, variant_id, None, ctx.is_static)?;
+        let assignment = val.and_then(|base| -> Result<_, TranslationError> {
+            Ok(variant.map(|variant| mk().assign_expr(base, variant)))
+        })?;
+
+        if ctx.is_unused() {
+            Ok(Some(assignment))
+        } else {
+            let val = self.convert_expr(ctx.used(), rhs)?;
+            assignment
+                .and_then(|assignment| -> Result<_, TranslationError> {
+                    let mut val = val;
+                    val.prepend_stmts(vec![mk().semi_stmt(assignment)]);
+                    Ok(val)
+                })
+                .map(Some)
+        }
+    }
+
+    /// Translate an initializer list for a struct that was translated into a Rust enum.
+    pub fn convert_tagged_union_literal(
+        &self,
+        ctx: ExprContext,
+        struct_id: CRecordId,
+        ids: &[CExprId],
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        let tag_enum = self.tagged_unions[&struct_id].tag_enum;
+        let (variant_id, _) = self
+            .tagged_union_init(tag_enum, ids)
+            .ok_or_else(|| format_err!("Tagged union initialized with a non-enumerator tag"))?;
+
+        let payload = match ids.get(1).map(|&id| &self.ast_context[id].kind) {
+            Some(&CExprKind::InitList(_, ref union_ids, Some(_), _)) => match union_ids[..] {
+                [payload] => Some(payload),
+                _ => None,
+            },
+            _ => None,
+        };
+        let payload = match payload {
+            Some(payload) => Some(self.convert_expr(ctx.used(), payload)?),
+            None => None,
+        };
+        self.tagged_union_variant(struct_id, variant_id, payload, ctx.is_static)
+    }
+
+    /// Translate the base of a `.` or `->` access to a struct that was translated into a Rust
+    /// enum into the enum value.
+    fn convert_tagged_union_base(
+        &self,
+        ctx: ExprContext,
+        member_expr: CExprId,
+        base: CExprId,
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        let val = self.convert_expr(ctx, base)?;
+        Ok(match self.ast_context[member_expr].kind {
+            CExprKind::Member(_, _, _, MemberKind::Arrow, _) => {
+                val.map(|v| mk().unary_expr(UnOp::Deref(Default::default()), v))
+            }
+            _ => val,
+        })
+    }
+
+    /// Zero-initialize a struct that was translated into a Rust enum, using the variant for the
+    /// enumerator with value zero.
+    pub fn tagged_union_zero_initializer(
+        &self,
+        struct_id: CRecordId,
+        is_static: bool,
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        let variant_id = self
+            .zero_enumerator(self.tagged_unions[&struct_id].tag_enum)
+            .ok_or_else(|| format_err!("Tagged union has no enumerator with value zero"))?;
+        self.tagged_union_variant(struct_id, variant_id, None, is_static)
+    }
+
+    /// Build the enum variant for the given enumerator, zero-initializing its payload unless one
+    /// is provided.
+    fn tagged_union_variant(
+        &self,
+        struct_id: CRecordId,
+        variant_id: CEnumConstantId,
+        payload: Option<WithStmts<Box<Expr>>>,
+        is_static: bool,
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        let tagged = &self.tagged_unions[&struct_id];
+        let name = self
+            .type_converter
+            .borrow()
+            .resolve_decl_name(struct_id)
+            .unwrap();
+        let variant_name = self.tagged_union_variant_name(variant_id);
+        let path = mk().path_expr(vec![name, variant_name]);
+
+        let member = tagged
+            .variants
+            .iter()
+            .find(|&&(id, _)| id == variant_id)
+            .and_then(|&(_, member)| member);
+        let member = match member {
+            Some(member) => member,
+            None => return Ok(WithStmts::new_val(path)),
+        };
+        let payload = match payload {
+            Some(payload) => payload,
+            None => match self.ast_context[member].kind {
+                CDeclKind::Field { typ, .. } => self.implicit_default_expr(typ.ctype, is_static)?,
+                _ => panic!("Union member is not a field"),
+            },
+        };
+        Ok(payload.map(|payload| mk().call_expr(path, vec![payload])))
+    }
+
+    fn tagged_union_variant_name(&self, variant_id: CEnumConstantId) -> String {
+        self.renamer
+            .borrow()
+            .get(&variant_id)
+            .expect("Enum constant not named")
+    }
+
+    /// Check that a struct consists of an enum tag followed by a union, and return the tag
+    /// field, the union field and the enum.
+    fn tagged_union_layout(
+        &self,
+        struct_id: CRecordId,
+    ) -> Result<(CFieldId, CFieldId, CEnumId), &'static str> {
+        let fields = match self.ast_context[struct_id].kind {
+            CDeclKind::Struct {
+                fields: Some(ref fields),
+                ..
+            } => fields,
+            _ => return Err("it is not defined"),
+        };
+        if self.ast_context.has_inner_struct_decl(struct_id) {
+            return Err("it has an alignment attribute");
+        }
+        let field_type = |field_id: CFieldId| match self.ast_context[field_id].kind {
+            CDeclKind::Field {
+                typ,
+                bitfield_width: None,
+                ..
+            } => Some(&self.ast_context.resolve_type(typ.ctype).kind),
+            _ => None,
+        };
+        let (tag_field, union_field) = match fields[..] {
+            [tag_field, union_field] => (tag_field, union_field),
+            _ => return Err("it does not consist of an enum followed by a union"),
+        };
+        let tag_enum = match (field_type(tag_field), field_type(union_field)) {
+            (Some(&CTypeKind::Enum(tag_enum)), Some(&CTypeKind::Union(union_id))) => {
+                match self.ast_context[union_id].kind {
+                    CDeclKind::Union {
+                        fields: Some(_), ..
+                    } => tag_enum,
+                    _ => return Err("its union is not defined"),
+                }
+            }
+            _ => return Err("it does not consist of an enum followed by a union"),
+        };
+        match self.ast_context[tag_enum].kind {
+            CDeclKind::Enum {
+                integral_type: Some(_),
+                ..
+            } => {}
+            _ => return Err("its tag enum is not defined"),
+        }
+        if self.zero_enumerator(tag_enum).is_none() {
+            return Err("its tag enum has no enumerator with value zero");
+        }
+        Ok((tag_field, union_field, tag_enum))
+    }
+
+    /// Classify a member access into a struct translated into a Rust enum, returning the struct,
+    /// the base of the access (the struct value or pointer) and which field is accessed. Uses
+    /// `candidates` during analysis and the final set of tagged unions afterwards.
+    fn tagged_union_access(
+        &self,
+        candidates: &HashMap<CRecordId, Candidate>,
+        expr_id: CExprId,
+    ) -> Option<(CRecordId, CExprId, TaggedUnionAccess)> {
+        let fields_of = |struct_id: CRecordId| {
+            if let Some(candidate) = candidates.get(&struct_id) {
+                Some((candidate.tag_field, candidate.union_field))
+            } else {
+                self.tagged_unions
+                    .get(&struct_id)
+                    .map(|tagged| (tagged.tag_field, tagged.union_field))
+            }
+        };
+
+        let (base, field) = match self.ast_context[expr_id].kind {
+            CExprKind::Member(_, base, field, _, _) => (base, field),
+            _ => return None,
+        };
+        let record_id = *self.ast_context.parents.get(&field)?;
+        if let Some((tag_field, union_field)) = fields_of(record_id) {
+            if field == tag_field {
+                return Some((record_id, base, TaggedUnionAccess::Tag));
+            } else if field == union_field {
+                return Some((record_id, base, TaggedUnionAccess::Union));
+            }
+            return None;
+        }
+
+        // A member of the union, accessed through the union field
+        match self.ast_context[base].kind {
+            CExprKind::Member(_, struct_base, union_field, _, _) => {
+                let struct_id = *self.ast_context.parents.get(&union_field)?;
+                match fields_of(struct_id) {
+                    Some((_, tagged_union_field)) if tagged_union_field == union_field => {
+                        Some((struct_id, struct_base, TaggedUnionAccess::Payload(field)))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// If `expr_id` reads or writes the tag of a tagged union, return the struct and the base of
+    /// the access.
+    fn tagged_union_tag(
+        &self,
+        candidates: &HashMap<CRecordId, Candidate>,
+        expr_id: CExprId,
+    ) -> Option<(CRecordId, CExprId)> {
+        let (expr_id, _) = self.ast_context.resolve_expr(expr_id);
+        match self.tagged_union_access(candidates, expr_id) {
+            Some((struct_id, base, TaggedUnionAccess::Tag)) => Some((struct_id, base)),
+            _ => None,
+        }
+    }
+
+    /// Is this a pointer to a non-`const` type?
+    pub fn is_mut_pointer(&self, ty: CQualTypeId) -> bool {
+        self.ast_context
+            .get_pointee_qual_type(ty.ctype)
+            .map_or(false, |pointee| !pointee.qualifiers.is_const)
+    }
+
+    /// If `expr_id` is an lvalue within a union member of a tagged union (the member itself, an
+    /// element of it or a field of it), return the access of the union member.
+    fn tagged_union_payload_lvalue(
+        &self,
+        candidates: &HashMap<CRecordId, Candidate>,
+        mut expr_id: CExprId,
+    ) -> Option<CExprId> {
+        loop {
+            if let Some((_, _, TaggedUnionAccess::Payload(_))) =
+                self.tagged_union_access(candidates, expr_id)
+            {
+                return Some(expr_id);
+            }
+            match self.ast_context[expr_id].kind {
+                CExprKind::Paren(_, sub_expr)
+                | CExprKind::ImplicitCast(_, sub_expr, CastKind::ArrayToPointerDecay, _, _)
+                | CExprKind::ArraySubscript(_, sub_expr, _, _)
+                | CExprKind::Member(_, sub_expr, _, MemberKind::Dot, _) => expr_id = sub_expr,
+                _ => return None,
+            }
+        }
+    }
+
+    /// Get the tag and payload of an initializer list for a tagged union. Returns `None` if the
+    /// tag is not an enumerator.
+    fn tagged_union_init(
+        &self,
+        tag_enum: CEnumId,
+        ids: &[CExprId],
+    ) -> Option<(CEnumConstantId, Option<CFieldId>)> {
+        let variant_id = match ids.get(0) {
+            Some(&tag) => match self.ast_context[tag].kind {
+                CExprKind::ImplicitValueInit(..) => self.zero_enumerator(tag_enum)?,
+                _ => self.tag_enumerator(tag_enum, tag)?,
+            },
+            None => self.zero_enumerator(tag_enum)?,
+        };
+        let member = match ids.get(1).map(|&id| &self.ast_context[id].kind) {
+            Some(&CExprKind::InitList(_, _, Some(member), _)) => Some(member),
+            _ => None,
+        };
+        Some((variant_id, member))
+    }
+
+    fn zero_enumerator(&self, enum_id: CEnumId) -> Option<CEnumConstantId> {
+        match self.ast_context[enum_id].kind {
+            CDeclKind::Enum { ref variants, .. } => variants.iter().cloned().find(|&variant_id| {
+                let value = self.enum_constant_value(variant_id);
+                value == ConstIntExpr::I(0) || value == ConstIntExpr::U(0)
+            }),
+            _ => None,
+        }
+    }
+
+    /// If `expr_id` refers to an enumerator of the given enum (possibly through casts), return
+    /// that enumerator.
+    fn tag_enumerator(&self, enum_id: CEnumId, expr_id: CExprId) -> Option<CEnumConstantId> {
+        let mut expr_id = expr_id;
+        loop {
+            match self.ast_context[expr_id].kind {
+                CExprKind::ImplicitCast(_, sub_expr, _, _, _)
+                | CExprKind::ExplicitCast(_, sub_expr, _, _, _)
+                | CExprKind::ConstantExpr(_, sub_expr, _)
+                | CExprKind::Paren(_, sub_expr) => expr_id = sub_expr,
+                CExprKind::DeclRef(_, decl_id, _) => {
+                    return match self.ast_context[enum_id].kind {
+                        CDeclKind::Enum { ref variants, .. } if variants.contains(&decl_id) => {
+                            Some(decl_id)
+                        }
+                        _ => None,
+                    }
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// If the statement is a plain assignment, return its left and right hand sides.
+    fn assignment_stmt(&self, stmt_id: CStmtId) -> Option<(CExprId, CExprId)> {
+        match self.ast_context[stmt_id].kind {
+            CStmtKind::Expr(expr_id) => match self.ast_context[expr_id].kind {
+                CExprKind::Binary(_, c_ast::BinOp::Assign, lhs, rhs, _, _) => Some((lhs, rhs)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Conservatively check whether two expressions denote the same lvalue (or the same pointer,
+    /// for the bases of `->` accesses).
+    fn same_lvalue(&self, a: CExprId, b: CExprId) -> bool {
+        let (_, a) = self.ast_context.resolve_expr(a);
+        let (_, b) = self.ast_context.resolve_expr(b);
+        match (a, b) {
+            (&CExprKind::DeclRef(_, a, _), &CExprKind::DeclRef(_, b, _)) => a == b,
+            (
+                &CExprKind::Member(_, a_base, a_field, a_kind, _),
+                &CExprKind::Member(_, b_base, b_field, b_kind, _),
+            ) => a_field == b_field && a_kind == b_kind && self.same_lvalue(a_base, b_base),
+            (
+                &CExprKind::Unary(_, c_ast::UnOp::Deref, a, _),
+                &CExprKind::Unary(_, c_ast::UnOp::Deref, b, _),
+            ) => self.same_lvalue(a, b),
+            _ => false,
+        }
+    }
+
+    /// Record that an enumerator carries the given union member.
+    fn add_tagged_union_payload(
+        candidates: &mut HashMap<CRecordId, Candidate>,
+        struct_id: CRecordId,
+        variant_id: CEnumConstantId,
+        member: CFieldId,
+    ) -> Result<(), &'static str> {
+        if let Some(candidate) = candidates.get_mut(&struct_id) {
+            match candidate.payloads.insert(variant_id, member) {
+                Some(previous) if previous != member => {
+                    return Err("an enumerator is used with more than one union member")
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+
+    use crate::{Diagnostic, Transpiler, TranspilerConfig};
+
+    /// Translate `uses` of `struct shape` with `--tagged-union=shape`, returning why it was not
+    /// translated into an enum, if it wasn't
+    fn rejection(uses: &str) -> Option<String> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shape.c");
+        let source = format!(
+            "#include <string.h>\n\
+             enum shape_kind {{ NONE, CIRCLE, SQUARE }};\n\
+             struct shape {{ enum shape_kind kind; union {{ int radius; int side; }} u; }};\n\
+             static int radius(struct shape *s) {{\n\
+                 return s->kind == CIRCLE ? s->u.radius : 0;\n\
+             }}\n\
+             {}\n",
+            uses
+        );
+        fs::write(&path, source).unwrap();
+
+        let messages = Arc::new(Mutex::new(vec![]));
+        let sink_messages = Arc::clone(&messages);
+        let config = TranspilerConfig {
+            tagged_unions: vec!["shape".to_owned()],
+            ..TranspilerConfig::default()
+        };
+        Transpiler::new(config)
+            .on_diagnostic(move |message| {
+                if message.diagnostic == Some(Diagnostic::Unions) {
+                    sink_messages.lock().unwrap().push(message.message.clone());
+                }
+            })
+            .transpile_file(&path)
+            .unwrap();
+        let prefix = "Cannot translate struct shape as a tagged union: ";
+        let messages = messages.lock().unwrap();
+        messages
+            .iter()
+            .find_map(|message| message.strip_prefix(prefix))
+            .map(str::to_owned)
+    }
+
+    #[test]
+    fn accepted() {
+        let uses = "int entry(void) { struct shape s = { CIRCLE, { 2 } }; return radius(&s); }";
+        assert_eq!(rejection(uses), None);
+    }
+
+    #[test]
+    fn shared_with_functions() {
+        let reason = "it is passed to or returned from a function that is defined elsewhere or \
+                      externally visible";
+        let uses = "\
+int area(const struct shape *s);
+int total(void) { struct shape s = { CIRCLE, { 2 } }; return area(&s) + radius(&s); }";
+        assert_eq!(rejection(uses).as_deref(), Some(reason));
+        let uses = "struct shape circle(int r) { struct shape s = { CIRCLE, { r } }; return s; }";
+        assert_eq!(rejection(uses).as_deref(), Some(reason));
+        // Through the fields of other structs and arrays
+        let uses = "\
+struct scene { struct shape shapes[4]; };
+void draw(struct scene *scene);
+void show(void) { struct scene scene = { 0 }; draw(&scene); }";
+        assert_eq!(rejection(uses).as_deref(), Some(reason));
+    }
+
+    #[test]
+    fn shared_with_variables() {
+        let reason = "it is stored in an externally visible variable";
+        let uses = "\
+extern struct shape current;
+int current_radius(void) { return radius(&current); }";
+        assert_eq!(rejection(uses).as_deref(), Some(reason));
+        assert_eq!(rejection("struct shape *shapes[8];").as_deref(), Some(reason));
+    }
+
+    #[test]
+    fn bytes_accessed() {
+        let reason = "a pointer to it is cast to another pointer type, as for memset, memcpy or \
+                      fwrite";
+        let uses = "\
+static void clear(struct shape *s) { memset(s, 0, sizeof *s); }
+void reset(void) { struct shape s; clear(&s); }";
+        assert_eq!(rejection(uses).as_deref(), Some(reason));
+        let uses = "int first(void) { struct shape s = { CIRCLE, { 2 } }; return *(char *)&s; }";
+        assert_eq!(rejection(uses).as_deref(), Some(reason));
+    }
+}
@@ -0,0 +1,223 @@
+//! This module implements `--emit-test-stubs`, which gives teams a starting point for validating
+//! the translated code. A test module written next to the translated module as
+//! `<module>_tests.rs` holds one ignored test per exported function, whose body shows how to
+//! compare the translation against the original C function:
+//!
+//! ```ignore
+//! #[test]
+//! #[ignore = "TODO: write a test for `add`"]
+//! fn test_add() {
+//!     // TODO: compare `add` against the C implementation, e.g.
+//!     //
+//!     //     extern "C" {
+//!     //         #[link_name = "add"]
+//!     //         fn c_add(a: libc::c_int, b: libc::c_int) -> libc::c_int;
+//!     //     }
+//!     //
+//!     //     let (a, b) = todo!();
+//!     //     assert_eq!(unsafe { add(a, b) }, unsafe { c_add(a, b) });
+//! }
+//! ```
+//!
+//! The C function has to be linked into the tests under a different symbol than the translated
+//! one, e.g. by translating with `--prefix-function-names`.
+
+use crate::convert_type::RESERVED_NAMES;
+
+use super::*;
+
+/// A function that gets a test stub
+struct StubbedFn {
+    /// Name of the translated function
+    rust_name: String,
+    args: Vec<String>,
+    returns_value: bool,
+    /// The example declaration of the C function
+    c_decl: String,
+}
+
+impl<'c> Translation<'c> {
+    /// Generate the test module item for the translated module, and the file holding the test
+    /// stubs for the exported functions recorded during translation.
+    pub fn test_stubs(&self) -> Option<(Box<Item>, Vec<(String, String)>)> {
+        let fns = self
+            .tested_fns
+            .borrow()
+            .iter()
+            .filter_map(|&decl_id| match self.stubbed_fn(decl_id) {
+                Ok(stubbed) => stubbed,
+                Err(e) => {
+                    warn!("Skipping test stub: {}", e);
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        if fns.is_empty() {
+            return None;
+        }
+
+        let file_name = format!("{}_tests.rs", self.main_module_name);
+        let mut file = format!(
+            "// Test stubs for the functions exported by {}.rs\n\
+             \n\
+             use super::*;\n",
+            self.main_module_name,
+        );
+        for stubbed in &fns {
+            file.push_str(&test_stub(stubbed));
+        }
+
+        let module_name = self.renamer.borrow_mut().pick_name("tests");
+        let test_mod = mk()
+            .call_attr("cfg", vec!["test"])
+            .str_attr("path", &file_name)
+            .mod_item(module_name, None);
+        Some((test_mod, vec![(file_name, file)]))
+    }
+
+    /// The name of the translated function `decl_id` if it is an exported function defined in
+    /// the main file, which tests can call like the C function
+    pub fn tested_fn_name(&self, decl_id: CDeclId) -> Option<String> {
+        let decl = &self.ast_context[decl_id];
+        match decl.kind {
+            CDeclKind::Function {
+                is_global: true,
+                body: Some(_),
+                ..
+            } => {}
+            _ => return None,
+        }
+        // `main` is called by the test harness's own `main`, idiomatic candidates aren't
+        // `extern "C"`, and static C strings are returned by reference
+        if self.ast_context.file_id(decl) != Some(self.main_file)
+            || self.ast_context.c_main == Some(decl_id)
+            || self.candidate_fns.contains(&decl_id)
+            || self.returns_static_cstr(decl_id)
+        {
+            return None;
+        }
+        self.renamer.borrow().get(&decl_id)
+    }
+
+    /// Describe the function `decl_id` if it gets a test stub
+    fn stubbed_fn(&self, decl_id: CDeclId) -> Result<Option<StubbedFn>, TranslationError> {
+        let rust_name = match self.tested_fn_name(decl_id) {
+            Some(rust_name) => rust_name,
+            None => return Ok(None),
+        };
+        let (typ, name, parameters, attrs) = match self.ast_context[decl_id].kind {
+            CDeclKind::Function {
+                typ,
+                ref name,
+                ref parameters,
+                ref attrs,
+                ..
+            } => (typ, name, parameters, attrs),
+            _ => return Ok(None),
+        };
+
+        let (ret, is_variadic, is_noreturn) = match self.ast_context.resolve_type(typ).kind {
+            CTypeKind::Function(ret, _, is_variadic, is_noreturn, _) => {
+                (ret, is_variadic, is_noreturn)
+            }
+            _ => return Err(format_err!("{} doesn't have a function type", name).into()),
+        };
+
+        let mut args = vec![];
+        let mut inputs = vec![];
+        for (i, &param_id) in parameters.iter().enumerate() {
+            let (ident, typ) = match self.ast_context[param_id].kind {
+                CDeclKind::Variable { ref ident, typ, .. } => (ident, typ),
+                _ => {
+                    return Err(TranslationError::generic(
+                        "Parameter is not variable declaration",
+                    ))
+                }
+            };
+            let arg = if ident.is_empty() || RESERVED_NAMES.contains(&ident.as_str()) {
+                format!("arg{}", i)
+            } else {
+                ident.clone()
+            };
+            inputs.push(mk().arg(self.convert_type(typ.ctype)?, mk().ident_pat(arg.as_str())));
+            args.push(arg);
+        }
+
+        let returns_value = !is_noreturn
+            && !attrs.contains(&c_ast::Attribute::NoReturn)
+            && self.ast_context.resolve_type(ret.ctype).kind != CTypeKind::Void;
+        let output = if is_noreturn || attrs.contains(&c_ast::Attribute::NoReturn) {
+            ReturnType::Type(Default::default(), mk().never_ty())
+        } else if returns_value {
+            ReturnType::Type(Default::default(), self.convert_type(ret.ctype)?)
+        } else {
+            ReturnType::Default
+        };
+        let c_fn_name = format!("c_{}", rust_name);
+        let c_fn = mk()
+            .str_attr("link_name", name)
+            .fn_foreign_item(mk().fn_decl(
+                c_fn_name,
+                inputs,
+                is_variadic.then(|| mk().variadic_arg(vec![])),
+                output,
+            ));
+        let c_decl = pprust::to_string(|| syn::File {
+            shebang: None,
+            attrs: vec![],
+            items: vec![*mk()
+                .extern_(self.function_abi(attrs))
+                .foreign_items(vec![c_fn])],
+        });
+
+        Ok(Some(StubbedFn {
+            rust_name,
+            args,
+            returns_value,
+            c_decl,
+        }))
+    }
+}
+
+/// The ignored test stub for a function, with the example comparison commented out
+fn test_stub(stubbed: &StubbedFn) -> String {
+    let mut example = stubbed.c_decl.trim_end().to_owned();
+    example.push('\n');
+    let args = stubbed.args.join(", ");
+    match stubbed.args.len() {
+        0 => {}
+        1 => example.push_str(&format!("\nlet {} = todo!();\n", args)),
+        _ => example.push_str(&format!("\nlet ({}) = todo!();\n", args)),
+    }
+    if stubbed.returns_value {
+        example.push_str(&format!(
+            "assert_eq!(unsafe {{ {0}({1}) }}, unsafe {{ c_{0}({1}) }});\n",
+            stubbed.rust_name, args,
+        ));
+    } else {
+        example.push_str(&format!(
+            "unsafe {{ {0}({1}) }};\n\
+             unsafe {{ c_{0}({1}) }};\n\
+             // compare the effects of both calls\n",
+            stubbed.rust_name, args,
+        ));
+    }
+
+    let mut stub = format!(
+        "\n#[test]\n\
+         #[ignore = \"TODO: write a test for `{0}`\"]\n\
+         fn test_{0}() {{\n    \
+             // TODO: compare `{0}` against the C implementation, e.g.\n    \
+             //\n",
+        stubbed.rust_name,
+    );
+    for line in example.lines() {
+        if line.is_empty() {
+            stub.push_str("    //\n");
+        } else {
+            stub.push_str(&format!("    //     {}\n", line));
+        }
+    }
+    stub.push_str("}\n");
+    stub
+}
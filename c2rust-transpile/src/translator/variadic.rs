@@ -175,7 +175,7 @@ impl<'c> Translation<'c> {
                 })
             {
                 real_arg_ty = Some(arg_ty.clone());
-                arg_ty = mk().mutbl().ptr_ty(mk().path_ty(vec!["libc", "c_void"]));
+                arg_ty = mk().mutbl().ptr_ty(self.type_converter.borrow().c_void_ty());
             }
 
             val.and_then(|val| {
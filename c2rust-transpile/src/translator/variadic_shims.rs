@@ -0,0 +1,223 @@
+//! This module implements `--variadic-shims`, which routes calls to external variadic functions
+//! with a literal format string through a `macro_rules!` shim with one arm per format string seen
+//! in the translated file:
+//!
+//! ```c
+//! extern int log_msg(int level, const char *fmt, ...);
+//! log_msg(1, "%s: %d\n", name, n);
+//! ```
+//!
+//! becomes
+//!
+//! ```ignore
+//! macro_rules! log_msg {
+//!     ($f0:expr, "%s: %d\n", $a0:expr, $a1:expr $(,)?) => {{
+//!         let f0 = $f0;
+//!         let a0: *const libc::c_char = $a0;
+//!         let a1: libc::c_int = $a1;
+//!         log_msg(f0, b"%s: %d\n\0" as *const u8 as *const libc::c_char, a0, a1)
+//!     }};
+//! }
+//! log_msg!(1 as libc::c_int, "%s: %d\n", name, n);
+//! ```
+//!
+//! The types of the variadic arguments are the ones the C call passed after the default argument
+//! promotions, so code edited later that passes a differently typed argument for a format is
+//! rejected by the compiler instead of being read as garbage by the C function, and an argument
+//! count that doesn't match any format seen in C doesn't expand at all.
+//!
+//! The format string is the last named parameter of the function. Calls passing anything but a
+//! string literal there, calls using a format already seen with other argument types, and calls
+//! to functions that already have `MAX_ARMS` formats are translated as plain calls.
+
+use super::*;
+use syn::__private::ToTokens;
+
+/// The most formats a shim is generated for
+const MAX_ARMS: usize = 16;
+
+/// Standard macros the translated code may use, which a shim must not shadow
+const STD_MACROS: &[&str] = &[
+    "assert", "assert_eq", "eprint", "eprintln", "format", "format_args", "panic", "print",
+    "println", "unimplemented", "unreachable", "vec", "write", "writeln",
+];
+
+/// The calls seen so far to one variadic function
+pub struct VariadicShim {
+    /// The translated callee
+    callee: String,
+    /// The number of named arguments before the format
+    fixed: usize,
+    /// The translated format argument and the types of the variadic arguments, by format
+    arms: IndexMap<String, (String, Vec<String>)>,
+}
+
+impl<'c> Translation<'c> {
+    /// Try to translate a call to an external variadic function through its shim. Returns `None`
+    /// if the call doesn't pass a string literal format or conflicts with the formats seen before.
+    pub fn convert_variadic_shim_call(
+        &self,
+        mut ctx: ExprContext,
+        callee: CExprId,
+        args: &[CExprId],
+    ) -> Result<Option<WithStmts<Box<Expr>>>, TranslationError> {
+        // Shims are only emitted into the main file
+        if self
+            .cur_file
+            .borrow()
+            .map_or(false, |file| file != self.main_file)
+        {
+            return Ok(None);
+        }
+        let func = match self.ast_context[callee].kind {
+            CExprKind::ImplicitCast(_, func, CastKind::FunctionToPointerDecay, _, _) => func,
+            _ => return Ok(None),
+        };
+        let (name, fixed) = match self.ast_context[func].kind {
+            CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[decl_id].kind {
+                CDeclKind::Function {
+                    typ, body: None, ..
+                } => match self.ast_context.resolve_type(typ).kind {
+                    CTypeKind::Function(_, ref params, true, _, _) => {
+                        (self.renamer.borrow().get(&decl_id), params.len())
+                    }
+                    _ => return Ok(None),
+                },
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        let name = match name {
+            Some(name) if fixed > 0 && fixed <= args.len() && !STD_MACROS.contains(&&*name) => {
+                name
+            }
+            _ => return Ok(None),
+        };
+        let fmt = match *self.ast_context.resolve_expr(args[fixed - 1]).1 {
+            CExprKind::Literal(_, CLiteral::String(ref bytes, 1)) => {
+                // C stops at the first NUL
+                let bytes = bytes.split(|&b| b == 0).next().unwrap_or_default();
+                match std::str::from_utf8(bytes) {
+                    Ok(fmt) => fmt.to_owned(),
+                    Err(_) => return Ok(None),
+                }
+            }
+            _ => return Ok(None),
+        };
+
+        let var_tys = args[fixed..]
+            .iter()
+            .map(|&arg| {
+                let ty = self.ast_context[arg]
+                    .kind
+                    .get_type()
+                    .ok_or_else(|| format_err!("bad variadic argument type"))?;
+                Ok(self.convert_type(ty)?.to_token_stream().to_string())
+            })
+            .collect::<Result<Vec<_>, TranslationError>>()?;
+        let seen = match self.variadic_shims.borrow().get(&name) {
+            Some(shim) => match shim.arms.get(&fmt) {
+                Some((_, tys)) if *tys == var_tys => true,
+                Some(_) => return Ok(None),
+                None if shim.arms.len() >= MAX_ARMS => return Ok(None),
+                None => false,
+            },
+            None => false,
+        };
+
+        let callee = self.convert_expr(ctx.used(), func)?;
+        // Like other variadic calls, references are decayed to pointers
+        ctx.decay_ref = DecayRef::Yes;
+        let fmt_expr = match self.convert_expr(ctx.used(), args[fixed - 1])?.to_pure_expr() {
+            Some(fmt_expr) => fmt_expr,
+            None => return Ok(None),
+        };
+        let fixed_args = self.convert_exprs(ctx.used(), &args[..fixed - 1])?;
+        let var_args = self.convert_exprs(ctx.used(), &args[fixed..])?;
+
+        if !seen {
+            let mut shims = self.variadic_shims.borrow_mut();
+            let shim = shims.entry(name.clone()).or_insert_with(|| VariadicShim {
+                callee: pprust::expr_to_string(&callee.to_expr()),
+                fixed: fixed - 1,
+                arms: IndexMap::new(),
+            });
+            let fmt_expr = fmt_expr.to_token_stream().to_string();
+            shim.arms.insert(fmt.clone(), (fmt_expr, var_tys));
+        }
+
+        let mut call = fixed_args.and_then(|fixed_args| {
+            var_args.and_then(|var_args| {
+                let mut tokens = vec![];
+                for arg in fixed_args {
+                    tokens.extend(arg.to_token_stream());
+                    tokens.push(TokenTree::Punct(Punct::new(',', Alone)));
+                }
+                tokens.push(TokenTree::Literal(proc_macro2::Literal::string(&fmt)));
+                for arg in var_args {
+                    tokens.push(TokenTree::Punct(Punct::new(',', Alone)));
+                    tokens.extend(arg.to_token_stream());
+                }
+                let mac = mk().mac_expr(mk().mac(
+                    vec![name],
+                    tokens.into_iter().collect::<TokenStream>(),
+                    MacroDelimiter::Paren(Default::default()),
+                ));
+                Ok::<_, TranslationError>(WithStmts::new_val(mac))
+            })
+        })?;
+        call.set_unsafe();
+        Ok(Some(call))
+    }
+
+    /// The `macro_rules!` shims for the variadic calls translated in this file
+    pub fn variadic_shim_macros(&self) -> Vec<Box<Item>> {
+        self.variadic_shims
+            .borrow_mut()
+            .drain(..)
+            .filter_map(|(name, shim)| {
+                let fixed = (0..shim.fixed).map(|i| format!("f{}", i)).collect::<Vec<_>>();
+                let arms = shim.arms.iter().map(|(fmt, (fmt_expr, tys))| {
+                    let vars = (0..tys.len()).map(|i| format!("a{}", i)).collect::<Vec<_>>();
+                    let pattern = fixed
+                        .iter()
+                        .map(|f| format!("${}:expr", f))
+                        .chain(std::iter::once(
+                            proc_macro2::Literal::string(fmt).to_string(),
+                        ))
+                        .chain(vars.iter().map(|a| format!("${}:expr", a)))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    let lets = fixed
+                        .iter()
+                        .map(|f| format!("let {0} = ${0};", f))
+                        .chain(
+                            vars.iter()
+                                .zip(tys)
+                                .map(|(a, ty)| format!("let {0}: {1} = ${0};", a, ty)),
+                        )
+                        .collect::<String>();
+                    let call_args = fixed
+                        .iter()
+                        .map(String::as_str)
+                        .chain(std::iter::once(fmt_expr.as_str()))
+                        .chain(vars.iter().map(String::as_str))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    format!(
+                        "({} $(,)?) => {{{{ {} {}({}) }}}};",
+                        pattern, lets, shim.callee, call_args
+                    )
+                });
+                let src = format!("macro_rules! {} {{ {} }}", name, arms.collect::<String>());
+                match syn::parse_str::<Item>(&src) {
+                    Ok(item) => Some(Box::new(item)),
+                    Err(e) => {
+                        warn!("Failed to generate the variadic shim for {}: {}", name, e);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,294 @@
+//! This module implements the WebAssembly profile of `--target wasm32-unknown-unknown`, whose
+//! translations are embedded into a WebAssembly module as is, without libc or `wasm-bindgen`:
+//!
+//! - the C types become the Rust types of the same width, as with `--fixed-width-ints`, so the
+//!   translation doesn't depend on the `libc` crate
+//! - the libc string and memory functions declared or used through their builtins are defined
+//!   in the module as private shims, on top of the `core::ptr` intrinsics for the memory ones
+//! - the exported functions stay `#[no_mangle] pub unsafe extern "C"`, which makes them the
+//!   exports of the WebAssembly module
+//!
+//! ```ignore
+//! unsafe extern "C" fn strlen(s: *const i8) -> usize {
+//!     let mut n = 0;
+//!     while *s.add(n) != 0 {
+//!         n += 1;
+//!     }
+//!     n
+//! }
+//! ```
+//!
+//! The shims are defined with the types C has on `wasm32`. Other libc functions are still
+//! declared `extern`, and have to be provided by the embedder.
+
+use super::*;
+
+/// The shims of the libc functions, by name
+const SHIMS: &[(&str, &str)] = &[
+    (
+        "memcpy",
+        r#"unsafe extern "C" fn memcpy(
+            dst: *mut ::core::ffi::c_void,
+            src: *const ::core::ffi::c_void,
+            n: usize,
+        ) -> *mut ::core::ffi::c_void {
+            ::core::ptr::copy_nonoverlapping(src as *const u8, dst as *mut u8, n);
+            dst
+        }"#,
+    ),
+    (
+        "memmove",
+        r#"unsafe extern "C" fn memmove(
+            dst: *mut ::core::ffi::c_void,
+            src: *const ::core::ffi::c_void,
+            n: usize,
+        ) -> *mut ::core::ffi::c_void {
+            ::core::ptr::copy(src as *const u8, dst as *mut u8, n);
+            dst
+        }"#,
+    ),
+    (
+        "memset",
+        r#"unsafe extern "C" fn memset(
+            dst: *mut ::core::ffi::c_void,
+            c: i32,
+            n: usize,
+        ) -> *mut ::core::ffi::c_void {
+            ::core::ptr::write_bytes(dst as *mut u8, c as u8, n);
+            dst
+        }"#,
+    ),
+    (
+        "memcmp",
+        r#"unsafe extern "C" fn memcmp(
+            a: *const ::core::ffi::c_void,
+            b: *const ::core::ffi::c_void,
+            n: usize,
+        ) -> i32 {
+            let (a, b) = (a as *const u8, b as *const u8);
+            for i in 0..n {
+                let (x, y) = (*a.add(i), *b.add(i));
+                if x != y {
+                    return x as i32 - y as i32;
+                }
+            }
+            0
+        }"#,
+    ),
+    (
+        "memchr",
+        r#"unsafe extern "C" fn memchr(
+            s: *const ::core::ffi::c_void,
+            c: i32,
+            n: usize,
+        ) -> *mut ::core::ffi::c_void {
+            let s = s as *const u8;
+            for i in 0..n {
+                if *s.add(i) == c as u8 {
+                    return s.add(i) as *mut ::core::ffi::c_void;
+                }
+            }
+            ::core::ptr::null_mut()
+        }"#,
+    ),
+    (
+        "strlen",
+        r#"unsafe extern "C" fn strlen(s: *const i8) -> usize {
+            let mut n = 0;
+            while *s.add(n) != 0 {
+                n += 1;
+            }
+            n
+        }"#,
+    ),
+    (
+        "strnlen",
+        r#"unsafe extern "C" fn strnlen(s: *const i8, max: usize) -> usize {
+            let mut n = 0;
+            while n < max && *s.add(n) != 0 {
+                n += 1;
+            }
+            n
+        }"#,
+    ),
+    (
+        "strcmp",
+        r#"unsafe extern "C" fn strcmp(a: *const i8, b: *const i8) -> i32 {
+            let mut i = 0;
+            loop {
+                let (x, y) = (*a.add(i) as u8, *b.add(i) as u8);
+                if x != y || x == 0 {
+                    return x as i32 - y as i32;
+                }
+                i += 1;
+            }
+        }"#,
+    ),
+    (
+        "strncmp",
+        r#"unsafe extern "C" fn strncmp(a: *const i8, b: *const i8, n: usize) -> i32 {
+            for i in 0..n {
+                let (x, y) = (*a.add(i) as u8, *b.add(i) as u8);
+                if x != y || x == 0 {
+                    return x as i32 - y as i32;
+                }
+            }
+            0
+        }"#,
+    ),
+    (
+        "strcpy",
+        r#"unsafe extern "C" fn strcpy(dst: *mut i8, src: *const i8) -> *mut i8 {
+            let mut i = 0;
+            loop {
+                *dst.add(i) = *src.add(i);
+                if *src.add(i) == 0 {
+                    return dst;
+                }
+                i += 1;
+            }
+        }"#,
+    ),
+    (
+        "strncpy",
+        r#"unsafe extern "C" fn strncpy(dst: *mut i8, src: *const i8, n: usize) -> *mut i8 {
+            let mut i = 0;
+            while i < n && *src.add(i) != 0 {
+                *dst.add(i) = *src.add(i);
+                i += 1;
+            }
+            while i < n {
+                *dst.add(i) = 0;
+                i += 1;
+            }
+            dst
+        }"#,
+    ),
+    (
+        "strcat",
+        r#"unsafe extern "C" fn strcat(dst: *mut i8, src: *const i8) -> *mut i8 {
+            let mut d = dst;
+            while *d != 0 {
+                d = d.add(1);
+            }
+            let mut i = 0;
+            loop {
+                *d.add(i) = *src.add(i);
+                if *src.add(i) == 0 {
+                    return dst;
+                }
+                i += 1;
+            }
+        }"#,
+    ),
+    (
+        "strncat",
+        r#"unsafe extern "C" fn strncat(dst: *mut i8, src: *const i8, n: usize) -> *mut i8 {
+            let mut d = dst;
+            while *d != 0 {
+                d = d.add(1);
+            }
+            let mut i = 0;
+            while i < n && *src.add(i) != 0 {
+                *d.add(i) = *src.add(i);
+                i += 1;
+            }
+            *d.add(i) = 0;
+            dst
+        }"#,
+    ),
+    (
+        "strchr",
+        r#"unsafe extern "C" fn strchr(s: *const i8, c: i32) -> *mut i8 {
+            let mut p = s;
+            loop {
+                if *p == c as i8 {
+                    return p as *mut i8;
+                }
+                if *p == 0 {
+                    return ::core::ptr::null_mut();
+                }
+                p = p.add(1);
+            }
+        }"#,
+    ),
+    (
+        "strrchr",
+        r#"unsafe extern "C" fn strrchr(s: *const i8, c: i32) -> *mut i8 {
+            let mut last = ::core::ptr::null_mut();
+            let mut p = s;
+            loop {
+                if *p == c as i8 {
+                    last = p as *mut i8;
+                }
+                if *p == 0 {
+                    return last;
+                }
+                p = p.add(1);
+            }
+        }"#,
+    ),
+    (
+        "strstr",
+        r#"unsafe extern "C" fn strstr(haystack: *const i8, needle: *const i8) -> *mut i8 {
+            let mut h = haystack;
+            loop {
+                let mut i = 0;
+                while *needle.add(i) != 0 && *h.add(i) == *needle.add(i) {
+                    i += 1;
+                }
+                if *needle.add(i) == 0 {
+                    return h as *mut i8;
+                }
+                if *h == 0 {
+                    return ::core::ptr::null_mut();
+                }
+                h = h.add(1);
+            }
+        }"#,
+    ),
+];
+
+impl<'c> Translation<'c> {
+    /// Define the libc function `name` in the module if it has a shim and the translation is for
+    /// WebAssembly. Returns whether it does.
+    pub fn use_wasm_shim(&self, name: &str) -> bool {
+        if !self.tcfg.is_wasm() {
+            return false;
+        }
+        match SHIMS.iter().find(|(shim, _)| *shim == name) {
+            Some(&(shim, _)) => {
+                self.wasm_shims.borrow_mut().insert(shim);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Translate a call to `__builtin_<name>`, for one of the libc string and memory functions,
+    /// into a call to the shim of the function
+    pub fn convert_wasm_builtin(
+        &self,
+        builtin_name: &str,
+        ctx: ExprContext,
+        args: &[CExprId],
+    ) -> Result<WithStmts<Box<Expr>>, TranslationError> {
+        let name = &builtin_name["__builtin_".len()..];
+        if !self.use_wasm_shim(name) {
+            Err(format_err!("{} has no shim for WebAssembly", builtin_name))?;
+        }
+        let args = self.convert_exprs(ctx.used(), args)?;
+        Ok(args.map(|args| mk().call_expr(mk().path_expr(vec![name]), args)))
+    }
+
+    /// The shims of the libc functions used by the module
+    pub fn wasm_shim_items(&self) -> Vec<Box<Item>> {
+        self.wasm_shims
+            .borrow()
+            .iter()
+            .filter_map(|name| SHIMS.iter().find(|(shim, _)| shim == name))
+            .map(|(_, src)| syn::parse_str::<Item>(src).expect("Invalid WebAssembly shim"))
+            .map(Box::new)
+            .collect()
+    }
+}
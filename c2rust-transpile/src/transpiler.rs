@@ -0,0 +1,233 @@
+//! Library API for translating C files in memory.
+//!
+//! Tools embedding the transpiler, such as editors and CI bots, translate files without writing
+//! anything and receive the diagnostics through a callback rather than on stderr. Each call
+//! passes the messages it logs to the sink of its own `Transpiler`, so several can translate
+//! concurrently on different threads. To do so, the process-wide `log` logger is replaced by
+//! one dispatching to these sinks, which drops messages logged outside of translations.
+//!
+//! ```no_run
+//! use c2rust_transpile::{Transpiler, TranspilerConfig};
+//!
+//! let config = TranspilerConfig {
+//!     translate_const_macros: true,
+//!     ..TranspilerConfig::default()
+//! };
+//! let transpiler = Transpiler::new(config)
+//!     .clang_args(vec!["-DNDEBUG"])
+//!     .on_diagnostic(|diagnostic| eprintln!("{}", diagnostic.message));
+//! let translated = transpiler.transpile_file("src/inflate.c".as_ref()).unwrap();
+//! println!("{}", translated.source);
+//! ```
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use failure::{format_err, Error};
+
+use crate::build_system::{write_compile_commands, CompileEntry};
+use crate::c_ast::ConversionContext;
+use crate::diagnostics::{self, Diagnostic, DiagnosticMessage, DiagnosticSink};
+use crate::{format_output, get_extra_args_macos, translator, ExternCrate, TranspilerConfig};
+
+/// Translates C files with a configuration
+pub struct Transpiler {
+    tcfg: TranspilerConfig,
+    compile_commands: Option<PathBuf>,
+    clang_args: Vec<String>,
+    sink: Option<DiagnosticSink>,
+}
+
+/// The translation of a C file into a Rust module
+#[derive(Debug)]
+pub struct TranslatedFile {
+    /// The source of the module
+    pub source: String,
+    /// Files to write next to the module, such as extracted tables, by name
+    pub extra_files: Vec<(String, String)>,
+    /// The crate attributes the module needs, such as `feature` and its features
+    pub pragmas: Vec<(String, Vec<String>)>,
+    /// The crates the module uses
+    pub crates: Vec<ExternCrate>,
+}
+
+impl TranslatedFile {
+    /// Parse the source of the module. The comments of the translation are not part of it.
+    pub fn syntax_tree(&self) -> syn::Result<syn::File> {
+        syn::parse_file(&self.source)
+    }
+}
+
+impl Transpiler {
+    pub fn new(tcfg: TranspilerConfig) -> Self {
+        Transpiler {
+            tcfg,
+            compile_commands: None,
+            clang_args: vec![],
+            sink: None,
+        }
+    }
+
+    /// Parse files with their commands in the compilation database `cc_db`. Otherwise, they are
+    /// parsed with the arguments of `clang_args` only.
+    pub fn compile_commands(mut self, cc_db: impl Into<PathBuf>) -> Self {
+        self.compile_commands = Some(cc_db.into());
+        self
+    }
+
+    /// Extra arguments to parse files with
+    pub fn clang_args<S: Into<String>>(mut self, args: impl IntoIterator<Item = S>) -> Self {
+        self.clang_args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Pass the diagnostics, and the other messages that are logged, to `sink` instead of
+    /// writing them to stderr. `sink` is called on the thread translating.
+    pub fn on_diagnostic(
+        mut self,
+        sink: impl Fn(&DiagnosticMessage) + Send + Sync + 'static,
+    ) -> Self {
+        self.sink = Some(Arc::new(sink));
+        self
+    }
+
+    /// Translate the C file `path` into a Rust module, without writing any files. With
+    /// `fail_on_error`, declarations that fail to translate make this return an error.
+    pub fn transpile_file(&self, path: &Path) -> Result<TranslatedFile, Error> {
+        let tcfg = &self.tcfg;
+        let sink = self.sink.clone().unwrap_or_else(|| Arc::new(write_to_stderr));
+        diagnostics::with_sink(
+            tcfg.enabled_warnings.clone(),
+            tcfg.allowed_warnings.clone(),
+            tcfg.denied_warnings.clone(),
+            tcfg.log_level,
+            sink,
+            || self.transpile_file_logged(path),
+        )
+    }
+
+    fn transpile_file_logged(&self, path: &Path) -> Result<TranslatedFile, Error> {
+        let input_path = path
+            .canonicalize()
+            .map_err(|e| format_err!("{}: {}", path.display(), e))?;
+        let tcfg = self.tcfg.file_config(&input_path);
+
+        // A compilation database written for the file alone is deleted once the file is parsed
+        let mut db_dir = None;
+        let cc_db = match &self.compile_commands {
+            Some(cc_db) => cc_db.clone(),
+            None => {
+                let dir = tempfile::Builder::new().prefix("c2rust-transpile").tempdir()?;
+                let dir = db_dir.insert(dir);
+                single_file_db(dir.path(), &input_path, &self.clang_args)?
+            }
+        };
+        let mut clang_args = match tcfg.target {
+            Some(ref target) if !target.contains("-apple-") => vec![],
+            _ => get_extra_args_macos(),
+        };
+        clang_args.extend(tcfg.target.iter().map(|target| format!("--target={}", target)));
+        if self.compile_commands.is_some() {
+            clang_args.extend(self.clang_args.iter().cloned());
+        }
+        let clang_args = clang_args.iter().map(AsRef::as_ref).collect::<Vec<&str>>();
+
+        let untyped_context = c2rust_ast_exporter::get_untyped_ast(
+            &input_path,
+            &cc_db,
+            &clang_args,
+            tcfg.debug_ast_exporter,
+        )
+        .map_err(|e| format_err!("Unable to parse {}: {}", input_path.display(), e))?;
+        drop(db_dir);
+        let conv = ConversionContext::new(&untyped_context);
+        if conv.invalid_clang_ast && tcfg.fail_on_error {
+            return Err(format_err!("The Clang AST of {} was invalid", input_path.display()));
+        }
+
+        let (source, pragmas, crates, extra_files, _, _) =
+            translator::translate(conv.typed_context, tcfg, input_path.clone(), &HashSet::new())?;
+        Ok(TranslatedFile {
+            source: format_output(tcfg, &input_path, source),
+            extra_files,
+            pragmas,
+            crates: crates.into_iter().collect(),
+        })
+    }
+}
+
+/// The sink of a `Transpiler` without one
+fn write_to_stderr(message: &DiagnosticMessage) {
+    let level = message.level.to_string().to_lowercase();
+    let code = match message.diagnostic.and_then(Diagnostic::code) {
+        Some(code) => format!("[{}]", code),
+        None => String::new(),
+    };
+    match &message.location {
+        Some(loc) => eprintln!("{}{}: {}: {}", level, code, loc, message.message),
+        None => eprintln!("{}{}: {}", level, code, message.message),
+    }
+}
+
+/// Write a compilation database compiling `file` with `clang_args` only to `dir`
+pub(crate) fn single_file_db(
+    dir: &Path,
+    file: &Path,
+    clang_args: &[String],
+) -> Result<PathBuf, Error> {
+    let mut arguments = vec!["cc".to_owned(), "-c".to_owned()];
+    arguments.extend(clang_args.iter().cloned());
+    arguments.push(file.display().to_string());
+    let entry = CompileEntry {
+        directory: file.parent().unwrap_or(dir).to_owned(),
+        file: file.to_owned(),
+        arguments,
+        output: None,
+    };
+    write_compile_commands(dir, &[entry])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::Mutex;
+
+    #[test]
+    fn transpile_small_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("add.c");
+        fs::write(&path, "int add(int a, int b) { return a + b; }\n").unwrap();
+
+        let translated = Transpiler::new(TranspilerConfig::default())
+            .transpile_file(&path)
+            .unwrap();
+        assert!(translated.source.contains("fn add("));
+        translated.syntax_tree().unwrap();
+    }
+
+    #[test]
+    fn fail_on_error_returns_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("reverse.c");
+        let source = "unsigned reverse(unsigned x) { return __builtin_bitreverse32(x); }\n";
+        fs::write(&path, source).unwrap();
+
+        let failures = Arc::new(Mutex::new(vec![]));
+        let sink_failures = Arc::clone(&failures);
+        let config = TranspilerConfig {
+            fail_on_error: true,
+            ..TranspilerConfig::default()
+        };
+        let result = Transpiler::new(config)
+            .on_diagnostic(move |message| {
+                if message.diagnostic == Some(Diagnostic::TranslationFailure) {
+                    sink_failures.lock().unwrap().push(message.message.clone());
+                }
+            })
+            .transpile_file(&path);
+        assert!(result.is_err());
+        assert_eq!(failures.lock().unwrap().len(), 1);
+    }
+}
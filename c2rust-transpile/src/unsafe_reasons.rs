@@ -0,0 +1,108 @@
+//! Report why the translated code needs `unsafe` (`--emit-unsafe-reasons`).
+//!
+//! The translator records every operation that is only allowed in `unsafe` Rust as it emits it,
+//! at the C expression or statement the operation is translated from, so C code translated into
+//! safe Rust is not reported: `printf` calls translated with `--translate-fmt`, `<ctype.h>` calls
+//! translated with `--ascii-char-classes`, reads of tables translated into immutable statics and
+//! functions replaced with stubs. The operations are written to a JSON file next to the
+//! translated one, keyed by their C source location and the name of the enclosing function:
+//!
+//! ```json
+//! [{"file": "foo.c", "line": 12, "column": 5, "function": "foo", "reason": "extern_call"}]
+//! ```
+
+use indexmap::IndexSet;
+use serde_derive::Serialize;
+
+/// Why an operation needs `unsafe`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsafeReason {
+    /// Dereference of a raw pointer (`*p`, `p->field`)
+    RawPointerDeref,
+    /// Dereference of a raw pointer offset by an index (`p[i]`, `*(p + i)`)
+    PointerArithmeticDeref,
+    /// Access to a field of a union
+    UnionFieldAccess,
+    /// Call to a function defined outside of the translated code
+    ExternCall,
+    /// Call to a translated function, all of which are `unsafe fn`
+    UnsafeFnCall,
+    /// Call through a function pointer
+    FnPointerCall,
+    /// Access to a mutable global or static variable (`static mut`)
+    StaticMutAccess,
+    /// Read or write of a `volatile` object
+    VolatileAccess,
+    /// Inline assembly
+    InlineAsm,
+}
+
+/// An operation that needs `unsafe`, located in the C source
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+pub struct UnsafeOperation {
+    pub file: Option<String>,
+    pub line: u64,
+    pub column: u64,
+    pub function: String,
+    pub reason: UnsafeReason,
+}
+
+/// The operations recorded by the translator, without the duplicates recorded when an expression
+/// is translated more than once
+pub fn unique_operations(operations: &[UnsafeOperation]) -> Vec<&UnsafeOperation> {
+    operations
+        .iter()
+        .collect::<IndexSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{Transpiler, TranspilerConfig};
+
+    #[test]
+    fn reasons_of_emitted_operations() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = "\
+#include <stdio.h>
+extern int ext(int *p);
+int sum(int *p) { return *p + p[1] + ext(p); }
+unsigned reversed(unsigned *x) { return __builtin_bitreverse32(*x); }
+void greet(void) { printf(\"%d\\n\", 1); }
+";
+        let path = dir.path().join("ops.c");
+        fs::write(&path, source).unwrap();
+
+        let config = TranspilerConfig {
+            emit_unsafe_reasons: true,
+            translate_fmt: true,
+            ..TranspilerConfig::default()
+        };
+        let translated = Transpiler::new(config).transpile_file(&path).unwrap();
+        let (_, json) = translated
+            .extra_files
+            .iter()
+            .find(|(name, _)| name == "ops.unsafe.json")
+            .unwrap();
+        let operations: Vec<serde_json::Value> = serde_json::from_str(json).unwrap();
+        let mut reasons = operations
+            .iter()
+            .map(|op| (op["function"].as_str().unwrap(), op["reason"].as_str().unwrap()))
+            .collect::<Vec<_>>();
+        reasons.sort_unstable();
+        // `reversed` is replaced with a stub, and `printf` is translated into `print!`
+        assert_eq!(
+            reasons,
+            [
+                ("sum", "extern_call"),
+                ("sum", "pointer_arithmetic_deref"),
+                ("sum", "raw_pointer_deref"),
+            ]
+        );
+        assert!(operations.iter().all(|op| op["line"] == 3));
+    }
+}
@@ -0,0 +1,34 @@
+use clap::{load_yaml, App};
+use std::env;
+use std::path::PathBuf;
+use std::process;
+
+use c2rust_transpile::build_system::capture;
+
+fn main() {
+    // The compilers the build runs are links to this executable
+    if capture::is_shim_invocation() {
+        capture::run_shim();
+    }
+
+    let yaml = load_yaml!("../capture-build.yaml");
+    let matches = App::from_yaml(yaml).get_matches();
+
+    let command = matches
+        .values_of("BUILD_COMMAND")
+        .unwrap()
+        .map(String::from)
+        .collect::<Vec<_>>();
+    let output_dir = matches
+        .value_of("output-dir")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| env::current_dir().expect("Unable to get the working directory"));
+
+    match capture::capture(&command, &output_dir) {
+        Ok(code) => process::exit(code),
+        Err(e) => {
+            eprintln!("Capturing the build failed: {}", e);
+            process::exit(1);
+        }
+    }
+}
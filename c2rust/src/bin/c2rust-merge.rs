@@ -0,0 +1,28 @@
+use clap::{load_yaml, App};
+use std::path::Path;
+use std::process;
+
+use c2rust_transpile::merge::{merge, Platform};
+
+fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let yaml = load_yaml!("../merge.yaml");
+    let matches = App::from_yaml(yaml).get_matches();
+
+    let platforms = matches
+        .values_of("TRANSLATIONS")
+        .unwrap()
+        .map(str::parse)
+        .collect::<Result<Vec<Platform>, _>>()
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+    let output_dir = Path::new(matches.value_of("output-dir").unwrap());
+
+    if let Err(e) = merge(&platforms, output_dir) {
+        eprintln!("Merging the translations failed: {}", e);
+        process::exit(1);
+    }
+}
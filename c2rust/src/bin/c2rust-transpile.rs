@@ -1,34 +1,142 @@
-use clap::{load_yaml, App, Values};
+use clap::{load_yaml, App, ArgMatches, Values};
 use regex::Regex;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::process;
 use std::str::FromStr;
 
-use c2rust_transpile::{Diagnostic, ReplaceMode, TranspilerConfig};
+use c2rust_transpile::build_system::{BuildOptions, BuildSystem};
+use c2rust_transpile::config_file::{ConfigFile, OptionValue, Options, CONFIG_FILE};
+use c2rust_transpile::dry_run::DryRun;
+use c2rust_transpile::filters::Filter;
+use c2rust_transpile::{
+    options, AstDumpFormat, BitfieldOrder, Diagnostic, EnumStyle, ReplaceMode, TranspilerConfig,
+};
 
 fn main() {
     let yaml = load_yaml!("../transpile.yaml");
     let matches = App::from_yaml(yaml).get_matches();
 
-    // Build a TranspilerConfig from the command line
+    if let Some(name) = matches.value_of("explain-option") {
+        let name = name.trim_start_matches('-');
+        let help = yaml["args"]
+            .as_vec()
+            .into_iter()
+            .flatten()
+            .find_map(|arg| arg[name]["help"].as_str());
+        match options::explain_option(name, help) {
+            Some(text) => print!("{}", text),
+            None => {
+                eprintln!("Unknown option: --{}", name);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // The project to translate
+    let build_system = matches
+        .value_of("build-system")
+        .map(|name| BuildSystem::from_str(name).unwrap());
     let cc_json_path = Path::new(matches.value_of("COMPILE_COMMANDS").unwrap());
-    let cc_json_path = cc_json_path.canonicalize().unwrap_or_else(|_| {
-        panic!(
-            "Could not find compile_commands.json file at path: {}",
-            cc_json_path.display()
-        )
-    });
+    let cc_json_path = match build_system {
+        // Bazel and Buck projects are given as target labels
+        Some(BuildSystem::Bazel | BuildSystem::Buck) => cc_json_path.to_owned(),
+        _ => cc_json_path.canonicalize().unwrap_or_else(|_| {
+            panic!(
+                "Could not find compile_commands.json file or project at path: {}",
+                cc_json_path.display()
+            )
+        }),
+    };
     let extra_args: Vec<&str> = match matches.values_of("extra-clang-args") {
         Some(args) => args.collect(),
         None => Vec::new(),
     };
 
-    let enabled_warnings: HashSet<Diagnostic> = matches
-        .values_of("warn")
-        .unwrap_or_else(|| Values::default())
-        .map(|s| Diagnostic::from_str(s).unwrap())
+    // The flags of the options, by name
+    let flags: HashMap<&str, String> = yaml["args"]
+        .as_vec()
+        .into_iter()
+        .flatten()
+        .filter_map(|arg| {
+            let (name, arg) = arg.as_hash()?.iter().next()?;
+            let flag = match (arg["long"].as_str(), arg["short"].as_str()) {
+                (Some(long), _) => format!("--{}", long),
+                (None, Some(short)) => format!("-{}", short),
+                _ => return None,
+            };
+            Some((name.as_str()?, flag))
+        })
         .collect();
 
+    // Options from `c2rust.toml` apply unless given on the command line, and those of files and
+    // functions override both
+    let config = match matches
+        .value_of("config")
+        .map(PathBuf::from)
+        .or_else(|| ConfigFile::find(&cc_json_path))
+    {
+        Some(path) => ConfigFile::load(&path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        }),
+        None => ConfigFile::default(),
+    };
+    let options = overlay(
+        &resolve(&config.options, &flags),
+        &given_options(&matches, &flags),
+    );
+    let parse = |options: &Options| {
+        let mut args = vec!["c2rust-transpile".to_owned()];
+        args.extend(flag_args(options, &flags));
+        args.push(matches.value_of("COMPILE_COMMANDS").unwrap().to_owned());
+        transpiler_config(&App::from_yaml(yaml).get_matches_from(args))
+    };
+    let function_configs = |options: &Options| {
+        config
+            .functions
+            .iter()
+            .map(|(name, overrides)| {
+                let options = overlay(options, &resolve(overrides, &flags));
+                (name.clone(), parse(&options))
+            })
+            .collect::<HashMap<_, _>>()
+    };
+
+    let mut tcfg = parse(&options);
+    tcfg.function_configs = function_configs(&options);
+    tcfg.file_configs = config
+        .files
+        .iter()
+        .map(|(path, overrides)| {
+            let options = overlay(&options, &resolve(overrides, &flags));
+            let mut file_tcfg = parse(&options);
+            file_tcfg.function_configs = function_configs(&options);
+            (path.clone(), file_tcfg)
+        })
+        .collect();
+
+    c2rust_transpile::transpile(tcfg, &cc_json_path, &extra_args);
+}
+
+/// Build a TranspilerConfig from the command line
+fn transpiler_config(matches: &ArgMatches) -> TranspilerConfig {
+    let build_system = matches
+        .value_of("build-system")
+        .map(|name| BuildSystem::from_str(name).unwrap());
+
+    let warnings = |name| -> HashSet<Diagnostic> {
+        matches
+            .values_of(name)
+            .unwrap_or_else(|| Values::default())
+            .map(|s| Diagnostic::parse(s).unwrap_or_else(|e| panic!("{}", e)))
+            .collect()
+    };
+    let enabled_warnings = warnings("warn");
+    let allowed_warnings = warnings("allow");
+    let denied_warnings = warnings("deny");
+
     let log_level = match matches.value_of("log-level") {
         Some("off") => log::LevelFilter::Off,
         Some("error") => log::LevelFilter::Error,
@@ -43,6 +151,16 @@ fn main() {
         dump_untyped_context: matches.is_present("dump-untyped-clang-ast"),
         dump_typed_context: matches.is_present("dump-typed-clang-ast"),
         pretty_typed_context: matches.is_present("pretty-typed-clang-ast"),
+        dump_c_ast: if matches.is_present("dump-c-ast") {
+            match matches.value_of("dump-c-ast") {
+                None | Some("text") => Some(AstDumpFormat::Text),
+                Some("json") => Some(AstDumpFormat::Json),
+                _ => panic!("Invalid option"),
+            }
+        } else {
+            None
+        },
+        dump_c_ast_function: matches.value_of("dump-c-ast-function").map(String::from),
         dump_function_cfgs: matches.is_present("dump-function-cfgs"),
         json_function_cfgs: matches.is_present("json-function-cfgs"),
         dump_cfg_liveness: matches.is_present("dump-cfgs-liveness"),
@@ -61,13 +179,14 @@ fn main() {
                 None
             }
         },
+        filter_fns: Filter::new(matches.values_of("filter-fn").into_iter().flatten(), false)
+            .expect("Invalid function filter"),
+        filter_files: Filter::new(matches.values_of("filter-file").into_iter().flatten(), true)
+            .expect("Invalid file filter"),
         debug_relooper_labels: matches.is_present("debug-labels"),
         prefix_function_names: matches.value_of("prefix-function-names").map(String::from),
 
-        // We used to guard asm translation with a command-line
-        // option. Defaulting to enabled now, can add an option to disable if
-        // needed.
-        translate_asm: true,
+        translate_asm: !matches.is_present("no-translate-asm"),
 
         // We used to guard varargs with a command-line option before nightly
         // support landed. We may still want to disable this option to target
@@ -76,15 +195,106 @@ fn main() {
 
         translate_const_macros: matches.is_present("translate-const-macros"),
         translate_fn_macros: matches.is_present("translate-fn-macros"),
+        enum_style: match matches.value_of("enum-style") {
+            Some("consts") => EnumStyle::Consts,
+            Some("rust") => EnumStyle::Rust,
+            _ => panic!("Invalid enum style"),
+        },
+        ascii_char_classes: matches.is_present("ascii-char-classes"),
+        translate_fmt: matches.is_present("translate-fmt"),
+        ndebug_asserts: matches.is_present("ndebug-asserts"),
+        variadic_shims: matches.is_present("variadic-shims"),
+        tagged_unions: matches
+            .values_of("tagged-union")
+            .map(|values| values.map(String::from).collect())
+            .unwrap_or_default(),
+        fn_pointer_unions: matches.is_present("fn-pointer-unions"),
+        emit_unsafe_reasons: matches.is_present("emit-unsafe-reasons"),
+        bindings: matches.is_present("bindings"),
+        report: matches.value_of("report").map(PathBuf::from),
+        dry_run: if matches.is_present("dry-run") {
+            match matches.value_of("dry-run") {
+                None | Some("diff") => Some(DryRun::Diff),
+                Some("list") => Some(DryRun::List),
+                Some("json") => Some(DryRun::Json),
+                _ => panic!("Invalid option"),
+            }
+        } else {
+            None
+        },
+        debug_pointers: matches.is_present("debug-pointers"),
+        large_table_threshold: matches
+            .value_of("large-table-threshold")
+            .map(|threshold| threshold.parse().expect("Invalid large table threshold"))
+            .unwrap(),
+        extract_large_tables: matches.is_present("extract-large-tables"),
+        outline_threshold: matches
+            .value_of("outline-threshold")
+            .map(|threshold| threshold.parse().expect("Invalid outline threshold")),
+        static_cstr_returns: matches.is_present("static-cstr-returns"),
+        box_local_allocs: matches.is_present("box-local-allocs"),
+        slice_fns: matches.is_present("slice-fns"),
+        range_loops: matches.is_present("range-loops"),
+        error_enums: matches.is_present("error-enums"),
+        fixed_width_ints: matches.is_present("fixed-width-ints"),
+        emit_safe_wrappers: matches.is_present("emit-safe-wrappers"),
+        layout_asserts: matches.is_present("layout-asserts"),
+        owned_alloca_strings: matches.is_present("owned-alloca-strings"),
+        derives: matches
+            .values_of("derive")
+            .map(|values| values.map(String::from).collect())
+            .unwrap_or_default(),
+        struct_derives: {
+            let mut struct_derives = HashMap::new();
+            for (name, traits) in matches
+                .values_of("struct-derive")
+                .into_iter()
+                .flatten()
+                .map(parse_struct_derive)
+            {
+                struct_derives
+                    .entry(name)
+                    .or_insert_with(Vec::new)
+                    .extend(traits);
+            }
+            struct_derives
+        },
+        frame_address_intrinsics: matches.is_present("frame-address-intrinsics"),
+        openmp_rayon: matches.is_present("openmp-rayon"),
+        candidate_functions: matches
+            .values_of("candidate-function")
+            .map(|values| values.map(String::from).collect())
+            .unwrap_or_default(),
+        restrict_attrs: matches.is_present("restrict-attrs"),
+        bitfield_order: match matches.value_of("bitfield-order") {
+            Some("target") => BitfieldOrder::Target,
+            Some("lsb0") => BitfieldOrder::Lsb0,
+            Some("msb0") => BitfieldOrder::Msb0,
+            _ => panic!("Invalid bitfield order"),
+        },
+        bitfield_tests: matches.is_present("bitfield-tests"),
+        abi_tests: matches.is_present("abi-tests"),
+        emit_test_stubs: matches.is_present("emit-test-stubs"),
+        ffi_tests: matches.is_present("ffi-tests"),
+        hybrid_build: matches.is_present("hybrid-build"),
+        c_unwind: matches.is_present("c-unwind"),
+        targeted_allows: matches.is_present("targeted-allows"),
         disable_refactoring: matches.is_present("disable-refactoring"),
         preserve_unused_functions: matches.is_present("preserve-unused-functions"),
+        prune_unused: matches.is_present("prune-unused"),
 
         use_c_loop_info: !matches.is_present("ignore-c-loop-info"),
         use_c_multiple_info: !matches.is_present("ignore-c-multiple-info"),
         simplify_structures: !matches.is_present("no-simplify-structures"),
         overwrite_existing: matches.is_present("overwrite-existing"),
+        force: matches.is_present("force"),
         reduce_type_annotations: matches.is_present("reduce-type-annotations"),
         reorganize_definitions: matches.is_present("reorganize-definitions"),
+        header_modules: matches.is_present("header-modules"),
+        share_inline_fns: matches.is_present("share-inline-fns"),
+        emit_src_locs: matches.is_present("emit-src-locs"),
+        migration_status: matches.is_present("migration-status"),
+        fmt: matches.is_present("fmt"),
         emit_modules: matches.is_present("emit-modules"),
         emit_build_files: matches.is_present("emit-build-files"),
         output_dir: matches.value_of("output-dir").map(PathBuf::from),
@@ -101,17 +311,98 @@ fn main() {
         },
         replace_unsupported_decls: ReplaceMode::Extern,
         emit_no_std: matches.is_present("emit-no-std"),
+        build_options: BuildOptions {
+            build_dir: matches.value_of("build-dir").map(PathBuf::from),
+            args: matches
+                .values_of("build-arg")
+                .map(|values| values.map(String::from).collect())
+                .unwrap_or_default(),
+            build_system,
+        },
+        target: matches.value_of("target").map(String::from),
+        crate_name: matches.value_of("crate-name").map(String::from),
+        file_configs: vec![],
+        function_configs: HashMap::new(),
         enabled_warnings,
+        allowed_warnings,
+        denied_warnings,
+        json_diagnostics: matches.is_present("json-diagnostics"),
         log_level,
     };
-    // binaries imply emit-build-files
-    if !tcfg.binaries.is_empty() {
-        tcfg.emit_build_files = true
-    };
-    // emit-build-files implies emit-modules
-    if tcfg.emit_build_files {
-        tcfg.emit_modules = true
-    };
+    tcfg.apply_implications();
+    tcfg
+}
 
-    c2rust_transpile::transpile(tcfg, &cc_json_path, &extra_args);
+/// The options given on the command line
+fn given_options(matches: &ArgMatches, flags: &HashMap<&str, String>) -> Options {
+    flags
+        .keys()
+        .filter(|&&name| matches.occurrences_of(name) > 0)
+        .map(|&name| {
+            let value = match matches.values_of(name) {
+                Some(values) => OptionValue::Values(values.map(String::from).collect()),
+                None => OptionValue::Switch(true),
+            };
+            (name.to_owned(), value)
+        })
+        .collect()
+}
+
+/// Name the options of `c2rust.toml` by their flags, turning off the switches with `no-` flags
+/// by their positive names
+fn resolve(options: &Options, flags: &HashMap<&str, String>) -> Options {
+    options
+        .iter()
+        .map(|(name, value)| {
+            let negated = format!("no-{}", name);
+            match *value {
+                _ if flags.contains_key(name.as_str()) => (name.clone(), value.clone()),
+                OptionValue::Switch(on) if flags.contains_key(negated.as_str()) => {
+                    (negated, OptionValue::Switch(!on))
+                }
+                _ => {
+                    eprintln!("Unknown option in {}: {}", CONFIG_FILE, name);
+                    process::exit(1);
+                }
+            }
+        })
+        .collect()
+}
+
+/// `base` with the options of `overrides` replacing its own
+fn overlay(base: &Options, overrides: &Options) -> Options {
+    let mut options = base.clone();
+    options.extend(overrides.clone());
+    options
+}
+
+/// The command-line arguments giving `options`
+fn flag_args(options: &Options, flags: &HashMap<&str, String>) -> Vec<String> {
+    let mut args = vec![];
+    for (name, value) in options {
+        let flag = &flags[name.as_str()];
+        match value {
+            OptionValue::Switch(true) => args.push(flag.clone()),
+            OptionValue::Switch(false) => {}
+            OptionValue::Values(values) => {
+                args.extend(values.iter().map(|value| format!("{}={}", flag, value)))
+            }
+        }
+    }
+    args
+}
+
+/// Parse a `--struct-derive STRUCT=TRAITS` value
+fn parse_struct_derive(value: &str) -> (String, Vec<String>) {
+    let (name, traits) = value
+        .split_once('=')
+        .unwrap_or_else(|| panic!("Invalid struct derive, expected STRUCT=TRAITS: {}", value));
+    let traits = traits
+        .split(',')
+        .map(|name| match name.trim() {
+            name @ ("Debug" | "Default" | "PartialEq") => name.to_owned(),
+            name => panic!("Invalid derive for {}: {}", value, name),
+        })
+        .collect();
+    (name.to_owned(), traits)
 }
@@ -73,7 +73,46 @@ class CFile:
         self.disallow_current_block = "disallow_current_block" in flags
         self.translate_const_macros = "translate_const_macros" in flags
         self.reorganize_definitions = "reorganize_definitions" in flags
+        self.header_modules = "header_modules" in flags
+        self.share_inline_fns = "share_inline_fns" in flags
+        self.emit_src_locs = "emit_src_locs" in flags
         self.emit_build_files = "emit_build_files" in flags
+        self.enum_style_rust = "enum_style_rust" in flags
+        self.ascii_char_classes = "ascii_char_classes" in flags
+        self.translate_fmt = "translate_fmt" in flags
+        self.variadic_shims = "variadic_shims" in flags
+        self.tagged_unions = sorted(flag[13:] for flag in flags if flag.startswith("tagged_union_"))
+        self.fn_pointer_unions = "fn_pointer_unions" in flags
+        self.debug_pointers = "debug_pointers" in flags
+        self.extract_large_tables = "extract_large_tables" in flags
+        self.outline_threshold = next(
+            (flag[18:] for flag in flags if flag.startswith("outline_threshold_")), None)
+        self.static_cstr_returns = "static_cstr_returns" in flags
+        self.box_local_allocs = "box_local_allocs" in flags
+        self.slice_fns = "slice_fns" in flags
+        self.range_loops = "range_loops" in flags
+        self.fixed_width_ints = "fixed_width_ints" in flags
+        self.emit_safe_wrappers = "emit_safe_wrappers" in flags
+        self.layout_asserts = "layout_asserts" in flags
+        self.owned_alloca_strings = "owned_alloca_strings" in flags
+        self.derives = sorted(flag[7:] for flag in flags if flag.startswith("derive_"))
+        self.frame_address_intrinsics = "frame_address_intrinsics" in flags
+        self.openmp_rayon = "openmp_rayon" in flags
+        self.candidate_functions = sorted(
+            flag[19:] for flag in flags if flag.startswith("candidate_function_"))
+        self.restrict_attrs = "restrict_attrs" in flags
+        self.bitfield_order = next(
+            (flag[15:] for flag in flags if flag.startswith("bitfield_order_")), None)
+        self.c_unwind = "c_unwind" in flags
+        self.emit_no_std = "emit_no_std" in flags
+        # The extern fallbacks of the functions replaced with stubs are checked to compile too
+        self.check_extern_fallback = "check_extern_fallback" in flags
+        # Files using newer C features, such as `#embed`, are skipped with older versions of clang
+        self.min_clang_version = int(next(
+            (flag[18:] for flag in flags if flag.startswith("min_clang_version_")), 0))
+        # Files of the same group are translated together, with the flags of the first one
+        self.translation_group = next(
+            (flag[18:] for flag in flags if flag.startswith("translation_group_")), None)
 
     def translate(self, cc_db, ld_lib_path, extra_args: List[str] = []) -> RustFile:
         extensionless_file, _ = os.path.splitext(self.path)
@@ -96,8 +135,64 @@ class CFile:
             args.append("--translate-const-macros")
         if self.reorganize_definitions:
             args.append("--reorganize-definitions")
+        if self.header_modules:
+            args.append("--header-modules")
+        if self.share_inline_fns:
+            args.append("--share-inline-fns")
+        if self.emit_src_locs:
+            args.append("--emit-src-locs")
         if self.emit_build_files:
             args.append("--emit-build-files")
+        if self.enum_style_rust:
+            args.append("--enum-style=rust")
+        if self.ascii_char_classes:
+            args.append("--ascii-char-classes")
+        if self.translate_fmt:
+            args.append("--translate-fmt")
+        if self.variadic_shims:
+            args.append("--variadic-shims")
+        for name in self.tagged_unions:
+            args.append("--tagged-union=" + name)
+        if self.fn_pointer_unions:
+            args.append("--fn-pointer-unions")
+        if self.debug_pointers:
+            args.append("--debug-pointers")
+        if self.extract_large_tables:
+            args.append("--extract-large-tables")
+        if self.outline_threshold is not None:
+            args.append("--outline-threshold=" + self.outline_threshold)
+        if self.static_cstr_returns:
+            args.append("--static-cstr-returns")
+        if self.box_local_allocs:
+            args.append("--box-local-allocs")
+        if self.slice_fns:
+            args.append("--slice-fns")
+        if self.range_loops:
+            args.append("--range-loops")
+        if self.fixed_width_ints:
+            args.append("--fixed-width-ints")
+        if self.emit_safe_wrappers:
+            args.append("--emit-safe-wrappers")
+        if self.layout_asserts:
+            args.append("--layout-asserts")
+        if self.owned_alloca_strings:
+            args.append("--owned-alloca-strings")
+        for name in self.derives:
+            args.append("--derive=" + name)
+        if self.frame_address_intrinsics:
+            args.append("--frame-address-intrinsics")
+        if self.openmp_rayon:
+            args.append("--openmp-rayon")
+        for name in self.candidate_functions:
+            args.append("--candidate-function=" + name)
+        if self.restrict_attrs:
+            args.append("--restrict-attrs")
+        if self.bitfield_order is not None:
+            args.append("--bitfield-order=" + self.bitfield_order)
+        if self.c_unwind:
+            args.append("--c-unwind")
+        if self.emit_no_std:
+            args.append("--emit-no-std")
 
         if self.logLevel == 'DEBUG':
             args.append("--log-level=debug")
@@ -134,6 +229,11 @@ def rustc_has_target(target: str) -> bool:
     retcode, stdout, stderr = rustc[args].run(retcode=None)
     return not "target may not be installed" in stderr
 
+def clang_major_version() -> int:
+    _, stdout, _ = clang["--version"].run()
+    version = re.search(r"clang version (\d+)", stdout)
+    return int(version.group(1)) if version else 0
+
 def target_args(target: Optional[str]) -> List[str]:
     if target:
         return ["-target", target]
@@ -308,20 +408,37 @@ class TestDirectory:
         if message:
             sys.stdout.write(message)
 
-    def _generate_cc_db(self, c_file_path: str) -> None:
-        directory, cfile = os.path.split(c_file_path)
+    def _translation_units(self) -> List[List[CFile]]:
+        """
+        Group the C files that are translated together, so that the header
+        declarations and functions they share are merged across the files.
+        """
+        units = []
+        groups = {}
+        for c_file in self.c_files:
+            if c_file.translation_group is None:
+                units.append([c_file])
+            elif c_file.translation_group in groups:
+                groups[c_file.translation_group].append(c_file)
+            else:
+                groups[c_file.translation_group] = [c_file]
+                units.append(groups[c_file.translation_group])
+        return units
+
+    def _generate_cc_db(self, c_file_paths: List[str]) -> None:
+        directory = os.path.dirname(c_file_paths[0])
 
         target_args = '"-target", "{}", '.format(self.target) if self.target else ""
 
-        compile_commands = """ \
-        [
+        entries = []
+        for c_file_path in c_file_paths:
+            entries.append(""" \
           {{
             "arguments": [ "cc", "-D_FORTIFY_SOURCE=0", "-c", {2}"{0}" ],
             "directory": "{1}",
             "file": "{0}"
-          }}
-        ]
-        """.format(cfile, directory, target_args)
+          }}""".format(os.path.basename(c_file_path), directory, target_args))
+        compile_commands = "[\n" + ",\n".join(entries) + "\n]\n"
 
         cc_db = os.path.join(directory, "compile_commands.json")
 
@@ -333,6 +450,32 @@ class TestDirectory:
         with open(cc_db, 'w') as fh:
             fh.write(compile_commands)
 
+    def _add_translated_file(self, rust_file_builder: RustFileBuilder, c_file: CFile,
+                             translated_rust_file: RustFile) -> None:
+        self.generated_files["rust_src"].append(translated_rust_file)
+        if c_file.extract_large_tables:
+            rust_path = Path(translated_rust_file.path)
+            self.generated_files["rust_src"].extend(
+                rust_path.parent.glob(rust_path.stem + "_*.rs"))
+        if c_file.emit_build_files:
+            self.generated_files["rust_src"].append(self.full_path + "/src/Cargo.toml")
+            self.generated_files["rust_src"].append(self.full_path + "/src/build.rs")
+            self.generated_files["rust_src"].append(self.full_path + "/src/c2rust-lib.rs")
+            self.generated_files["rust_src"].append(self.full_path + "/src/rust-toolchain")
+
+        _, rust_file_short = os.path.split(translated_rust_file.path)
+        extensionless_rust_file, _ = os.path.splitext(rust_file_short)
+
+        rust_file_builder.add_mod(RustMod(extensionless_rust_file,
+                                          RustVisibility.Public))
+
+        # The translated file imports its header declarations from `crate::headers`
+        if c_file.header_modules or c_file.share_inline_fns:
+            headers_file = os.path.join(os.path.dirname(translated_rust_file.path), "headers.rs")
+            if headers_file not in self.generated_files["rust_src"]:
+                self.generated_files["rust_src"].append(headers_file)
+            rust_file_builder.add_mod(RustMod("headers", RustVisibility.Public))
+
     def run(self) -> List[TestOutcome]:
         outcomes = []
 
@@ -353,6 +496,14 @@ class TestDirectory:
                           Colors.NO_COLOR, description)
             return []
 
+        min_clang_version = max(c_file.min_clang_version for c_file in self.c_files)
+        if min_clang_version > clang_major_version():
+            description = "Needs clang {} or later...".format(min_clang_version)
+            logging.debug("%s:", self.name)
+            logging.debug("%s [ SKIPPED ] %s %s", Colors.OKBLUE,
+                          Colors.NO_COLOR, description)
+            return []
+
         sys.stdout.write("{}:\n".format(self.name))
 
         # .c -> .a
@@ -393,15 +544,16 @@ class TestDirectory:
             ld_lib_path += ':' + pb.local.env['LD_LIBRARY_PATH']
 
         # .c -> .rs
-        for c_file in self.c_files:
-            _, c_file_short = os.path.split(c_file.path)
+        for unit in self._translation_units():
+            c_file = unit[0]
+            c_file_short = ", ".join(os.path.basename(unit_file.path) for unit_file in unit)
             description = "{}: translating the C file into Rust...".format(
                 c_file_short)
 
             # Run the step
             self.print_status(Colors.WARNING, "RUNNING", description)
 
-            self._generate_cc_db(c_file.path)
+            self._generate_cc_db([unit_file.path for unit_file in unit])
 
             try:
                 logging.debug("translating %s", c_file_short)
@@ -417,18 +569,11 @@ class TestDirectory:
                 outcomes.append(TestOutcome.UnexpectedFailure)
                 continue
 
-            self.generated_files["rust_src"].append(translated_rust_file)
-            if c_file.emit_build_files:
-                self.generated_files["rust_src"].append(self.full_path + "/src/Cargo.toml")
-                self.generated_files["rust_src"].append(self.full_path + "/src/build.rs")
-                self.generated_files["rust_src"].append(self.full_path + "/src/c2rust-lib.rs")
-                self.generated_files["rust_src"].append(self.full_path + "/src/rust-toolchain")
-
-            _, rust_file_short = os.path.split(translated_rust_file.path)
-            extensionless_rust_file, _ = os.path.splitext(rust_file_short)
-
-            rust_file_builder.add_mod(RustMod(extensionless_rust_file,
-                                              RustVisibility.Public))
+            self._add_translated_file(rust_file_builder, c_file, translated_rust_file)
+            for unit_file in unit[1:]:
+                extensionless_file, _ = os.path.splitext(unit_file.path)
+                self._add_translated_file(rust_file_builder, c_file,
+                                          RustFile(extensionless_file + ".rs"))
 
         match_arms = []
         rustc_extra_args = ["-C", "target-cpu=native"]
@@ -517,6 +662,30 @@ class TestDirectory:
 
             return outcomes
 
+        if any(c_file.check_extern_fallback for c_file in self.c_files):
+            description = "checking the extern fallbacks of stubs..."
+            self.print_status(Colors.WARNING, "RUNNING", description)
+
+            # A separate target directory keeps the main build from being invalidated
+            args = ["check", "--target-dir", "target/extern_fallback"]
+            if self.target:
+                args.append(["--target", self.target])
+            with pb.local.cwd(self.full_path), \
+                    pb.local.env(RUSTFLAGS="--cfg c2rust_extern_fallback"):
+                retcode, stdout, stderr = cargo[args].run(retcode=None)
+
+            if retcode != 0:
+                self.print_status(Colors.FAIL, "FAILED", "check extern fallbacks")
+                sys.stdout.write('\n')
+                sys.stdout.write(stderr)
+
+                outcomes.append(TestOutcome.UnexpectedFailure)
+            else:
+                self.print_status(Colors.OKGREEN, "OK", "    check extern fallbacks")
+                sys.stdout.write('\n')
+
+                outcomes.append(TestOutcome.Success)
+
         for test_file in self.rs_test_files:
             if not test_file.pass_expected:
                 continue
@@ -0,0 +1,24 @@
+// Jumps to `negative` or `zero` depending on the sign of `x`
+int classify(int x) {
+    asm goto("cmpl $0, %0\n\t"
+             "jl %l1\n\t"
+             "je %l2"
+             :
+             : "r"(x)
+             : "cc"
+             : negative, zero);
+    return 1;
+negative:
+    return -1;
+zero:
+    return 0;
+}
+
+void entry2(const unsigned int buffer_size, int buffer[const])
+{
+    int i = 0;
+
+    buffer[i++] = classify(42);
+    buffer[i++] = classify(-7);
+    buffer[i++] = classify(0);
+}
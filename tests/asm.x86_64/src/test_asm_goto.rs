@@ -0,0 +1,25 @@
+//! feature_asm_goto
+
+use crate::asm_goto::rust_entry2;
+use libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn entry2(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 3;
+
+pub fn test_asm_goto() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [1, -1, 0];
+
+    unsafe {
+        entry2(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_entry2(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
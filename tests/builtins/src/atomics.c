@@ -66,3 +66,49 @@ void new_atomics(const unsigned buffer_size, int buffer[const])
     __atomic_store_n(&x, 0, __ATOMIC_RELAXED);
     buffer[i++] = x;
 }
+
+static int relaxed_order(void) { return __ATOMIC_RELAXED; }
+
+void more_atomics(const unsigned buffer_size, int buffer[const])
+{
+    int i = 0, x = 34, y = 0;
+    int order = relaxed_order();
+
+    __atomic_thread_fence(__ATOMIC_SEQ_CST);
+    __atomic_thread_fence(__ATOMIC_ACQUIRE);
+    __atomic_thread_fence(__ATOMIC_RELAXED);
+    __atomic_signal_fence(__ATOMIC_RELEASE);
+    __atomic_thread_fence(order);
+
+    buffer[i++] = __atomic_load_n(&x, __ATOMIC_ACQUIRE);
+    buffer[i++] = __atomic_load_n(&x, order);
+    __atomic_load(&x, &y, __ATOMIC_SEQ_CST);
+    buffer[i++] = y;
+
+    __atomic_store_n(&x, 12, order);
+    buffer[i++] = x;
+    y = 13;
+    __atomic_store(&x, &y, __ATOMIC_RELEASE);
+    buffer[i++] = x;
+
+    buffer[i++] = __atomic_exchange_n(&x, 21, order);
+    buffer[i++] = x;
+    buffer[i++] = __atomic_fetch_add(&x, 4, order);
+    buffer[i++] = __atomic_sub_fetch(&x, 5, order);
+
+    y = 20;
+    buffer[i++] = __atomic_compare_exchange_n(&x, &y, 30, 0, order, order);
+    buffer[i++] = x;
+    buffer[i++] = __atomic_compare_exchange_n(&x, &y, 40, 0, __ATOMIC_ACQ_REL, order);
+    buffer[i++] = y;
+
+    _Bool flag = 0;
+    buffer[i++] = __atomic_test_and_set(&flag, __ATOMIC_ACQUIRE);
+    buffer[i++] = __atomic_test_and_set(&flag, order);
+    __atomic_clear(&flag, __ATOMIC_RELEASE);
+    buffer[i++] = flag;
+
+    buffer[i++] = __atomic_always_lock_free(sizeof(int), 0);
+    buffer[i++] = __atomic_is_lock_free(sizeof(x), &x);
+    buffer[i++] = __atomic_always_lock_free(3, 0);
+}
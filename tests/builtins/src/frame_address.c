@@ -0,0 +1,9 @@
+//! frame_address_intrinsics
+
+int has_frame_address(void) {
+    return __builtin_frame_address(0) != 0;
+}
+
+int has_return_address(void) {
+    return __builtin_return_address(0) != 0;
+}
@@ -20,4 +20,44 @@ int isnan(double a) {
 
 int isinf_sign(double a) {
     return __builtin_isinf_sign(a);
-}
\ No newline at end of file
+}
+unsigned rotl32(unsigned x, unsigned n) {
+    return __builtin_rotateleft32(x, n);
+}
+
+unsigned char rotr8(unsigned char x, unsigned char n) {
+    return __builtin_rotateright8(x, n);
+}
+
+unsigned long long rotr64(unsigned long long x, unsigned long long n) {
+    return __builtin_rotateright64(x, n);
+}
+
+int clrsb(int a) {
+    return __builtin_clrsb(a);
+}
+
+int clrsbll(long long a) {
+    return __builtin_clrsbll(a);
+}
+
+int clrsb_next(int *a) {
+    return __builtin_clrsb((*a)++);
+}
+
+int parity(unsigned a) {
+    return __builtin_parity(a);
+}
+
+int parityll(unsigned long long a) {
+    return __builtin_parityll(a);
+}
+
+int hinted_count(int *a) {
+    int hits = 0;
+    if (__builtin_expect_with_probability((*a)++ > 0, 1, 0.9))
+        hits++;
+    if (__builtin_unpredictable((*a)++ > 1))
+        hits++;
+    return hits;
+}
@@ -1,14 +1,20 @@
-//! feature_core_intrinsics
-
-use crate::atomics::{rust_atomics_entry, rust_new_atomics};
-use crate::math::{rust_ffs, rust_ffsl, rust_ffsll, rust_isfinite, rust_isinf_sign, rust_isnan};
+//! feature_core_intrinsics, feature_link_llvm_intrinsics
+
+use crate::atomics::{rust_atomics_entry, rust_more_atomics, rust_new_atomics};
+use crate::frame_address::{rust_has_frame_address, rust_has_return_address};
+use crate::math::{
+    rust_clrsb, rust_clrsb_next, rust_clrsbll, rust_ffs, rust_ffsl, rust_ffsll, rust_hinted_count,
+    rust_isfinite, rust_isinf_sign, rust_isnan, rust_parity, rust_parityll, rust_rotl32,
+    rust_rotr64, rust_rotr8,
+};
 use crate::mem_x_fns::{rust_assume_aligned, rust_mem_x};
-use libc::{c_char, c_double, c_int, c_long, c_longlong, c_uint};
+use libc::{c_char, c_double, c_int, c_long, c_longlong, c_uchar, c_uint, c_ulonglong};
 
 #[link(name = "test")]
 extern "C" {
     fn atomics_entry(_: c_uint, _: *mut c_int);
     fn new_atomics(_: c_uint, _: *mut c_int);
+    fn more_atomics(_: c_uint, _: *mut c_int);
     fn mem_x(_: *const c_char, _: *mut c_char);
     fn ffs(_: c_int) -> c_int;
     fn ffsl(_: c_long) -> c_int;
@@ -16,6 +22,17 @@ extern "C" {
     fn isfinite(_: c_double) -> c_int;
     fn isnan(_: c_double) -> c_int;
     fn isinf_sign(_: c_double) -> c_int;
+    fn rotl32(_: c_uint, _: c_uint) -> c_uint;
+    fn rotr8(_: c_uchar, _: c_uchar) -> c_uchar;
+    fn rotr64(_: c_ulonglong, _: c_ulonglong) -> c_ulonglong;
+    fn clrsb(_: c_int) -> c_int;
+    fn clrsbll(_: c_longlong) -> c_int;
+    fn clrsb_next(_: *mut c_int) -> c_int;
+    fn parity(_: c_uint) -> c_int;
+    fn parityll(_: c_ulonglong) -> c_int;
+    fn hinted_count(_: *mut c_int) -> c_int;
+    fn has_frame_address() -> c_int;
+    fn has_return_address() -> c_int;
 }
 
 const BUFFER_SIZE: usize = 1024;
@@ -50,6 +67,22 @@ pub fn test_new_atomics() {
     }
 }
 
+pub fn test_more_atomics() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected = [
+        34, 34, 34, 12, 13, 13, 21, 21, 20, 1, 30, 0, 30, 0, 1, 0, 1, 1, 0,
+    ];
+
+    unsafe {
+        more_atomics(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_more_atomics(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer[..expected.len()], expected);
+    assert_eq!(buffer, rust_buffer);
+}
+
 pub fn test_mem_fns() {
     let const_string = "I am ten!\0";
     let mut buffer = [0; BUFFER_SIZE2];
@@ -85,6 +118,40 @@ pub fn test_ffs() {
     }
 }
 
+pub fn test_bit_builtins() {
+    let values = [
+        0, 1, 2, 0x7f, 0x80, 0x12345678, 0x80000000, 0xdeadbeef, 0xffffffff,
+    ];
+
+    for &x in &values {
+        for n in 0..40 {
+            unsafe {
+                assert_eq!(rotl32(x, n), rust_rotl32(x, n));
+                assert_eq!(rotr8(x as u8, n as u8), rust_rotr8(x as u8, n as u8));
+                let wide = (x as u64) << 17 | 0x5;
+                assert_eq!(rotr64(wide, n as u64), rust_rotr64(wide, n as u64));
+            }
+        }
+
+        unsafe {
+            assert_eq!(clrsb(x as i32), rust_clrsb(x as i32));
+            assert_eq!(clrsbll(-(x as i64)), rust_clrsbll(-(x as i64)));
+            assert_eq!(parity(x), rust_parity(x));
+            assert_eq!(parityll(x as u64 * 3), rust_parityll(x as u64 * 3));
+        }
+    }
+
+    let mut a = -2;
+    let mut rust_a = -2;
+    unsafe {
+        assert_eq!(clrsb_next(&mut a), rust_clrsb_next(&mut rust_a));
+    }
+    assert_eq!(a, rust_a);
+    assert_eq!(unsafe { rust_clrsb(0) }, 31);
+    assert_eq!(unsafe { rust_clrsb(-1) }, 31);
+    assert_eq!(unsafe { rust_clrsb(1) }, 30);
+}
+
 pub fn test_clang9_intrinsics() {
     let pinf = 1.0 / 0.0;
     let ninf = -1.0 / 0.0;
@@ -121,6 +188,17 @@ pub fn test_clang9_intrinsics() {
     }
 }
 
+pub fn test_hints() {
+    for start in 0..3 {
+        let (mut a, mut rust_a) = (start, start);
+        let ret = unsafe { hinted_count(&mut a) };
+        let rust_ret = unsafe { rust_hinted_count(&mut rust_a) };
+
+        assert_eq!(ret, rust_ret);
+        assert_eq!(a, rust_a);
+    }
+}
+
 pub fn test_assume_aligned() {
     let null = std::ptr::null_mut();
 
@@ -128,3 +206,12 @@ pub fn test_assume_aligned() {
         assert_eq!(rust_assume_aligned(null), null);
     }
 }
+
+pub fn test_frame_address() {
+    unsafe {
+        assert_eq!(has_frame_address(), 1);
+        assert_eq!(rust_has_frame_address(), 1);
+        assert_eq!(has_return_address(), 1);
+        assert_eq!(rust_has_return_address(), 1);
+    }
+}
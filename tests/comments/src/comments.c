@@ -66,4 +66,24 @@ int test_fn() {
   /** even a double comment is not turned into a doc comment *//** */
 }
 
+/**
+ * A point, documented with Doxygen comments.
+ */
+struct point {
+  /** The horizontal coordinate */
+  int x;
+  /// The vertical coordinate
+  int y;
+};
+
+/**
+ * @brief Sums the coordinates of a point.
+ *
+ * @param[in] p the point
+ * @return the sum of its coordinates
+ */
+int point_sum(struct point p) {
+  return p.x + p.y;
+}
+
 /* after all functions */
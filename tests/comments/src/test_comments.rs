@@ -1,6 +1,11 @@
-use crate::comments::{rust_test_fn, CONSTANT, CONSTANT1};
+use crate::comments::{point, rust_point_sum, rust_test_fn, CONSTANT, CONSTANT1};
 
 pub fn test_comments() {
     let val = unsafe { rust_test_fn() };
     assert_eq!(6, val);
 }
+
+pub fn test_doc_comments() {
+    let val = unsafe { rust_point_sum(point { x: 2, y: 3 }) };
+    assert_eq!(5, val);
+}
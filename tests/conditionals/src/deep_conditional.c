@@ -0,0 +1,16 @@
+// Generated code can nest `?:` chains thousands of levels deep
+
+// Each step compares against the next value of `__COUNTER__`, so `STEPS_10000`
+// expands to a chain of 10000 conditionals `n <= 0 ? n : n <= 1 ? n : ...`
+#define STEP n <= __COUNTER__ ? n :
+#define STEPS_10 STEP STEP STEP STEP STEP STEP STEP STEP STEP STEP
+#define STEPS_100 STEPS_10 STEPS_10 STEPS_10 STEPS_10 STEPS_10 \
+    STEPS_10 STEPS_10 STEPS_10 STEPS_10 STEPS_10
+#define STEPS_1000 STEPS_100 STEPS_100 STEPS_100 STEPS_100 STEPS_100 \
+    STEPS_100 STEPS_100 STEPS_100 STEPS_100 STEPS_100
+#define STEPS_10000 STEPS_1000 STEPS_1000 STEPS_1000 STEPS_1000 STEPS_1000 \
+    STEPS_1000 STEPS_1000 STEPS_1000 STEPS_1000 STEPS_1000
+
+int deep_conditional(int n) {
+    return STEPS_10000 -1;
+}
@@ -0,0 +1,21 @@
+// Generated code can also nest statements thousands of levels deep
+
+// Each step compares against the next value of `__COUNTER__`, so `STEPS_1000`
+// expands to a chain of 1000 statements `if (n <= 0) return n; else if ...`,
+// each nested in the `else` branch of the previous one
+#define STEP if (n <= __COUNTER__) return n; else
+#define STEPS_10 STEP STEP STEP STEP STEP STEP STEP STEP STEP STEP
+#define STEPS_100 STEPS_10 STEPS_10 STEPS_10 STEPS_10 STEPS_10 \
+    STEPS_10 STEPS_10 STEPS_10 STEPS_10 STEPS_10
+#define STEPS_1000 STEPS_100 STEPS_100 STEPS_100 STEPS_100 STEPS_100 \
+    STEPS_100 STEPS_100 STEPS_100 STEPS_100 STEPS_100
+
+// Nested 3000 levels deep, within the limit of the translator
+int deep_if(int n) {
+    STEPS_1000 STEPS_1000 STEPS_1000 return -1;
+}
+
+// Nested 5000 levels deep, past the limit, so this is translated into a stub
+int too_deep_if(int n) {
+    STEPS_1000 STEPS_1000 STEPS_1000 STEPS_1000 STEPS_1000 return -1;
+}
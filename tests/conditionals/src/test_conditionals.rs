@@ -1,6 +1,8 @@
 use crate::binary_conditional::rust_entry3;
 use crate::conditional::rust_entry;
 use crate::conditionals::{rust_entry2, rust_ternaries};
+use crate::deep_conditional::rust_deep_conditional;
+use crate::deep_if::rust_deep_if;
 use crate::unused_conditionals::{
     rust_unused_conditional1, rust_unused_conditional2, rust_unused_conditional3,
 };
@@ -17,6 +19,10 @@ extern "C" {
     fn unused_conditional1() -> c_int;
     fn unused_conditional2() -> c_int;
     fn unused_conditional3() -> c_int;
+
+    fn deep_conditional(_: c_int) -> c_int;
+
+    fn deep_if(_: c_int) -> c_int;
 }
 
 const BUFFER_SIZE: usize = 4;
@@ -74,3 +80,37 @@ pub fn test_unused_conditional() {
         assert_eq!(unused_conditional3(), rust_unused_conditional3());
     }
 }
+
+pub fn test_deep_conditional() {
+    for &n in &[-1, 0, 1, 5000, 9999, 10000, 20000] {
+        let expected = if n < 10000 { n } else { -1 };
+
+        unsafe {
+            assert_eq!(deep_conditional(n), rust_deep_conditional(n));
+            assert_eq!(deep_conditional(n), expected);
+        }
+    }
+}
+
+pub fn test_deep_if() {
+    for &n in &[-1, 0, 1, 1500, 2999, 3000, 20000] {
+        let expected = if n < 3000 { n } else { -1 };
+
+        unsafe {
+            assert_eq!(deep_if(n), rust_deep_if(n));
+            assert_eq!(deep_if(n), expected);
+        }
+    }
+}
+
+pub fn test_too_deep_if() {
+    // The translation gives up on statements nested past its limit instead of overflowing
+    // its stack, and replaces the function with a stub
+    let translated = include_str!("deep_if.rs");
+    let stub = translated
+        .lines()
+        .find(|line| line.starts_with("// Failed to translate too_deep_if: "))
+        .expect("too_deep_if is stubbed");
+    assert!(stub.ends_with(" is nested more than 4096 levels deep"));
+    assert!(!translated.contains("// Failed to translate deep_if: "));
+}
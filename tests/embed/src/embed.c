@@ -0,0 +1,27 @@
+//! min_clang_version_19
+
+// An array of bytes initialized with nothing but the embedded file is translated into
+// `include_bytes!`
+static const unsigned char logo[] = {
+#embed "logo.bin"
+};
+
+// Mixed with other elements, the embedded bytes become integer elements
+static const int framed[] = {
+    -1,
+#embed "logo.bin"
+    , -1
+};
+
+void embed(const unsigned buffer_size, int buffer[]) {
+    unsigned i, j = 0;
+
+    if (buffer_size < sizeof(logo) + sizeof(framed) / sizeof(framed[0])) return;
+
+    for (i = 0; i < sizeof(logo); i++) {
+        buffer[j++] = logo[i];
+    }
+    for (i = 0; i < sizeof(framed) / sizeof(framed[0]); i++) {
+        buffer[j++] = framed[i];
+    }
+}
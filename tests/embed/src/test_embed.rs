@@ -0,0 +1,31 @@
+use crate::embed::rust_embed;
+use libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn embed(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 34;
+
+pub fn test_embed() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let logo = include_bytes!("logo.bin").map(c_int::from);
+    let mut expected_buffer = [-1; BUFFER_SIZE];
+    expected_buffer[..16].copy_from_slice(&logo);
+    expected_buffer[17..33].copy_from_slice(&logo);
+
+    unsafe {
+        embed(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_embed(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_include_bytes() {
+    let translated = include_str!("embed.rs");
+    assert!(translated.contains("include_bytes!(\"logo.bin\")"));
+}
@@ -0,0 +1,39 @@
+//! enum_style_rust
+
+enum Direction {
+        North,
+        East,
+        South,
+        West,
+};
+
+// Arithmetic is done on values of this type, so it must stay a set of constants
+enum Flags {
+        NONE = 0,
+        READ = 1,
+        WRITE = 2,
+};
+
+static enum Direction turn_right(enum Direction d) {
+        switch (d) {
+        case North: return East;
+        case East: return South;
+        case South: return West;
+        default: return North;
+        }
+}
+
+void entry6(const unsigned int sz, int buffer[const]) {
+        enum Direction d = North;
+        enum Flags f = NONE;
+        int i = 0;
+
+        f |= READ;
+        f |= WRITE;
+
+        while (i < sz - 1) {
+                buffer[i++] = d;
+                d = turn_right(d);
+        }
+        buffer[i] = f;
+}
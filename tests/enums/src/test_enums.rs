@@ -6,6 +6,7 @@ use crate::enum_ret::{rust_entry2, Color};
 use crate::non_canonical_enum_def::{
     hrtimer_restart, rust_abc, HRTIMER_NORESTART, HRTIMER_RESTART,
 };
+use crate::rust_enum::{rust_entry6, Direction, East, North};
 use crate::top_enum::{rust_entry4, E as otherE};
 
 use libc::{c_int, c_uint};
@@ -21,6 +22,8 @@ extern "C" {
     fn entry4(_: c_uint, _: *mut c_int);
 
     fn entry5(_: c_uint, _: *mut c_int);
+
+    fn entry6(_: c_uint, _: *mut c_int);
 }
 
 const BUFFER_SIZE: usize = 10;
@@ -28,6 +31,7 @@ const BUFFER_SIZE2: usize = 7;
 const BUFFER_SIZE3: usize = 4;
 const BUFFER_SIZE4: usize = 1;
 const BUFFER_SIZE5: usize = 6;
+const BUFFER_SIZE6: usize = 6;
 
 pub fn test_variants() {
     assert_eq!(A as u32, 0);
@@ -103,3 +107,26 @@ pub fn test_buffer5() {
     assert_eq!(buffer, rust_buffer);
     assert_eq!(buffer, expected_buffer);
 }
+
+pub fn test_rust_enum() {
+    use std::convert::TryFrom;
+
+    assert_eq!(North, Direction::North);
+    assert_eq!(c_uint::from(East), 1);
+    assert_eq!(Direction::try_from(3), Ok(Direction::West));
+    assert_eq!(Direction::try_from(4), Err(4));
+}
+
+pub fn test_buffer6() {
+    let mut buffer = [0; BUFFER_SIZE6];
+    let mut rust_buffer = [0; BUFFER_SIZE6];
+    let expected_buffer = [0, 1, 2, 3, 0, 3];
+
+    unsafe {
+        entry6(BUFFER_SIZE6 as u32, buffer.as_mut_ptr());
+        rust_entry6(BUFFER_SIZE6 as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,34 @@
+#include <stdint.h>
+#include <string.h>
+
+void float_bits(const unsigned buffer_size, uint32_t buffer[]) {
+    float f = 1.5f;
+    double d = -2.25;
+    int32_t i;
+    uint32_t u;
+    uint64_t ul;
+
+    if (buffer_size < 8) return;
+
+    // Pointer casts
+    u = *(uint32_t *)&f;
+    buffer[0] = u;
+    i = *(int32_t *)&f;
+    buffer[1] = (uint32_t)i;
+    u += 1;
+    buffer[2] = (uint32_t)(*(float *)&u * 2.0f);
+
+    // Union compound literals
+    buffer[3] = ((union { float f; uint32_t u; }){ f }).u;
+    ul = ((union { double d; uint64_t u; }){ d }).u;
+    buffer[4] = (uint32_t)(ul >> 32);
+
+    // memcpy
+    memcpy(&u, &f, sizeof u);
+    buffer[5] = u;
+    memcpy(&ul, &d, sizeof(double));
+    buffer[6] = (uint32_t)(ul >> 32);
+    ul ^= (uint64_t)1 << 63;
+    memcpy(&d, &ul, sizeof d);
+    buffer[7] = (uint32_t)d;
+}
@@ -0,0 +1,20 @@
+struct sample {
+    char tag;
+    _Float16 value;
+};
+
+static _Float16 scale = 0.5;
+
+float half_sum(unsigned n, const float *xs) {
+    struct sample s = { 's', 0 };
+
+    for (unsigned i = 0; i < n; i++) {
+        s.value += (_Float16)xs[i] * scale;
+    }
+    s.value++;
+    return s.value;
+}
+
+unsigned half_layout(void) {
+    return sizeof(struct sample) * 10 + _Alignof(struct sample);
+}
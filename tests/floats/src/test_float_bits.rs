@@ -0,0 +1,32 @@
+use crate::float_bits::rust_float_bits;
+use libc::c_uint;
+
+#[link(name = "test")]
+extern "C" {
+    fn float_bits(_: c_uint, _: *mut u32);
+}
+
+const BUFFER_SIZE: usize = 8;
+
+pub fn test_float_bits() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [
+        1.5f32.to_bits(),
+        1.5f32.to_bits(),
+        3,
+        1.5f32.to_bits(),
+        ((-2.25f64).to_bits() >> 32) as u32,
+        1.5f32.to_bits(),
+        ((-2.25f64).to_bits() >> 32) as u32,
+        2,
+    ];
+
+    unsafe {
+        float_bits(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_float_bits(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
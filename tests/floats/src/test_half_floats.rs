@@ -0,0 +1,24 @@
+use crate::half_floats::{rust_half_layout, rust_half_sum};
+use libc::{c_float, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn half_sum(_: c_uint, _: *const c_float) -> c_float;
+    fn half_layout() -> c_uint;
+}
+
+pub fn test_half_sum() {
+    let xs = [1.0, 2.5, -3.0, 8.0];
+
+    unsafe {
+        assert_eq!(half_sum(4, xs.as_ptr()), 5.25);
+        assert_eq!(rust_half_sum(4, xs.as_ptr()), 5.25);
+    }
+}
+
+pub fn test_half_layout() {
+    unsafe {
+        assert_eq!(half_layout(), 42);
+        assert_eq!(rust_half_layout(), 42);
+    }
+}
@@ -0,0 +1,27 @@
+//! fixed_width_ints
+
+static long long scale(short s, unsigned char c, long l) {
+    return (long long)s * c + l;
+}
+
+void fixed_width_ints(unsigned buffer_size, int buffer[]) {
+    if (buffer_size < 10) return;
+
+    signed char sc = -5;
+    unsigned short us = 65535;
+    unsigned long ul = 4000000000UL;
+    unsigned long long ull = 0xffffffffffULL;
+    float f = 1.5f;
+    double d = 2.25;
+
+    buffer[0] = sc * 3;
+    buffer[1] = us + 1;
+    buffer[2] = (int)(ul / 1000000);
+    buffer[3] = (int)(ull >> 32);
+    buffer[4] = (int)scale(-300, 200, 7);
+    buffer[5] = (int)(f * d * 4);
+    buffer[6] = sc < 0;
+    buffer[7] = !us;
+    buffer[8] = (int)sizeof(long long);
+    buffer[9] = 'a' + 017;
+}
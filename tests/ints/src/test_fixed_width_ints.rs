@@ -0,0 +1,23 @@
+use crate::fixed_width_ints::rust_fixed_width_ints;
+
+#[link(name = "test")]
+extern "C" {
+    fn fixed_width_ints(_: u32, _: *mut i32);
+}
+
+const BUFFER_SIZE: usize = 10;
+
+pub fn test_fixed_width_ints() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [-15, 65536, 4000, 255, -59993, 13, 1, 0, 8, 112];
+
+    unsafe {
+        fixed_width_ints(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        // The translation takes the Rust types themselves rather than libc aliases
+        rust_fixed_width_ints(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,38 @@
+//! openmp_rayon
+
+static int square(int x) {
+    return x * x;
+}
+
+// Iterations are independent, so these are translated into parallel iterators
+static void squares(int n, int *out) {
+#pragma omp parallel for schedule(static)
+    for (int i = 0; i < n; i++) {
+        int tmp = square(i);
+        out[i] = tmp + 1;
+    }
+}
+
+static void scale(int n, const int *in, int *out, int factor) {
+    int i;
+#pragma omp parallel for
+    for (i = 0; i <= n; ++i)
+        out[i] = in[i] * factor;
+}
+
+// Reductions write to a variable shared by all iterations and are left sequential
+static int sum(int n, const int *in) {
+    int total = 0;
+#pragma omp parallel for reduction(+:total)
+    for (int i = 0; i < n; i++)
+        total += in[i];
+    return total;
+}
+
+void parallel_for(unsigned buffer_size, int buffer[]) {
+    int tmp[8];
+
+    squares(8, buffer);
+    scale(7, buffer, tmp, 3);
+    buffer[8] = sum(8, tmp);
+}
@@ -0,0 +1,34 @@
+//! range_loops
+
+void range_loops(unsigned buffer_size, int buffer[]) {
+    int n = 4;
+    int total = 0;
+
+    if (buffer_size < 10) return;
+
+    for (int i = 0; i < n; i++)
+        buffer[i] = i * i;
+
+    // Counts down, including the bound
+    for (int i = n - 1; i >= 0; i--)
+        buffer[4 + i] = buffer[n - 1 - i];
+
+    for (unsigned i = 1; i <= 9; i += 3)
+        total += i;
+    buffer[8] = total;
+
+    // The body shrinks the bound, so this stays a `while` loop
+    total = 0;
+    for (int i = 0; i < n; i++) {
+        total += i;
+        n--;
+    }
+
+    // The body leaves the loop early, so this stays a `while` loop too
+    for (int i = 0; i < 10; i++) {
+        if (i == 3)
+            break;
+        total += 10;
+    }
+    buffer[9] = total;
+}
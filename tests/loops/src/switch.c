@@ -9,3 +9,23 @@ int switch_val(int val) {
             return val + 1;
     }
 }
+
+#define LOWER 10
+#define UPPER 20
+
+int switch_case_range(int val) {
+    switch (val) {
+        case -5 ... -1:
+            return -1;
+        case 1 ... 3:
+            return 3;
+        case 4 ... 4:
+            return 4;
+        case LOWER ... UPPER:
+            return 20;
+        case 9 ... 5:
+            return 100;
+        default:
+            return 0;
+    }
+}
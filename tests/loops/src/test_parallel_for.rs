@@ -0,0 +1,23 @@
+use crate::parallel_for::rust_parallel_for;
+use libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn parallel_for(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 9;
+
+pub fn test_parallel_for() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [1, 2, 5, 10, 17, 26, 37, 50, 444];
+
+    unsafe {
+        parallel_for(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_parallel_for(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
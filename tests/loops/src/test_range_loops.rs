@@ -0,0 +1,23 @@
+use crate::range_loops::rust_range_loops;
+use libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn range_loops(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 10;
+
+pub fn test_range_loops() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [0, 1, 4, 9, 9, 4, 1, 0, 12, 31];
+
+    unsafe {
+        range_loops(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_range_loops(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -1,9 +1,11 @@
-use crate::switch::rust_switch_val;
+use crate::switch::{rust_switch_case_range, rust_switch_val};
 use libc::c_int;
 
 #[link(name = "test")]
 extern "C" {
     fn switch_val(_: c_int) -> c_int;
+
+    fn switch_case_range(_: c_int) -> c_int;
 }
 
 pub fn test_switch() {
@@ -25,3 +27,29 @@ pub fn test_switch() {
     assert_eq!(val, rust_val);
     assert_eq!(val, 11);
 }
+
+pub fn test_switch_case_range() {
+    let expected = [
+        (-6, 0),
+        (-5, -1),
+        (-1, -1),
+        (0, 0),
+        (1, 3),
+        (3, 3),
+        (4, 4),
+        (5, 0),
+        (7, 0),
+        (10, 20),
+        (15, 20),
+        (20, 20),
+        (21, 0),
+    ];
+
+    for &(input, output) in expected.iter() {
+        let val = unsafe { switch_case_range(input) };
+        let rust_val = unsafe { rust_switch_case_range(input) };
+
+        assert_eq!(val, rust_val);
+        assert_eq!(val, output);
+    }
+}
@@ -70,6 +70,21 @@ int stmt_expr_inc(void) {
   return inc(b);
 }
 
+#define BUF_SIZE 4096
+
+size_t buf_size_sum(int n) {
+  size_t total = BUF_SIZE;
+  int half = BUF_SIZE / 2;
+  return total + half * n;
+}
+
+int get_page_size(void) { return 4096; }
+
+// Function calls can't be evaluated in a const, so this is translated inline
+#define PAGE_SIZE get_page_size()
+
+int page_count(int bytes) { return bytes / PAGE_SIZE; }
+
 int test_switch(int x) {
   switch (x) {
   case TEST_CONST1:
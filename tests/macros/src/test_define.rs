@@ -1,3 +1,4 @@
+use crate::define::{rust_buf_size_sum, rust_page_count, BUF_SIZE};
 use crate::define::{rust_fns, rust_stmt_expr_inc};
 use crate::define::{rust_reference_define, TEST_CONST1, TEST_CONST2, TEST_PARENS};
 use crate::define::{rust_test_zstd, ZSTD_WINDOWLOG_MAX_32, ZSTD_WINDOWLOG_MAX_64};
@@ -6,6 +7,8 @@ use libc::{c_int, c_uint, c_ulong};
 #[link(name = "test")]
 extern "C" {
     fn reference_define() -> c_uint;
+
+    fn buf_size_sum(n: c_int) -> c_ulong;
 }
 
 pub fn test_define() {
@@ -24,3 +27,13 @@ pub fn test_macro_stmt_expr() {
 
     assert_eq!(ret, 2);
 }
+
+pub fn test_const_macro_uses() {
+    let sum = unsafe { buf_size_sum(3) };
+    let rust_sum = unsafe { rust_buf_size_sum(3) };
+
+    assert_eq!(BUF_SIZE, 4096);
+    assert_eq!(sum, rust_sum);
+    assert_eq!(rust_sum, 4096 + 2048 * 3);
+    assert_eq!(unsafe { rust_page_count(3 * 4096) }, 3);
+}
@@ -0,0 +1,21 @@
+#include <assert.h>
+#include <stdlib.h>
+
+static int checked_sum(const int *values, unsigned len) {
+    int sum = 0;
+    assert(values != NULL);
+    for (unsigned i = 0; i < len; i++) {
+        assert(values[i] >= 0 && "values must not be negative");
+        sum += values[i];
+    }
+    if (sum < 0) {
+        abort();
+    }
+    return sum;
+}
+
+void asserts(unsigned buffer_size, int buffer[]) {
+    for (unsigned i = 0; i < buffer_size; i++) {
+        buffer[i] = checked_sum(buffer, i + 1);
+    }
+}
@@ -0,0 +1,9 @@
+int auto_type(int n) {
+    __auto_type x = n * 2;
+    __auto_type y = x + 1L;
+    const __auto_type p = &x;
+    __auto_type q = (unsigned char)n;
+    __auto_type d = n / 4.0;
+    *p += 3;
+    return (int)(y + *p + q + d);
+}
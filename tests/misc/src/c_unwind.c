@@ -0,0 +1,24 @@
+//! c_unwind
+
+typedef int (*callback)(int);
+
+static int twice(int x) {
+    return 2 * x;
+}
+
+static int apply(callback f, int x) {
+    return f(x);
+}
+
+__attribute__((annotate("c2rust::nounwind")))
+static int add_one(int x) {
+    return x + 1;
+}
+
+void unwind_calls(const unsigned buffer_size, int buffer[]) {
+    if (buffer_size < 3) return;
+
+    buffer[0] = apply(twice, 3);
+    buffer[1] = add_one(4);
+    buffer[2] = apply(twice, add_one(1));
+}
@@ -0,0 +1,15 @@
+//! candidate_function_count_digits
+
+#include <ctype.h>
+#undef isdigit
+
+int count_digits(const char *s) {
+    int count = 0;
+
+    for (; *s; s++) {
+        if (isdigit((unsigned char)*s)) {
+            count++;
+        }
+    }
+    return count;
+}
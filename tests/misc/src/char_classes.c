@@ -0,0 +1,30 @@
+//! ascii_char_classes
+
+// Undefine any macro versions so that we see actual calls
+#include <ctype.h>
+#undef isalpha
+#undef isdigit
+#undef isupper
+#undef isxdigit
+#undef tolower
+#undef toupper
+
+void char_classes(const unsigned buffer_size, int buffer[]) {
+    const char *s = "aZ09 x\xe9";
+    int i = 0;
+
+    if (buffer_size < 20) return;
+
+    for (const char *p = s; *p; p++) {
+        unsigned char c = *p;
+        buffer[i++] = (isdigit(c) != 0) + 2 * (isalpha(c) != 0);
+        buffer[i++] = toupper(c);
+    }
+    buffer[i++] = isxdigit('f') != 0;
+    buffer[i++] = isupper('f') != 0;
+    buffer[i++] = tolower('Q');
+    buffer[i++] = toupper(EOF);
+    // The case of these depends on the locale, so they're converted by libc
+    buffer[i++] = toupper('i');
+    buffer[i++] = tolower('I');
+}
@@ -0,0 +1,19 @@
+//! translate_fmt
+
+#include <stdio.h>
+
+void fmt(const unsigned buffer_size, char buffer[]) {
+    const char *name = "c2rust";
+    int n = -42;
+    unsigned long big = 4000000000ul;
+    double x = 3.14159;
+    int len = 0;
+
+    if (buffer_size < 128) return;
+
+    len += snprintf(buffer + len, buffer_size - len, "%s: %d|%5d|%-5d|%+d|", name, n, 7, 7, 7);
+    len += snprintf(buffer + len, buffer_size - len, "%lu %x %X %o %05u|", big, 255, 255, 8, 42u);
+    len += snprintf(buffer + len, buffer_size - len, "%f %.2f %8.3f %c 100%% {}|", x, x, x, 'z');
+    // Truncated to the size of the buffer
+    snprintf(buffer + len, 4, "%s", "truncated");
+}
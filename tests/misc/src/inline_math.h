@@ -0,0 +1,14 @@
+typedef unsigned int word;
+
+// Files can rotate narrower words, which gives them a different definition of `rotl`
+#ifndef WORD_BITS
+#define WORD_BITS 32
+#endif
+
+static inline word rotl(word x, int n) {
+    return x << n | x >> (WORD_BITS - n);
+}
+
+static inline int clamp(int x, int lo, int hi) {
+    return x < lo ? lo : x > hi ? hi : x;
+}
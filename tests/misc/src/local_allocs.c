@@ -0,0 +1,46 @@
+//! box_local_allocs
+
+#include <stdlib.h>
+
+struct point {
+    int x;
+    int y;
+};
+
+// Escapes through the return value, so it stays a raw allocation
+static int *make_counts(int n) {
+    return calloc(n, sizeof(int));
+}
+
+void local_allocs(const unsigned buffer_size, int buffer[]) {
+    int n = 4;
+    struct point *p = malloc(sizeof(struct point));
+    int *squares = malloc(n * sizeof(int));
+    int *sums = calloc(n, sizeof *sums);
+
+    if (buffer_size < 8) return;
+    if (!p || squares == NULL || sums == NULL) return;
+
+    for (int i = 0; i < n; i++) {
+        squares[i] = i * i;
+        sums[i] = (i > 0 ? sums[i - 1] : 0) + squares[i];
+    }
+    p->x = squares[n - 1];
+    (*p).y = sums[n - 1];
+
+    int *counts = make_counts(n);
+    counts[1] = p->x + p->y;
+
+    for (int i = 0; i < n; i++) {
+        buffer[i] = sums[i];
+    }
+    buffer[4] = p->x;
+    buffer[5] = p->y;
+    buffer[6] = counts[1];
+    buffer[7] = p != NULL;
+
+    free(counts);
+    free(sums);
+    free(squares);
+    free(p);
+}
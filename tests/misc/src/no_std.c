@@ -0,0 +1,20 @@
+//! emit_no_std
+
+#include <alloca.h>
+
+void no_std(const unsigned buffer_size, int buffer[]) {
+    // A constant size becomes a stack array, other sizes and VLAs become
+    // vectors from `alloc`
+    char *fixed = alloca(4);
+    char *dynamic = alloca(buffer_size);
+    int vla[buffer_size];
+
+    __builtin_bzero(fixed, 4);
+    for (unsigned i = 0; i < buffer_size; i++) {
+        dynamic[i] = i * 3;
+        vla[i] = fixed[i % 4] + dynamic[i];
+        fixed[i % 4] += 1;
+    }
+    for (unsigned i = 0; i < buffer_size; i++)
+        buffer[i] = vla[buffer_size - 1 - i];
+}
@@ -0,0 +1,48 @@
+#include <stddef.h>
+
+struct ops {
+    int (* _Nonnull step)(int);
+    void (* _Nullable done)(int *);
+};
+
+static int twice(int x) { return 2 * x; }
+static int inc(int x) { return x + 1; }
+static void mark(int *out) { *out = -1; }
+
+__attribute__((nonnull(1)))
+static int apply(int (*f)(int), int x, int (*g)(int)) {
+    return g == NULL ? f(x) : g(f(x));
+}
+
+static int (* _Nonnull pick(int n))(int) {
+    if (n > 0) {
+        return twice;
+    }
+    return inc;
+}
+
+static int run(const struct ops *ops, int x, int *out) {
+    int y = ops->step(x);
+    if (ops->done != NULL) {
+        ops->done(out);
+    }
+    if (!ops->done) {
+        y += 100;
+    }
+    return y;
+}
+
+void nonnull_fn_ptrs(int buffer[]) {
+    struct ops a = { twice, mark };
+    struct ops b = { .step = inc };
+    int unused = 0;
+    int (* _Nonnull f)(int) = pick(1);
+
+    buffer[0] = run(&a, 3, &buffer[6]);
+    buffer[1] = run(&b, 3, &unused);
+    a.step = b.step;
+    buffer[2] = run(&a, 5, &buffer[7]);
+    buffer[3] = apply(f, 4, NULL);
+    buffer[4] = apply(pick(0), 4, twice);
+    buffer[5] = apply(a.step, 1, b.step);
+}
@@ -0,0 +1,23 @@
+#include <stdlib.h>
+
+_Noreturn static void fail(void) {
+    abort();
+}
+
+static int checked_div(int a, int b) {
+    if (b == 0) {
+        fail();
+    }
+    return a / b;
+}
+
+void noreturn_calls(unsigned buffer_size, int buffer[]) {
+    void (*on_error)(void) = fail;
+
+    for (unsigned i = 0; i < buffer_size; i++) {
+        buffer[i] = checked_div(100, i + 1);
+    }
+    if (buffer_size > 0 && on_error == 0) {
+        buffer[0] = 0;
+    }
+}
@@ -0,0 +1,54 @@
+//! outline_threshold_8
+
+struct point {
+    int x, y;
+};
+
+static int scale(int v) { return v * 3; }
+
+// Long enough to be split into a driver and several helpers
+void outline(int buffer[], int n)
+{
+    int i = 0, acc = n;
+    struct point p = { 1, 2 };
+    const int k = 7;
+    int *cursor = &buffer[10];
+
+    acc += k;
+    p.x += acc;
+    p.y = scale(p.y);
+    buffer[i++] = acc;
+    buffer[i++] = p.x;
+    buffer[i++] = p.y;
+    for (int j = 0; j < 3; j++) {
+        acc += j * k;
+    }
+    buffer[i++] = acc;
+
+    if (n < 0) {
+        buffer[i++] = -1;
+        return;
+    }
+
+    int total = 0;
+    switch (n % 3) {
+    case 0:
+        total += 10;
+    case 1:
+        total += 20;
+        break;
+    default:
+        total = 5;
+    }
+    buffer[i++] = total;
+    *cursor++ = total + k;
+    *cursor++ = p.x * p.y;
+    while (total > 0) {
+        total -= 4;
+        acc++;
+    }
+    buffer[i++] = acc;
+    buffer[i++] = total;
+    p = (struct point){ .x = total, .y = acc };
+    buffer[i++] = p.x + p.y;
+}
@@ -0,0 +1,26 @@
+//! owned_alloca_strings
+
+#define _GNU_SOURCE
+#include <string.h>
+
+static int count_upper(const char *s) {
+    int n = 0;
+    for (; *s; s++)
+        n += *s >= 'A' && *s <= 'Z';
+    return n;
+}
+
+void owned_alloca_strings(const char *name, int buffer[]) {
+    char *copy = strdupa(name);
+    copy[0] = 'X';
+    buffer[0] = strlen(copy);
+    buffer[1] = count_upper(copy);
+    buffer[2] = name[0];
+
+    const char *prefix = strndupa(name, 3);
+    buffer[3] = strlen(prefix);
+    buffer[4] = prefix[2];
+
+    // Longer bound than the string
+    buffer[5] = strlen(strndupa(prefix, 10));
+}
@@ -0,0 +1,24 @@
+//! emit_safe_wrappers
+
+#include <stddef.h>
+
+int clamp(int x, int lo, int hi) {
+    return x < lo ? lo : x > hi ? hi : x;
+}
+
+long sum(const int *xs, size_t len) {
+    long total = 0;
+    for (size_t i = 0; i < len; i++)
+        total += xs[i];
+    return total;
+}
+
+void fill(double *out, int count, double value) {
+    for (int i = 0; i < count; i++)
+        out[i] = value * i;
+}
+
+// The pointer has no length, so it gets no wrapper
+int first(const int *xs) {
+    return xs[0];
+}
@@ -0,0 +1,7 @@
+//! share_inline_fns, translation_group_shared_inline
+
+#include "inline_math.h"
+
+int shared_inline_a(int x) {
+    return clamp(x, 0, 100) + (int)rotl(1, (x & 7) + 1);
+}
@@ -0,0 +1,7 @@
+//! share_inline_fns, translation_group_shared_inline
+
+#include "inline_math.h"
+
+unsigned shared_inline_b(unsigned x) {
+    return rotl(x, 8) + clamp(x, 0, 10);
+}
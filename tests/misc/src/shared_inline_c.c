@@ -0,0 +1,8 @@
+//! share_inline_fns, translation_group_shared_inline
+
+#define WORD_BITS 16
+#include "inline_math.h"
+
+unsigned shared_inline_c(unsigned x) {
+    return rotl(x & 0xffff, 4) + clamp(x, 5, 50);
+}
@@ -0,0 +1,23 @@
+//! slice_fns
+
+#include <stddef.h>
+
+void scale(float *restrict dst, const float *src, size_t n, float k) {
+    for (size_t i = 0; i < n; i++)
+        dst[i] = src[i] * k;
+}
+
+int count_above(const int *xs, int n, int threshold) {
+    int count = 0;
+    for (int i = 0; i < n; i++) {
+        if (xs[i] > threshold)
+            count++;
+    }
+    return count;
+}
+
+// Writes through a pointer that may alias `src`, so it keeps only the C version
+void shift(int *dst, const int *src, int n) {
+    for (int i = 0; i < n; i++)
+        dst[i] = src[i] << 1;
+}
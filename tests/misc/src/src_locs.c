@@ -0,0 +1,17 @@
+//! emit_src_locs
+
+struct point {
+    int x, y;
+};
+
+static int scale = 3;
+
+int src_locs(int n) {
+    struct point p = { n, n + 1 };
+    int sum = 0;
+    for (int i = 0; i < n; i++) {
+        int step = p.x * scale;
+        sum += step - p.y;
+    }
+    return sum;
+}
@@ -0,0 +1,39 @@
+//! static_cstr_returns
+
+#include <string.h>
+
+static const char *sign_name(int n) {
+    if (n < 0) {
+        return "negative";
+    }
+    return n > 0 ? "positive" : "zero";
+}
+
+static const char *parity_name(int n) {
+    switch (n % 2) {
+    case 0:
+        return "even";
+    default:
+        return (const char *)"odd";
+    }
+}
+
+static const char *describe(int n) {
+    return n == 0 ? sign_name(n) : parity_name(n);
+}
+
+// Not translated: the address is taken
+static const char *first_name(void) { return "first"; }
+
+// Not translated: returns a pointer into a literal
+static const char *suffix(int n) { return "abcdef" + n; }
+
+void static_cstrs(int n, unsigned buffer[]) {
+    const char *(*name_fn)(void) = first_name;
+    buffer[0] = strlen(sign_name(n));
+    buffer[1] = strlen(parity_name(n));
+    buffer[2] = strlen(describe(n));
+    buffer[3] = strlen(name_fn());
+    buffer[4] = strlen(suffix(n < 0 ? 0 : n % 6));
+    buffer[5] = sign_name(n)[0];
+}
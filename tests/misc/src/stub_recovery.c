@@ -0,0 +1,19 @@
+//! check_extern_fallback
+
+// The translator doesn't support `__builtin_bitreverse32`, so `reversed` is replaced with a stub
+// (which unwinds into the test) while the rest of the file still translates
+__attribute__((annotate("c2rust::unwind")))
+unsigned reversed(unsigned x) {
+    return __builtin_bitreverse32(x);
+}
+
+int doubled(int x) {
+    return 2 * x;
+}
+
+void stub_recovery(const unsigned buffer_size, int buffer[]) {
+    if (buffer_size < 2) return;
+
+    buffer[0] = doubled(3);
+    buffer[1] = doubled(-4);
+}
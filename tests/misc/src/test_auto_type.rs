@@ -0,0 +1,16 @@
+use crate::auto_type::rust_auto_type;
+use libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    fn auto_type(_: c_int) -> c_int;
+}
+
+pub fn test_auto_type() {
+    for n in [-5, 0, 1, 7, 300] {
+        let ret = unsafe { auto_type(n) };
+        let rust_ret = unsafe { rust_auto_type(n) };
+
+        assert_eq!(ret, rust_ret);
+    }
+}
@@ -0,0 +1,23 @@
+use crate::c_unwind::rust_unwind_calls;
+use libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn unwind_calls(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 3;
+
+pub fn test_c_unwind() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [6, 5, 4];
+
+    unsafe {
+        unwind_calls(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_unwind_calls(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,17 @@
+use crate::candidates::rust_count_digits;
+use libc::{c_char, c_int};
+
+#[link(name = "test")]
+extern "C" {
+    fn count_digits(_: *const c_char) -> c_int;
+}
+
+pub fn test_count_digits() {
+    for s in &[&b"\0"[..], b"abc\0", b"a1b22c333\0", b"\xe9 2020\0"] {
+        let s = s.as_ptr() as *const c_char;
+        let expected = unsafe { count_digits(s) };
+        let translated = unsafe { rust_count_digits(s) };
+
+        assert_eq!(translated, expected);
+    }
+}
@@ -0,0 +1,25 @@
+use crate::char_classes::rust_char_classes;
+use libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn char_classes(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 20;
+
+pub fn test_char_classes() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [
+        2, 65, 2, 90, 1, 48, 1, 57, 0, 32, 2, 88, 0, 233, 1, 0, 113, -1, 73, 105,
+    ];
+
+    unsafe {
+        char_classes(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_char_classes(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
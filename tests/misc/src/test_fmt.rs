@@ -0,0 +1,23 @@
+use crate::fmt::rust_fmt;
+use libc::{c_char, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn fmt(_: c_uint, _: *mut c_char);
+}
+
+const BUFFER_SIZE: usize = 128;
+
+pub fn test_fmt() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected = b"c2rust: -42|    7|7    |+7|4000000000 ff FF 10 00042|3.141590 3.14    3.142 z 100% {}|tru\0";
+
+    unsafe {
+        fmt(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_fmt(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(&buffer[..expected.len()], &expected[..]);
+}
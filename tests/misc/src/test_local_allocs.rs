@@ -0,0 +1,23 @@
+use crate::local_allocs::rust_local_allocs;
+use libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn local_allocs(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 8;
+
+pub fn test_local_allocs() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [0, 1, 5, 14, 9, 14, 23, 1];
+
+    unsafe {
+        local_allocs(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_local_allocs(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,25 @@
+//! extern_crate_alloc
+
+use crate::no_std::rust_no_std;
+use libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn no_std(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 8;
+
+pub fn test_no_std() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [22, 19, 16, 13, 9, 6, 3, 0];
+
+    unsafe {
+        no_std(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_no_std(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,23 @@
+use crate::nonnull_fn_ptrs::rust_nonnull_fn_ptrs;
+use libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    fn nonnull_fn_ptrs(_: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 8;
+
+pub fn test_nonnull_fn_ptrs() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [6, 104, 6, 8, 10, 3, -1, -1];
+
+    unsafe {
+        nonnull_fn_ptrs(buffer.as_mut_ptr());
+        rust_nonnull_fn_ptrs(rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,23 @@
+use crate::noreturn::rust_noreturn_calls;
+use libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn noreturn_calls(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 5;
+
+pub fn test_noreturn() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [100, 50, 33, 25, 20];
+
+    unsafe {
+        noreturn_calls(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_noreturn_calls(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,31 @@
+use crate::outline::rust_outline;
+use libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    fn outline(_: *mut c_int, _: c_int);
+}
+
+const BUFFER_SIZE: usize = 12;
+
+pub fn test_outline() {
+    let expected_buffers = [
+        [6, 7, 6, 27, -1, 0, 0, 0, 0, 0, 0, 0],
+        [7, 8, 6, 28, 30, 36, -2, 34, 0, 0, 37, 48],
+        [8, 9, 6, 29, 20, 34, 0, 34, 0, 0, 27, 54],
+        [9, 10, 6, 30, 5, 32, -3, 29, 0, 0, 12, 60],
+    ];
+
+    for (n, expected_buffer) in (-1..).zip(expected_buffers) {
+        let mut buffer = [0; BUFFER_SIZE];
+        let mut rust_buffer = [0; BUFFER_SIZE];
+
+        unsafe {
+            outline(buffer.as_mut_ptr(), n);
+            rust_outline(rust_buffer.as_mut_ptr(), n);
+        }
+
+        assert_eq!(buffer, rust_buffer);
+        assert_eq!(buffer, expected_buffer);
+    }
+}
@@ -0,0 +1,24 @@
+use crate::owned_alloca_strings::rust_owned_alloca_strings;
+use libc::{c_char, c_int};
+
+#[link(name = "test")]
+extern "C" {
+    fn owned_alloca_strings(_: *const c_char, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 6;
+
+pub fn test_owned_alloca_strings() {
+    let name = b"hello World\0";
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [11, 2, 'h' as c_int, 3, 'l' as c_int, 3];
+
+    unsafe {
+        owned_alloca_strings(name.as_ptr() as *const c_char, buffer.as_mut_ptr());
+        rust_owned_alloca_strings(name.as_ptr() as *const c_char, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,38 @@
+use crate::safe_wrappers::{rust_clamp_safe, rust_fill_safe, rust_sum_safe};
+use libc::{c_double, c_int, c_long, size_t};
+
+#[link(name = "test")]
+extern "C" {
+    fn clamp(_: c_int, _: c_int, _: c_int) -> c_int;
+    fn sum(_: *const c_int, _: size_t) -> c_long;
+    fn fill(_: *mut c_double, _: c_int, _: c_double);
+}
+
+pub fn test_clamp() {
+    for &x in [-20, -10, 0, 7, 10, 30].iter() {
+        let expected = unsafe { clamp(x, -10, 10) };
+        assert_eq!(rust_clamp_safe(x, -10, 10), expected);
+    }
+}
+
+pub fn test_sum() {
+    let xs = [4, -8, 15, 16, 23, 42];
+    let expected = unsafe { sum(xs.as_ptr(), xs.len()) };
+
+    assert_eq!(rust_sum_safe(&xs), expected);
+    assert_eq!(rust_sum_safe(&xs), 92);
+    assert_eq!(rust_sum_safe(&[]), 0);
+}
+
+pub fn test_fill() {
+    let mut out = [0.0; 4];
+    let mut rust_out = [0.0; 4];
+
+    unsafe {
+        fill(out.as_mut_ptr(), out.len() as c_int, 1.5);
+    }
+    rust_fill_safe(&mut rust_out, 1.5);
+
+    assert_eq!(out, rust_out);
+    assert_eq!(out, [0.0, 1.5, 3.0, 4.5]);
+}
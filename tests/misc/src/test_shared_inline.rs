@@ -0,0 +1,26 @@
+use crate::shared_inline_a::rust_shared_inline_a;
+use crate::shared_inline_b::rust_shared_inline_b;
+use crate::shared_inline_c::rust_shared_inline_c;
+use libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn shared_inline_a(_: c_int) -> c_int;
+    fn shared_inline_b(_: c_uint) -> c_uint;
+    fn shared_inline_c(_: c_uint) -> c_uint;
+}
+
+pub fn test_shared_inline() {
+    for &x in &[-5, 0, 3, 42, 1000] {
+        unsafe {
+            assert_eq!(shared_inline_a(x), rust_shared_inline_a(x));
+        }
+    }
+    for &x in &[0, 7, 0x1234, 0xdead_beef] {
+        unsafe {
+            assert_eq!(shared_inline_b(x), rust_shared_inline_b(x));
+            // `shared_inline_c` defines `rotl` differently, so it keeps its own copy
+            assert_eq!(shared_inline_c(x), rust_shared_inline_c(x));
+        }
+    }
+}
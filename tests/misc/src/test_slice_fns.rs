@@ -0,0 +1,44 @@
+use crate::slice_fns::{rust_count_above, rust_count_above_slice, rust_scale, rust_scale_slice};
+use libc::{c_float, c_int, size_t};
+
+#[link(name = "test")]
+extern "C" {
+    fn scale(_: *mut c_float, _: *const c_float, _: size_t, _: c_float);
+    fn count_above(_: *const c_int, _: c_int, _: c_int) -> c_int;
+}
+
+const LEN: usize = 5;
+
+pub fn test_scale() {
+    let src = [1.0, -2.5, 0.0, 4.0, 8.25];
+    let mut dst = [0.0; LEN];
+    let mut rust_dst = [0.0; LEN];
+    let mut slice_dst = [0.0; LEN];
+    let expected = [2.0, -5.0, 0.0, 8.0, 16.5];
+
+    unsafe {
+        scale(dst.as_mut_ptr(), src.as_ptr(), LEN, 2.0);
+        rust_scale(rust_dst.as_mut_ptr(), src.as_ptr(), LEN, 2.0);
+    }
+    rust_scale_slice(&mut slice_dst, &src, 2.0);
+
+    assert_eq!(dst, rust_dst);
+    assert_eq!(dst, slice_dst);
+    assert_eq!(dst, expected);
+}
+
+pub fn test_count_above() {
+    let xs = [3, 9, -1, 12, 7, 7];
+
+    let count = unsafe { count_above(xs.as_ptr(), xs.len() as c_int, 6) };
+    let rust_count = unsafe { rust_count_above(xs.as_ptr(), xs.len() as c_int, 6) };
+    let slice_count = rust_count_above_slice(&xs, 6);
+
+    assert_eq!(count, rust_count);
+    assert_eq!(count, slice_count);
+    assert_eq!(count, 4);
+
+    // A null pointer with no elements is an empty slice
+    let empty = unsafe { rust_count_above(std::ptr::null(), 0, 6) };
+    assert_eq!(empty, 0);
+}
@@ -0,0 +1,15 @@
+use crate::src_locs::rust_src_locs;
+use libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    fn src_locs(_: c_int) -> c_int;
+}
+
+pub fn test_src_locs() {
+    for &n in &[0, 1, 5, 20] {
+        unsafe {
+            assert_eq!(src_locs(n), rust_src_locs(n));
+        }
+    }
+}
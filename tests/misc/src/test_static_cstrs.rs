@@ -0,0 +1,31 @@
+use crate::static_cstrs::rust_static_cstrs;
+use libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn static_cstrs(_: c_int, _: *mut c_uint);
+}
+
+const BUFFER_SIZE: usize = 6;
+
+pub fn test_static_cstrs() {
+    let expected_buffers = [
+        [8, 3, 3, 5, 6, 'n' as c_uint],
+        [4, 4, 4, 5, 6, 'z' as c_uint],
+        [8, 3, 3, 5, 5, 'p' as c_uint],
+        [8, 4, 4, 5, 4, 'p' as c_uint],
+    ];
+
+    for (n, expected_buffer) in (-1..).zip(expected_buffers) {
+        let mut buffer = [0; BUFFER_SIZE];
+        let mut rust_buffer = [0; BUFFER_SIZE];
+
+        unsafe {
+            static_cstrs(n, buffer.as_mut_ptr());
+            rust_static_cstrs(n, rust_buffer.as_mut_ptr());
+        }
+
+        assert_eq!(buffer, rust_buffer);
+        assert_eq!(buffer, expected_buffer);
+    }
+}
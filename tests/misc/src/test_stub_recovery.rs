@@ -0,0 +1,39 @@
+//! feature_c_unwind
+
+#[cfg(not(c2rust_extern_fallback))]
+use crate::stub_recovery::rust_reversed;
+use crate::stub_recovery::rust_stub_recovery;
+use libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn stub_recovery(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 2;
+
+pub fn test_translated_fn() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [6, -8];
+
+    unsafe {
+        stub_recovery(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_stub_recovery(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_stub_panics() {
+    // With `--cfg c2rust_extern_fallback`, the stub is replaced with a private declaration of the
+    // C definition, which is only checked to compile
+    #[cfg(not(c2rust_extern_fallback))]
+    {
+        let result = std::panic::catch_unwind(|| unsafe { rust_reversed(1) });
+        let payload = result.unwrap_err();
+        let message = payload.downcast_ref::<String>().unwrap();
+        assert!(message.starts_with("not implemented: rust_reversed: "));
+    }
+}
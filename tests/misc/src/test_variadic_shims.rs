@@ -0,0 +1,23 @@
+use crate::variadic_shims::rust_variadic_shims;
+use libc::{c_char, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn variadic_shims(_: c_uint, _: *mut c_char);
+}
+
+const BUFFER_SIZE: usize = 64;
+
+pub fn test_variadic_shims() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected = b"1-one|2-two|0.5 x|no args|7|\0";
+
+    unsafe {
+        variadic_shims(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_variadic_shims(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(&buffer[..expected.len()], &expected[..]);
+}
@@ -0,0 +1,17 @@
+//! variadic_shims
+
+#include <stdio.h>
+
+void variadic_shims(const unsigned buffer_size, char buffer[]) {
+    int len = 0;
+
+    if (buffer_size < 64) return;
+
+    len += sprintf(buffer + len, "%d-%s|", 1, "one");
+    len += sprintf(buffer + len, "%d-%s|", 2, "two");
+    len += snprintf(buffer + len, buffer_size - len, "%.1f %c|", 0.5, 'x');
+    len += snprintf(buffer + len, buffer_size - len, "no args|");
+    // Not a literal format, so called directly
+    const char *fmt = "%u|";
+    len += snprintf(buffer + len, buffer_size - len, fmt, 7u);
+}
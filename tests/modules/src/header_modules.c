@@ -0,0 +1,9 @@
+//! header_modules
+
+#include "shapes.h"
+#include "other_mod2.h"
+
+int header_modules(int width, int height) {
+  struct rect r = { width, height };
+  return rect_area(&r) + use_enum();
+}
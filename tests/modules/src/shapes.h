@@ -0,0 +1,8 @@
+struct rect {
+  int width;
+  int height;
+};
+
+static inline int rect_area(const struct rect *r) {
+  return r->width * r->height;
+}
@@ -0,0 +1,14 @@
+use crate::header_modules::rust_header_modules;
+use libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    fn header_modules(_: c_int, _: c_int) -> c_int;
+}
+
+pub fn test_header_modules() {
+    unsafe {
+        assert_eq!(header_modules(3, 4), rust_header_modules(3, 4));
+        assert_eq!(header_modules(3, 4), 14);
+    }
+}
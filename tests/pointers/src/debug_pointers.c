@@ -0,0 +1,24 @@
+//! debug_pointers
+
+#include <malloc.h>
+#include <stdlib.h>
+
+void entry4(const unsigned sz, int buf[const]) {
+        int *values = malloc(sz * sizeof(int));
+        for (unsigned i = 0; i < sz; i++) {
+                values[i] = i * i;
+        }
+
+        values = realloc(values, 2 * sz * sizeof(int));
+        int *end = values + 2 * sz;
+        for (int *cursor = values + sz; cursor != end; cursor++) {
+                *cursor = 1;
+        }
+
+        for (unsigned i = 0; i < sz; i++) {
+                buf[i] = values[i] + values[sz + i];
+        }
+        buf[0] += malloc_usable_size(values) >= 2 * sz * sizeof(int);
+
+        free(values);
+}
@@ -0,0 +1,15 @@
+//! restrict_attrs
+
+static void scale_into(unsigned n, int *restrict dst, const int *restrict src, int factor) {
+    for (unsigned i = 0; i < n; i++) {
+        dst[i] = src[i] * factor;
+    }
+}
+
+void entry5(const unsigned buffer_size, int buffer[]) {
+    int src[4] = {1, -2, 3, 5};
+
+    if (buffer_size < 4) return;
+
+    scale_into(4, buffer, src, 3);
+}
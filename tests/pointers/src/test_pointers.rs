@@ -1,11 +1,13 @@
 //! feature_c_variadic
 
+use crate::debug_pointers::rust_entry4;
 use crate::function_pointers::rust_entry3;
 use crate::pointer_arith::rust_entry2;
 use crate::pointer_init::rust_entry;
 use crate::ref_decay::{
     rust_address_cast, rust_bar, rust_bitcast, rust_calls_all, rust_f, rust_foobar,
 };
+use crate::restrict::rust_entry5;
 use libc::{c_int, c_uint};
 
 #[link(name = "test")]
@@ -15,11 +17,17 @@ extern "C" {
     fn entry2(_: c_uint, _: *mut c_int);
 
     fn entry3(_: c_uint, _: *mut c_int);
+
+    fn entry4(_: c_uint, _: *mut c_int);
+
+    fn entry5(_: c_uint, _: *mut c_int);
 }
 
 const BUFFER_SIZE: usize = 5;
 const BUFFER_SIZE2: usize = 31;
 const BUFFER_SIZE3: usize = 18;
+const BUFFER_SIZE4: usize = 5;
+const BUFFER_SIZE5: usize = 4;
 
 pub fn test_init() {
     let mut buffer = [0; BUFFER_SIZE];
@@ -67,3 +75,31 @@ pub fn test_fn_ptrs() {
     assert_eq!(&buffer[..], &expected_buffer[..], "c version");
     assert_eq!(&rust_buffer[..], &expected_buffer[..], "rust version");
 }
+
+pub fn test_debug_pointers() {
+    let mut buffer = [0; BUFFER_SIZE4];
+    let mut rust_buffer = [0; BUFFER_SIZE4];
+    let expected_buffer = [2, 2, 5, 10, 17];
+
+    unsafe {
+        entry4(BUFFER_SIZE4 as u32, buffer.as_mut_ptr());
+        rust_entry4(BUFFER_SIZE4 as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_restrict() {
+    let mut buffer = [0; BUFFER_SIZE5];
+    let mut rust_buffer = [0; BUFFER_SIZE5];
+    let expected_buffer = [3, -6, 9, 15];
+
+    unsafe {
+        entry5(BUFFER_SIZE5 as u32, buffer.as_mut_ptr());
+        rust_entry5(BUFFER_SIZE5 as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,36 @@
+#include <arm_neon.h>
+
+static int32_t sum_lanes(int32x4_t v) {
+    return vgetq_lane_s32(v, 0) + vgetq_lane_s32(v, 1) + vgetq_lane_s32(v, 2) +
+           vgetq_lane_s32(v, 3);
+}
+
+void entry(const unsigned buffer_size, int buffer[const])
+{
+    if (buffer_size < 6) {
+        return;
+    }
+
+    int32_t a_data[4] = {1, 2, 3, 4};
+    int32_t b_data[4] = {10, 20, 30, 40};
+    int32x4_t a = vld1q_s32(a_data);
+    int32x4_t b = vld1q_s32(b_data);
+
+    int32x4_t sum = vaddq_s32(a, b);
+    int32x4_t product = vmulq_s32(a, b);
+    int32x4_t shifted = vshlq_n_s32(a, 2);
+
+    int32_t out[4];
+    vst1q_s32(out, sum);
+
+    buffer[0] = out[0];
+    buffer[1] = out[3];
+    buffer[2] = sum_lanes(product);
+    buffer[3] = sum_lanes(shifted);
+
+    uint32x4_t u = vdupq_n_u32(7);
+    buffer[4] = (int)vgetq_lane_u32(vaddq_u32(u, u), 2);
+
+    float32x4_t f = vdupq_n_f32(1.5f);
+    buffer[5] = (int)vgetq_lane_f32(vmulq_f32(f, f), 1);
+}
@@ -0,0 +1,27 @@
+//! feature_portable_simd
+
+use crate::vector_ext::rust_vector_ext;
+use libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    fn vector_ext(buffer: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 23;
+
+pub fn test_vector_ext() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [
+        25, 41, 2, 3, 4, 20, 2, 2, 0, 0, -1, -1, 1, 3, 5, 7, 0x3f800000, 0, 1, 3, 5, 7, 10,
+    ];
+
+    unsafe {
+        vector_ext(buffer.as_mut_ptr());
+        rust_vector_ext(rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
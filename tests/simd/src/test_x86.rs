@@ -1,12 +1,17 @@
 use crate::x86::{
-    rust_call_all, rust_call_all_used, rust_simd_fn_codegen, rust_static_m128, rust_static_m128d,
-    rust_static_m128i, rust_static_m256, rust_static_m256d, rust_static_m256i,
+    rust_call_all, rust_call_all_used, rust_convert_vectors, rust_simd_fn_codegen, rust_static_m128,
+    rust_static_m128d, rust_static_m128i, rust_static_m256, rust_static_m256d, rust_static_m256i,
     rust_static_uninit_m128, rust_static_uninit_m128d, rust_static_uninit_m128i,
     rust_static_uninit_m256, rust_static_uninit_m256d, rust_static_uninit_m256i,
     rust_unpack_128_2x128, rust_vector_init_lists, rust_vector_init_lists_used, rust_zero_init_all,
     ShuffleVectors, VectorInitLists,
 };
 
+#[cfg(target_feature = "avx512f")]
+use crate::x86::rust_avx512_ops;
+#[cfg(target_feature = "avx512f")]
+use libc::c_int;
+use libc::{c_double, c_longlong};
 #[cfg(target_arch = "x86")]
 use std::arch::x86::{
     __m128, __m128d, __m128i, __m256, __m256d, __m256i, _mm_set_epi32, _mm_setzero_si128,
@@ -24,6 +29,8 @@ use std::mem::transmute;
 extern "C" {
     fn unpack_128_2x128(data: __m128i, data_lo: *mut __m128i, data_hi: *mut __m128i);
 
+    fn convert_vectors(buffer: *mut c_longlong, doubles: *mut c_double);
+
     fn call_all() -> ShuffleVectors;
 
     fn call_all_used() -> ShuffleVectors;
@@ -43,6 +50,9 @@ extern "C" {
     static static_m128i: __m128i;
 
     static static_m256i: __m256i;
+
+    #[cfg(target_feature = "avx512f")]
+    fn avx512_ops(buffer: *mut c_int);
 }
 
 static UNSAFETY_ERROR: &str =
@@ -138,6 +148,25 @@ pub fn test_unpack_128_2x128() {
     }
 }
 
+pub fn test_convert_vectors() {
+    assert!(is_x86_feature_detected!("sse2"), "{}", UNSAFETY_ERROR);
+
+    let mut buffer = [0; 2];
+    let mut rust_buffer = [0; 2];
+    let mut doubles = [0.0; 2];
+    let mut rust_doubles = [0.0; 2];
+
+    unsafe {
+        convert_vectors(buffer.as_mut_ptr(), doubles.as_mut_ptr());
+        rust_convert_vectors(rust_buffer.as_mut_ptr(), rust_doubles.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, [-2, 7]);
+    assert_eq!(doubles, rust_doubles);
+    assert_eq!(doubles, [-2.0, 7.0]);
+}
+
 pub fn test_zero_initializers() {
     assert!(is_x86_feature_detected!("sse"), "{}", UNSAFETY_ERROR);
     assert!(is_x86_feature_detected!("sse2"), "{}", UNSAFETY_ERROR);
@@ -227,3 +256,23 @@ pub fn test_static_init_lists() {
 
     assert_eq!(static_init_lists, rust_static_init_lists);
 }
+
+// Most machines don't support AVX-512 either, so this is only tested where it is available
+pub fn test_avx512() {
+    #[cfg(target_feature = "avx512f")]
+    {
+        let mut buffer = [0; 20];
+        let mut rust_buffer = [0; 20];
+        let expected_buffer = [
+            3, 3, 3, 3, 11, 11, 11, 11, 19, 19, 19, 19, 27, 27, 27, 27, 0x3333, 0x33, 7, 56,
+        ];
+
+        unsafe {
+            avx512_ops(buffer.as_mut_ptr());
+            rust_avx512_ops(rust_buffer.as_mut_ptr());
+        }
+
+        assert_eq!(buffer, rust_buffer);
+        assert_eq!(buffer, expected_buffer);
+    }
+}
@@ -0,0 +1,31 @@
+typedef int v4si __attribute__((vector_size(16)));
+typedef float v4sf __attribute__((vector_size(16)));
+
+static v4si zero;
+
+void vector_ext(int buffer[]) {
+    v4si a = {1, 2, 3, 4};
+    v4si b = {10, 20};
+    v4si c = a + b * 2 - 1;
+    v4si d = __builtin_shufflevector(a, c, 3, 4, 1, 6);
+    v4si mask = a > 2;
+    v4sf f = {0.5f, 1.5f, 2.5f, 3.5f};
+    v4si ones;
+
+    f = f * 2;
+    ones = (v4si)(f / f);
+    v4si g = __builtin_convertvector(f + 0.25f, v4si);
+    v4sf h = __builtin_convertvector(a, v4sf);
+    c[0] += 5;
+
+    for (int i = 0; i < 4; i++) {
+        buffer[i] = c[i];
+        buffer[4 + i] = d[i];
+        buffer[8 + i] = mask[i];
+        buffer[12 + i] = (int)f[i];
+        buffer[18 + i] = g[i];
+    }
+    buffer[16] = ones[0];
+    buffer[17] = zero[1];
+    buffer[22] = (int)(h[3] * 2.5f);
+}
@@ -43,6 +43,14 @@ void unpack_128_2x128(__m128i data, __m128i* data_lo, __m128i* data_hi)
     *data_hi = _mm_unpackhi_epi8 (data, _mm_setzero_si128());
 }
 
+// Architecture-specific vectors are converted lane by lane, like generic ones
+void convert_vectors(long long buffer[], double doubles[]) {
+    __m128d d = _mm_setr_pd(-2.5, 7.75);
+    __m128i i = __builtin_convertvector(d, __m128i);
+    _mm_storeu_si128((__m128i *)buffer, i);
+    _mm_storeu_pd(doubles, __builtin_convertvector(i, __m128d));
+}
+
 void zero_init_all(void) {
     __m128 a;
     __m256 b;
@@ -293,3 +301,21 @@ void simd_fn_codegen(__m128i i, __m128d d, __m128 y, __m256i m256i) {
     i = _mm_insert_epi64(i, 2, 1);
 #endif // __clang_major__
 }
+
+#ifdef __AVX512F__
+void avx512_ops(int buffer[const]) {
+    __m512i a = _mm512_setr_epi32(0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15);
+    __m512i b = _mm512_shuffle_epi32(a, 0x1B);
+    _mm512_storeu_si512(buffer, _mm512_add_epi32(a, b));
+
+    __mmask16 lt = _mm512_cmp_epi32_mask(a, b, 1);
+    buffer[16] = lt;
+    buffer[17] = _mm512_mask_cmp_epi32_mask(0x00FF, a, b, 1);
+
+    __m128i hi = _mm512_extracti32x4_epi32(b, 1);
+    buffer[18] = _mm_extract_epi32(hi, 0);
+
+    __m512i evens = _mm512_mask_add_epi32(_mm512_setzero_si512(), 0x00FF, a, a);
+    buffer[19] = _mm512_reduce_add_epi32(evens);
+}
+#endif
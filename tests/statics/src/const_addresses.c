@@ -0,0 +1,29 @@
+struct point {
+    int x;
+    int y;
+    char name[4];
+};
+
+static int small_primes[6] = {2, 3, 5, 7, 11, 13};
+static struct point origin = {10, 20, "org"};
+
+int *fourth_prime = &small_primes[3];
+int *fifth_prime = small_primes + 4;
+int *last_prime = &small_primes[6 - 1];
+int *origin_y = &origin.y;
+char *origin_name = (char *)&origin.name[1];
+const char *suffix = "hello" + 2;
+int *prime_table[] = {&small_primes[1], small_primes + 2};
+
+void entry2(const unsigned buffer_size, int buffer[]) {
+    if (buffer_size < 8) return;
+
+    buffer[0] = *fourth_prime;
+    buffer[1] = *fifth_prime;
+    buffer[2] = *last_prime;
+    buffer[3] = *origin_y;
+    buffer[4] = *origin_name;
+    buffer[5] = suffix[0];
+    buffer[6] = *prime_table[0];
+    buffer[7] = *prime_table[1];
+}
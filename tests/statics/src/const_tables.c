@@ -0,0 +1,34 @@
+// Read-only lookup tables that can be translated into immutable statics
+static const unsigned char hex_values[16] = {
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+};
+static const int squares[] = {0, 1, 4, 9, 16, 25};
+
+// The address of this table escapes, so it has to stay a `static mut`
+static const int primes[] = {2, 3, 5, 7, 11, 13};
+
+static int sum(const int *xs, unsigned n) {
+    int total = 0;
+    for (unsigned i = 0; i < n; i++) {
+        total += xs[i];
+    }
+    return total;
+}
+
+void const_tables(const unsigned buffer_size, int buffer[]) {
+    unsigned char c = 12;
+    int i;
+
+    if (buffer_size < 8) return;
+
+    buffer[0] = hex_values[c];
+    buffer[1] = hex_values[3];
+    for (i = 0; i < (int)(sizeof(squares) / sizeof(squares[0])); i++) {
+        buffer[2] += squares[i];
+    }
+    buffer[3] = primes[2];
+    buffer[4] = sum(primes, 6);
+    buffer[5] = *&primes[5];
+    buffer[6] = squares[(unsigned short)5];
+    buffer[7] = sizeof(hex_values);
+}
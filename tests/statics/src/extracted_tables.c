@@ -0,0 +1,18 @@
+//! extract_large_tables
+
+// X-macros expanding to a table that is moved to its own file
+#define OP4(n) OP(n) OP(n + 1) OP(n + 2) OP(n + 3)
+#define OP16(n) OP4(n) OP4(n + 4) OP4(n + 8) OP4(n + 12)
+#define OP64(n) OP16(n) OP16(n + 16) OP16(n + 32) OP16(n + 48)
+#define OP320 OP64(0) OP64(64) OP64(128) OP64(192) OP64(256)
+
+#define OP(n) (n) ^ 0x55,
+static unsigned short codes[] = {OP320};
+#undef OP
+
+void extracted_tables(const unsigned buffer_size, int buffer[]) {
+    if (buffer_size < 2) return;
+
+    buffer[0] = codes[257];
+    buffer[1] = codes[0];
+}
@@ -0,0 +1,31 @@
+// X-macros expanding to tables with more entries than the default --large-table-threshold
+#define OP4(n) OP(n) OP(n + 1) OP(n + 2) OP(n + 3)
+#define OP16(n) OP4(n) OP4(n + 4) OP4(n + 8) OP4(n + 12)
+#define OP64(n) OP16(n) OP16(n + 16) OP16(n + 32) OP16(n + 48)
+#define OP320 OP64(0) OP64(64) OP64(128) OP64(192) OP64(256)
+
+struct opcode {
+    int code;
+    int length;
+};
+
+#define OP(n) {n, (n) % 7},
+static struct opcode opcodes[] = {OP320};
+#undef OP
+
+#define OP(n) (n) * 3,
+static const int weights[] = {OP320};
+#undef OP
+
+void large_tables(const unsigned buffer_size, int buffer[]) {
+    int i;
+
+    if (buffer_size < 4) return;
+
+    buffer[0] = opcodes[300].code;
+    buffer[1] = opcodes[300].length;
+    buffer[2] = weights[319];
+    for (i = 0; i < 320; i++) {
+        buffer[3] += opcodes[i].length;
+    }
+}
@@ -36,6 +36,9 @@ unsigned dont_section_me = 1 + 1;
 Bar bar;
 void *int_to_pointer = -1;
 uintptr_t null_to_ptr_ty = (uintptr_t) NULL;
+stat s = {&bar.bar};
+stat2 s2 = {&bar.bar};
+static stat3 selfref = { NULL, &selfref.first };
 
 // These should be initialized via sections:
 unsigned section_me = -1U;
@@ -46,9 +49,6 @@ unsigned section_me5 = 1U + 1;
 Foo section_foo_b_field = {1, -1U, 1.2};
 const unsigned int section_num_params = sizeof(params) / sizeof(params[0]);
 const size_t if_expr = sizeof(size_t) == 4 ? 30 : 31;
-stat s = {&bar.bar};
-stat2 s2 = {&bar.bar};
-static stat3 selfref = { NULL, &selfref.first };
 void (*int_to_fn_ptr)(void) = -1;
 void (*int_to_fn_ptr2)(int, ...) = -1;
 void_fn_ptr int_to_fn_ptr3 = -1;
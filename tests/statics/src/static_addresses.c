@@ -0,0 +1,32 @@
+struct node {
+    int value;
+    struct node *next;
+};
+
+static int counter = 3;
+static int values[4] = {1, 2, 3, 4};
+static struct node tail = {30, 0};
+static struct node head = {10, &tail};
+
+int *counter_ptr = &counter;
+int *values_ptr = values;
+const int *const_values_ptr = values;
+int (*values_array_ptr)[4] = &values;
+struct node *head_ptr = &head;
+int *tail_value = &tail.value;
+int **counter_ptr_ptr = &counter_ptr;
+
+void entry3(const unsigned buffer_size, int buffer[]) {
+    static int *scoped_ptr = &counter;
+
+    if (buffer_size < 8) return;
+
+    buffer[0] = *counter_ptr;
+    buffer[1] = values_ptr[2];
+    buffer[2] = const_values_ptr[3];
+    buffer[3] = (*values_array_ptr)[1];
+    buffer[4] = head_ptr->next->value;
+    buffer[5] = *tail_value;
+    buffer[6] = **counter_ptr_ptr;
+    buffer[7] = *scoped_ptr;
+}
@@ -0,0 +1,25 @@
+#include <limits.h>
+
+struct header {
+    unsigned char kind;
+    unsigned char flags;
+    unsigned short len;
+};
+
+enum { MAX_ENTRIES = 16 };
+
+_Static_assert(sizeof(struct header) == 4, "struct header is {packed}");
+_Static_assert(MAX_ENTRIES % 4 == 0, "entries come in groups of 4");
+_Static_assert(CHAR_BIT == 8, "bytes have 8 bits");
+_Static_assert(sizeof(short) == 2, "short holds 16 bits \xe2\x80\x94 \"half\" an int");
+
+static int table[MAX_ENTRIES];
+
+void static_asserts(unsigned buffer_size, int buffer[]) {
+    _Static_assert(sizeof(table) / sizeof(table[0]) == MAX_ENTRIES, "table size");
+
+    for (unsigned i = 0; i < buffer_size && i < MAX_ENTRIES; i++) {
+        table[i] = (int)(i * sizeof(struct header));
+        buffer[i] = table[i];
+    }
+}
@@ -0,0 +1,23 @@
+use crate::const_addresses::rust_entry2;
+use libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn entry2(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 8;
+
+pub fn test_const_addresses() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [7, 11, 13, 20, 'r' as c_int, 'l' as c_int, 3, 5];
+
+    unsafe {
+        entry2(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_entry2(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,23 @@
+use crate::const_tables::rust_const_tables;
+use libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn const_tables(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 8;
+
+pub fn test_const_tables() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [12, 3, 55, 5, 41, 13, 25, 16];
+
+    unsafe {
+        const_tables(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_const_tables(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
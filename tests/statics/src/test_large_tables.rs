@@ -0,0 +1,41 @@
+use crate::extracted_tables::rust_extracted_tables;
+use crate::large_tables::rust_large_tables;
+use libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn large_tables(_: c_uint, _: *mut c_int);
+
+    fn extracted_tables(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 4;
+const BUFFER_SIZE2: usize = 2;
+
+pub fn test_large_tables() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [300, 6, 957, 955];
+
+    unsafe {
+        large_tables(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_large_tables(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_extracted_tables() {
+    let mut buffer = [0; BUFFER_SIZE2];
+    let mut rust_buffer = [0; BUFFER_SIZE2];
+    let expected_buffer = [340, 85];
+
+    unsafe {
+        extracted_tables(BUFFER_SIZE2 as u32, buffer.as_mut_ptr());
+        rust_extracted_tables(BUFFER_SIZE2 as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,23 @@
+use crate::static_addresses::rust_entry3;
+use libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn entry3(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 8;
+
+pub fn test_static_addresses() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [3, 3, 4, 2, 30, 30, 3, 3];
+
+    unsafe {
+        entry3(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_entry3(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,23 @@
+use crate::static_asserts::rust_static_asserts;
+use libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn static_asserts(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 5;
+
+pub fn test_static_asserts() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [0, 4, 8, 12, 16];
+
+    unsafe {
+        static_asserts(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_static_asserts(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
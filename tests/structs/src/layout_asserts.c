@@ -0,0 +1,32 @@
+//! layout_asserts
+
+#include <stddef.h>
+
+struct mixed {
+    char tag;
+    long value;
+    short small;
+};
+
+struct packed_pair {
+    char a;
+    int b;
+} __attribute__((packed));
+
+struct aligned_byte {
+    char c;
+} __attribute__((aligned(16)));
+
+void layout_sizes(unsigned buffer_size, int buffer[]) {
+    if (buffer_size < 9) return;
+
+    buffer[0] = sizeof(struct mixed);
+    buffer[1] = _Alignof(struct mixed);
+    buffer[2] = offsetof(struct mixed, value);
+    buffer[3] = offsetof(struct mixed, small);
+    buffer[4] = sizeof(struct packed_pair);
+    buffer[5] = _Alignof(struct packed_pair);
+    buffer[6] = offsetof(struct packed_pair, b);
+    buffer[7] = sizeof(struct aligned_byte);
+    buffer[8] = _Alignof(struct aligned_byte);
+}
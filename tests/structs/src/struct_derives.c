@@ -0,0 +1,33 @@
+//! derive_Debug, derive_Default, derive_PartialEq
+
+struct point {
+    int x;
+    int y;
+};
+
+// Raw pointers have no `Default`
+struct node {
+    int value;
+    struct node *next;
+};
+
+// Unions derive neither `Debug` nor `PartialEq`
+union number {
+    int i;
+    float f;
+};
+
+struct tagged {
+    int tag;
+    union number n;
+};
+
+// Arrays of more than 32 elements have no `Default`
+struct big {
+    char name[40];
+    struct point origin;
+};
+
+int struct_derives(struct point p, struct node n, struct tagged t, struct big b) {
+    return p.x + n.value + t.tag + b.origin.y;
+}
@@ -0,0 +1,26 @@
+//! extern_crate_memoffset
+
+use crate::layout_asserts::rust_layout_sizes;
+use libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn layout_sizes(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 9;
+
+// The translated structs assert their sizes and alignments at compile time, so this test only
+// builds if they match the C layout
+pub fn test_layout_sizes() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+
+    unsafe {
+        layout_sizes(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_layout_sizes(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer[4..], [5, 1, 1, 16, 16]);
+}
@@ -0,0 +1,25 @@
+use crate::struct_derives::{big, node, point, tagged};
+
+pub fn test_derived() {
+    let p = point { x: 1, y: 2 };
+
+    assert_eq!(point::default(), point { x: 0, y: 0 });
+    assert_eq!(p, p.clone());
+    assert_ne!(p, point::default());
+    assert_eq!(format!("{:?}", p), "point { x: 1, y: 2 }");
+}
+
+pub fn test_default_impls() {
+    let n = node::default();
+    assert_eq!(n.value, 0);
+    assert!(n.next.is_null());
+    assert_eq!(n, node::default());
+
+    let t = tagged::default();
+    assert_eq!(t.tag, 0);
+    assert_eq!(unsafe { t.n.i }, 0);
+
+    let b = big::default();
+    assert_eq!(b.name, [0; 40]);
+    assert_eq!(b.origin, point::default());
+}
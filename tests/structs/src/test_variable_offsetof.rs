@@ -1,11 +1,15 @@
 //! extern_crate_memoffset
 
-use crate::variable_offsetof::{rust_get_offset, rust_get_offset2, size_t};
+use crate::variable_offsetof::{
+    rust_get_nested_offset, rust_get_nested_offset2, rust_get_offset, rust_get_offset2, size_t,
+};
 
 #[link(name = "test")]
 extern "C" {
     fn get_offset(_: size_t) -> size_t;
     fn get_offset2(_: size_t) -> size_t;
+    fn get_nested_offset(_: size_t) -> size_t;
+    fn get_nested_offset2(_: size_t) -> size_t;
 }
 
 pub fn test_get_offset() {
@@ -21,3 +25,17 @@ pub fn test_get_offset() {
         assert_eq!(rust_ret2, c_ret2);
     }
 }
+
+pub fn test_get_nested_offset() {
+    for idx in 0..3 {
+        let rust_ret = unsafe { rust_get_nested_offset(idx) };
+        let c_ret = unsafe { get_nested_offset(idx) };
+
+        assert_eq!(rust_ret, c_ret);
+
+        let rust_ret2 = unsafe { rust_get_nested_offset2(idx) };
+        let c_ret2 = unsafe { get_nested_offset2(idx) };
+
+        assert_eq!(rust_ret2, c_ret2);
+    }
+}
@@ -17,3 +17,21 @@ struct yield {
 size_t get_offset2(size_t idx) {
     return offsetof(struct yield, mod[idx]);
 }
+
+struct inner {
+    char c;
+    short s[4];
+};
+
+struct outer {
+    int a;
+    struct inner inners[3];
+};
+
+size_t get_nested_offset(size_t idx) {
+    return offsetof(struct outer, inners[idx].s[2]);
+}
+
+size_t get_nested_offset2(size_t idx) {
+    return offsetof(struct outer, inners[1].s[idx]);
+}
@@ -0,0 +1,49 @@
+//! fn_pointer_unions
+
+union callback {
+    int (*func)(int);
+    const int *value;
+};
+
+struct handler {
+    int use_fn;
+    union callback cb;
+};
+
+static const int ten = 10;
+
+static union callback none;
+
+static int twice(int x) {
+    return 2 * x;
+}
+
+static int inc(int x) {
+    return x + 1;
+}
+
+static int run(const struct handler *h, int x) {
+    if (h->use_fn) {
+        return h->cb.func(x);
+    }
+    return *h->cb.value;
+}
+
+void entry3(const unsigned buffer_size, int buffer[const]) {
+    if (buffer_size < 5) {
+        return;
+    }
+
+    struct handler h = {1, {twice}};
+    buffer[0] = run(&h, 3);
+
+    h.cb.func = inc;
+    buffer[1] = run(&h, 12);
+
+    h.use_fn = 0;
+    h.cb.value = &ten;
+    buffer[2] = run(&h, 0);
+
+    buffer[3] = (h.cb.func = twice)(4);
+    buffer[4] = none.func == 0 ? ten : 0;
+}
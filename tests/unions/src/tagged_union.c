@@ -0,0 +1,69 @@
+//! tagged_union_shape
+
+enum shape_kind {
+    NONE,
+    CIRCLE,
+    RECTANGLE,
+    SQUARE,
+};
+
+struct rect {
+    int w;
+    int h;
+};
+
+struct shape {
+    enum shape_kind kind;
+    union {
+        int radius;
+        struct rect rect;
+        int side;
+    } u;
+};
+
+static int area(const struct shape *s) {
+    switch (s->kind) {
+    case CIRCLE:
+        return 3 * s->u.radius * s->u.radius;
+    case RECTANGLE:
+        return s->u.rect.w * s->u.rect.h;
+    case SQUARE:
+        return s->u.side * s->u.side;
+    default:
+        return 0;
+    }
+}
+
+static void grow(struct shape *s) {
+    if (s->kind == RECTANGLE) {
+        s->u.rect.w += 1;
+        s->u.rect.h *= 2;
+    }
+}
+
+void entry2(const unsigned int buffer_size, int buffer[const])
+{
+    int i = 0;
+
+    struct shape none;
+    struct shape circle = { CIRCLE, { .radius = 2 } };
+    struct shape rect = { .kind = RECTANGLE, .u.rect = { 3, 4 } };
+    struct shape square;
+
+    square.kind = SQUARE;
+    square.u.side = 5;
+
+    none.kind = NONE;
+
+    buffer[i++] = area(&none);
+    buffer[i++] = area(&circle);
+    buffer[i++] = area(&rect);
+    buffer[i++] = area(&square);
+
+    grow(&rect);
+    buffer[i++] = area(&rect);
+    buffer[i++] = rect.kind;
+
+    circle = square;
+    buffer[i++] = area(&circle);
+}
@@ -1,12 +1,20 @@
+use crate::fn_pointer_union::rust_entry3;
+use crate::tagged_union::{rust_entry2, shape, CIRCLE, SQUARE};
 use crate::unions::rust_entry;
 use libc::{c_int, c_uint};
 
 #[link(name = "test")]
 extern "C" {
     fn entry(_: c_uint, _: *mut c_int);
+
+    fn entry2(_: c_uint, _: *mut c_int);
+
+    fn entry3(_: c_uint, _: *mut c_int);
 }
 
 const BUFFER_SIZE: usize = 19;
+const BUFFER_SIZE2: usize = 7;
+const BUFFER_SIZE3: usize = 5;
 
 pub fn test_buffer() {
     let mut buffer = [0; BUFFER_SIZE];
@@ -21,3 +29,39 @@ pub fn test_buffer() {
     assert_eq!(buffer, rust_buffer);
     assert_eq!(buffer, expected_buffer);
 }
+
+pub fn test_tagged_union() {
+    let circle = shape::CIRCLE(2);
+    let square = shape::SQUARE(5);
+
+    assert_eq!(circle.kind(), CIRCLE);
+    assert_eq!(square.kind(), SQUARE);
+}
+
+pub fn test_buffer2() {
+    let mut buffer = [0; BUFFER_SIZE2];
+    let mut rust_buffer = [0; BUFFER_SIZE2];
+    let expected_buffer = [0, 12, 12, 25, 32, 2, 25];
+
+    unsafe {
+        entry2(BUFFER_SIZE2 as u32, buffer.as_mut_ptr());
+        rust_entry2(BUFFER_SIZE2 as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_buffer3() {
+    let mut buffer = [0; BUFFER_SIZE3];
+    let mut rust_buffer = [0; BUFFER_SIZE3];
+    let expected_buffer = [6, 13, 10, 8, 10];
+
+    unsafe {
+        entry3(BUFFER_SIZE3 as u32, buffer.as_mut_ptr());
+        rust_entry3(BUFFER_SIZE3 as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}